@@ -1,7 +1,7 @@
-use crate::common::Service;
-use reqwest::{Client, Response, StatusCode};
+use prevant_client::{Client, ClientError, ServiceConfig};
 use std::collections::HashMap;
 use testcontainers::{core::WaitFor, Container, Image};
+use url::Url;
 use uuid::Uuid;
 
 pub struct PREvant {
@@ -47,84 +47,51 @@ impl Image for PREvant {
     }
 }
 
-pub async fn deploy_app(
-    prevant: &Container<'_, PREvant>,
-    services: &Vec<Service>,
-) -> Result<Uuid, Response> {
+fn client(prevant: &Container<'_, PREvant>) -> Client {
     let port = prevant.get_host_port_ipv4(80);
+    Client::new(Url::parse(&format!("http://localhost:{}/", port)).unwrap())
+}
 
+pub async fn deploy_app(
+    prevant: &Container<'_, PREvant>,
+    services: &[ServiceConfig],
+) -> Result<Uuid, ClientError> {
     let app_name = Uuid::new_v4();
 
-    let res = Client::new()
-        .post(&format!("http://localhost:{}/api/apps/{}", port, app_name))
-        .json(&services)
-        .send()
-        .await
-        .unwrap();
+    client(prevant)
+        .deploy_app(&app_name.to_string(), services)
+        .await?;
 
-    match res.status() {
-        StatusCode::OK => Ok(app_name),
-        _ => Err(res),
-    }
+    Ok(app_name)
 }
 
 pub async fn replicate_app(
     prevant: &Container<'_, PREvant>,
     from_app_name: &Uuid,
-) -> Result<Uuid, Response> {
-    let port = prevant.get_host_port_ipv4(80);
-
+) -> Result<Uuid, ClientError> {
     let app_name = Uuid::new_v4();
 
-    let res = Client::new()
-        .post(&format!(
-            "http://localhost:{}/api/apps/{}?replicateFrom={}",
-            port, app_name, from_app_name
-        ))
-        .json(&Vec::<Service>::new())
-        .send()
-        .await
-        .unwrap();
+    client(prevant)
+        .replicate_app(&app_name.to_string(), &from_app_name.to_string(), &[])
+        .await?;
 
-    match res.status() {
-        StatusCode::OK => Ok(app_name),
-        _ => Err(res),
-    }
+    Ok(app_name)
 }
 
-pub async fn delete_app(prevant: &Container<'_, PREvant>, app_name: &Uuid) -> Result<(), Response> {
-    let port = prevant.get_host_port_ipv4(80);
-
-    let res = Client::new()
-        .delete(&format!("http://localhost:{}/api/apps/{}", port, app_name))
-        .send()
-        .await
-        .unwrap();
-
-    match res.status() {
-        StatusCode::OK => Ok(()),
-        _ => Err(res),
-    }
+pub async fn delete_app(
+    prevant: &Container<'_, PREvant>,
+    app_name: &Uuid,
+) -> Result<(), ClientError> {
+    client(prevant).delete_app(&app_name.to_string()).await?;
+    Ok(())
 }
 
 pub async fn logs(
     prevant: &Container<'_, PREvant>,
     app_name: &Uuid,
     service_name: &str,
-) -> Result<String, Response> {
-    let port = prevant.get_host_port_ipv4(80);
-
-    let res = Client::new()
-        .get(&format!(
-            "http://localhost:{}/api/apps/{}/logs/{}",
-            port, app_name, service_name
-        ))
-        .send()
+) -> Result<String, ClientError> {
+    client(prevant)
+        .logs(&app_name.to_string(), service_name, None, None)
         .await
-        .unwrap();
-
-    match res.status() {
-        StatusCode::OK => Ok(res.text().await.expect("")),
-        _ => Err(res),
-    }
 }
@@ -3,10 +3,11 @@ mod container;
 
 use testcontainers::RunnableImage;
 
-use crate::common::{docker, Service};
+use crate::common::docker;
 use crate::container::{
     delete_app, deploy_app, logs, make_request, replicate_app, PREvant, Traefik,
 };
+use prevant_client::ServiceConfig;
 use std::time::Duration;
 
 #[tokio::test]
@@ -23,7 +24,7 @@ async fn should_deploy_nginx() {
 
     let app_name = deploy_app(
         &prevant,
-        &vec![Service::new(
+        &vec![ServiceConfig::new(
             String::from("nginx"),
             String::from("nginx:alpine"),
         )],
@@ -60,7 +61,7 @@ async fn should_replicate_mariadb_with_replicated_env() {
     );
     let prevant = docker.run(PREvant::default());
 
-    let db_service = Service::new(String::from("db"), String::from("mariadb:10.3.17"))
+    let db_service = ServiceConfig::new(String::from("db"), String::from("mariadb:10.3.17"))
         .with_replicated_env(
             String::from("MYSQL_RANDOM_ROOT_PASSWORD"),
             String::from("yes"),
@@ -0,0 +1,186 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::state_store::{StateStore, StateStoreError};
+use crate::models::{AppBackup, AppName, Backup, ShareToken, StoredShareToken};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Collects the state of the deployed apps and of all apps that have an owner or that have been
+/// deleted softly.
+pub(super) async fn create_backup(
+    state_store: &dyn StateStore,
+    deployed_apps: impl Iterator<Item = AppName>,
+) -> Result<Backup, StateStoreError> {
+    let owners = state_store.owners().await?;
+    let soft_deleted_apps = state_store
+        .soft_deleted_apps()
+        .await?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let app_names = deployed_apps
+        .chain(owners.keys().cloned())
+        .chain(soft_deleted_apps.keys().cloned())
+        .collect::<BTreeSet<_>>();
+
+    let mut apps = BTreeMap::new();
+    for app_name in app_names {
+        let app = AppBackup {
+            owner: owners.get(&app_name).cloned(),
+            soft_deleted_until: soft_deleted_apps.get(&app_name).cloned(),
+            deployment_history: state_store.deployment_history(&app_name).await?,
+            share_tokens: state_store
+                .share_tokens(&app_name)
+                .await?
+                .iter()
+                .map(StoredShareToken::from)
+                .collect(),
+            pre_destroy_hooks: state_store.pre_destroy_hooks(&app_name).await?,
+        };
+        apps.insert(app_name, app);
+    }
+
+    Ok(Backup::new(apps, state_store.audit_log().await?))
+}
+
+/// Replaces the state of the apps of the backup. The backup's audit log is not restored because
+/// the audit log must only contain the requests that PREvant has recorded itself.
+pub(super) async fn restore_backup(
+    state_store: &dyn StateStore,
+    backup: &Backup,
+    max_history_entries: usize,
+) -> Result<(), StateStoreError> {
+    for (app_name, app) in backup.apps() {
+        match &app.owner {
+            Some(owner) => state_store.set_owner(app_name, owner).await?,
+            None => state_store.remove_owner(app_name).await?,
+        }
+
+        match app.soft_deleted_until {
+            Some(expires_at) => state_store.soft_delete(app_name, expires_at).await?,
+            None => {
+                state_store.remove_soft_deletion(app_name).await?;
+            }
+        }
+
+        state_store.remove_deployment_history(app_name).await?;
+        for entry in &app.deployment_history {
+            state_store
+                .push_deployment(app_name, entry, max_history_entries)
+                .await?;
+        }
+
+        state_store.remove_share_tokens(app_name).await?;
+        for share_token in &app.share_tokens {
+            let share_token = ShareToken::from(share_token.clone());
+            state_store.add_share_token(app_name, &share_token).await?;
+        }
+
+        if app.pre_destroy_hooks.is_empty() {
+            state_store.remove_pre_destroy_hooks(app_name).await?;
+        } else {
+            state_store
+                .set_pre_destroy_hooks(app_name, &app.pre_destroy_hooks)
+                .await?;
+        }
+    }
+
+    if !backup.audit_log().is_empty() {
+        info!(
+            "Skipping the {} entries of the backup's audit log",
+            backup.audit_log().len()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::state_store::InMemoryStateStore;
+    use crate::models::{
+        AuditEntry, DeploymentDiff, DeploymentHistoryEntry, LifecycleHook, ShareToken,
+    };
+    use chrono::Utc;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn should_restore_backup_into_empty_store() -> Result<(), StateStoreError> {
+        let master = AppName::master();
+        let feature = AppName::from_str("feature-1").unwrap();
+
+        let store = InMemoryStateStore::default();
+        store.set_owner(&feature, "alice").await?;
+        store
+            .push_deployment(
+                &master,
+                &DeploymentHistoryEntry::new(DeploymentDiff::default(), Vec::new()),
+                20,
+            )
+            .await?;
+        let (share_token, token) = ShareToken::new(None, Utc::now() + chrono::Duration::days(1));
+        store.add_share_token(&master, &share_token).await?;
+        let hooks = vec![serde_json::from_value::<LifecycleHook>(
+            serde_json::json!({
+                "serviceName": "backend",
+                "command": ["./deregister-license.sh"]
+            }),
+        )?];
+        store.set_pre_destroy_hooks(&master, &hooks).await?;
+        store
+            .append_audit_entry(&AuditEntry::new(
+                Some(String::from("alice")),
+                String::from("POST"),
+                String::from("/api/apps/feature-1"),
+                200,
+                None,
+            ))
+            .await?;
+
+        let backup = create_backup(&store, std::iter::once(master.clone())).await?;
+        let backup = serde_json::from_str::<Backup>(&serde_json::to_string(&backup)?)?;
+        assert_eq!(
+            backup.apps().keys().collect::<Vec<_>>(),
+            vec![&feature, &master]
+        );
+
+        let restored_store = InMemoryStateStore::default();
+        restore_backup(&restored_store, &backup, 20).await?;
+        restore_backup(&restored_store, &backup, 20).await?;
+
+        assert_eq!(
+            restored_store.owners().await?.get(&feature),
+            Some(&String::from("alice"))
+        );
+        assert_eq!(restored_store.deployment_history(&master).await?.len(), 1);
+        assert!(restored_store.share_tokens(&master).await?[0].grants_access(&token));
+        assert_eq!(restored_store.pre_destroy_hooks(&master).await?, hooks);
+        assert_eq!(backup.audit_log(), store.audit_log().await?.as_slice());
+        assert!(restored_store.audit_log().await?.is_empty());
+
+        Ok(())
+    }
+}
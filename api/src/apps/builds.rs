@@ -0,0 +1,85 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::AppsServiceError;
+use crate::config::BuildsConfig;
+use crate::infrastructure::Infrastructure;
+use crate::models::{AppName, ImagePullPolicy, ServiceConfig, SourceBuild};
+use futures::future::try_join_all;
+
+/// Builds the images of the `builds` concurrently and returns the `service_configs` with the
+/// built images. A build of a service that is not part of `service_configs` adds the service with
+/// its default configuration.
+pub(super) async fn build_images(
+    infrastructure: &dyn Infrastructure,
+    app_name: &AppName,
+    config: &BuildsConfig,
+    builds: &[SourceBuild],
+    service_configs: &[ServiceConfig],
+) -> Result<Vec<ServiceConfig>, AppsServiceError> {
+    let images = try_join_all(builds.iter().map(|build| async move {
+        let build_failed = |logs: String| AppsServiceError::BuildFailed {
+            service_name: build.service_name().to_string(),
+            logs,
+        };
+
+        let destination = config.destination(app_name, build).ok_or_else(|| {
+            build_failed(String::from(
+                "The configured registry is not a valid image repository",
+            ))
+        })?;
+        let outcome = infrastructure
+            .build_image(app_name, build, &destination, config)
+            .await?;
+        if !outcome.passed() {
+            return Err(build_failed(outcome.logs().to_string()));
+        }
+
+        Ok((build.service_name(), destination))
+    }))
+    .await?;
+
+    let mut service_configs = service_configs.to_vec();
+    for (service_name, image) in images {
+        let index = match service_configs
+            .iter()
+            .position(|config| config.service_name() == service_name)
+        {
+            Some(index) => index,
+            None => {
+                service_configs.push(ServiceConfig::new(service_name.to_string(), image.clone()));
+                service_configs.len() - 1
+            }
+        };
+
+        let service_config = &mut service_configs[index];
+        service_config.set_image(image);
+        // The tag of the image follows the revision, e.g. a branch, and thus it moves with every
+        // build.
+        service_config.set_image_pull_policy(Some(ImagePullPolicy::Always));
+    }
+
+    Ok(service_configs)
+}
@@ -0,0 +1,114 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::AppsServiceError;
+use crate::config::{CapacityCheckConfig, CapacityCheckMode, ContainerConfig};
+use crate::deployment::DeploymentUnit;
+use crate::infrastructure::Infrastructure;
+use crate::models::{parse_quantity, ResourceRequests};
+use std::collections::BTreeMap;
+
+/// Compares the resources that the services of the `deployment_unit` request with the free
+/// resources of the cluster. Depending on the configured mode, deployments that cannot be
+/// scheduled are rejected or only logged. If the capacity of the cluster is unknown, the
+/// deployment passes.
+pub(super) async fn check_capacity(
+    infrastructure: &dyn Infrastructure,
+    config: &CapacityCheckConfig,
+    container_config: &ContainerConfig,
+    deployment_unit: &DeploymentUnit,
+) -> Result<(), AppsServiceError> {
+    let capacity = match infrastructure.cluster_capacity(deployment_unit).await {
+        Ok(Some(capacity)) => capacity,
+        Ok(None) => return Ok(()),
+        Err(err) => {
+            warn!(
+                "Cannot determine the capacity of the cluster for {}: {}",
+                deployment_unit.app_name(),
+                err
+            );
+            return Ok(());
+        }
+    };
+
+    let shortages = capacity.shortages(&resource_requests(container_config, deployment_unit));
+    if shortages.is_empty() {
+        return Ok(());
+    }
+
+    match config.mode() {
+        CapacityCheckMode::Reject => Err(AppsServiceError::InsufficientCapacity {
+            app_name: deployment_unit.app_name().clone(),
+            reason: shortages
+                .iter()
+                .map(|shortage| shortage.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+            shortages,
+        }),
+        CapacityCheckMode::Warn => {
+            for shortage in &shortages {
+                warn!(
+                    "The cluster might not be able to run {}: {}",
+                    deployment_unit.app_name(),
+                    shortage
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The resources that each service requests from the cluster. Like the deployments of the
+/// Kubernetes backend, only the memory limit and the extended resources are requested and
+/// autoscaled services start with their minimum of replicas.
+fn resource_requests(
+    container_config: &ContainerConfig,
+    deployment_unit: &DeploymentUnit,
+) -> Vec<ResourceRequests> {
+    deployment_unit
+        .services()
+        .iter()
+        .map(|service| {
+            let mut resources = service
+                .extended_resources()
+                .iter()
+                .filter_map(|(resource, quantity)| {
+                    Some((resource.clone(), parse_quantity(quantity)?))
+                })
+                .collect::<BTreeMap<_, _>>();
+            if let Some(memory_limit) = container_config.memory_limit() {
+                resources.insert(String::from("memory"), memory_limit.as_u64() as f64);
+            }
+
+            let replicas = match service.autoscale() {
+                Some(autoscale) => autoscale.min(),
+                None => service.replicas().unwrap_or(1).max(1),
+            };
+
+            ResourceRequests::new(service.service_name().clone(), replicas, resources)
+        })
+        .collect()
+}
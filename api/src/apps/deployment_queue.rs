@@ -0,0 +1,323 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::{AppName, AppStatusChangeId};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// A deployment that holds the deployment lock of its app or waits for it.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedDeployment {
+    status_id: AppStatusChangeId,
+    queued_at: DateTime<Utc>,
+    running: bool,
+    #[serde(skip)]
+    cancellation: Arc<CancellationToken>,
+}
+
+impl QueuedDeployment {
+    pub fn status_id(&self) -> &AppStatusChangeId {
+        &self.status_id
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
+
+/// Signals a running deployment that it should stop at its next step.
+#[derive(Debug, Default)]
+pub(super) struct CancellationToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes as soon as the token has been cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum CancelError {
+    NotQueued,
+    AlreadyRunning,
+}
+
+/// Serializes the deployments of each app in the order of their requests so that overlapping
+/// deployments cannot interleave their changes of the infrastructure.
+pub(super) struct DeploymentQueue {
+    queues: Mutex<HashMap<AppName, VecDeque<QueuedDeployment>>>,
+    notify: Notify,
+}
+
+/// The place of a deployment in the queue of its app. Dropping the ticket releases the place,
+/// regardless of whether the deployment has finished, failed, or has been cancelled.
+pub(super) struct QueueTicket<'a> {
+    queue: &'a DeploymentQueue,
+    app_name: AppName,
+    status_id: AppStatusChangeId,
+    cancellation: Arc<CancellationToken>,
+}
+
+impl QueueTicket<'_> {
+    /// The token that signals the cancellation of the running deployment.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+}
+
+impl DeploymentQueue {
+    pub fn new() -> Self {
+        Self {
+            queues: Mutex::new(HashMap::new()),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn enqueue(&self, app_name: &AppName, status_id: &AppStatusChangeId) -> QueueTicket<'_> {
+        let cancellation = Arc::new(CancellationToken::default());
+        self.queues
+            .lock()
+            .unwrap()
+            .entry(app_name.clone())
+            .or_default()
+            .push_back(QueuedDeployment {
+                status_id: *status_id,
+                queued_at: Utc::now(),
+                running: false,
+                cancellation: cancellation.clone(),
+            });
+
+        QueueTicket {
+            queue: self,
+            app_name: app_name.clone(),
+            status_id: *status_id,
+            cancellation,
+        }
+    }
+
+    /// Waits until all deployments that have been queued before the ticket are finished. Returns
+    /// `false` if the deployment has been cancelled while waiting.
+    pub async fn wait_for_turn(&self, ticket: &QueueTicket<'_>) -> bool {
+        loop {
+            // Created before the check so that no notification between check and wait gets lost.
+            let notified = self.notify.notified();
+            {
+                let mut queues = self.queues.lock().unwrap();
+                let Some(position) = queues.get(&ticket.app_name).and_then(|queue| {
+                    queue
+                        .iter()
+                        .position(|deployment| deployment.status_id == ticket.status_id)
+                }) else {
+                    return false;
+                };
+
+                if position == 0 {
+                    if let Some(deployment) = queues
+                        .get_mut(&ticket.app_name)
+                        .and_then(|queue| queue.front_mut())
+                    {
+                        deployment.running = true;
+                    }
+                    return true;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    /// The running deployment of the app followed by the waiting ones.
+    pub fn queued_deployments(&self, app_name: &AppName) -> Vec<QueuedDeployment> {
+        self.queues
+            .lock()
+            .unwrap()
+            .get(app_name)
+            .map(|queue| queue.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes a waiting deployment from the queue. Running deployments cannot be cancelled.
+    pub fn cancel(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+    ) -> Result<(), CancelError> {
+        {
+            let mut queues = self.queues.lock().unwrap();
+            let queue = queues.get_mut(app_name).ok_or(CancelError::NotQueued)?;
+            let position = queue
+                .iter()
+                .position(|deployment| &deployment.status_id == status_id)
+                .ok_or(CancelError::NotQueued)?;
+            if queue[position].running {
+                return Err(CancelError::AlreadyRunning);
+            }
+            queue.remove(position);
+        }
+
+        self.notify.notify_waiters();
+        Ok(())
+    }
+
+    /// Cancels a deployment regardless of whether it is waiting or running. A running deployment
+    /// stops cooperatively at its next step.
+    pub fn abort(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+    ) -> Result<(), CancelError> {
+        match self.cancel(app_name, status_id) {
+            Err(CancelError::AlreadyRunning) => {
+                let queues = self.queues.lock().unwrap();
+                if let Some(deployment) = queues.get(app_name).and_then(|queue| {
+                    queue
+                        .iter()
+                        .find(|deployment| &deployment.status_id == status_id)
+                }) {
+                    deployment.cancellation.cancel();
+                }
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Returns `true` if a panic occurred while the queues have been modified.
+    pub fn is_poisoned(&self) -> bool {
+        self.queues.is_poisoned()
+    }
+
+    fn release(&self, app_name: &AppName, status_id: &AppStatusChangeId) {
+        {
+            let mut queues = self.queues.lock().unwrap();
+            if let Some(queue) = queues.get_mut(app_name) {
+                queue.retain(|deployment| &deployment.status_id != status_id);
+                if queue.is_empty() {
+                    queues.remove(app_name);
+                }
+            }
+        }
+
+        self.notify.notify_waiters();
+    }
+}
+
+impl Drop for QueueTicket<'_> {
+    fn drop(&mut self) {
+        self.queue.release(&self.app_name, &self.status_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_run_deployments_in_order() {
+        let queue = DeploymentQueue::new();
+        let app_name = AppName::master();
+
+        let first = queue.enqueue(&app_name, &AppStatusChangeId::new());
+        let second_id = AppStatusChangeId::new();
+        let second = queue.enqueue(&app_name, &second_id);
+
+        assert!(queue.wait_for_turn(&first).await);
+        let deployments = queue.queued_deployments(&app_name);
+        assert!(deployments[0].is_running());
+        assert!(!deployments[1].is_running());
+
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            queue.wait_for_turn(&second),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        drop(first);
+        assert!(queue.wait_for_turn(&second).await);
+        assert_eq!(
+            queue.queued_deployments(&app_name)[0].status_id(),
+            &second_id
+        );
+    }
+
+    #[tokio::test]
+    async fn should_cancel_waiting_deployments_only() {
+        let queue = DeploymentQueue::new();
+        let app_name = AppName::master();
+
+        let first_id = AppStatusChangeId::new();
+        let first = queue.enqueue(&app_name, &first_id);
+        let second_id = AppStatusChangeId::new();
+        let second = queue.enqueue(&app_name, &second_id);
+        assert!(queue.wait_for_turn(&first).await);
+
+        assert_eq!(
+            queue.cancel(&app_name, &first_id),
+            Err(CancelError::AlreadyRunning)
+        );
+        assert_eq!(queue.cancel(&app_name, &second_id), Ok(()));
+        assert!(!queue.wait_for_turn(&second).await);
+        assert_eq!(
+            queue.cancel(&app_name, &second_id),
+            Err(CancelError::NotQueued)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_abort_running_deployments() {
+        let queue = DeploymentQueue::new();
+        let app_name = AppName::master();
+
+        let status_id = AppStatusChangeId::new();
+        let ticket = queue.enqueue(&app_name, &status_id);
+        assert!(queue.wait_for_turn(&ticket).await);
+
+        assert_eq!(queue.abort(&app_name, &status_id), Ok(()));
+        assert!(ticket.cancellation().is_cancelled());
+        ticket.cancellation().cancelled().await;
+    }
+}
@@ -0,0 +1,299 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::GitConfig;
+use base64::{engine::general_purpose, Engine};
+use serde::de::DeserializeOwned;
+use std::path::{Component, Path, PathBuf};
+use tokio::process::Command;
+use url::Url;
+use uuid::Uuid;
+
+/// References a deployment descriptor, a YAML, JSON, or TOML file that contains the deployment
+/// payload, in a Git repository so that a branch of a repository can be deployed by its URL.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DescriptorSource {
+    url: Url,
+    /// The branch, tag, or commit. Without it, the default branch of the repository is used.
+    #[serde(rename = "ref")]
+    reference: Option<String>,
+    /// The path of the descriptor relative to the root of the repository.
+    path: PathBuf,
+}
+
+impl DescriptorSource {
+    /// Fetches the descriptor with the Git executable and parses it according to its file
+    /// extension. Only the referenced commit is fetched and the descriptor is read from the
+    /// fetched tree without checking out the repository.
+    pub async fn fetch<T>(&self, config: &GitConfig) -> Result<T, DescriptorError>
+    where
+        T: DeserializeOwned,
+    {
+        self.validate(config)?;
+
+        let repository =
+            std::env::temp_dir().join(format!("prevant-descriptor-{}", Uuid::new_v4()));
+        let result = self.fetch_into(config, &repository).await;
+        if let Err(err) = tokio::fs::remove_dir_all(&repository).await {
+            debug!("Cannot remove {}: {}", repository.display(), err);
+        }
+
+        parse_descriptor(&self.path, &result?)
+    }
+
+    fn validate(&self, config: &GitConfig) -> Result<(), DescriptorError> {
+        // Other transports, e.g. file:// or ext::, would allow to read the files of PREvant's
+        // host or to execute commands, and plain HTTP would expose the credentials.
+        if self.url.scheme() != "https" {
+            return Err(DescriptorError::UnsupportedUrl {
+                url: self.url.to_string(),
+            });
+        }
+        let host = self.url.host_str().unwrap_or_default();
+        if !config.permits_host(host) {
+            return Err(DescriptorError::HostNotPermitted {
+                host: host.to_string(),
+            });
+        }
+
+        let supported_extension = matches!(
+            self.path
+                .extension()
+                .and_then(|extension| extension.to_str()),
+            Some("yaml" | "yml" | "json" | "toml")
+        );
+        if !supported_extension
+            || !self
+                .path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(DescriptorError::InvalidPath {
+                path: self.path.display().to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_into(
+        &self,
+        config: &GitConfig,
+        repository: &Path,
+    ) -> Result<String, DescriptorError> {
+        tokio::fs::create_dir_all(repository).await.map_err(|err| {
+            DescriptorError::CannotRunGit {
+                err: err.to_string(),
+            }
+        })?;
+        self.git(config, repository, &["init", "--quiet"]).await?;
+        self.git(
+            config,
+            repository,
+            &[
+                "fetch",
+                "--quiet",
+                "--depth",
+                "1",
+                "--",
+                self.url.as_str(),
+                self.reference.as_deref().unwrap_or("HEAD"),
+            ],
+        )
+        .await?;
+
+        let path = self
+            .path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.git(config, repository, &["show", &format!("FETCH_HEAD:{path}")])
+            .await
+    }
+
+    async fn git(
+        &self,
+        config: &GitConfig,
+        repository: &Path,
+        args: &[&str],
+    ) -> Result<String, DescriptorError> {
+        let mut command = Command::new(config.command());
+        command
+            .arg("-C")
+            .arg(repository)
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0");
+
+        if let Some((username, password)) = self
+            .url
+            .host_str()
+            .and_then(|host| config.credentials(host))
+        {
+            // Passing the credentials through the environment keeps them out of the process list
+            // and out of the error messages that contain the URL.
+            let token =
+                general_purpose::STANDARD.encode(format!("{}:{}", username, password.unsecure()));
+            command
+                .env("GIT_CONFIG_COUNT", "1")
+                .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+                .env(
+                    "GIT_CONFIG_VALUE_0",
+                    format!("Authorization: Basic {token}"),
+                );
+        }
+
+        // Dropping the child on timeout kills git instead of leaving it behind.
+        command.kill_on_drop(true);
+        let output = tokio::time::timeout(config.timeout(), command.output())
+            .await
+            .map_err(|_| DescriptorError::FetchFailed {
+                url: self.url.to_string(),
+                message: format!("timed out after {}s", config.timeout().as_secs()),
+            })?
+            .map_err(|err| DescriptorError::CannotRunGit {
+                err: err.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(DescriptorError::FetchFailed {
+                url: self.url.to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+fn parse_descriptor<T>(path: &Path, content: &str) -> Result<T, DescriptorError>
+where
+    T: DeserializeOwned,
+{
+    let invalid_descriptor = |err: String| DescriptorError::InvalidDescriptor {
+        path: path.display().to_string(),
+        err,
+    };
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => toml::from_str(content).map_err(|err| invalid_descriptor(err.to_string())),
+        // JSON is a subset of YAML
+        Some("yaml") | Some("yml") | Some("json") => {
+            serde_yaml::from_str(content).map_err(|err| invalid_descriptor(err.to_string()))
+        }
+        _ => Err(DescriptorError::InvalidPath {
+            path: path.display().to_string(),
+        }),
+    }
+}
+
+#[derive(Debug, Clone, Fail)]
+pub enum DescriptorError {
+    #[fail(display = "Only HTTPS repositories are supported but got {}", url)]
+    UnsupportedUrl { url: String },
+    #[fail(display = "Fetching descriptors from {} is not permitted", host)]
+    HostNotPermitted { host: String },
+    #[fail(
+        display = "The descriptor path {} must be relative to the repository and end with .yaml, .yml, .json, or .toml",
+        path
+    )]
+    InvalidPath { path: String },
+    #[fail(display = "Cannot run git: {}", err)]
+    CannotRunGit { err: String },
+    #[fail(display = "Cannot fetch the descriptor from {}: {}", url, message)]
+    FetchFailed { url: String, message: String },
+    #[fail(display = "Invalid descriptor {}: {}", path, err)]
+    InvalidDescriptor { path: String, err: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn source(url: &str, path: &str) -> DescriptorSource {
+        DescriptorSource {
+            url: Url::parse(url).unwrap(),
+            reference: None,
+            path: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn should_reject_local_repositories_and_paths_outside_of_the_repository() {
+        let config = toml::de::from_str::<GitConfig>("allowedHosts = [ 'github.com' ]").unwrap();
+
+        assert!(matches!(
+            source("file:///etc", "prevant.yaml").validate(&config),
+            Err(DescriptorError::UnsupportedUrl { .. })
+        ));
+        assert!(matches!(
+            source("http://github.com/aixigo/PREvant.git", "prevant.yaml").validate(&config),
+            Err(DescriptorError::UnsupportedUrl { .. })
+        ));
+        assert!(matches!(
+            source("https://169.254.169.254/latest.git", "prevant.yaml").validate(&config),
+            Err(DescriptorError::HostNotPermitted { .. })
+        ));
+        assert!(matches!(
+            source("https://github.com/aixigo/PREvant.git", "../prevant.yaml").validate(&config),
+            Err(DescriptorError::InvalidPath { .. })
+        ));
+        assert!(matches!(
+            source("https://github.com/aixigo/PREvant.git", "/prevant.yaml").validate(&config),
+            Err(DescriptorError::InvalidPath { .. })
+        ));
+        assert!(matches!(
+            source("https://github.com/aixigo/PREvant.git", "README.md").validate(&config),
+            Err(DescriptorError::InvalidPath { .. })
+        ));
+        assert!(source(
+            "https://github.com/aixigo/PREvant.git",
+            "deploy/prevant.yaml"
+        )
+        .validate(&config)
+        .is_ok());
+    }
+
+    #[test]
+    fn should_parse_descriptor_by_its_extension() {
+        let yaml = parse_descriptor::<BTreeMap<String, String>>(
+            Path::new("prevant.yaml"),
+            "serviceName: db",
+        )
+        .unwrap();
+        let toml = parse_descriptor::<BTreeMap<String, String>>(
+            Path::new("prevant.toml"),
+            "serviceName = 'db'",
+        )
+        .unwrap();
+
+        assert_eq!(yaml, toml);
+        assert!(matches!(
+            parse_descriptor::<BTreeMap<String, String>>(Path::new("prevant.txt"), ""),
+            Err(DescriptorError::InvalidPath { .. })
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::AppsServiceError;
+use crate::config::{Config, Runtime};
+use crate::models::ServiceConfig;
+
+/// Ensures that services only mount directories of the host if the dev mode has been enabled,
+/// the app runs on Docker, and the directories are within the configured source roots. Dev
+/// mounts are never deployed to Kubernetes because the nodes do not share the developer's files.
+pub(super) fn check_dev_mounts<'a, I>(
+    config: &Config,
+    target: Option<&String>,
+    service_configs: I,
+) -> Result<(), AppsServiceError>
+where
+    I: IntoIterator<Item = &'a ServiceConfig>,
+{
+    let runtime = target
+        .and_then(|target| config.runtime_clusters().get(target))
+        .unwrap_or_else(|| config.runtime_config());
+
+    for service_config in service_configs {
+        if service_config.dev_mounts().is_empty() {
+            continue;
+        }
+        let not_permitted = |reason: String| AppsServiceError::DevMountNotPermitted {
+            service_name: service_config.service_name().clone(),
+            reason,
+        };
+
+        let Some(dev_mode) = config.dev_mode_config() else {
+            return Err(not_permitted(String::from("the dev mode is disabled")));
+        };
+        if !matches!(runtime, Runtime::Docker(_)) {
+            return Err(not_permitted(String::from(
+                "dev mounts are only supported by the Docker backend",
+            )));
+        }
+        if let Some(dev_mount) = service_config
+            .dev_mounts()
+            .iter()
+            .find(|dev_mount| !dev_mode.permits(dev_mount.source()))
+        {
+            return Err(not_permitted(format!(
+                "{} is not within the source roots of the dev mode",
+                dev_mount.source().display()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config_from_str, sc};
+
+    fn service_with_dev_mount(source: &str) -> ServiceConfig {
+        let mut service_config = sc!("shop");
+        service_config.set_dev_mounts(
+            serde_json::from_value(serde_json::json!([{ "source": source, "target": "/app" }]))
+                .unwrap(),
+        );
+        service_config
+    }
+
+    #[test]
+    fn should_permit_dev_mounts_within_source_roots() {
+        let config = config_from_str!(
+            r#"
+            [devMode]
+            sourceRoots = [ '/home/dev/projects' ]
+            "#
+        );
+
+        let result = check_dev_mounts(
+            &config,
+            None,
+            &[service_with_dev_mount("/home/dev/projects/shop")],
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn should_reject_dev_mounts_outside_of_source_roots() {
+        let config = config_from_str!(
+            r#"
+            [devMode]
+            sourceRoots = [ '/home/dev/projects' ]
+            "#
+        );
+
+        let result = check_dev_mounts(&config, None, &[service_with_dev_mount("/etc")]);
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::DevMountNotPermitted { .. })
+        ));
+    }
+
+    #[test]
+    fn should_reject_dev_mounts_without_dev_mode() {
+        let config = config_from_str!("");
+
+        let result = check_dev_mounts(
+            &config,
+            None,
+            &[service_with_dev_mount("/home/dev/projects/shop")],
+        );
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::DevMountNotPermitted { .. })
+        ));
+    }
+
+    #[test]
+    fn should_reject_dev_mounts_on_kubernetes() {
+        let config = config_from_str!(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+
+            [devMode]
+            sourceRoots = [ '/home/dev/projects' ]
+            "#
+        );
+
+        let result = check_dev_mounts(
+            &config,
+            None,
+            &[service_with_dev_mount("/home/dev/projects/shop")],
+        );
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::DevMountNotPermitted { .. })
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::AppsService;
+use crate::models::service::{Service, ServiceStatus};
+use crate::models::AppName;
+use multimap::MultiMap;
+use rocket::response::stream::{Event, EventStream};
+use rocket::{Shutdown, State};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+/// The number of events that a slow client may lag behind before it misses events.
+const CAPACITY: usize = 256;
+
+/// The interval in which the states of the services are compared while clients listen.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A change of an app or of one of its services that is pushed to the clients of `GET /api/events`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AppEvent {
+    #[serde(rename_all = "camelCase")]
+    AppCreated {
+        app_name: AppName,
+        services: Vec<Service>,
+    },
+    #[serde(rename_all = "camelCase")]
+    AppUpdated {
+        app_name: AppName,
+        services: Vec<Service>,
+    },
+    #[serde(rename_all = "camelCase")]
+    AppSoftDeleted { app_name: AppName },
+    #[serde(rename_all = "camelCase")]
+    AppDeleted { app_name: AppName },
+    #[serde(rename_all = "camelCase")]
+    ServiceStateChanged {
+        app_name: AppName,
+        service_name: String,
+        status: ServiceStatus,
+    },
+    #[serde(rename_all = "camelCase")]
+    ServiceRemoved {
+        app_name: AppName,
+        service_name: String,
+    },
+}
+
+impl AppEvent {
+    /// The name of the server-sent event so that clients can listen to specific events.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppEvent::AppCreated { .. } => "appCreated",
+            AppEvent::AppUpdated { .. } => "appUpdated",
+            AppEvent::AppSoftDeleted { .. } => "appSoftDeleted",
+            AppEvent::AppDeleted { .. } => "appDeleted",
+            AppEvent::ServiceStateChanged { .. } => "serviceStateChanged",
+            AppEvent::ServiceRemoved { .. } => "serviceRemoved",
+        }
+    }
+}
+
+/// Broadcasts the events of the deployment pipeline and of the service state watcher to all
+/// subscribers.
+pub struct AppEvents {
+    sender: Sender<AppEvent>,
+}
+
+impl AppEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: AppEvent) {
+        // Without subscribers, there is nobody who could miss the event.
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<AppEvent> {
+        self.sender.subscribe()
+    }
+
+    fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+}
+
+type ServiceStates = BTreeMap<(AppName, String), ServiceStatus>;
+
+fn service_states(services: &MultiMap<AppName, Service>) -> ServiceStates {
+    services
+        .iter_all()
+        .flat_map(|(app_name, services)| {
+            services.iter().map(move |service| {
+                (
+                    (app_name.clone(), service.service_name().clone()),
+                    service.status().clone(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Compares the states of the services and returns the transitions between them.
+fn state_changes(previous: &ServiceStates, current: &ServiceStates) -> Vec<AppEvent> {
+    let changed = current
+        .iter()
+        .filter(|(key, status)| previous.get(*key) != Some(*status))
+        .map(
+            |((app_name, service_name), status)| AppEvent::ServiceStateChanged {
+                app_name: app_name.clone(),
+                service_name: service_name.clone(),
+                status: status.clone(),
+            },
+        );
+    let removed = previous
+        .keys()
+        .filter(|key| !current.contains_key(*key))
+        .map(|(app_name, service_name)| AppEvent::ServiceRemoved {
+            app_name: app_name.clone(),
+            service_name: service_name.clone(),
+        });
+
+    changed.chain(removed).collect()
+}
+
+/// Watches the states of the services while clients listen to the events and publishes the
+/// transitions that have not been caused by PREvant, e.g. services that have been paused or
+/// stopped by the container runtime.
+pub fn spawn_service_state_watcher(apps: Arc<AppsService>) {
+    tokio::spawn(async move {
+        let mut states: Option<ServiceStates> = None;
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            if !apps.events.has_subscribers() {
+                states = None;
+                continue;
+            }
+
+            let current = match apps.infrastructure.get_services().await {
+                Ok(services) => service_states(&services),
+                Err(err) => {
+                    warn!("Cannot watch the states of the services: {}", err);
+                    continue;
+                }
+            };
+            if let Some(previous) = &states {
+                for event in state_changes(previous, &current) {
+                    apps.events.publish(event);
+                }
+            }
+            states = Some(current);
+        }
+    });
+}
+
+/// Pushes the changes of apps and services as server-sent events so that clients don't need to
+/// poll `GET /api/apps`. Clients that fall behind receive a `lagged` event with the number of
+/// missed events and should reload the apps.
+#[get("/events")]
+pub fn events(apps: &State<Arc<AppsService>>, mut shutdown: Shutdown) -> EventStream![] {
+    let mut receiver = apps.subscribe_events();
+    EventStream! {
+        loop {
+            let event = tokio::select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(missed)) => {
+                        yield Event::data(missed.to_string()).event("lagged");
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+            yield Event::json(&event).event(event.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(states: &[(&str, ServiceStatus)]) -> ServiceStates {
+        states
+            .iter()
+            .map(|(service_name, status)| {
+                (
+                    (AppName::master(), service_name.to_string()),
+                    status.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_detect_state_changes() {
+        let previous = states(&[
+            ("db", ServiceStatus::Running),
+            ("blog", ServiceStatus::Running),
+            ("mail", ServiceStatus::Running),
+        ]);
+        let current = states(&[
+            ("db", ServiceStatus::Running),
+            ("blog", ServiceStatus::Paused),
+            ("api", ServiceStatus::Running),
+        ]);
+
+        assert_eq!(
+            state_changes(&previous, &current),
+            vec![
+                AppEvent::ServiceStateChanged {
+                    app_name: AppName::master(),
+                    service_name: String::from("api"),
+                    status: ServiceStatus::Running,
+                },
+                AppEvent::ServiceStateChanged {
+                    app_name: AppName::master(),
+                    service_name: String::from("blog"),
+                    status: ServiceStatus::Paused,
+                },
+                AppEvent::ServiceRemoved {
+                    app_name: AppName::master(),
+                    service_name: String::from("mail"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_serialize_event_with_type() {
+        let event = AppEvent::AppDeleted {
+            app_name: AppName::master(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "type": "appDeleted", "appName": "master" })
+        );
+    }
+}
@@ -257,6 +257,7 @@ impl HostMetaCrawler {
         };
 
         let get_request = reqwest::Client::builder()
+            .no_proxy()
             .connect_timeout(Duration::from_millis(500))
             .timeout(Duration::from_millis(750))
             .user_agent(format!("PREvant/{}", clap::crate_version!()))
@@ -0,0 +1,67 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::infrastructure::Infrastructure;
+use crate::models::{AppName, LifecycleHook, LifecycleHookLog, LifecycleHookTarget};
+
+/// Runs the `hooks` of the app one after another and stops at the first hook that fails. The
+/// returned logs contain the output of all hooks that have been run.
+pub(super) async fn run_lifecycle_hooks(
+    infrastructure: &dyn Infrastructure,
+    app_name: &AppName,
+    hooks: &[LifecycleHook],
+) -> Vec<LifecycleHookLog> {
+    let mut logs = Vec::with_capacity(hooks.len());
+
+    for hook in hooks {
+        let outcome = match hook.target() {
+            LifecycleHookTarget::Service(service_name) => {
+                infrastructure
+                    .exec_in_service(app_name, service_name, hook.command(), hook.timeout())
+                    .await
+            }
+            // One-shot jobs run like the containers of smoke tests within the network of the app.
+            LifecycleHookTarget::Job(image) => {
+                infrastructure
+                    .run_smoke_test(app_name, image, hook.command(), hook.timeout())
+                    .await
+            }
+        };
+
+        let log = match outcome {
+            Ok(outcome) => {
+                LifecycleHookLog::new(hook, outcome.passed(), outcome.logs().to_string())
+            }
+            Err(err) => LifecycleHookLog::new(hook, false, err.to_string()),
+        };
+        let passed = log.passed();
+        logs.push(log);
+        if !passed {
+            break;
+        }
+    }
+
+    logs
+}
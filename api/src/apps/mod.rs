@@ -23,37 +23,271 @@
  * THE SOFTWARE.
  * =========================LICENSE_END==================================
  */
+mod backup;
+mod builds;
+mod capacity;
+mod deployment_queue;
+mod descriptor;
+mod dev_mode;
+mod events;
 mod host_meta_cache;
+mod lifecycle_hooks;
+mod mail_catcher;
+mod query;
 mod routes;
+mod seeding;
+mod shared_database;
+mod smoke_test;
+mod state_store;
+mod v2;
 
 pub use crate::apps::AppsService as Apps;
 pub use crate::apps::AppsServiceError as AppsError;
-use crate::config::{Config, ConfigError};
-use crate::deployment::deployment_unit::DeploymentUnitBuilder;
-use crate::infrastructure::Infrastructure;
-use crate::models::service::{ContainerType, Service, ServiceStatus};
-use crate::models::{AppName, AppStatusChangeId, LogChunk, ServiceConfig};
+use crate::config::{
+    BundleFormat, Config, ConfigError, ConfigHandle, CostsConfig, GitConfig, LogArchiveConfig,
+    NetworkConfig, NotificationEvent, ServiceTemplateError, StaticAssetsConfig,
+};
+use crate::deployment::deployment_unit::{DeploymentUnit, DeploymentUnitBuilder};
+use crate::infrastructure::{Infrastructure, TraefikRouterRuleError};
+use crate::leadership::Leadership;
+use crate::models::service::{ContainerType, Service, ServiceBuilder, ServiceStatus};
+use crate::models::{
+    is_dns_label, AppGraph, AppName, AppNameError, AppStatusChangeId, AuditEntry, Backup,
+    CapacityShortage, DeploymentDiff, DeploymentHistoryEntry, Environment, FileReload, FilesUpdate,
+    LifecycleHookLog, LifecycleHooks, Locale, LogChunk, LogFilter, Mail, ReplicationRules,
+    ResourceMetadata, Sbom, SelfCheck, ServiceConfig, ServicePatch, Severity, ShareToken,
+    SmokeTest, SourceBuild, TimeZone,
+};
+use crate::notifications::{send_notifications, Notification};
 use crate::registry::Registry;
 use crate::registry::RegistryError;
-use chrono::{DateTime, FixedOffset};
+use crate::sbom::SbomGenerator;
+use crate::static_assets::{self, StaticAssetsError};
+use crate::vulnerabilities::{VulnerabilityScanError, VulnerabilityScanner};
+use chrono::{DateTime, FixedOffset, Utc};
+pub use deployment_queue::QueuedDeployment;
+use deployment_queue::{CancelError, CancellationToken, DeploymentQueue};
+use events::AppEvents;
+pub use events::{events, spawn_service_state_watcher, AppEvent};
 use handlebars::RenderError;
 pub use host_meta_cache::new as host_meta_crawling;
 pub use host_meta_cache::HostMetaCache;
 use multimap::MultiMap;
-pub use routes::{apps_routes, delete_app_sync};
-use std::collections::{HashMap, HashSet};
+use regex::Regex;
+pub use routes::{apps_routes, apps_v2_routes, delete_app_sync, RequestUser};
+pub use state_store::StateStoreError;
+use state_store::{create_state_store, StateStore};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::From;
 use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
+use uuid::Uuid;
+
+/// The number of deployments that will be kept in the deployment history of each app.
+const MAX_HISTORY_ENTRIES: usize = 20;
+
+/// The lifetime of share tokens that have been created without an explicit expiry.
+const DEFAULT_SHARE_TOKEN_LIFETIME_DAYS: i64 = 7;
 
 pub struct AppsService {
-    config: Config,
+    config: ConfigHandle,
     infrastructure: Box<dyn Infrastructure>,
     app_guards: Mutex<HashMap<AppName, Arc<AppGuard>>>,
+    vulnerability_scanner: Arc<VulnerabilityScanner>,
+    sbom_generator: Arc<SbomGenerator>,
+    /// The deployment history, the apps that have been deleted softly, and the hooks of the
+    /// latest deployment of each app that run before the app is deleted.
+    state_store: Arc<dyn StateStore>,
+    deployment_queue: DeploymentQueue,
+    leadership: Leadership,
+    events: AppEvents,
 }
 
 type GuardedResult = Result<Vec<Service>, AppsServiceError>;
 
+/// The app metadata key that protects an app from deletion if its value is `true`.
+const PROTECTED_METADATA_KEY: &str = "protected";
+
+/// App wide options of a deployment request that are not bound to a specific service.
+#[derive(Clone, Debug, Default)]
+pub struct DeploymentOptions {
+    namespace_annotations: BTreeMap<String, String>,
+    stacks: Vec<String>,
+    target: Option<String>,
+    smoke_test: Option<SmokeTest>,
+    lifecycle_hooks: Option<LifecycleHooks>,
+    app_metadata: BTreeMap<String, String>,
+    replication_rules: ReplicationRules,
+    owner: Option<String>,
+    builds: Vec<SourceBuild>,
+    app_env: Option<Environment>,
+    timezone: Option<TimeZone>,
+    locale: Option<Locale>,
+    service_doubles: Vec<String>,
+    resource_metadata: Option<ResourceMetadata>,
+    only_services: Vec<String>,
+}
+
+impl DeploymentOptions {
+    pub fn with_namespace_annotations(
+        mut self,
+        namespace_annotations: BTreeMap<String, String>,
+    ) -> Self {
+        self.namespace_annotations = namespace_annotations;
+        self
+    }
+
+    pub fn with_stacks(mut self, stacks: Vec<String>) -> Self {
+        self.stacks = stacks;
+        self
+    }
+
+    pub fn with_target(mut self, target: Option<String>) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_smoke_test(mut self, smoke_test: Option<SmokeTest>) -> Self {
+        self.smoke_test = smoke_test;
+        self
+    }
+
+    pub fn with_lifecycle_hooks(mut self, lifecycle_hooks: Option<LifecycleHooks>) -> Self {
+        self.lifecycle_hooks = lifecycle_hooks;
+        self
+    }
+
+    pub fn with_app_metadata(mut self, app_metadata: BTreeMap<String, String>) -> Self {
+        self.app_metadata = app_metadata;
+        self
+    }
+
+    pub fn with_replication_rules(mut self, replication_rules: ReplicationRules) -> Self {
+        self.replication_rules = replication_rules;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    pub fn with_builds(mut self, builds: Vec<SourceBuild>) -> Self {
+        self.builds = builds;
+        self
+    }
+
+    pub fn with_app_env(mut self, app_env: Option<Environment>) -> Self {
+        self.app_env = app_env;
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: Option<TimeZone>) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn with_locale(mut self, locale: Option<Locale>) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    pub fn with_service_doubles(mut self, service_doubles: Vec<String>) -> Self {
+        self.service_doubles = service_doubles;
+        self
+    }
+
+    pub fn with_resource_metadata(mut self, resource_metadata: Option<ResourceMetadata>) -> Self {
+        self.resource_metadata = resource_metadata;
+        self
+    }
+
+    /// Annotations for the namespace of the app which will be merged with the annotations of
+    /// the runtime configuration.
+    pub fn namespace_annotations(&self) -> &BTreeMap<String, String> {
+        &self.namespace_annotations
+    }
+
+    /// Names of the configured stacks whose companions will be deployed with the app.
+    pub fn stacks(&self) -> &[String] {
+        &self.stacks
+    }
+
+    /// Name of the configured cluster the app will be deployed to.
+    pub fn target(&self) -> Option<&String> {
+        self.target.as_ref()
+    }
+
+    /// The smoke test that must pass after all services have been deployed.
+    pub fn smoke_test(&self) -> Option<&SmokeTest> {
+        self.smoke_test.as_ref()
+    }
+
+    /// The hooks that run after the deployment and before the deletion of the app.
+    pub fn lifecycle_hooks(&self) -> Option<&LifecycleHooks> {
+        self.lifecycle_hooks.as_ref()
+    }
+
+    /// Arbitrary metadata, e.g. the originating branch or commit, that will be stored with the
+    /// app and returned with its services.
+    pub fn app_metadata(&self) -> &BTreeMap<String, String> {
+        &self.app_metadata
+    }
+
+    /// Services that are excluded from, or overridden during, the replication of the template app.
+    pub fn replication_rules(&self) -> &ReplicationRules {
+        &self.replication_rules
+    }
+
+    /// The user that will be recorded as owner of the app unless the app already has an owner.
+    pub fn owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
+
+    /// The services whose images will be built from their sources before the deployment.
+    pub fn builds(&self) -> &[SourceBuild] {
+        &self.builds
+    }
+
+    /// Environment variables that will be injected into all services of the app. They take
+    /// precedence over the app env of the configuration but not over the variables of the services.
+    pub fn app_env(&self) -> Option<&Environment> {
+        self.app_env.as_ref()
+    }
+
+    /// The time zone of all services of the app that don't define their own.
+    pub fn timezone(&self) -> Option<&TimeZone> {
+        self.timezone.as_ref()
+    }
+
+    /// The locale of all services of the app that don't define their own.
+    pub fn locale(&self) -> Option<&Locale> {
+        self.locale.as_ref()
+    }
+
+    /// Names of the service doubles, e.g. `s3`, that will be deployed with the app instead of
+    /// the real services.
+    pub fn service_doubles(&self) -> &[String] {
+        &self.service_doubles
+    }
+
+    /// Labels and annotations that will be attached to the resources of all services of the app.
+    pub fn resource_metadata(&self) -> Option<&ResourceMetadata> {
+        self.resource_metadata.as_ref()
+    }
+
+    pub fn with_only_services(mut self, only_services: Vec<String>) -> Self {
+        self.only_services = only_services;
+        self
+    }
+
+    /// The services the deployment is restricted to while the other services of the app, e.g.
+    /// the companions, stay untouched. An empty list deploys all services.
+    pub fn only_services(&self) -> &[String] {
+        &self.only_services
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum AppGuardKind {
     Deployment,
@@ -125,22 +359,165 @@ impl AppGuard {
     }
 }
 
+/// Periodically tears down the apps whose grace period after their soft deletion has expired.
+pub fn spawn_soft_delete_reaper(apps: Arc<AppsService>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            apps.reap_soft_deleted_apps().await;
+        }
+    });
+}
+
 impl AppsService {
     pub fn new(
         config: Config,
         infrastructure: Box<dyn Infrastructure>,
     ) -> Result<AppsService, AppsServiceError> {
+        Self::with_config_handle(ConfigHandle::new(config), infrastructure)
+    }
+
+    /// Creates the service with a configuration that might be replaced while PREvant is running.
+    pub fn with_config_handle(
+        config: ConfigHandle,
+        infrastructure: Box<dyn Infrastructure>,
+    ) -> Result<AppsService, AppsServiceError> {
+        let state_store = create_state_store(config.current().state_config());
         Ok(AppsService {
             config,
             infrastructure,
             app_guards: Mutex::new(HashMap::new()),
+            vulnerability_scanner: Arc::new(VulnerabilityScanner::new()),
+            sbom_generator: Arc::new(SbomGenerator::new()),
+            state_store: Arc::from(state_store),
+            deployment_queue: DeploymentQueue::new(),
+            leadership: Leadership::default(),
+            events: AppEvents::new(),
         })
     }
 
+    /// Subscribes to the changes of apps and services, see [`AppEvent`].
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<AppEvent> {
+        self.events.subscribe()
+    }
+
+    /// Restricts the changes of apps to the periods in which this replica of PREvant is the
+    /// leader among the replicas.
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = leadership;
+        self
+    }
+
+    /// Waits for the leadership if the lease is being handed over and fails if another replica
+    /// still leads afterwards.
+    async fn ensure_leader(&self) -> Result<(), AppsServiceError> {
+        if self.leadership.wait_for_leadership().await {
+            Ok(())
+        } else {
+            self.check_leader()
+        }
+    }
+
+    fn check_leader(&self) -> Result<(), AppsServiceError> {
+        if self.leadership.is_leader() {
+            Ok(())
+        } else {
+            Err(AppsServiceError::NotLeader {
+                leader: self
+                    .leadership
+                    .leader()
+                    .unwrap_or_else(|| String::from("<unknown>")),
+            })
+        }
+    }
+
+    /// The interval in seconds in which the placeholder page of unavailable services reloads
+    /// itself.
+    pub fn placeholder_refresh_interval(&self) -> u32 {
+        self.config
+            .current()
+            .placeholder_config()
+            .map(|placeholder| placeholder.refresh_interval())
+            .unwrap_or(5)
+    }
+
+    /// The configuration to fetch deployment descriptors from Git repositories.
+    pub fn git_config(&self) -> GitConfig {
+        self.config.current().git_config().clone()
+    }
+
+    /// The prices of the resources to estimate the costs of the apps, if configured.
+    pub fn costs_config(&self) -> Option<CostsConfig> {
+        self.config.current().costs_config().cloned()
+    }
+
+    /// The hosting of uploaded static assets, if configured.
+    pub fn static_assets_config(&self) -> Option<StaticAssetsConfig> {
+        self.config.current().static_assets_config().cloned()
+    }
+
+    /// Verifies the runtime prerequisites of the infrastructure, e.g. the connectivity to the
+    /// cluster.
+    pub async fn self_check(&self) -> Vec<SelfCheck> {
+        self.infrastructure.self_check().await
+    }
+
+    /// Returns `true` if this replica of PREvant is permitted to change the apps.
+    pub fn is_leader(&self) -> bool {
+        self.leadership.is_leader()
+    }
+
+    /// Returns `false` if a panic left the in-memory state of the apps, for example, the
+    /// deployment queue, in an inconsistent state.
+    pub fn is_state_consistent(&self) -> bool {
+        !(self.app_guards.is_poisoned() || self.deployment_queue.is_poisoned())
+    }
+
+    /// Verifies that the store of the deployment history and the softly deleted apps is usable.
+    pub async fn check_state_store(&self) -> Result<(), StateStoreError> {
+        self.state_store.check().await
+    }
+
     /// Analyzes running containers and returns a map of `app-name` with the
     /// corresponding list of `Service`s.
     pub async fn get_apps(&self) -> Result<MultiMap<AppName, Service>, AppsServiceError> {
-        Ok(self.infrastructure.get_services().await?)
+        let services = self.infrastructure.get_services().await?;
+        let config = self.config.current();
+        let memory_limit = config.container_config().memory_limit();
+        let owners = match self.state_store.owners().await {
+            Ok(owners) => owners,
+            Err(err) => {
+                warn!("Cannot determine the owners of the apps: {}", err);
+                HashMap::new()
+            }
+        };
+
+        let mut apps = MultiMap::new();
+        for (app_name, services) in services.into_iter() {
+            for service in services {
+                let summary = self
+                    .vulnerability_scanner
+                    .cached_summary(service.config().image(), service.image_digest());
+                let cost_estimate = config
+                    .costs_config()
+                    .map(|costs| costs.estimate(&service, memory_limit));
+
+                let mut builder = ServiceBuilder::from(service);
+                if let Some(summary) = summary {
+                    builder = builder.vulnerabilities(summary);
+                }
+                if let Some(cost_estimate) = cost_estimate {
+                    builder = builder.cost_estimate(cost_estimate);
+                }
+                if let Some(owner) = owners.get(&app_name) {
+                    builder = builder.owner(owner.clone());
+                }
+                let service = builder.build().map_err(failure::Error::from)?;
+                apps.insert(app_name.clone(), service);
+            }
+        }
+
+        Ok(apps)
     }
 
     fn create_or_get_app_guard(
@@ -191,6 +568,7 @@ impl AppsService {
             .map(|config| {
                 let mut replicated_config = config;
                 replicated_config.set_container_type(ContainerType::Replica);
+                replicated_config.set_replicated_from(Some(replicate_from_app_name.to_string()));
                 replicated_config
             })
             .collect::<Vec<ServiceConfig>>())
@@ -228,6 +606,37 @@ impl AppsService {
         replicate_from: Option<AppName>,
         service_configs: &[ServiceConfig],
     ) -> Result<Vec<Service>, AppsServiceError> {
+        self.create_or_update_with_options(
+            app_name,
+            status_id,
+            replicate_from,
+            service_configs,
+            &DeploymentOptions::default(),
+        )
+        .await
+    }
+
+    /// Same as [`AppsService::create_or_update`] but with additional, app wide
+    /// [options](`DeploymentOptions`) of the deployment request.
+    pub async fn create_or_update_with_options(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        options: &DeploymentOptions,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        // Overlapping deployments of the app wait for each other in the order of their requests.
+        let ticket = self.deployment_queue.enqueue(app_name, status_id);
+        if !self.deployment_queue.wait_for_turn(&ticket).await {
+            return Err(AppsServiceError::DeploymentCancelled {
+                app_name: app_name.clone(),
+                status_id: *status_id,
+            });
+        }
+
         let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deployment)?;
 
         if !guard.is_first() {
@@ -238,146 +647,1665 @@ impl AppsService {
 
         guard.notify_with_result(
             self,
-            self.create_or_update_impl(app_name, status_id, replicate_from, service_configs)
-                .await,
+            self.create_or_update_impl(
+                app_name,
+                status_id,
+                replicate_from,
+                service_configs,
+                options,
+                ticket.cancellation(),
+            )
+            .await,
         )
     }
 
-    async fn create_or_update_impl(
+    /// Returns the running deployment of the app followed by the deployments waiting for it.
+    pub fn queued_deployments(&self, app_name: &AppName) -> Vec<QueuedDeployment> {
+        self.deployment_queue.queued_deployments(app_name)
+    }
+
+    /// Removes a deployment that waits for the running deployment of the app from the queue.
+    pub fn cancel_queued_deployment(
         &self,
         app_name: &AppName,
         status_id: &AppStatusChangeId,
-        replicate_from: Option<AppName>,
-        service_configs: &[ServiceConfig],
-    ) -> Result<Vec<Service>, AppsServiceError> {
-        let mut configs = service_configs.to_vec();
-
-        let replicate_from_app_name = replicate_from.unwrap_or_else(AppName::master);
-        if &replicate_from_app_name != app_name {
-            configs.extend(
-                self.configs_to_replicate(service_configs, app_name, &replicate_from_app_name)
-                    .await?,
-            );
-        }
-
-        let configs_for_templating = self
-            .infrastructure
-            .get_configs_of_app(app_name)
-            .await?
-            .into_iter()
-            .filter(|config| config.container_type() == &ContainerType::Instance)
-            .filter(|config| {
-                !service_configs
-                    .iter()
-                    .any(|c| c.service_name() == config.service_name())
+    ) -> Result<(), AppsServiceError> {
+        self.check_leader()?;
+
+        self.deployment_queue
+            .cancel(app_name, status_id)
+            .map_err(|err| match err {
+                CancelError::NotQueued => AppsServiceError::DeploymentNotQueued {
+                    app_name: app_name.clone(),
+                    status_id: *status_id,
+                },
+                CancelError::AlreadyRunning => AppsServiceError::AppIsInDeployment {
+                    app_name: app_name.clone(),
+                },
             })
-            .collect::<Vec<_>>();
-
-        let deployment_unit_builder = DeploymentUnitBuilder::init(app_name.clone(), configs)
-            .extend_with_config(&self.config)
-            .extend_with_templating_only_service_configs(configs_for_templating);
+    }
 
-        let images = deployment_unit_builder.images();
-        let image_infos = Registry::new(&self.config)
-            .resolve_image_infos(&images)
-            .await?;
+    /// Cancels a deployment of the app, regardless of whether it is waiting or already running.
+    /// A running deployment stops at its next infrastructure call, cleans up what it created and
+    /// is recorded as canceled in the deployment history.
+    pub fn cancel_deployment(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+    ) -> Result<(), AppsServiceError> {
+        self.check_leader()?;
 
-        let deployment_unit_builder = deployment_unit_builder
-            .extend_with_image_infos(image_infos)
-            .apply_templating()?
-            .apply_hooks(&self.config)
-            .await?;
+        self.deployment_queue
+            .abort(app_name, status_id)
+            .map_err(|_| AppsServiceError::DeploymentNotQueued {
+                app_name: app_name.clone(),
+                status_id: *status_id,
+            })
+    }
 
-        let deployment_unit = if let Ok(Some(base_traefik_ingress_route)) =
-            self.infrastructure.base_traefik_ingress_route().await
-        {
-            deployment_unit_builder
-                .apply_base_traefik_ingress_route(base_traefik_ingress_route)
-                .build()
-        } else {
-            deployment_unit_builder.build()
-        };
+    /// Stores the uploaded bundle of static assets, e.g. a built single page application, and
+    /// deploys the service that serves the bundle with nginx.
+    pub async fn deploy_static_assets(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        service_name: &str,
+        bundle: Vec<u8>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let config = self.config.current();
+        let assets_config = config
+            .static_assets_config()
+            .ok_or(AppsServiceError::StaticAssetsNotConfigured)?;
+        if !is_dns_label(service_name) {
+            return Err(AppsServiceError::InvalidServiceName {
+                service_name: service_name.to_string(),
+            });
+        }
+        self.validate_app_name(&config, app_name).await?;
+        let format = BundleFormat::detect(&bundle).ok_or(AppsServiceError::InvalidStaticAssets)?;
 
-        let services = self
-            .infrastructure
-            .deploy_services(
-                &status_id.to_string(),
-                &deployment_unit,
-                &self.config.container_config(),
-            )
-            .await?;
+        let digest = static_assets::digest(&bundle);
+        static_assets::store_bundle(
+            config.network_config(),
+            assets_config.storage(),
+            app_name,
+            service_name,
+            bundle,
+        )
+        .await?;
 
-        Ok(services)
+        let service_config = assets_config.service_config(app_name, service_name, format, &digest);
+        self.create_or_update(app_name, status_id, None, &[service_config])
+            .await
     }
 
-    /// Deletes all services for the given `app_name`.
-    pub async fn delete_app(
+    /// The uploaded bundle of static assets that the nginx service downloads on startup.
+    pub async fn static_assets_bundle(
         &self,
         app_name: &AppName,
-        status_id: &AppStatusChangeId,
-    ) -> Result<Vec<Service>, AppsServiceError> {
-        let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deletion)?;
+        service_name: &str,
+    ) -> Result<Vec<u8>, AppsServiceError> {
+        let config = self.config.current();
+        let assets_config = config
+            .static_assets_config()
+            .ok_or(AppsServiceError::StaticAssetsNotConfigured)?;
+        let not_found = || AppsServiceError::StaticAssetsNotFound {
+            app_name: app_name.clone(),
+            service_name: service_name.to_string(),
+        };
 
-        if !guard.is_first() {
-            guard.wait_for_result()
-        } else {
-            guard.notify_with_result(self, self.delete_app_impl(app_name, status_id).await)
+        let services = self.infrastructure.get_services().await?;
+        if !is_dns_label(service_name)
+            || !services.get_vec(app_name).map_or(false, |services| {
+                services
+                    .iter()
+                    .any(|service| service.service_name() == service_name)
+            })
+        {
+            return Err(not_found());
         }
+
+        static_assets::load_bundle(
+            config.network_config(),
+            assets_config.storage(),
+            app_name,
+            service_name,
+        )
+        .await?
+        .ok_or_else(not_found)
     }
 
-    async fn delete_app_impl(
+    /// Computes the changes that a deployment of the `service_configs` would apply to the
+    /// running services of the app without deploying anything.
+    pub async fn dry_run(
         &self,
         app_name: &AppName,
-        status_id: &AppStatusChangeId,
-    ) -> Result<Vec<Service>, AppsServiceError> {
-        let services = self
-            .infrastructure
-            .stop_services(&status_id.to_string(), app_name)
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        options: &DeploymentOptions,
+    ) -> Result<DeploymentDiff, AppsServiceError> {
+        let config = self.config.current();
+        self.validate_app_name(&config, app_name).await?;
+
+        let deployment_unit = self
+            .deployment_unit(&config, app_name, replicate_from, service_configs, options)
             .await?;
-        if services.is_empty() {
-            Err(AppsServiceError::AppNotFound {
-                app_name: app_name.clone(),
-            })
-        } else {
-            Ok(services)
-        }
+
+        self.deployment_diff(&deployment_unit).await
     }
 
-    pub async fn get_logs(
+    /// Validates the name of a new app against the configured policy on every path that creates
+    /// an app, e.g. deployments, adoptions, or restored backups. Apps that have been created
+    /// before the policy has been configured can still be updated.
+    async fn validate_app_name(
         &self,
+        config: &Config,
         app_name: &AppName,
-        service_name: &String,
-        since: &Option<DateTime<FixedOffset>>,
-        limit: usize,
-    ) -> Result<Option<LogChunk>, AppsServiceError> {
-        match self
+    ) -> Result<(), AppsServiceError> {
+        if !self
             .infrastructure
-            .get_logs(app_name, service_name, since, limit)
+            .get_configs_of_app(app_name)
             .await?
+            .is_empty()
         {
-            None => Ok(None),
-            Some(ref logs) if logs.is_empty() => Ok(None),
-            Some(logs) => Ok(Some(LogChunk::from(logs))),
+            return Ok(());
         }
+
+        config
+            .app_name_policy()
+            .validate(app_name)
+            .map_err(|error| AppsServiceError::InvalidAppName { error })
     }
 
-    pub async fn change_status(
-        &self,
-        app_name: &AppName,
-        service_name: &String,
-        status: ServiceStatus,
-    ) -> Result<Option<Service>, AppsServiceError> {
-        Ok(self
-            .infrastructure
-            .change_status(app_name, service_name, status)
-            .await?)
+    /// Describes the services of the app and their relations as a graph. Companions are
+    /// described by their configuration because their running services only carry a part of it,
+    /// e.g. no files.
+    pub async fn graph(&self, app_name: &AppName) -> Result<AppGraph, AppsServiceError> {
+        let services = self.infrastructure.get_services().await?;
+        let services = services
+            .get_vec(app_name)
+            .ok_or_else(|| AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            })?;
+
+        let config = self.config.current();
+        let companions = config
+            .application_companion_configs(app_name)
+            .into_iter()
+            .chain(config.service_companion_configs(app_name))
+            .map(|(companion, _, _)| (companion.service_name().clone(), companion))
+            .collect::<HashMap<_, _>>();
+
+        let configs = services
+            .iter()
+            .map(|service| {
+                match (
+                    service.container_type(),
+                    companions.get(service.service_name()),
+                ) {
+                    (
+                        ContainerType::ApplicationCompanion | ContainerType::ServiceCompanion,
+                        Some(companion),
+                    ) => {
+                        let mut companion = companion.clone();
+                        companion.set_container_type(service.container_type().clone());
+                        companion
+                    }
+                    _ => service.config().clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(AppGraph::new(app_name, &configs))
     }
-}
 
-/// Defines error cases for the `AppService`
-#[derive(Debug, Clone, Fail)]
-pub enum AppsServiceError {
-    /// Will be used when no app with a given name is found
+    /// Returns the mails that the mail catcher of the app has captured.
+    pub async fn mails(&self, app_name: &AppName) -> Result<Vec<Mail>, AppsServiceError> {
+        let config = self.config.current();
+        let mail_catcher_not_found = || AppsServiceError::MailCatcherNotFound {
+            app_name: app_name.clone(),
+        };
+        let mail_catcher = config
+            .mail_catcher_config(app_name)
+            .ok_or_else(mail_catcher_not_found)?;
+
+        let services = self.infrastructure.get_services().await?;
+        let endpoint_url = services
+            .get_vec(app_name)
+            .and_then(|services| {
+                services
+                    .iter()
+                    .find(|service| service.service_name() == mail_catcher.service_name())
+            })
+            .and_then(Service::endpoint_url)
+            .ok_or_else(mail_catcher_not_found)?;
+
+        mail_catcher::fetch_mails(&endpoint_url)
+            .await
+            .map_err(|error| AppsServiceError::MailCatcherRequestFailed { error })
+    }
+
+    /// Returns the changes of the previous deployments of the app, starting with the oldest one.
+    /// The history is limited to the last deployments.
+    pub async fn deployment_history(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<DeploymentHistoryEntry>, AppsServiceError> {
+        Ok(self.state_store.deployment_history(app_name).await?)
+    }
+
+    async fn deployment_diff(
+        &self,
+        deployment_unit: &DeploymentUnit,
+    ) -> Result<DeploymentDiff, AppsServiceError> {
+        let services = self.infrastructure.get_services().await?;
+        let running = services
+            .get_vec(deployment_unit.app_name())
+            .map(|services| services.iter().map(Service::config).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        Ok(DeploymentDiff::new(
+            running,
+            deployment_unit
+                .services()
+                .iter()
+                .map(std::ops::Deref::deref),
+        ))
+    }
+
+    async fn record_deployment(&self, app_name: &AppName, entry: DeploymentHistoryEntry) {
+        // The deployment has been applied anyway, thus, it does not fail due to its history.
+        if let Err(err) = self
+            .state_store
+            .push_deployment(app_name, &entry, MAX_HISTORY_ENTRIES)
+            .await
+        {
+            warn!("Cannot record the deployment of {}: {}", app_name, err);
+        }
+    }
+
+    /// Ensures that the service accounts of the requested services only ask for rules within the
+    /// configured allowlist. The companions are configured by the operator and not checked.
+    fn check_service_account_rules(
+        config: &Config,
+        service_configs: &[ServiceConfig],
+    ) -> Result<(), AppsServiceError> {
+        let allowlist = config.service_accounts_config();
+        for service_config in service_configs {
+            let Some(service_account) = service_config.service_account() else {
+                continue;
+            };
+            if let Some(rule) = service_account
+                .rules()
+                .iter()
+                .find(|rule| !allowlist.permits(rule))
+            {
+                return Err(AppsServiceError::ServiceAccountRuleNotPermitted {
+                    service_name: service_config.service_name().clone(),
+                    rule: format!(
+                        "{} on {} of the API groups {:?}",
+                        rule.verbs().join(", "),
+                        rule.resources().join(", "),
+                        rule.api_groups()
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_or_update_impl(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        options: &DeploymentOptions,
+        cancellation: &CancellationToken,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let canceled = || AppsServiceError::DeploymentCancelled {
+            app_name: app_name.clone(),
+            status_id: *status_id,
+        };
+
+        // The snapshot ensures that the whole deployment uses the same configuration even if
+        // the configuration file is reloaded in the meantime.
+        let config = self.config.current();
+        self.validate_app_name(&config, app_name).await?;
+        Self::check_service_account_rules(&config, service_configs)?;
+
+        let service_configs = self
+            .build_images(&config, app_name, service_configs, options)
+            .await?;
+        if cancellation.is_cancelled() {
+            return Err(canceled());
+        }
+
+        self.deploy_shared_companions(&config, app_name).await?;
+
+        let deployment_unit = self
+            .deployment_unit(&config, app_name, replicate_from, &service_configs, options)
+            .await?;
+        // The aliases become the names of Kubernetes services, respectively hostnames on Docker.
+        if let Some((service, alias)) = deployment_unit.services().iter().find_map(|service| {
+            service
+                .aliases()
+                .iter()
+                .find(|alias| !is_dns_label(alias))
+                .map(|alias| (service, alias))
+        }) {
+            return Err(AppsServiceError::InvalidAlias {
+                service_name: service.service_name().clone(),
+                alias: alias.clone(),
+            });
+        }
+        dev_mode::check_dev_mounts(
+            &config,
+            deployment_unit.target(),
+            deployment_unit.services().iter().map(|service| &**service),
+        )?;
+        let seed_jobs =
+            seeding::seed_jobs(deployment_unit.services().iter().map(|service| &**service))?;
+        if let Some(capacity_check) = config.capacity_check_config() {
+            capacity::check_capacity(
+                self.infrastructure.as_ref(),
+                capacity_check,
+                &config.container_config(),
+                &deployment_unit,
+            )
+            .await?;
+        }
+        let diff = self.deployment_diff(&deployment_unit).await?;
+
+        if cancellation.is_cancelled() {
+            return Err(canceled());
+        }
+        let is_new_app = !self
+            .infrastructure
+            .get_services()
+            .await?
+            .contains_key(app_name);
+
+        if let Some(shared_database) = config.shared_database_config(app_name) {
+            shared_database::create_database(shared_database, app_name)
+                .await
+                .map_err(|err| AppsServiceError::SharedDatabaseFailure {
+                    error: err.to_string(),
+                })?;
+        }
+
+        let added_services = diff.added_services().clone();
+        let deployment = async {
+            let mut result = self
+                .scan_and_deploy(&config, status_id, &deployment_unit, options)
+                .await;
+
+            // Seeds run before the hooks because the hooks might rely on the seeded data.
+            let mut seed_logs = Vec::new();
+            if result.is_ok() {
+                seed_logs = seeding::run_seed_jobs(
+                    self.infrastructure.as_ref(),
+                    app_name,
+                    &seed_jobs,
+                    &added_services,
+                )
+                .await;
+                if let Some(failed_seed) = seed_logs.iter().find(|log| !log.passed()) {
+                    result = Err(AppsServiceError::SeedFailed {
+                        service_name: failed_seed.service_name().to_string(),
+                        logs: failed_seed.logs().to_string(),
+                    });
+                }
+            }
+
+            let mut hook_logs = Vec::new();
+            if let (Ok(_), Some(hooks)) = (&result, options.lifecycle_hooks()) {
+                hook_logs = lifecycle_hooks::run_lifecycle_hooks(
+                    self.infrastructure.as_ref(),
+                    app_name,
+                    hooks.post_deploy(),
+                )
+                .await;
+                if let Some(failed_hook) = hook_logs.iter().find(|log| !log.passed()) {
+                    result = Err(AppsServiceError::LifecycleHookFailed {
+                        logs: failed_hook.logs().to_string(),
+                    });
+                }
+            }
+
+            (result, hook_logs, seed_logs)
+        };
+
+        // Dropping the deployment future aborts the pending calls of the infrastructure.
+        let (result, hook_logs, seed_logs) = tokio::select! {
+            outcome = deployment => outcome,
+            _ = cancellation.cancelled() => {
+                self.clean_up_canceled_deployment(app_name, status_id, is_new_app)
+                    .await;
+                self.record_deployment(app_name, DeploymentHistoryEntry::canceled(diff))
+                    .await;
+                return Err(canceled());
+            }
+        };
+
+        if result.is_ok() {
+            if let Some(owner) = options.owner() {
+                self.record_owner(app_name, owner).await;
+            }
+            self.record_sboms(&config, &deployment_unit);
+        }
+
+        match &result {
+            Ok(services) if is_new_app => self.notify(
+                &config,
+                Notification::for_services(NotificationEvent::Created, app_name.clone(), services)
+                    .with_metadata(options.app_metadata().clone())
+                    .with_owner(self.owner_of(app_name).await),
+            ),
+            Ok(_) => {}
+            Err(err) => self.notify(
+                &config,
+                Notification::new(NotificationEvent::Failed, app_name.clone())
+                    .with_metadata(options.app_metadata().clone())
+                    .with_owner(self.owner_of(app_name).await)
+                    .with_error(err.to_string()),
+            ),
+        }
+
+        if let Ok(services) = &result {
+            let app_name = app_name.clone();
+            let services = services.clone();
+            self.events.publish(if is_new_app {
+                AppEvent::AppCreated { app_name, services }
+            } else {
+                AppEvent::AppUpdated { app_name, services }
+            });
+        }
+
+        // Services that did not pass the smoke test or the hooks stay deployed, thus the diff has
+        // been applied.
+        if matches!(
+            result,
+            Ok(_)
+                | Err(AppsServiceError::SmokeTestFailed { .. })
+                | Err(AppsServiceError::LifecycleHookFailed { .. })
+                | Err(AppsServiceError::SeedFailed { .. })
+        ) {
+            self.record_deployment(
+                app_name,
+                DeploymentHistoryEntry::new(diff, hook_logs).with_seeds(seed_logs),
+            )
+            .await;
+            // A new deployment revives an app that has been deleted softly.
+            if let Err(err) = self.state_store.remove_soft_deletion(app_name).await {
+                warn!("Cannot revive the deleted app {}: {}", app_name, err);
+            }
+            if let Some(hooks) = options.lifecycle_hooks() {
+                if let Err(err) = self
+                    .state_store
+                    .set_pre_destroy_hooks(app_name, hooks.pre_destroy())
+                    .await
+                {
+                    warn!(
+                        "Cannot store the pre-destroy hooks of {}: {}",
+                        app_name, err
+                    );
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Builds the images of the services that have been requested to be built from their sources
+    /// and returns the service configurations that refer to the built images.
+    async fn build_images(
+        &self,
+        config: &Config,
+        app_name: &AppName,
+        service_configs: &[ServiceConfig],
+        options: &DeploymentOptions,
+    ) -> Result<Vec<ServiceConfig>, AppsServiceError> {
+        if options.builds().is_empty() {
+            return Ok(service_configs.to_vec());
+        }
+
+        let builds_config = config
+            .builds_config()
+            .ok_or(AppsServiceError::BuildsNotConfigured)?;
+        builds::build_images(
+            self.infrastructure.as_ref(),
+            app_name,
+            builds_config,
+            options.builds(),
+            service_configs,
+        )
+        .await
+    }
+
+    /// Tears down the services of a canceled deployment of a new app. Apps that have been running
+    /// before keep the changes that have been applied until the cancellation.
+    async fn clean_up_canceled_deployment(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        is_new_app: bool,
+    ) {
+        if !is_new_app {
+            warn!(
+                "The deployment {} of {} has been canceled after parts of it have been applied.",
+                status_id, app_name
+            );
+            return;
+        }
+
+        if let Err(err) = self
+            .infrastructure
+            .stop_services(&status_id.to_string(), app_name)
+            .await
+        {
+            warn!(
+                "Cannot clean up the canceled deployment {} of {}: {}",
+                status_id, app_name, err
+            );
+        }
+    }
+
+    async fn deployment_unit(
+        &self,
+        config: &Config,
+        app_name: &AppName,
+        replicate_from: Option<AppName>,
+        service_configs: &[ServiceConfig],
+        options: &DeploymentOptions,
+    ) -> Result<DeploymentUnit, AppsServiceError> {
+        let mut resolved_configs = service_configs.to_vec();
+        for service_config in resolved_configs.iter_mut() {
+            config
+                .apply_templates_to(service_config)
+                .map_err(|error| AppsServiceError::InvalidServiceTemplate { error })?;
+        }
+        let service_configs = resolved_configs.as_slice();
+
+        let replicate_from_app_name = replicate_from.unwrap_or_else(AppName::master);
+        let replicated_configs = if &replicate_from_app_name != app_name {
+            self.configs_to_replicate(service_configs, app_name, &replicate_from_app_name)
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        let configs_for_templating = self
+            .infrastructure
+            .get_configs_of_app(app_name)
+            .await?
+            .into_iter()
+            .filter(|config| config.container_type() == &ContainerType::Instance)
+            .filter(|config| {
+                !service_configs
+                    .iter()
+                    .any(|c| c.service_name() == config.service_name())
+            })
+            .collect::<Vec<_>>();
+
+        lazy_static! {
+            static ref METADATA_KEY_REGEX: Regex =
+                Regex::new(r"^[A-Za-z0-9]([-A-Za-z0-9_.]{0,61}[A-Za-z0-9])?$").unwrap();
+        }
+        if let Some(key) = options
+            .app_metadata()
+            .keys()
+            .find(|key| !METADATA_KEY_REGEX.is_match(key))
+        {
+            return Err(AppsServiceError::InvalidAppMetadataKey { key: key.clone() });
+        }
+
+        if let Some(target) = options.target() {
+            if !config.runtime_clusters().contains_key(target) {
+                return Err(AppsServiceError::UnknownTarget {
+                    target: target.clone(),
+                });
+            }
+        }
+
+        let deployment_unit_builder =
+            DeploymentUnitBuilder::init(app_name.clone(), service_configs.to_vec())
+                .extend_with_replicated_configs(replicated_configs, options.replication_rules())
+                .extend_with_config(config)
+                .extend_with_stacks(config, options.stacks())?
+                .extend_with_service_doubles(config, options.service_doubles())?
+                .extend_with_localization(options.timezone(), options.locale())
+                .extend_with_app_env(config, options.app_env())
+                .extend_with_resource_metadata(options.resource_metadata())
+                .extend_with_templating_only_service_configs(configs_for_templating);
+
+        let images = deployment_unit_builder.images();
+        let image_infos = Registry::new(config).resolve_image_infos(&images).await?;
+
+        let deployment_unit_builder = deployment_unit_builder
+            .extend_with_image_infos(image_infos)
+            .with_app_metadata(options.app_metadata().clone())
+            .apply_templating()?
+            .apply_hooks(config)
+            .await?;
+
+        let deployment_unit = if let Ok(Some(base_traefik_ingress_route)) =
+            self.infrastructure.base_traefik_ingress_route().await
+        {
+            deployment_unit_builder
+                .apply_base_traefik_ingress_route(base_traefik_ingress_route)
+                .build()
+        } else {
+            deployment_unit_builder.build()
+        }
+        .with_namespace_annotations(options.namespace_annotations().clone())
+        .with_target(options.target().cloned())
+        .with_app_metadata(options.app_metadata().clone())
+        .with_helm_releases(config.helm_releases(app_name)?)
+        .with_custom_resources(config.custom_resources(app_name)?)
+        .restrict_to_services(options.only_services());
+
+        for service in deployment_unit.services() {
+            config
+                .app_name_policy()
+                .validate_service(app_name, service.service_name())
+                .map_err(|error| AppsServiceError::InvalidAppName { error })?;
+        }
+
+        Ok(deployment_unit)
+    }
+
+    /// Deploys the shared companions the app uses into the app of the shared companions unless
+    /// they are already running there.
+    async fn deploy_shared_companions(
+        &self,
+        config: &Arc<Config>,
+        app_name: &AppName,
+    ) -> Result<(), AppsServiceError> {
+        let shared_companions = config.shared_companion_configs(app_name);
+        if shared_companions.is_empty() {
+            return Ok(());
+        }
+
+        let shared_app_name = AppName::shared_companions();
+        let running_configs = self
+            .infrastructure
+            .get_configs_of_app(&shared_app_name)
+            .await?;
+        let missing_companions = shared_companions
+            .into_iter()
+            .map(|(companion, _, _)| companion)
+            .filter(|companion| {
+                !running_configs
+                    .iter()
+                    .any(|running| running.service_name() == companion.service_name())
+            })
+            .collect::<Vec<_>>();
+        if missing_companions.is_empty() {
+            return Ok(());
+        }
+
+        let options = DeploymentOptions::default();
+        let deployment_unit = self
+            .deployment_unit(
+                config,
+                &shared_app_name,
+                Some(shared_app_name.clone()),
+                &missing_companions,
+                &options,
+            )
+            .await?;
+        self.scan_and_deploy(
+            config,
+            &AppStatusChangeId::new(),
+            &deployment_unit,
+            &options,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stops the app of the shared companions once none of the remaining apps uses a shared
+    /// companion anymore.
+    async fn stop_unused_shared_companions(&self, config: &Config) -> Result<(), AppsServiceError> {
+        let shared_app_name = AppName::shared_companions();
+        let services = self.infrastructure.get_services().await?;
+        if !services.contains_key(&shared_app_name) {
+            return Ok(());
+        }
+
+        let in_use = services
+            .keys()
+            .filter(|app_name| *app_name != &shared_app_name)
+            .any(|app_name| !config.shared_companion_configs(app_name).is_empty());
+        if !in_use {
+            self.infrastructure
+                .stop_services(&AppStatusChangeId::new().to_string(), &shared_app_name)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn scan_and_deploy(
+        &self,
+        config: &Arc<Config>,
+        status_id: &AppStatusChangeId,
+        deployment_unit: &DeploymentUnit,
+        options: &DeploymentOptions,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let app_name = deployment_unit.app_name();
+        let images_to_scan = deployment_unit
+            .services()
+            .iter()
+            .map(|service| (service.image().clone(), service.image_digest().cloned()))
+            .collect::<Vec<_>>();
+        match config.trivy_config().and_then(|trivy| trivy.block_on()) {
+            Some(block_on) => {
+                for (image, digest) in &images_to_scan {
+                    let summary = self
+                        .vulnerability_scanner
+                        .scan(config, image, digest.as_ref())
+                        .await?;
+
+                    let count = summary.count_at_least(block_on);
+                    if count > 0 {
+                        return Err(AppsServiceError::VulnerableImage {
+                            image: image.to_string(),
+                            severity: block_on,
+                            count,
+                        });
+                    }
+                }
+            }
+            None if config.trivy_config().is_some() => {
+                let scanner = self.vulnerability_scanner.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    for (image, digest) in images_to_scan {
+                        if let Err(err) = scanner.scan(&config, &image, digest.as_ref()).await {
+                            warn!("Cannot scan {} for vulnerabilities: {}", image, err);
+                        }
+                    }
+                });
+            }
+            None => {}
+        }
+
+        let services = self
+            .infrastructure
+            .deploy_services(
+                &status_id.to_string(),
+                deployment_unit,
+                &config.container_config(),
+            )
+            .await?;
+
+        if let Some(smoke_test) = options.smoke_test() {
+            let outcome = smoke_test::run_smoke_test(
+                self.infrastructure.as_ref(),
+                app_name,
+                &services,
+                smoke_test,
+            )
+            .await?;
+
+            if !outcome.passed() {
+                return Err(AppsServiceError::SmokeTestFailed {
+                    logs: outcome.logs().to_string(),
+                });
+            }
+        }
+
+        Ok(services)
+    }
+
+    /// Deletes all services for the given `app_name`.
+    pub async fn delete_app(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        self.delete_app_with_force_token(app_name, status_id, None)
+            .await
+    }
+
+    /// Same as [`AppsService::delete_app`] but deletes protected apps if the `force_token`
+    /// matches the configured token of the deletion protection.
+    pub async fn delete_app_with_force_token(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        force_token: Option<&str>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deletion)?;
+
+        if !guard.is_first() {
+            guard.wait_for_result()
+        } else {
+            guard.notify_with_result(
+                self,
+                self.delete_app_impl(app_name, status_id, force_token).await,
+            )
+        }
+    }
+
+    /// Checks whether the app is protected from deletion, either by the configuration or by the
+    /// app metadata `protected=true`.
+    async fn check_deletion_protection(
+        &self,
+        config: &Config,
+        app_name: &AppName,
+        force_token: Option<&str>,
+    ) -> Result<(), AppsServiceError> {
+        let services = self.infrastructure.get_services().await?;
+        // Unknown apps are reported as not found by the deletion itself.
+        let Some(services) = services.get_vec(app_name) else {
+            return Ok(());
+        };
+        let protected_by_metadata = services.iter().any(|service| {
+            service
+                .app_metadata()
+                .get(PROTECTED_METADATA_KEY)
+                .map(String::as_str)
+                == Some("true")
+        });
+        if !protected_by_metadata && !config.deletion_protection().protects(app_name) {
+            return Ok(());
+        }
+
+        match force_token {
+            None => Err(AppsServiceError::AppIsProtected {
+                app_name: app_name.clone(),
+            }),
+            Some(token) if config.deletion_protection().permits_forced_deletion(token) => {
+                warn!("Forced deletion of the protected app {}", app_name);
+                Ok(())
+            }
+            Some(_) => Err(AppsServiceError::ForcedDeletionNotPermitted {
+                app_name: app_name.clone(),
+            }),
+        }
+    }
+
+    async fn delete_app_impl(
+        &self,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        force_token: Option<&str>,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let config = self.config.current();
+        self.check_deletion_protection(&config, app_name, force_token)
+            .await?;
+
+        // The deletion of an app that has already been deleted softly tears it down immediately.
+        let is_soft_deleted = self
+            .state_store
+            .soft_deleted_apps()
+            .await?
+            .iter()
+            .any(|(soft_deleted_app, _)| soft_deleted_app == app_name);
+        match config.soft_delete_config() {
+            Some(soft_delete) if !is_soft_deleted => {
+                let owner = self.owner_of(app_name).await;
+                let services = self
+                    .soft_delete_app(app_name, soft_delete.grace_period())
+                    .await?;
+
+                self.notify(
+                    &config,
+                    Notification::for_services(
+                        NotificationEvent::SoftDeleted,
+                        app_name.clone(),
+                        &services,
+                    )
+                    .with_owner(owner),
+                );
+                self.events.publish(AppEvent::AppSoftDeleted {
+                    app_name: app_name.clone(),
+                });
+                Ok(services)
+            }
+            _ => {
+                self.finalize_deletion(&config, app_name, status_id, NotificationEvent::Deleted)
+                    .await
+            }
+        }
+    }
+
+    /// Runs the pre-destroy hooks of the app and pauses all its services. The app is kept until
+    /// the grace period expires. The hooks run before the services are paused so that they can
+    /// still reach them.
+    async fn soft_delete_app(
+        &self,
+        app_name: &AppName,
+        grace_period: Duration,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        self.run_pre_destroy_hooks(app_name).await?;
+
+        let services = self
+            .change_status_of_app(app_name, ServiceStatus::Paused)
+            .await?;
+
+        let expires_at = Utc::now() + chrono::Duration::seconds(grace_period.as_secs() as i64);
+        info!(
+            "Deleted {} softly, it can be restored until {}",
+            app_name, expires_at
+        );
+        self.state_store.soft_delete(app_name, expires_at).await?;
+
+        Ok(services)
+    }
+
+    /// Runs the pre-destroy hooks of the latest deployment of the app.
+    async fn run_pre_destroy_hooks(&self, app_name: &AppName) -> Result<(), AppsServiceError> {
+        // The app is not deleted without its hooks if the store is unavailable.
+        let pre_destroy_hooks = self.state_store.pre_destroy_hooks(app_name).await?;
+        if pre_destroy_hooks.is_empty() {
+            return Ok(());
+        }
+
+        // Failing hooks must not prevent the deletion, e.g. if a service is already broken.
+        let hook_logs = lifecycle_hooks::run_lifecycle_hooks(
+            self.infrastructure.as_ref(),
+            app_name,
+            &pre_destroy_hooks,
+        )
+        .await;
+        for log in hook_logs.iter().filter(|log| !log.passed()) {
+            warn!("Pre-destroy hook of {} failed: {}", app_name, log.logs());
+        }
+        Ok(())
+    }
+
+    /// Returns the apps that have been deleted softly and can still be restored.
+    pub async fn soft_deleted_apps(&self) -> Result<Vec<AppName>, AppsServiceError> {
+        Ok(self
+            .state_store
+            .soft_deleted_apps()
+            .await?
+            .into_iter()
+            .map(|(app_name, _)| app_name)
+            .collect())
+    }
+
+    /// Restores an app that has been deleted softly by resuming its services.
+    pub async fn restore_app(&self, app_name: &AppName) -> Result<Vec<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deployment)?;
+        if !guard.is_first() {
+            return Err(AppsServiceError::AppIsInDeployment {
+                app_name: app_name.clone(),
+            });
+        }
+
+        let result = match self.state_store.remove_soft_deletion(app_name).await {
+            Ok(true) => {
+                self.change_status_of_app(app_name, ServiceStatus::Running)
+                    .await
+            }
+            Ok(false) => Err(AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            }),
+            Err(error) => Err(error.into()),
+        };
+
+        guard.notify_with_result(self, result)
+    }
+
+    /// Takes over the services of an app that have not been deployed by PREvant, e.g. the
+    /// deployments of a manually prepared namespace. Afterwards, PREvant manages the lifecycle of
+    /// these services like the lifecycle of any other service.
+    pub async fn adopt_app(&self, app_name: &AppName) -> Result<Vec<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        let config = self.config.current();
+        self.validate_app_name(&config, app_name).await?;
+
+        let guard = self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deployment)?;
+        if !guard.is_first() {
+            return Err(AppsServiceError::AppIsInDeployment {
+                app_name: app_name.clone(),
+            });
+        }
+
+        let result = match self.infrastructure.adopt_services(app_name).await {
+            Ok(services) if services.is_empty() => Err(AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            }),
+            Ok(services) => Ok(services),
+            Err(error) => Err(error.into()),
+        };
+
+        guard.notify_with_result(self, result)
+    }
+
+    /// Tears down the apps whose grace period after their soft deletion has expired.
+    pub async fn reap_soft_deleted_apps(&self) {
+        if !self.leadership.is_leader() {
+            return;
+        }
+
+        let soft_deleted_apps = match self.state_store.soft_deleted_apps().await {
+            Ok(soft_deleted_apps) => soft_deleted_apps,
+            Err(err) => {
+                warn!("Cannot determine the deleted apps to tear down: {}", err);
+                return;
+            }
+        };
+
+        let now = Utc::now();
+        let expired_apps = soft_deleted_apps
+            .into_iter()
+            .filter(|(_, expires_at)| *expires_at <= now)
+            .map(|(app_name, _)| app_name)
+            .collect::<Vec<_>>();
+
+        for app_name in expired_apps {
+            let guard = match self.create_or_get_app_guard(app_name.clone(), AppGuardKind::Deletion)
+            {
+                Ok(guard) if guard.is_first() => guard,
+                _ => continue,
+            };
+
+            let config = self.config.current();
+            let result = self
+                .finalize_deletion(
+                    &config,
+                    &app_name,
+                    &AppStatusChangeId::new(),
+                    NotificationEvent::Expired,
+                )
+                .await;
+            if let Err(err) = guard.notify_with_result(self, result) {
+                warn!("Cannot tear down the deleted app {}: {}", app_name, err);
+            }
+        }
+    }
+
+    /// Records the owner of the app unless the app is already owned by someone.
+    async fn record_owner(&self, app_name: &AppName, owner: &str) {
+        let result = match self.state_store.owners().await {
+            Ok(owners) if owners.contains_key(app_name) => Ok(()),
+            Ok(_) => self.state_store.set_owner(app_name, owner).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            warn!("Cannot record {} as owner of {}: {}", owner, app_name, err);
+        }
+    }
+
+    async fn owner_of(&self, app_name: &AppName) -> Option<String> {
+        match self.state_store.owners().await {
+            Ok(mut owners) => owners.remove(app_name),
+            Err(err) => {
+                warn!("Cannot determine the owner of {}: {}", app_name, err);
+                None
+            }
+        }
+    }
+
+    /// Transfers the app to the new owner or removes its owner if `owner` is `None`. If the
+    /// request has been made by an authenticated `user`, only the current owner may change the
+    /// ownership of an owned app.
+    pub async fn change_owner(
+        &self,
+        app_name: &AppName,
+        owner: Option<&str>,
+        user: Option<&str>,
+    ) -> Result<Option<String>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        if !self
+            .infrastructure
+            .get_services()
+            .await?
+            .contains_key(app_name)
+        {
+            return Err(AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            });
+        }
+
+        let current_owner = self.state_store.owners().await?.remove(app_name);
+        if let (Some(current_owner), Some(user)) = (&current_owner, user) {
+            if current_owner != user {
+                return Err(AppsServiceError::NotOwnerOfApp {
+                    app_name: app_name.clone(),
+                    user: user.to_string(),
+                });
+            }
+        }
+
+        match owner {
+            Some(owner) => self.state_store.set_owner(app_name, owner).await?,
+            None => self.state_store.remove_owner(app_name).await?,
+        }
+        Ok(owner.map(String::from))
+    }
+
+    /// Sends the notification in the background so that slow or unavailable channels don't
+    /// delay the change of the app.
+    fn notify(&self, config: &Config, notification: Notification) {
+        let channels = config.notification_channels().to_vec();
+        if channels.is_empty() {
+            return;
+        }
+
+        let network = config.network_config().clone();
+        tokio::spawn(async move {
+            send_notifications(&network, &channels, &notification).await;
+        });
+    }
+
+    /// Generates the SBOMs of the deployed images in the background and stores them with the state
+    /// of the app.
+    fn record_sboms(&self, config: &Arc<Config>, deployment_unit: &DeploymentUnit) {
+        if config.sbom_config().is_none() {
+            return;
+        }
+
+        let app_name = deployment_unit.app_name().clone();
+        let images = deployment_unit
+            .services()
+            .iter()
+            .map(|service| {
+                (
+                    service.service_name().clone(),
+                    service.image().clone(),
+                    service.image_digest().cloned(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let config = config.clone();
+        let generator = self.sbom_generator.clone();
+        let state_store = self.state_store.clone();
+        tokio::spawn(async move {
+            for (service_name, image, digest) in images {
+                let sbom = match generator.generate(&config, &image, digest.as_ref()).await {
+                    Ok(Some(sbom)) => sbom,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!("Cannot generate the SBOM of {}: {}", image, err);
+                        continue;
+                    }
+                };
+                if let Err(err) = state_store.set_sbom(&app_name, &service_name, &sbom).await {
+                    warn!(
+                        "Cannot store the SBOM of {} of {}: {}",
+                        service_name, app_name, err
+                    );
+                }
+            }
+        });
+    }
+
+    /// Returns the SBOM of the image that has been deployed for the service of the app.
+    pub async fn sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Sbom, AppsServiceError> {
+        self.state_store
+            .sbom(app_name, service_name)
+            .await?
+            .ok_or_else(|| AppsServiceError::SbomNotFound {
+                app_name: app_name.clone(),
+                service_name: service_name.to_string(),
+            })
+    }
+
+    /// Creates a token that grants access to the services of the app until it expires or has been
+    /// revoked and returns it together with the secret token that will be shown only once.
+    pub async fn create_share_token(
+        &self,
+        app_name: &AppName,
+        description: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(ShareToken, String), AppsServiceError> {
+        self.ensure_leader().await?;
+
+        let expires_at = expires_at.unwrap_or_else(|| {
+            Utc::now() + chrono::Duration::days(DEFAULT_SHARE_TOKEN_LIFETIME_DAYS)
+        });
+        if expires_at <= Utc::now() {
+            return Err(AppsServiceError::InvalidShareTokenExpiry);
+        }
+
+        if !self
+            .infrastructure
+            .get_services()
+            .await?
+            .contains_key(app_name)
+        {
+            return Err(AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            });
+        }
+
+        let (share_token, token) = ShareToken::new(description, expires_at);
+        self.state_store
+            .add_share_token(app_name, &share_token)
+            .await?;
+        Ok((share_token, token))
+    }
+
+    pub async fn share_tokens(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<ShareToken>, AppsServiceError> {
+        Ok(self.state_store.share_tokens(app_name).await?)
+    }
+
+    pub async fn revoke_share_token(
+        &self,
+        app_name: &AppName,
+        id: &Uuid,
+    ) -> Result<(), AppsServiceError> {
+        self.ensure_leader().await?;
+
+        if self.state_store.remove_share_token(app_name, id).await? {
+            Ok(())
+        } else {
+            Err(AppsServiceError::ShareTokenNotFound {
+                app_name: app_name.clone(),
+                id: *id,
+            })
+        }
+    }
+
+    /// Returns the share token of the app that grants access with the secret token or `None` if
+    /// the token is unknown, revoked, or expired.
+    pub async fn share_token_granting_access(
+        &self,
+        app_name: &AppName,
+        token: &str,
+    ) -> Option<ShareToken> {
+        match self.state_store.share_tokens(app_name).await {
+            Ok(share_tokens) => share_tokens
+                .into_iter()
+                .find(|share_token| share_token.grants_access(token)),
+            Err(err) => {
+                warn!("Cannot determine the share tokens of {}: {}", app_name, err);
+                None
+            }
+        }
+    }
+
+    /// Appends the entry to the audit log. The audited request has been processed already, thus, a
+    /// failing state store is only logged.
+    pub async fn append_audit_entry(&self, entry: &AuditEntry) {
+        if let Err(err) = self.state_store.append_audit_entry(entry).await {
+            error!("Cannot append {:?} to the audit log: {}", entry, err);
+        }
+    }
+
+    pub async fn audit_log(&self) -> Result<Vec<AuditEntry>, AppsServiceError> {
+        Ok(self.state_store.audit_log().await?)
+    }
+
+    /// Creates a backup of the state of all apps and of the audit log.
+    pub async fn backup(&self) -> Result<Backup, AppsServiceError> {
+        let deployed_apps = self.infrastructure.get_services().await?;
+        Ok(backup::create_backup(self.state_store.as_ref(), deployed_apps.keys().cloned()).await?)
+    }
+
+    /// Restores the state of the apps of the backup, e.g. after PREvant has been migrated to
+    /// another cluster. The state of apps that are not part of the backup remains untouched.
+    pub async fn restore(&self, backup: &Backup) -> Result<(), AppsServiceError> {
+        self.ensure_leader().await?;
+
+        if backup.version() > Backup::VERSION {
+            return Err(AppsServiceError::UnsupportedBackupVersion {
+                version: backup.version(),
+            });
+        }
+
+        let config = self.config.current();
+        for app_name in backup.apps().keys() {
+            self.validate_app_name(&config, app_name).await?;
+        }
+
+        Ok(backup::restore_backup(self.state_store.as_ref(), backup, MAX_HISTORY_ENTRIES).await?)
+    }
+
+    async fn change_status_of_app(
+        &self,
+        app_name: &AppName,
+        status: ServiceStatus,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let services = self.infrastructure.get_services().await?;
+        let Some(services) = services.get_vec(app_name) else {
+            return Err(AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            });
+        };
+
+        let mut changed_services = Vec::with_capacity(services.len());
+        for service in services {
+            let changed_service = self
+                .infrastructure
+                .change_status(app_name, service.service_name(), status.clone())
+                .await?;
+            changed_services.push(changed_service.unwrap_or_else(|| service.clone()));
+        }
+
+        Ok(changed_services)
+    }
+
+    /// Tears down the app and announces its deletion with the given notification event, e.g.
+    /// [`NotificationEvent::Expired`] if the grace period of a softly deleted app expired.
+    async fn finalize_deletion(
+        &self,
+        config: &Config,
+        app_name: &AppName,
+        status_id: &AppStatusChangeId,
+        notification_event: NotificationEvent,
+    ) -> Result<Vec<Service>, AppsServiceError> {
+        let owner = self.owner_of(app_name).await;
+
+        // The hooks of a softly deleted app have run before its services were paused.
+        let is_soft_deleted = self
+            .state_store
+            .soft_deleted_apps()
+            .await?
+            .iter()
+            .any(|(soft_deleted_app, _)| soft_deleted_app == app_name);
+        if !is_soft_deleted {
+            self.run_pre_destroy_hooks(app_name).await?;
+        }
+
+        if let Some(log_archive_config) = config.log_archive_config() {
+            if let Err(err) = self
+                .archive_logs(config.network_config(), log_archive_config, app_name)
+                .await
+            {
+                warn!("Cannot archive the logs of {}: {}", app_name, err);
+            }
+        }
+
+        let services = self
+            .infrastructure
+            .stop_services(&status_id.to_string(), app_name)
+            .await?;
+        // A softly deleted app whose teardown failed keeps its record so that it can be restored
+        // or expires again.
+        self.state_store.remove_soft_deletion(app_name).await?;
+        if services.is_empty() {
+            Err(AppsServiceError::AppNotFound {
+                app_name: app_name.clone(),
+            })
+        } else {
+            if let Err(err) = self.state_store.remove_deployment_history(app_name).await {
+                warn!(
+                    "Cannot remove the deployment history of {}: {}",
+                    app_name, err
+                );
+            }
+            if let Err(err) = self.state_store.remove_owner(app_name).await {
+                warn!("Cannot remove the owner of {}: {}", app_name, err);
+            }
+            if let Err(err) = self.state_store.remove_sboms(app_name).await {
+                warn!("Cannot remove the SBOMs of {}: {}", app_name, err);
+            }
+            if let Err(err) = self.state_store.remove_share_tokens(app_name).await {
+                warn!("Cannot remove the share tokens of {}: {}", app_name, err);
+            }
+            if let Err(err) = self.state_store.remove_pre_destroy_hooks(app_name).await {
+                warn!(
+                    "Cannot remove the pre-destroy hooks of {}: {}",
+                    app_name, err
+                );
+            }
+            if let Some(assets_config) = config.static_assets_config() {
+                let service_names = services
+                    .iter()
+                    .map(|service| service.service_name().clone())
+                    .collect::<Vec<_>>();
+                if let Err(err) = static_assets::remove_bundles(
+                    config.network_config(),
+                    assets_config.storage(),
+                    app_name,
+                    &service_names,
+                )
+                .await
+                {
+                    warn!("Cannot remove the static assets of {}: {}", app_name, err);
+                }
+            }
+            if let Err(err) = self.stop_unused_shared_companions(config).await {
+                warn!("Cannot stop the unused shared companions: {}", err);
+            }
+            if let Some(shared_database) = config.shared_database_config(app_name) {
+                if let Err(err) = shared_database::drop_database(shared_database, app_name).await {
+                    warn!("Cannot drop the shared database of {}: {}", app_name, err);
+                }
+            }
+
+            self.notify(
+                config,
+                Notification::for_services(notification_event, app_name.clone(), &services)
+                    .with_owner(owner),
+            );
+            self.events.publish(AppEvent::AppDeleted {
+                app_name: app_name.clone(),
+            });
+            Ok(services)
+        }
+    }
+
+    /// Pushes the complete logs of all services of the app to the configured archive before the
+    /// services are torn down.
+    async fn archive_logs(
+        &self,
+        network: &NetworkConfig,
+        config: &LogArchiveConfig,
+        app_name: &AppName,
+    ) -> Result<(), failure::Error> {
+        let services = self.infrastructure.get_services().await?;
+        let Some(services) = services.get_vec(app_name) else {
+            return Ok(());
+        };
+
+        let futures = services.iter().map(|service| async move {
+            let logs = self
+                .infrastructure
+                .get_logs_with_streams(
+                    app_name,
+                    service.service_name(),
+                    &None,
+                    usize::MAX,
+                    &LogFilter::default(),
+                )
+                .await?;
+            Ok::<_, failure::Error>((service.service_name().clone(), logs.unwrap_or_default()))
+        });
+        let logs = futures::future::try_join_all(futures).await?;
+
+        crate::log_archive::archive_logs(network, config, app_name, &logs).await?;
+        Ok(())
+    }
+
+    pub async fn get_logs(
+        &self,
+        app_name: &AppName,
+        service_name: &String,
+        since: &Option<DateTime<FixedOffset>>,
+        limit: usize,
+        filter: &LogFilter,
+    ) -> Result<Option<LogChunk>, AppsServiceError> {
+        match self
+            .infrastructure
+            .get_logs(app_name, service_name, since, limit, filter)
+            .await?
+        {
+            None => Ok(None),
+            Some(ref logs) if logs.is_empty() => Ok(None),
+            Some(logs) => Ok(Some(LogChunk::from(logs))),
+        }
+    }
+
+    /// Collects the logs of the services of the app in parallel and interleaves them by their
+    /// timestamps. If `service_names` is empty, the logs of all services will be collected.
+    pub async fn get_app_logs(
+        &self,
+        app_name: &AppName,
+        service_names: &[String],
+        since: &Option<DateTime<FixedOffset>>,
+        limit: usize,
+        filter: &LogFilter,
+    ) -> Result<Option<LogChunk>, AppsServiceError> {
+        let services = self.infrastructure.get_services().await?;
+        let Some(services) = services.get_vec(app_name) else {
+            return Ok(None);
+        };
+
+        let futures = services
+            .iter()
+            .map(|service| service.service_name())
+            .filter(|service_name| service_names.is_empty() || service_names.contains(service_name))
+            .map(|service_name| async move {
+                let logs = self
+                    .infrastructure
+                    .get_logs_with_streams(app_name, service_name, since, limit, filter)
+                    .await?;
+                Ok::<_, failure::Error>((service_name.clone(), logs.unwrap_or_default()))
+            });
+
+        let logs = futures::future::try_join_all(futures).await?;
+        if logs.iter().all(|(_, logs)| logs.is_empty()) {
+            return Ok(None);
+        }
+
+        Ok(Some(LogChunk::from_services(logs, limit)))
+    }
+
+    pub async fn change_status(
+        &self,
+        app_name: &AppName,
+        service_name: &String,
+        status: ServiceStatus,
+    ) -> Result<Option<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        Ok(self
+            .infrastructure
+            .change_status(app_name, service_name, status)
+            .await?)
+    }
+
+    pub async fn restart_service(
+        &self,
+        app_name: &AppName,
+        service_name: &String,
+    ) -> Result<Option<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        Ok(self
+            .infrastructure
+            .restart_service(app_name, service_name)
+            .await?)
+    }
+
+    /// Replaces the contents of files that are mounted into the running service and notifies the
+    /// service about the change as requested by the `update`, e.g. by sending a signal to the main
+    /// process of every instance. The service is not redeployed but the update is recorded in the
+    /// deployment history of the app.
+    pub async fn update_files(
+        &self,
+        app_name: &AppName,
+        service_name: &String,
+        update: FilesUpdate,
+    ) -> Result<Option<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        let Some(service) = self
+            .infrastructure
+            .update_files(app_name, service_name, update.files())
+            .await?
+        else {
+            return Ok(None);
+        };
+        self.record_deployment(
+            app_name,
+            DeploymentHistoryEntry::new(
+                DeploymentDiff::with_updated_files(service_name, update.files().keys()),
+                Vec::new(),
+            ),
+        )
+        .await;
+
+        match update.reload() {
+            None => Ok(Some(service)),
+            Some(FileReload::Restart) => Ok(self
+                .infrastructure
+                .restart_service(app_name, service_name)
+                .await?),
+            Some(FileReload::Signal(signal)) => {
+                let outcome = self
+                    .infrastructure
+                    .signal_service(app_name, service_name, signal, update.files())
+                    .await?;
+                if !outcome.passed() {
+                    return Err(AppsServiceError::ReloadFailed {
+                        service_name: service_name.clone(),
+                        logs: outcome.logs().to_string(),
+                    });
+                }
+                Ok(Some(service))
+            }
+        }
+    }
+
+    /// Merges the `patch` into the configuration of the running service and redeploys only this
+    /// service. The other instances and replicas of the app stay untouched.
+    pub async fn update_service(
+        &self,
+        app_name: &AppName,
+        service_name: &String,
+        patch: ServicePatch,
+    ) -> Result<Option<Service>, AppsServiceError> {
+        self.ensure_leader().await?;
+
+        let Some(mut config) = self
+            .infrastructure
+            .get_configs_of_app(app_name)
+            .await?
+            .into_iter()
+            .find(|config| config.service_name() == service_name)
+        else {
+            return Ok(None);
+        };
+
+        // The service keeps its container type, e.g. a replica stays a replica.
+        patch.apply_to(&mut config);
+
+        // Replicating from the app itself ensures that no other service will be replicated and
+        // the restriction keeps the companions of the app untouched.
+        let services = self
+            .create_or_update_with_options(
+                app_name,
+                &AppStatusChangeId::new(),
+                Some(app_name.clone()),
+                &[config],
+                &DeploymentOptions::default().with_only_services(vec![service_name.clone()]),
+            )
+            .await?;
+
+        Ok(services
+            .into_iter()
+            .find(|service| service.service_name() == service_name))
+    }
+}
+
+/// Defines error cases for the `AppService`
+#[derive(Debug, Clone, Fail)]
+pub enum AppsServiceError {
+    /// Will be used when no app with a given name is found
     #[fail(display = "Cannot find app {}.", app_name)]
     AppNotFound { app_name: AppName },
     #[fail(
@@ -385,6 +2313,30 @@ pub enum AppsServiceError {
         app_name
     )]
     AppIsInDeployment { app_name: AppName },
+    /// Will be used if a queued deployment has been cancelled before it started.
+    #[fail(
+        display = "The deployment {} of {} has been cancelled.",
+        status_id, app_name
+    )]
+    DeploymentCancelled {
+        app_name: AppName,
+        status_id: AppStatusChangeId,
+    },
+    /// Will be used if another replica of PREvant is the leader that changes the apps.
+    #[fail(
+        display = "This replica is not the leader, changes must be sent to {}.",
+        leader
+    )]
+    NotLeader { leader: String },
+    /// Will be used if a deployment to cancel does not wait in the queue of the app.
+    #[fail(
+        display = "The deployment {} of {} is not queued.",
+        status_id, app_name
+    )]
+    DeploymentNotQueued {
+        app_name: AppName,
+        status_id: AppStatusChangeId,
+    },
     #[fail(
         display = "The app {} is currently within deletion in by another request.",
         app_name
@@ -394,274 +2346,1471 @@ pub enum AppsServiceError {
     #[fail(display = "Cannot interact with infrastructure: {}", error)]
     InfrastructureError { error: Arc<failure::Error> },
     /// Will be used if the service configuration cannot be loaded.
+    #[fail(display = "Building images from sources has not been configured.")]
+    BuildsNotConfigured,
+    #[fail(display = "The build of {} failed: {}", service_name, logs)]
+    BuildFailed { service_name: String, logs: String },
     #[fail(display = "Invalid configuration: {}", error)]
     InvalidServerConfiguration { error: Arc<ConfigError> },
     #[fail(display = "Invalid configuration (invalid template): {}", error)]
     InvalidTemplateFormat { error: Arc<RenderError> },
+    #[fail(display = "Invalid configuration of {}: {}", service_name, error)]
+    InvalidRouterRule {
+        service_name: String,
+        error: TraefikRouterRuleError,
+    },
     #[fail(display = "Unable to resolve information about image: {}", error)]
     UnableToResolveImage { error: RegistryError },
+    #[fail(display = "{}", error)]
+    StateStoreFailure { error: StateStoreError },
     #[fail(display = "Invalid deployment hook.")]
     InvalidDeploymentHook,
+    /// Will be used if a deletion request is not forced but the app is protected from deletion.
+    #[fail(
+        display = "The app {} is protected from deletion. Use a forced deletion instead.",
+        app_name
+    )]
+    AppIsProtected { app_name: AppName },
+    /// Will be used if a forced deletion does not provide the configured token.
+    #[fail(
+        display = "The forced deletion of the protected app {} is not permitted.",
+        app_name
+    )]
+    ForcedDeletionNotPermitted { app_name: AppName },
+    /// Will be used if a user tries to change the owner of an app that is owned by someone else.
+    #[fail(display = "The app {} is not owned by {}.", app_name, user)]
+    NotOwnerOfApp { app_name: AppName, user: String },
+    /// Will be used if the name of a new app violates the configured policy.
+    #[fail(display = "{}", error)]
+    InvalidAppName { error: AppNameError },
+    /// Will be used if the deployment hook refuses to deploy the services.
+    #[fail(display = "The deployment has been rejected: {}", reason)]
+    DeploymentRejectedByHook { reason: String },
+    /// Will be used if a deployment request refers to a stack that is not configured.
+    #[fail(display = "Unknown stack {}.", stack)]
+    UnknownStack { stack: String },
+    /// Will be used if a deployment request refers to a service double that is neither built in
+    /// nor configured.
+    #[fail(display = "Unknown service double {}.", name)]
+    UnknownServiceDouble { name: String },
+    /// Will be used if a deployment request refers to a cluster that is not configured.
+    #[fail(display = "Unknown target cluster {}.", target)]
+    UnknownTarget { target: String },
+    /// Will be used if the smoke test of a deployment did not pass. The services of the app
+    /// stay deployed so that the failure can be investigated.
+    #[fail(display = "The smoke test failed: {}", logs)]
+    SmokeTestFailed { logs: String },
+    /// Will be used if a post-deploy hook failed. The services of the app stay deployed.
+    #[fail(display = "The lifecycle hook failed: {}", logs)]
+    LifecycleHookFailed { logs: String },
+    /// Will be used if a service declares a seed but the engine of its database is unknown.
+    #[fail(
+        display = "Cannot seed the database of {}: the database engine is unknown.",
+        service_name
+    )]
+    InvalidSeed { service_name: String },
+    /// Will be used if the seed job of a service failed. The services of the app stay deployed.
+    #[fail(display = "Seeding the database of {} failed: {}", service_name, logs)]
+    SeedFailed { service_name: String, logs: String },
+    /// Will be used if the database of the app cannot be created on the shared database server.
+    #[fail(display = "Cannot provision the shared database: {}", error)]
+    SharedDatabaseFailure { error: String },
+    /// Will be used if a service extends an unknown or invalid template.
+    #[fail(display = "{}", error)]
+    InvalidServiceTemplate { error: ServiceTemplateError },
+    /// Will be used if a key of the app metadata cannot be stored as label or annotation.
+    #[fail(
+        display = "Invalid metadata key {}: keys must consist of at most 63 alphanumeric characters, '-', '_' or '.'.",
+        key
+    )]
+    InvalidAppMetadataKey { key: String },
+    /// Will be used if an image has vulnerabilities that are configured to block deployments.
+    #[fail(
+        display = "The image {} has {} vulnerabilities with severity {:?} or higher.",
+        image, count, severity
+    )]
+    VulnerableImage {
+        image: String,
+        severity: Severity,
+        count: usize,
+    },
+    #[fail(display = "Cannot scan image for vulnerabilities: {}", error)]
+    VulnerabilityScanFailed { error: VulnerabilityScanError },
+    /// Will be used if the app does not run a mail catcher.
+    #[fail(display = "The app {} has no mail catcher.", app_name)]
+    MailCatcherNotFound { app_name: AppName },
+    #[fail(display = "Cannot fetch the mails of the mail catcher: {}", error)]
+    MailCatcherRequestFailed { error: String },
+    /// Will be used if a service name cannot be used as hostname, object name or path segment.
+    #[fail(
+        display = "Invalid service name {}: names must be RFC 1123 labels, i.e. at most 63 lowercase alphanumeric characters or '-'.",
+        service_name
+    )]
+    InvalidServiceName { service_name: String },
+    #[fail(
+        display = "Invalid alias {} of service {}: aliases must be RFC 1123 labels, i.e. at most 63 lowercase alphanumeric characters or '-'.",
+        alias, service_name
+    )]
+    InvalidAlias { service_name: String, alias: String },
+    #[fail(display = "The hosting of static assets has not been configured.")]
+    StaticAssetsNotConfigured,
+    /// Will be used if an uploaded bundle is neither a tarball nor a zip archive.
+    #[fail(display = "The static assets must be uploaded as tar, tar.gz, or zip archive.")]
+    InvalidStaticAssets,
+    #[fail(
+        display = "No static assets have been uploaded for service {} of app {}.",
+        service_name, app_name
+    )]
+    StaticAssetsNotFound {
+        app_name: AppName,
+        service_name: String,
+    },
+    #[fail(display = "{}", error)]
+    StaticAssetsStorageFailed { error: StaticAssetsError },
+    #[fail(
+        display = "There is no SBOM of service {} of app {}.",
+        service_name, app_name
+    )]
+    SbomNotFound {
+        app_name: AppName,
+        service_name: String,
+    },
+    #[fail(display = "A share token must expire in the future.")]
+    InvalidShareTokenExpiry,
+    #[fail(display = "There is no share token {} of app {}.", id, app_name)]
+    ShareTokenNotFound { app_name: AppName, id: Uuid },
+    #[fail(
+        display = "The backup has been created by a newer version of PREvant (format version {}).",
+        version
+    )]
+    UnsupportedBackupVersion { version: u32 },
+    /// Will be used if the pods of the app cannot be scheduled on the nodes of the cluster.
+    #[fail(
+        display = "The cluster lacks the capacity to run {}: {}",
+        app_name, reason
+    )]
+    InsufficientCapacity {
+        app_name: AppName,
+        reason: String,
+        shortages: Vec<CapacityShortage>,
+    },
+    /// Will be used if a service could not be notified about updated files.
+    #[fail(
+        display = "The service {} could not be reloaded: {}",
+        service_name, logs
+    )]
+    ReloadFailed { service_name: String, logs: String },
+    /// Will be used if a service mounts a directory of the host without permission.
+    #[fail(
+        display = "The dev mounts of service {} are not permitted: {}",
+        service_name, reason
+    )]
+    DevMountNotPermitted {
+        service_name: String,
+        reason: String,
+    },
+    /// Will be used if the service account of a service requests a rule outside of the allowlist.
+    #[fail(
+        display = "The service account of service {} must not be granted {}",
+        service_name, rule
+    )]
+    ServiceAccountRuleNotPermitted { service_name: String, rule: String },
+}
+
+impl From<ConfigError> for AppsServiceError {
+    fn from(error: ConfigError) -> Self {
+        AppsServiceError::InvalidServerConfiguration {
+            error: Arc::new(error),
+        }
+    }
+}
+
+impl From<failure::Error> for AppsServiceError {
+    fn from(error: failure::Error) -> Self {
+        AppsServiceError::InfrastructureError {
+            error: Arc::new(error),
+        }
+    }
+}
+
+impl From<RenderError> for AppsServiceError {
+    fn from(error: RenderError) -> Self {
+        AppsServiceError::InvalidTemplateFormat {
+            error: Arc::new(error),
+        }
+    }
+}
+
+impl From<StateStoreError> for AppsServiceError {
+    fn from(error: StateStoreError) -> Self {
+        AppsServiceError::StateStoreFailure { error }
+    }
+}
+
+impl From<RegistryError> for AppsServiceError {
+    fn from(error: RegistryError) -> Self {
+        AppsServiceError::UnableToResolveImage { error }
+    }
+}
+
+impl From<StaticAssetsError> for AppsServiceError {
+    fn from(error: StaticAssetsError) -> Self {
+        AppsServiceError::StaticAssetsStorageFailed { error }
+    }
+}
+
+impl From<VulnerabilityScanError> for AppsServiceError {
+    fn from(error: VulnerabilityScanError) -> Self {
+        AppsServiceError::VulnerabilityScanFailed { error }
+    }
 }
 
-impl From<ConfigError> for AppsServiceError {
-    fn from(error: ConfigError) -> Self {
-        AppsServiceError::InvalidServerConfiguration {
-            error: Arc::new(error),
-        }
-    }
-}
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config::InfrastructureOperation;
+    use crate::infrastructure::{Dummy, TraefikIngressRoute, TraefikRouterRule};
+    use crate::models::{AppBackup, DeploymentOutcome, EnvironmentVariable, ServiceBuilder};
+    use crate::sc;
+    use chrono::Utc;
+    use secstr::SecUtf8;
+    use std::hash::Hash;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use tempfile::NamedTempFile;
+    use tokio::runtime;
+
+    macro_rules! config_from_str {
+        ( $config_str:expr ) => {
+            toml::from_str::<Config>($config_str).unwrap()
+        };
+    }
+
+    macro_rules! assert_contains_service {
+        ( $services:expr, $service_name:expr, $container_type:expr ) => {
+            assert!(
+                $services
+                    .iter()
+                    .find(|s| s.service_name() == $service_name
+                        && s.container_type() == &$container_type)
+                    .is_some(),
+                "services should contain {:?} with type {:?}",
+                $service_name,
+                $container_type
+            );
+        };
+    }
+
+    fn config_with_deployment_hook(script: &str) -> (NamedTempFile, Config) {
+        let mut hook_file = NamedTempFile::new().unwrap();
+
+        hook_file.write_all(script.as_bytes()).unwrap();
+
+        let config = crate::config_from_str!(&format!(
+            r#"
+            [hooks]
+            deployment = {:?}
+            "#,
+            hook_file.path()
+        ));
+
+        (hook_file, config)
+    }
+
+    #[tokio::test]
+    async fn should_create_app_for_master() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        assert_eq!(deployed_apps.len(), 1);
+        let services = deployed_apps.get_vec(&AppName::master()).unwrap();
+        assert_eq!(services.len(), 1);
+        assert_contains_service!(services, "service-a", ContainerType::Instance);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_publish_events_of_app_changes() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+        let mut events = apps.subscribe_events();
+
+        let app_name = AppName::master();
+        for _ in 0..2 {
+            apps.create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            )
+            .await?;
+        }
+        apps.delete_app(&app_name, &AppStatusChangeId::new())
+            .await?;
+
+        match events.try_recv() {
+            Ok(AppEvent::AppCreated { app_name, services }) => {
+                assert_eq!(app_name, AppName::master());
+                assert_eq!(services.len(), 1);
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+        assert!(matches!(events.try_recv(), Ok(AppEvent::AppUpdated { .. })));
+        assert_eq!(
+            events.try_recv().ok(),
+            Some(AppEvent::AppDeleted {
+                app_name: AppName::master()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_publish_distinct_events_of_soft_and_final_deletions(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [softDelete]
+            gracePeriod = 3600
+            "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+
+        let app_name = AppName::master();
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await?;
+        let mut events = apps.subscribe_events();
+
+        apps.delete_app(&app_name, &AppStatusChangeId::new())
+            .await?;
+        assert_eq!(
+            events.try_recv().ok(),
+            Some(AppEvent::AppSoftDeleted {
+                app_name: app_name.clone()
+            })
+        );
+        assert!(events.try_recv().is_err());
+
+        apps.delete_app(&app_name, &AppStatusChangeId::new())
+            .await?;
+        assert_eq!(
+            events.try_recv().ok(),
+            Some(AppEvent::AppDeleted { app_name })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_store_static_assets_of_invalid_service_names(
+    ) -> Result<(), AppsServiceError> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_from_str!(&format!(
+            r#"
+            [staticAssets]
+            prevantUrl = 'http://prevant.tools'
+
+            [staticAssets.storage]
+            type = 'Directory'
+            path = '{}'
+            "#,
+            dir.path().display()
+        ));
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+        let app_name = AppName::from_str("feature-1").unwrap();
+
+        for service_name in ["../../x", "/tmp/x", "Web"] {
+            let result = apps
+                .deploy_static_assets(
+                    &app_name,
+                    &AppStatusChangeId::new(),
+                    service_name,
+                    b"PK\x03\x04".to_vec(),
+                )
+                .await;
+
+            assert!(
+                matches!(result, Err(AppsServiceError::InvalidServiceName { .. })),
+                "{service_name} should be rejected"
+            );
+            assert!(matches!(
+                apps.static_assets_bundle(&app_name, service_name).await,
+                Err(AppsServiceError::StaticAssetsNotFound { .. })
+            ));
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_create_app_with_passing_smoke_test() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        let smoke_test = serde_json::from_value::<SmokeTest>(serde_json::json!({
+            "image": "curlimages/curl"
+        }))
+        .unwrap();
+
+        let services = apps
+            .create_or_update_with_options(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+                &DeploymentOptions::default().with_smoke_test(Some(smoke_test)),
+            )
+            .await?;
+
+        assert_contains_service!(services, "service-a", ContainerType::Instance);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_fail_deployment_with_failing_smoke_test() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(
+            Dummy::new().with_failing_operations(vec![InfrastructureOperation::RunSmokeTest]),
+        );
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        let smoke_test = serde_json::from_value::<SmokeTest>(serde_json::json!({
+            "image": "curlimages/curl"
+        }))
+        .unwrap();
+
+        let result = apps
+            .create_or_update_with_options(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+                &DeploymentOptions::default().with_smoke_test(Some(smoke_test)),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::SmokeTestFailed { logs }) if logs == "Injected failure of RunSmokeTest"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_record_logs_of_post_deploy_hooks() -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+
+        let hooks = serde_json::from_value::<LifecycleHooks>(serde_json::json!({
+            "postDeploy": [{ "serviceName": "service-a", "command": ["./flush-caches.sh"] }]
+        }))
+        .unwrap();
+
+        apps.create_or_update_with_options(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+            &DeploymentOptions::default().with_lifecycle_hooks(Some(hooks)),
+        )
+        .await?;
+
+        let history = apps.deployment_history(&AppName::master()).await?;
+        assert_eq!(history[0].hooks().len(), 1);
+        assert!(history[0].hooks()[0].passed());
+        assert_eq!(
+            history[0].hooks()[0].logs(),
+            "Executed ./flush-caches.sh in service-a"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_seed_database_of_new_services_only() -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+
+        let mut db = sc!("db", "postgres:15");
+        db.set_seed(Some(
+            serde_json::from_value(serde_json::json!({
+                "scripts": ["INSERT INTO users (name) VALUES ('alice');"]
+            }))
+            .unwrap(),
+        ));
+
+        for _ in 0..2 {
+            apps.create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![db.clone()],
+            )
+            .await?;
+        }
+
+        let history = apps.deployment_history(&AppName::master()).await?;
+        let seeds = history
+            .iter()
+            .flat_map(|entry| entry.seeds())
+            .collect::<Vec<_>>();
+        assert_eq!(seeds.len(), 1);
+        assert_eq!(seeds[0].service_name(), "db");
+        assert!(seeds[0].passed());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_fail_deployment_with_failing_post_deploy_hook() -> Result<(), AppsServiceError>
+    {
+        let infrastructure = Box::new(
+            Dummy::new().with_failing_operations(vec![InfrastructureOperation::ExecInService]),
+        );
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        let hooks = serde_json::from_value::<LifecycleHooks>(serde_json::json!({
+            "postDeploy": [{ "serviceName": "service-a", "command": ["./flush-caches.sh"] }]
+        }))
+        .unwrap();
+
+        let result = apps
+            .create_or_update_with_options(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+                &DeploymentOptions::default().with_lifecycle_hooks(Some(hooks)),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::LifecycleHookFailed { logs }) if logs == "Injected failure of ExecInService"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_block_deployment_of_vulnerable_image() -> Result<(), AppsServiceError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut trivy = NamedTempFile::new().unwrap();
+        trivy
+            .write_all(
+                br#"#!/bin/sh
+echo '{"Results":[{"Vulnerabilities":[{"Severity":"CRITICAL"},{"Severity":"LOW"}]}]}'
+"#,
+            )
+            .unwrap();
+        let trivy = trivy.into_temp_path();
+        std::fs::set_permissions(&trivy, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = crate::config_from_str!(&format!(
+            r#"
+            [trivy]
+            command = {:?}
+            blockOn = 'HIGH'
+            "#,
+            trivy.to_path_buf()
+        ));
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+
+        let result = apps
+            .create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::VulnerableImage { count, .. }) if count == 1
+        ));
+        assert!(apps.get_apps().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_return_app_metadata_with_services() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        apps.create_or_update_with_options(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+            &DeploymentOptions::default().with_app_metadata(BTreeMap::from([(
+                String::from("commit"),
+                String::from("1a2b3c4"),
+            )])),
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec(&AppName::master()).unwrap();
+        assert_eq!(
+            services[0].app_metadata(),
+            &BTreeMap::from([(String::from("commit"), String::from("1a2b3c4"))])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_keep_the_first_owner_until_the_app_is_transferred(
+    ) -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        for owner in ["alice", "bob"] {
+            apps.create_or_update_with_options(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+                &DeploymentOptions::default().with_owner(Some(String::from(owner))),
+            )
+            .await?;
+        }
+
+        let deployed_apps = apps.get_apps().await?;
+        let services = deployed_apps.get_vec(&AppName::master()).unwrap();
+        assert_eq!(services[0].owner(), Some(&String::from("alice")));
+
+        let result = apps
+            .change_owner(&AppName::master(), Some("bob"), Some("bob"))
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::NotOwnerOfApp { .. })
+        ));
+
+        let owner = apps
+            .change_owner(&AppName::master(), Some("bob"), Some("alice"))
+            .await?;
+        assert_eq!(owner, Some(String::from("bob")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_grant_access_with_share_token_until_revocation() -> Result<(), AppsServiceError>
+    {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        let result = apps
+            .create_share_token(&AppName::master(), None, None)
+            .await;
+        assert!(matches!(result, Err(AppsServiceError::AppNotFound { .. })));
+
+        apps.create_or_update(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await?;
+
+        let (share_token, token) = apps
+            .create_share_token(&AppName::master(), Some(String::from("customer")), None)
+            .await?;
+        assert_eq!(
+            apps.share_token_granting_access(&AppName::master(), &token)
+                .await,
+            Some(share_token.clone())
+        );
+
+        apps.revoke_share_token(&AppName::master(), share_token.id())
+            .await?;
+        assert_eq!(
+            apps.share_token_granting_access(&AppName::master(), &token)
+                .await,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_fail_deployment_with_failing_build() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [builds]
+            registry = 'registry.example.com/previews'
+        "#
+        );
+        let apps = AppsService::new(
+            config,
+            Box::new(
+                Dummy::new().with_failing_operations(vec![InfrastructureOperation::BuildImage]),
+            ),
+        )?;
+
+        let build = serde_json::from_value::<SourceBuild>(serde_json::json!({
+            "serviceName": "frontend",
+            "url": "https://github.com/aixigo/PREvant.git"
+        }))
+        .unwrap();
+        let result = apps
+            .create_or_update_with_options(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![],
+                &DeploymentOptions::default().with_builds(vec![build]),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::BuildFailed { service_name, .. }) if service_name == "frontend"
+        ));
+        assert!(apps.get_apps().await?.get_vec(&AppName::master()).is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_app_metadata_key() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        let result = apps
+            .create_or_update_with_options(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+                &DeploymentOptions::default().with_app_metadata(BTreeMap::from([(
+                    String::from("ticket id"),
+                    String::from("PREV-42"),
+                )])),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InvalidAppMetadataKey { key }) if key == "ticket id"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_new_apps_violating_the_app_name_policy() -> Result<(), AppsServiceError>
+    {
+        let config = config_from_str!(
+            r#"
+            [appNames]
+            pattern = '[a-z0-9-]+'
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let result = apps
+            .create_or_update(
+                &AppName::from_str("Feature_43").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InvalidAppName {
+                error: AppNameError::InvalidPattern { .. }
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_restored_apps_violating_the_app_name_policy(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [appNames]
+            reserved = ['production']
+            "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+
+        let backup = Backup::new(
+            BTreeMap::from([(
+                AppName::from_str("production").unwrap(),
+                AppBackup::default(),
+            )]),
+            Vec::new(),
+        );
+        let result = apps.restore(&backup).await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InvalidAppName {
+                error: AppNameError::Reserved { .. }
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_deployments_exceeding_the_capacity() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [containers]
+            memory_limit = '1g'
+
+            [capacityCheck]
+            mode = 'reject'
+            "#
+        );
+        let capacity = crate::models::ClusterCapacity::new().with_node(
+            String::from("worker-1"),
+            BTreeMap::from([(String::from("memory"), 1.5 * 1024_f64.powi(3))]),
+        );
+        let infrastructure = Box::new(Dummy::new().with_capacity(capacity));
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let result = apps
+            .create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a"), sc!("service-b")],
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InsufficientCapacity { shortages, .. })
+                if shortages.len() == 1 && shortages[0].service_name().is_none()
+        ));
+        assert!(apps.get_apps().await?.is_empty());
+
+        apps.create_or_update(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await?;
+        assert!(apps.get_apps().await?.contains_key(&AppName::master()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_replication_from_master() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a"), sc!("service-b")],
+        )
+        .await?;
+
+        apps.create_or_update(
+            &AppName::from_str("branch").unwrap(),
+            &AppStatusChangeId::new(),
+            Some(AppName::from_str("master").unwrap()),
+            &vec![sc!("service-b")],
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+
+        let services = deployed_apps
+            .get_vec(&AppName::from_str("branch").unwrap())
+            .unwrap();
+        assert_eq!(services.len(), 2);
+        assert_contains_service!(services, "service-b", ContainerType::Instance);
+        assert_contains_service!(services, "service-a", ContainerType::Replica);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_override_replicas_from_master() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a"), sc!("service-b")],
+        )
+        .await?;
+
+        apps.create_or_update(
+            &AppName::from_str("branch").unwrap(),
+            &AppStatusChangeId::new(),
+            Some(AppName::master()),
+            &vec![sc!("service-b")],
+        )
+        .await?;
+
+        apps.create_or_update(
+            &AppName::from_str("branch").unwrap(),
+            &AppStatusChangeId::new(),
+            Some(AppName::from_str("master").unwrap()),
+            &vec![sc!("service-a")],
+        )
+        .await?;
+
+        let deployed_apps = apps.get_apps().await?;
+
+        let services = deployed_apps
+            .get_vec(&AppName::from_str("branch").unwrap())
+            .unwrap();
+        assert_eq!(services.len(), 2);
+        assert_contains_service!(services, "service-a", ContainerType::Instance);
+        assert_contains_service!(services, "service-b", ContainerType::Instance);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_create_app_for_master_with_secrets() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [services.mariadb]
+            [[services.mariadb.secrets]]
+            name = "user"
+            data = "SGVsbG8="
+            appSelector = "master"
+        "#
+        );
+
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        apps.create_or_update(
+            &AppName::from_str("master").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("mariadb")],
+        )
+        .await?;
+
+        let configs = apps
+            .infrastructure
+            .get_configs_of_app(&AppName::master())
+            .await?;
+        assert_eq!(configs.len(), 1);
+
+        let files = configs.get(0).unwrap().files().unwrap();
+        assert_eq!(
+            files.get(&PathBuf::from("/run/secrets/user")).unwrap(),
+            &SecUtf8::from("Hello")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_create_app_for_master_without_secrets_because_of_none_matching_app_selector(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [services.mariadb]
+            [[services.mariadb.secrets]]
+            name = "user"
+            data = "SGVsbG8="
+            appSelector = "master"
+        "#
+        );
+
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
 
-impl From<failure::Error> for AppsServiceError {
-    fn from(error: failure::Error) -> Self {
-        AppsServiceError::InfrastructureError {
-            error: Arc::new(error),
-        }
-    }
-}
+        apps.create_or_update(
+            &AppName::from_str("master-1.x").unwrap(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("mariadb")],
+        )
+        .await?;
 
-impl From<RenderError> for AppsServiceError {
-    fn from(error: RenderError) -> Self {
-        AppsServiceError::InvalidTemplateFormat {
-            error: Arc::new(error),
-        }
-    }
-}
+        let configs = apps
+            .infrastructure
+            .get_configs_of_app(&AppName::from_str("master-1.x").unwrap())
+            .await?;
+        assert_eq!(configs.len(), 1);
 
-impl From<RegistryError> for AppsServiceError {
-    fn from(error: RegistryError) -> Self {
-        AppsServiceError::UnableToResolveImage { error }
+        let files = configs.get(0).unwrap().files();
+        assert_eq!(files, None);
+
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[tokio::test]
+    async fn should_collect_log_chunk_from_infrastructure() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
 
-    use super::*;
-    use crate::infrastructure::{Dummy, TraefikIngressRoute, TraefikRouterRule};
-    use crate::models::{EnvironmentVariable, ServiceBuilder};
-    use crate::sc;
-    use chrono::Utc;
-    use secstr::SecUtf8;
-    use std::hash::Hash;
-    use std::io::Write;
-    use std::path::PathBuf;
-    use std::str::FromStr;
-    use tempfile::NamedTempFile;
-    use tokio::runtime;
+        let app_name = AppName::from_str("master").unwrap();
 
-    macro_rules! config_from_str {
-        ( $config_str:expr ) => {
-            toml::from_str::<Config>($config_str).unwrap()
-        };
-    }
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a"), sc!("service-b")],
+        )
+        .await?;
 
-    macro_rules! assert_contains_service {
-        ( $services:expr, $service_name:expr, $container_type:expr ) => {
-            assert!(
-                $services
-                    .iter()
-                    .find(|s| s.service_name() == $service_name
-                        && s.container_type() == &$container_type)
-                    .is_some(),
-                "services should contain {:?} with type {:?}",
-                $service_name,
-                $container_type
-            );
-        };
-    }
+        let log_chunk = apps
+            .get_logs(
+                &app_name,
+                &String::from("service-a"),
+                &None,
+                100,
+                &LogFilter::default(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
 
-    fn config_with_deployment_hook(script: &str) -> (NamedTempFile, Config) {
-        let mut hook_file = NamedTempFile::new().unwrap();
+        assert_eq!(
+            log_chunk.since(),
+            &DateTime::parse_from_rfc3339("2019-07-18T07:25:00.000000000Z").unwrap()
+        );
 
-        hook_file.write_all(script.as_bytes()).unwrap();
+        assert_eq!(
+            log_chunk.until(),
+            &DateTime::parse_from_rfc3339("2019-07-18T07:35:00.000000000Z").unwrap()
+        );
 
-        let config = crate::config_from_str!(&format!(
-            r#"
-            [hooks]
-            deployment = {:?}
-            "#,
-            hook_file.path()
-        ));
+        assert_eq!(
+            log_chunk.log_lines(),
+            r#"Log msg 1 of service-a of app master
+Log msg 2 of service-a of app master
+Log msg 3 of service-a of app master
+"#
+        );
 
-        (hook_file, config)
+        Ok(())
     }
 
     #[tokio::test]
-    async fn should_create_app_for_master() -> Result<(), AppsServiceError> {
+    async fn should_compute_diff_without_deploying_and_record_deployments(
+    ) -> Result<(), AppsServiceError> {
         let config = Config::default();
         let infrastructure = Box::new(Dummy::new());
         let apps = AppsService::new(config, infrastructure)?;
 
+        let app_name = AppName::from_str("master").unwrap();
+
         apps.create_or_update(
-            &AppName::master(),
+            &app_name,
             &AppStatusChangeId::new(),
             None,
             &vec![sc!("service-a")],
         )
         .await?;
 
-        let deployed_apps = apps.get_apps().await?;
-        assert_eq!(deployed_apps.len(), 1);
-        let services = deployed_apps.get_vec(&AppName::master()).unwrap();
-        assert_eq!(services.len(), 1);
-        assert_contains_service!(services, "service-a", ContainerType::Instance);
+        let diff = apps
+            .dry_run(
+                &app_name,
+                None,
+                &vec![
+                    sc!(
+                        "service-a",
+                        "sha256:9a89aaf7b4b8e5a9ffb4a2e6a4e2e2d0b38cbc56b8e1c0e74a3fd3aa2e6a1c88"
+                    ),
+                    sc!("service-b"),
+                ],
+                &DeploymentOptions::default(),
+            )
+            .await?;
+
+        assert_eq!(diff.added_services(), &vec![String::from("service-b")]);
+        assert_eq!(diff.changed_services().len(), 1);
+
+        let history = apps.deployment_history(&app_name).await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            history[0].diff().added_services(),
+            &vec![String::from("service-a")]
+        );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn should_replication_from_master() -> Result<(), AppsServiceError> {
+    async fn should_replicate_services_without_unset_env() -> Result<(), AppsServiceError> {
         let config = Config::default();
         let infrastructure = Box::new(Dummy::new());
         let apps = AppsService::new(config, infrastructure)?;
 
+        let service_a = serde_json::from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "service-a",
+            "image": "sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913",
+            "env": {
+                "VAR_1": { "value": "a", "replicate": true },
+                "VAR_2": { "value": "b", "replicate": true }
+            },
+            "unsetEnv": [ "VAR_2" ]
+        }))
+        .unwrap();
         apps.create_or_update(
-            &AppName::from_str("master").unwrap(),
+            &AppName::master(),
             &AppStatusChangeId::new(),
             None,
-            &vec![sc!("service-a"), sc!("service-b")],
+            &vec![service_a],
         )
         .await?;
 
+        let app_name = AppName::from_str("branch").unwrap();
+        let services = apps
+            .create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-b")],
+            )
+            .await?;
+
+        let replica = services
+            .iter()
+            .find(|service| service.service_name() == "service-a")
+            .unwrap();
+        assert_eq!(replica.container_type(), &ContainerType::Replica);
+        assert_eq!(
+            replica
+                .config()
+                .env()
+                .unwrap()
+                .iter()
+                .map(|env| env.key())
+                .collect::<Vec<_>>(),
+            vec!["VAR_1"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_replicate_services_with_replication_rules() -> Result<(), AppsServiceError> {
+        let config = Config::default();
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let template = AppName::from_str("template").unwrap();
         apps.create_or_update(
-            &AppName::from_str("branch").unwrap(),
+            &template,
             &AppStatusChangeId::new(),
-            Some(AppName::from_str("master").unwrap()),
-            &vec![sc!("service-b")],
+            None,
+            &vec![sc!("service-a"), sc!("service-b"), sc!("service-c")],
         )
         .await?;
 
-        let deployed_apps = apps.get_apps().await?;
+        let rules = serde_json::from_value::<ReplicationRules>(serde_json::json!({
+            "exclude": [ "service-b" ],
+            "overrides": {
+                "service-c": { "image": "service-c:template" }
+            }
+        }))
+        .unwrap();
+        let app_name = AppName::from_str("branch").unwrap();
+        let services = apps
+            .create_or_update_with_options(
+                &app_name,
+                &AppStatusChangeId::new(),
+                Some(template),
+                &vec![sc!("service-d")],
+                &DeploymentOptions::default().with_replication_rules(rules),
+            )
+            .await?;
 
-        let services = deployed_apps
-            .get_vec(&AppName::from_str("branch").unwrap())
+        let mut service_names = services
+            .iter()
+            .map(|service| service.service_name().as_str())
+            .collect::<Vec<_>>();
+        service_names.sort_unstable();
+        assert_eq!(service_names, vec!["service-a", "service-c", "service-d"]);
+
+        let replica = services
+            .iter()
+            .find(|service| service.service_name() == "service-c")
             .unwrap();
-        assert_eq!(services.len(), 2);
-        assert_contains_service!(services, "service-b", ContainerType::Instance);
-        assert_contains_service!(services, "service-a", ContainerType::Replica);
+        assert_eq!(replica.container_type(), &ContainerType::Replica);
+        assert_eq!(
+            replica.config().image().to_string(),
+            "docker.io/library/service-c:template"
+        );
+        assert_eq!(
+            replica.config().replicated_from(),
+            Some(&String::from("template"))
+        );
+
+        let instance = services
+            .iter()
+            .find(|service| service.service_name() == "service-d")
+            .unwrap();
+        assert_eq!(instance.config().replicated_from(), None);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn should_override_replicas_from_master() -> Result<(), AppsServiceError> {
+    async fn should_share_companions_between_apps() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.keycloak]
+            serviceName = 'keycloak'
+            type = 'shared'
+            image = 'quay.io/keycloak/keycloak:22.0'
+
+            [companions.keycloak.appEnv]
+            KEYCLOAK_URL = 'http://keycloak.shared-companions:8080/realms/{{application.name}}'
+        "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let master = AppName::master();
+        let branch = AppName::from_str("branch").unwrap();
+        for app_name in [&master, &branch].iter() {
+            apps.create_or_update(app_name, &AppStatusChangeId::new(), None, &vec![sc!("api")])
+                .await?;
+        }
+
+        let shared_configs = apps
+            .infrastructure
+            .get_configs_of_app(&AppName::shared_companions())
+            .await?;
+        assert_eq!(shared_configs.len(), 1);
+        assert_eq!(shared_configs[0].service_name(), "keycloak");
+
+        let branch_configs = apps.infrastructure.get_configs_of_app(&branch).await?;
+        assert_eq!(branch_configs.len(), 1);
+        assert_eq!(
+            branch_configs[0]
+                .env()
+                .and_then(|env| env.variable("KEYCLOAK_URL"))
+                .map(|variable| variable.value().unsecure().to_string()),
+            Some(String::from(
+                "http://keycloak.shared-companions:8080/realms/branch"
+            ))
+        );
+
+        apps.delete_app(&master, &AppStatusChangeId::new()).await?;
+        assert!(apps
+            .get_apps()
+            .await?
+            .contains_key(&AppName::shared_companions()));
+
+        apps.delete_app(&branch, &AppStatusChangeId::new()).await?;
+        assert!(apps.get_apps().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_update_single_service() -> Result<(), AppsServiceError> {
         let config = Config::default();
         let infrastructure = Box::new(Dummy::new());
         let apps = AppsService::new(config, infrastructure)?;
 
+        let app_name = AppName::from_str("master").unwrap();
+
         apps.create_or_update(
-            &AppName::master(),
+            &app_name,
             &AppStatusChangeId::new(),
             None,
-            &vec![sc!("service-a"), sc!("service-b")],
+            &vec![
+                sc!("service-a", labels = (), env = ("LOG_LEVEL" => "info"), files = ()),
+                sc!("service-b"),
+            ],
         )
         .await?;
 
+        let patch = serde_json::from_value::<ServicePatch>(serde_json::json!({
+            "env": { "LOG_LEVEL": "debug" }
+        }))
+        .unwrap();
+        let service = apps
+            .update_service(&app_name, &String::from("service-a"), patch)
+            .await?
+            .unwrap();
+
+        assert_eq!(
+            service
+                .config()
+                .env()
+                .and_then(|env| env.variable("LOG_LEVEL"))
+                .map(|variable| variable.value()),
+            Some(&SecUtf8::from("debug"))
+        );
+
+        let services = apps.get_apps().await?;
+        assert_eq!(services.get_vec(&app_name).unwrap().len(), 2);
+
+        let unknown_service = apps
+            .update_service(
+                &app_name,
+                &String::from("service-c"),
+                ServicePatch::default(),
+            )
+            .await?;
+        assert!(unknown_service.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_update_replicated_service_without_turning_it_into_an_instance(
+    ) -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+
         apps.create_or_update(
-            &AppName::from_str("branch").unwrap(),
+            &AppName::master(),
             &AppStatusChangeId::new(),
-            Some(AppName::master()),
-            &vec![sc!("service-b")],
+            None,
+            &vec![sc!("service-a"), sc!("service-b")],
         )
         .await?;
-
+        let app_name = AppName::from_str("branch").unwrap();
         apps.create_or_update(
-            &AppName::from_str("branch").unwrap(),
+            &app_name,
             &AppStatusChangeId::new(),
-            Some(AppName::from_str("master").unwrap()),
-            &vec![sc!("service-a")],
+            None,
+            &vec![sc!("service-b")],
         )
         .await?;
 
-        let deployed_apps = apps.get_apps().await?;
-
-        let services = deployed_apps
-            .get_vec(&AppName::from_str("branch").unwrap())
+        let patch = serde_json::from_value::<ServicePatch>(serde_json::json!({
+            "env": { "LOG_LEVEL": "debug" }
+        }))
+        .unwrap();
+        let service = apps
+            .update_service(&app_name, &String::from("service-a"), patch)
+            .await?
             .unwrap();
+        assert_eq!(service.container_type(), &ContainerType::Replica);
+
+        let services = apps.get_apps().await?;
+        let services = services.get_vec(&app_name).unwrap();
         assert_eq!(services.len(), 2);
-        assert_contains_service!(services, "service-a", ContainerType::Instance);
+        assert_contains_service!(services, "service-a", ContainerType::Replica);
         assert_contains_service!(services, "service-b", ContainerType::Instance);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn should_create_app_for_master_with_secrets() -> Result<(), AppsServiceError> {
-        let config = config_from_str!(
-            r#"
-            [services.mariadb]
-            [[services.mariadb.secrets]]
-            name = "user"
-            data = "SGVsbG8="
-            appSelector = "master"
-        "#
+    async fn should_fail_to_update_files_if_signal_cannot_be_sent() -> Result<(), AppsServiceError>
+    {
+        let infrastructure = Box::new(
+            Dummy::new().with_failing_operations(vec![InfrastructureOperation::ExecInService]),
         );
+        let apps = AppsService::new(Config::default(), infrastructure)?;
 
-        let infrastructure = Box::new(Dummy::new());
-        let apps = AppsService::new(config, infrastructure)?;
-
+        let app_name = AppName::master();
         apps.create_or_update(
-            &AppName::from_str("master").unwrap(),
+            &app_name,
             &AppStatusChangeId::new(),
             None,
-            &vec![sc!("mariadb")],
+            &vec![sc!("service-a")],
         )
         .await?;
 
-        let configs = apps
-            .infrastructure
-            .get_configs_of_app(&AppName::master())
-            .await?;
-        assert_eq!(configs.len(), 1);
+        let update = || {
+            serde_json::from_value::<FilesUpdate>(serde_json::json!({
+                "files": { "/etc/service-a.conf": "a=2" },
+                "reload": { "signal": "HUP" }
+            }))
+            .unwrap()
+        };
 
-        let files = configs.get(0).unwrap().files().unwrap();
-        assert_eq!(
-            files.get(&PathBuf::from("/run/secrets/user")).unwrap(),
-            &SecUtf8::from("Hello")
-        );
+        let result = apps
+            .update_files(&app_name, &String::from("service-a"), update())
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::ReloadFailed { service_name, .. }) if service_name == "service-a"
+        ));
+
+        let unknown_service = apps
+            .update_files(&app_name, &String::from("service-b"), update())
+            .await?;
+        assert!(unknown_service.is_none());
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn should_create_app_for_master_without_secrets_because_of_none_matching_app_selector(
-    ) -> Result<(), AppsServiceError> {
-        let config = config_from_str!(
-            r#"
-            [services.mariadb]
-            [[services.mariadb.secrets]]
-            name = "user"
-            data = "SGVsbG8="
-            appSelector = "master"
-        "#
-        );
-
-        let infrastructure = Box::new(Dummy::new());
-        let apps = AppsService::new(config, infrastructure)?;
+    async fn should_record_updated_files_in_deployment_history() -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
 
+        let app_name = AppName::master();
         apps.create_or_update(
-            &AppName::from_str("master-1.x").unwrap(),
+            &app_name,
             &AppStatusChangeId::new(),
             None,
-            &vec![sc!("mariadb")],
+            &vec![sc!("service-a")],
         )
         .await?;
 
-        let configs = apps
-            .infrastructure
-            .get_configs_of_app(&AppName::from_str("master-1.x").unwrap())
+        let update = serde_json::from_value::<FilesUpdate>(serde_json::json!({
+            "files": { "/etc/service-a.conf": "a=2" }
+        }))
+        .unwrap();
+        apps.update_files(&app_name, &String::from("service-a"), update)
             .await?;
-        assert_eq!(configs.len(), 1);
 
-        let files = configs.get(0).unwrap().files();
-        assert_eq!(files, None);
+        let history = apps.deployment_history(&app_name).await?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            serde_json::to_value(history[1].diff().changed_services()).unwrap(),
+            serde_json::json!([{
+                "serviceName": "service-a",
+                "updatedFiles": ["/etc/service-a.conf"]
+            }])
+        );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn should_collect_log_chunk_from_infrastructure() -> Result<(), AppsServiceError> {
+    async fn should_collect_interleaved_log_chunk_of_app() -> Result<(), AppsServiceError> {
         let config = Config::default();
         let infrastructure = Box::new(Dummy::new());
         let apps = AppsService::new(config, infrastructure)?;
@@ -672,33 +3821,91 @@ mod tests {
             &app_name,
             &AppStatusChangeId::new(),
             None,
-            &vec![sc!("service-a"), sc!("service-b")],
+            &vec![sc!("service-a"), sc!("service-b"), sc!("service-c")],
         )
         .await?;
 
         let log_chunk = apps
-            .get_logs(&app_name, &String::from("service-a"), &None, 100)
+            .get_app_logs(
+                &app_name,
+                &[String::from("service-a"), String::from("service-b")],
+                &None,
+                4,
+                &LogFilter::default(),
+            )
             .await
             .unwrap()
             .unwrap();
 
         assert_eq!(
-            log_chunk.since(),
-            &DateTime::parse_from_rfc3339("2019-07-18T07:25:00.000000000Z").unwrap()
+            log_chunk.log_lines(),
+            r#"[service-a] Log msg 1 of service-a of app master
+[service-b] Log msg 1 of service-b of app master
+[service-a] Log msg 2 of service-a of app master
+[service-b] Log msg 2 of service-b of app master
+"#
         );
 
-        assert_eq!(
-            log_chunk.until(),
-            &DateTime::parse_from_rfc3339("2019-07-18T07:35:00.000000000Z").unwrap()
-        );
+        Ok(())
+    }
 
-        assert_eq!(
-            log_chunk.log_lines(),
-            r#"Log msg 1 of service-a of app master
-Log msg 2 of service-a of app master
-Log msg 3 of service-a of app master
-"#
+    #[tokio::test]
+    async fn should_reject_aliases_that_are_no_dns_labels() -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+
+        let mut service_config = sc!("db");
+        service_config.set_aliases(vec![String::from("MySQL.local")]);
+        let result = apps
+            .create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![service_config],
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InvalidAlias { alias, .. }) if alias == "MySQL.local"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_service_account_rules_outside_of_allowlist(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [serviceAccounts]
+            allowedVerbs = [ 'get', 'list' ]
+            allowedResources = [ 'pods', 'pods/log' ]
+        "#
         );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+
+        let mut service_config = sc!("jenkins-agent");
+        service_config.set_service_account(Some(
+            serde_json::from_value(serde_json::json!({
+                "rules": [{ "resources": ["pods"], "verbs": ["get", "create"] }]
+            }))
+            .unwrap(),
+        ));
+        let result = apps
+            .create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![service_config],
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::ServiceAccountRuleNotPermitted { service_name, .. })
+                if service_name == "jenkins-agent"
+        ));
+        assert!(apps.get_apps().await?.is_empty());
 
         Ok(())
     }
@@ -740,6 +3947,61 @@ Log msg 3 of service-a of app master
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_deploy_services_based_on_templates() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [templates.spring-service]
+            image = 'example/spring-service:latest'
+            env = [ 'SPRING_PROFILES_ACTIVE=preview' ]
+        "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+
+        let service_config = serde_json::from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "orders",
+            "extends": "spring-service"
+        }))
+        .unwrap();
+        let services = apps
+            .create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![service_config],
+            )
+            .await?;
+
+        assert_eq!(
+            services[0].config().image().to_string(),
+            "docker.io/example/spring-service:latest"
+        );
+        assert!(services[0]
+            .config()
+            .env()
+            .and_then(|env| env.variable("SPRING_PROFILES_ACTIVE"))
+            .is_some());
+
+        let result = apps
+            .create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![serde_json::from_value::<ServiceConfig>(serde_json::json!({
+                    "serviceName": "billing",
+                    "extends": "go-service"
+                }))
+                .unwrap()],
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InvalidServiceTemplate { .. })
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_filter_companions_if_services_to_deploy_contain_same_service_name(
     ) -> Result<(), AppsServiceError> {
@@ -905,6 +4167,274 @@ Log msg 3 of service-a of app master
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_not_delete_protected_apps_without_force_token() -> Result<(), AppsServiceError>
+    {
+        let config = config_from_str!(
+            r#"
+            [deletionProtection]
+            apps = [ 'master' ]
+            forceToken = 'secret'
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let app_name = AppName::master();
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await?;
+
+        let result = apps.delete_app(&app_name, &AppStatusChangeId::new()).await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::AppIsProtected { .. })
+        ));
+
+        let result = apps
+            .delete_app_with_force_token(&app_name, &AppStatusChangeId::new(), Some("guess"))
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::ForcedDeletionNotPermitted { .. })
+        ));
+
+        let deleted_services = apps
+            .delete_app_with_force_token(&app_name, &AppStatusChangeId::new(), Some("secret"))
+            .await?;
+        assert_eq!(deleted_services.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_delete_apps_protected_by_metadata() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+
+        let app_name = AppName::from_str("release").unwrap();
+        apps.create_or_update_with_options(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+            &DeploymentOptions::default().with_app_metadata(BTreeMap::from([(
+                String::from("protected"),
+                String::from("true"),
+            )])),
+        )
+        .await?;
+
+        let result = apps.delete_app(&app_name, &AppStatusChangeId::new()).await;
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::AppIsProtected { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_queue_overlapping_deployments() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::with_delay(Duration::from_millis(100)));
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+        let app_name = AppName::master();
+
+        let (first, second) = tokio::join!(
+            apps.create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            ),
+            apps.create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-b")],
+            )
+        );
+
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        assert_eq!(apps.deployment_history(&app_name).await?.len(), 2);
+        assert!(apps.queued_deployments(&app_name).is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_cancel_queued_deployments() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::with_delay(Duration::from_millis(200)));
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+        let app_name = AppName::master();
+        let queued_status_id = AppStatusChangeId::new();
+
+        let (first, second, cancellation) = tokio::join!(
+            apps.create_or_update(
+                &app_name,
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            ),
+            apps.create_or_update(&app_name, &queued_status_id, None, &vec![sc!("service-b")]),
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                assert_eq!(apps.queued_deployments(&app_name).len(), 2);
+                apps.cancel_queued_deployment(&app_name, &queued_status_id)
+            }
+        );
+
+        assert!(first.is_ok());
+        assert!(cancellation.is_ok());
+        assert!(matches!(
+            second,
+            Err(AppsServiceError::DeploymentCancelled { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_deployments_of_followers() -> Result<(), AppsServiceError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?
+            .with_leadership(Leadership::follower());
+
+        let result = apps
+            .create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            )
+            .await;
+
+        assert!(matches!(result, Err(AppsServiceError::NotLeader { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_cancel_running_deployments() -> Result<(), AppsServiceError> {
+        let infrastructure = Box::new(Dummy::with_delay(Duration::from_millis(200)));
+        let apps = AppsService::new(Config::default(), infrastructure)?;
+        let app_name = AppName::master();
+        let status_id = AppStatusChangeId::new();
+
+        let (deployment, cancellation) = tokio::join!(
+            apps.create_or_update(&app_name, &status_id, None, &vec![sc!("service-a")]),
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                apps.cancel_deployment(&app_name, &status_id)
+            }
+        );
+
+        assert!(cancellation.is_ok());
+        assert!(matches!(
+            deployment,
+            Err(AppsServiceError::DeploymentCancelled { .. })
+        ));
+        assert!(apps.get_apps().await?.get_vec(&app_name).is_none());
+
+        let history = apps.deployment_history(&app_name).await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].outcome(), DeploymentOutcome::Canceled);
+
+        let result = apps.cancel_deployment(&app_name, &status_id);
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::DeploymentNotQueued { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_delete_apps_softly_and_restore_them() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [softDelete]
+            gracePeriod = 3600
+            "#
+        );
+        let infrastructure = Box::new(Dummy::new());
+        let apps = AppsService::new(config, infrastructure)?;
+
+        let app_name = AppName::master();
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await?;
+
+        let paused_services = apps
+            .delete_app(&app_name, &AppStatusChangeId::new())
+            .await?;
+        assert_eq!(paused_services.len(), 1);
+        assert_eq!(paused_services[0].status(), &ServiceStatus::Paused);
+        assert!(apps.get_apps().await?.get_vec(&app_name).is_some());
+
+        let restored_services = apps.restore_app(&app_name).await?;
+        assert_eq!(restored_services[0].status(), &ServiceStatus::Running);
+
+        apps.delete_app(&app_name, &AppStatusChangeId::new())
+            .await?;
+        let deleted_services = apps
+            .delete_app(&app_name, &AppStatusChangeId::new())
+            .await?;
+        assert_eq!(deleted_services.len(), 1);
+        assert!(apps.get_apps().await?.get_vec(&app_name).is_none());
+
+        let result = apps.restore_app(&app_name).await;
+        assert!(matches!(result, Err(AppsServiceError::AppNotFound { .. })));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_keep_soft_deletion_if_teardown_fails() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [softDelete]
+            gracePeriod = 3600
+            "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+
+        let app_name = AppName::master();
+        apps.create_or_update(
+            &app_name,
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await?;
+        apps.delete_app(&app_name, &AppStatusChangeId::new())
+            .await?;
+
+        let dummy = apps
+            .infrastructure
+            .as_any()
+            .downcast_ref::<Dummy>()
+            .unwrap();
+        dummy.fail_on(InfrastructureOperation::StopServices);
+        let result = apps.delete_app(&app_name, &AppStatusChangeId::new()).await;
+        assert!(result.is_err());
+        assert_eq!(apps.soft_deleted_apps().await?, vec![app_name.clone()]);
+
+        dummy.recover(InfrastructureOperation::StopServices);
+        let restored_services = apps.restore_app(&app_name).await?;
+        assert_eq!(restored_services[0].status(), &ServiceStatus::Running);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_delete_apps() -> Result<(), AppsServiceError> {
         let config = Config::default();
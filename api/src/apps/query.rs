@@ -0,0 +1,400 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::http_result::ETaggedJson;
+use crate::models::service::{Service, ServiceStatus};
+use crate::models::AppName;
+use chrono::{DateTime, Utc};
+use multimap::MultiMap;
+use rocket::form::{self, FromFormField, ValueField};
+use rocket::request::Request;
+use rocket::response::{Responder, Response};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::cmp::Ordering;
+
+/// Query parameters of `GET /api/apps` that filter, sort and paginate the listed apps, e.g.
+/// `?filter=status:running,team:payments&sort=-created_at&limit=50&offset=0`.
+#[derive(Default, FromForm)]
+pub struct AppsQuery {
+    filter: Option<AppFilters>,
+    sort: Option<AppSort>,
+    limit: Option<usize>,
+    #[field(default = 0)]
+    offset: usize,
+    /// Only the apps of this owner, `me` refers to the user of the request.
+    owner: Option<String>,
+}
+
+/// Comma separated filters in the format `key:value`. All filters must match.
+#[derive(Debug, Default, PartialEq)]
+pub struct AppFilters(Vec<AppFilter>);
+
+#[derive(Debug, PartialEq)]
+enum AppFilter {
+    /// The app name contains the value
+    Name(String),
+    /// At least one service of the app has the status
+    Status(ServiceStatus),
+    /// The app contains a service with the name
+    Service(String),
+    /// At least one service of the app runs on the cluster
+    Cluster(String),
+    /// The app metadata contains the key with the value
+    Metadata(String, String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AppSort {
+    key: AppSortKey,
+    descending: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum AppSortKey {
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// The apps that match an [`AppsQuery`](AppsQuery) in the requested order. It will be serialized
+/// as the map of apps and their services while the total number of matching apps is provided
+/// by the `X-Total-Count` header.
+pub struct AppsPage {
+    total: usize,
+    apps: Vec<(AppName, Vec<Service>)>,
+}
+
+impl AppsQuery {
+    /// Resolves `owner=me` to the authenticated user of the request.
+    pub fn with_user(mut self, user: Option<&str>) -> Result<Self, String> {
+        if self.owner.as_deref() == Some("me") {
+            match user {
+                Some(user) => self.owner = Some(user.to_string()),
+                None => {
+                    return Err(String::from(
+                        "owner=me requires a user that has been authenticated by a proxy.",
+                    ))
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn apply(&self, apps: MultiMap<AppName, Service>) -> AppsPage {
+        let mut apps = apps
+            .into_iter()
+            .filter(|(_, services)| match &self.owner {
+                Some(owner) => services
+                    .iter()
+                    .any(|service| service.owner() == Some(owner)),
+                None => true,
+            })
+            .filter(|(app_name, services)| {
+                self.filter
+                    .iter()
+                    .flat_map(|filters| filters.0.iter())
+                    .all(|filter| filter.matches(app_name, services))
+            })
+            .collect::<Vec<_>>();
+
+        let sort = self.sort.as_ref().unwrap_or(&AppSort {
+            key: AppSortKey::Name,
+            descending: false,
+        });
+        apps.sort_by(|(name1, services1), (name2, services2)| {
+            let ordering = match sort.key {
+                AppSortKey::Name => Ordering::Equal,
+                AppSortKey::CreatedAt => created_at(services1).cmp(&created_at(services2)),
+                AppSortKey::UpdatedAt => updated_at(services1).cmp(&updated_at(services2)),
+            }
+            .then_with(|| name1.as_str().cmp(name2.as_str()));
+
+            if sort.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let total = apps.len();
+        let apps = apps
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        AppsPage { total, apps }
+    }
+}
+
+fn created_at(services: &[Service]) -> Option<&DateTime<Utc>> {
+    services.iter().map(|service| service.started_at()).min()
+}
+
+fn updated_at(services: &[Service]) -> Option<&DateTime<Utc>> {
+    services.iter().map(|service| service.started_at()).max()
+}
+
+impl AppFilter {
+    fn matches(&self, app_name: &AppName, services: &[Service]) -> bool {
+        match self {
+            AppFilter::Name(name) => app_name.contains(name.as_str()),
+            AppFilter::Status(status) => services.iter().any(|s| s.status() == status),
+            AppFilter::Service(name) => services.iter().any(|s| s.service_name() == name),
+            AppFilter::Cluster(cluster) => services.iter().any(|s| s.cluster() == Some(cluster)),
+            AppFilter::Metadata(key, value) => services
+                .iter()
+                .any(|s| s.app_metadata().get(key) == Some(value)),
+        }
+    }
+}
+
+impl std::str::FromStr for AppFilters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let filters = s
+            .split(',')
+            .filter(|filter| !filter.trim().is_empty())
+            .map(|filter| {
+                let (key, value) = filter
+                    .split_once(':')
+                    .ok_or_else(|| format!("“{}” is not in the format key:value", filter))?;
+                let value = value.trim().to_string();
+                Ok(match key.trim() {
+                    "name" => AppFilter::Name(value),
+                    "status" => AppFilter::Status(match value.as_str() {
+                        "running" => ServiceStatus::Running,
+                        "paused" => ServiceStatus::Paused,
+                        _ => return Err(format!("Unknown status “{}”", value)),
+                    }),
+                    "service" => AppFilter::Service(value),
+                    "cluster" => AppFilter::Cluster(value),
+                    key => AppFilter::Metadata(key.to_string(), value),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(AppFilters(filters))
+    }
+}
+
+impl std::str::FromStr for AppSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (descending, key) = match s.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, s),
+        };
+
+        let key = match key {
+            "name" => AppSortKey::Name,
+            "created_at" => AppSortKey::CreatedAt,
+            "updated_at" => AppSortKey::UpdatedAt,
+            key => {
+                return Err(format!(
+                    "Cannot sort by “{}”, expected name, created_at or updated_at",
+                    key
+                ))
+            }
+        };
+
+        Ok(AppSort { key, descending })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromFormField<'r> for AppFilters {
+    fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
+        field
+            .value
+            .parse()
+            .map_err(|err: String| form::Error::validation(err).into())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromFormField<'r> for AppSort {
+    fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
+        field
+            .value
+            .parse()
+            .map_err(|err: String| form::Error::validation(err).into())
+    }
+}
+
+impl Serialize for AppsPage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.apps.len()))?;
+        for (app_name, services) in &self.apps {
+            map.serialize_entry(app_name, services)?;
+        }
+        map.end()
+    }
+}
+
+impl<'r> Responder<'r, 'static> for AppsPage {
+    fn respond_to(self, request: &'r Request) -> rocket::response::Result<'static> {
+        let total = self.total;
+        Response::build_from(ETaggedJson(self).respond_to(request)?)
+            .raw_header("X-Total-Count", total.to_string())
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ServiceBuilder;
+    use crate::sc;
+    use rocket::form::Form;
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    fn service(app_name: &str, service_name: &str, started_at: &str) -> Service {
+        ServiceBuilder::new()
+            .id(format!("{}-{}", app_name, service_name))
+            .app_name(app_name.to_string())
+            .config(sc!(service_name))
+            .started_at(DateTime::parse_from_rfc3339(started_at).unwrap().into())
+            .app_metadata(BTreeMap::from([(
+                String::from("team"),
+                if app_name.starts_with("pay") {
+                    String::from("payments")
+                } else {
+                    String::from("checkout")
+                },
+            )]))
+            .build()
+            .unwrap()
+    }
+
+    fn apps() -> MultiMap<AppName, Service> {
+        let mut apps = MultiMap::new();
+        for (app_name, started_at) in [
+            ("pay-1", "2023-07-01T10:00:00Z"),
+            ("pay-2", "2023-06-01T10:00:00Z"),
+            ("checkout-1", "2023-05-01T10:00:00Z"),
+        ] {
+            apps.insert(
+                AppName::from_str(app_name).unwrap(),
+                service(app_name, "nginx", started_at),
+            );
+        }
+        apps
+    }
+
+    fn app_names(page: &AppsPage) -> Vec<&str> {
+        page.apps
+            .iter()
+            .map(|(app_name, _)| app_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parse_query() {
+        let query = Form::<AppsQuery>::parse(
+            "filter=status:running,team:payments&sort=-created_at&limit=50",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.filter,
+            Some(AppFilters(vec![
+                AppFilter::Status(ServiceStatus::Running),
+                AppFilter::Metadata(String::from("team"), String::from("payments"))
+            ]))
+        );
+        assert_eq!(
+            query.sort,
+            Some(AppSort {
+                key: AppSortKey::CreatedAt,
+                descending: true
+            })
+        );
+        assert_eq!(query.limit, Some(50));
+        assert_eq!(query.offset, 0);
+    }
+
+    #[test]
+    fn reject_unknown_sort_key() {
+        assert!(Form::<AppsQuery>::parse("sort=size").is_err());
+    }
+
+    #[test]
+    fn sort_by_name_by_default() {
+        let page = AppsQuery::default().apply(apps());
+
+        assert_eq!(page.total, 3);
+        assert_eq!(app_names(&page), vec!["checkout-1", "pay-1", "pay-2"]);
+    }
+
+    #[test]
+    fn filter_by_metadata_and_sort_by_creation() {
+        let query = Form::<AppsQuery>::parse("filter=team:payments&sort=created_at").unwrap();
+
+        let page = query.apply(apps());
+
+        assert_eq!(app_names(&page), vec!["pay-2", "pay-1"]);
+    }
+
+    #[test]
+    fn paginate() {
+        let query = Form::<AppsQuery>::parse("limit=1&offset=1").unwrap();
+
+        let page = query.apply(apps());
+
+        assert_eq!(page.total, 3);
+        assert_eq!(app_names(&page), vec!["pay-1"]);
+    }
+
+    #[test]
+    fn filter_by_owner_of_request() {
+        let mut apps = apps();
+        apps.insert(
+            AppName::from_str("mine").unwrap(),
+            ServiceBuilder::from(service("mine", "nginx", "2023-07-01T10:00:00Z"))
+                .owner(String::from("alice"))
+                .build()
+                .unwrap(),
+        );
+        let query = Form::<AppsQuery>::parse("owner=me").unwrap();
+
+        let page = query.with_user(Some("alice")).unwrap().apply(apps);
+
+        assert_eq!(app_names(&page), vec!["mine"]);
+    }
+
+    #[test]
+    fn reject_owner_me_without_user() {
+        let query = Form::<AppsQuery>::parse("owner=me").unwrap();
+
+        assert!(query.with_user(None).is_err());
+    }
+}
@@ -24,50 +24,107 @@
  * =========================LICENSE_END==================================
  */
 
+use super::descriptor::{DescriptorError, DescriptorSource};
+use super::query::{AppsPage, AppsQuery};
+use super::v2;
 use crate::apps::HostMetaCache;
-use crate::apps::{Apps, AppsError};
-use crate::http_result::{HttpApiError, HttpResult};
+use crate::apps::{Apps, AppsError, DeploymentOptions, QueuedDeployment};
+use crate::audit::{AuditedJson, AuditedYaml};
+use crate::auth::AuthenticatedUser;
+use crate::config::{ConfigHandle, GitConfig};
+use crate::http_result::{HttpApiError, HttpResult, JsonOrYaml};
 use crate::models::request_info::RequestInfo;
 use crate::models::service::{Service, ServiceStatus};
-use crate::models::ServiceConfig;
-use crate::models::{AppName, AppNameError, LogChunk};
+use crate::models::{AppGraph, AppName, AppNameError, LogChunk, LogFilter, LogLevel};
 use crate::models::{AppStatusChangeId, AppStatusChangeIdError};
-use chrono::DateTime;
+use crate::models::{
+    DeploymentDiff, DeploymentHistoryEntry, Environment, FilesUpdate, Locale, Mail,
+};
+use crate::models::{
+    LifecycleHooks, ReplicationRules, ResourceMetadata, ServiceConfig, ServicePatch, ShareToken,
+    SmokeTest, SourceBuild, TimeZone,
+};
+use chrono::{DateTime, FixedOffset, Utc};
 use http_api_problem::{HttpApiProblem, StatusCode};
-use multimap::MultiMap;
 use regex::Regex;
-use rocket::http::{RawStr, Status};
+use rocket::data::{Data, ToByteUnit};
+use rocket::form::{self, FromFormField, ValueField};
+use rocket::http::{ContentType, MediaType, RawStr, Status};
 use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::content::RawHtml;
 use rocket::response::{Responder, Response};
 use rocket::serde::json::Json;
 use rocket::State;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
 use std::future::Future;
 use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
 use tokio::time::timeout;
+use uuid::Uuid;
 
 pub fn apps_routes() -> Vec<rocket::Route> {
     rocket::routes![
         apps,
         delete_app,
+        restore_app,
+        adopt_app,
+        change_owner,
+        remove_owner,
+        share_tokens,
+        create_share_token,
+        revoke_share_token,
+        upload_static_assets,
+        static_assets_bundle,
         create_app,
+        create_app_from_yaml,
+        create_app_negotiated,
+        dry_run,
+        dry_run_negotiated,
+        deployment_history,
+        deployment_queue,
+        cancel_queued_deployment,
+        mails,
+        graph,
         logs,
+        app_logs,
         change_status,
-        status_change
+        status_change,
+        cancel_deployment,
+        restart_service,
+        sbom,
+        update_service,
+        update_files,
+        placeholder
     ]
 }
 
-#[get("/", format = "application/json")]
+pub fn apps_v2_routes() -> Vec<rocket::Route> {
+    rocket::routes![create_app_v2, dry_run_v2]
+}
+
+#[get("/?<query..>", format = "application/json")]
 async fn apps(
     apps: &State<Arc<Apps>>,
     request_info: RequestInfo,
     host_meta_cache: &State<HostMetaCache>,
-) -> HttpResult<Json<MultiMap<AppName, Service>>> {
+    query: form::Result<'_, AppsQuery>,
+    user: RequestUser,
+) -> HttpResult<AppsPage> {
+    let query = query
+        .map_err(|errors| errors.to_string())
+        .and_then(|query| query.with_user(user.0.as_deref()))
+        .map_err(|error| {
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(error)
+        })?;
+
     let services = apps.get_apps().await?;
-    Ok(Json(
-        host_meta_cache.update_meta_data(services, &request_info),
-    ))
+    Ok(query.apply(host_meta_cache.update_meta_data(services, &request_info)))
 }
 
 #[get("/<app_name>/status-changes/<status_id>", format = "application/json")]
@@ -90,18 +147,43 @@ async fn status_change(
     }
 }
 
-#[delete("/<app_name>")]
+/// Cancels the deployment with the status id, even if it is already running. The response lists
+/// the deployments of the app that are still queued or running.
+#[delete("/<app_name>/status-changes/<status_id>")]
+async fn cancel_deployment(
+    app_name: Result<AppName, AppNameError>,
+    status_id: Result<AppStatusChangeId, AppStatusChangeIdError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<QueuedDeployment>>> {
+    let app_name = app_name?;
+    let status_id = status_id?;
+
+    apps.cancel_deployment(&app_name, &status_id)?;
+    Ok(Json(apps.queued_deployments(&app_name)))
+}
+
+#[delete("/<app_name>?<force>")]
 pub async fn delete_app(
     app_name: Result<AppName, AppNameError>,
     apps: &State<Arc<Apps>>,
+    force: Option<bool>,
+    bearer_token: BearerToken,
     options: RunOptions,
 ) -> HttpResult<AsyncCompletion<Json<Vec<Service>>>> {
     let app_name = app_name?;
     let app_name_cloned = app_name.clone();
     let status_id = AppStatusChangeId::new();
+    // A forced deletion without token is rejected if the app is protected.
+    let force_token = match (force, bearer_token) {
+        (Some(true), BearerToken(token)) => Some(token.unwrap_or_default()),
+        _ => None,
+    };
 
     let apps = (**apps).clone();
-    let future = async move { apps.delete_app(&app_name, &status_id).await };
+    let future = async move {
+        apps.delete_app_with_force_token(&app_name, &status_id, force_token.as_deref())
+            .await
+    };
 
     match spawn_with_options(options, future).await? {
         Poll::Pending => Ok(AsyncCompletion::Pending(app_name_cloned, status_id)),
@@ -114,7 +196,7 @@ pub async fn delete_app_sync(
     app_name: Result<AppName, AppNameError>,
     apps: &State<Arc<Apps>>,
 ) -> HttpResult<Json<Vec<Service>>> {
-    match delete_app(app_name, apps, RunOptions::Sync).await? {
+    match delete_app(app_name, apps, None, BearerToken(None), RunOptions::Sync).await? {
         AsyncCompletion::Pending(_, _) => {
             Err(HttpApiProblem::with_title(StatusCode::INTERNAL_SERVER_ERROR).into())
         }
@@ -122,50 +204,465 @@ pub async fn delete_app_sync(
     }
 }
 
+#[post("/<app_name>/restore")]
+async fn restore_app(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<Service>>> {
+    let app_name = app_name?;
+    let services = apps.restore_app(&app_name).await?;
+    Ok(Json(services))
+}
+
+/// Takes over the services of an app that have not been deployed by PREvant.
+#[post("/<app_name>/adopt")]
+async fn adopt_app(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<Service>>> {
+    let app_name = app_name?;
+    let services = apps.adopt_app(&app_name).await?;
+    Ok(Json(services))
+}
+
+/// Transfers the app to another owner. Authenticated users can only transfer their own apps or
+/// apps without an owner.
+#[put("/<app_name>/owner", format = "application/json", data = "<owner>")]
+async fn change_owner(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    owner: AuditedJson<AppOwner>,
+    user: RequestUser,
+) -> HttpResult<Json<AppOwner>> {
+    let app_name = app_name?;
+    let owner = apps
+        .change_owner(&app_name, owner.owner.as_deref(), user.0.as_deref())
+        .await?;
+    Ok(Json(AppOwner { owner }))
+}
+
+#[delete("/<app_name>/owner")]
+async fn remove_owner(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    user: RequestUser,
+) -> HttpResult<Json<AppOwner>> {
+    let app_name = app_name?;
+    let owner = apps
+        .change_owner(&app_name, None, user.0.as_deref())
+        .await?;
+    Ok(Json(AppOwner { owner }))
+}
+
+#[get("/<app_name>/share-tokens", format = "application/json")]
+async fn share_tokens(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    _user: AuthenticatedUser,
+) -> HttpResult<Json<Vec<ShareToken>>> {
+    let app_name = app_name?;
+    let share_tokens = apps.share_tokens(&app_name).await?;
+    Ok(Json(share_tokens))
+}
+
+/// Creates a token that grants access to the services of the app without authentication. The
+/// secret token is part of this response only.
+#[post(
+    "/<app_name>/share-tokens",
+    format = "application/json",
+    data = "<payload>"
+)]
+async fn create_share_token(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    payload: AuditedJson<ShareTokenPayload>,
+    _user: AuthenticatedUser,
+) -> HttpResult<(Status, Json<CreatedShareToken>)> {
+    let app_name = app_name?;
+    let payload = payload.into_inner();
+    let (share_token, token) = apps
+        .create_share_token(&app_name, payload.description, payload.expires_at)
+        .await?;
+    Ok((
+        Status::Created,
+        Json(CreatedShareToken { share_token, token }),
+    ))
+}
+
+#[delete("/<app_name>/share-tokens/<id>")]
+async fn revoke_share_token(
+    app_name: Result<AppName, AppNameError>,
+    id: &str,
+    apps: &State<Arc<Apps>>,
+    _user: AuthenticatedUser,
+) -> HttpResult<Status> {
+    let app_name = app_name?;
+    let id = Uuid::parse_str(id).map_err(|_| {
+        HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail(format!("There is no share token {id} of app {app_name}."))
+    })?;
+    apps.revoke_share_token(&app_name, &id).await?;
+    Ok(Status::NoContent)
+}
+
+/// Deploys the uploaded tarball or zip archive of static assets, e.g. a built single page
+/// application, as service of the app that is served by nginx.
+#[put("/<app_name>/static/<service_name>", data = "<bundle>")]
+async fn upload_static_assets(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    apps: &State<Arc<Apps>>,
+    bundle: Data<'_>,
+) -> HttpResult<Json<Vec<Service>>> {
+    let app_name = app_name?;
+    let max_size = apps
+        .static_assets_config()
+        .map(|config| config.max_size())
+        .ok_or(AppsError::StaticAssetsNotConfigured)?;
+
+    let bundle = bundle
+        .open(max_size.as_u64().bytes())
+        .into_bytes()
+        .await
+        .map_err(|err| {
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(err.to_string())
+        })?;
+    if !bundle.is_complete() {
+        return Err(
+            HttpApiProblem::with_title_and_type(StatusCode::PAYLOAD_TOO_LARGE)
+                .detail(format!(
+                    "The static assets exceed the size of {}.",
+                    max_size
+                ))
+                .into(),
+        );
+    }
+
+    let services = apps
+        .deploy_static_assets(
+            &app_name,
+            &AppStatusChangeId::new(),
+            &service_name,
+            bundle.into_inner(),
+        )
+        .await?;
+    Ok(Json(services))
+}
+
+/// The bundle of static assets that the nginx service of the app downloads on startup.
+#[get("/<app_name>/static/<service_name>/bundle")]
+async fn static_assets_bundle(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Vec<u8>> {
+    let app_name = app_name?;
+    Ok(apps.static_assets_bundle(&app_name, &service_name).await?)
+}
+
 #[post(
     "/<app_name>?<create_app_form..>",
     format = "application/json",
-    data = "<service_configs>"
+    data = "<payload>"
 )]
 pub async fn create_app(
     app_name: Result<AppName, AppNameError>,
     apps: &State<Arc<Apps>>,
     create_app_form: CreateAppOptions,
-    service_configs: Result<Json<Vec<ServiceConfig>>, rocket::serde::json::Error<'_>>,
+    payload: Result<AuditedJson<DeploymentPayload>, rocket::serde::json::Error<'_>>,
     options: RunOptions,
-) -> HttpResult<AsyncCompletion<Json<Vec<Service>>>> {
-    let service_configs = service_configs.map_err(|e| {
-        let detail = match e {
-            rocket::serde::json::Error::Parse(_, e) => e.to_string(),
-            e => e.to_string(),
-        };
+    user: RequestUser,
+    _api_version: ApiV1,
+) -> HttpResult<AsyncCompletion<JsonOrYaml<Vec<Service>>>> {
+    let payload = parse_deployment_payload(payload)?
+        .with_resolved_source(&apps.git_config())
+        .await?;
 
-        HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(detail)
-    })?;
+    deploy(app_name?, apps, create_app_form, payload, options, user).await
+}
+
+/// Deploys the same payload as [`create_app`] written in YAML which is easier to template in CI
+/// pipelines.
+#[post(
+    "/<app_name>?<create_app_form..>",
+    format = "application/yaml",
+    data = "<payload>"
+)]
+async fn create_app_from_yaml(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Result<AuditedYaml<DeploymentPayload>, String>,
+    options: RunOptions,
+    user: RequestUser,
+) -> HttpResult<AsyncCompletion<JsonOrYaml<Vec<Service>>>> {
+    let payload = payload
+        .map_err(|err| HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(err))?
+        .into_inner()
+        .with_resolved_source(&apps.git_config())
+        .await?;
+
+    deploy(app_name?, apps, create_app_form, payload, options, user).await
+}
+
+/// Deploys the payload of version 2 of the REST API, see [`v2::DeploymentRequest`].
+#[post(
+    "/<app_name>?<create_app_form..>",
+    format = "application/json",
+    data = "<payload>"
+)]
+async fn create_app_v2(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Result<AuditedJson<v2::DeploymentRequest>, rocket::serde::json::Error<'_>>,
+    options: RunOptions,
+    user: RequestUser,
+) -> HttpResult<AsyncCompletion<JsonOrYaml<Vec<Service>>>> {
+    let payload = DeploymentPayload::try_from(parse_deployment_payload(payload)?)?;
+    deploy(app_name?, apps, create_app_form, payload, options, user).await
+}
+
+/// Deploys the payload of version 2 of the REST API if the `Accept` header requests it on the
+/// routes of version 1.
+#[post(
+    "/<app_name>?<create_app_form..>",
+    format = "application/json",
+    data = "<payload>",
+    rank = 1
+)]
+async fn create_app_negotiated(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Result<AuditedJson<v2::DeploymentRequest>, rocket::serde::json::Error<'_>>,
+    options: RunOptions,
+    user: RequestUser,
+    _api_version: ApiV2,
+) -> HttpResult<AsyncCompletion<JsonOrYaml<Vec<Service>>>> {
+    create_app_v2(app_name, apps, create_app_form, payload, options, user).await
+}
+
+async fn deploy(
+    app_name: AppName,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: DeploymentPayload,
+    options: RunOptions,
+    user: RequestUser,
+) -> HttpResult<AsyncCompletion<JsonOrYaml<Vec<Service>>>> {
+    let DeploymentPayload {
+        services: service_configs,
+        smoke_test,
+        hooks,
+        metadata,
+        replicate_from,
+        replication,
+        owner,
+        builds,
+        env,
+        timezone,
+        locale,
+        service_doubles,
+        stacks,
+        resource_metadata,
+        ..
+    } = payload;
 
     let status_id = AppStatusChangeId::new();
-    let app_name = app_name?;
     let app_name_cloned = app_name.clone();
-    let replicate_from = create_app_form.replicate_from().clone();
+    let replicate_from = create_app_form.replicate_from().clone().or(replicate_from);
+    let deployment_options = create_app_form
+        .deployment_options(smoke_test, hooks, metadata, replication, stacks)
+        .with_owner(owner.or(user.0))
+        .with_builds(builds)
+        .with_app_env(env)
+        .with_timezone(timezone)
+        .with_locale(locale)
+        .with_service_doubles(service_doubles)
+        .with_resource_metadata(resource_metadata);
 
     let apps = (**apps).clone();
     let future = async move {
-        apps.create_or_update(
+        apps.create_or_update_with_options(
             &app_name.clone(),
             &status_id,
             replicate_from,
             &service_configs,
+            &deployment_options,
         )
         .await
     };
 
     match spawn_with_options(options, future).await? {
         Poll::Pending => Ok(AsyncCompletion::Pending(app_name_cloned, status_id)),
-        Poll::Ready(Ok(services)) => Ok(AsyncCompletion::Ready(Json(services))),
+        Poll::Ready(Ok(services)) => Ok(AsyncCompletion::Ready(JsonOrYaml(services))),
         Poll::Ready(Err(err)) => Err(err.into()),
     }
 }
 
+/// Returns the changes that a deployment of the payload would apply to the app without deploying
+/// anything.
+#[post(
+    "/<app_name>/dry-run?<create_app_form..>",
+    format = "application/json",
+    data = "<payload>"
+)]
+async fn dry_run(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Result<AuditedJson<DeploymentPayload>, rocket::serde::json::Error<'_>>,
+    _api_version: ApiV1,
+) -> HttpResult<Json<DeploymentDiff>> {
+    let payload = parse_deployment_payload(payload)?
+        .with_resolved_source(&apps.git_config())
+        .await?;
+
+    diff(app_name?, apps, create_app_form, payload).await
+}
+
+#[post(
+    "/<app_name>/dry-run?<create_app_form..>",
+    format = "application/json",
+    data = "<payload>"
+)]
+async fn dry_run_v2(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Result<AuditedJson<v2::DeploymentRequest>, rocket::serde::json::Error<'_>>,
+) -> HttpResult<Json<DeploymentDiff>> {
+    let payload = DeploymentPayload::try_from(parse_deployment_payload(payload)?)?;
+    diff(app_name?, apps, create_app_form, payload).await
+}
+
+#[post(
+    "/<app_name>/dry-run?<create_app_form..>",
+    format = "application/json",
+    data = "<payload>",
+    rank = 1
+)]
+async fn dry_run_negotiated(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: Result<AuditedJson<v2::DeploymentRequest>, rocket::serde::json::Error<'_>>,
+    _api_version: ApiV2,
+) -> HttpResult<Json<DeploymentDiff>> {
+    dry_run_v2(app_name, apps, create_app_form, payload).await
+}
+
+async fn diff(
+    app_name: AppName,
+    apps: &State<Arc<Apps>>,
+    create_app_form: CreateAppOptions,
+    payload: DeploymentPayload,
+) -> HttpResult<Json<DeploymentDiff>> {
+    let DeploymentPayload {
+        services: service_configs,
+        smoke_test,
+        hooks,
+        metadata,
+        replicate_from,
+        replication,
+        env,
+        timezone,
+        locale,
+        service_doubles,
+        stacks,
+        resource_metadata,
+        ..
+    } = payload;
+
+    let deployment_options = create_app_form
+        .deployment_options(smoke_test, hooks, metadata, replication, stacks)
+        .with_app_env(env)
+        .with_timezone(timezone)
+        .with_locale(locale)
+        .with_service_doubles(service_doubles)
+        .with_resource_metadata(resource_metadata);
+
+    let diff = apps
+        .dry_run(
+            &app_name,
+            create_app_form.replicate_from().clone().or(replicate_from),
+            &service_configs,
+            &deployment_options,
+        )
+        .await?;
+
+    Ok(Json(diff))
+}
+
+#[get("/<app_name>/history", format = "application/json")]
+async fn deployment_history(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<DeploymentHistoryEntry>>> {
+    let app_name = app_name?;
+    Ok(Json(apps.deployment_history(&app_name).await?))
+}
+
+/// Lists the running deployment of the app followed by the deployments that wait for it.
+#[get("/<app_name>/queue", format = "application/json")]
+async fn deployment_queue(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<QueuedDeployment>>> {
+    let app_name = app_name?;
+    Ok(Json(apps.queued_deployments(&app_name)))
+}
+
+#[delete("/<app_name>/queue/<status_id>")]
+async fn cancel_queued_deployment(
+    app_name: Result<AppName, AppNameError>,
+    status_id: Result<AppStatusChangeId, AppStatusChangeIdError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<QueuedDeployment>>> {
+    let app_name = app_name?;
+    let status_id = status_id?;
+
+    apps.cancel_queued_deployment(&app_name, &status_id)?;
+    Ok(Json(apps.queued_deployments(&app_name)))
+}
+
+/// Returns the services of the app with their companions, volumes, and routes and the relations
+/// between them, so that the frontend can render a diagram of the app.
+#[get("/<app_name>/graph", format = "application/json")]
+async fn graph(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<AppGraph>> {
+    let app_name = app_name?;
+    Ok(Json(apps.graph(&app_name).await?))
+}
+
+/// Lists the mails that have been captured by the mail catcher of the app.
+#[get("/<app_name>/mail", format = "application/json")]
+async fn mails(
+    app_name: Result<AppName, AppNameError>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<Mail>>> {
+    let app_name = app_name?;
+    Ok(Json(apps.mails(&app_name).await?))
+}
+
+fn parse_deployment_payload<T>(
+    payload: Result<AuditedJson<T>, rocket::serde::json::Error<'_>>,
+) -> HttpResult<T> {
+    Ok(payload
+        .map_err(|e| {
+            let detail = match e {
+                rocket::serde::json::Error::Parse(_, e) => e.to_string(),
+                e => e.to_string(),
+            };
+
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(detail)
+        })?
+        .into_inner())
+}
+
 #[put(
     "/<app_name>/states/<service_name>",
     format = "application/json",
@@ -175,7 +672,7 @@ async fn change_status(
     app_name: Result<AppName, AppNameError>,
     service_name: String,
     apps: &State<Arc<Apps>>,
-    status_data: Json<ServiceStatusData>,
+    status_data: AuditedJson<ServiceStatusData>,
 ) -> HttpResult<ServiceStatusResponse> {
     let app_name = app_name?;
     let status = status_data.status.clone();
@@ -185,8 +682,36 @@ async fn change_status(
     Ok(ServiceStatusResponse { service })
 }
 
+/// The SBOM of the image that has been deployed for the service in the configured format, e.g.
+/// CycloneDX.
+#[get("/<app_name>/services/<service_name>/sbom")]
+async fn sbom(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<(ContentType, String)> {
+    let app_name = app_name?;
+    let sbom = apps.sbom(&app_name, &service_name).await?;
+
+    let (top, sub) = sbom.format().media_type();
+    Ok((ContentType::new(top, sub), sbom.document().to_string()))
+}
+
+#[post("/<app_name>/services/<service_name>/restart")]
+async fn restart_service(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<ServiceStatusResponse> {
+    let app_name = app_name?;
+
+    let service = apps.restart_service(&app_name, &service_name).await?;
+
+    Ok(ServiceStatusResponse { service })
+}
+
 #[get(
-    "/<app_name>/logs/<service_name>?<since>&<limit>",
+    "/<app_name>/logs/<service_name>?<since>&<limit>&<filter_options..>",
     format = "text/plain"
 )]
 async fn logs(
@@ -194,27 +719,17 @@ async fn logs(
     service_name: String,
     since: Option<String>,
     limit: Option<usize>,
+    filter_options: LogFilterOptions,
     apps: &State<Arc<Apps>>,
 ) -> HttpResult<LogsResponse> {
     let app_name = app_name?;
 
-    let since = match since {
-        None => None,
-        Some(since) => match DateTime::parse_from_rfc3339(&since) {
-            Ok(since) => Some(since),
-            Err(err) => {
-                return Err(
-                    HttpApiProblem::with_title(http_api_problem::StatusCode::BAD_REQUEST)
-                        .detail(format!("{}", err))
-                        .into(),
-                );
-            }
-        },
-    };
+    let since = parse_since(since)?;
     let limit = limit.unwrap_or(20_000);
+    let filter = filter_options.log_filter()?;
 
     let log_chunk = apps
-        .get_logs(&app_name, &service_name, &since, limit)
+        .get_logs(&app_name, &service_name, &since, limit, &filter)
         .await?;
 
     Ok(LogsResponse {
@@ -222,9 +737,171 @@ async fn logs(
         app_name,
         service_name,
         limit,
+        filter_options,
+    })
+}
+
+/// Returns the interleaved logs of all services of the app, or of the services given by the
+/// `service` parameters. Each line is prefixed with the service name and the stream.
+#[get(
+    "/<app_name>/logs?<since>&<limit>&<service>&<filter_options..>",
+    format = "text/plain"
+)]
+async fn app_logs(
+    app_name: Result<AppName, AppNameError>,
+    since: Option<String>,
+    limit: Option<usize>,
+    service: Vec<String>,
+    filter_options: LogFilterOptions,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<AppLogsResponse> {
+    let app_name = app_name?;
+
+    let since = parse_since(since)?;
+    let limit = limit.unwrap_or(20_000);
+    let filter = filter_options.log_filter()?;
+
+    let log_chunk = apps
+        .get_app_logs(&app_name, &service, &since, limit, &filter)
+        .await?;
+
+    Ok(AppLogsResponse {
+        log_chunk,
+        app_name,
+        service_names: service,
+        limit,
+        filter_options,
     })
 }
 
+fn parse_since(since: Option<String>) -> HttpResult<Option<DateTime<FixedOffset>>> {
+    match since {
+        None => Ok(None),
+        Some(since) => match DateTime::parse_from_rfc3339(&since) {
+            Ok(since) => Ok(Some(since)),
+            Err(err) => Err(
+                HttpApiProblem::with_title(http_api_problem::StatusCode::BAD_REQUEST)
+                    .detail(format!("{}", err))
+                    .into(),
+            ),
+        },
+    }
+}
+
+/// Merges the image, environment variables, or files of the payload into the configuration of the
+/// running service and redeploys only this service.
+#[patch(
+    "/<app_name>/services/<service_name>",
+    format = "application/json",
+    data = "<patch>"
+)]
+async fn update_service(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    apps: &State<Arc<Apps>>,
+    patch: Result<AuditedJson<ServicePatch>, rocket::serde::json::Error<'_>>,
+) -> HttpResult<Json<Service>> {
+    let app_name = app_name?;
+    let patch = patch
+        .map_err(|err| {
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(err.to_string())
+        })?
+        .into_inner();
+
+    match apps.update_service(&app_name, &service_name, patch).await? {
+        Some(service) => Ok(Json(service)),
+        None => Err(HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail(format!(
+                "Cannot find service {} of app {}.",
+                service_name, app_name
+            ))
+            .into()),
+    }
+}
+
+/// Replaces the contents of files that are mounted into the running service without redeploying
+/// it and optionally restarts the service or sends a signal to it.
+#[put(
+    "/<app_name>/services/<service_name>/files",
+    format = "application/json",
+    data = "<update>"
+)]
+async fn update_files(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    apps: &State<Arc<Apps>>,
+    update: Result<AuditedJson<FilesUpdate>, rocket::serde::json::Error<'_>>,
+) -> HttpResult<Json<Service>> {
+    let app_name = app_name?;
+    let update = update
+        .map_err(|err| {
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(err.to_string())
+        })?
+        .into_inner();
+
+    match apps.update_files(&app_name, &service_name, update).await? {
+        Some(service) => Ok(Json(service)),
+        None => Err(HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail(format!(
+                "Cannot find service {} of app {}.",
+                service_name, app_name
+            ))
+            .into()),
+    }
+}
+
+/// Renders the page that Traefik shows instead of its error page while the service is starting
+/// or stopped.
+#[get("/<app_name>/services/<service_name>/placeholder?<status>")]
+async fn placeholder(
+    app_name: Result<AppName, AppNameError>,
+    service_name: String,
+    status: Option<u16>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<(Status, RawHtml<String>)> {
+    let app_name = app_name?;
+
+    let services = apps.get_apps().await?;
+    let message = match services
+        .get_vec(&app_name)
+        .and_then(|services| {
+            services
+                .iter()
+                .find(|service| service.service_name() == &service_name)
+        })
+        .map(|service| service.status())
+    {
+        Some(ServiceStatus::Running) => "is starting",
+        Some(ServiceStatus::Paused) => "is stopped",
+        None => "is not available",
+    };
+
+    let refresh_interval = apps.placeholder_refresh_interval();
+
+    let page = handlebars::Handlebars::new()
+        .render_template(
+            include_str!("../../res/placeholder.html.hbs"),
+            &serde_json::json!({
+                "appName": app_name,
+                "serviceName": service_name,
+                "message": message,
+                "refreshInterval": refresh_interval,
+            }),
+        )
+        .map_err(|err| {
+            HttpApiError::from(
+                HttpApiProblem::with_title(StatusCode::INTERNAL_SERVER_ERROR)
+                    .detail(err.to_string()),
+            )
+        })?;
+
+    let status = status
+        .and_then(|status| Status::from_code(status))
+        .unwrap_or(Status::ServiceUnavailable);
+
+    Ok((status, RawHtml(page)))
+}
+
 #[derive(Debug, PartialEq)]
 pub enum RunOptions {
     Sync,
@@ -270,18 +947,372 @@ pub struct LogsResponse {
     app_name: AppName,
     service_name: String,
     limit: usize,
+    filter_options: LogFilterOptions,
+}
+
+pub struct AppLogsResponse {
+    log_chunk: Option<LogChunk>,
+    app_name: AppName,
+    service_names: Vec<String>,
+    limit: usize,
+    filter_options: LogFilterOptions,
+}
+
+/// The query parameters that restrict the returned log lines, see [`LogFilter`].
+#[derive(FromForm)]
+pub struct LogFilterOptions {
+    grep: Option<String>,
+    regex: Option<String>,
+    level: Option<String>,
+}
+
+impl LogFilterOptions {
+    fn log_filter(&self) -> HttpResult<LogFilter> {
+        let bad_request = |detail: String| {
+            HttpApiError::from(HttpApiProblem::with_title(StatusCode::BAD_REQUEST).detail(detail))
+        };
+
+        let regex = match &self.regex {
+            Some(regex) => Some(Regex::new(regex).map_err(|err| bad_request(err.to_string()))?),
+            None => None,
+        };
+        let level = match &self.level {
+            Some(level) => Some(level.parse::<LogLevel>().map_err(bad_request)?),
+            None => None,
+        };
+
+        Ok(LogFilter::new(self.grep.clone(), regex, level))
+    }
+
+    /// Returns the filter as query string suffix so that the next logs URL keeps the filter.
+    fn query_string(&self) -> String {
+        [
+            ("grep", &self.grep),
+            ("regex", &self.regex),
+            ("level", &self.level),
+        ]
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_ref()
+                .map(|value| format!("&{}={}", name, RawStr::new(value).percent_encode()))
+        })
+        .collect()
+    }
+}
+
+/// The body of a deployment request which is either the list of services or an object that
+/// contains the services and further options of the deployment, e.g. a smoke test.
+pub struct DeploymentPayload {
+    pub(super) services: Vec<ServiceConfig>,
+    pub(super) smoke_test: Option<SmokeTest>,
+    pub(super) hooks: Option<LifecycleHooks>,
+    pub(super) metadata: BTreeMap<String, String>,
+    pub(super) replicate_from: Option<AppName>,
+    pub(super) replication: ReplicationRules,
+    pub(super) source: Option<DescriptorSource>,
+    pub(super) owner: Option<String>,
+    pub(super) builds: Vec<SourceBuild>,
+    pub(super) env: Option<Environment>,
+    pub(super) timezone: Option<TimeZone>,
+    pub(super) locale: Option<Locale>,
+    pub(super) service_doubles: Vec<String>,
+    pub(super) stacks: Vec<String>,
+    pub(super) resource_metadata: Option<ResourceMetadata>,
+}
+
+impl DeploymentPayload {
+    /// Fetches the descriptor the payload refers to and merges it with the payload. The services
+    /// and options of the request take precedence over the ones of the descriptor.
+    async fn with_resolved_source(self, git_config: &GitConfig) -> HttpResult<Self> {
+        let Some(source) = &self.source else {
+            return Ok(self);
+        };
+
+        let descriptor = source.fetch::<DeploymentPayload>(git_config).await?;
+
+        let mut services = descriptor
+            .services
+            .into_iter()
+            .filter(|service| {
+                !self
+                    .services
+                    .iter()
+                    .any(|s| s.service_name() == service.service_name())
+            })
+            .collect::<Vec<_>>();
+        services.extend(self.services);
+
+        let mut builds = descriptor
+            .builds
+            .into_iter()
+            .filter(|build| {
+                !self
+                    .builds
+                    .iter()
+                    .any(|b| b.service_name() == build.service_name())
+            })
+            .collect::<Vec<_>>();
+        builds.extend(self.builds);
+
+        let mut metadata = descriptor.metadata;
+        metadata.extend(self.metadata);
+
+        let env = match (descriptor.env, self.env) {
+            (Some(descriptor_env), Some(env)) => Some(Environment::new(
+                descriptor_env
+                    .into_iter()
+                    .filter(|variable| env.variable(variable.key()).is_none())
+                    .chain(env.into_iter())
+                    .collect(),
+            )),
+            (descriptor_env, env) => env.or(descriptor_env),
+        };
+
+        let mut service_doubles = descriptor
+            .service_doubles
+            .into_iter()
+            .filter(|name| !self.service_doubles.contains(name))
+            .collect::<Vec<_>>();
+        service_doubles.extend(self.service_doubles);
+
+        let mut stacks = descriptor
+            .stacks
+            .into_iter()
+            .filter(|name| !self.stacks.contains(name))
+            .collect::<Vec<_>>();
+        stacks.extend(self.stacks);
+
+        let resource_metadata = match (descriptor.resource_metadata, self.resource_metadata) {
+            (Some(descriptor_metadata), Some(mut metadata)) => {
+                metadata.merge_with(&descriptor_metadata);
+                Some(metadata)
+            }
+            (descriptor_metadata, metadata) => metadata.or(descriptor_metadata),
+        };
+
+        Ok(DeploymentPayload {
+            services,
+            smoke_test: self.smoke_test.or(descriptor.smoke_test),
+            hooks: self.hooks.or(descriptor.hooks),
+            metadata,
+            replicate_from: self.replicate_from.or(descriptor.replicate_from),
+            replication: if self.replication.is_empty() {
+                descriptor.replication
+            } else {
+                self.replication
+            },
+            source: None,
+            owner: self.owner.or(descriptor.owner),
+            builds,
+            env,
+            timezone: self.timezone.or(descriptor.timezone),
+            locale: self.locale.or(descriptor.locale),
+            service_doubles,
+            stacks,
+            resource_metadata,
+        })
+    }
+}
+
+impl From<DescriptorError> for HttpApiError {
+    fn from(err: DescriptorError) -> Self {
+        let status = match err {
+            DescriptorError::UnsupportedUrl { .. }
+            | DescriptorError::HostNotPermitted { .. }
+            | DescriptorError::InvalidPath { .. }
+            | DescriptorError::InvalidDescriptor { .. } => StatusCode::BAD_REQUEST,
+            DescriptorError::FetchFailed { .. } => StatusCode::BAD_GATEWAY,
+            DescriptorError::CannotRunGit { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        HttpApiError::from(HttpApiProblem::with_title_and_type(status).detail(format!("{}", err)))
+    }
+}
+
+impl From<v2::RequestError> for HttpApiError {
+    fn from(err: v2::RequestError) -> Self {
+        HttpApiError::from(
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST).detail(format!("{}", err)),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for DeploymentPayload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DeploymentPayloadVisitor;
+
+        impl<'de> Visitor<'de> for DeploymentPayloadVisitor {
+            type Value = DeploymentPayload;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of services or an object with services")
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                Ok(DeploymentPayload {
+                    services: Vec::deserialize(SeqAccessDeserializer::new(seq))?,
+                    smoke_test: None,
+                    hooks: None,
+                    metadata: BTreeMap::new(),
+                    replicate_from: None,
+                    replication: ReplicationRules::default(),
+                    source: None,
+                    owner: None,
+                    builds: Vec::new(),
+                    env: None,
+                    timezone: None,
+                    locale: None,
+                    service_doubles: Vec::new(),
+                    stacks: Vec::new(),
+                    resource_metadata: None,
+                })
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                #[derive(Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct Payload {
+                    #[serde(default)]
+                    services: Vec<ServiceConfig>,
+                    smoke_test: Option<SmokeTest>,
+                    hooks: Option<LifecycleHooks>,
+                    #[serde(default)]
+                    metadata: BTreeMap<String, String>,
+                    replicate_from: Option<AppName>,
+                    #[serde(default)]
+                    replication: ReplicationRules,
+                    source: Option<DescriptorSource>,
+                    owner: Option<String>,
+                    #[serde(default)]
+                    builds: Vec<SourceBuild>,
+                    env: Option<Environment>,
+                    timezone: Option<TimeZone>,
+                    locale: Option<Locale>,
+                    #[serde(default)]
+                    service_doubles: Vec<String>,
+                    #[serde(default)]
+                    stacks: Vec<String>,
+                    resource_metadata: Option<ResourceMetadata>,
+                }
+
+                let payload = Payload::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(DeploymentPayload {
+                    services: payload.services,
+                    smoke_test: payload.smoke_test,
+                    hooks: payload.hooks,
+                    metadata: payload.metadata,
+                    replicate_from: payload.replicate_from,
+                    replication: payload.replication,
+                    source: payload.source,
+                    owner: payload.owner,
+                    builds: payload.builds,
+                    env: payload.env,
+                    timezone: payload.timezone,
+                    locale: payload.locale,
+                    service_doubles: payload.service_doubles,
+                    stacks: payload.stacks,
+                    resource_metadata: payload.resource_metadata,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(DeploymentPayloadVisitor)
+    }
 }
 
 #[derive(FromForm)]
 pub struct CreateAppOptions {
     #[field(name = "replicateFrom")]
     replicate_from: Option<AppName>,
+    /// Annotations for the app's namespace, provided as repeatable `key=value` query parameter.
+    #[field(name = "namespaceAnnotation")]
+    namespace_annotations: Option<Vec<KeyValuePair>>,
+    /// Names of configured stacks whose companions will be deployed with the app.
+    #[field(default = Vec::new())]
+    stacks: Vec<String>,
+    /// Name of the configured cluster the app will be deployed to.
+    target: Option<String>,
 }
 
 impl CreateAppOptions {
     fn replicate_from(&self) -> &Option<AppName> {
         &self.replicate_from
     }
+
+    fn namespace_annotations(&self) -> BTreeMap<String, String> {
+        self.namespace_annotations
+            .iter()
+            .flatten()
+            .map(|pair| (pair.key.clone(), pair.value.clone()))
+            .collect()
+    }
+
+    /// The stacks of the query followed by the stacks of the payload that are not part of the
+    /// query.
+    fn stacks(&self, payload_stacks: Vec<String>) -> Vec<String> {
+        let mut stacks = self.stacks.clone();
+        stacks.extend(
+            payload_stacks
+                .into_iter()
+                .filter(|name| !self.stacks.contains(name)),
+        );
+        stacks
+    }
+
+    fn deployment_options(
+        &self,
+        smoke_test: Option<SmokeTest>,
+        lifecycle_hooks: Option<LifecycleHooks>,
+        metadata: BTreeMap<String, String>,
+        replication_rules: ReplicationRules,
+        stacks: Vec<String>,
+    ) -> DeploymentOptions {
+        DeploymentOptions::default()
+            .with_namespace_annotations(self.namespace_annotations())
+            .with_stacks(self.stacks(stacks))
+            .with_target(self.target().clone())
+            .with_smoke_test(smoke_test)
+            .with_lifecycle_hooks(lifecycle_hooks)
+            .with_app_metadata(metadata)
+            .with_replication_rules(replication_rules)
+    }
+
+    fn target(&self) -> &Option<String> {
+        &self.target
+    }
+}
+
+/// A query parameter value in the format `key=value`
+pub struct KeyValuePair {
+    key: String,
+    value: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromFormField<'r> for KeyValuePair {
+    fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
+        match field.value.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(KeyValuePair {
+                key: key.trim().to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(form::Error::validation(format!(
+                "“{}” is not in the format key=value",
+                field.value
+            ))
+            .into()),
+        }
+    }
 }
 
 impl<'r> Responder<'r, 'static> for LogsResponse {
@@ -303,11 +1334,12 @@ impl<'r> Responder<'r, 'static> for LogsResponse {
         let from = *log_chunk.until() + chrono::Duration::milliseconds(1);
 
         let next_logs_url = format!(
-            "/api/apps/{}/logs/{}/?limit={}&since={}",
+            "/api/apps/{}/logs/{}/?limit={}&since={}{}",
             self.app_name,
             self.service_name,
             self.limit,
             RawStr::new(&from.to_rfc3339()).percent_encode(),
+            self.filter_options.query_string(),
         );
 
         let log_lines = log_chunk.log_lines();
@@ -318,6 +1350,67 @@ impl<'r> Responder<'r, 'static> for LogsResponse {
     }
 }
 
+impl<'r> Responder<'r, 'static> for AppLogsResponse {
+    fn respond_to(self, request: &'r Request) -> Result<Response<'static>, Status> {
+        let log_chunk = match self.log_chunk {
+            None => {
+                return LogsResponse {
+                    log_chunk: None,
+                    app_name: self.app_name,
+                    service_name: String::new(),
+                    limit: self.limit,
+                    filter_options: self.filter_options,
+                }
+                .respond_to(request);
+            }
+            Some(log_chunk) => log_chunk,
+        };
+
+        let from = *log_chunk.until() + chrono::Duration::milliseconds(1);
+
+        let mut next_logs_url = format!(
+            "/api/apps/{}/logs?limit={}&since={}",
+            self.app_name,
+            self.limit,
+            RawStr::new(&from.to_rfc3339()).percent_encode(),
+        );
+        for service_name in &self.service_names {
+            next_logs_url.push_str(&format!(
+                "&service={}",
+                RawStr::new(service_name).percent_encode()
+            ));
+        }
+        next_logs_url.push_str(&self.filter_options.query_string());
+
+        let log_lines = log_chunk.log_lines().clone();
+        Response::build()
+            .raw_header("Link", format!("<{}>;rel=next", next_logs_url))
+            .sized_body(log_lines.len(), std::io::Cursor::new(log_lines))
+            .ok()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AppOwner {
+    owner: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareTokenPayload {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct CreatedShareToken {
+    #[serde(flatten)]
+    share_token: ShareToken,
+    token: String,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ServiceStatusData {
     status: ServiceStatus,
@@ -368,19 +1461,63 @@ impl From<AppsError> for HttpApiError {
             },
             AppsError::AppNotFound { .. } => StatusCode::NOT_FOUND,
             AppsError::AppIsInDeployment { .. } => StatusCode::CONFLICT,
+            AppsError::DeploymentCancelled { .. } => StatusCode::CONFLICT,
+            AppsError::DeploymentNotQueued { .. } => StatusCode::NOT_FOUND,
+            AppsError::NotLeader { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            AppsError::StateStoreFailure { .. } => StatusCode::SERVICE_UNAVAILABLE,
             AppsError::AppIsInDeletion { .. } => StatusCode::CONFLICT,
+            AppsError::UnknownStack { .. } => StatusCode::BAD_REQUEST,
+            AppsError::UnknownServiceDouble { .. } => StatusCode::BAD_REQUEST,
+            AppsError::UnknownTarget { .. } => StatusCode::BAD_REQUEST,
+            AppsError::DeploymentRejectedByHook { .. } => StatusCode::BAD_REQUEST,
+            AppsError::SmokeTestFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppsError::LifecycleHookFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppsError::InvalidSeed { .. } => StatusCode::BAD_REQUEST,
+            AppsError::SeedFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppsError::SharedDatabaseFailure { .. } => StatusCode::BAD_GATEWAY,
+            AppsError::InvalidServiceTemplate { .. } => StatusCode::BAD_REQUEST,
+            AppsError::InvalidAppMetadataKey { .. } => StatusCode::BAD_REQUEST,
+            AppsError::InvalidAppName { .. } => StatusCode::BAD_REQUEST,
+            AppsError::InvalidServiceName { .. } => StatusCode::BAD_REQUEST,
+            AppsError::InvalidAlias { .. } => StatusCode::BAD_REQUEST,
+            AppsError::AppIsProtected { .. } => StatusCode::CONFLICT,
+            AppsError::ForcedDeletionNotPermitted { .. } => StatusCode::FORBIDDEN,
+            AppsError::NotOwnerOfApp { .. } => StatusCode::FORBIDDEN,
+            AppsError::VulnerableImage { .. } => StatusCode::BAD_REQUEST,
+            AppsError::MailCatcherNotFound { .. } => StatusCode::NOT_FOUND,
+            AppsError::StaticAssetsNotConfigured => StatusCode::NOT_FOUND,
+            AppsError::BuildsNotConfigured => StatusCode::BAD_REQUEST,
+            AppsError::BuildFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppsError::InvalidStaticAssets => StatusCode::BAD_REQUEST,
+            AppsError::StaticAssetsNotFound { .. } => StatusCode::NOT_FOUND,
+            AppsError::SbomNotFound { .. } => StatusCode::NOT_FOUND,
+            AppsError::InvalidShareTokenExpiry => StatusCode::BAD_REQUEST,
+            AppsError::ShareTokenNotFound { .. } => StatusCode::NOT_FOUND,
+            AppsError::UnsupportedBackupVersion { .. } => StatusCode::BAD_REQUEST,
+            AppsError::InsufficientCapacity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppsError::DevMountNotPermitted { .. } => StatusCode::BAD_REQUEST,
+            AppsError::ServiceAccountRuleNotPermitted { .. } => StatusCode::BAD_REQUEST,
+            AppsError::ReloadFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppsError::MailCatcherRequestFailed { .. } => StatusCode::BAD_GATEWAY,
             AppsError::InfrastructureError { .. }
+            | AppsError::VulnerabilityScanFailed { .. }
             | AppsError::InvalidServerConfiguration { .. }
             | AppsError::InvalidTemplateFormat { .. }
+            | AppsError::InvalidRouterRule { .. }
+            | AppsError::StaticAssetsStorageFailed { .. }
             | AppsError::InvalidDeploymentHook => {
                 error!("Internal server error: {}", error);
                 StatusCode::INTERNAL_SERVER_ERROR
             }
         };
 
-        HttpApiProblem::with_title_and_type(status)
-            .detail(format!("{}", error))
-            .into()
+        let problem = HttpApiProblem::with_title_and_type(status).detail(format!("{}", error));
+        match &error {
+            AppsError::InsufficientCapacity { shortages, .. } => {
+                problem.value("shortages", shortages).into()
+            }
+            _ => problem.into(),
+        }
     }
 }
 
@@ -419,10 +1556,119 @@ impl<'r> FromRequest<'r> for RunOptions {
             }
         }
 
-        Outcome::Success(match (run_options, wait) {
-            (RunOptions::Sync, _) => RunOptions::Sync,
-            (RunOptions::Async { .. }, wait) => RunOptions::Async { wait },
-        })
+        Outcome::Success(match (run_options, wait) {
+            (RunOptions::Sync, _) => RunOptions::Sync,
+            (RunOptions::Async { .. }, wait) => RunOptions::Async { wait },
+        })
+    }
+}
+
+/// Request guard that forwards requests for version 2 of the REST API, see [`ApiV2`], so that the
+/// routes of version 1 only handle requests that do not ask for a version.
+pub struct ApiV1;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiV1 {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        if accepts_v2(request) {
+            Outcome::Forward(Status::NotAcceptable)
+        } else {
+            Outcome::Success(ApiV1)
+        }
+    }
+}
+
+/// Request guard that succeeds if the `Accept` header requests version 2 of the REST API, e.g.
+/// `Accept: application/vnd.prevant.v2+json`.
+pub struct ApiV2;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiV2 {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        if accepts_v2(request) {
+            Outcome::Success(ApiV2)
+        } else {
+            Outcome::Forward(Status::NotAcceptable)
+        }
+    }
+}
+
+fn accepts_v2(request: &Request<'_>) -> bool {
+    let v2 = MediaType::parse_flexible(v2::MEDIA_TYPE);
+    request.accept().map_or(false, |accept| {
+        accept
+            .iter()
+            .any(|media_type| Some(media_type) == v2.as_ref())
+    })
+}
+
+/// The token of the `Authorization: Bearer <token>` header that grants elevated permissions,
+/// e.g. the forced deletion of protected apps.
+pub struct BearerToken(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BearerToken {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        Outcome::Success(BearerToken(
+            request
+                .headers()
+                .get_one("Authorization")
+                .and_then(|header| header.strip_prefix("Bearer "))
+                .map(|token| token.trim().to_string()),
+        ))
+    }
+}
+
+/// The user that has been authenticated by a proxy in front of PREvant, e.g. by
+/// [oauth2-proxy](https://oauth2-proxy.github.io/oauth2-proxy/), through the header
+/// `X-Forwarded-User` or `X-Auth-Request-User`. The headers are ignored unless the request
+/// carries the secret of the trusted proxy, see [`crate::config::TrustedProxyConfig`].
+pub struct RequestUser(Option<String>);
+
+impl RequestUser {
+    /// The name of the user that has been authenticated by an upstream proxy.
+    pub fn name(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestUser {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        let user = ["X-Forwarded-User", "X-Auth-Request-User"]
+            .iter()
+            .find_map(|header| request.headers().get_one(header))
+            .map(str::trim)
+            .filter(|user| !user.is_empty())
+            .map(String::from);
+
+        let is_trusted = request
+            .rocket()
+            .state::<ConfigHandle>()
+            .and_then(|config| {
+                config
+                    .current()
+                    .trusted_proxy_config()
+                    .map(|proxy| proxy.trusts(request.headers().get_one(proxy.secret_header())))
+            })
+            .unwrap_or(false);
+        if !is_trusted && user.is_some() {
+            debug!(
+                "Ignoring the user header of the untrusted request to {}",
+                request.uri()
+            );
+            return Outcome::Success(RequestUser(None));
+        }
+
+        Outcome::Success(RequestUser(user))
     }
 }
 
@@ -525,6 +1771,147 @@ mod tests {
         }
     }
 
+    mod create_app_options {
+        use super::super::*;
+        use rocket::form::Form;
+
+        #[test]
+        fn parse_namespace_annotations() {
+            let options = Form::<CreateAppOptions>::parse(
+                "namespaceAnnotation=field.cattle.io%2FprojectId%3Dc-abc%3Ap-xyz&namespaceAnnotation=istio-injection%3Denabled",
+            )
+            .unwrap();
+
+            assert_eq!(
+                options.namespace_annotations(),
+                BTreeMap::from([
+                    (
+                        String::from("field.cattle.io/projectId"),
+                        String::from("c-abc:p-xyz")
+                    ),
+                    (String::from("istio-injection"), String::from("enabled")),
+                ])
+            );
+        }
+
+        #[test]
+        fn parse_without_namespace_annotations() {
+            let options = Form::<CreateAppOptions>::parse("replicateFrom=master").unwrap();
+
+            assert!(options.namespace_annotations().is_empty());
+        }
+
+        #[test]
+        fn parse_stacks() {
+            let options = Form::<CreateAppOptions>::parse("stacks=kafka&stacks=mongodb").unwrap();
+
+            assert_eq!(
+                options.stacks(Vec::new()),
+                vec![String::from("kafka"), String::from("mongodb")]
+            );
+        }
+
+        #[test]
+        fn merge_stacks_of_query_and_payload() {
+            let options = Form::<CreateAppOptions>::parse("stacks=kafka").unwrap();
+
+            assert_eq!(
+                options.stacks(vec![String::from("mongodb"), String::from("kafka")]),
+                vec![String::from("kafka"), String::from("mongodb")]
+            );
+        }
+
+        #[test]
+        fn reject_namespace_annotation_without_key() {
+            let options = Form::<CreateAppOptions>::parse("namespaceAnnotation=enabled");
+
+            assert!(options.is_err());
+        }
+    }
+
+    mod parse_deployment_payload {
+        use super::super::*;
+        use crate::sc;
+        use std::str::FromStr;
+
+        #[test]
+        fn parse_list_of_services() {
+            let payload = serde_json::from_str::<DeploymentPayload>(
+                r#"[{ "serviceName": "db", "image": "postgres" }]"#,
+            )
+            .unwrap();
+
+            assert_eq!(payload.services.len(), 1);
+            assert_eq!(payload.smoke_test, None);
+        }
+
+        #[test]
+        fn parse_services_with_smoke_test() {
+            let payload = serde_json::from_str::<DeploymentPayload>(
+                r#"{
+                    "services": [{ "serviceName": "db", "image": "postgres" }],
+                    "smokeTest": { "http": [{ "serviceName": "db", "path": "/health" }] }
+                }"#,
+            )
+            .unwrap();
+
+            assert_eq!(payload.services.len(), 1);
+            assert_eq!(
+                payload.smoke_test.unwrap().http_checks()[0].path(),
+                "/health"
+            );
+        }
+
+        #[test]
+        fn parse_services_with_stacks() {
+            let payload = serde_json::from_str::<DeploymentPayload>(
+                r#"{
+                    "services": [{ "serviceName": "api", "image": "api" }],
+                    "stacks": ["kafka"]
+                }"#,
+            )
+            .unwrap();
+
+            assert_eq!(payload.stacks, vec![String::from("kafka")]);
+        }
+
+        #[test]
+        fn parse_services_with_replication() {
+            let payload = serde_json::from_str::<DeploymentPayload>(
+                r#"{
+                    "services": [{ "serviceName": "api", "image": "api" }],
+                    "replicateFrom": "template",
+                    "replication": { "exclude": ["db"] }
+                }"#,
+            )
+            .unwrap();
+
+            assert_eq!(
+                payload.replicate_from,
+                Some(AppName::from_str("template").unwrap())
+            );
+            assert_eq!(payload.replication.apply_to(sc!("db")), None);
+        }
+
+        #[test]
+        fn parse_services_with_app_env() {
+            let payload = serde_json::from_str::<DeploymentPayload>(
+                r#"{
+                    "services": [{ "serviceName": "api", "image": "api" }],
+                    "env": { "SENTRY_ENVIRONMENT": "{{application.name}}" }
+                }"#,
+            )
+            .unwrap();
+
+            let env = payload.env.unwrap();
+            assert_eq!(
+                env.variable("SENTRY_ENVIRONMENT")
+                    .map(|variable| variable.value().unsecure()),
+                Some("{{application.name}}")
+            );
+        }
+    }
+
     mod url_rendering {
         use crate::apps::{AppsService, HostMetaCache};
         use crate::infrastructure::Dummy;
@@ -768,10 +2155,293 @@ mod tests {
         }
     }
 
+    mod placeholder_page {
+        use crate::apps::AppsService;
+        use crate::infrastructure::Dummy;
+        use crate::models::{AppName, AppStatusChangeId};
+        use crate::sc;
+        use rocket::http::Status;
+        use rocket::local::asynchronous::Client;
+        use std::str::FromStr;
+        use std::sync::Arc;
+
+        #[tokio::test]
+        async fn should_render_placeholder_of_starting_service(
+        ) -> Result<(), crate::apps::AppsServiceError> {
+            let infrastructure = Box::new(Dummy::new());
+            let apps = Arc::new(AppsService::new(Default::default(), infrastructure).unwrap());
+            apps.create_or_update(
+                &AppName::from_str("master").unwrap(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            )
+            .await?;
+
+            let rocket = rocket::build()
+                .manage(apps)
+                .mount("/api/apps", crate::apps::apps_routes());
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+
+            let response = client
+                .get("/api/apps/master/services/service-a/placeholder?status=502")
+                .dispatch()
+                .await;
+
+            assert_eq!(response.status(), Status::BadGateway);
+            let body = response.into_string().await.expect("valid response body");
+            assert!(body.contains(
+                "<strong>service-a</strong> of the preview <strong>master</strong> is starting"
+            ));
+            assert!(body.contains(r#"<meta http-equiv="refresh" content="5">"#));
+
+            Ok(())
+        }
+    }
+
+    mod share_tokens {
+        use crate::apps::AppsService;
+        use crate::config::{Config, ConfigHandle};
+        use crate::infrastructure::Dummy;
+        use crate::models::{AppName, AppStatusChangeId};
+        use crate::sc;
+        use rocket::http::{ContentType, Header, Status};
+        use rocket::local::asynchronous::Client;
+        use std::sync::Arc;
+
+        async fn client() -> Client {
+            let config = toml::de::from_str::<Config>(
+                r#"
+                [trustedProxy]
+                secret = 'proxy-secret'
+                "#,
+            )
+            .unwrap();
+            let apps = Arc::new(AppsService::new(config.clone(), Box::new(Dummy::new())).unwrap());
+            apps.create_or_update(
+                &AppName::master(),
+                &AppStatusChangeId::new(),
+                None,
+                &vec![sc!("service-a")],
+            )
+            .await
+            .unwrap();
+
+            let rocket = rocket::build()
+                .manage(ConfigHandle::new(config))
+                .manage(apps)
+                .mount("/api/apps", crate::apps::apps_routes());
+            Client::tracked(rocket).await.expect("valid rocket")
+        }
+
+        #[tokio::test]
+        async fn should_reject_unauthenticated_requests() {
+            let client = client().await;
+
+            let response = client
+                .post("/api/apps/master/share-tokens")
+                .header(ContentType::JSON)
+                .header(Header::new("X-Forwarded-User", "jdoe"))
+                .body("{}")
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Unauthorized);
+
+            let response = client
+                .get("/api/apps/master/share-tokens")
+                .header(ContentType::JSON)
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Unauthorized);
+
+            let response = client
+                .delete("/api/apps/master/share-tokens/6a2f41a3-c54c-fce8-32d2-0324e1c32e22")
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Unauthorized);
+        }
+
+        #[tokio::test]
+        async fn should_list_share_tokens_without_token_hash() {
+            let client = client().await;
+            let user = Header::new("X-Forwarded-User", "jdoe");
+            let secret = Header::new("X-Prevant-Proxy-Secret", "proxy-secret");
+
+            let response = client
+                .post("/api/apps/master/share-tokens")
+                .header(ContentType::JSON)
+                .header(user.clone())
+                .header(secret.clone())
+                .body("{}")
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+
+            let response = client
+                .get("/api/apps/master/share-tokens")
+                .header(ContentType::JSON)
+                .header(user)
+                .header(secret)
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            let share_tokens = response.into_json::<serde_json::Value>().await.unwrap();
+            assert_eq!(share_tokens.as_array().map(Vec::len), Some(1));
+            assert_eq!(share_tokens[0].get("tokenHash"), None);
+        }
+    }
+
+    mod api_versions {
+        use super::super::*;
+        use crate::apps::AppsService;
+        use crate::infrastructure::Dummy;
+        use rocket::http::{Accept, ContentType};
+        use rocket::local::asynchronous::Client;
+
+        async fn client() -> Client {
+            let infrastructure = Box::new(Dummy::new());
+            let apps = Arc::new(AppsService::new(Default::default(), infrastructure).unwrap());
+
+            let rocket = rocket::build()
+                .manage(apps)
+                .mount("/api/apps", routes![create_app, create_app_negotiated])
+                .mount("/api/v2/apps", apps_v2_routes());
+            Client::tracked(rocket).await.expect("valid rocket")
+        }
+
+        fn v2_payload() -> String {
+            serde_json::json!({
+                "services": [{
+                    "name": "wordpress",
+                    "image": "wordpress:6.4",
+                    "ports": [{ "port": 8080 }]
+                }]
+            })
+            .to_string()
+        }
+
+        #[tokio::test]
+        async fn deploy_with_versioned_route() {
+            let client = client().await;
+
+            let response = client
+                .post("/api/v2/apps/master")
+                .body(v2_payload())
+                .header(ContentType::JSON)
+                .dispatch()
+                .await;
+
+            assert_eq!(response.status(), Status::Ok);
+        }
+
+        #[tokio::test]
+        async fn deploy_with_negotiated_version() {
+            let client = client().await;
+
+            let response = client
+                .post("/api/apps/master")
+                .body(v2_payload())
+                .header(ContentType::JSON)
+                .header(Accept::new([
+                    MediaType::parse_flexible(v2::MEDIA_TYPE).unwrap()
+                ]))
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+
+            let response = client
+                .post("/api/apps/master")
+                .body(v2_payload())
+                .header(ContentType::JSON)
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::BadRequest);
+        }
+    }
+
+    mod yaml_payloads {
+        use super::super::*;
+        use crate::apps::AppsService;
+        use crate::infrastructure::Dummy;
+        use rocket::http::{Accept, ContentType};
+        use rocket::local::asynchronous::Client;
+
+        async fn client() -> Client {
+            let infrastructure = Box::new(Dummy::new());
+            let apps = Arc::new(AppsService::new(Default::default(), infrastructure).unwrap());
+
+            let rocket = rocket::build()
+                .manage(apps)
+                .mount("/api/apps", routes![create_app, create_app_from_yaml]);
+            Client::tracked(rocket).await.expect("valid rocket")
+        }
+
+        fn yaml() -> ContentType {
+            ContentType::new("application", "yaml")
+        }
+
+        #[tokio::test]
+        async fn deploy_yaml_payload() {
+            let client = client().await;
+
+            let response = client
+                .post("/api/apps/master")
+                .body(
+                    r#"
+- serviceName: db
+  image: postgres:16
+  env:
+    POSTGRES_PASSWORD: example
+"#,
+                )
+                .header(yaml())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+            let services: Vec<serde_json::Value> = response.into_json().await.unwrap();
+            assert_eq!(services[0]["name"], "db");
+        }
+
+        #[tokio::test]
+        async fn respond_with_yaml() {
+            let client = client().await;
+
+            let response = client
+                .post("/api/apps/master")
+                .body(r#"[{ "serviceName": "db", "image": "postgres:16" }]"#)
+                .header(ContentType::JSON)
+                .header(Accept::new([MediaType::new("application", "yaml")]))
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.content_type(), Some(yaml()));
+
+            let services: serde_yaml::Value =
+                serde_yaml::from_str(&response.into_string().await.unwrap()).unwrap();
+            assert_eq!(services[0]["name"], serde_yaml::Value::from("db"));
+        }
+
+        #[tokio::test]
+        async fn reject_invalid_yaml_payload() {
+            let client = client().await;
+
+            let response = client
+                .post("/api/apps/master")
+                .body("- serviceName: [db")
+                .header(yaml())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::BadRequest);
+        }
+    }
+
     mod http_api_error {
         use super::super::*;
         use crate::{
             apps::{AppsError, AppsService},
+            config::InfrastructureOperation,
             infrastructure::Dummy,
             registry::RegistryError,
         };
@@ -815,6 +2485,80 @@ mod tests {
             );
         }
 
+        #[tokio::test]
+        async fn infrastructure_error() {
+            let infrastructure = Box::new(
+                Dummy::new().with_failing_operations(vec![InfrastructureOperation::GetLogs]),
+            );
+            let apps = Arc::new(AppsService::new(Default::default(), infrastructure).unwrap());
+
+            let rocket = rocket::build()
+                .manage(apps)
+                .mount("/", routes![crate::apps::routes::logs]);
+
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let response = client
+                .get("/master/logs/db")
+                .header(rocket::http::Accept::Plain)
+                .dispatch()
+                .await;
+
+            assert_eq!(response.status(), Status::InternalServerError);
+        }
+
+        #[tokio::test]
+        async fn invalid_log_filter() {
+            let infrastructure = Box::new(Dummy::new());
+            let apps = Arc::new(AppsService::new(Default::default(), infrastructure).unwrap());
+
+            let rocket = rocket::build()
+                .manage(apps)
+                .mount("/", routes![crate::apps::routes::logs]);
+
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let response = client
+                .get("/master/logs/db?level=verbose")
+                .header(rocket::http::Accept::Plain)
+                .dispatch()
+                .await;
+
+            assert_eq!(response.status(), Status::BadRequest);
+        }
+
+        #[tokio::test]
+        async fn mails_of_app_without_mail_catcher() {
+            let infrastructure = Box::new(Dummy::new());
+            let apps = Arc::new(AppsService::new(Default::default(), infrastructure).unwrap());
+
+            let rocket = rocket::build()
+                .manage(apps)
+                .mount("/", routes![crate::apps::routes::mails]);
+
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let response = client
+                .get("/master/mail")
+                .header(rocket::http::Accept::JSON)
+                .dispatch()
+                .await;
+
+            assert_eq!(response.status(), Status::NotFound);
+        }
+
+        #[tokio::test]
+        async fn sbom_of_service_without_sbom() {
+            let infrastructure = Box::new(Dummy::new());
+            let apps = Arc::new(AppsService::new(Default::default(), infrastructure).unwrap());
+
+            let rocket = rocket::build()
+                .manage(apps)
+                .mount("/", routes![crate::apps::routes::sbom]);
+
+            let client = Client::tracked(rocket).await.expect("valid rocket");
+            let response = client.get("/master/services/db/sbom").dispatch().await;
+
+            assert_eq!(response.status(), Status::NotFound);
+        }
+
         #[tokio::test]
         async fn image_registry_authentication_error() {
             #[get("/")]
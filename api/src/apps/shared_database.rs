@@ -0,0 +1,126 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::SharedDatabaseConfig;
+use crate::models::AppName;
+use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::Client;
+
+/// Creates the database and the user of the app on the shared server unless they exist. The
+/// password of an existing user is reset so that it matches the injected variables.
+pub(super) async fn create_database(
+    config: &SharedDatabaseConfig,
+    app_name: &AppName,
+) -> Result<(), failure::Error> {
+    let database = config.database(app_name);
+    let client = connect(config).await?;
+
+    // The name consists of alphanumeric characters and underscores only and the password is a hex
+    // string, thus both can be embedded into the statements that do not support parameters.
+    client
+        .batch_execute(&format!(
+            r#"DO $$
+            BEGIN
+                IF EXISTS (SELECT FROM pg_roles WHERE rolname = '{name}') THEN
+                    ALTER ROLE "{name}" WITH LOGIN PASSWORD '{password}';
+                ELSE
+                    CREATE ROLE "{name}" WITH LOGIN PASSWORD '{password}';
+                END IF;
+            END
+            $$"#,
+            name = database.name(),
+            password = database.password().unsecure()
+        ))
+        .await?;
+
+    let exists = client
+        .query_opt(
+            "SELECT 1 FROM pg_database WHERE datname = $1",
+            &[&database.name()],
+        )
+        .await?
+        .is_some();
+    if !exists {
+        // CREATE DATABASE cannot run within the transaction of a DO block.
+        client
+            .batch_execute(&format!(
+                r#"CREATE DATABASE "{name}" OWNER "{name}""#,
+                name = database.name()
+            ))
+            .await?;
+        info!(
+            "Created the database {} of {} on the shared server",
+            database.name(),
+            app_name
+        );
+    }
+
+    Ok(())
+}
+
+/// Drops the database and the user of the app, terminating the open connections of the app.
+pub(super) async fn drop_database(
+    config: &SharedDatabaseConfig,
+    app_name: &AppName,
+) -> Result<(), failure::Error> {
+    let database = config.database(app_name);
+    let client = connect(config).await?;
+
+    client
+        .batch_execute(&format!(
+            r#"DROP DATABASE IF EXISTS "{name}" WITH (FORCE)"#,
+            name = database.name()
+        ))
+        .await?;
+    client
+        .batch_execute(&format!(
+            r#"DROP ROLE IF EXISTS "{name}""#,
+            name = database.name()
+        ))
+        .await?;
+
+    info!(
+        "Dropped the database {} of {} on the shared server",
+        database.name(),
+        app_name
+    );
+    Ok(())
+}
+
+/// Connects to the shared server with TLS if the server supports it. The `sslmode` parameter of
+/// the URL, e.g. `sslmode=require`, enforces TLS or `sslmode=disable` prevents it.
+async fn connect(config: &SharedDatabaseConfig) -> Result<Client, failure::Error> {
+    let tls = MakeTlsConnector::new(native_tls::TlsConnector::new()?);
+    let (client, connection) = tokio_postgres::connect(config.url().unsecure(), tls).await?;
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            warn!(
+                "The connection to the shared database server failed: {}",
+                err
+            );
+        }
+    });
+    Ok(client)
+}
@@ -0,0 +1,99 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2021 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::infrastructure::{wait_for, Condition, HttpCondition, Infrastructure};
+use crate::models::service::Service;
+use crate::models::{AppName, HttpCheck, SmokeTest, SmokeTestOutcome};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Runs the HTTP checks and afterwards the container of the `smoke_test` against the freshly
+/// deployed `services` of the app. HTTP checks will be retried until they pass or until the
+/// timeout of the smoke test has been reached.
+pub(super) async fn run_smoke_test(
+    infrastructure: &dyn Infrastructure,
+    app_name: &AppName,
+    services: &[Service],
+    smoke_test: &SmokeTest,
+) -> Result<SmokeTestOutcome, failure::Error> {
+    let deadline = Instant::now() + smoke_test.timeout();
+    let mut logs = String::new();
+
+    for check in smoke_test.http_checks() {
+        let url = match check_url(services, check) {
+            Ok(url) => url,
+            Err(message) => {
+                logs.push_str(&message);
+                return Ok(SmokeTestOutcome::new(false, logs));
+            }
+        };
+
+        let condition = HttpCondition::new(url, check.expected_status());
+        match wait_for(
+            &condition,
+            deadline.saturating_duration_since(Instant::now()),
+            Duration::from_secs(2),
+        )
+        .await
+        {
+            Ok(()) => {
+                logs.push_str(&condition.description());
+                logs.push('\n');
+            }
+            Err(err) => {
+                logs.push_str(&err.to_string());
+                return Ok(SmokeTestOutcome::new(false, logs));
+            }
+        }
+    }
+
+    if let Some(image) = smoke_test.image() {
+        let outcome = infrastructure
+            .run_smoke_test(
+                app_name,
+                image,
+                smoke_test.command(),
+                deadline.saturating_duration_since(Instant::now()),
+            )
+            .await?;
+        logs.push_str(outcome.logs());
+        return Ok(SmokeTestOutcome::new(outcome.passed(), logs));
+    }
+
+    Ok(SmokeTestOutcome::new(true, logs))
+}
+
+fn check_url(services: &[Service], check: &HttpCheck) -> Result<Url, String> {
+    let endpoint = services
+        .iter()
+        .find(|service| service.service_name() == check.service_name())
+        .ok_or_else(|| format!("Service {} is not part of the app", check.service_name()))?
+        .endpoint_url()
+        .ok_or_else(|| format!("Service {} has no endpoint", check.service_name()))?;
+
+    endpoint
+        .join(check.path().trim_start_matches('/'))
+        .map_err(|err| format!("Invalid path {}: {}", check.path(), err))
+}
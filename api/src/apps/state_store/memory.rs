@@ -0,0 +1,255 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::{StateStore, StateStoreError};
+use crate::models::{AppName, AuditEntry, DeploymentHistoryEntry, LifecycleHook, Sbom, ShareToken};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Keeps the state in the memory of PREvant. The state is lost on restarts.
+#[derive(Default)]
+pub struct InMemoryStateStore {
+    deployment_history: Mutex<HashMap<AppName, Vec<DeploymentHistoryEntry>>>,
+    soft_deleted_apps: Mutex<HashMap<AppName, DateTime<Utc>>>,
+    owners: Mutex<HashMap<AppName, String>>,
+    sboms: Mutex<HashMap<AppName, HashMap<String, Sbom>>>,
+    share_tokens: Mutex<HashMap<AppName, Vec<ShareToken>>>,
+    pre_destroy_hooks: Mutex<HashMap<AppName, Vec<LifecycleHook>>>,
+    audit_log: Mutex<Vec<AuditEntry>>,
+}
+
+#[async_trait]
+impl StateStore for InMemoryStateStore {
+    async fn push_deployment(
+        &self,
+        app_name: &AppName,
+        entry: &DeploymentHistoryEntry,
+        max_entries: usize,
+    ) -> Result<(), StateStoreError> {
+        let mut history = self.deployment_history.lock().unwrap();
+        let entries = history.entry(app_name.clone()).or_default();
+        entries.push(entry.clone());
+        if entries.len() > max_entries {
+            entries.remove(0);
+        }
+        Ok(())
+    }
+
+    async fn deployment_history(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<DeploymentHistoryEntry>, StateStoreError> {
+        let history = self.deployment_history.lock().unwrap();
+        Ok(history.get(app_name).cloned().unwrap_or_default())
+    }
+
+    async fn remove_deployment_history(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.deployment_history.lock().unwrap().remove(app_name);
+        Ok(())
+    }
+
+    async fn soft_delete(
+        &self,
+        app_name: &AppName,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), StateStoreError> {
+        self.soft_deleted_apps
+            .lock()
+            .unwrap()
+            .insert(app_name.clone(), expires_at);
+        Ok(())
+    }
+
+    async fn remove_soft_deletion(&self, app_name: &AppName) -> Result<bool, StateStoreError> {
+        Ok(self
+            .soft_deleted_apps
+            .lock()
+            .unwrap()
+            .remove(app_name)
+            .is_some())
+    }
+
+    async fn soft_deleted_apps(&self) -> Result<Vec<(AppName, DateTime<Utc>)>, StateStoreError> {
+        Ok(self
+            .soft_deleted_apps
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(app_name, expires_at)| (app_name.clone(), *expires_at))
+            .collect())
+    }
+
+    async fn set_owner(&self, app_name: &AppName, owner: &str) -> Result<(), StateStoreError> {
+        self.owners
+            .lock()
+            .unwrap()
+            .insert(app_name.clone(), owner.to_string());
+        Ok(())
+    }
+
+    async fn remove_owner(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.owners.lock().unwrap().remove(app_name);
+        Ok(())
+    }
+
+    async fn owners(&self) -> Result<HashMap<AppName, String>, StateStoreError> {
+        Ok(self.owners.lock().unwrap().clone())
+    }
+
+    async fn set_sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        sbom: &Sbom,
+    ) -> Result<(), StateStoreError> {
+        self.sboms
+            .lock()
+            .unwrap()
+            .entry(app_name.clone())
+            .or_default()
+            .insert(service_name.to_string(), sbom.clone());
+        Ok(())
+    }
+
+    async fn sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Option<Sbom>, StateStoreError> {
+        Ok(self
+            .sboms
+            .lock()
+            .unwrap()
+            .get(app_name)
+            .and_then(|sboms| sboms.get(service_name))
+            .cloned())
+    }
+
+    async fn remove_sboms(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.sboms.lock().unwrap().remove(app_name);
+        Ok(())
+    }
+
+    async fn add_share_token(
+        &self,
+        app_name: &AppName,
+        share_token: &ShareToken,
+    ) -> Result<(), StateStoreError> {
+        self.share_tokens
+            .lock()
+            .unwrap()
+            .entry(app_name.clone())
+            .or_default()
+            .push(share_token.clone());
+        Ok(())
+    }
+
+    async fn share_tokens(&self, app_name: &AppName) -> Result<Vec<ShareToken>, StateStoreError> {
+        Ok(self
+            .share_tokens
+            .lock()
+            .unwrap()
+            .get(app_name)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn remove_share_token(
+        &self,
+        app_name: &AppName,
+        id: &Uuid,
+    ) -> Result<bool, StateStoreError> {
+        let mut share_tokens = self.share_tokens.lock().unwrap();
+        let Some(tokens) = share_tokens.get_mut(app_name) else {
+            return Ok(false);
+        };
+        let len = tokens.len();
+        tokens.retain(|token| token.id() != id);
+        Ok(tokens.len() < len)
+    }
+
+    async fn remove_share_tokens(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.share_tokens.lock().unwrap().remove(app_name);
+        Ok(())
+    }
+
+    async fn set_pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+        hooks: &[LifecycleHook],
+    ) -> Result<(), StateStoreError> {
+        self.pre_destroy_hooks
+            .lock()
+            .unwrap()
+            .insert(app_name.clone(), hooks.to_vec());
+        Ok(())
+    }
+
+    async fn pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<LifecycleHook>, StateStoreError> {
+        Ok(self
+            .pre_destroy_hooks
+            .lock()
+            .unwrap()
+            .get(app_name)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn remove_pre_destroy_hooks(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.pre_destroy_hooks.lock().unwrap().remove(app_name);
+        Ok(())
+    }
+
+    async fn append_audit_entry(&self, entry: &AuditEntry) -> Result<(), StateStoreError> {
+        self.audit_log.lock().unwrap().push(entry.clone());
+        Ok(())
+    }
+
+    async fn audit_log(&self) -> Result<Vec<AuditEntry>, StateStoreError> {
+        Ok(self.audit_log.lock().unwrap().clone())
+    }
+
+    async fn check(&self) -> Result<(), StateStoreError> {
+        if self.deployment_history.is_poisoned()
+            || self.soft_deleted_apps.is_poisoned()
+            || self.owners.is_poisoned()
+            || self.sboms.is_poisoned()
+            || self.share_tokens.is_poisoned()
+            || self.pre_destroy_hooks.is_poisoned()
+            || self.audit_log.is_poisoned()
+        {
+            return Err(StateStoreError::InvalidData {
+                internal_message: String::from("A panic occurred while the state was modified"),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::StateConfig;
+use crate::models::{AppName, AuditEntry, DeploymentHistoryEntry, LifecycleHook, Sbom, ShareToken};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+pub(super) use memory::InMemoryStateStore;
+use postgres_store::PostgresStateStore;
+use redis_store::RedisStateStore;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+mod memory;
+mod postgres_store;
+mod redis_store;
+
+/// Keeps the state of the apps that is not part of the infrastructure, i.e. the deployment
+/// history, the apps that have been deleted softly, the owners of the apps, the SBOMs of their
+/// services, the tokens to share them, their pre-destroy hooks, and the audit log. Stores that
+/// are backed by a database share the state among multiple replicas of PREvant and keep it across
+/// restarts.
+#[async_trait]
+pub(super) trait StateStore: Send + Sync {
+    /// Appends the entry to the deployment history of the app and drops the oldest entries
+    /// beyond `max_entries`.
+    async fn push_deployment(
+        &self,
+        app_name: &AppName,
+        entry: &DeploymentHistoryEntry,
+        max_entries: usize,
+    ) -> Result<(), StateStoreError>;
+
+    /// Returns the deployment history of the app, starting with the oldest entry.
+    async fn deployment_history(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<DeploymentHistoryEntry>, StateStoreError>;
+
+    async fn remove_deployment_history(&self, app_name: &AppName) -> Result<(), StateStoreError>;
+
+    /// Marks the app as deleted softly until `expires_at`.
+    async fn soft_delete(
+        &self,
+        app_name: &AppName,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), StateStoreError>;
+
+    /// Removes the mark of a soft deletion and returns `true` if the app has been deleted softly.
+    async fn remove_soft_deletion(&self, app_name: &AppName) -> Result<bool, StateStoreError>;
+
+    /// Returns the apps that have been deleted softly and when their grace period expires.
+    async fn soft_deleted_apps(&self) -> Result<Vec<(AppName, DateTime<Utc>)>, StateStoreError>;
+
+    /// Records the user that owns the app and replaces the previous owner.
+    async fn set_owner(&self, app_name: &AppName, owner: &str) -> Result<(), StateStoreError>;
+
+    async fn remove_owner(&self, app_name: &AppName) -> Result<(), StateStoreError>;
+
+    /// Returns the owners of all apps that have an owner.
+    async fn owners(&self) -> Result<HashMap<AppName, String>, StateStoreError>;
+
+    /// Stores the SBOM of the image of the service and replaces the SBOM of a previous deployment.
+    async fn set_sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        sbom: &Sbom,
+    ) -> Result<(), StateStoreError>;
+
+    async fn sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Option<Sbom>, StateStoreError>;
+
+    async fn remove_sboms(&self, app_name: &AppName) -> Result<(), StateStoreError>;
+
+    async fn add_share_token(
+        &self,
+        app_name: &AppName,
+        share_token: &ShareToken,
+    ) -> Result<(), StateStoreError>;
+
+    async fn share_tokens(&self, app_name: &AppName) -> Result<Vec<ShareToken>, StateStoreError>;
+
+    /// Revokes the share token and returns `true` if the app had a share token with the id.
+    async fn remove_share_token(
+        &self,
+        app_name: &AppName,
+        id: &Uuid,
+    ) -> Result<bool, StateStoreError>;
+
+    async fn remove_share_tokens(&self, app_name: &AppName) -> Result<(), StateStoreError>;
+
+    /// Stores the hooks that run before the app is deleted and replaces the hooks of a previous
+    /// deployment.
+    async fn set_pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+        hooks: &[LifecycleHook],
+    ) -> Result<(), StateStoreError>;
+
+    async fn pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<LifecycleHook>, StateStoreError>;
+
+    async fn remove_pre_destroy_hooks(&self, app_name: &AppName) -> Result<(), StateStoreError>;
+
+    /// Appends the entry to the audit log. Entries of the audit log cannot be changed or removed.
+    async fn append_audit_entry(&self, entry: &AuditEntry) -> Result<(), StateStoreError>;
+
+    /// Returns the audit log, starting with the oldest entry.
+    async fn audit_log(&self) -> Result<Vec<AuditEntry>, StateStoreError>;
+
+    /// Verifies that the store is able to read and write the state.
+    async fn check(&self) -> Result<(), StateStoreError>;
+}
+
+#[derive(Clone, Debug, Fail, PartialEq)]
+pub enum StateStoreError {
+    #[fail(display = "The state store is unavailable: {}", internal_message)]
+    Unavailable { internal_message: String },
+    #[fail(
+        display = "The state store contains invalid data: {}",
+        internal_message
+    )]
+    InvalidData { internal_message: String },
+    #[fail(
+        display = "The schema version {} of the state store is newer than the supported version {}.",
+        found, supported
+    )]
+    UnsupportedSchemaVersion { found: i64, supported: i64 },
+}
+
+pub(super) fn create_state_store(config: &StateConfig) -> Box<dyn StateStore> {
+    match config {
+        StateConfig::InMemory => Box::new(InMemoryStateStore::default()),
+        StateConfig::Redis(config) => {
+            log::info!("Keeping the state of the apps in Redis");
+            Box::new(RedisStateStore::new(config.clone()))
+        }
+        StateConfig::Postgres(config) => {
+            log::info!("Keeping the state of the apps in Postgres");
+            Box::new(PostgresStateStore::new(config.clone()))
+        }
+    }
+}
+
+impl From<serde_json::Error> for StateStoreError {
+    fn from(err: serde_json::Error) -> Self {
+        StateStoreError::InvalidData {
+            internal_message: err.to_string(),
+        }
+    }
+}
+
+impl From<crate::models::AppNameError> for StateStoreError {
+    fn from(err: crate::models::AppNameError) -> Self {
+        StateStoreError::InvalidData {
+            internal_message: err.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DeploymentDiff, LifecycleHook, SbomFormat};
+    use std::str::FromStr;
+
+    /// Exercises a store that has to start empty, e.g. a store that is backed by a fresh
+    /// database.
+    pub(super) async fn should_keep_the_state_of_apps(
+        store: &dyn StateStore,
+    ) -> Result<(), StateStoreError> {
+        let app_name = AppName::from_str("state-store-test").unwrap();
+        store.check().await?;
+
+        for _ in 0..3 {
+            store
+                .push_deployment(
+                    &app_name,
+                    &DeploymentHistoryEntry::new(DeploymentDiff::default(), Vec::new()),
+                    2,
+                )
+                .await?;
+        }
+        assert_eq!(store.deployment_history(&app_name).await?.len(), 2);
+        store.remove_deployment_history(&app_name).await?;
+        assert!(store.deployment_history(&app_name).await?.is_empty());
+
+        let expires_at = Utc::now();
+        store.soft_delete(&app_name, expires_at).await?;
+        let soft_deleted_apps = store.soft_deleted_apps().await?;
+        assert_eq!(soft_deleted_apps.len(), 1);
+        assert_eq!(soft_deleted_apps[0].0, app_name);
+        assert!(store.remove_soft_deletion(&app_name).await?);
+        assert!(!store.remove_soft_deletion(&app_name).await?);
+
+        store.set_owner(&app_name, "alice").await?;
+        store.set_owner(&app_name, "bob").await?;
+        assert_eq!(
+            store.owners().await?.get(&app_name),
+            Some(&String::from("bob"))
+        );
+        store.remove_owner(&app_name).await?;
+        assert!(!store.owners().await?.contains_key(&app_name));
+
+        let sbom = Sbom::new(
+            String::from("docker.io/library/nginx:1.25"),
+            None,
+            SbomFormat::CycloneDxJson,
+            serde_json::json!({ "bomFormat": "CycloneDX" }),
+        );
+        store.set_sbom(&app_name, "nginx", &sbom).await?;
+        assert_eq!(store.sbom(&app_name, "nginx").await?, Some(sbom));
+        assert_eq!(store.sbom(&app_name, "db").await?, None);
+        store.remove_sboms(&app_name).await?;
+        assert_eq!(store.sbom(&app_name, "nginx").await?, None);
+
+        let (share_token, _) = ShareToken::new(None, Utc::now());
+        let (other_share_token, _) = ShareToken::new(Some(String::from("customer")), Utc::now());
+        store.add_share_token(&app_name, &share_token).await?;
+        store.add_share_token(&app_name, &other_share_token).await?;
+        assert_eq!(store.share_tokens(&app_name).await?.len(), 2);
+        assert!(
+            store
+                .remove_share_token(&app_name, share_token.id())
+                .await?
+        );
+        assert!(
+            !store
+                .remove_share_token(&app_name, share_token.id())
+                .await?
+        );
+        assert_eq!(
+            store.share_tokens(&app_name).await?,
+            vec![other_share_token]
+        );
+        store.remove_share_tokens(&app_name).await?;
+        assert!(store.share_tokens(&app_name).await?.is_empty());
+
+        let hooks = vec![serde_json::from_value::<LifecycleHook>(
+            serde_json::json!({
+                "serviceName": "backend",
+                "command": ["./deregister-license.sh"]
+            }),
+        )?];
+        store.set_pre_destroy_hooks(&app_name, &hooks).await?;
+        store.set_pre_destroy_hooks(&app_name, &hooks).await?;
+        assert_eq!(store.pre_destroy_hooks(&app_name).await?, hooks);
+        store.remove_pre_destroy_hooks(&app_name).await?;
+        assert!(store.pre_destroy_hooks(&app_name).await?.is_empty());
+
+        let entry = AuditEntry::new(
+            Some(String::from("alice")),
+            String::from("DELETE"),
+            format!("/api/apps/{app_name}"),
+            202,
+            None,
+        );
+        store.append_audit_entry(&entry).await?;
+        assert_eq!(store.audit_log().await?.last(), Some(&entry));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_keep_the_state_of_apps_in_memory() -> Result<(), StateStoreError> {
+        should_keep_the_state_of_apps(&InMemoryStateStore::default()).await
+    }
+}
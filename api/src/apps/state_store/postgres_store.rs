@@ -0,0 +1,540 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::{StateStore, StateStoreError};
+use crate::config::PostgresStateConfig;
+use crate::models::{
+    AppName, AuditEntry, DeploymentHistoryEntry, LifecycleHook, Sbom, ShareToken, StoredShareToken,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::warn;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, NoTls};
+use uuid::Uuid;
+
+/// The schema migrations in the order they have to be applied. The version of a migration is its
+/// position in this list, starting with one. Released migrations must not be changed.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE deployment_history (
+        id BIGSERIAL PRIMARY KEY,
+        app_name TEXT NOT NULL,
+        entry JSONB NOT NULL
+    );
+    CREATE INDEX deployment_history_app_name ON deployment_history (app_name, id);
+    CREATE TABLE soft_deleted_apps (
+        app_name TEXT PRIMARY KEY,
+        expires_at TIMESTAMPTZ NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE app_owners (
+        app_name TEXT PRIMARY KEY,
+        owner TEXT NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE app_sboms (
+        app_name TEXT NOT NULL,
+        service_name TEXT NOT NULL,
+        sbom JSONB NOT NULL,
+        PRIMARY KEY (app_name, service_name)
+    );
+"#,
+    r#"
+    CREATE TABLE app_share_tokens (
+        app_name TEXT NOT NULL,
+        id TEXT NOT NULL,
+        share_token JSONB NOT NULL,
+        PRIMARY KEY (app_name, id)
+    );
+"#,
+    r#"
+    CREATE TABLE audit_log (
+        id BIGSERIAL PRIMARY KEY,
+        entry JSONB NOT NULL
+    );
+    CREATE RULE audit_log_no_update AS ON UPDATE TO audit_log DO INSTEAD NOTHING;
+    CREATE RULE audit_log_no_delete AS ON DELETE TO audit_log DO INSTEAD NOTHING;
+"#,
+    r#"
+    CREATE TABLE app_pre_destroy_hooks (
+        app_name TEXT PRIMARY KEY,
+        hooks JSONB NOT NULL
+    );
+"#,
+];
+
+/// Keeps the state in a Postgres database and migrates its schema on the first connection.
+pub struct PostgresStateStore {
+    config: PostgresStateConfig,
+    client: Mutex<Option<Arc<Client>>>,
+}
+
+impl PostgresStateStore {
+    pub fn new(config: PostgresStateConfig) -> Self {
+        Self {
+            config,
+            client: Mutex::new(None),
+        }
+    }
+
+    /// Returns the client of the current connection or connects again if there is no open
+    /// connection.
+    async fn client(&self) -> Result<Arc<Client>, StateStoreError> {
+        let mut client = self.client.lock().await;
+        if let Some(client) = client.as_ref().filter(|client| !client.is_closed()) {
+            return Ok(client.clone());
+        }
+
+        let (mut new_client, connection) =
+            tokio_postgres::connect(self.config.url().unsecure(), NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                warn!("The connection to the Postgres state store failed: {}", err);
+            }
+        });
+        migrate(&mut new_client).await?;
+
+        let new_client = Arc::new(new_client);
+        *client = Some(new_client.clone());
+        Ok(new_client)
+    }
+}
+
+async fn migrate(client: &mut Client) -> Result<(), StateStoreError> {
+    let transaction = client.transaction().await?;
+
+    // Replicas of PREvant that start at the same time must not apply the migrations twice.
+    transaction
+        .execute("SELECT pg_advisory_xact_lock(7370)", &[])
+        .await?;
+    transaction
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS prevant_schema_migrations (
+                version BIGINT PRIMARY KEY,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .await?;
+
+    let current_version: i64 = transaction
+        .query_one(
+            "SELECT COALESCE(MAX(version), 0) FROM prevant_schema_migrations",
+            &[],
+        )
+        .await?
+        .get(0);
+    let supported_version = MIGRATIONS.len() as i64;
+    if current_version > supported_version {
+        return Err(StateStoreError::UnsupportedSchemaVersion {
+            found: current_version,
+            supported: supported_version,
+        });
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current_version as usize) {
+        let version = index as i64 + 1;
+        log::info!("Migrating the Postgres state store to version {}", version);
+        transaction.batch_execute(migration).await?;
+        transaction
+            .execute(
+                "INSERT INTO prevant_schema_migrations (version) VALUES ($1)",
+                &[&version],
+            )
+            .await?;
+    }
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[async_trait]
+impl StateStore for PostgresStateStore {
+    async fn push_deployment(
+        &self,
+        app_name: &AppName,
+        entry: &DeploymentHistoryEntry,
+        max_entries: usize,
+    ) -> Result<(), StateStoreError> {
+        // The statements of the query share a snapshot, thus, the deletion keeps the newest
+        // entries besides the inserted one.
+        self.client()
+            .await?
+            .execute(
+                "WITH inserted AS (
+                    INSERT INTO deployment_history (app_name, entry) VALUES ($1, $2)
+                )
+                DELETE FROM deployment_history WHERE app_name = $1 AND id NOT IN (
+                    SELECT id FROM deployment_history WHERE app_name = $1 ORDER BY id DESC LIMIT $3
+                )",
+                &[
+                    &app_name.to_string(),
+                    &serde_json::to_value(entry)?,
+                    &(max_entries.saturating_sub(1) as i64),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn deployment_history(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<DeploymentHistoryEntry>, StateStoreError> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                "SELECT entry FROM deployment_history WHERE app_name = $1 ORDER BY id",
+                &[&app_name.to_string()],
+            )
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                serde_json::from_value(row.get::<_, serde_json::Value>(0))
+                    .map_err(StateStoreError::from)
+            })
+            .collect()
+    }
+
+    async fn remove_deployment_history(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "DELETE FROM deployment_history WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn soft_delete(
+        &self,
+        app_name: &AppName,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "INSERT INTO soft_deleted_apps (app_name, expires_at) VALUES ($1, $2)
+                ON CONFLICT (app_name) DO UPDATE SET expires_at = EXCLUDED.expires_at",
+                &[&app_name.to_string(), &expires_at],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_soft_deletion(&self, app_name: &AppName) -> Result<bool, StateStoreError> {
+        let removed = self
+            .client()
+            .await?
+            .execute(
+                "DELETE FROM soft_deleted_apps WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+
+    async fn soft_deleted_apps(&self) -> Result<Vec<(AppName, DateTime<Utc>)>, StateStoreError> {
+        let rows = self
+            .client()
+            .await?
+            .query("SELECT app_name, expires_at FROM soft_deleted_apps", &[])
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok((
+                    AppName::from_str(row.get::<_, &str>(0))?,
+                    row.get::<_, DateTime<Utc>>(1),
+                ))
+            })
+            .collect()
+    }
+
+    async fn set_owner(&self, app_name: &AppName, owner: &str) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "INSERT INTO app_owners (app_name, owner) VALUES ($1, $2)
+                ON CONFLICT (app_name) DO UPDATE SET owner = EXCLUDED.owner",
+                &[&app_name.to_string(), &owner],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_owner(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "DELETE FROM app_owners WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn owners(&self) -> Result<HashMap<AppName, String>, StateStoreError> {
+        let rows = self
+            .client()
+            .await?
+            .query("SELECT app_name, owner FROM app_owners", &[])
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok((
+                    AppName::from_str(row.get::<_, &str>(0))?,
+                    row.get::<_, String>(1),
+                ))
+            })
+            .collect()
+    }
+
+    async fn set_sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        sbom: &Sbom,
+    ) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "INSERT INTO app_sboms (app_name, service_name, sbom) VALUES ($1, $2, $3)
+                ON CONFLICT (app_name, service_name) DO UPDATE SET sbom = EXCLUDED.sbom",
+                &[
+                    &app_name.to_string(),
+                    &service_name,
+                    &serde_json::to_value(sbom)?,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Option<Sbom>, StateStoreError> {
+        let row = self
+            .client()
+            .await?
+            .query_opt(
+                "SELECT sbom FROM app_sboms WHERE app_name = $1 AND service_name = $2",
+                &[&app_name.to_string(), &service_name],
+            )
+            .await?;
+
+        row.map(|row| {
+            serde_json::from_value(row.get::<_, serde_json::Value>(0))
+                .map_err(StateStoreError::from)
+        })
+        .transpose()
+    }
+
+    async fn remove_sboms(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "DELETE FROM app_sboms WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn add_share_token(
+        &self,
+        app_name: &AppName,
+        share_token: &ShareToken,
+    ) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "INSERT INTO app_share_tokens (app_name, id, share_token) VALUES ($1, $2, $3)",
+                &[
+                    &app_name.to_string(),
+                    &share_token.id().to_string(),
+                    &serde_json::to_value(StoredShareToken::from(share_token))?,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn share_tokens(&self, app_name: &AppName) -> Result<Vec<ShareToken>, StateStoreError> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                "SELECT share_token FROM app_share_tokens WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                serde_json::from_value::<StoredShareToken>(row.get::<_, serde_json::Value>(0))
+                    .map(ShareToken::from)
+                    .map_err(StateStoreError::from)
+            })
+            .collect()
+    }
+
+    async fn remove_share_token(
+        &self,
+        app_name: &AppName,
+        id: &Uuid,
+    ) -> Result<bool, StateStoreError> {
+        let removed = self
+            .client()
+            .await?
+            .execute(
+                "DELETE FROM app_share_tokens WHERE app_name = $1 AND id = $2",
+                &[&app_name.to_string(), &id.to_string()],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+
+    async fn remove_share_tokens(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "DELETE FROM app_share_tokens WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn set_pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+        hooks: &[LifecycleHook],
+    ) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "INSERT INTO app_pre_destroy_hooks (app_name, hooks) VALUES ($1, $2)
+                ON CONFLICT (app_name) DO UPDATE SET hooks = EXCLUDED.hooks",
+                &[&app_name.to_string(), &serde_json::to_value(hooks)?],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<LifecycleHook>, StateStoreError> {
+        let row = self
+            .client()
+            .await?
+            .query_opt(
+                "SELECT hooks FROM app_pre_destroy_hooks WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+
+        match row {
+            Some(row) => Ok(serde_json::from_value(row.get::<_, serde_json::Value>(0))?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn remove_pre_destroy_hooks(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "DELETE FROM app_pre_destroy_hooks WHERE app_name = $1",
+                &[&app_name.to_string()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn append_audit_entry(&self, entry: &AuditEntry) -> Result<(), StateStoreError> {
+        self.client()
+            .await?
+            .execute(
+                "INSERT INTO audit_log (entry) VALUES ($1)",
+                &[&serde_json::to_value(entry)?],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn audit_log(&self) -> Result<Vec<AuditEntry>, StateStoreError> {
+        let rows = self
+            .client()
+            .await?
+            .query("SELECT entry FROM audit_log ORDER BY id", &[])
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                serde_json::from_value(row.get::<_, serde_json::Value>(0))
+                    .map_err(StateStoreError::from)
+            })
+            .collect()
+    }
+
+    async fn check(&self) -> Result<(), StateStoreError> {
+        self.client().await?.execute("SELECT 1", &[]).await?;
+        Ok(())
+    }
+}
+
+impl From<tokio_postgres::Error> for StateStoreError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        StateStoreError::Unavailable {
+            internal_message: err.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires an empty Postgres database at PREVANT_TEST_POSTGRES_URL"]
+    async fn should_keep_the_state_of_apps_in_postgres() -> Result<(), StateStoreError> {
+        let url = std::env::var("PREVANT_TEST_POSTGRES_URL").expect("PREVANT_TEST_POSTGRES_URL");
+        let config = toml::from_str::<PostgresStateConfig>(&format!("url = '{url}'")).unwrap();
+
+        super::super::tests::should_keep_the_state_of_apps(&PostgresStateStore::new(config)).await
+    }
+}
@@ -0,0 +1,418 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::{StateStore, StateStoreError};
+use crate::config::RedisStateConfig;
+use crate::models::{
+    AppName, AuditEntry, DeploymentHistoryEntry, LifecycleHook, Sbom, ShareToken, StoredShareToken,
+};
+use ::redis::aio::ConnectionManager;
+use ::redis::{AsyncCommands, Client, RedisError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+/// The version of the key layout that is written by this version of PREvant.
+const SCHEMA_VERSION: i64 = 1;
+
+/// Keeps the state in Redis:
+/// - `<prefix>history:<app>` is a list of the JSON encoded history entries of the app,
+/// - `<prefix>soft-deleted-apps` is a hash of the apps and the expiry of their grace periods,
+/// - `<prefix>owners` is a hash of the apps and their owners,
+/// - `<prefix>sboms:<app>` is a hash of the services of the app and their JSON encoded SBOMs,
+/// - `<prefix>share-tokens:<app>` is a hash of the ids and the JSON encoded share tokens of the
+///   app,
+/// - `<prefix>pre-destroy-hooks` is a hash of the apps and their JSON encoded pre-destroy hooks
+///   and
+/// - `<prefix>audit-log` is a list of the JSON encoded entries of the audit log.
+pub struct RedisStateStore {
+    config: RedisStateConfig,
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisStateStore {
+    pub fn new(config: RedisStateConfig) -> Self {
+        Self {
+            config,
+            connection: OnceCell::new(),
+        }
+    }
+
+    /// Connects lazily to Redis. The connection manager reconnects on its own if the connection
+    /// breaks later on.
+    async fn connection(&self) -> Result<ConnectionManager, StateStoreError> {
+        self.connection
+            .get_or_try_init(|| async {
+                let client = Client::open(self.config.url().unsecure())?;
+                let mut connection = ConnectionManager::new(client).await?;
+                self.migrate(&mut connection).await?;
+                Ok::<_, StateStoreError>(connection)
+            })
+            .await
+            .cloned()
+    }
+
+    async fn migrate(&self, connection: &mut ConnectionManager) -> Result<(), StateStoreError> {
+        let key = self.key("schema-version");
+        let version: Option<i64> = connection.get(&key).await?;
+        match version {
+            None => {
+                let _: bool = connection.set_nx(&key, SCHEMA_VERSION).await?;
+                Ok(())
+            }
+            Some(version) if version > SCHEMA_VERSION => {
+                Err(StateStoreError::UnsupportedSchemaVersion {
+                    found: version,
+                    supported: SCHEMA_VERSION,
+                })
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{}", self.config.key_prefix(), name)
+    }
+
+    fn history_key(&self, app_name: &AppName) -> String {
+        self.key(&format!("history:{app_name}"))
+    }
+
+    fn sboms_key(&self, app_name: &AppName) -> String {
+        self.key(&format!("sboms:{app_name}"))
+    }
+
+    fn share_tokens_key(&self, app_name: &AppName) -> String {
+        self.key(&format!("share-tokens:{app_name}"))
+    }
+}
+
+#[async_trait]
+impl StateStore for RedisStateStore {
+    async fn push_deployment(
+        &self,
+        app_name: &AppName,
+        entry: &DeploymentHistoryEntry,
+        max_entries: usize,
+    ) -> Result<(), StateStoreError> {
+        let key = self.history_key(app_name);
+        ::redis::pipe()
+            .atomic()
+            .rpush(&key, serde_json::to_string(entry)?)
+            .ignore()
+            .ltrim(&key, -(max_entries as isize), -1)
+            .ignore()
+            .query_async::<_, ()>(&mut self.connection().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn deployment_history(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<DeploymentHistoryEntry>, StateStoreError> {
+        let entries: Vec<String> = self
+            .connection()
+            .await?
+            .lrange(self.history_key(app_name), 0, -1)
+            .await?;
+
+        entries
+            .iter()
+            .map(|entry| serde_json::from_str(entry).map_err(StateStoreError::from))
+            .collect()
+    }
+
+    async fn remove_deployment_history(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .del::<_, ()>(self.history_key(app_name))
+            .await?;
+        Ok(())
+    }
+
+    async fn soft_delete(
+        &self,
+        app_name: &AppName,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .hset::<_, _, _, ()>(
+                self.key("soft-deleted-apps"),
+                app_name.to_string(),
+                expires_at.to_rfc3339(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_soft_deletion(&self, app_name: &AppName) -> Result<bool, StateStoreError> {
+        let removed: i64 = self
+            .connection()
+            .await?
+            .hdel(self.key("soft-deleted-apps"), app_name.to_string())
+            .await?;
+        Ok(removed > 0)
+    }
+
+    async fn soft_deleted_apps(&self) -> Result<Vec<(AppName, DateTime<Utc>)>, StateStoreError> {
+        let apps: HashMap<String, String> = self
+            .connection()
+            .await?
+            .hgetall(self.key("soft-deleted-apps"))
+            .await?;
+
+        apps.into_iter()
+            .map(|(app_name, expires_at)| {
+                let expires_at = DateTime::parse_from_rfc3339(&expires_at).map_err(|err| {
+                    StateStoreError::InvalidData {
+                        internal_message: err.to_string(),
+                    }
+                })?;
+                Ok((
+                    AppName::from_str(&app_name)?,
+                    expires_at.with_timezone(&Utc),
+                ))
+            })
+            .collect()
+    }
+
+    async fn set_owner(&self, app_name: &AppName, owner: &str) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .hset::<_, _, _, ()>(self.key("owners"), app_name.to_string(), owner)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_owner(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .hdel::<_, _, ()>(self.key("owners"), app_name.to_string())
+            .await?;
+        Ok(())
+    }
+
+    async fn owners(&self) -> Result<HashMap<AppName, String>, StateStoreError> {
+        let owners: HashMap<String, String> =
+            self.connection().await?.hgetall(self.key("owners")).await?;
+
+        owners
+            .into_iter()
+            .map(|(app_name, owner)| Ok((AppName::from_str(&app_name)?, owner)))
+            .collect()
+    }
+
+    async fn set_sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        sbom: &Sbom,
+    ) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .hset::<_, _, _, ()>(
+                self.sboms_key(app_name),
+                service_name,
+                serde_json::to_string(sbom)?,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn sbom(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Option<Sbom>, StateStoreError> {
+        let sbom: Option<String> = self
+            .connection()
+            .await?
+            .hget(self.sboms_key(app_name), service_name)
+            .await?;
+
+        sbom.map(|sbom| serde_json::from_str(&sbom).map_err(StateStoreError::from))
+            .transpose()
+    }
+
+    async fn remove_sboms(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .del::<_, ()>(self.sboms_key(app_name))
+            .await?;
+        Ok(())
+    }
+
+    async fn add_share_token(
+        &self,
+        app_name: &AppName,
+        share_token: &ShareToken,
+    ) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .hset::<_, _, _, ()>(
+                self.share_tokens_key(app_name),
+                share_token.id().to_string(),
+                serde_json::to_string(&StoredShareToken::from(share_token))?,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn share_tokens(&self, app_name: &AppName) -> Result<Vec<ShareToken>, StateStoreError> {
+        let share_tokens: Vec<String> = self
+            .connection()
+            .await?
+            .hvals(self.share_tokens_key(app_name))
+            .await?;
+
+        share_tokens
+            .iter()
+            .map(|share_token| {
+                serde_json::from_str::<StoredShareToken>(share_token)
+                    .map(ShareToken::from)
+                    .map_err(StateStoreError::from)
+            })
+            .collect()
+    }
+
+    async fn remove_share_token(
+        &self,
+        app_name: &AppName,
+        id: &Uuid,
+    ) -> Result<bool, StateStoreError> {
+        let removed: i64 = self
+            .connection()
+            .await?
+            .hdel(self.share_tokens_key(app_name), id.to_string())
+            .await?;
+        Ok(removed > 0)
+    }
+
+    async fn remove_share_tokens(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .del::<_, ()>(self.share_tokens_key(app_name))
+            .await?;
+        Ok(())
+    }
+
+    async fn set_pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+        hooks: &[LifecycleHook],
+    ) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .hset::<_, _, _, ()>(
+                self.key("pre-destroy-hooks"),
+                app_name.to_string(),
+                serde_json::to_string(hooks)?,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn pre_destroy_hooks(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<LifecycleHook>, StateStoreError> {
+        let hooks: Option<String> = self
+            .connection()
+            .await?
+            .hget(self.key("pre-destroy-hooks"), app_name.to_string())
+            .await?;
+
+        match hooks {
+            Some(hooks) => Ok(serde_json::from_str(&hooks)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn remove_pre_destroy_hooks(&self, app_name: &AppName) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .hdel::<_, _, ()>(self.key("pre-destroy-hooks"), app_name.to_string())
+            .await?;
+        Ok(())
+    }
+
+    async fn append_audit_entry(&self, entry: &AuditEntry) -> Result<(), StateStoreError> {
+        self.connection()
+            .await?
+            .rpush::<_, _, ()>(self.key("audit-log"), serde_json::to_string(entry)?)
+            .await?;
+        Ok(())
+    }
+
+    async fn audit_log(&self) -> Result<Vec<AuditEntry>, StateStoreError> {
+        let entries: Vec<String> = self
+            .connection()
+            .await?
+            .lrange(self.key("audit-log"), 0, -1)
+            .await?;
+
+        entries
+            .iter()
+            .map(|entry| serde_json::from_str(entry).map_err(StateStoreError::from))
+            .collect()
+    }
+
+    async fn check(&self) -> Result<(), StateStoreError> {
+        ::redis::cmd("PING")
+            .query_async::<_, String>(&mut self.connection().await?)
+            .await?;
+        Ok(())
+    }
+}
+
+impl From<RedisError> for StateStoreError {
+    fn from(err: RedisError) -> Self {
+        StateStoreError::Unavailable {
+            internal_message: err.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires a Redis server at PREVANT_TEST_REDIS_URL"]
+    async fn should_keep_the_state_of_apps_in_redis() -> Result<(), StateStoreError> {
+        let url = std::env::var("PREVANT_TEST_REDIS_URL").expect("PREVANT_TEST_REDIS_URL");
+        let config = toml::from_str::<RedisStateConfig>(&format!(
+            "url = '{url}'\nkeyPrefix = 'prevant-test:'"
+        ))
+        .unwrap();
+
+        super::super::tests::should_keep_the_state_of_apps(&RedisStateStore::new(config)).await
+    }
+}
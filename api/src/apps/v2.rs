@@ -0,0 +1,334 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+//! Version 2 of the deployment payload. The payload evolves independently of the internal models
+//! which are shared by all versions: each request model converts into them.
+
+use super::routes::DeploymentPayload;
+use crate::models::{
+    AppName, Autoscale, Environment, Image, LifecycleHooks, Locale, ReplicationRules,
+    ResourceMetadata, Seed, ServiceAccount, ServiceConfig, SmokeTest, SourceBuild, StartupProbe,
+    TimeZone,
+};
+use secstr::SecUtf8;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The media type that requests version 2 of the REST API through the `Accept` header.
+pub const MEDIA_TYPE: &str = "application/vnd.prevant.v2+json";
+
+/// In contrast to version 1, the payload is always an object, services describe their ports and
+/// probes explicitly, and unknown fields are rejected instead of being ignored.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeploymentRequest {
+    services: Vec<ServiceSpec>,
+    #[serde(default)]
+    smoke_test: Option<SmokeTest>,
+    #[serde(default)]
+    hooks: Option<LifecycleHooks>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    replicate_from: Option<AppName>,
+    #[serde(default)]
+    replication: ReplicationRules,
+    #[serde(default)]
+    owner: Option<String>,
+    #[serde(default)]
+    builds: Vec<SourceBuild>,
+    #[serde(default)]
+    env: Option<Environment>,
+    #[serde(default)]
+    timezone: Option<TimeZone>,
+    #[serde(default)]
+    locale: Option<Locale>,
+    #[serde(default)]
+    service_doubles: Vec<String>,
+    #[serde(default)]
+    stacks: Vec<String>,
+    #[serde(default)]
+    resource_metadata: Option<ResourceMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ServiceSpec {
+    name: String,
+    #[serde(default)]
+    image: Option<Image>,
+    #[serde(default)]
+    extends: Option<String>,
+    #[serde(default)]
+    env: Option<Environment>,
+    #[serde(default)]
+    files: BTreeMap<PathBuf, SecUtf8>,
+    #[serde(default)]
+    command: Option<Vec<String>>,
+    #[serde(default)]
+    args: Option<Vec<String>>,
+    #[serde(default)]
+    working_dir: Option<String>,
+    #[serde(default)]
+    replicas: Option<u32>,
+    #[serde(default)]
+    autoscale: Option<Autoscale>,
+    #[serde(default)]
+    metadata: Option<ResourceMetadata>,
+    #[serde(default)]
+    service_account: Option<ServiceAccount>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    depends_on: Vec<String>,
+    /// The ports the service listens on. The first one receives the traffic of the service's
+    /// route instead of the port that the image exposes.
+    #[serde(default)]
+    ports: Vec<PortSpec>,
+    #[serde(default)]
+    probes: ProbesSpec,
+    #[serde(default)]
+    seed: Option<Seed>,
+    /// The seconds the service may take to shut down gracefully before it is killed.
+    #[serde(default)]
+    termination_grace_period: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PortSpec {
+    port: u16,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProbesSpec {
+    #[serde(default)]
+    startup: Option<StartupProbe>,
+}
+
+impl TryFrom<ServiceSpec> for ServiceConfig {
+    type Error = RequestError;
+
+    fn try_from(spec: ServiceSpec) -> Result<Self, Self::Error> {
+        if spec.ports.len() > 1 {
+            return Err(RequestError::TooManyPorts {
+                service_name: spec.name,
+                ports: spec
+                    .ports
+                    .iter()
+                    .map(|port| match &port.name {
+                        Some(name) => format!("{} ({})", port.port, name),
+                        None => port.port.to_string(),
+                    })
+                    .collect(),
+            });
+        }
+
+        let mut config = ServiceConfig::new(
+            spec.name,
+            spec.image.unwrap_or_else(ServiceConfig::image_of_template),
+        );
+        config.set_extends(spec.extends);
+        config.set_env(spec.env);
+        if !spec.files.is_empty() {
+            config.set_files(Some(spec.files));
+        }
+        config.set_command(spec.command);
+        config.set_args(spec.args);
+        config.set_working_dir(spec.working_dir);
+        config.set_replicas(spec.replicas);
+        config.set_autoscale(spec.autoscale);
+        config.set_metadata(spec.metadata);
+        config.set_service_account(spec.service_account);
+        config.set_aliases(spec.aliases);
+        config.set_depends_on(spec.depends_on);
+        if let Some(port) = spec.ports.first() {
+            config.set_fixed_port(port.port);
+        }
+        config.set_startup_probe(spec.probes.startup);
+        config.set_seed(spec.seed);
+        config.set_termination_grace_period(spec.termination_grace_period.map(Duration::from_secs));
+
+        Ok(config)
+    }
+}
+
+impl TryFrom<DeploymentRequest> for DeploymentPayload {
+    type Error = RequestError;
+
+    fn try_from(request: DeploymentRequest) -> Result<Self, Self::Error> {
+        let mut service_names = HashSet::new();
+        let mut services = Vec::with_capacity(request.services.len());
+        for spec in request.services {
+            if !service_names.insert(spec.name.clone()) {
+                return Err(RequestError::DuplicateService {
+                    service_name: spec.name,
+                });
+            }
+            services.push(ServiceConfig::try_from(spec)?);
+        }
+
+        Ok(DeploymentPayload {
+            services,
+            smoke_test: request.smoke_test,
+            hooks: request.hooks,
+            metadata: request.metadata,
+            replicate_from: request.replicate_from,
+            replication: request.replication,
+            source: None,
+            owner: request.owner,
+            builds: request.builds,
+            env: request.env,
+            timezone: request.timezone,
+            locale: request.locale,
+            service_doubles: request.service_doubles,
+            stacks: request.stacks,
+            resource_metadata: request.resource_metadata,
+        })
+    }
+}
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum RequestError {
+    #[fail(
+        display = "Service {} must not listen on more than one port but defines {:?}.",
+        service_name, ports
+    )]
+    TooManyPorts {
+        service_name: String,
+        ports: Vec<String>,
+    },
+    #[fail(display = "Service {} is defined more than once.", service_name)]
+    DuplicateService { service_name: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(payload: serde_json::Value) -> Result<DeploymentPayload, RequestError> {
+        DeploymentPayload::try_from(serde_json::from_value::<DeploymentRequest>(payload).unwrap())
+    }
+
+    #[test]
+    fn should_convert_service_spec() {
+        let payload = parse(serde_json::json!({
+            "services": [{
+                "name": "wordpress",
+                "image": "wordpress:6.4",
+                "env": { "WORDPRESS_DEBUG": "1" },
+                "ports": [{ "port": 8080, "name": "http" }],
+                "probes": { "startup": { "path": "/health" } }
+            }]
+        }))
+        .unwrap();
+
+        let config = &payload.services[0];
+        assert_eq!(config.service_name(), "wordpress");
+        assert_eq!(
+            config.image().to_string(),
+            "docker.io/library/wordpress:6.4"
+        );
+        assert_eq!(config.port(), 8080);
+        assert!(config.has_fixed_port());
+        assert_eq!(
+            config.startup_probe().and_then(|probe| probe.path()),
+            Some(&String::from("/health"))
+        );
+    }
+
+    #[test]
+    fn should_convert_resource_metadata_of_app() {
+        let payload = parse(serde_json::json!({
+            "services": [{ "name": "wordpress", "image": "wordpress:6.4" }],
+            "resourceMetadata": {
+                "labels": { "cost-center": "4711" },
+                "annotations": { "example.com/owner": "team-a" }
+            }
+        }))
+        .unwrap();
+
+        let metadata = payload.resource_metadata.unwrap();
+        assert_eq!(
+            metadata.labels().get("cost-center"),
+            Some(&String::from("4711"))
+        );
+        assert_eq!(
+            metadata.annotations().get("example.com/owner"),
+            Some(&String::from("team-a"))
+        );
+    }
+
+    #[test]
+    fn should_reject_unknown_fields() {
+        let request = serde_json::from_value::<DeploymentRequest>(serde_json::json!({
+            "services": [{ "serviceName": "wordpress", "image": "wordpress:6.4" }]
+        }));
+
+        assert!(request.is_err());
+    }
+
+    #[test]
+    fn should_reject_multiple_ports() {
+        let result = parse(serde_json::json!({
+            "services": [{
+                "name": "wordpress",
+                "image": "wordpress:6.4",
+                "ports": [{ "port": 8080 }, { "port": 9090, "name": "metrics" }]
+            }]
+        }));
+
+        assert_eq!(
+            result.err(),
+            Some(RequestError::TooManyPorts {
+                service_name: String::from("wordpress"),
+                ports: vec![String::from("8080"), String::from("9090 (metrics)")],
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_duplicate_services() {
+        let result = parse(serde_json::json!({
+            "services": [
+                { "name": "wordpress", "image": "wordpress:6.4" },
+                { "name": "wordpress", "image": "wordpress:6.5" }
+            ]
+        }));
+
+        assert_eq!(
+            result.err(),
+            Some(RequestError::DuplicateService {
+                service_name: String::from("wordpress")
+            })
+        );
+    }
+}
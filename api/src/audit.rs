@@ -0,0 +1,535 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::{Apps, RequestUser};
+use crate::auth::Admin;
+use crate::config::{AuditExport, ConfigHandle, NetworkConfig, SyslogProtocol};
+use crate::http_result::HttpResult;
+use crate::models::AuditEntry;
+use chrono::{DateTime, Utc};
+use http_api_problem::{HttpApiProblem, StatusCode};
+use rocket::data::{self, Data, FromData, Limits};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Method, Status};
+use rocket::request::FromRequest;
+use rocket::serde::json::{self, Json};
+use rocket::{Request, Response, State};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::net::{TcpStream, UdpSocket};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Records all mutating requests to the API, i.e. all requests except `GET`, `HEAD`, and
+/// `OPTIONS`, with their outcome in the audit log if the audit log has been configured.
+pub struct AuditLog;
+
+#[rocket::async_trait]
+impl Fairing for AuditLog {
+    fn info(&self) -> Info {
+        Info {
+            name: "Audit Log",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if matches!(
+            request.method(),
+            Method::Get | Method::Head | Method::Options
+        ) || !request.uri().path().starts_with("/api/")
+        {
+            return;
+        }
+
+        let (Some(config), Some(apps)) = (
+            request.rocket().state::<ConfigHandle>(),
+            request.rocket().state::<Arc<Apps>>(),
+        ) else {
+            return;
+        };
+        let config = config.current();
+        let Some(audit_config) = config.audit_config() else {
+            return;
+        };
+
+        let user = RequestUser::from_request(request)
+            .await
+            .succeeded()
+            .and_then(|user| user.name().map(String::from));
+        let entry = AuditEntry::new(
+            user,
+            request.method().to_string(),
+            request.uri().to_string(),
+            response.status().code,
+            request.local_cache(|| PayloadHash(None)).0.clone(),
+        );
+
+        apps.append_audit_entry(&entry).await;
+
+        let exports = audit_config.exports().to_vec();
+        if !exports.is_empty() {
+            let network = config.network_config().clone();
+            tokio::spawn(async move {
+                for export in &exports {
+                    if let Err(err) = export_entry(&network, export, &entry).await {
+                        warn!("Cannot export the audit log entry: {}", err);
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Returns the entries of the audit log that match all given filters, starting with the oldest
+/// entry. Only admins may read the audit log.
+#[get("/audit?<user>&<app>&<since>&<until>", format = "application/json")]
+pub async fn audit_log(
+    user: Option<String>,
+    app: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    config_state: &State<ConfigHandle>,
+    apps: &State<Arc<Apps>>,
+    _admin: Admin,
+) -> HttpResult<Json<Vec<AuditEntry>>> {
+    if config_state.current().audit_config().is_none() {
+        return Err(HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail("The audit log has not been configured.")
+            .into());
+    }
+
+    let since = since.as_deref().map(parse_timestamp).transpose()?;
+    let until = until.as_deref().map(parse_timestamp).transpose()?;
+
+    let entries = apps
+        .audit_log()
+        .await?
+        .into_iter()
+        .filter(|entry| user.is_none() || entry.user() == user.as_ref())
+        .filter(|entry| {
+            app.is_none() || entry.app_name().map(|app_name| app_name.to_string()) == app
+        })
+        .filter(|entry| since.map_or(true, |since| *entry.recorded_at() >= since))
+        .filter(|entry| until.map_or(true, |until| *entry.recorded_at() <= until))
+        .collect();
+
+    Ok(Json(entries))
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, HttpApiProblem> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|err| {
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                .detail(format!("Invalid timestamp {timestamp}: {err}"))
+        })
+}
+
+async fn export_entry(
+    network: &NetworkConfig,
+    export: &AuditExport,
+    entry: &AuditEntry,
+) -> Result<(), AuditExportError> {
+    let payload = serde_json::to_string(entry).map_err(|err| AuditExportError::Failed {
+        target: String::from("JSON"),
+        err: err.to_string(),
+    })?;
+
+    match export {
+        AuditExport::Http { url, headers } => {
+            let target = url.host_str().unwrap_or_default().to_string();
+            let client =
+                network
+                    .http_client_builder()
+                    .build()
+                    .map_err(|err| AuditExportError::Failed {
+                        target: target.clone(),
+                        err: err.to_string(),
+                    })?;
+            let mut request = client
+                .post(url.clone())
+                .header("Content-Type", "application/json")
+                .body(payload);
+            for (name, value) in headers {
+                request = request.header(name, value.unsecure());
+            }
+
+            let response = request
+                .send()
+                .await
+                .map_err(|err| AuditExportError::Failed {
+                    target: target.clone(),
+                    err: err.to_string(),
+                })?;
+            if !response.status().is_success() {
+                return Err(AuditExportError::Failed {
+                    target,
+                    err: response.status().to_string(),
+                });
+            }
+            Ok(())
+        }
+        AuditExport::Syslog { address, protocol } => {
+            let message = syslog_message(entry, &payload);
+            let address = address.clone();
+            let protocol = *protocol;
+            tokio::task::spawn_blocking(move || send_syslog_message(&address, protocol, &message))
+                .await
+                .map_err(|err| AuditExportError::Failed {
+                    target: String::from("syslog"),
+                    err: err.to_string(),
+                })?
+        }
+    }
+}
+
+/// Formats the entry as [RFC 5424](https://datatracker.ietf.org/doc/html/rfc5424) message with
+/// the facility `log audit` and the severity `informational`.
+fn syslog_message(entry: &AuditEntry, payload: &str) -> String {
+    format!(
+        "<110>1 {} - prevant - audit - {}",
+        entry.recorded_at().to_rfc3339(),
+        payload
+    )
+}
+
+fn send_syslog_message(
+    address: &str,
+    protocol: SyslogProtocol,
+    message: &str,
+) -> Result<(), AuditExportError> {
+    let to_error = |err: std::io::Error| AuditExportError::Failed {
+        target: address.to_string(),
+        err: err.to_string(),
+    };
+
+    match protocol {
+        SyslogProtocol::Udp => {
+            let socket = UdpSocket::bind("0.0.0.0:0").map_err(to_error)?;
+            socket
+                .send_to(message.as_bytes(), address)
+                .map_err(to_error)?;
+        }
+        SyslogProtocol::Tcp => {
+            let mut stream = TcpStream::connect(address).map_err(to_error)?;
+            // Octet counting framing, see RFC 6587
+            write!(stream, "{} {}", message.len(), message).map_err(to_error)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Fail)]
+enum AuditExportError {
+    #[fail(display = "Cannot export the entry to {}: {}", target, err)]
+    Failed { target: String, err: String },
+}
+
+/// The hex encoded SHA-256 hash of the request's payload that data guards keep in the local cache
+/// of the request for the audit log.
+struct PayloadHash(Option<String>);
+
+/// Keeps the hash of the payload for the audit log. Data guards that read the payload themselves
+/// have to call this function because fairings cannot read the whole payload.
+pub fn record_payload(request: &Request<'_>, payload: &[u8]) {
+    let hash = hex::encode(Sha256::digest(payload));
+    request.local_cache(|| PayloadHash(Some(hash)));
+}
+
+struct RawPayload(String);
+
+/// Data guard that parses JSON payloads like Rocket's `Json` and keeps the hash of the raw payload
+/// for the audit log.
+pub struct AuditedJson<T>(T);
+
+impl<T> AuditedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for AuditedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: DeserializeOwned> FromData<'r> for AuditedJson<T> {
+    type Error = json::Error<'r>;
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let payload = match read_payload(request, data, "json").await {
+            Ok(payload) => payload,
+            Err((status, err)) => return data::Outcome::Error((status, json::Error::Io(err))),
+        };
+
+        match serde_json::from_str(payload) {
+            Ok(value) => data::Outcome::Success(AuditedJson(value)),
+            Err(err) if err.is_data() => data::Outcome::Error((
+                Status::UnprocessableEntity,
+                json::Error::Parse(payload, err),
+            )),
+            Err(err) => {
+                data::Outcome::Error((Status::BadRequest, json::Error::Parse(payload, err)))
+            }
+        }
+    }
+}
+
+/// Data guard that parses YAML payloads and keeps the hash of the raw payload for the audit log,
+/// see [`AuditedJson`]. The error describes why the payload could not be parsed.
+pub struct AuditedYaml<T>(T);
+
+impl<T> AuditedYaml<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: DeserializeOwned> FromData<'r> for AuditedYaml<T> {
+    type Error = String;
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let payload = match read_payload(request, data, "yaml").await {
+            Ok(payload) => payload,
+            Err((status, err)) => return data::Outcome::Error((status, err.to_string())),
+        };
+
+        match serde_yaml::from_str(payload) {
+            Ok(value) => data::Outcome::Success(AuditedYaml(value)),
+            Err(err) => data::Outcome::Error((Status::BadRequest, err.to_string())),
+        }
+    }
+}
+
+/// Reads the payload within the limit of the given name, e.g. `json`, and keeps it in the local
+/// cache of the request together with its hash for the audit log.
+async fn read_payload<'r>(
+    request: &'r Request<'_>,
+    data: Data<'r>,
+    limit_name: &str,
+) -> Result<&'r str, (Status, std::io::Error)> {
+    let limit = request.limits().get(limit_name).unwrap_or(Limits::JSON);
+    let payload = match data.open(limit).into_string().await {
+        Ok(payload) if payload.is_complete() => payload.into_inner(),
+        Ok(_) => {
+            let err = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "data limit exceeded");
+            return Err((Status::PayloadTooLarge, err));
+        }
+        Err(err) => return Err((Status::BadRequest, err)),
+    };
+
+    record_payload(request, payload.as_bytes());
+    Ok(&request.local_cache(|| RawPayload(payload)).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::AppsService;
+    use crate::config::Config;
+    use crate::infrastructure::Dummy;
+    use rocket::http::{ContentType, Header};
+    use rocket::local::asynchronous::Client;
+
+    fn admin() -> [Header<'static>; 2] {
+        [
+            Header::new("X-Forwarded-User", "carol"),
+            Header::new("X-Prevant-Proxy-Secret", "proxy-secret"),
+        ]
+    }
+
+    async fn client(config: &str) -> Client {
+        let config = toml::de::from_str::<Config>(config).unwrap();
+        let apps = Arc::new(AppsService::new(config.clone(), Box::new(Dummy::new())).unwrap());
+        let rocket = rocket::build()
+            .manage(ConfigHandle::new(config))
+            .manage(apps)
+            .attach(AuditLog)
+            .mount("/api/apps", crate::apps::apps_routes())
+            .mount("/api", routes![audit_log]);
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    #[tokio::test]
+    async fn should_record_mutating_requests() {
+        let client = client(
+            r#"
+            [audit]
+
+            [trustedProxy]
+            secret = 'proxy-secret'
+
+            [admin]
+            users = [ 'carol' ]
+            "#,
+        )
+        .await;
+        let payload = r#"{ "owner": "bob" }"#;
+
+        let response = client
+            .put("/api/apps/master/owner")
+            .header(ContentType::JSON)
+            .header(Header::new("X-Forwarded-User", "alice"))
+            .header(Header::new("X-Prevant-Proxy-Secret", "proxy-secret"))
+            .body(payload)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        client.get("/api/apps").dispatch().await;
+
+        let [user, secret] = admin();
+        let response = client
+            .get("/api/audit?user=alice")
+            .header(user)
+            .header(secret)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let entries = response.into_json::<Vec<AuditEntry>>().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&entries[0]).unwrap()["payloadHash"],
+            hex::encode(Sha256::digest(payload.as_bytes()))
+        );
+
+        let [user, secret] = admin();
+        let response = client
+            .get("/api/audit?user=bob")
+            .header(user)
+            .header(secret)
+            .dispatch()
+            .await;
+        let entries = response.into_json::<Vec<AuditEntry>>().await.unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_not_record_spoofed_user() {
+        let client = client(
+            r#"
+            [audit]
+
+            [trustedProxy]
+            secret = 'proxy-secret'
+
+            [admin]
+            users = [ 'carol' ]
+            "#,
+        )
+        .await;
+
+        let response = client
+            .put("/api/apps/master/owner")
+            .header(ContentType::JSON)
+            .header(Header::new("X-Forwarded-User", "alice"))
+            .body(r#"{ "owner": "bob" }"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let [user, secret] = admin();
+        let response = client
+            .get("/api/audit")
+            .header(user)
+            .header(secret)
+            .dispatch()
+            .await;
+        let entries = response.into_json::<Vec<AuditEntry>>().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].user(), None);
+    }
+
+    #[tokio::test]
+    async fn should_not_provide_audit_log_without_configuration() {
+        let client = client(
+            r#"
+            [trustedProxy]
+            secret = 'proxy-secret'
+
+            [admin]
+            users = [ 'carol' ]
+            "#,
+        )
+        .await;
+
+        let [user, secret] = admin();
+        let response = client
+            .get("/api/audit")
+            .header(user)
+            .header(secret)
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn should_provide_audit_log_only_to_admins() {
+        let client = client(
+            r#"
+            [audit]
+
+            [trustedProxy]
+            secret = 'proxy-secret'
+
+            [admin]
+            users = [ 'carol' ]
+            "#,
+        )
+        .await;
+
+        let response = client.get("/api/audit").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = client
+            .get("/api/audit")
+            .header(Header::new("X-Forwarded-User", "alice"))
+            .header(Header::new("X-Prevant-Proxy-Secret", "proxy-secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn should_format_syslog_message() {
+        let entry = AuditEntry::new(
+            None,
+            String::from("POST"),
+            String::from("/api/apps/master"),
+            200,
+            None,
+        );
+
+        assert!(syslog_message(&entry, "{}").starts_with("<110>1 "));
+        assert!(syslog_message(&entry, "{}").ends_with(" - prevant - audit - {}"));
+    }
+}
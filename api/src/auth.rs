@@ -0,0 +1,464 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::{Apps, RequestUser};
+use crate::config::ConfigHandle;
+use crate::models::AppName;
+use rocket::http::{Cookie, CookieJar, Header, SameSite, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::Redirect;
+use rocket::{Request, State};
+use std::sync::Arc;
+use url::Url;
+
+/// The query parameter of the shared links that carries the share token.
+const SHARE_TOKEN_PARAMETER: &str = "prevantShareToken";
+
+#[derive(Responder)]
+pub enum ForwardAuthResponse {
+    #[response(status = 200)]
+    Authenticated((), Header<'static>),
+    #[response(status = 200)]
+    Shared(()),
+    Redirect(Redirect),
+    #[response(status = 401)]
+    Unauthorized(()),
+}
+
+/// Endpoint for Traefik's forwardAuth middleware that accepts the requests to the apps if the
+/// trusted SSO proxy authenticated the user or if the request carries a valid share token of the
+/// app. Unauthenticated users will be redirected to the configured login URL which takes the
+/// originally requested URL as `rd` parameter if PREvant permits redirects to its host.
+#[get("/auth/forward?<app>")]
+pub async fn forward(
+    app: Option<AppName>,
+    config_state: &State<ConfigHandle>,
+    apps: &State<Arc<Apps>>,
+    user: RequestUser,
+    original_url: ForwardedUrl,
+    cookies: &CookieJar<'_>,
+) -> ForwardAuthResponse {
+    if let Some(user) = user.name() {
+        return ForwardAuthResponse::Authenticated(
+            (),
+            Header::new("X-Forwarded-User", user.to_string()),
+        );
+    }
+
+    if let Some(app_name) = &app {
+        if let Some(response) =
+            authenticate_with_share_token(apps, app_name, original_url.0.as_ref(), cookies).await
+        {
+            return response;
+        }
+    }
+
+    let config = config_state.current();
+    let Some(forward_auth) = config.forward_auth_config() else {
+        return ForwardAuthResponse::Unauthorized(());
+    };
+    match forward_auth.login_url() {
+        Some(login_url) => {
+            let mut login_url = login_url.clone();
+            match original_url.0 {
+                Some(original_url) if forward_auth.permits_redirect_to(&original_url) => {
+                    login_url
+                        .query_pairs_mut()
+                        .append_pair("rd", original_url.as_str());
+                }
+                Some(original_url) => {
+                    debug!("Redirects to {} are not permitted", original_url);
+                }
+                None => {}
+            }
+            ForwardAuthResponse::Redirect(Redirect::found(login_url.to_string()))
+        }
+        None => ForwardAuthResponse::Unauthorized(()),
+    }
+}
+
+/// Accepts requests with the cookie of a valid share token. A share token in the query of a
+/// shared link will be moved into the cookie by redirecting to the link without the token. The
+/// redirect is relative so that the browser stays on the host it requested, whatever the
+/// `X-Forwarded-Host` header claims.
+async fn authenticate_with_share_token(
+    apps: &Apps,
+    app_name: &AppName,
+    original_url: Option<&Url>,
+    cookies: &CookieJar<'_>,
+) -> Option<ForwardAuthResponse> {
+    let cookie_name = format!("prevant-share-token-{app_name}");
+
+    if let Some(original_url) = original_url {
+        let token = original_url
+            .query_pairs()
+            .find(|(name, _)| name == SHARE_TOKEN_PARAMETER)
+            .map(|(_, token)| token.into_owned());
+
+        if let Some(token) = token {
+            let share_token = apps.share_token_granting_access(app_name, &token).await?;
+            let max_age = (*share_token.expires_at() - chrono::Utc::now()).num_seconds();
+            cookies.add(
+                Cookie::build((cookie_name, token))
+                    .path("/")
+                    .http_only(true)
+                    .same_site(SameSite::Lax)
+                    .max_age(rocket::time::Duration::seconds(max_age)),
+            );
+
+            let mut url = original_url.clone();
+            let query = original_url
+                .query_pairs()
+                .filter(|(name, _)| name != SHARE_TOKEN_PARAMETER)
+                .collect::<Vec<_>>();
+            if query.is_empty() {
+                url.set_query(None);
+            } else {
+                url.query_pairs_mut().clear().extend_pairs(query);
+            }
+
+            // A path starting with `//` would be a protocol-relative URL of another host.
+            let mut location = format!("/{}", url.path().trim_start_matches('/'));
+            if let Some(query) = url.query() {
+                location.push('?');
+                location.push_str(query);
+            }
+            return Some(ForwardAuthResponse::Redirect(Redirect::found(location)));
+        }
+    }
+
+    let token = cookies.get(&cookie_name)?.value().to_string();
+    apps.share_token_granting_access(app_name, &token)
+        .await
+        .map(|_| ForwardAuthResponse::Shared(()))
+}
+
+/// Request guard that succeeds if the trusted proxy authenticated one of the configured admins.
+/// Anonymous requests fail with `401 Unauthorized` and requests of other users with
+/// `403 Forbidden`.
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = RequestUser::from_request(request).await.succeeded();
+        let Some(user) = user.as_ref().and_then(RequestUser::name) else {
+            return Outcome::Error((Status::Unauthorized, "The user is not authenticated"));
+        };
+
+        let is_admin = request
+            .rocket()
+            .state::<ConfigHandle>()
+            .map_or(false, |config| {
+                config.current().admin_config().is_admin(user)
+            });
+        if is_admin {
+            Outcome::Success(Admin)
+        } else {
+            Outcome::Error((Status::Forbidden, "The user is not an admin"))
+        }
+    }
+}
+
+/// Request guard that succeeds if the trusted proxy authenticated the user. Anonymous requests
+/// fail with `401 Unauthorized`.
+pub struct AuthenticatedUser(String);
+
+impl AuthenticatedUser {
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthenticatedUser {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = RequestUser::from_request(request).await.succeeded();
+        match user.as_ref().and_then(RequestUser::name) {
+            Some(user) => Outcome::Success(AuthenticatedUser(user.to_string())),
+            None => Outcome::Error((Status::Unauthorized, "The user is not authenticated")),
+        }
+    }
+}
+
+/// The URL that has been requested originally, reconstructed from the `X-Forwarded-*` headers
+/// that Traefik sends to the authentication endpoint.
+pub struct ForwardedUrl(Option<Url>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ForwardedUrl {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+        Outcome::Success(ForwardedUrl(headers.get_one("X-Forwarded-Host").and_then(
+            |host| {
+                Url::parse(&format!(
+                    "{}://{}{}",
+                    headers.get_one("X-Forwarded-Proto").unwrap_or("https"),
+                    host,
+                    headers.get_one("X-Forwarded-Uri").unwrap_or("/")
+                ))
+                .ok()
+            },
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::AppsService;
+    use crate::config::Config;
+    use crate::infrastructure::Dummy;
+    use crate::models::AppStatusChangeId;
+    use crate::sc;
+    use rocket::http::Status;
+    use rocket::local::asynchronous::Client;
+
+    async fn client(config: &str) -> Client {
+        let config = toml::de::from_str::<Config>(config).unwrap();
+        let apps = Arc::new(AppsService::new(config.clone(), Box::new(Dummy::new())).unwrap());
+        let rocket = rocket::build()
+            .manage(ConfigHandle::new(config))
+            .manage(apps)
+            .mount("/", routes![forward]);
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    #[tokio::test]
+    async fn should_accept_authenticated_user() {
+        let client = client(
+            r#"
+            [trustedProxy]
+            secret = 'proxy-secret'
+            "#,
+        )
+        .await;
+
+        let response = client
+            .get("/auth/forward")
+            .header(Header::new("X-Auth-Request-User", "jdoe"))
+            .header(Header::new("X-Prevant-Proxy-Secret", "proxy-secret"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("X-Forwarded-User"), Some("jdoe"));
+    }
+
+    #[tokio::test]
+    async fn should_reject_spoofed_user() {
+        let client = client(
+            r#"
+            [trustedProxy]
+            secret = 'proxy-secret'
+            "#,
+        )
+        .await;
+
+        let response = client
+            .get("/auth/forward")
+            .header(Header::new("X-Forwarded-User", "jdoe"))
+            .header(Header::new("X-Prevant-Proxy-Secret", "guessed-secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.headers().get_one("X-Forwarded-User"), None);
+
+        let response = client
+            .get("/auth/forward")
+            .header(Header::new("X-Forwarded-User", "jdoe"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn should_reject_user_without_trusted_proxy() {
+        let client = client("").await;
+
+        let response = client
+            .get("/auth/forward")
+            .header(Header::new("X-Auth-Request-User", "jdoe"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn should_reject_unauthenticated_user() {
+        let client = client("").await;
+
+        let response = client.get("/auth/forward").dispatch().await;
+
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn should_redirect_unauthenticated_user_to_login() {
+        let client = client(
+            r#"
+            [forwardAuth]
+            address = 'http://prevant.tools.svc/auth/forward'
+            loginUrl = 'https://sso.example.com/oauth2/start'
+            redirectHosts = [ '*.previews.example.com' ]
+            "#,
+        )
+        .await;
+
+        let response = client
+            .get("/auth/forward")
+            .header(Header::new(
+                "X-Forwarded-Host",
+                "master.previews.example.com",
+            ))
+            .header(Header::new("X-Forwarded-Uri", "/wordpress/"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Found);
+        assert_eq!(
+            response.headers().get_one("Location"),
+            Some("https://sso.example.com/oauth2/start?rd=https%3A%2F%2Fmaster.previews.example.com%2Fwordpress%2F")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_redirect_to_foreign_host_after_login() {
+        let client = client(
+            r#"
+            [forwardAuth]
+            address = 'http://prevant.tools.svc/auth/forward'
+            loginUrl = 'https://sso.example.com/oauth2/start'
+            redirectHosts = [ '*.previews.example.com' ]
+            "#,
+        )
+        .await;
+
+        let response = client
+            .get("/auth/forward")
+            .header(Header::new("X-Forwarded-Host", "evil.com"))
+            .header(Header::new("X-Forwarded-Uri", "/wordpress/"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Found);
+        assert_eq!(
+            response.headers().get_one("Location"),
+            Some("https://sso.example.com/oauth2/start")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_accept_share_token_of_app() {
+        let client = client("").await;
+        let apps = client.rocket().state::<Arc<Apps>>().unwrap();
+        apps.create_or_update(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await
+        .unwrap();
+        let (_, token) = apps
+            .create_share_token(&AppName::master(), None, None)
+            .await
+            .unwrap();
+
+        let response = client
+            .get("/auth/forward?app=master")
+            .header(Header::new("X-Forwarded-Host", "previews.example.com"))
+            .header(Header::new(
+                "X-Forwarded-Uri",
+                format!("/master/service-a/?prevantShareToken={token}&page=2"),
+            ))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Found);
+        assert_eq!(
+            response.headers().get_one("Location"),
+            Some("/master/service-a/?page=2")
+        );
+        assert_eq!(
+            response
+                .cookies()
+                .get("prevant-share-token-master")
+                .map(|cookie| cookie.value()),
+            Some(token.as_str())
+        );
+
+        let response = client
+            .get("/auth/forward?app=master")
+            .cookie(Cookie::new("prevant-share-token-master", token))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .get("/auth/forward?app=other")
+            .cookie(Cookie::new("prevant-share-token-other", "guessed-token"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn should_redirect_shared_link_to_same_host() {
+        let client = client("").await;
+        let apps = client.rocket().state::<Arc<Apps>>().unwrap();
+        apps.create_or_update(
+            &AppName::master(),
+            &AppStatusChangeId::new(),
+            None,
+            &vec![sc!("service-a")],
+        )
+        .await
+        .unwrap();
+        let (_, token) = apps
+            .create_share_token(&AppName::master(), None, None)
+            .await
+            .unwrap();
+
+        let response = client
+            .get("/auth/forward?app=master")
+            .header(Header::new("X-Forwarded-Host", "previews.example.com"))
+            .header(Header::new(
+                "X-Forwarded-Uri",
+                format!("//evil.com/?prevantShareToken={token}"),
+            ))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Found);
+        assert_eq!(response.headers().get_one("Location"), Some("/evil.com/"));
+    }
+}
@@ -0,0 +1,170 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::Apps;
+use crate::audit::AuditedJson;
+use crate::auth::Admin;
+use crate::http_result::HttpResult;
+use crate::models::Backup;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+
+/// Exports the state of PREvant, i.e. the owners, the deployment histories, the share tokens of
+/// the apps, and the audit log, as portable JSON document. Only admins may export the state.
+#[get("/admin/backup", format = "application/json")]
+pub async fn backup(_admin: Admin, apps: &State<Arc<Apps>>) -> HttpResult<Json<Backup>> {
+    Ok(Json(apps.backup().await?))
+}
+
+/// Restores the state of the apps of the backup that has been exported by `GET /admin/backup`.
+/// Only admins may restore the state.
+#[post("/admin/restore", format = "application/json", data = "<backup>")]
+pub async fn restore(
+    _admin: Admin,
+    apps: &State<Arc<Apps>>,
+    backup: AuditedJson<Backup>,
+) -> HttpResult<Status> {
+    apps.restore(&backup).await?;
+    Ok(Status::NoContent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::AppsService;
+    use crate::config::{Config, ConfigHandle};
+    use crate::infrastructure::Dummy;
+    use rocket::http::{ContentType, Header};
+    use rocket::local::asynchronous::Client;
+
+    async fn client() -> Client {
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [trustedProxy]
+            secret = 'proxy-secret'
+
+            [admin]
+            users = [ 'alice' ]
+            "#,
+        )
+        .unwrap();
+        let apps = Arc::new(AppsService::new(config.clone(), Box::new(Dummy::new())).unwrap());
+        let rocket = rocket::build()
+            .manage(ConfigHandle::new(config))
+            .manage(apps)
+            .mount("/api", routes![backup, restore]);
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    fn user(name: &str) -> [Header<'static>; 2] {
+        [
+            Header::new("X-Forwarded-User", name.to_string()),
+            Header::new("X-Prevant-Proxy-Secret", "proxy-secret"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn should_restore_exported_backup() {
+        let client = client().await;
+
+        let [user, secret] = user("alice");
+        let response = client
+            .post("/api/admin/restore")
+            .header(ContentType::JSON)
+            .header(user.clone())
+            .header(secret.clone())
+            .body(
+                r#"{
+                    "version": 1,
+                    "createdAt": "2026-01-01T00:00:00Z",
+                    "apps": { "master": { "owner": "alice" } }
+                }"#,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+
+        let response = client
+            .get("/api/admin/backup")
+            .header(user)
+            .header(secret)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let backup = response.into_json::<serde_json::Value>().await.unwrap();
+        assert_eq!(backup["apps"]["master"]["owner"], "alice");
+    }
+
+    #[tokio::test]
+    async fn should_reject_backup_of_newer_version() {
+        let client = client().await;
+
+        let [user, secret] = user("alice");
+        let response = client
+            .post("/api/admin/restore")
+            .header(ContentType::JSON)
+            .header(user)
+            .header(secret)
+            .body(r#"{ "version": 2, "createdAt": "2030-01-01T00:00:00Z" }"#)
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn should_reject_unauthenticated_requests() {
+        let client = client().await;
+
+        let response = client.get("/api/admin/backup").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = client
+            .post("/api/admin/restore")
+            .header(ContentType::JSON)
+            .header(Header::new("X-Forwarded-User", "alice"))
+            .body(r#"{ "version": 1, "createdAt": "2026-01-01T00:00:00Z" }"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn should_reject_requests_of_other_users() {
+        let client = client().await;
+        let [user, secret] = user("bob");
+
+        let response = client
+            .get("/api/admin/backup")
+            .header(user)
+            .header(secret)
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+}
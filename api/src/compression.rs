@@ -0,0 +1,247 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use flate2::write::GzEncoder;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Method};
+use rocket::{Request, Response};
+use std::io::{Cursor, Write};
+
+/// Bodies smaller than this amount of bytes are sent uncompressed because the compression does
+/// not pay off.
+const MIN_SIZE: usize = 1024;
+
+/// Compresses textual responses, e.g. the JSON of `GET /api/apps`, with Brotli or gzip if the
+/// client accepts one of these encodings.
+pub struct ResponseCompression;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// Picks the encoding from the `Accept-Encoding` header, preferring Brotli over gzip.
+    fn negotiate(accept_encoding: &str) -> Option<Self> {
+        let accepted = accept_encoding
+            .split(',')
+            .filter_map(|coding| {
+                let mut params = coding.split(';');
+                let name = params.next()?.trim().to_ascii_lowercase();
+                let quality = params
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((name, quality))
+            })
+            .collect::<Vec<_>>();
+
+        let quality = |name: &str| {
+            accepted
+                .iter()
+                .find(|(coding, _)| coding == name)
+                .or_else(|| accepted.iter().find(|(coding, _)| coding == "*"))
+                .map(|(_, quality)| *quality)
+                .unwrap_or(0.0)
+        };
+
+        [Encoding::Brotli, Encoding::Gzip]
+            .iter()
+            .copied()
+            .find(|encoding| quality(encoding.name()) > 0.0)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn compress(&self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+                writer.write_all(body)?;
+                writer.flush()?;
+                Ok(writer.into_inner())
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+fn is_compressible(content_type: &ContentType) -> bool {
+    let media_type = content_type.media_type();
+    media_type.is_json()
+        || media_type.sub() == "problem+json"
+        || media_type.sub() == "yaml"
+        || media_type.is_javascript()
+        || media_type.is_css()
+        || media_type.is_html()
+        || media_type.is_svg()
+        || (media_type.top() == "text" && media_type.sub() != "event-stream")
+}
+
+#[rocket::async_trait]
+impl Fairing for ResponseCompression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response Compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if request.method() == Method::Head
+            || response.headers().contains("Content-Encoding")
+            || !response
+                .content_type()
+                .map_or(false, |ct| is_compressible(&ct))
+        {
+            return;
+        }
+
+        // Streamed bodies, e.g. server-sent events, do not have a size and must not be buffered.
+        match response.body().preset_size() {
+            Some(size) if size >= MIN_SIZE => {}
+            _ => return,
+        }
+
+        let Some(encoding) = request
+            .headers()
+            .get_one("Accept-Encoding")
+            .and_then(Encoding::negotiate)
+        else {
+            return;
+        };
+
+        let body = match response.body_mut().to_bytes().await {
+            Ok(body) => body,
+            Err(err) => {
+                warn!("Cannot read the response body for compression: {}", err);
+                return;
+            }
+        };
+
+        match encoding.compress(&body) {
+            Ok(compressed) => {
+                response.set_raw_header("Content-Encoding", encoding.name());
+                response.set_sized_body(compressed.len(), Cursor::new(compressed));
+            }
+            Err(err) => {
+                warn!(
+                    "Cannot compress the response with {}: {}",
+                    encoding.name(),
+                    err
+                );
+                response.set_sized_body(body.len(), Cursor::new(body));
+            }
+        }
+        response.adjoin_raw_header("Vary", "Accept-Encoding");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use rocket::http::Header;
+    use rocket::local::asynchronous::Client;
+    use rocket::serde::json::{json, Value};
+    use std::io::Read;
+
+    #[get("/")]
+    fn large() -> Value {
+        json!({ "services": vec!["wordpress"; 500] })
+    }
+
+    #[get("/small")]
+    fn small() -> Value {
+        json!({ "services": ["wordpress"] })
+    }
+
+    async fn client() -> Client {
+        let rocket = rocket::build()
+            .attach(ResponseCompression)
+            .mount("/", routes![large, small]);
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    #[test]
+    fn negotiate_encoding() {
+        assert_eq!(
+            Encoding::negotiate("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(Encoding::negotiate("gzip, br;q=0"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::negotiate("identity"), None);
+    }
+
+    #[tokio::test]
+    async fn compress_with_gzip() {
+        let client = client().await;
+
+        let response = client
+            .get("/")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+        assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+
+        let mut body = String::new();
+        GzDecoder::new(&response.into_bytes().await.unwrap()[..])
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            json!({ "services": vec!["wordpress"; 500] })
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_small_or_unaccepted_responses() {
+        let client = client().await;
+
+        let response = client
+            .get("/small")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch()
+            .await;
+        assert_eq!(response.headers().get_one("Content-Encoding"), None);
+
+        let response = client.get("/").dispatch().await;
+        assert_eq!(response.headers().get_one("Content-Encoding"), None);
+    }
+}
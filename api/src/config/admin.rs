@@ -0,0 +1,53 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// The users that are permitted to use the administrative endpoints, e.g. the backup and the
+/// restore of PREvant's state. The users must be authenticated by the trusted proxy.
+#[derive(Clone, Default, Deserialize)]
+pub struct AdminConfig {
+    #[serde(default)]
+    users: Vec<String>,
+}
+
+impl AdminConfig {
+    pub fn is_admin(&self, user: &str) -> bool {
+        self.users.iter().any(|admin| admin == user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_permit_configured_users() {
+        let config = toml::from_str::<AdminConfig>("users = [ 'alice' ]").unwrap();
+
+        assert!(config.is_admin("alice"));
+        assert!(!config.is_admin("bob"));
+        assert!(!AdminConfig::default().is_admin("alice"));
+    }
+}
@@ -0,0 +1,202 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::AppSelector;
+use crate::models::{AppName, AppNameError};
+
+/// Constraints for the names of new apps. The app names are part of the names of the containers
+/// and of the Kubernetes resources, thus names that are too long or contain invalid characters
+/// would make the deployment fail.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppNamePolicy {
+    pattern: Option<AppSelector>,
+    #[serde(default = "AppNamePolicy::default_max_length")]
+    max_length: usize,
+    #[serde(default)]
+    reserved: Vec<String>,
+}
+
+/// Kubernetes limits namespaces, labels, and the host names of pods to 63 characters and
+/// PREvant names the deployments `<app>-<service>-deployment`.
+const MAX_DEPLOYMENT_NAME_LENGTH: usize = 63 - "-deployment".len();
+
+impl AppNamePolicy {
+    /// Leaves room for the shortest possible suffix `-<service>`, i.e. a service name with a
+    /// single character. The actual services of an app are checked by
+    /// [`AppNamePolicy::validate_service`].
+    fn default_max_length() -> usize {
+        MAX_DEPLOYMENT_NAME_LENGTH - "-s".len()
+    }
+
+    /// Checks the name of an app that is about to be created. The name `api` is always reserved
+    /// because PREvant serves its API below `/api`.
+    pub fn validate(&self, app_name: &AppName) -> Result<(), AppNameError> {
+        if app_name.as_str() == "api"
+            || app_name == &AppName::shared_companions()
+            || self
+                .reserved
+                .iter()
+                .any(|reserved| reserved == app_name.as_str())
+        {
+            return Err(AppNameError::Reserved {
+                app_name: app_name.to_string(),
+            });
+        }
+
+        let length = app_name.chars().count();
+        if length > self.max_length {
+            return Err(AppNameError::TooLong {
+                app_name: app_name.to_string(),
+                length,
+                max_length: self.max_length,
+            });
+        }
+
+        if let Some(pattern) = &self.pattern {
+            if !pattern.matches(app_name) {
+                return Err(AppNameError::InvalidPattern {
+                    app_name: app_name.to_string(),
+                    pattern: pattern.as_str().to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the resources of the service, e.g. its deployment, can be named after the
+    /// app and the service. The check applies to existing apps as well because the names of
+    /// their new services might be too long.
+    pub fn validate_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<(), AppNameError> {
+        let max_length = MAX_DEPLOYMENT_NAME_LENGTH.saturating_sub(service_name.len() + 1);
+        if app_name.chars().count() > max_length {
+            return Err(AppNameError::TooLongForService {
+                app_name: app_name.to_string(),
+                service_name: service_name.to_string(),
+                max_length,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for AppNamePolicy {
+    fn default() -> Self {
+        Self {
+            pattern: None,
+            max_length: Self::default_max_length(),
+            reserved: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_accept_app_names_by_default() {
+        let policy = AppNamePolicy::default();
+
+        assert!(policy.validate(&AppName::master()).is_ok());
+        assert!(policy
+            .validate(&AppName::from_str("feature-1234").unwrap())
+            .is_ok());
+    }
+
+    #[test]
+    fn should_reject_reserved_app_names() {
+        let policy = toml::from_str::<AppNamePolicy>("reserved = ['master']").unwrap();
+
+        assert!(matches!(
+            policy.validate(&AppName::master()),
+            Err(AppNameError::Reserved { .. })
+        ));
+        assert!(matches!(
+            policy.validate(&AppName::from_str("api").unwrap()),
+            Err(AppNameError::Reserved { .. })
+        ));
+        assert!(matches!(
+            policy.validate(&AppName::shared_companions()),
+            Err(AppNameError::Reserved { .. })
+        ));
+    }
+
+    #[test]
+    fn should_reject_long_app_names() {
+        let policy = AppNamePolicy::default();
+
+        let app_name = AppName::from_str(&"a".repeat(51)).unwrap();
+
+        assert!(matches!(
+            policy.validate(&app_name),
+            Err(AppNameError::TooLong {
+                length: 51,
+                max_length: 50,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn should_reject_app_names_that_are_too_long_for_the_service() {
+        let policy = AppNamePolicy::default();
+
+        let app_name = AppName::from_str(&"a".repeat(40)).unwrap();
+
+        assert!(policy.validate_service(&app_name, "db").is_ok());
+        assert!(matches!(
+            policy.validate_service(&app_name, "notification-service"),
+            Err(AppNameError::TooLongForService { max_length: 31, .. })
+        ));
+    }
+
+    #[test]
+    fn should_reject_app_names_not_matching_the_pattern() {
+        let policy = toml::from_str::<AppNamePolicy>(
+            r#"
+            pattern = '[a-z0-9-]+'
+            maxLength = 20
+            "#,
+        )
+        .unwrap();
+
+        assert!(policy
+            .validate(&AppName::from_str("feature-1234").unwrap())
+            .is_ok());
+        assert!(matches!(
+            policy.validate(&AppName::from_str("Feature_1234").unwrap()),
+            Err(AppNameError::InvalidPattern { .. })
+        ));
+    }
+}
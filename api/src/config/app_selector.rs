@@ -35,6 +35,10 @@ impl AppSelector {
             Some(captures) => captures.get(0).map_or("", |m| m.as_str()) == app_name,
         }
     }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
 }
 
 impl Default for AppSelector {
@@ -0,0 +1,100 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use secstr::SecUtf8;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Enables the audit log that records all mutating requests to PREvant's API in the state store
+/// and, optionally, exports them to external systems, e.g. a SIEM.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditConfig {
+    #[serde(default)]
+    exports: Vec<AuditExport>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum AuditExport {
+    /// Posts each entry as JSON to the URL.
+    Http {
+        url: Url,
+        #[serde(default)]
+        headers: BTreeMap<String, SecUtf8>,
+    },
+    /// Sends each entry as [RFC 5424](https://datatracker.ietf.org/doc/html/rfc5424) message to
+    /// the syslog server, e.g. `syslog.example.com:514`.
+    Syslog {
+        address: String,
+        #[serde(default)]
+        protocol: SyslogProtocol,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogProtocol {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+impl AuditConfig {
+    pub fn exports(&self) -> &[AuditExport] {
+        &self.exports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_exports() {
+        let config = toml::de::from_str::<AuditConfig>(
+            r#"
+            [[exports]]
+            type = 'Http'
+            url = 'https://siem.example.com/ingest'
+            headers = { Authorization = 'Bearer secret' }
+
+            [[exports]]
+            type = 'Syslog'
+            address = 'syslog.example.com:514'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.exports().len(), 2);
+        assert!(matches!(
+            &config.exports()[1],
+            AuditExport::Syslog {
+                protocol: SyslogProtocol::Udp,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,263 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::{AppName, Image, SourceBuild};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Configures how PREvant builds the images of services from their sources in Git repositories,
+/// e.g. with [Kaniko](https://github.com/GoogleContainerTools/kaniko) or
+/// [Cloud Native Buildpacks](https://buildpacks.io/).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildsConfig {
+    /// The repository the built images are pushed to, e.g. `registry.example.com/previews`.
+    registry: String,
+    #[serde(default)]
+    builder: Builder,
+    /// Overrides the default image of the builder.
+    #[serde(default)]
+    image: Option<Image>,
+    #[serde(default = "BuildsConfig::default_git_image")]
+    git_image: Image,
+    #[serde(default = "BuildsConfig::default_timeout")]
+    timeout: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+pub enum Builder {
+    /// Builds the Dockerfile of the sources.
+    #[default]
+    Kaniko,
+    /// Detects how to build the sources, e.g. with Maven or npm, without a Dockerfile.
+    Buildpacks,
+}
+
+impl BuildsConfig {
+    fn default_git_image() -> Image {
+        Image::from_str("alpine/git:2.43.0").unwrap()
+    }
+
+    fn default_timeout() -> u64 {
+        1800
+    }
+
+    pub fn builder(&self) -> Builder {
+        self.builder
+    }
+
+    /// The image of the container that builds and pushes the image.
+    pub fn builder_image(&self) -> Image {
+        self.image.clone().unwrap_or_else(|| {
+            Image::from_str(match self.builder {
+                Builder::Kaniko => "gcr.io/kaniko-project/executor:v1.23.2",
+                Builder::Buildpacks => "paketobuildpacks/builder-jammy-base:latest",
+            })
+            .unwrap()
+        })
+    }
+
+    /// The image of the container that checks out the sources before the build.
+    pub fn git_image(&self) -> &Image {
+        &self.git_image
+    }
+
+    /// The maximal time a build may take until it is considered as failed.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+
+    /// The image that will be built for the service of the app. The image is tagged with the
+    /// revision of the sources so that different apps do not overwrite their images.
+    pub fn destination(&self, app_name: &AppName, build: &SourceBuild) -> Option<Image> {
+        let tag = build
+            .reference()
+            .map(|reference| {
+                reference
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                            c
+                        } else {
+                            '-'
+                        }
+                    })
+                    .take(128)
+                    .collect::<String>()
+                    .trim_start_matches(['.', '-'])
+                    .to_string()
+            })
+            .filter(|tag| !tag.is_empty())
+            .unwrap_or_else(|| String::from("latest"));
+
+        Image::from_str(&format!(
+            "{}/{}/{}:{}",
+            self.registry.trim_end_matches('/'),
+            repository_component(&app_name.to_string()),
+            repository_component(build.service_name()),
+            tag
+        ))
+        .ok()
+    }
+
+    /// The command and the arguments of the builder container for the sources that have been
+    /// checked out into `workspace`.
+    pub fn builder_command(
+        &self,
+        build: &SourceBuild,
+        workspace: &str,
+        destination: &Image,
+    ) -> (Option<Vec<String>>, Vec<String>) {
+        let context = if build.context().is_empty() {
+            workspace.to_string()
+        } else {
+            format!("{}/{}", workspace, build.context())
+        };
+
+        match self.builder {
+            Builder::Kaniko => (
+                None,
+                vec![
+                    format!("--context=dir://{context}"),
+                    format!("--dockerfile={}", build.dockerfile()),
+                    format!("--destination={destination}"),
+                ],
+            ),
+            Builder::Buildpacks => (
+                Some(vec![String::from("/cnb/lifecycle/creator")]),
+                vec![format!("-app={context}"), destination.to_string()],
+            ),
+        }
+    }
+}
+
+/// Replaces the characters of `name` that are not permitted in the path of an image repository.
+fn repository_component(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(reference: Option<&str>) -> SourceBuild {
+        serde_json::from_value(serde_json::json!({
+            "serviceName": "frontend",
+            "url": "https://github.com/aixigo/PREvant.git",
+            "ref": reference,
+            "context": "frontend/"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn should_tag_destination_with_revision() {
+        let config = toml::de::from_str::<BuildsConfig>(
+            r#"
+            registry = 'registry.example.com/previews/'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config
+                .destination(
+                    &AppName::from_str("Feature_1.x").unwrap(),
+                    &build(Some("feature/login"))
+                )
+                .map(|image| image.to_string()),
+            Some(String::from(
+                "registry.example.com/previews/feature-1-x/frontend:feature-login"
+            ))
+        );
+        assert_eq!(
+            config
+                .destination(&AppName::master(), &build(None))
+                .map(|image| image.to_string()),
+            Some(String::from(
+                "registry.example.com/previews/master/frontend:latest"
+            ))
+        );
+    }
+
+    #[test]
+    fn should_build_with_kaniko_by_default() {
+        let config = toml::de::from_str::<BuildsConfig>(
+            r#"
+            registry = 'registry.example.com/previews'
+            "#,
+        )
+        .unwrap();
+        let destination = Image::from_str("registry.example.com/previews/master/frontend").unwrap();
+
+        assert_eq!(config.builder(), Builder::Kaniko);
+        assert_eq!(
+            config.builder_image().to_string(),
+            "gcr.io/kaniko-project/executor:v1.23.2"
+        );
+        assert_eq!(
+            config.builder_command(&build(None), "/workspace", &destination),
+            (
+                None,
+                vec![
+                    String::from("--context=dir:///workspace/frontend"),
+                    String::from("--dockerfile=Dockerfile"),
+                    String::from(
+                        "--destination=registry.example.com/previews/master/frontend:latest"
+                    ),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn should_build_with_buildpacks() {
+        let config = toml::de::from_str::<BuildsConfig>(
+            r#"
+            registry = 'registry.example.com/previews'
+            builder = 'Buildpacks'
+            timeout = 600
+            "#,
+        )
+        .unwrap();
+        let destination = Image::from_str("registry.example.com/previews/master/frontend").unwrap();
+
+        assert_eq!(config.timeout(), Duration::from_secs(600));
+        assert_eq!(
+            config.builder_command(&build(None), "/workspace", &destination),
+            (
+                Some(vec![String::from("/cnb/lifecycle/creator")]),
+                vec![
+                    String::from("-app=/workspace/frontend"),
+                    String::from("registry.example.com/previews/master/frontend:latest"),
+                ]
+            )
+        );
+    }
+}
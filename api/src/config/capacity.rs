@@ -0,0 +1,69 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// Before deploying an app, PREvant compares the resources that its services request with the
+/// free resources of the cluster's nodes to detect deployments that cannot be scheduled.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapacityCheckConfig {
+    #[serde(default)]
+    mode: CapacityCheckMode,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CapacityCheckMode {
+    /// Deployments that exceed the capacity are rejected.
+    #[default]
+    Reject,
+    /// Deployments that exceed the capacity are logged but deployed nonetheless.
+    Warn,
+}
+
+impl CapacityCheckConfig {
+    pub fn mode(&self) -> CapacityCheckMode {
+        self.mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_reject_by_default() {
+        let config = toml::de::from_str::<CapacityCheckConfig>("").unwrap();
+
+        assert_eq!(config.mode(), CapacityCheckMode::Reject);
+    }
+
+    #[test]
+    fn should_parse_warn_mode() {
+        let config = toml::de::from_str::<CapacityCheckConfig>("mode = 'warn'").unwrap();
+
+        assert_eq!(config.mode(), CapacityCheckMode::Warn);
+    }
+}
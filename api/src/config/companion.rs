@@ -25,11 +25,18 @@
  */
 use crate::config::AppSelector;
 use crate::models::service::ContainerType;
-use crate::models::{Environment, Image, Router, ServiceConfig};
+use crate::models::{
+    json_path_to_pointer, Autoscale, CustomResource, CustomResourceReadiness, DnsConfig,
+    Environment, HelmRelease, HostAlias, Image, ImagePullPolicy, LoadBalancer, Locale,
+    OperatingSystem, Router, ScratchVolume, ServiceAccount, ServiceConfig, StartupProbe,
+    TemplateEngine, TimeZone,
+};
+use handlebars::{Handlebars, RenderError};
 use secstr::SecUtf8;
 use serde_value::Value;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -37,7 +44,16 @@ pub(super) struct Companion {
     service_name: String,
     #[serde(rename = "type")]
     companion_type: CompanionType,
-    image: Image,
+    /// The image of the companion which is required for all types except `helm` and
+    /// `custom-resource`.
+    #[serde(default)]
+    image: Option<Image>,
+    /// The chart of a companion of the type `helm`.
+    #[serde(default)]
+    chart: Option<HelmChart>,
+    /// The manifest of a companion of the type `custom-resource`.
+    #[serde(default)]
+    custom_resource: Option<CustomResourceSpec>,
     #[serde(default)]
     deployment_strategy: DeploymentStrategy,
     env: Option<Environment>,
@@ -50,6 +66,39 @@ pub(super) struct Companion {
     middlewares: Option<BTreeMap<String, Value>>,
     #[serde(default)]
     storage_strategy: StorageStrategy,
+    service_account: Option<ServiceAccount>,
+    command: Option<Vec<String>>,
+    args: Option<Vec<String>>,
+    working_dir: Option<String>,
+    autoscale: Option<Autoscale>,
+    #[serde(
+        default,
+        deserialize_with = "crate::models::deserialize_extended_resources"
+    )]
+    extended_resources: BTreeMap<String, String>,
+    image_pull_policy: Option<ImagePullPolicy>,
+    load_balancer: Option<LoadBalancer>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    host_aliases: Vec<HostAlias>,
+    dns_config: Option<DnsConfig>,
+    timezone: Option<TimeZone>,
+    locale: Option<Locale>,
+    #[serde(default)]
+    scratch_volumes: Vec<ScratchVolume>,
+    #[serde(default)]
+    read_only_root_filesystem: bool,
+    #[serde(default)]
+    writable_paths: Vec<PathBuf>,
+    startup_probe: Option<StartupProbe>,
+    termination_grace_period: Option<u64>,
+    platform: Option<String>,
+    os: Option<OperatingSystem>,
+    #[serde(default)]
+    template_engine: TemplateEngine,
+    /// Environment variables that wire the apps to a shared companion, e.g. the URL of their realm.
+    app_env: Option<Environment>,
 }
 
 #[derive(Clone, Deserialize, Debug, PartialEq)]
@@ -58,6 +107,81 @@ pub(super) enum CompanionType {
     Application,
     #[serde(rename = "service")]
     Service,
+    /// A companion that runs only once in the app of the shared companions and is used by all
+    /// apps that match its app selector.
+    #[serde(rename = "shared")]
+    Shared,
+    /// A Helm chart that is installed into the namespace of each app on Kubernetes.
+    #[serde(rename = "helm")]
+    Helm,
+    /// An arbitrary custom resource that is applied into the namespace of each app on Kubernetes
+    /// and provisioned by an operator.
+    #[serde(rename = "custom-resource")]
+    CustomResource,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct HelmChart {
+    name: String,
+    #[serde(default)]
+    repository: Option<String>,
+    #[serde(default)]
+    version: Option<String>,
+    /// The values of the release in YAML that are rendered by Handlebars with the variable
+    /// `application.name`.
+    #[serde(default)]
+    values: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct CustomResourceSpec {
+    /// The manifest in YAML that is rendered by Handlebars with the variable `application.name`.
+    manifest: String,
+    #[serde(default)]
+    readiness: Option<ReadinessSpec>,
+}
+
+/// Either the type of a condition in `status.conditions` or a JSON pointer and its expected
+/// value.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ReadinessSpec {
+    #[serde(default)]
+    condition: Option<String>,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default = "ReadinessSpec::default_timeout")]
+    timeout: u64,
+}
+
+impl ReadinessSpec {
+    fn default_timeout() -> u64 {
+        600
+    }
+
+    fn readiness(&self) -> Result<CustomResourceReadiness, String> {
+        match (&self.condition, &self.path, &self.value) {
+            (Some(condition), None, None) => {
+                Ok(CustomResourceReadiness::Condition(condition.clone()))
+            }
+            (None, Some(path), Some(value)) => Ok(CustomResourceReadiness::Value {
+                // Paths starting with / are JSON pointers, everything else is a JSONPath.
+                pointer: if path.starts_with('/') {
+                    path.clone()
+                } else {
+                    json_path_to_pointer(path)?
+                },
+                value: value.clone(),
+            }),
+            _ => Err(String::from(
+                "the readiness requires either a condition or a path and a value",
+            )),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -94,12 +218,129 @@ impl Companion {
     pub fn storage_strategy(&self) -> &StorageStrategy {
         &self.storage_strategy
     }
+
+    pub fn app_env(&self) -> Option<&Environment> {
+        self.app_env.as_ref()
+    }
+
+    pub fn router(&self) -> Option<&Router> {
+        self.router.as_ref()
+    }
+
+    /// Returns `true` if the companion runs as a container, i.e. it is neither a Helm chart nor a
+    /// custom resource.
+    pub fn is_container(&self) -> bool {
+        !matches!(
+            self.companion_type,
+            CompanionType::Helm | CompanionType::CustomResource
+        )
+    }
+
+    /// Checks that Helm companions declare a chart, custom resource companions a manifest, and
+    /// that all other companions declare an image.
+    pub fn check(&self) -> Result<(), String> {
+        match (&self.companion_type, &self.image, &self.chart) {
+            (CompanionType::Helm, _, None) => Err(String::from(
+                "a companion of the type helm requires a chart",
+            )),
+            (CompanionType::Helm, _, Some(_)) => Ok(()),
+            (CompanionType::CustomResource, _, _) => match &self.custom_resource {
+                None => Err(String::from(
+                    "a companion of the type custom-resource requires a customResource",
+                )),
+                Some(spec) => spec
+                    .readiness
+                    .as_ref()
+                    .map_or(Ok(()), |readiness| readiness.readiness().map(|_| ())),
+            },
+            (_, None, _) => Err(String::from("a companion requires an image")),
+            (_, Some(_), _) => Ok(()),
+        }
+    }
+
+    /// The custom resource of a companion whose manifest has been rendered for the app.
+    pub fn custom_resource(&self, app_name: &str) -> Result<Option<CustomResource>, RenderError> {
+        let spec = match (&self.companion_type, &self.custom_resource) {
+            (CompanionType::CustomResource, Some(spec)) => spec,
+            _ => return Ok(None),
+        };
+
+        let manifest = Handlebars::new().render_template(
+            &spec.manifest,
+            &serde_json::json!({ "application": { "name": app_name } }),
+        )?;
+        let manifest = serde_yaml::from_str::<serde_json::Value>(&manifest).map_err(|err| {
+            RenderError::new(format!(
+                "The manifest of {} is invalid: {}",
+                self.service_name, err
+            ))
+        })?;
+        for field in ["/apiVersion", "/kind", "/metadata/name"] {
+            if manifest
+                .pointer(field)
+                .and_then(|value| value.as_str())
+                .is_none()
+            {
+                return Err(RenderError::new(format!(
+                    "The manifest of {} requires {}",
+                    self.service_name,
+                    &field[1..]
+                )));
+            }
+        }
+
+        let readiness = match &spec.readiness {
+            Some(readiness) => Some(readiness.readiness().map_err(RenderError::new)?),
+            None => None,
+        };
+        let timeout = spec
+            .readiness
+            .as_ref()
+            .map_or_else(ReadinessSpec::default_timeout, |readiness| {
+                readiness.timeout
+            });
+
+        Ok(Some(CustomResource::new(
+            self.service_name.clone(),
+            manifest,
+            readiness,
+            Duration::from_secs(timeout),
+        )))
+    }
+
+    /// The release of a Helm companion whose values have been rendered for the app.
+    pub fn helm_release(&self, app_name: &str) -> Result<Option<HelmRelease>, RenderError> {
+        let chart = match (&self.companion_type, &self.chart) {
+            (CompanionType::Helm, Some(chart)) => chart,
+            _ => return Ok(None),
+        };
+
+        let values = match &chart.values {
+            Some(values) => Some(Handlebars::new().render_template(
+                values,
+                &serde_json::json!({ "application": { "name": app_name } }),
+            )?),
+            None => None,
+        };
+
+        // Releases of different apps can share a namespace and would overwrite each other.
+        Ok(Some(HelmRelease::new(
+            format!("{app_name}-{}", self.service_name),
+            chart.name.clone(),
+            chart.repository.clone(),
+            chart.version.clone(),
+            values,
+        )))
+    }
 }
 
 impl From<Companion> for ServiceConfig {
     fn from(companion: Companion) -> ServiceConfig {
-        let mut config =
-            ServiceConfig::new(companion.service_name.clone(), companion.image.clone());
+        let image = companion
+            .image
+            .clone()
+            .expect("Companions without image are rejected when the configuration is loaded");
+        let mut config = ServiceConfig::new(companion.service_name.clone(), image);
 
         config.set_env(companion.env.clone().map(|env| {
             Environment::new(
@@ -122,6 +363,29 @@ impl From<Companion> for ServiceConfig {
             config.set_middlewares(middlewares.clone());
         }
 
+        config.set_service_account(companion.service_account.clone());
+        config.set_command(companion.command.clone());
+        config.set_args(companion.args.clone());
+        config.set_working_dir(companion.working_dir.clone());
+        config.set_autoscale(companion.autoscale.clone());
+        config.set_extended_resources(companion.extended_resources.clone());
+        config.set_image_pull_policy(companion.image_pull_policy);
+        config.set_load_balancer(companion.load_balancer.clone());
+        config.set_aliases(companion.aliases.clone());
+        config.set_host_aliases(companion.host_aliases.clone());
+        config.set_dns_config(companion.dns_config.clone());
+        config.set_timezone(companion.timezone.clone());
+        config.set_locale(companion.locale.clone());
+        config.set_scratch_volumes(companion.scratch_volumes.clone());
+        config.set_read_only_root_filesystem(companion.read_only_root_filesystem);
+        config.set_writable_paths(companion.writable_paths.clone());
+        config.set_startup_probe(companion.startup_probe.clone());
+        config.set_termination_grace_period(
+            companion.termination_grace_period.map(Duration::from_secs),
+        );
+        config.set_platform(companion.platform.clone());
+        config.set_os(companion.os);
+        config.set_template_engine(companion.template_engine);
         config.set_container_type(companion.companion_type.into());
 
         config
@@ -131,7 +395,9 @@ impl From<Companion> for ServiceConfig {
 impl From<CompanionType> for ContainerType {
     fn from(t: CompanionType) -> Self {
         match t {
-            CompanionType::Application => ContainerType::ApplicationCompanion,
+            CompanionType::Application | CompanionType::Shared | CompanionType::Helm => {
+                ContainerType::ApplicationCompanion
+            }
             CompanionType::Service => ContainerType::ServiceCompanion,
         }
     }
@@ -174,11 +440,119 @@ mod tests {
         assert_eq!(companion.companion_type, CompanionType::Application);
         assert_eq!(
             companion.image,
-            Image::from_str("private.example.com/library/openid:latest").unwrap()
+            Some(Image::from_str("private.example.com/library/openid:latest").unwrap())
         );
         assert_eq!(
             companion.deployment_strategy,
             DeploymentStrategy::RedeployAlways
         );
+        assert_eq!(companion.check(), Ok(()));
+    }
+
+    #[test]
+    fn should_parse_helm_companion_and_render_values() {
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'kafka'
+            type = 'helm'
+
+            [chart]
+            name = 'kafka'
+            repository = 'https://charts.bitnami.com/bitnami'
+            version = '26.4.2'
+            values = '''
+            commonLabels:
+              preview: {{application.name}}
+            '''
+        "#
+        );
+
+        assert_eq!(companion.check(), Ok(()));
+        let release = companion.helm_release("feature-x").unwrap().unwrap();
+        assert_eq!(release.name(), "kafka");
+        assert_eq!(release.chart(), "kafka");
+        assert_eq!(release.version(), Some(&String::from("26.4.2")));
+        assert!(release.values().unwrap().contains("preview: feature-x"));
+    }
+
+    #[test]
+    fn should_reject_companions_without_image_or_chart() {
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'openid'
+            type = 'application'
+        "#
+        );
+        assert!(companion.check().is_err());
+
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'kafka'
+            type = 'helm'
+        "#
+        );
+        assert!(companion.check().is_err());
+    }
+
+    #[test]
+    fn should_parse_custom_resource_companion_and_render_manifest() {
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'kafka'
+            type = 'custom-resource'
+
+            [customResource]
+            manifest = '''
+            apiVersion: kafka.strimzi.io/v1beta2
+            kind: Kafka
+            metadata:
+              name: kafka
+              labels:
+                preview: {{application.name}}
+            spec:
+              kafka:
+                replicas: 1
+            '''
+
+            [customResource.readiness]
+            condition = 'Ready'
+            timeout = 300
+        "#
+        );
+
+        assert_eq!(companion.check(), Ok(()));
+        assert!(!companion.is_container());
+        let resource = companion.custom_resource("feature-x").unwrap().unwrap();
+        assert_eq!(resource.name(), "kafka");
+        assert_eq!(resource.api_version(), Some("kafka.strimzi.io/v1beta2"));
+        assert_eq!(resource.kind(), Some("Kafka"));
+        assert_eq!(
+            resource.manifest().pointer("/metadata/labels/preview"),
+            Some(&serde_json::json!("feature-x"))
+        );
+        assert_eq!(
+            resource.readiness(),
+            Some(&CustomResourceReadiness::Condition(String::from("Ready")))
+        );
+        assert_eq!(resource.timeout(), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn should_reject_custom_resource_companions_with_ambiguous_readiness() {
+        let companion = companion_from_str!(
+            r#"
+            serviceName = 'postgres'
+            type = 'custom-resource'
+
+            [customResource]
+            manifest = 'kind: postgresql'
+
+            [customResource.readiness]
+            condition = 'Ready'
+            path = '/status/PostgresClusterStatus'
+        "#
+        );
+
+        assert!(companion.check().is_err());
     }
 }
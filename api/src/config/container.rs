@@ -23,17 +23,30 @@
  * THE SOFTWARE.
  * =========================LICENSE_END==================================
  */
+use crate::models::{ImagePullPolicy, ServiceConfig};
 use bytesize::ByteSize;
 use serde::{de, Deserialize, Deserializer};
+use std::fmt::{Display, Formatter};
 
 #[derive(Clone, Default, Deserialize)]
 pub struct ContainerConfig {
-    #[serde(deserialize_with = "ContainerConfig::parse_from_memory_string")]
+    #[serde(
+        default,
+        deserialize_with = "ContainerConfig::parse_from_memory_string"
+    )]
     memory_limit: Option<ByteSize>,
+    #[serde(default, deserialize_with = "ContainerConfig::parse_cpu_limit")]
+    cpu_limit: Option<f64>,
+    #[serde(default)]
+    image_pull_policy: ImagePullPolicy,
+    #[serde(default)]
+    restart_policy: RestartPolicy,
 }
 
 impl ContainerConfig {
-    fn parse_from_memory_string<'de, D>(deserializer: D) -> Result<Option<ByteSize>, D::Error>
+    pub(super) fn parse_from_memory_string<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<ByteSize>, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -55,7 +68,115 @@ impl ContainerConfig {
         }
     }
 
+    /// Parses the CPU limit either as number of cores or as
+    /// [Kubernetes quantity](https://kubernetes.io/docs/reference/kubernetes-api/common-definitions/quantity/)
+    /// in millicores, e.g. `'500m'`.
+    fn parse_cpu_limit<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum CpuLimit {
+            Cores(f64),
+            Quantity(String),
+        }
+
+        let cores = match CpuLimit::deserialize(deserializer)? {
+            CpuLimit::Cores(cores) => cores,
+            CpuLimit::Quantity(quantity) => match quantity.strip_suffix('m') {
+                Some(millis) => millis.parse::<f64>().map_err(de::Error::custom)? / 1000.0,
+                None => quantity.parse::<f64>().map_err(de::Error::custom)?,
+            },
+        };
+        if !cores.is_finite() || cores <= 0.0 {
+            return Err(de::Error::custom("The CPU limit must be a positive number"));
+        }
+        Ok(Some(cores))
+    }
+
     pub fn memory_limit(&self) -> Option<ByteSize> {
         self.memory_limit
     }
+
+    /// The number of CPU cores that a container may use.
+    pub fn cpu_limit(&self) -> Option<f64> {
+        self.cpu_limit
+    }
+
+    /// The restart policy of the containers which only applies to Docker because Kubernetes
+    /// always restarts the containers of deployments.
+    pub fn restart_policy(&self) -> RestartPolicy {
+        self.restart_policy
+    }
+
+    /// The pull policy for the image of the service. The pull policy of the service has
+    /// precedence over the configured one.
+    pub fn image_pull_policy(&self, service_config: &ServiceConfig) -> ImagePullPolicy {
+        service_config
+            .image_pull_policy()
+            .unwrap_or(self.image_pull_policy)
+    }
+}
+
+/// Defines when Docker restarts a container (see
+/// [Docker's restart policies](https://docs.docker.com/engine/reference/run/#restart-policies---restart)).
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    #[default]
+    Always,
+    OnFailure,
+    UnlessStopped,
+}
+
+impl Display for RestartPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestartPolicy::Always => write!(f, "always"),
+            RestartPolicy::OnFailure => write!(f, "on-failure"),
+            RestartPolicy::UnlessStopped => write!(f, "unless-stopped"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_cpu_limit_as_millicores() {
+        let config = toml::from_str::<ContainerConfig>("cpu_limit = '500m'").unwrap();
+
+        assert_eq!(config.cpu_limit(), Some(0.5));
+    }
+
+    #[test]
+    fn should_parse_cpu_limit_as_cores() {
+        let config = toml::from_str::<ContainerConfig>("cpu_limit = 1.5").unwrap();
+
+        assert_eq!(config.cpu_limit(), Some(1.5));
+    }
+
+    #[test]
+    fn should_reject_negative_cpu_limit() {
+        assert!(toml::from_str::<ContainerConfig>("cpu_limit = '-1'").is_err());
+    }
+
+    #[test]
+    fn should_reject_cpu_limit_that_is_not_a_number() {
+        assert!(toml::from_str::<ContainerConfig>("cpu_limit = nan").is_err());
+        assert!(toml::from_str::<ContainerConfig>("cpu_limit = 'NaN'").is_err());
+        assert!(toml::from_str::<ContainerConfig>("cpu_limit = inf").is_err());
+        assert!(toml::from_str::<ContainerConfig>("cpu_limit = 'infm'").is_err());
+    }
+
+    #[test]
+    fn should_parse_restart_policy() {
+        let config =
+            toml::from_str::<ContainerConfig>("restart_policy = 'unless-stopped'").unwrap();
+
+        assert_eq!(config.restart_policy(), RestartPolicy::UnlessStopped);
+        assert_eq!(config.restart_policy().to_string(), "unless-stopped");
+    }
 }
@@ -0,0 +1,181 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::ContainerConfig;
+use crate::models::service::{Service, ServiceStatus};
+use crate::models::CostEstimate;
+use bytesize::ByteSize;
+use std::collections::BTreeMap;
+
+/// Prices of the resources that the containers of the apps request. PREvant uses them to estimate
+/// the hourly and monthly costs of each app.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostsConfig {
+    #[serde(default = "CostsConfig::default_currency")]
+    currency: String,
+    /// The price of one CPU core per hour
+    #[serde(default)]
+    cpu_per_hour: f64,
+    /// The price of one GiB of memory per hour
+    #[serde(default)]
+    memory_per_gb_hour: f64,
+    /// The price of one unit of an extended resource, e.g. `nvidia.com/gpu`, per hour
+    #[serde(default)]
+    extended_resources_per_hour: BTreeMap<String, f64>,
+    /// PREvant does not request a specific amount of CPU for the containers, thus this amount of
+    /// CPU cores will be assumed.
+    #[serde(default = "CostsConfig::default_cpu")]
+    default_cpu: f64,
+    /// The memory that will be assumed if no memory limit has been configured for the containers.
+    #[serde(
+        default,
+        deserialize_with = "ContainerConfig::parse_from_memory_string"
+    )]
+    default_memory: Option<ByteSize>,
+}
+
+impl CostsConfig {
+    fn default_currency() -> String {
+        String::from("EUR")
+    }
+
+    fn default_cpu() -> f64 {
+        0.25
+    }
+
+    pub fn currency(&self) -> &String {
+        &self.currency
+    }
+
+    /// Estimates the costs of the service by multiplying the resources of all replicas with the
+    /// configured prices. Paused services don't cause any costs.
+    pub fn estimate(&self, service: &Service, memory_limit: Option<ByteSize>) -> CostEstimate {
+        let config = service.config();
+        let replicas = match service.status() {
+            ServiceStatus::Paused => 0,
+            ServiceStatus::Running => config
+                .autoscale()
+                .map(|autoscale| autoscale.min())
+                .or_else(|| config.replicas())
+                .unwrap_or(1),
+        };
+
+        let memory_in_gb = memory_limit
+            .or(self.default_memory)
+            .map(|memory| memory.as_u64() as f64 / ByteSize::gib(1).as_u64() as f64)
+            .unwrap_or_default();
+
+        let extended_resources = config
+            .extended_resources()
+            .iter()
+            .filter_map(|(resource, quantity)| {
+                let price = self.extended_resources_per_hour.get(resource)?;
+                let quantity = quantity.parse::<f64>().ok()?;
+                Some(price * quantity)
+            })
+            .sum::<f64>();
+
+        let per_replica = self.default_cpu * self.cpu_per_hour
+            + memory_in_gb * self.memory_per_gb_hour
+            + extended_resources;
+
+        CostEstimate::from_hourly(self.currency.clone(), f64::from(replicas) * per_replica)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ServiceBuilder;
+    use crate::sc;
+
+    fn costs_config() -> CostsConfig {
+        toml::de::from_str::<CostsConfig>(
+            r#"
+            cpuPerHour = 0.04
+            memoryPerGbHour = 0.005
+            defaultCpu = 0.5
+            defaultMemory = "1g"
+
+            [extendedResourcesPerHour]
+            'nvidia.com/gpu' = 0.5
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_estimate_costs_of_replicas() {
+        let mut config = sc!("api", "api:latest");
+        config.set_replicas(Some(2));
+        let service = ServiceBuilder::new()
+            .id(String::from("api"))
+            .app_name(String::from("master"))
+            .config(config)
+            .build()
+            .unwrap();
+
+        let estimate = costs_config().estimate(&service, Some(ByteSize::gib(2)));
+
+        assert_eq!(estimate.currency(), "EUR");
+        assert_eq!(estimate.hourly(), 0.06);
+        assert_eq!(estimate.monthly(), 43.8);
+    }
+
+    #[test]
+    fn should_estimate_costs_of_extended_resources() {
+        let mut config = sc!("api", "api:latest");
+        config.set_extended_resources(BTreeMap::from([(
+            String::from("nvidia.com/gpu"),
+            String::from("1"),
+        )]));
+        let service = ServiceBuilder::new()
+            .id(String::from("api"))
+            .app_name(String::from("master"))
+            .config(config)
+            .build()
+            .unwrap();
+
+        let estimate = costs_config().estimate(&service, None);
+
+        assert_eq!(estimate.hourly(), 0.525);
+    }
+
+    #[test]
+    fn should_not_estimate_costs_of_paused_services() {
+        let service = ServiceBuilder::new()
+            .id(String::from("api"))
+            .app_name(String::from("master"))
+            .config(sc!("api", "api:latest"))
+            .service_status(ServiceStatus::Paused)
+            .build()
+            .unwrap();
+
+        let estimate = costs_config().estimate(&service, None);
+
+        assert_eq!(estimate.hourly(), 0.0);
+    }
+}
@@ -0,0 +1,81 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::AppSelector;
+use secstr::SecUtf8;
+
+/// Protects apps, e.g. `master`, from being deleted by accident. Protected apps can only be
+/// deleted by forced requests that provide the configured token.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionProtectionConfig {
+    #[serde(default)]
+    apps: Vec<AppSelector>,
+    force_token: Option<SecUtf8>,
+}
+
+impl DeletionProtectionConfig {
+    pub fn protects(&self, app_name: &str) -> bool {
+        self.apps.iter().any(|selector| selector.matches(app_name))
+    }
+
+    /// Returns `true` if the token permits the forced deletion of protected apps. Without a
+    /// configured token, protected apps cannot be deleted.
+    pub fn permits_forced_deletion(&self, token: &str) -> bool {
+        self.force_token
+            .as_ref()
+            .map_or(false, |force_token| force_token.unsecure() == token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_protect_matching_apps() {
+        let config = toml::from_str::<DeletionProtectionConfig>(
+            r#"
+            apps = [ 'master', 'release-.*' ]
+            forceToken = 'secret'
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.protects("master"));
+        assert!(config.protects("release-1.0"));
+        assert!(!config.protects("feature-master"));
+        assert!(config.permits_forced_deletion("secret"));
+        assert!(!config.permits_forced_deletion("guess"));
+    }
+
+    #[test]
+    fn should_not_permit_forced_deletion_without_token() {
+        let config = toml::from_str::<DeletionProtectionConfig>("apps = [ 'master' ]").unwrap();
+
+        assert!(!config.permits_forced_deletion(""));
+    }
+}
@@ -0,0 +1,54 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Declares a YAML file with apps, e.g. `master` or `demo`, that PREvant creates, updates, and
+/// deletes to match the file.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DesiredAppsConfig {
+    file: PathBuf,
+    #[serde(default = "DesiredAppsConfig::default_interval")]
+    interval: u64,
+}
+
+impl DesiredAppsConfig {
+    fn default_interval() -> u64 {
+        5 * 60
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// The period, configured in seconds, in which the apps are reconciled even if the file did
+    /// not change, e.g. to revert manual changes.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval)
+    }
+}
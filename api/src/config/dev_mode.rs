@@ -0,0 +1,66 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use std::path::{Component, Path, PathBuf};
+
+/// Allows services deployed by the Docker backend to mount directories of the host, e.g. their
+/// source code, for the inner development loop.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevModeConfig {
+    /// The directories whose content may be mounted into containers.
+    #[serde(default)]
+    source_roots: Vec<PathBuf>,
+}
+
+impl DevModeConfig {
+    /// Whether the `source` is an absolute path within one of the source roots.
+    pub fn permits(&self, source: &Path) -> bool {
+        source.is_absolute()
+            && !source
+                .components()
+                .any(|component| component == Component::ParentDir)
+            && self
+                .source_roots
+                .iter()
+                .any(|source_root| source.starts_with(source_root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_permit_sources_within_source_roots() {
+        let config =
+            toml::de::from_str::<DevModeConfig>("sourceRoots = [ '/home/dev/projects' ]").unwrap();
+
+        assert!(config.permits(Path::new("/home/dev/projects/shop/src")));
+        assert!(!config.permits(Path::new("/home/dev/.ssh")));
+        assert!(!config.permits(Path::new("/home/dev/projects/../.ssh")));
+        assert!(!config.permits(Path::new("projects/shop")));
+    }
+}
@@ -0,0 +1,134 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::AppSelector;
+use serde::{de, Deserialize, Deserializer};
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+
+/// Restricts the outbound traffic of the services of all apps matching the app selector to the
+/// allowed CIDRs and hosts, e.g. so that previews cannot call production APIs by accident.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct EgressRule {
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    #[serde(default, deserialize_with = "deserialize_cidrs")]
+    allowed_cidrs: Vec<String>,
+    #[serde(default)]
+    allowed_hosts: Vec<String>,
+}
+
+impl EgressRule {
+    pub fn matches(&self, app_name: &str) -> bool {
+        self.app_selector.matches(app_name)
+    }
+}
+
+/// The destinations that the services of an app may connect to, merged from all egress rules
+/// that match the app. Traffic within the app and DNS lookups are always allowed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EgressAllowlist {
+    cidrs: BTreeSet<String>,
+    hosts: BTreeSet<String>,
+}
+
+impl EgressAllowlist {
+    pub(super) fn merge(&mut self, rule: &EgressRule) {
+        self.cidrs.extend(rule.allowed_cidrs.iter().cloned());
+        self.hosts.extend(rule.allowed_hosts.iter().cloned());
+    }
+
+    pub fn cidrs(&self) -> &BTreeSet<String> {
+        &self.cidrs
+    }
+
+    /// Hostnames that will be resolved to their IP addresses whenever the app is deployed.
+    pub fn hosts(&self) -> &BTreeSet<String> {
+        &self.hosts
+    }
+}
+
+fn deserialize_cidrs<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let cidrs = Vec::<String>::deserialize(deserializer)?;
+    for cidr in &cidrs {
+        if !is_cidr(cidr) {
+            return Err(de::Error::custom(format!("{cidr} is not a valid CIDR")));
+        }
+    }
+    Ok(cidrs)
+}
+
+fn is_cidr(cidr: &str) -> bool {
+    let Some((ip, prefix)) = cidr.split_once('/') else {
+        return false;
+    };
+    match (ip.parse::<IpAddr>(), prefix.parse::<u8>()) {
+        (Ok(IpAddr::V4(_)), Ok(prefix)) => prefix <= 32,
+        (Ok(IpAddr::V6(_)), Ok(prefix)) => prefix <= 128,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_egress_rule() {
+        let rule = toml::de::from_str::<EgressRule>(
+            r#"
+            appSelector = 'feature-.*'
+            allowedCidrs = [ '10.10.0.0/16', 'fd00::/8' ]
+            allowedHosts = [ 'api.staging.example.com' ]
+            "#,
+        )
+        .unwrap();
+
+        let mut allowlist = EgressAllowlist::default();
+        allowlist.merge(&rule);
+
+        assert!(rule.matches("feature-1234"));
+        assert!(!rule.matches("master"));
+        assert_eq!(
+            allowlist.cidrs(),
+            &BTreeSet::from([String::from("10.10.0.0/16"), String::from("fd00::/8")])
+        );
+        assert_eq!(
+            allowlist.hosts(),
+            &BTreeSet::from([String::from("api.staging.example.com")])
+        );
+    }
+
+    #[test]
+    fn should_not_parse_invalid_cidr() {
+        let rule = toml::de::from_str::<EgressRule>("allowedCidrs = [ '10.10.0.0/33' ]");
+
+        assert!(rule.is_err());
+    }
+}
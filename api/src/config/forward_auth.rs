@@ -0,0 +1,151 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::AppSelector;
+use crate::models::AppName;
+use serde_value::Value;
+use url::Url;
+
+/// Protects the routes of all apps matching the app selector with a Traefik
+/// [forwardAuth middleware](https://doc.traefik.io/traefik/middlewares/http/forwardauth/) that
+/// delegates the authentication to PREvant's `/auth/forward` endpoint or to an external SSO
+/// proxy, e.g. oauth2-proxy.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardAuthConfig {
+    address: Url,
+    #[serde(default = "ForwardAuthConfig::default_trust_forward_header")]
+    trust_forward_header: bool,
+    #[serde(default = "ForwardAuthConfig::default_auth_response_headers")]
+    auth_response_headers: Vec<String>,
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    login_url: Option<Url>,
+    #[serde(default)]
+    redirect_hosts: Vec<String>,
+}
+
+impl ForwardAuthConfig {
+    fn default_trust_forward_header() -> bool {
+        false
+    }
+
+    fn default_auth_response_headers() -> Vec<String> {
+        vec![String::from("X-Forwarded-User")]
+    }
+
+    pub fn matches(&self, app_name: &str) -> bool {
+        self.app_selector.matches(app_name)
+    }
+
+    /// The URL to which PREvant's `/auth/forward` endpoint redirects unauthenticated users or
+    /// `None` if it responds with `401 Unauthorized`.
+    pub fn login_url(&self) -> Option<&Url> {
+        self.login_url.as_ref()
+    }
+
+    /// Returns `true` if unauthenticated users may be sent back to the URL after the login, i.e.
+    /// if the host of the URL is PREvant's host or matches one of the redirect hosts. A redirect
+    /// host `*.example.com` matches all subdomains of `example.com`.
+    pub fn permits_redirect_to(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        self.address.host_str() == Some(host)
+            || self.redirect_hosts.iter().any(|redirect_host| {
+                match redirect_host.strip_prefix('*') {
+                    Some(domain) => {
+                        domain.starts_with('.') && host.ends_with(&domain.to_ascii_lowercase())
+                    }
+                    None => redirect_host.eq_ignore_ascii_case(host),
+                }
+            })
+    }
+
+    /// Creates the spec of the Traefik `forwardAuth` middleware. The address gets the name of the
+    /// app as `app` parameter so that PREvant's endpoint is able to accept the share tokens of
+    /// the app.
+    pub fn middleware(&self, app_name: &AppName) -> Value {
+        let mut address = self.address.clone();
+        address.query_pairs_mut().append_pair("app", app_name);
+
+        serde_value::to_value(serde_json::json!({
+            "address": address.as_str(),
+            "trustForwardHeader": self.trust_forward_header,
+            "authResponseHeaders": self.auth_response_headers,
+        }))
+        .expect("JSON values should be convertible")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_create_forward_auth_middleware() {
+        let config = toml::de::from_str::<ForwardAuthConfig>(
+            r#"
+            address = 'http://prevant.tools.svc/auth/forward'
+            appSelector = 'review-.+'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.middleware(&AppName::from_str("review-1234").unwrap()),
+            serde_value::to_value(serde_json::json!({
+                "address": "http://prevant.tools.svc/auth/forward?app=review-1234",
+                "trustForwardHeader": false,
+                "authResponseHeaders": ["X-Forwarded-User"]
+            }))
+            .unwrap()
+        );
+        assert!(config.matches("review-1234"));
+        assert!(!config.matches("master"));
+        assert_eq!(config.login_url(), None);
+    }
+
+    #[test]
+    fn should_permit_redirects_to_allowed_hosts() {
+        let config = toml::de::from_str::<ForwardAuthConfig>(
+            r#"
+            address = 'https://prevant.example.com/auth/forward'
+            redirectHosts = [ '*.previews.example.com', 'docs.example.com' ]
+            "#,
+        )
+        .unwrap();
+
+        let permits = |url: &str| config.permits_redirect_to(&Url::parse(url).unwrap());
+        assert!(permits("https://prevant.example.com/master/wordpress/"));
+        assert!(permits("https://master.previews.example.com/"));
+        assert!(permits("https://docs.example.com/"));
+        assert!(!permits("https://previews.example.com.evil.com/"));
+        assert!(!permits("https://evilpreviews.example.com/"));
+        assert!(!permits("https://evil.com/?docs.example.com"));
+    }
+}
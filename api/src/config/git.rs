@@ -0,0 +1,140 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use secstr::SecUtf8;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Configures how PREvant fetches deployment descriptors from Git repositories.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitConfig {
+    #[serde(default = "GitConfig::default_command")]
+    command: PathBuf,
+    /// The credentials of the Git hosts, e.g. `github.com`, which are sent with HTTPS requests.
+    #[serde(default)]
+    credentials: BTreeMap<String, GitCredentials>,
+    /// The hosts that descriptors may be fetched from in addition to the hosts with credentials.
+    #[serde(default)]
+    allowed_hosts: Vec<String>,
+    /// The number of seconds a fetch may take.
+    #[serde(default = "GitConfig::default_timeout")]
+    timeout: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitCredentials {
+    username: String,
+    password: SecUtf8,
+}
+
+impl GitConfig {
+    fn default_command() -> PathBuf {
+        PathBuf::from("git")
+    }
+
+    fn default_timeout() -> u64 {
+        60
+    }
+
+    /// The path of the Git executable.
+    pub fn command(&self) -> &PathBuf {
+        &self.command
+    }
+
+    pub fn credentials(&self, host: &str) -> Option<(&str, &SecUtf8)> {
+        self.credentials
+            .get(host)
+            .map(|credentials| (credentials.username.as_str(), &credentials.password))
+    }
+
+    /// Whether descriptors may be fetched from the host, i.e. whether the host is allowed
+    /// explicitly or has credentials. Otherwise, the requests would reach any host that is
+    /// reachable from PREvant.
+    pub fn permits_host(&self, host: &str) -> bool {
+        self.credentials.contains_key(host) || self.allowed_hosts.iter().any(|h| h == host)
+    }
+
+    /// The maximal time a fetch may take until it is aborted.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            command: Self::default_command(),
+            credentials: BTreeMap::new(),
+            allowed_hosts: Vec::new(),
+            timeout: Self::default_timeout(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_git_config() {
+        let config = toml::de::from_str::<GitConfig>(
+            r#"
+            [credentials.'github.com']
+            username = 'prevant'
+            password = 'token'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.command(), &PathBuf::from("git"));
+        assert_eq!(
+            config
+                .credentials("github.com")
+                .map(|(username, password)| (username, password.unsecure())),
+            Some(("prevant", "token"))
+        );
+        assert_eq!(config.credentials("gitlab.com"), None);
+    }
+
+    #[test]
+    fn should_permit_allowed_hosts_and_hosts_with_credentials() {
+        let config = toml::de::from_str::<GitConfig>(
+            r#"
+            allowedHosts = [ 'gitlab.com' ]
+
+            [credentials.'github.com']
+            username = 'prevant'
+            password = 'token'
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.permits_host("github.com"));
+        assert!(config.permits_host("gitlab.com"));
+        assert!(!config.permits_host("169.254.169.254"));
+    }
+}
@@ -0,0 +1,135 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::AppSelector;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Configures the [kustomize](https://kustomize.io) overlays that are applied to the manifests
+/// PREvant generates on Kubernetes, e.g. to add site-specific labels, sidecars, or pull secrets.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KustomizeConfig {
+    #[serde(default = "KustomizeConfig::default_command")]
+    command: PathBuf,
+    #[serde(default)]
+    overlays: Vec<KustomizeOverlay>,
+}
+
+/// A directory containing a kustomization of the kind `Component` that will be applied to the
+/// manifests of all apps matching the app selector.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KustomizeOverlay {
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    #[serde(deserialize_with = "KustomizeOverlay::parse_path")]
+    path: PathBuf,
+}
+
+impl KustomizeOverlay {
+    /// Canonicalizes the path when the configuration is loaded so that a missing overlay is
+    /// reported immediately and kustomize receives absolute paths without symbolic links,
+    /// independent of the working directory of PREvant.
+    fn parse_path<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let path = PathBuf::deserialize(deserializer)?;
+        std::fs::canonicalize(&path).map_err(|err| {
+            serde::de::Error::custom(format!(
+                "Cannot resolve the kustomize overlay {}: {}",
+                path.display(),
+                err
+            ))
+        })
+    }
+}
+
+impl KustomizeConfig {
+    fn default_command() -> PathBuf {
+        PathBuf::from("kustomize")
+    }
+
+    /// The path of the kustomize executable.
+    pub fn command(&self) -> &PathBuf {
+        &self.command
+    }
+
+    /// Returns the overlays matching the app name in the order of their configuration.
+    pub fn overlays(&self, app_name: &str) -> Vec<&PathBuf> {
+        self.overlays
+            .iter()
+            .filter(|overlay| overlay.app_selector.matches(app_name))
+            .map(|overlay| &overlay.path)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_return_overlays_of_matching_apps() {
+        let directory = tempfile::tempdir().unwrap();
+        let common = directory.path().join("common");
+        let features = directory.path().join("features");
+        std::fs::create_dir(&common).unwrap();
+        std::fs::create_dir(&features).unwrap();
+
+        let config = toml::de::from_str::<KustomizeConfig>(&format!(
+            r#"
+            [[overlays]]
+            path = '{}'
+
+            [[overlays]]
+            appSelector = 'feature-.*'
+            path = '{}/../features'
+            "#,
+            common.display(),
+            common.display()
+        ))
+        .unwrap();
+
+        let common = std::fs::canonicalize(common).unwrap();
+        let features = std::fs::canonicalize(features).unwrap();
+        assert_eq!(config.command(), &PathBuf::from("kustomize"));
+        assert_eq!(config.overlays("master"), vec![&common]);
+        assert_eq!(config.overlays("feature-123"), vec![&common, &features]);
+    }
+
+    #[test]
+    fn should_reject_missing_overlays() {
+        let config = toml::de::from_str::<KustomizeConfig>(
+            r#"
+            [[overlays]]
+            path = '/etc/prevant/kustomize/missing'
+            "#,
+        );
+
+        assert!(config.is_err());
+    }
+}
@@ -0,0 +1,84 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use std::path::Path;
+use std::time::Duration;
+
+/// Multiple replicas of PREvant elect a leader through a [Kubernetes
+/// lease](https://kubernetes.io/docs/concepts/architecture/leases/). Only the leader deploys and
+/// deletes apps while all replicas serve read requests.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderElectionConfig {
+    #[serde(default = "LeaderElectionConfig::default_lease_name")]
+    lease_name: String,
+    namespace: Option<String>,
+    #[serde(default = "LeaderElectionConfig::default_lease_duration")]
+    lease_duration: u64,
+    #[serde(default = "LeaderElectionConfig::default_renew_interval")]
+    renew_interval: u64,
+}
+
+impl LeaderElectionConfig {
+    fn default_lease_name() -> String {
+        String::from("prevant")
+    }
+
+    fn default_lease_duration() -> u64 {
+        15
+    }
+
+    fn default_renew_interval() -> u64 {
+        5
+    }
+
+    pub fn lease_name(&self) -> &String {
+        &self.lease_name
+    }
+
+    /// The namespace of the lease. By default, PREvant uses the namespace it is running in.
+    pub fn namespace(&self) -> String {
+        const SERVICE_ACCOUNT_NAMESPACE: &str =
+            "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+        self.namespace.clone().unwrap_or_else(|| {
+            std::fs::read_to_string(Path::new(SERVICE_ACCOUNT_NAMESPACE))
+                .map(|namespace| namespace.trim().to_string())
+                .unwrap_or_else(|_| String::from("default"))
+        })
+    }
+
+    /// The period, configured in seconds, after which other replicas may take over the lease if
+    /// the leader did not renew it.
+    pub fn lease_duration(&self) -> Duration {
+        Duration::from_secs(self.lease_duration)
+    }
+
+    /// The interval, configured in seconds, in which the leader renews the lease and the other
+    /// replicas try to acquire it.
+    pub fn renew_interval(&self) -> Duration {
+        Duration::from_secs(self.renew_interval)
+    }
+}
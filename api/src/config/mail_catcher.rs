@@ -0,0 +1,135 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::AppSelector;
+use crate::models::service::ContainerType;
+use crate::models::{EnvironmentVariable, Image, ServiceConfig};
+use secstr::SecUtf8;
+use std::str::FromStr;
+
+/// Configures a mail catcher, e.g. [MailHog](https://github.com/mailhog/MailHog), that PREvant
+/// deploys as companion of each app. The services of the app send their mails to the catcher
+/// instead of a real SMTP server and the captured mails can be listed through PREvant's API.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MailCatcherConfig {
+    #[serde(default = "MailCatcherConfig::default_image")]
+    image: Image,
+    #[serde(default = "MailCatcherConfig::default_service_name")]
+    service_name: String,
+    #[serde(default = "MailCatcherConfig::default_smtp_port")]
+    smtp_port: u16,
+    #[serde(default = "MailCatcherConfig::default_http_port")]
+    http_port: u16,
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+}
+
+impl MailCatcherConfig {
+    fn default_image() -> Image {
+        Image::from_str("mailhog/mailhog:v1.0.1").unwrap()
+    }
+
+    fn default_service_name() -> String {
+        String::from("mail")
+    }
+
+    fn default_smtp_port() -> u16 {
+        1025
+    }
+
+    fn default_http_port() -> u16 {
+        8025
+    }
+
+    pub fn service_name(&self) -> &String {
+        &self.service_name
+    }
+
+    pub fn matches_app_name(&self, app_name: &str) -> bool {
+        self.app_selector.matches(app_name)
+    }
+
+    /// The application companion that runs the mail catcher. Its web interface is routed like
+    /// any other service of the app and its SMTP port is reachable by the services of the app.
+    pub fn companion_config(&self) -> ServiceConfig {
+        let mut config = ServiceConfig::new(self.service_name.clone(), self.image.clone());
+        config.set_container_type(ContainerType::ApplicationCompanion);
+        // The image exposes both ports, thus the exposed ports must not replace the HTTP port.
+        config.set_fixed_port(self.http_port);
+        config.set_additional_ports(vec![self.smtp_port]);
+        config
+    }
+
+    /// Points the service to the SMTP server of the mail catcher through the variables
+    /// `SMTP_HOST` and `SMTP_PORT` unless the service defines them itself.
+    pub fn add_smtp_env_to(&self, service_config: &mut ServiceConfig) {
+        service_config.add_env(EnvironmentVariable::new(
+            String::from("SMTP_HOST"),
+            SecUtf8::from(self.service_name.as_str()),
+        ));
+        service_config.add_env(EnvironmentVariable::new(
+            String::from("SMTP_PORT"),
+            SecUtf8::from(self.smtp_port.to_string()),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_mail_catcher_with_defaults() {
+        let config = toml::de::from_str::<MailCatcherConfig>("").unwrap();
+
+        let companion = config.companion_config();
+        assert_eq!(companion.service_name(), "mail");
+        assert_eq!(companion.port(), 8025);
+        assert_eq!(companion.additional_ports(), &vec![1025]);
+        assert_eq!(
+            companion.image().to_string(),
+            "docker.io/mailhog/mailhog:v1.0.1"
+        );
+
+        let mut service_config =
+            ServiceConfig::new(String::from("api"), Image::from_str("api").unwrap());
+        service_config.add_env(EnvironmentVariable::new(
+            String::from("SMTP_PORT"),
+            SecUtf8::from("25"),
+        ));
+        config.add_smtp_env_to(&mut service_config);
+
+        let env = service_config.env().unwrap();
+        assert_eq!(
+            env.variable("SMTP_HOST").unwrap().value(),
+            &SecUtf8::from("mail")
+        );
+        assert_eq!(
+            env.variable("SMTP_PORT").unwrap().value(),
+            &SecUtf8::from("25")
+        );
+    }
+}
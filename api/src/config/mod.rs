@@ -24,17 +24,60 @@
  * =========================LICENSE_END==================================
  */
 
+pub use self::admin::AdminConfig;
+pub use self::app_name_policy::AppNamePolicy;
+pub use self::audit::{AuditConfig, AuditExport, SyslogProtocol};
+pub use self::builds::{Builder, BuildsConfig};
+pub use self::capacity::{CapacityCheckConfig, CapacityCheckMode};
 pub use self::companion::DeploymentStrategy;
 pub use self::companion::StorageStrategy;
 use self::companion::{Companion, CompanionType};
-pub use self::container::ContainerConfig;
+pub use self::container::{ContainerConfig, RestartPolicy};
+pub use self::costs::CostsConfig;
+pub use self::deletion_protection::DeletionProtectionConfig;
+pub use self::desired_apps::DesiredAppsConfig;
+pub use self::dev_mode::DevModeConfig;
+pub use self::egress::EgressAllowlist;
+use self::egress::EgressRule;
+pub use self::forward_auth::ForwardAuthConfig;
+pub use self::git::GitConfig;
+pub use self::helm::HelmConfig;
+pub use self::kustomize::KustomizeConfig;
+pub use self::leader_election::LeaderElectionConfig;
+pub use self::log_archive::{LogArchiveConfig, LokiConfig, S3Config};
+pub use self::mail_catcher::MailCatcherConfig;
+pub use self::network::NetworkConfig;
+pub use self::notifications::{NotificationChannelConfig, NotificationEvent, NotificationTarget};
+pub use self::placeholder::PlaceholderConfig;
+pub use self::reload::{watch_config_file, ConfigHandle};
+pub use self::runtime::DockerTraefikConfig;
+#[cfg(any(test, feature = "dummy-infrastructure"))]
+pub use self::runtime::InfrastructureOperation;
 pub use self::runtime::Runtime;
-use crate::models::ServiceConfig;
+use self::runtime::Runtimes;
+pub use self::runtime::{CertManagerConfig, IstioConfig, KubernetesAppHostConfig, ServiceMesh};
+pub use self::sbom::SbomConfig;
+pub use self::service_accounts::ServiceAccountsConfig;
+use self::service_doubles::{ServiceDouble, BUILT_IN_SERVICE_DOUBLES};
+use self::service_template::ServiceTemplate;
+pub use self::service_template::ServiceTemplateError;
+pub use self::shared_database::SharedDatabaseConfig;
+pub use self::soft_delete::SoftDeleteConfig;
+use self::stack::Stack;
+pub use self::state::{PostgresStateConfig, RedisStateConfig, StateConfig};
+pub use self::static_assets::{BundleFormat, StaticAssetsConfig, StaticAssetsStorage};
+pub use self::trivy::TrivyConfig;
+pub use self::trusted_proxy::TrustedProxyConfig;
+pub use self::validation::{validate, validate_config};
+use crate::models::{
+    AppName, CustomResource, Environment, HelmRelease, ResourceMetadata, ServiceConfig,
+};
 pub(self) use app_selector::AppSelector;
 use clap::Parser;
 use figment::providers::{Env, Format, Toml};
 use figment::value::{Dict, Map, Tag, Value};
 use figment::{Metadata, Profile};
+use handlebars::RenderError;
 pub(self) use secret::Secret;
 use secstr::SecUtf8;
 use std::collections::BTreeMap;
@@ -45,13 +88,46 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use toml::de::Error as TomlError;
 
+mod admin;
+mod app_name_policy;
 mod app_selector;
+mod audit;
+mod builds;
+mod capacity;
 mod companion;
 mod container;
+mod costs;
+mod deletion_protection;
+mod desired_apps;
+mod dev_mode;
+mod egress;
+mod forward_auth;
+mod git;
+mod helm;
+mod kustomize;
+mod leader_election;
+mod log_archive;
+mod mail_catcher;
+mod network;
+mod notifications;
+mod placeholder;
+mod reload;
 mod runtime;
+mod sbom;
 mod secret;
-
-#[derive(Default, Parser)]
+mod service_accounts;
+mod service_doubles;
+mod service_template;
+mod shared_database;
+mod soft_delete;
+mod stack;
+mod state;
+mod static_assets;
+mod trivy;
+mod trusted_proxy;
+mod validation;
+
+#[derive(Clone, Default, Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct CliArgs {
     /// Sets a custom config file
@@ -61,6 +137,22 @@ pub struct CliArgs {
     /// Sets the container backend type, e.g. Docker or Kubernetes
     #[clap(short, long)]
     runtime_type: Option<RuntimeTypeCliFlag>,
+
+    /// Validates the configuration and exits without starting the server
+    #[clap(long)]
+    check_config: bool,
+}
+
+impl CliArgs {
+    pub fn config_file(&self) -> PathBuf {
+        self.config
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("config.toml"))
+    }
+
+    pub fn check_config(&self) -> bool {
+        self.check_config
+    }
 }
 
 #[derive(Clone)]
@@ -136,14 +228,55 @@ pub enum JiraAuth {
 }
 
 #[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct Service {
     secrets: Option<Vec<Secret>>,
+    metadata: Option<ResourceMetadata>,
+    #[serde(
+        default,
+        deserialize_with = "crate::models::deserialize_extended_resources"
+    )]
+    extended_resources: BTreeMap<String, String>,
+}
+
+/// Labels and annotations that will be attached to the resources of all apps matching the
+/// app selector.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppMetadata {
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    #[serde(flatten)]
+    metadata: ResourceMetadata,
+}
+
+/// Headers that will be injected into the requests to and the responses of the services of all
+/// apps matching the app selector, e.g. `X-Robots-Tag: noindex`.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppHeaders {
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    #[serde(default)]
+    request: BTreeMap<String, String>,
+    #[serde(default)]
+    response: BTreeMap<String, String>,
+}
+
+/// Environment variables that will be injected into all services of all apps matching the app
+/// selector, e.g. `SENTRY_ENVIRONMENT = '{{application.name}}'`.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppEnv {
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    env: Environment,
 }
 
 #[derive(Clone, Default, Deserialize)]
 pub struct Config {
     #[serde(default)]
-    runtime: Runtime,
+    runtime: Runtimes,
     containers: Option<ContainerConfig>,
     jira: Option<JiraConfig>,
     companions: Option<BTreeMap<String, Companion>>,
@@ -151,6 +284,67 @@ pub struct Config {
     hooks: Option<BTreeMap<String, PathBuf>>,
     #[serde(default)]
     registries: BTreeMap<String, Registry>,
+    #[serde(default)]
+    metadata: Vec<AppMetadata>,
+    #[serde(default)]
+    headers: Vec<AppHeaders>,
+    #[serde(rename = "appEnv", default)]
+    app_env: Vec<AppEnv>,
+    #[serde(default)]
+    stacks: BTreeMap<String, Stack>,
+    #[serde(rename = "serviceDoubles", default)]
+    service_doubles: BTreeMap<String, ServiceDouble>,
+    #[serde(default)]
+    templates: BTreeMap<String, ServiceTemplate>,
+    trivy: Option<TrivyConfig>,
+    #[serde(default)]
+    helm: HelmConfig,
+    kustomize: Option<KustomizeConfig>,
+    #[serde(default)]
+    network: NetworkConfig,
+    placeholder: Option<PlaceholderConfig>,
+    #[serde(rename = "forwardAuth")]
+    forward_auth: Option<ForwardAuthConfig>,
+    #[serde(rename = "trustedProxy")]
+    trusted_proxy: Option<TrustedProxyConfig>,
+    #[serde(default)]
+    admin: AdminConfig,
+    #[serde(rename = "logArchive")]
+    log_archive: Option<LogArchiveConfig>,
+    #[serde(rename = "mailCatcher")]
+    mail_catcher: Option<MailCatcherConfig>,
+    #[serde(rename = "sharedDatabase")]
+    shared_database: Option<SharedDatabaseConfig>,
+    #[serde(rename = "appNames", default)]
+    app_names: AppNamePolicy,
+    #[serde(rename = "deletionProtection", default)]
+    deletion_protection: DeletionProtectionConfig,
+    #[serde(rename = "softDelete")]
+    soft_delete: Option<SoftDeleteConfig>,
+    #[serde(rename = "leaderElection")]
+    leader_election: Option<LeaderElectionConfig>,
+    #[serde(default)]
+    state: StateConfig,
+    #[serde(default)]
+    git: GitConfig,
+    costs: Option<CostsConfig>,
+    #[serde(default)]
+    notifications: Vec<NotificationChannelConfig>,
+    #[serde(rename = "staticAssets")]
+    static_assets: Option<StaticAssetsConfig>,
+    builds: Option<BuildsConfig>,
+    sbom: Option<SbomConfig>,
+    audit: Option<AuditConfig>,
+    #[serde(rename = "desiredApps")]
+    desired_apps: Option<DesiredAppsConfig>,
+    #[serde(rename = "capacityCheck")]
+    capacity_check: Option<CapacityCheckConfig>,
+    #[serde(default)]
+    egress: Vec<EgressRule>,
+    #[serde(rename = "devMode")]
+    dev_mode: Option<DevModeConfig>,
+    #[serde(default, rename = "serviceAccounts")]
+    service_accounts: ServiceAccountsConfig,
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
@@ -161,19 +355,79 @@ struct Registry {
 
 impl Config {
     pub fn from_figment(cli: &CliArgs) -> Result<Self, figment::Error> {
+        let config = Self::figment(cli).extract::<Config>()?;
+
+        if let Some((path, message)) = config.companion_issues().into_iter().next() {
+            return Err(figment::Error::from(format!("{}: {}", path, message)));
+        }
+        Ok(config)
+    }
+
+    /// Returns the sources of the configuration: the configuration file, the environment variables,
+    /// and the command line arguments.
+    pub(super) fn figment(cli: &CliArgs) -> figment::Figment {
         figment::Figment::new()
-            .merge(Toml::file(
-                cli.config
-                    .as_ref()
-                    .unwrap_or(&PathBuf::from_str("config.toml").unwrap()),
-            ))
+            .merge(Toml::file(cli.config_file()))
             .merge(Env::prefixed("PREVANT_").split("_"))
             .merge(cli)
-            .extract::<Config>()
+    }
+
+    /// Returns the path and the description of companions that lack an image, a chart, or a
+    /// manifest. Stacks and service doubles cannot be Helm charts or custom resources.
+    pub(super) fn companion_issues(&self) -> Vec<(String, String)> {
+        let mut issues = Vec::new();
+        for (name, companion) in self.companions.iter().flatten() {
+            if let Err(message) = companion.check() {
+                issues.push((format!("companions.{}", name), message));
+            }
+        }
+
+        let nested_companions = self
+            .stacks
+            .iter()
+            .flat_map(|(stack_name, stack)| {
+                stack.companions().map(move |(name, companion)| {
+                    (
+                        format!("stacks.{}.companions.{}", stack_name, name),
+                        companion,
+                    )
+                })
+            })
+            .chain(self.service_doubles.iter().filter_map(|(name, double)| {
+                double
+                    .companion()
+                    .map(|companion| (format!("serviceDoubles.{}", name), companion))
+            }));
+        for (path, companion) in nested_companions {
+            if !companion.is_container() {
+                issues.push((
+                    path,
+                    String::from("stacks and service doubles can only run containers"),
+                ));
+            } else if let Err(message) = companion.check() {
+                issues.push((path, message));
+            }
+        }
+
+        issues
     }
 
     pub fn runtime_config(&self) -> &Runtime {
-        &self.runtime
+        self.runtime.default_runtime()
+    }
+
+    /// Further runtimes, e.g. other Kubernetes clusters, that can be used as deployment target
+    /// by their name.
+    pub fn runtime_clusters(&self) -> &BTreeMap<String, Runtime> {
+        self.runtime.clusters()
+    }
+
+    /// Returns a copy of this configuration that uses the given runtime, e.g. one of the
+    /// clusters, as its only runtime.
+    pub fn with_runtime(&self, runtime: Runtime) -> Config {
+        let mut config = self.clone();
+        config.runtime = Runtimes::from(runtime);
+        config
     }
 
     pub fn container_config(&self) -> ContainerConfig {
@@ -187,6 +441,203 @@ impl Config {
         self.jira.as_ref().cloned()
     }
 
+    pub fn network_config(&self) -> &NetworkConfig {
+        &self.network
+    }
+
+    /// The configuration of the placeholder page or `None` if Traefik's error pages will be shown.
+    pub fn placeholder_config(&self) -> Option<&PlaceholderConfig> {
+        self.placeholder.as_ref()
+    }
+
+    /// Adds a Traefik `errors` middleware to the service config that shows the placeholder page
+    /// while the service is unavailable.
+    pub fn add_placeholder_to(&self, service_config: &mut ServiceConfig, app_name: &AppName) {
+        if let Some(placeholder) = &self.placeholder {
+            service_config.add_middleware(
+                String::from("errors"),
+                placeholder.errors_middleware(app_name, service_config.service_name()),
+            );
+        }
+    }
+
+    /// The configuration of the forward authentication or `None` if the routes of the apps are
+    /// publicly accessible.
+    pub fn forward_auth_config(&self) -> Option<&ForwardAuthConfig> {
+        self.forward_auth.as_ref()
+    }
+
+    /// The proxy whose user headers will be trusted or `None` if PREvant ignores these headers.
+    pub fn trusted_proxy_config(&self) -> Option<&TrustedProxyConfig> {
+        self.trusted_proxy.as_ref()
+    }
+
+    /// The users that are permitted to use the administrative endpoints.
+    pub fn admin_config(&self) -> &AdminConfig {
+        &self.admin
+    }
+
+    /// Adds a Traefik `forwardAuth` middleware to the service config if the app has to be
+    /// protected by the forward authentication. A `forwardAuth` middleware of the payload is
+    /// replaced so that deployments cannot bypass the authentication.
+    pub fn add_forward_auth_to(&self, service_config: &mut ServiceConfig, app_name: &AppName) {
+        if let Some(forward_auth) = self
+            .forward_auth
+            .as_ref()
+            .filter(|forward_auth| forward_auth.matches(app_name))
+        {
+            service_config.set_middleware(
+                String::from("forwardAuth"),
+                forward_auth.middleware(app_name),
+            );
+        }
+    }
+
+    /// The configuration of the vulnerability scanning or `None` if images won't be scanned.
+    pub fn trivy_config(&self) -> Option<&TrivyConfig> {
+        self.trivy.as_ref()
+    }
+
+    pub fn helm_config(&self) -> &HelmConfig {
+        &self.helm
+    }
+
+    pub fn kustomize_config(&self) -> Option<&KustomizeConfig> {
+        self.kustomize.as_ref()
+    }
+
+    /// Returns the custom resources of the companions of the type `custom-resource` that match
+    /// the app name.
+    pub fn custom_resources(&self, app_name: &str) -> Result<Vec<CustomResource>, RenderError> {
+        self.companions(app_name)
+            .filter_map(|companion| companion.custom_resource(app_name).transpose())
+            .collect()
+    }
+
+    /// Returns the releases of the companions of the type `helm` that match the app name.
+    pub fn helm_releases(&self, app_name: &str) -> Result<Vec<HelmRelease>, RenderError> {
+        self.companions(app_name)
+            .filter_map(|companion| companion.helm_release(app_name).transpose())
+            .collect()
+    }
+
+    /// The sink the logs of the services are archived to before an app is deleted, or `None` if
+    /// the logs won't be archived.
+    pub fn log_archive_config(&self) -> Option<&LogArchiveConfig> {
+        self.log_archive.as_ref()
+    }
+
+    /// The constraints for the names of new apps.
+    pub fn app_name_policy(&self) -> &AppNamePolicy {
+        &self.app_names
+    }
+
+    pub fn soft_delete_config(&self) -> Option<&SoftDeleteConfig> {
+        self.soft_delete.as_ref()
+    }
+
+    pub fn leader_election_config(&self) -> Option<&LeaderElectionConfig> {
+        self.leader_election.as_ref()
+    }
+
+    /// The prices of the resources to estimate the costs of the apps or `None` if the costs
+    /// won't be estimated.
+    pub fn costs_config(&self) -> Option<&CostsConfig> {
+        self.costs.as_ref()
+    }
+
+    /// The channels, e.g. Slack channels, that will be notified about changes of the apps.
+    pub fn notification_channels(&self) -> &[NotificationChannelConfig] {
+        &self.notifications
+    }
+
+    /// The hosting of uploaded static assets or `None` if static assets are not supported.
+    pub fn static_assets_config(&self) -> Option<&StaticAssetsConfig> {
+        self.static_assets.as_ref()
+    }
+
+    /// The builds of images from sources or `None` if apps must be deployed with prebuilt images.
+    pub fn builds_config(&self) -> Option<&BuildsConfig> {
+        self.builds.as_ref()
+    }
+
+    /// The generation of SBOMs of the deployed images or `None` if no SBOMs will be generated.
+    pub fn sbom_config(&self) -> Option<&SbomConfig> {
+        self.sbom.as_ref()
+    }
+
+    /// The configuration of the audit log or `None` if the requests won't be audited.
+    pub fn audit_config(&self) -> Option<&AuditConfig> {
+        self.audit.as_ref()
+    }
+
+    /// The configuration of the file of apps that PREvant keeps in sync or `None` if all apps
+    /// are managed through the API.
+    pub fn desired_apps_config(&self) -> Option<&DesiredAppsConfig> {
+        self.desired_apps.as_ref()
+    }
+
+    /// The check of the cluster's capacity before deployments or `None` if deployments are
+    /// applied regardless of the free resources.
+    pub fn capacity_check_config(&self) -> Option<&CapacityCheckConfig> {
+        self.capacity_check.as_ref()
+    }
+
+    /// The dev mode that permits services to mount directories of the Docker host or `None` if
+    /// dev mounts are rejected.
+    pub fn dev_mode_config(&self) -> Option<&DevModeConfig> {
+        self.dev_mode.as_ref()
+    }
+
+    /// The allowlist of the rules that the service accounts of deployment payloads may request.
+    pub fn service_accounts_config(&self) -> &ServiceAccountsConfig {
+        &self.service_accounts
+    }
+
+    pub fn has_egress_rules(&self) -> bool {
+        !self.egress.is_empty()
+    }
+
+    /// The destinations that the services of the app may connect to or `None` if the outbound
+    /// traffic of the app is not restricted.
+    pub fn egress_allowlist(&self, app_name: &str) -> Option<EgressAllowlist> {
+        let mut rules = self
+            .egress
+            .iter()
+            .filter(|rule| rule.matches(app_name))
+            .peekable();
+        rules.peek()?;
+
+        let mut allowlist = EgressAllowlist::default();
+        for rule in rules {
+            allowlist.merge(rule);
+        }
+        Some(allowlist)
+    }
+
+    pub fn state_config(&self) -> &StateConfig {
+        &self.state
+    }
+
+    pub fn deletion_protection(&self) -> &DeletionProtectionConfig {
+        &self.deletion_protection
+    }
+
+    /// The mail catcher that will be deployed with the app or `None` if the app does not get one.
+    pub fn mail_catcher_config(&self, app_name: &str) -> Option<&MailCatcherConfig> {
+        self.mail_catcher.as_ref().filter(|mail_catcher| {
+            app_name != AppName::shared_companions().as_str()
+                && mail_catcher.matches_app_name(app_name)
+        })
+    }
+
+    pub fn shared_database_config(&self, app_name: &str) -> Option<&SharedDatabaseConfig> {
+        self.shared_database.as_ref().filter(|shared_database| {
+            app_name != AppName::shared_companions().as_str()
+                && shared_database.matches_app_name(app_name)
+        })
+    }
+
     pub fn service_companion_configs(
         &self,
         app_name: &str,
@@ -205,6 +656,48 @@ impl Config {
         })
     }
 
+    /// Returns the shared companions the app uses. These companions are deployed once into the
+    /// app [`AppName::shared_companions`] instead of into the app itself.
+    pub fn shared_companion_configs(
+        &self,
+        app_name: &str,
+    ) -> Vec<(ServiceConfig, DeploymentStrategy, StorageStrategy)> {
+        self.companion_configs(app_name, |companion| {
+            companion.companion_type() == &CompanionType::Shared
+        })
+    }
+
+    /// Adds the `appEnv` of the shared companions the app uses to the service config. The
+    /// variables are templated with the app, e.g. to point each app to its own realm, and do not
+    /// override variables of the service.
+    pub fn add_shared_companion_env_to(&self, service_config: &mut ServiceConfig, app_name: &str) {
+        for companion in self.companions(app_name) {
+            if companion.companion_type() != &CompanionType::Shared {
+                continue;
+            }
+
+            if let Some(app_env) = companion.app_env() {
+                for variable in app_env.iter() {
+                    service_config.add_env(variable.clone().with_templated(true));
+                }
+            }
+        }
+    }
+
+    /// Adds the variables of all app env sections matching the app name to the service config.
+    /// Variables of the service config have precedence.
+    pub fn add_app_env_to(&self, service_config: &mut ServiceConfig, app_name: &str) {
+        for app_env in self
+            .app_env
+            .iter()
+            .filter(|app_env| app_env.app_selector.matches(app_name))
+        {
+            for variable in app_env.env.iter() {
+                service_config.add_env(variable.clone().with_templated(true));
+            }
+        }
+    }
+
     fn companion_configs<P>(
         &self,
         app_name: &str,
@@ -213,21 +706,54 @@ impl Config {
     where
         P: Fn(&Companion) -> bool,
     {
-        match &self.companions {
-            None => vec![],
-            Some(companions_map) => companions_map
-                .iter()
-                .filter(|(_, companion)| companion.matches_app_name(app_name))
-                .filter(|(_, companion)| predicate(companion))
-                .map(|(_, companion)| {
-                    (
-                        companion.clone().into(),
-                        companion.deployment_strategy().clone(),
-                        companion.storage_strategy().clone(),
-                    )
-                })
-                .collect(),
-        }
+        self.companions(app_name)
+            .filter(|companion| predicate(companion))
+            .map(|companion| {
+                (
+                    companion.clone().into(),
+                    companion.deployment_strategy().clone(),
+                    companion.storage_strategy().clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the companions that match the app name. The app of the shared companions does not
+    /// get any companions itself.
+    fn companions<'a>(&'a self, app_name: &'a str) -> impl Iterator<Item = &'a Companion> + 'a {
+        let is_shared_app = app_name == AppName::shared_companions().as_str();
+        self.companions
+            .iter()
+            .flat_map(|companions| companions.values())
+            .filter(move |companion| !is_shared_app && companion.matches_app_name(app_name))
+    }
+
+    /// Returns the companions of the stack with the given name that match the app name or `None`
+    /// if there is no such stack.
+    pub fn stack_companion_configs(
+        &self,
+        stack_name: &str,
+        app_name: &str,
+    ) -> Option<Vec<(ServiceConfig, DeploymentStrategy, StorageStrategy)>> {
+        self.stacks
+            .get(stack_name)
+            .map(|stack| stack.companion_configs(app_name))
+    }
+
+    /// Returns the service double with the given name. Configured doubles replace the built-in
+    /// ones with the same name.
+    pub fn service_double(&self, name: &str) -> Option<&ServiceDouble> {
+        self.service_doubles
+            .get(name)
+            .or_else(|| BUILT_IN_SERVICE_DOUBLES.get(name))
+    }
+
+    /// Applies the configured templates that the service extends to the service.
+    pub fn apply_templates_to(
+        &self,
+        service_config: &mut ServiceConfig,
+    ) -> Result<(), ServiceTemplateError> {
+        service_template::resolve_templates(&self.templates, service_config)
     }
 
     pub fn add_secrets_to(&self, service_config: &mut ServiceConfig, app_name: &str) {
@@ -238,10 +764,86 @@ impl Config {
         }
     }
 
+    /// Adds the configured labels and annotations to the service config. Labels and annotations
+    /// of the service config have precedence over the ones of the service section which have
+    /// precedence over the ones that are configured per app.
+    pub fn add_metadata_to(&self, service_config: &mut ServiceConfig, app_name: &str) {
+        if let Some(metadata) = self
+            .services
+            .as_ref()
+            .and_then(|services| services.get(service_config.service_name()))
+            .and_then(|service| service.metadata.as_ref())
+        {
+            service_config.add_metadata(metadata);
+        }
+
+        for app_metadata in self
+            .metadata
+            .iter()
+            .filter(|app_metadata| app_metadata.app_selector.matches(app_name))
+        {
+            service_config.add_metadata(&app_metadata.metadata);
+        }
+    }
+
+    /// Adds a Traefik `headers` middleware to the service config that injects the headers of all
+    /// header sections matching the app name. A `headers` middleware of the service config has
+    /// precedence.
+    pub fn add_headers_to(&self, service_config: &mut ServiceConfig, app_name: &str) {
+        let mut request = BTreeMap::new();
+        let mut response = BTreeMap::new();
+        for app_headers in self
+            .headers
+            .iter()
+            .filter(|app_headers| app_headers.app_selector.matches(app_name))
+        {
+            request.extend(app_headers.request.iter());
+            response.extend(app_headers.response.iter());
+        }
+
+        if request.is_empty() && response.is_empty() {
+            return;
+        }
+
+        let mut headers = BTreeMap::new();
+        if !request.is_empty() {
+            headers.insert("customRequestHeaders", request);
+        }
+        if !response.is_empty() {
+            headers.insert("customResponseHeaders", response);
+        }
+
+        service_config.add_middleware(
+            String::from("headers"),
+            serde_value::to_value(headers).expect("Headers should be serializable"),
+        );
+    }
+
+    /// Adds the extended resources, e.g. GPUs, of the service section to the service config.
+    /// Extended resources of the service config have precedence.
+    pub fn add_extended_resources_to(&self, service_config: &mut ServiceConfig) {
+        if let Some(service) = self
+            .services
+            .as_ref()
+            .and_then(|services| services.get(service_config.service_name()))
+        {
+            service_config.add_extended_resources(&service.extended_resources);
+        }
+    }
+
     pub fn hook(&self, hook_name: &str) -> Option<&PathBuf> {
         self.hooks.as_ref().and_then(|hooks| hooks.get(hook_name))
     }
 
+    pub fn git_config(&self) -> &GitConfig {
+        &self.git
+    }
+
+    /// The hosts of the registries that have been configured with credentials.
+    pub fn registry_hosts(&self) -> impl Iterator<Item = &String> {
+        self.registries.keys()
+    }
+
     pub fn registry_credentials<'a, 'b: 'a>(
         &'b self,
         registry_host: &str,
@@ -308,7 +910,7 @@ macro_rules! config_from_str {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{service::ContainerType, Image};
+    use crate::models::{service::ContainerType, EnvironmentVariable, Image};
     use std::str::FromStr;
 
     macro_rules! service_config {
@@ -389,6 +991,105 @@ mod tests {
         });
     }
 
+    #[test]
+    fn should_return_companions_of_stack() {
+        let config = config_from_str!(
+            r#"
+            [stacks.kafka.companions.kafka]
+            serviceName = 'kafka'
+            type = 'application'
+            image = 'confluentinc/cp-kafka:7.4.0'
+            "#
+        );
+
+        let companion_configs = config.stack_companion_configs("kafka", "master").unwrap();
+
+        assert_eq!(companion_configs.len(), 1);
+        assert_eq!(companion_configs[0].0.service_name(), "kafka");
+        assert!(config
+            .stack_companion_configs("mongodb", "master")
+            .is_none());
+        assert!(config.application_companion_configs("master").is_empty());
+    }
+
+    #[test]
+    fn should_return_helm_releases_but_no_service_configs_of_helm_companions() {
+        let config = config_from_str!(
+            r#"
+            [companions.kafka]
+            serviceName = 'kafka'
+            type = 'helm'
+
+            [companions.kafka.chart]
+            name = 'oci://registry-1.docker.io/bitnamicharts/kafka'
+            version = '26.4.2'
+            "#
+        );
+
+        let releases = config.helm_releases("master").unwrap();
+
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0].name(), "master-kafka");
+        assert!(config.application_companion_configs("master").is_empty());
+        assert!(config.companion_issues().is_empty());
+    }
+
+    #[test]
+    fn should_report_helm_charts_in_stacks() {
+        let config = config_from_str!(
+            r#"
+            [stacks.kafka.companions.kafka]
+            serviceName = 'kafka'
+            type = 'helm'
+
+            [stacks.kafka.companions.kafka.chart]
+            name = 'oci://registry-1.docker.io/bitnamicharts/kafka'
+            "#
+        );
+
+        assert_eq!(
+            config.companion_issues(),
+            vec![(
+                String::from("stacks.kafka.companions.kafka"),
+                String::from("stacks and service doubles can only run containers")
+            )]
+        );
+    }
+
+    #[test]
+    fn should_return_shared_companions_only_for_other_apps() {
+        let config = config_from_str!(
+            r#"
+            [companions.keycloak]
+            serviceName = 'keycloak'
+            type = 'shared'
+            image = 'quay.io/keycloak/keycloak:22.0'
+
+            [companions.keycloak.appEnv]
+            KEYCLOAK_REALM = '{{application.name}}'
+            "#
+        );
+
+        let companion_configs = config.shared_companion_configs("master");
+        assert_eq!(companion_configs.len(), 1);
+        assert_eq!(
+            companion_configs[0].0.container_type(),
+            &ContainerType::ApplicationCompanion
+        );
+        assert!(config.application_companion_configs("master").is_empty());
+        assert!(config
+            .shared_companion_configs("shared-companions")
+            .is_empty());
+
+        let mut service_config = service_config!("api");
+        config.add_shared_companion_env_to(&mut service_config, "master");
+        let variable = service_config
+            .env()
+            .and_then(|env| env.variable("KEYCLOAK_REALM"))
+            .unwrap();
+        assert!(variable.templated());
+    }
+
     #[test]
     fn should_return_service_companions_with_deployment_strategy() {
         let config = config_from_str!(
@@ -608,11 +1309,223 @@ mod tests {
         assert_eq!(service_config.files(), None);
     }
 
+    #[test]
+    fn should_add_extended_resources_of_service() {
+        let config = config_from_str!(
+            r#"
+            [services.model-server]
+            extendedResources = { 'nvidia.com/gpu' = 1, 'example.com/fpga' = '2' }
+            "#
+        );
+
+        let mut service_config = service_config!("model-server");
+        service_config.set_extended_resources(BTreeMap::from([(
+            String::from("example.com/fpga"),
+            String::from("1"),
+        )]));
+        config.add_extended_resources_to(&mut service_config);
+
+        assert_eq!(
+            service_config.extended_resources(),
+            &BTreeMap::from([
+                (String::from("example.com/fpga"), String::from("1")),
+                (String::from("nvidia.com/gpu"), String::from("1")),
+            ])
+        );
+    }
+
+    #[test]
+    fn should_add_headers_of_matching_apps() {
+        let config = config_from_str!(
+            r#"
+            [[headers]]
+            response = { 'X-Robots-Tag' = 'noindex' }
+
+            [[headers]]
+            appSelector = "master"
+            request = { 'X-Environment' = 'master' }
+            "#
+        );
+
+        let mut service_config = service_config!("mariadb");
+        config.add_headers_to(&mut service_config, "feature-xxx");
+
+        let middlewares = service_config
+            .middlewares()
+            .expect("Middlewares are missing");
+        assert_eq!(
+            middlewares.get("headers"),
+            Some(
+                &serde_value::to_value(serde_json::json!({
+                    "customResponseHeaders": { "X-Robots-Tag": "noindex" }
+                }))
+                .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn should_replace_forward_auth_middleware_of_payload() {
+        let config = config_from_str!(
+            r#"
+            [forwardAuth]
+            address = 'http://prevant.tools.svc/auth/forward'
+            "#
+        );
+
+        let mut service_config = serde_json::from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "wordpress",
+            "image": "wordpress",
+            "middlewares": {
+                "forwardAuth": { "address": "http://evil.example.com/always-ok" }
+            }
+        }))
+        .unwrap();
+        config.add_forward_auth_to(&mut service_config, &AppName::master());
+
+        let middlewares = service_config
+            .middlewares()
+            .expect("Middlewares are missing");
+        assert_eq!(
+            middlewares.get("forwardAuth"),
+            Some(
+                &serde_value::to_value(serde_json::json!({
+                    "address": "http://prevant.tools.svc/auth/forward?app=master",
+                    "trustForwardHeader": false,
+                    "authResponseHeaders": ["X-Forwarded-User"]
+                }))
+                .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn should_add_app_env_of_matching_apps() {
+        let config = config_from_str!(
+            r#"
+            [[appEnv]]
+            env = { SENTRY_ENVIRONMENT = '{{application.name}}', LOG_LEVEL = 'info' }
+
+            [[appEnv]]
+            appSelector = "master"
+            env = { FEATURE_FLAGS = 'none' }
+            "#
+        );
+
+        let mut service_config = service_config!("mariadb");
+        service_config.set_env(Some(Environment::new(vec![EnvironmentVariable::new(
+            String::from("LOG_LEVEL"),
+            SecUtf8::from("debug"),
+        )])));
+        config.add_app_env_to(&mut service_config, "feature-xxx");
+
+        let env = service_config.env().unwrap();
+        let sentry_environment = env.variable("SENTRY_ENVIRONMENT").unwrap();
+        assert_eq!(
+            sentry_environment.value().unsecure(),
+            "{{application.name}}"
+        );
+        assert!(sentry_environment.templated());
+        assert_eq!(
+            env.variable("LOG_LEVEL")
+                .map(|variable| variable.value().unsecure()),
+            Some("debug")
+        );
+        assert_eq!(env.variable("FEATURE_FLAGS"), None);
+    }
+
+    #[test]
+    fn should_merge_egress_rules_of_matching_apps() {
+        let config = config_from_str!(
+            r#"
+            [[egress]]
+            allowedCidrs = [ '10.10.0.0/16' ]
+
+            [[egress]]
+            appSelector = "feature-.*"
+            allowedHosts = [ 'api.staging.example.com' ]
+            "#
+        );
+
+        let allowlist = config.egress_allowlist("feature-xxx").unwrap();
+
+        assert_eq!(
+            allowlist.cidrs().iter().collect::<Vec<_>>(),
+            vec!["10.10.0.0/16"]
+        );
+        assert_eq!(
+            allowlist.hosts().iter().collect::<Vec<_>>(),
+            vec!["api.staging.example.com"]
+        );
+    }
+
+    #[test]
+    fn should_not_restrict_egress_without_rules() {
+        let config = config_from_str!("");
+
+        assert_eq!(config.egress_allowlist("master"), None);
+    }
+
+    #[test]
+    fn should_add_metadata_of_app_and_service() {
+        let config = config_from_str!(
+            r#"
+            [[metadata]]
+            labels = { 'cost-center' = '4711', owner = 'team-a' }
+
+            [[metadata]]
+            appSelector = "master"
+            annotations = { 'example.com/description' = 'main line' }
+
+            [services.mariadb.metadata]
+            labels = { owner = 'team-db' }
+            "#
+        );
+
+        let mut service_config = service_config!("mariadb");
+        config.add_metadata_to(&mut service_config, "master");
+
+        let metadata = service_config.metadata().expect("Metadata is missing");
+        assert_eq!(
+            metadata.labels(),
+            &BTreeMap::from([
+                (String::from("cost-center"), String::from("4711")),
+                (String::from("owner"), String::from("team-db")),
+            ])
+        );
+        assert_eq!(
+            metadata.annotations(),
+            &BTreeMap::from([(
+                String::from("example.com/description"),
+                String::from("main line")
+            )])
+        );
+    }
+
+    #[test]
+    fn should_not_add_metadata_of_non_matching_app_selector() {
+        let config = config_from_str!(
+            r#"
+            [[metadata]]
+            appSelector = "master"
+            labels = { 'cost-center' = '4711' }
+            "#
+        );
+
+        let mut service_config = service_config!("mariadb");
+        config.add_metadata_to(&mut service_config, "feature-xyz");
+
+        assert_eq!(service_config.metadata(), None);
+    }
+
     #[test]
     fn should_parse_config_with_default_container_runtime() {
         let config = config_from_str!("");
 
-        assert_eq!(config.runtime_config(), &Runtime::Docker);
+        assert_eq!(
+            config.runtime_config(),
+            &Runtime::Docker(Default::default())
+        );
     }
 
     #[test]
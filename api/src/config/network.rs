@@ -0,0 +1,160 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use std::path::PathBuf;
+use url::Url;
+
+/// Settings for outbound HTTP calls of PREvant, e.g. to registries or to Jira, in networks that
+/// require a proxy or an internal certificate authority.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NetworkConfig {
+    proxy: Option<Url>,
+    no_proxy: Option<String>,
+    #[serde(default)]
+    extra_ca_certs: Vec<PathBuf>,
+}
+
+impl NetworkConfig {
+    pub fn proxy(&self) -> Option<&Url> {
+        self.proxy.as_ref()
+    }
+
+    /// Comma separated hosts or domains that will be called without the proxy.
+    pub fn no_proxy(&self) -> Option<&String> {
+        self.no_proxy.as_ref()
+    }
+
+    pub fn extra_ca_certs(&self) -> &Vec<PathBuf> {
+        &self.extra_ca_certs
+    }
+
+    /// Reads the PEM encoded certificates of the extra certificate authorities. Files that
+    /// cannot be read will be skipped.
+    pub fn extra_ca_certificates(&self) -> Vec<Vec<u8>> {
+        self.extra_ca_certs
+            .iter()
+            .filter_map(|path| match std::fs::read(path) {
+                Ok(pem) => Some(pem),
+                Err(err) => {
+                    warn!("Cannot read CA certificate {}: {}", path.display(), err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Creates the builder of the clients for outbound HTTP calls, e.g. to webhooks, to S3, or to
+    /// Jira, that uses the proxy and trusts the extra certificate authorities. Certificates that
+    /// cannot be parsed will be skipped.
+    pub fn http_client_builder(&self) -> reqwest::ClientBuilder {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &self.proxy {
+            match reqwest::Proxy::all(proxy.as_str()) {
+                Ok(proxy) => {
+                    let no_proxy = self
+                        .no_proxy
+                        .as_deref()
+                        .and_then(reqwest::NoProxy::from_string);
+                    builder = builder.proxy(proxy.no_proxy(no_proxy));
+                }
+                Err(err) => warn!("Cannot use the proxy {}: {}", proxy, err),
+            }
+        }
+        for certificate in self.extra_ca_certificates() {
+            match reqwest::Certificate::from_pem(&certificate) {
+                Ok(certificate) => builder = builder.add_root_certificate(certificate),
+                Err(err) => warn!("Cannot parse CA certificate: {}", err),
+            }
+        }
+        builder
+    }
+
+    /// Exports the proxy settings as environment variables (`HTTP_PROXY`, `HTTPS_PROXY`, and
+    /// `NO_PROXY`) for HTTP clients that PREvant cannot configure directly, e.g. the registry
+    /// client or Trivy. Requests to the deployed services do not use the proxy.
+    /// Must be called before the async runtime starts because changing the environment is not
+    /// thread-safe.
+    pub fn export_proxy_environment(&self) {
+        if let Some(proxy) = &self.proxy {
+            for name in ["HTTP_PROXY", "HTTPS_PROXY", "http_proxy", "https_proxy"] {
+                std::env::set_var(name, proxy.as_str());
+            }
+        }
+        if let Some(no_proxy) = &self.no_proxy {
+            for name in ["NO_PROXY", "no_proxy"] {
+                std::env::set_var(name, no_proxy);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_network_config() {
+        let config = toml::de::from_str::<NetworkConfig>(
+            r#"
+            proxy = 'http://proxy.example.com:3128'
+            no_proxy = 'localhost,.cluster.local'
+            extra_ca_certs = [ '/etc/ssl/internal-ca.pem' ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.proxy(),
+            Some(&Url::parse("http://proxy.example.com:3128").unwrap())
+        );
+        assert_eq!(
+            config.no_proxy(),
+            Some(&String::from("localhost,.cluster.local"))
+        );
+        assert_eq!(
+            config.extra_ca_certs(),
+            &vec![PathBuf::from("/etc/ssl/internal-ca.pem")]
+        );
+    }
+
+    #[test]
+    fn should_build_http_client_without_invalid_certificates() {
+        let dir = tempfile::tempdir().unwrap();
+        let certificate = dir.path().join("internal-ca.pem");
+        std::fs::write(&certificate, "not a certificate").unwrap();
+
+        let config = toml::de::from_str::<NetworkConfig>(&format!(
+            r#"
+            proxy = 'http://proxy.example.com:3128'
+            no_proxy = 'localhost,.cluster.local'
+            extra_ca_certs = [ '{}' ]
+            "#,
+            certificate.display()
+        ))
+        .unwrap();
+
+        assert!(config.http_client_builder().build().is_ok());
+    }
+}
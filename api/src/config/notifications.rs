@@ -0,0 +1,187 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::AppSelector;
+use secstr::SecUtf8;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use url::Url;
+
+/// The changes of the apps that can be announced through a notification channel.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationEvent {
+    /// A new app has been deployed successfully.
+    Created,
+    /// The deployment of an app failed.
+    Failed,
+    /// An app has been deleted softly and can be restored until its grace period expires.
+    #[serde(rename = "softDeleted")]
+    SoftDeleted,
+    /// An app has been deleted immediately or a softly deleted app has been deleted again.
+    Deleted,
+    /// The grace period of a softly deleted app expired and the app has been torn down.
+    Expired,
+}
+
+impl NotificationEvent {
+    fn all() -> Vec<NotificationEvent> {
+        vec![
+            NotificationEvent::Created,
+            NotificationEvent::Failed,
+            NotificationEvent::SoftDeleted,
+            NotificationEvent::Deleted,
+            NotificationEvent::Expired,
+        ]
+    }
+}
+
+impl Display for NotificationEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NotificationEvent::Created => write!(f, "created"),
+            NotificationEvent::Failed => write!(f, "failed"),
+            NotificationEvent::SoftDeleted => write!(f, "softly deleted"),
+            NotificationEvent::Deleted => write!(f, "deleted"),
+            NotificationEvent::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// A channel, e.g. a Slack channel, that PREvant posts messages to when apps change.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationChannelConfig {
+    #[serde(flatten)]
+    target: NotificationTarget,
+    #[serde(default = "NotificationEvent::all")]
+    events: Vec<NotificationEvent>,
+    #[serde(default = "AppSelector::default")]
+    app_selector: AppSelector,
+    /// The URL of PREvant's dashboard that is used to link the app and its services.
+    base_url: Option<Url>,
+    /// Handlebars templates per event that override the default messages.
+    #[serde(default)]
+    templates: BTreeMap<NotificationEvent, String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum NotificationTarget {
+    /// Posts the message to a Slack [incoming webhook](https://api.slack.com/messaging/webhooks).
+    Slack {
+        #[serde(rename = "webhookUrl")]
+        webhook_url: SecUtf8,
+    },
+    /// Posts the message to an incoming webhook of a Microsoft Teams channel.
+    Teams {
+        #[serde(rename = "webhookUrl")]
+        webhook_url: SecUtf8,
+    },
+    /// Sends the message as text message into a Matrix room.
+    Matrix {
+        homeserver: Url,
+        #[serde(rename = "roomId")]
+        room_id: String,
+        #[serde(rename = "accessToken")]
+        access_token: SecUtf8,
+    },
+}
+
+impl NotificationChannelConfig {
+    pub fn target(&self) -> &NotificationTarget {
+        &self.target
+    }
+
+    pub fn base_url(&self) -> Option<&Url> {
+        self.base_url.as_ref()
+    }
+
+    /// Checks whether the channel wants to be notified about the event of the given app.
+    pub fn subscribes_to(&self, event: NotificationEvent, app_name: &str) -> bool {
+        self.events.contains(&event) && self.app_selector.matches(app_name)
+    }
+
+    /// The Handlebars template of the message for the event.
+    pub fn template(&self, event: NotificationEvent) -> &str {
+        match self.templates.get(&event) {
+            Some(template) => template,
+            None => match event {
+                NotificationEvent::Created => {
+                    "The app {{application.name}} has been deployed.{{#each services}} {{name}}{{#if url}}: {{url}}{{/if}}{{/each}}{{#each application.metadata}} {{@key}}: {{this}}{{/each}}"
+                }
+                NotificationEvent::Failed => {
+                    "The deployment of the app {{application.name}} failed: {{error}}{{#each application.metadata}} {{@key}}: {{this}}{{/each}}"
+                }
+                NotificationEvent::SoftDeleted => {
+                    "The app {{application.name}} has been deleted and can be restored until it expires."
+                }
+                NotificationEvent::Deleted => "The app {{application.name}} has been deleted.",
+                NotificationEvent::Expired => {
+                    "The app {{application.name}} expired and has been torn down."
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_matrix_channel_with_template_override() {
+        let config = toml::de::from_str::<NotificationChannelConfig>(
+            r#"
+            type = 'Matrix'
+            homeserver = 'https://matrix.example.com'
+            roomId = '!review:example.com'
+            accessToken = 'secret'
+            events = ['created', 'expired']
+            appSelector = 'feature-.+'
+
+            [templates]
+            created = 'Try {{application.name}}'
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            config.target(),
+            NotificationTarget::Matrix { room_id, .. } if room_id == "!review:example.com"
+        ));
+        assert!(config.subscribes_to(NotificationEvent::Created, "feature-1"));
+        assert!(!config.subscribes_to(NotificationEvent::Failed, "feature-1"));
+        assert!(!config.subscribes_to(NotificationEvent::Created, "master"));
+        assert_eq!(
+            config.template(NotificationEvent::Created),
+            "Try {{application.name}}"
+        );
+        assert_eq!(
+            config.template(NotificationEvent::Deleted),
+            "The app {{application.name}} has been deleted."
+        );
+    }
+}
@@ -0,0 +1,111 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::AppName;
+use serde_value::Value;
+
+/// Configures the page that will be shown instead of Traefik's raw error page while a service
+/// is starting or stopped. PREvant serves this page itself, thus, the configuration refers to the
+/// Kubernetes service of PREvant.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaceholderConfig {
+    service: String,
+    namespace: Option<String>,
+    #[serde(default = "PlaceholderConfig::default_port")]
+    port: u16,
+    #[serde(default = "PlaceholderConfig::default_refresh_interval")]
+    refresh_interval: u32,
+}
+
+impl PlaceholderConfig {
+    fn default_port() -> u16 {
+        80
+    }
+
+    fn default_refresh_interval() -> u32 {
+        5
+    }
+
+    /// The interval in seconds in which the placeholder page reloads itself.
+    pub fn refresh_interval(&self) -> u32 {
+        self.refresh_interval
+    }
+
+    /// Creates the spec of a Traefik
+    /// [errors middleware](https://doc.traefik.io/traefik/middlewares/http/errorpages/) that
+    /// replaces the responses of unavailable services with the placeholder page.
+    pub fn errors_middleware(&self, app_name: &AppName, service_name: &str) -> Value {
+        let mut service = serde_json::json!({
+            "name": self.service,
+            "port": self.port,
+        });
+        if let Some(namespace) = &self.namespace {
+            service["namespace"] = serde_json::json!(namespace);
+        }
+
+        serde_value::to_value(serde_json::json!({
+            "status": ["502-504"],
+            "service": service,
+            "query": format!("/api/apps/{app_name}/services/{service_name}/placeholder?status={{status}}"),
+        }))
+        .expect("JSON values should be convertible")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_create_errors_middleware() {
+        let config = toml::de::from_str::<PlaceholderConfig>(
+            r#"
+            service = 'prevant'
+            namespace = 'tools'
+            "#,
+        )
+        .unwrap();
+
+        let middleware =
+            config.errors_middleware(&AppName::from_str("master").unwrap(), "wordpress");
+
+        assert_eq!(
+            middleware,
+            serde_value::to_value(serde_json::json!({
+                "status": ["502-504"],
+                "service": {
+                    "name": "prevant",
+                    "namespace": "tools",
+                    "port": 80
+                },
+                "query": "/api/apps/master/services/wordpress/placeholder?status={status}"
+            }))
+            .unwrap()
+        );
+        assert_eq!(config.refresh_interval(), 5);
+    }
+}
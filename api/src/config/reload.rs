@@ -0,0 +1,249 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::{validate, CliArgs, Config};
+use figment::Figment;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Shared handle to the current configuration that will be replaced when the configuration file
+/// changes.
+#[derive(Clone)]
+pub struct ConfigHandle {
+    current: Arc<RwLock<Arc<Config>>>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: Config) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(config))),
+        }
+    }
+
+    /// Returns a snapshot of the current configuration. Callers, e.g. an in-flight deployment,
+    /// keep using their snapshot even if the configuration is replaced in the meantime.
+    pub fn current(&self) -> Arc<Config> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, config: Config) {
+        *self.current.write().unwrap() = Arc::new(config);
+    }
+}
+
+impl From<Config> for ConfigHandle {
+    fn from(config: Config) -> Self {
+        Self::new(config)
+    }
+}
+
+/// The sections of the configuration that are only read at startup, e.g. to create the clients of
+/// the runtime, the state store, the leader election, or the reconciler of the desired apps.
+const RESTART_REQUIRED_KEYS: [&str; 10] = [
+    "runtime",
+    "registries",
+    "helm",
+    "kustomize",
+    "git",
+    "egress",
+    "network",
+    "state",
+    "leaderElection",
+    "desiredApps",
+];
+
+/// Watches the configuration file and replaces the configuration of the handle if the file
+/// changes and contains a valid configuration. Changes of the sections in
+/// [`RESTART_REQUIRED_KEYS`] are rejected because they would leave the runtime in a stale state.
+/// The parent directory is watched because editors and Kubernetes config maps replace the file
+/// instead of modifying it.
+///
+/// The returned watcher must be kept alive as long as the file should be watched.
+pub fn watch_config_file(
+    cli: CliArgs,
+    handle: ConfigHandle,
+) -> Result<RecommendedWatcher, notify::Error> {
+    let config_file = cli.config_file();
+    let directory = match config_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut last_content = std::fs::read_to_string(&config_file).ok();
+    let mut running = Config::figment(&cli);
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Err(err) = event {
+            error!("Cannot watch configuration file: {}", err);
+            return;
+        }
+
+        let content = std::fs::read_to_string(&config_file).ok();
+        if content == last_content {
+            return;
+        }
+        last_content = content;
+
+        reload(&cli, &handle, &mut running);
+    })?;
+
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
+
+fn reload(cli: &CliArgs, handle: &ConfigHandle, running: &mut Figment) {
+    let validation = validate(cli);
+    if !validation.is_valid() {
+        for issue in validation.issues() {
+            warn!(
+                "Ignoring changes of {}: {}",
+                cli.config_file().display(),
+                issue
+            );
+        }
+        return;
+    }
+
+    let figment = Config::figment(cli);
+    let changed_keys = restart_required_changes(running, &figment);
+    if !changed_keys.is_empty() {
+        warn!(
+            "Ignoring changes of {}: changes of {} require a restart",
+            cli.config_file().display(),
+            changed_keys.join(", ")
+        );
+        return;
+    }
+
+    match Config::from_figment(cli) {
+        Ok(config) => {
+            info!("Reloaded configuration {}", cli.config_file().display());
+            handle.replace(config);
+            *running = figment;
+        }
+        Err(err) => warn!(
+            "Ignoring changes of {}: {}",
+            cli.config_file().display(),
+            err
+        ),
+    }
+}
+
+fn restart_required_changes(running: &Figment, changed: &Figment) -> Vec<&'static str> {
+    RESTART_REQUIRED_KEYS
+        .iter()
+        .filter(|key| running.find_value(key).ok() != changed.find_value(key).ok())
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use figment::providers::{Format, Toml};
+
+    #[test]
+    fn should_keep_snapshot_when_replacing_config() {
+        let handle = ConfigHandle::new(Config::default());
+        let snapshot = handle.current();
+
+        handle.replace(crate::config_from_str!(
+            r#"
+            [stacks.kafka.companions.kafka]
+            serviceName = 'kafka'
+            type = 'application'
+            image = 'confluentinc/cp-kafka:7.4.0'
+            "#
+        ));
+
+        assert!(snapshot
+            .stack_companion_configs("kafka", "master")
+            .is_none());
+        assert!(handle
+            .current()
+            .stack_companion_configs("kafka", "master")
+            .is_some());
+    }
+
+    #[test]
+    fn should_detect_changes_requiring_a_restart() {
+        let running = Figment::from(Toml::string(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            "#,
+        ));
+        let changed = Figment::from(Toml::string(
+            r#"
+            [runtime]
+            type = 'Docker'
+
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:1.0'
+
+            [leaderElection]
+            leaseName = 'prevant'
+
+            [desiredApps]
+            file = '/etc/prevant/desired-apps.yaml'
+            "#,
+        ));
+
+        assert_eq!(
+            restart_required_changes(&running, &changed),
+            vec!["runtime", "leaderElection", "desiredApps"]
+        );
+    }
+
+    #[test]
+    fn should_hot_reload_changes_of_companions() {
+        let running = Figment::from(Toml::string(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            "#,
+        ));
+        let changed = Figment::from(Toml::string(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:1.0'
+            "#,
+        ));
+
+        assert!(restart_required_changes(&running, &changed).is_empty());
+    }
+}
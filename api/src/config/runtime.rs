@@ -23,20 +23,150 @@
  * THE SOFTWARE.
  * =========================LICENSE_END==================================
  */
+use crate::models::Image;
 use bytesize::ByteSize;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use std::{collections::BTreeMap, path::PathBuf};
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(tag = "type")]
 pub enum Runtime {
-    Docker,
+    Docker(DockerRuntimeConfig),
     Kubernetes(KubernetesRuntimeConfig),
+    /// An in-memory runtime that does not deploy anything, e.g. to test clients of PREvant.
+    #[cfg(feature = "dummy-infrastructure")]
+    Dummy(DummyRuntimeConfig),
 }
 
 impl Default for Runtime {
     fn default() -> Self {
-        Self::Docker
+        Self::Docker(DockerRuntimeConfig::default())
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerRuntimeConfig {
+    ipv6_network: Option<String>,
+    traefik: Option<DockerTraefikConfig>,
+    files_directory: Option<PathBuf>,
+}
+
+impl DockerRuntimeConfig {
+    /// The name of an existing, IPv6 enabled network that all containers join in addition to
+    /// the network of their app. Traefik routes the requests through this network.
+    pub fn ipv6_network(&self) -> Option<&String> {
+        self.ipv6_network.as_ref()
+    }
+
+    /// The Traefik container that PREvant creates and upgrades itself. Without it, PREvant uses
+    /// a Traefik container that is already running.
+    pub fn traefik(&self) -> Option<&DockerTraefikConfig> {
+        self.traefik.as_ref()
+    }
+
+    /// The directory in which PREvant writes the files of the services before it bind-mounts
+    /// them into the containers. The directory must have the same path on the host and inside
+    /// of PREvant's container. Without it, PREvant copies the files into the containers.
+    pub fn files_directory(&self) -> Option<&PathBuf> {
+        self.files_directory.as_ref()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerTraefikConfig {
+    #[serde(default = "DockerTraefikConfig::default_image")]
+    image: Image,
+    #[serde(default = "DockerTraefikConfig::default_http_port")]
+    http_port: u16,
+}
+
+impl DockerTraefikConfig {
+    fn default_image() -> Image {
+        Image::from_str("docker.io/library/traefik:v1.7-alpine").unwrap()
+    }
+
+    fn default_http_port() -> u16 {
+        80
+    }
+
+    /// The image of Traefik. Changing it replaces the running Traefik container.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// The port of the host that Traefik publishes. It is the only port that the Docker backend
+    /// publishes at all.
+    pub fn http_port(&self) -> u16 {
+        self.http_port
+    }
+}
+
+#[cfg(feature = "dummy-infrastructure")]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DummyRuntimeConfig {
+    /// Delay of deployments and deletions in milliseconds
+    #[serde(default)]
+    delay: u64,
+    #[serde(default)]
+    failing_operations: Vec<InfrastructureOperation>,
+}
+
+#[cfg(feature = "dummy-infrastructure")]
+impl DummyRuntimeConfig {
+    pub fn delay(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.delay)
+    }
+
+    pub fn failing_operations(&self) -> &[InfrastructureOperation] {
+        &self.failing_operations
+    }
+}
+
+/// Operations of an infrastructure that can be configured to fail in test environments.
+#[cfg(any(test, feature = "dummy-infrastructure"))]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum InfrastructureOperation {
+    GetServices,
+    DeployServices,
+    StopServices,
+    GetLogs,
+    ChangeStatus,
+    RunSmokeTest,
+    ExecInService,
+    BuildImage,
+}
+
+/// The runtime PREvant deploys to by default and further named runtimes, e.g. other Kubernetes
+/// clusters, that can be chosen as target of a deployment.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub(super) struct Runtimes {
+    #[serde(flatten)]
+    default: Runtime,
+    #[serde(default)]
+    clusters: BTreeMap<String, Runtime>,
+}
+
+impl Runtimes {
+    pub fn default_runtime(&self) -> &Runtime {
+        &self.default
+    }
+
+    pub fn clusters(&self) -> &BTreeMap<String, Runtime> {
+        &self.clusters
+    }
+}
+
+impl From<Runtime> for Runtimes {
+    fn from(default: Runtime) -> Self {
+        Self {
+            default,
+            clusters: BTreeMap::new(),
+        }
     }
 }
 
@@ -49,6 +179,15 @@ pub struct KubernetesRuntimeConfig {
     downward_api: KubernetesDownwardApiConfig,
     #[serde(default)]
     storage_config: KubernetesStorageConfig,
+    #[serde(default)]
+    namespace: KubernetesNamespaceConfig,
+    kubeconfig: Option<KubernetesKubeconfigConfig>,
+    ip_family_policy: Option<IpFamilyPolicy>,
+    #[serde(default)]
+    ip_families: Vec<IpFamily>,
+    service_mesh: Option<ServiceMesh>,
+    app_host: Option<KubernetesAppHostConfig>,
+    cluster_domain: Option<String>,
 }
 
 impl KubernetesRuntimeConfig {
@@ -63,6 +202,188 @@ impl KubernetesRuntimeConfig {
     pub fn annotations(&self) -> &KubernetesAnnotationsConfig {
         &self.annotations
     }
+
+    pub fn kubeconfig(&self) -> Option<&KubernetesKubeconfigConfig> {
+        self.kubeconfig.as_ref()
+    }
+
+    pub fn namespace(&self) -> &KubernetesNamespaceConfig {
+        &self.namespace
+    }
+
+    /// The IP family policy of the services PREvant creates, e.g. `PreferDualStack` in
+    /// dual-stack clusters. If missing, the cluster's default applies.
+    pub fn ip_family_policy(&self) -> Option<IpFamilyPolicy> {
+        self.ip_family_policy
+    }
+
+    /// The IP families of the services PREvant creates, in the order of preference.
+    pub fn ip_families(&self) -> &[IpFamily] {
+        &self.ip_families
+    }
+
+    /// The service mesh that the apps join. If missing, the apps are routed through Traefik's
+    /// custom resources only.
+    pub fn service_mesh(&self) -> Option<&ServiceMesh> {
+        self.service_mesh.as_ref()
+    }
+
+    /// The host each app is reachable at in addition to PREvant's host.
+    pub fn app_host(&self) -> Option<&KubernetesAppHostConfig> {
+        self.app_host.as_ref()
+    }
+
+    /// The DNS domain of the cluster that completes the fully qualified names of the services.
+    pub fn cluster_domain(&self) -> &str {
+        self.cluster_domain.as_deref().unwrap_or("cluster.local")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesAppHostConfig {
+    template: String,
+    cert_manager: Option<CertManagerConfig>,
+    #[serde(default)]
+    external_dns: bool,
+    #[serde(default)]
+    annotations: BTreeMap<String, String>,
+}
+
+impl KubernetesAppHostConfig {
+    /// The host of the app where `{app}` is replaced with the app name, e.g.
+    /// `{app}.previews.example.com`.
+    pub fn host(&self, app_name: &str) -> String {
+        self.template.replace("{app}", app_name)
+    }
+
+    /// The cert-manager issuer that issues the certificates of the app hosts. Without it, the
+    /// app hosts are served by plain HTTP.
+    pub fn cert_manager(&self) -> Option<&CertManagerConfig> {
+        self.cert_manager.as_ref()
+    }
+
+    /// The annotations of the resources that publish the host of the app, e.g. for
+    /// [ExternalDNS](https://github.com/kubernetes-sigs/external-dns). `{app}` and `{host}` are
+    /// replaced in the configured annotations.
+    pub fn annotations(&self, app_name: &str) -> BTreeMap<String, String> {
+        let host = self.host(app_name);
+
+        let mut annotations = BTreeMap::new();
+        if self.external_dns {
+            annotations.insert(
+                String::from("external-dns.alpha.kubernetes.io/hostname"),
+                host.clone(),
+            );
+        }
+        annotations.extend(self.annotations.iter().map(|(key, value)| {
+            (
+                key.clone(),
+                value.replace("{app}", app_name).replace("{host}", &host),
+            )
+        }));
+        annotations
+    }
+}
+
+/// References the [cert-manager issuer](https://cert-manager.io/docs/concepts/issuer/) that
+/// issues the certificates of the app hosts.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CertManagerConfig {
+    issuer: String,
+    #[serde(default = "CertManagerConfig::default_issuer_kind")]
+    issuer_kind: String,
+}
+
+impl CertManagerConfig {
+    pub fn issuer(&self) -> &String {
+        &self.issuer
+    }
+
+    /// Either `ClusterIssuer` (default) or `Issuer`.
+    pub fn issuer_kind(&self) -> &String {
+        &self.issuer_kind
+    }
+
+    fn default_issuer_kind() -> String {
+        String::from("ClusterIssuer")
+    }
+}
+
+/// The service mesh that injects its sidecars into the pods of the apps.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum ServiceMesh {
+    /// Routes the requests through Istio's ingress gateway with a `VirtualService` per service
+    /// instead of Traefik's `IngressRoute`.
+    Istio(IstioConfig),
+    /// Keeps routing the requests through Traefik which acts as the mesh ingress.
+    Linkerd,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IstioConfig {
+    #[serde(default = "IstioConfig::default_gateways")]
+    gateways: Vec<String>,
+    #[serde(default = "IstioConfig::default_hosts")]
+    hosts: Vec<String>,
+}
+
+impl IstioConfig {
+    /// The gateways, e.g. `istio-system/prevant-gateway`, the virtual services are bound to.
+    pub fn gateways(&self) -> &Vec<String> {
+        &self.gateways
+    }
+
+    /// The hosts the virtual services match, e.g. the domain PREvant is published at.
+    pub fn hosts(&self) -> &Vec<String> {
+        &self.hosts
+    }
+
+    fn default_gateways() -> Vec<String> {
+        vec![String::from("istio-system/prevant-gateway")]
+    }
+
+    fn default_hosts() -> Vec<String> {
+        vec![String::from("*")]
+    }
+}
+
+/// See [IPv4/IPv6 dual-stack](https://kubernetes.io/docs/concepts/services-networking/dual-stack/#services).
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IpFamilyPolicy {
+    SingleStack,
+    PreferDualStack,
+    RequireDualStack,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IpFamily {
+    IPv4,
+    IPv6,
+}
+
+/// Points to a kubeconfig so that PREvant is able to manage a cluster from outside, e.g. a
+/// remote cluster or a local kind cluster.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct KubernetesKubeconfigConfig {
+    path: Option<PathBuf>,
+    context: Option<String>,
+}
+
+impl KubernetesKubeconfigConfig {
+    /// The path to the kubeconfig. If missing, the default kubeconfig will be used, e.g.
+    /// `$KUBECONFIG` or `~/.kube/config`.
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// The context to use instead of the current context of the kubeconfig.
+    pub fn context(&self) -> &Option<String> {
+        &self.context
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, PartialEq)]
@@ -119,6 +440,50 @@ impl KubernetesStorageConfig {
     }
 }
 
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesNamespaceConfig {
+    #[serde(default = "KubernetesNamespaceConfig::default_name")]
+    name: String,
+    #[serde(default)]
+    mode: KubernetesNamespaceMode,
+}
+
+impl KubernetesNamespaceConfig {
+    /// The name of the namespace of an app where `{app}` is replaced with the app name, e.g.
+    /// `preview-{app}`. Without the placeholder, all apps share the same namespace.
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Returns `true` if PREvant creates the namespace of an app and deletes it with the app.
+    /// Otherwise, the namespace must exist and PREvant identifies the resources of an app by
+    /// their labels.
+    pub fn is_managed(&self) -> bool {
+        self.mode == KubernetesNamespaceMode::Create && self.name.contains("{app}")
+    }
+
+    fn default_name() -> String {
+        String::from("{app}")
+    }
+}
+
+impl Default for KubernetesNamespaceConfig {
+    fn default() -> Self {
+        Self {
+            name: Self::default_name(),
+            mode: KubernetesNamespaceMode::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+pub enum KubernetesNamespaceMode {
+    #[default]
+    Create,
+    Existing,
+}
+
 impl Default for KubernetesStorageConfig {
     fn default() -> Self {
         Self {
@@ -132,6 +497,61 @@ impl Default for KubernetesStorageConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_runtimes_with_clusters() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+
+        [clusters.gpu]
+        type = 'Kubernetes'
+        [clusters.gpu.storageConfig]
+        storageClass = 'fast'
+        "#;
+
+        let runtimes = toml::de::from_str::<Runtimes>(runtime_toml).unwrap();
+
+        assert_eq!(
+            runtimes.default_runtime(),
+            &Runtime::Kubernetes(Default::default())
+        );
+        assert_eq!(
+            runtimes.clusters(),
+            &BTreeMap::from([(
+                String::from("gpu"),
+                Runtime::Kubernetes(KubernetesRuntimeConfig {
+                    storage_config: KubernetesStorageConfig {
+                        storage_class: Some(String::from("fast")),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+            )])
+        );
+    }
+
+    #[test]
+    fn parse_as_kubernetes_runtime_with_kubeconfig() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        [kubeconfig]
+        path = '/home/user/.kube/config'
+        context = 'kind-prevant'
+        "#;
+
+        let Runtime::Kubernetes(config) = toml::de::from_str::<Runtime>(runtime_toml).unwrap()
+        else {
+            panic!("Need a K8s config")
+        };
+
+        assert_eq!(
+            config.kubeconfig(),
+            Some(&KubernetesKubeconfigConfig {
+                path: Some(PathBuf::from("/home/user/.kube/config")),
+                context: Some(String::from("kind-prevant")),
+            })
+        );
+    }
+
     #[test]
     fn parse_from_minimal_config_as_docker_runtime() {
         let runtime_toml = r#"
@@ -140,7 +560,231 @@ mod tests {
 
         let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
 
-        assert_eq!(runtime, Runtime::Docker);
+        assert_eq!(runtime, Runtime::Docker(DockerRuntimeConfig::default()));
+    }
+
+    #[test]
+    fn parse_as_docker_runtime_with_ipv6_network() {
+        let runtime_toml = r#"
+        type = 'Docker'
+        ipv6Network = 'prevant-ipv6'
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        assert_eq!(
+            runtime,
+            Runtime::Docker(DockerRuntimeConfig {
+                ipv6_network: Some(String::from("prevant-ipv6")),
+                traefik: None,
+                files_directory: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_as_docker_runtime_with_managed_traefik() {
+        let runtime_toml = r#"
+        type = 'Docker'
+
+        [traefik]
+        httpPort = 8000
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        let Runtime::Docker(runtime) = runtime else {
+            panic!("Expected Docker runtime");
+        };
+        let traefik = runtime.traefik().unwrap();
+        assert_eq!(
+            traefik.image(),
+            &Image::from_str("traefik:v1.7-alpine").unwrap()
+        );
+        assert_eq!(traefik.http_port(), 8000);
+    }
+
+    #[test]
+    fn parse_as_docker_runtime_with_files_directory() {
+        let runtime_toml = r#"
+        type = 'Docker'
+        filesDirectory = '/var/lib/prevant/files'
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        let Runtime::Docker(runtime) = runtime else {
+            panic!("Expected Docker runtime");
+        };
+        assert_eq!(
+            runtime.files_directory(),
+            Some(&PathBuf::from("/var/lib/prevant/files"))
+        );
+    }
+
+    #[test]
+    fn parse_as_kubernetes_runtime_with_ip_families() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        ipFamilyPolicy = 'RequireDualStack'
+        ipFamilies = [ 'IPv4', 'IPv6' ]
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        let Runtime::Kubernetes(runtime) = runtime else {
+            panic!("Expected Kubernetes runtime");
+        };
+        assert_eq!(
+            runtime.ip_family_policy(),
+            Some(IpFamilyPolicy::RequireDualStack)
+        );
+        assert_eq!(runtime.ip_families(), &[IpFamily::IPv4, IpFamily::IPv6]);
+    }
+
+    #[test]
+    fn parse_as_kubernetes_runtime_with_existing_namespaces() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        [namespace]
+        name = 'preview-{app}'
+        mode = 'Existing'
+        "#;
+
+        let runtime = toml::de::from_str::<Runtime>(runtime_toml).unwrap();
+
+        assert_eq!(
+            runtime,
+            Runtime::Kubernetes(KubernetesRuntimeConfig {
+                namespace: KubernetesNamespaceConfig {
+                    name: String::from("preview-{app}"),
+                    mode: KubernetesNamespaceMode::Existing,
+                },
+                ..Default::default()
+            })
+        );
+        let Runtime::Kubernetes(runtime) = runtime else {
+            unreachable!()
+        };
+        assert!(!runtime.namespace().is_managed());
+    }
+
+    #[test]
+    fn shared_namespace_is_never_managed() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        [namespace]
+        name = 'previews'
+        "#;
+
+        let Runtime::Kubernetes(runtime) = toml::de::from_str::<Runtime>(runtime_toml).unwrap()
+        else {
+            panic!("Expected Kubernetes runtime");
+        };
+
+        assert!(!runtime.namespace().is_managed());
+        assert!(KubernetesNamespaceConfig::default().is_managed());
+    }
+
+    #[test]
+    fn parse_as_kubernetes_runtime_with_istio() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        [serviceMesh]
+        type = 'Istio'
+        gateways = [ 'istio-system/previews' ]
+        "#;
+
+        let Runtime::Kubernetes(runtime) = toml::de::from_str::<Runtime>(runtime_toml).unwrap()
+        else {
+            panic!("Expected Kubernetes runtime");
+        };
+
+        assert_eq!(
+            runtime.service_mesh(),
+            Some(&ServiceMesh::Istio(IstioConfig {
+                gateways: vec![String::from("istio-system/previews")],
+                hosts: vec![String::from("*")],
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_as_kubernetes_runtime_with_linkerd() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        [serviceMesh]
+        type = 'Linkerd'
+        "#;
+
+        let Runtime::Kubernetes(runtime) = toml::de::from_str::<Runtime>(runtime_toml).unwrap()
+        else {
+            panic!("Expected Kubernetes runtime");
+        };
+
+        assert_eq!(runtime.service_mesh(), Some(&ServiceMesh::Linkerd));
+    }
+
+    #[test]
+    fn parse_as_kubernetes_runtime_with_app_host_and_cert_manager() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        [appHost]
+        template = '{app}.previews.example.com'
+        [appHost.certManager]
+        issuer = 'letsencrypt'
+        "#;
+
+        let Runtime::Kubernetes(runtime) = toml::de::from_str::<Runtime>(runtime_toml).unwrap()
+        else {
+            panic!("Expected Kubernetes runtime");
+        };
+
+        let app_host = runtime.app_host().unwrap();
+        assert_eq!(app_host.host("master"), "master.previews.example.com");
+        assert_eq!(
+            app_host.cert_manager(),
+            Some(&CertManagerConfig {
+                issuer: String::from("letsencrypt"),
+                issuer_kind: String::from("ClusterIssuer"),
+            })
+        );
+    }
+
+    #[test]
+    fn provide_external_dns_annotations_of_app_host() {
+        let runtime_toml = r#"
+        type = 'Kubernetes'
+        [appHost]
+        template = '{app}.previews.example.com'
+        externalDns = true
+        [appHost.annotations]
+        'external-dns.alpha.kubernetes.io/ttl' = '60'
+        'example.com/owner' = '{app}@{host}'
+        "#;
+
+        let Runtime::Kubernetes(runtime) = toml::de::from_str::<Runtime>(runtime_toml).unwrap()
+        else {
+            panic!("Expected Kubernetes runtime");
+        };
+
+        assert_eq!(
+            runtime.app_host().unwrap().annotations("master"),
+            BTreeMap::from([
+                (
+                    String::from("example.com/owner"),
+                    String::from("master@master.previews.example.com")
+                ),
+                (
+                    String::from("external-dns.alpha.kubernetes.io/hostname"),
+                    String::from("master.previews.example.com")
+                ),
+                (
+                    String::from("external-dns.alpha.kubernetes.io/ttl"),
+                    String::from("60")
+                ),
+            ])
+        );
     }
 
     #[test]
@@ -0,0 +1,71 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::SbomFormat;
+use std::path::PathBuf;
+
+/// Configures the generation of SBOMs of the deployed images with
+/// [Syft](https://github.com/anchore/syft).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SbomConfig {
+    #[serde(default = "SbomConfig::default_command")]
+    command: PathBuf,
+    #[serde(default)]
+    format: SbomFormat,
+}
+
+impl SbomConfig {
+    fn default_command() -> PathBuf {
+        PathBuf::from("syft")
+    }
+
+    /// The path of the Syft executable.
+    pub fn command(&self) -> &PathBuf {
+        &self.command
+    }
+
+    pub fn format(&self) -> SbomFormat {
+        self.format
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_sbom_config() {
+        let config = toml::de::from_str::<SbomConfig>(
+            r#"
+            format = 'spdx-json'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.command(), &PathBuf::from("syft"));
+        assert_eq!(config.format(), SbomFormat::SpdxJson);
+    }
+}
@@ -0,0 +1,113 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::AccessRule;
+
+/// Restricts the rules that the service accounts of the deployment payloads may request. Rules
+/// of companions are configured by the operator of PREvant and are not restricted.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAccountsConfig {
+    /// The verbs that may be granted, e.g. `get`.
+    #[serde(default)]
+    allowed_verbs: Vec<String>,
+    /// The resources that may be granted in the notation of kubectl, e.g. `pods/log` or
+    /// `deployments.apps`.
+    #[serde(default)]
+    allowed_resources: Vec<String>,
+}
+
+impl ServiceAccountsConfig {
+    /// Whether all verbs and all resources of the rule are in the allowlists. Wildcards of the
+    /// rule are only permitted if the allowlist contains the wildcard as well.
+    pub fn permits(&self, rule: &AccessRule) -> bool {
+        rule.verbs()
+            .iter()
+            .all(|verb| self.allowed_verbs.contains(verb))
+            && rule.api_groups().iter().all(|api_group| {
+                rule.resources().iter().all(|resource| {
+                    let resource = if api_group.is_empty() {
+                        resource.clone()
+                    } else {
+                        format!("{resource}.{api_group}")
+                    };
+                    self.allowed_resources.contains(&resource)
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(rule: serde_json::Value) -> AccessRule {
+        serde_json::from_value::<AccessRule>(rule).unwrap()
+    }
+
+    #[test]
+    fn should_permit_rules_within_allowlists() {
+        let config = toml::de::from_str::<ServiceAccountsConfig>(
+            r#"
+            allowedVerbs = [ 'get', 'list' ]
+            allowedResources = [ 'pods', 'pods/log', 'deployments.apps' ]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.permits(&rule(serde_json::json!({
+            "resources": ["pods", "pods/log"],
+            "verbs": ["get", "list"]
+        }))));
+        assert!(config.permits(&rule(serde_json::json!({
+            "apiGroups": ["apps"],
+            "resources": ["deployments"],
+            "verbs": ["get"]
+        }))));
+        assert!(!config.permits(&rule(serde_json::json!({
+            "resources": ["pods"],
+            "verbs": ["delete"]
+        }))));
+        assert!(!config.permits(&rule(serde_json::json!({
+            "resources": ["secrets"],
+            "verbs": ["get"]
+        }))));
+        assert!(!config.permits(&rule(serde_json::json!({
+            "apiGroups": ["*"],
+            "resources": ["*"],
+            "verbs": ["get"]
+        }))));
+    }
+
+    #[test]
+    fn should_permit_no_rules_by_default() {
+        let config = ServiceAccountsConfig::default();
+
+        assert!(!config.permits(&rule(serde_json::json!({
+            "resources": ["pods"],
+            "verbs": ["get"]
+        }))));
+    }
+}
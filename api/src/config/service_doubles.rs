@@ -0,0 +1,161 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::companion::{Companion, DeploymentStrategy, StorageStrategy};
+use crate::config::MailCatcherConfig;
+use crate::models::service::ContainerType;
+use crate::models::ServiceConfig;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+
+lazy_static! {
+    /// The catalog of service doubles that PREvant ships, see `res/service-doubles.toml`. The
+    /// `smtp` double is the [mail catcher](MailCatcherConfig) deployed on request.
+    pub(super) static ref BUILT_IN_SERVICE_DOUBLES: BTreeMap<String, ServiceDouble> = {
+        let mut doubles: BTreeMap<String, ServiceDouble> =
+            toml::de::from_str(include_str!("../../res/service-doubles.toml"))
+                .expect("The built-in service doubles should be valid");
+        doubles.insert(
+            String::from("smtp"),
+            ServiceDouble::MailCatcher(
+                toml::de::from_str("serviceName = 'smtp'")
+                    .expect("The built-in mail catcher should be valid"),
+            ),
+        );
+        doubles
+    };
+}
+
+/// A companion, e.g. MinIO or LocalStack, that stands in for an external service and will only be
+/// deployed if a deployment request asks for it by name.
+#[derive(Clone)]
+pub enum ServiceDouble {
+    Companion(Companion),
+    MailCatcher(MailCatcherConfig),
+}
+
+/// The configured service doubles are companions.
+impl<'de> Deserialize<'de> for ServiceDouble {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Companion::deserialize(deserializer).map(ServiceDouble::Companion)
+    }
+}
+
+impl ServiceDouble {
+    /// The companion of a configured double that has to be validated with the configuration.
+    pub(super) fn companion(&self) -> Option<&Companion> {
+        match self {
+            ServiceDouble::Companion(companion) => Some(companion),
+            ServiceDouble::MailCatcher(_) => None,
+        }
+    }
+
+    pub fn companion_config(&self) -> (ServiceConfig, DeploymentStrategy, StorageStrategy) {
+        match self {
+            ServiceDouble::Companion(companion) => {
+                let mut config = ServiceConfig::from(companion.clone());
+                config.set_container_type(ContainerType::ApplicationCompanion);
+                (
+                    config,
+                    companion.deployment_strategy().clone(),
+                    companion.storage_strategy().clone(),
+                )
+            }
+            // Like the mail catcher of the configuration, the captured mails are kept when the
+            // app is redeployed.
+            ServiceDouble::MailCatcher(mail_catcher) => (
+                mail_catcher.companion_config(),
+                DeploymentStrategy::RedeployOnImageUpdate,
+                StorageStrategy::NoMountVolumes,
+            ),
+        }
+    }
+
+    /// Adds the variables that wire the services of the app to the double unless a service
+    /// defines them itself.
+    pub fn add_app_env_to(&self, service_config: &mut ServiceConfig) {
+        match self {
+            ServiceDouble::Companion(companion) => {
+                for variable in companion.app_env().into_iter().flat_map(|env| env.iter()) {
+                    service_config.add_env(variable.clone().with_templated(true));
+                }
+            }
+            ServiceDouble::MailCatcher(mail_catcher) => {
+                mail_catcher.add_smtp_env_to(service_config)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Image;
+    use secstr::SecUtf8;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_parse_built_in_service_doubles() {
+        let names = BUILT_IN_SERVICE_DOUBLES.keys().collect::<Vec<_>>();
+        assert_eq!(names, vec!["oidc", "s3", "smtp", "sqs"]);
+
+        let (config, _, _) = BUILT_IN_SERVICE_DOUBLES["s3"].companion_config();
+        assert_eq!(config.service_name(), "s3");
+        assert_eq!(
+            config.container_type(),
+            &ContainerType::ApplicationCompanion
+        );
+
+        let mut service_config =
+            ServiceConfig::new(String::from("api"), Image::from_str("api").unwrap());
+        BUILT_IN_SERVICE_DOUBLES["s3"].add_app_env_to(&mut service_config);
+        assert!(service_config
+            .env()
+            .and_then(|env| env.variable("AWS_ENDPOINT_URL_S3"))
+            .is_some());
+    }
+
+    #[test]
+    fn should_reuse_mail_catcher_for_smtp_double() {
+        let (config, _, _) = BUILT_IN_SERVICE_DOUBLES["smtp"].companion_config();
+        assert_eq!(config.service_name(), "smtp");
+        assert_eq!(config.port(), 8025);
+        assert_eq!(config.additional_ports(), &vec![1025]);
+
+        let mut service_config =
+            ServiceConfig::new(String::from("api"), Image::from_str("api").unwrap());
+        BUILT_IN_SERVICE_DOUBLES["smtp"].add_app_env_to(&mut service_config);
+        assert_eq!(
+            service_config
+                .env()
+                .and_then(|env| env.variable("SMTP_HOST"))
+                .map(|variable| variable.value()),
+            Some(&SecUtf8::from("smtp"))
+        );
+    }
+}
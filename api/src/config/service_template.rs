@@ -0,0 +1,269 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::{
+    Environment, Image, ImagePullPolicy, ResourceMetadata, ServiceConfig, StartupProbe,
+};
+use secstr::SecUtf8;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// An abstract service definition that services of deployment payloads reference with `extends`.
+/// A template can extend another template. The values of the service take precedence over the
+/// values of its template, which take precedence over the values of the extended template.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ServiceTemplate {
+    #[serde(default)]
+    extends: Option<String>,
+    #[serde(default)]
+    image: Option<Image>,
+    #[serde(default)]
+    env: Option<Environment>,
+    #[serde(alias = "volumes", default)]
+    files: BTreeMap<PathBuf, SecUtf8>,
+    #[serde(default)]
+    command: Option<Vec<String>>,
+    #[serde(default)]
+    args: Option<Vec<String>>,
+    #[serde(default)]
+    working_dir: Option<String>,
+    #[serde(default)]
+    replicas: Option<u32>,
+    #[serde(
+        default,
+        deserialize_with = "crate::models::deserialize_extended_resources"
+    )]
+    extended_resources: BTreeMap<String, String>,
+    #[serde(default)]
+    image_pull_policy: Option<ImagePullPolicy>,
+    #[serde(default)]
+    metadata: Option<ResourceMetadata>,
+    #[serde(default)]
+    startup_probe: Option<StartupProbe>,
+    #[serde(default)]
+    termination_grace_period: Option<u64>,
+}
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum ServiceTemplateError {
+    #[fail(
+        display = "The service {} extends the unknown template {}.",
+        service_name, template
+    )]
+    UnknownTemplate {
+        service_name: String,
+        template: String,
+    },
+    #[fail(display = "The template {} extends itself.", template)]
+    CyclicTemplate { template: String },
+    #[fail(
+        display = "The service {} requires an image, either of its own or of its template.",
+        service_name
+    )]
+    MissingImage { service_name: String },
+}
+
+impl ServiceTemplate {
+    /// Fills the values that the service does not define with the values of this template.
+    fn apply_to(&self, service_config: &mut ServiceConfig) {
+        if service_config.has_image_of_template() {
+            if let Some(image) = &self.image {
+                service_config.set_image(image.clone());
+            }
+        }
+        if let Some(env) = &self.env {
+            for variable in env.iter() {
+                service_config.add_env(variable.clone());
+            }
+        }
+        for (path, data) in &self.files {
+            if !service_config
+                .files()
+                .map_or(false, |files| files.contains_key(path))
+            {
+                service_config.add_file(path.clone(), data.clone());
+            }
+        }
+        if service_config.command().is_none() {
+            service_config.set_command(self.command.clone());
+        }
+        if service_config.args().is_none() {
+            service_config.set_args(self.args.clone());
+        }
+        if service_config.working_dir().is_none() {
+            service_config.set_working_dir(self.working_dir.clone());
+        }
+        if service_config.replicas().is_none() {
+            service_config.set_replicas(self.replicas);
+        }
+        service_config.add_extended_resources(&self.extended_resources);
+        if service_config.image_pull_policy().is_none() {
+            service_config.set_image_pull_policy(self.image_pull_policy);
+        }
+        if let Some(metadata) = &self.metadata {
+            service_config.add_metadata(metadata);
+        }
+        if service_config.startup_probe().is_none() {
+            service_config.set_startup_probe(self.startup_probe.clone());
+        }
+        if service_config.termination_grace_period().is_none() {
+            service_config.set_termination_grace_period(
+                self.termination_grace_period.map(Duration::from_secs),
+            );
+        }
+    }
+}
+
+/// Applies the template that the service extends, and the templates these templates extend, to
+/// the service.
+pub(super) fn resolve_templates(
+    templates: &BTreeMap<String, ServiceTemplate>,
+    service_config: &mut ServiceConfig,
+) -> Result<(), ServiceTemplateError> {
+    let mut visited = BTreeSet::new();
+    let mut next = service_config.extends().cloned();
+
+    while let Some(name) = next {
+        let template =
+            templates
+                .get(&name)
+                .ok_or_else(|| ServiceTemplateError::UnknownTemplate {
+                    service_name: service_config.service_name().clone(),
+                    template: name.clone(),
+                })?;
+        if !visited.insert(name.clone()) {
+            return Err(ServiceTemplateError::CyclicTemplate { template: name });
+        }
+
+        template.apply_to(service_config);
+        next = template.extends.clone();
+    }
+
+    if service_config.has_image_of_template() {
+        return Err(ServiceTemplateError::MissingImage {
+            service_name: service_config.service_name().clone(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templates() -> BTreeMap<String, ServiceTemplate> {
+        toml::de::from_str::<BTreeMap<String, ServiceTemplate>>(
+            r#"
+            [base]
+            env = [ 'LOG_LEVEL=info', 'TZ=UTC' ]
+            extendedResources = { 'example.com/license' = 1 }
+
+            [spring-service]
+            extends = 'base'
+            image = 'example/spring-service:latest'
+            env = [ 'SPRING_PROFILES_ACTIVE=preview', 'LOG_LEVEL=warn' ]
+            replicas = 2
+
+            [spring-service.startupProbe]
+            path = '/actuator/health'
+
+            [cycle-a]
+            extends = 'cycle-b'
+
+            [cycle-b]
+            extends = 'cycle-a'
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_resolve_inherited_templates() {
+        let mut service_config = serde_json::from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "orders",
+            "extends": "spring-service",
+            "env": { "LOG_LEVEL": "debug" }
+        }))
+        .unwrap();
+
+        resolve_templates(&templates(), &mut service_config).unwrap();
+
+        assert_eq!(
+            service_config.image().to_string(),
+            "docker.io/example/spring-service:latest"
+        );
+        assert_eq!(service_config.replicas(), Some(2));
+        let env = service_config.env().unwrap();
+        assert_eq!(
+            env.variable("LOG_LEVEL").unwrap().value().unsecure(),
+            "debug"
+        );
+        assert_eq!(
+            env.variable("SPRING_PROFILES_ACTIVE")
+                .unwrap()
+                .value()
+                .unsecure(),
+            "preview"
+        );
+        assert_eq!(env.variable("TZ").unwrap().value().unsecure(), "UTC");
+        assert_eq!(
+            service_config
+                .extended_resources()
+                .get("example.com/license"),
+            Some(&String::from("1"))
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_templates() {
+        let service = |extends: Option<&str>| {
+            serde_json::from_value::<ServiceConfig>(serde_json::json!({
+                "serviceName": "orders",
+                "extends": extends
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            resolve_templates(&templates(), &mut service(Some("unknown"))),
+            Err(ServiceTemplateError::UnknownTemplate {
+                service_name: String::from("orders"),
+                template: String::from("unknown"),
+            })
+        );
+        assert!(matches!(
+            resolve_templates(&templates(), &mut service(Some("cycle-a"))),
+            Err(ServiceTemplateError::CyclicTemplate { .. })
+        ));
+        assert_eq!(
+            resolve_templates(&templates(), &mut service(None)),
+            Err(ServiceTemplateError::MissingImage {
+                service_name: String::from("orders")
+            })
+        );
+    }
+}
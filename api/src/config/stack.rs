@@ -0,0 +1,96 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::companion::{Companion, DeploymentStrategy, StorageStrategy};
+use crate::models::ServiceConfig;
+use std::collections::BTreeMap;
+
+/// A named bundle of companions, e.g. Kafka with Zookeeper and a schema registry, that will only
+/// be deployed if a deployment request asks for it.
+#[derive(Clone, Deserialize)]
+pub(super) struct Stack {
+    #[serde(default)]
+    companions: BTreeMap<String, Companion>,
+}
+
+impl Stack {
+    pub fn companions(&self) -> impl Iterator<Item = (&String, &Companion)> {
+        self.companions.iter()
+    }
+
+    pub fn companion_configs(
+        &self,
+        app_name: &str,
+    ) -> Vec<(ServiceConfig, DeploymentStrategy, StorageStrategy)> {
+        self.companions
+            .values()
+            .filter(|companion| companion.matches_app_name(app_name))
+            .map(|companion| {
+                (
+                    companion.clone().into(),
+                    companion.deployment_strategy().clone(),
+                    companion.storage_strategy().clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::service::ContainerType;
+
+    #[test]
+    fn should_return_companions_of_stack() {
+        let stack = toml::de::from_str::<Stack>(
+            r#"
+            [companions.zookeeper]
+            serviceName = 'zookeeper'
+            type = 'application'
+            image = 'confluentinc/cp-zookeeper:7.4.0'
+            env = [ 'ZOOKEEPER_CLIENT_PORT=2181' ]
+
+            [companions.kafka]
+            serviceName = 'kafka'
+            type = 'application'
+            image = 'confluentinc/cp-kafka:7.4.0'
+            env = [ 'KAFKA_ZOOKEEPER_CONNECT=zookeeper:2181' ]
+            appSelector = 'master'
+            "#,
+        )
+        .unwrap();
+
+        let configs = stack.companion_configs("PR-1");
+
+        assert_eq!(configs.len(), 1);
+        let (config, _, _) = &configs[0];
+        assert_eq!(config.service_name(), "zookeeper");
+        assert_eq!(
+            config.container_type(),
+            &ContainerType::ApplicationCompanion
+        );
+    }
+}
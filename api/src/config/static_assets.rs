@@ -0,0 +1,238 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::{ContainerConfig, S3Config};
+use crate::models::{AppName, EnvironmentVariable, Image, ServiceConfig};
+use bytesize::ByteSize;
+use secstr::SecUtf8;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use url::Url;
+
+/// Configures the hosting of static assets, e.g. the bundle of a single page application, that
+/// are uploaded to PREvant instead of being built into an image. PREvant stores the bundles and
+/// deploys an nginx service per bundle that downloads the bundle from PREvant on startup.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaticAssetsConfig {
+    storage: StaticAssetsStorage,
+    /// The URL under which the nginx containers reach PREvant, e.g. `http://prevant.tools`.
+    prevant_url: Url,
+    #[serde(default = "StaticAssetsConfig::default_image")]
+    image: Image,
+    #[serde(
+        default = "StaticAssetsConfig::default_max_size",
+        deserialize_with = "StaticAssetsConfig::parse_max_size"
+    )]
+    max_size: ByteSize,
+}
+
+/// Where PREvant keeps the uploaded bundles.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum StaticAssetsStorage {
+    /// A directory of PREvant's file system, e.g. a mounted persistent volume.
+    Directory { path: PathBuf },
+    /// An S3 compatible bucket.
+    S3(S3Config),
+}
+
+/// The formats of bundles that the nginx containers can extract.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BundleFormat {
+    TarGz,
+    Tar,
+    Zip,
+}
+
+impl BundleFormat {
+    /// Detects the format by the magic bytes of the bundle.
+    pub fn detect(bundle: &[u8]) -> Option<BundleFormat> {
+        if bundle.starts_with(&[0x1f, 0x8b]) {
+            Some(BundleFormat::TarGz)
+        } else if bundle.starts_with(b"PK\x03\x04") {
+            Some(BundleFormat::Zip)
+        } else if bundle.len() > 262 && &bundle[257..262] == b"ustar" {
+            Some(BundleFormat::Tar)
+        } else {
+            None
+        }
+    }
+
+    fn extract_command(&self) -> &'static str {
+        match self {
+            BundleFormat::TarGz => "tar -xzf /tmp/bundle -C /usr/share/nginx/html",
+            BundleFormat::Tar => "tar -xf /tmp/bundle -C /usr/share/nginx/html",
+            BundleFormat::Zip => "unzip -o /tmp/bundle -d /usr/share/nginx/html",
+        }
+    }
+}
+
+impl StaticAssetsConfig {
+    fn default_image() -> Image {
+        Image::from_str("nginx:1.25-alpine").unwrap()
+    }
+
+    fn default_max_size() -> ByteSize {
+        ByteSize::mib(100)
+    }
+
+    fn parse_max_size<'de, D>(deserializer: D) -> Result<ByteSize, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        ContainerConfig::parse_from_memory_string(deserializer)
+            .map(|size| size.unwrap_or_else(Self::default_max_size))
+    }
+
+    pub fn storage(&self) -> &StaticAssetsStorage {
+        &self.storage
+    }
+
+    /// The maximal size of an uploaded bundle.
+    pub fn max_size(&self) -> ByteSize {
+        self.max_size
+    }
+
+    /// The nginx service that serves the bundle. The digest of the bundle is part of the service
+    /// so that uploading a new bundle replaces the running container.
+    pub fn service_config(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        format: BundleFormat,
+        digest: &str,
+    ) -> ServiceConfig {
+        let mut bundle_url = self.prevant_url.clone();
+        bundle_url
+            .path_segments_mut()
+            .expect("PREvant's URL should be a base URL")
+            .pop_if_empty()
+            .extend(&[
+                "api",
+                "apps",
+                app_name.as_str(),
+                "static",
+                service_name,
+                "bundle",
+            ]);
+
+        let mut config = ServiceConfig::new(service_name.to_string(), self.image.clone());
+        config.set_port(80);
+        config.set_command(Some(vec![
+            String::from("/bin/sh"),
+            String::from("-c"),
+            format!(
+                "wget -q -O /tmp/bundle \"$BUNDLE_URL\" && {} && rm /tmp/bundle && exec nginx -g 'daemon off;'",
+                format.extract_command()
+            ),
+        ]));
+        config.add_env(EnvironmentVariable::new(
+            String::from("BUNDLE_URL"),
+            SecUtf8::from(bundle_url.as_str()),
+        ));
+        config.add_env(EnvironmentVariable::new(
+            String::from("BUNDLE_DIGEST"),
+            SecUtf8::from(digest),
+        ));
+        config.set_files(Some(BTreeMap::from([(
+            PathBuf::from("/etc/nginx/conf.d/default.conf"),
+            SecUtf8::from(SPA_NGINX_CONF),
+        )])));
+        config
+    }
+}
+
+/// Single page applications route on the client side, thus, unknown paths fall back to the
+/// `index.html` of the bundle.
+const SPA_NGINX_CONF: &str = r#"server {
+    listen 80;
+    root /usr/share/nginx/html;
+
+    location / {
+        try_files $uri $uri/ /index.html;
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_bundle_formats() {
+        assert_eq!(
+            BundleFormat::detect(&[0x1f, 0x8b, 0x08]),
+            Some(BundleFormat::TarGz)
+        );
+        assert_eq!(
+            BundleFormat::detect(b"PK\x03\x04rest"),
+            Some(BundleFormat::Zip)
+        );
+
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(BundleFormat::detect(&tar), Some(BundleFormat::Tar));
+
+        assert_eq!(BundleFormat::detect(b"<html></html>"), None);
+    }
+
+    #[test]
+    fn should_create_nginx_service_that_downloads_the_bundle() {
+        let config = toml::de::from_str::<StaticAssetsConfig>(
+            r#"
+            prevantUrl = 'http://prevant.tools'
+            maxSize = '10MiB'
+
+            [storage]
+            type = 'Directory'
+            path = '/var/lib/prevant/static-assets'
+            "#,
+        )
+        .unwrap();
+
+        let service = config.service_config(
+            &AppName::from_str("feature-1").unwrap(),
+            "web",
+            BundleFormat::Zip,
+            "abc",
+        );
+
+        assert_eq!(config.max_size(), ByteSize::mib(10));
+        assert_eq!(service.service_name(), "web");
+        assert_eq!(service.port(), 80);
+        assert_eq!(
+            service
+                .env()
+                .unwrap()
+                .variable("BUNDLE_URL")
+                .unwrap()
+                .value(),
+            &SecUtf8::from("http://prevant.tools/api/apps/feature-1/static/web/bundle")
+        );
+        assert!(service.command().unwrap()[2].contains("unzip -o /tmp/bundle"));
+    }
+}
@@ -0,0 +1,83 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::Severity;
+use std::path::PathBuf;
+use url::Url;
+
+/// Configures the scanning of deployed images with [Trivy](https://trivy.dev).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrivyConfig {
+    #[serde(default = "TrivyConfig::default_command")]
+    command: PathBuf,
+    server: Option<Url>,
+    block_on: Option<Severity>,
+}
+
+impl TrivyConfig {
+    fn default_command() -> PathBuf {
+        PathBuf::from("trivy")
+    }
+
+    /// The path of the Trivy executable.
+    pub fn command(&self) -> &PathBuf {
+        &self.command
+    }
+
+    /// The Trivy server that performs the scans in client/server mode.
+    pub fn server(&self) -> Option<&Url> {
+        self.server.as_ref()
+    }
+
+    /// Deployments with images that have vulnerabilities of this severity or a higher one will
+    /// be rejected.
+    pub fn block_on(&self) -> Option<Severity> {
+        self.block_on
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_trivy_config() {
+        let config = toml::de::from_str::<TrivyConfig>(
+            r#"
+            server = 'http://trivy:4954'
+            blockOn = 'CRITICAL'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.command(), &PathBuf::from("trivy"));
+        assert_eq!(
+            config.server(),
+            Some(&Url::parse("http://trivy:4954").unwrap())
+        );
+        assert_eq!(config.block_on(), Some(Severity::Critical));
+    }
+}
@@ -0,0 +1,69 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use secstr::SecUtf8;
+
+/// The proxy in front of PREvant, e.g. [oauth2-proxy](https://oauth2-proxy.github.io/oauth2-proxy/),
+/// that authenticates the users. PREvant only trusts the user headers `X-Forwarded-User` and
+/// `X-Auth-Request-User` of requests that carry the secret of the proxy because any client is able
+/// to send these headers.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedProxyConfig {
+    secret: SecUtf8,
+    #[serde(default = "TrustedProxyConfig::default_secret_header")]
+    secret_header: String,
+}
+
+impl TrustedProxyConfig {
+    fn default_secret_header() -> String {
+        String::from("X-Prevant-Proxy-Secret")
+    }
+
+    /// The request header that carries the secret of the proxy.
+    pub fn secret_header(&self) -> &str {
+        &self.secret_header
+    }
+
+    /// Returns `true` if the request has been sent by the proxy, i.e. if it carries the secret.
+    pub fn trusts(&self, secret: Option<&str>) -> bool {
+        secret.map_or(false, |secret| self.secret.unsecure() == secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_trust_requests_with_secret() {
+        let config = toml::from_str::<TrustedProxyConfig>("secret = 'proxy-secret'").unwrap();
+
+        assert_eq!(config.secret_header(), "X-Prevant-Proxy-Secret");
+        assert!(config.trusts(Some("proxy-secret")));
+        assert!(!config.trusts(Some("guess")));
+        assert!(!config.trusts(None));
+    }
+}
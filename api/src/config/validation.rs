@@ -0,0 +1,388 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::{CliArgs, Config};
+use crate::infrastructure::TraefikRouterRule;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::fmt::Display;
+
+const KNOWN_HOOKS: [&str; 1] = ["deployment"];
+
+/// Describes an unknown or invalid key of the configuration. The line and column point to
+/// the position within the configuration file if it can be determined.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigIssue {
+    path: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigValidation {
+    valid: bool,
+    issues: Vec<ConfigIssue>,
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ConfigValidation {
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    pub fn issues(&self) -> &[ConfigIssue] {
+        &self.issues
+    }
+}
+
+/// Validates the configuration file and the configuration values that are provided through
+/// environment variables and CLI arguments.
+pub fn validate(cli: &CliArgs) -> ConfigValidation {
+    let config_file = cli.config_file();
+
+    let mut issues = match std::fs::read_to_string(&config_file) {
+        Ok(content) => validate_str(&content),
+        Err(err) if cli.config.is_some() => vec![ConfigIssue {
+            path: None,
+            line: None,
+            column: None,
+            message: format!("Cannot read {}: {}", config_file.display(), err),
+        }],
+        // Without an explicit config file PREvant runs with the default configuration
+        Err(_) => Vec::new(),
+    };
+
+    // Invalid values of the file itself would be reported twice
+    if issues.iter().all(|issue| issue.path.is_some()) {
+        if let Err(err) = Config::from_figment(cli) {
+            issues.extend(err.into_iter().map(|err| ConfigIssue {
+                path: Some(err.path.join(".")).filter(|path| !path.is_empty()),
+                line: None,
+                column: None,
+                message: err.kind.to_string(),
+            }));
+        }
+    }
+
+    ConfigValidation {
+        valid: issues.is_empty(),
+        issues,
+    }
+}
+
+fn validate_str(content: &str) -> Vec<ConfigIssue> {
+    let mut unknown_keys = Vec::new();
+    let config: Result<Config, _> =
+        serde_ignored::deserialize(toml::de::Deserializer::new(content), |path| {
+            unknown_keys.push(path.to_string())
+        });
+
+    let mut issues = unknown_keys
+        .into_iter()
+        .map(|path| issue_for_key(content, path, "unknown key"))
+        .collect::<Vec<_>>();
+
+    match config {
+        Ok(config) => {
+            for (hook_name, hook_path) in config.hooks.iter().flatten() {
+                let path = format!("hooks.{}", hook_name);
+                if !KNOWN_HOOKS.contains(&hook_name.as_str()) {
+                    issues.push(issue_for_key(content, path, "unknown hook"));
+                } else if !hook_path.is_file() {
+                    let message = format!("hook file {} does not exist", hook_path.display());
+                    issues.push(issue_for_key(content, path, &message));
+                }
+            }
+
+            for (name, companion) in config.companions.iter().flatten() {
+                let Some(router) = companion.router() else {
+                    continue;
+                };
+
+                // Block helpers and Tera tags may generate parts of the rule that can only be
+                // checked after the templating.
+                if router.rule().contains("{{#") || router.rule().contains("{%") {
+                    continue;
+                }
+
+                if let Err(err) = router.rule().parse::<TraefikRouterRule>() {
+                    issues.push(issue_for_key(
+                        content,
+                        format!("companions.{}.router.rule", name),
+                        &err.to_string(),
+                    ));
+                }
+            }
+
+            for (path, message) in config.companion_issues() {
+                issues.push(issue_for_key(content, path, &message));
+            }
+
+            for ca_cert in config.network.extra_ca_certs() {
+                if !ca_cert.is_file() {
+                    let message = format!("CA certificate {} does not exist", ca_cert.display());
+                    issues.push(issue_for_key(
+                        content,
+                        String::from("network.extra_ca_certs"),
+                        &message,
+                    ));
+                }
+            }
+        }
+        Err(err) => {
+            let (line, column) = err
+                .span()
+                .map(|span| position_of_offset(content, span.start))
+                .unzip();
+            issues.push(ConfigIssue {
+                path: None,
+                line,
+                column,
+                message: err.message().to_string(),
+            });
+        }
+    }
+
+    issues
+}
+
+fn issue_for_key(content: &str, path: String, message: &str) -> ConfigIssue {
+    let (line, column) = position_of_key(content, &path).unzip();
+    ConfigIssue {
+        path: Some(path),
+        line,
+        column,
+        message: message.to_string(),
+    }
+}
+
+/// Finds the first table header or key/value pair that belongs to the dotted path. Indices of
+/// arrays are ignored because array entries are defined as repeated tables.
+fn position_of_key(content: &str, path: &str) -> Option<(usize, usize)> {
+    let wanted = split_key(path)
+        .into_iter()
+        .filter(|segment| segment.parse::<usize>().is_err())
+        .collect::<Vec<_>>();
+
+    let mut table = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        let key_path = if let Some(header) = trimmed
+            .strip_prefix("[[")
+            .and_then(|header| header.split("]]").next())
+        {
+            table = split_key(header);
+            Some(table.clone())
+        } else if let Some(header) = trimmed
+            .strip_prefix('[')
+            .and_then(|header| header.split(']').next())
+        {
+            table = split_key(header);
+            Some(table.clone())
+        } else if trimmed.starts_with('#') {
+            None
+        } else {
+            trimmed.split_once('=').map(|(key, _)| {
+                table
+                    .iter()
+                    .cloned()
+                    .chain(split_key(key))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        if matches!(key_path, Some(key_path) if key_path.starts_with(&wanted)) {
+            return Some(position_of_offset(content, offset + indent));
+        }
+
+        offset += line.len();
+    }
+
+    None
+}
+
+fn split_key(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut quote = None;
+
+    for c in key.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('.', None) => segments.push(std::mem::take(&mut segment)),
+            (c, None) if c.is_whitespace() => {}
+            (c, _) => segment.push(c),
+        }
+    }
+    segments.push(segment);
+
+    segments
+}
+
+fn position_of_offset(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+/// Validates the configuration file that PREvant has been started with. This allows to check
+/// modifications of the file before restarting PREvant.
+#[get("/config/validate", format = "application/json")]
+pub fn validate_config(cli: &State<CliArgs>) -> Json<ConfigValidation> {
+    Json(validate(cli))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_accept_valid_config() {
+        let issues = validate_str(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+
+            [registries.'docker.io']
+            username = "user"
+            password = "pass"
+            "#,
+        );
+
+        assert_eq!(issues, Vec::new());
+    }
+
+    #[test]
+    fn should_report_invalid_router_rules() {
+        let issues = validate_str(
+            r#"[companions.openid]
+serviceName = 'openid'
+type = 'application'
+image = 'private.example.com/library/openid:latest'
+
+[companions.openid.router]
+rule = 'PathPrefix(`/{{application.name}}/openid/`) && Hots(`example.com`)'
+"#,
+        );
+
+        assert_eq!(
+            issues,
+            vec![ConfigIssue {
+                path: Some(String::from("companions.openid.router.rule")),
+                line: Some(7),
+                column: Some(1),
+                message: String::from("Invalid router rule: unknown matcher Hots"),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_report_unknown_keys_with_position() {
+        let issues = validate_str(
+            r#"[companions.openid]
+serviceName = 'openid'
+type = 'application'
+image = 'private.example.com/library/openid:latest'
+  deploymentStrategie = 'redeploy-never'
+
+[companoins.db]
+image = 'postgres'
+"#,
+        );
+
+        assert_eq!(
+            issues,
+            vec![
+                ConfigIssue {
+                    path: Some(String::from("companions.openid.deploymentStrategie")),
+                    line: Some(5),
+                    column: Some(3),
+                    message: String::from("unknown key"),
+                },
+                ConfigIssue {
+                    path: Some(String::from("companoins")),
+                    line: Some(7),
+                    column: Some(1),
+                    message: String::from("unknown key"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_report_invalid_values_with_position() {
+        let issues = validate_str(
+            r#"[companions.openid]
+serviceName = 'openid'
+type = 'applikation'
+image = 'private.example.com/library/openid:latest'
+"#,
+        );
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(3));
+        assert!(issues[0].message.contains("applikation"));
+    }
+
+    #[test]
+    fn should_report_missing_hook_files() {
+        let issues = validate_str(
+            r#"[hooks]
+deployment = '/does/not/exist.js'
+"#,
+        );
+
+        assert_eq!(
+            issues,
+            vec![ConfigIssue {
+                path: Some(String::from("hooks.deployment")),
+                line: Some(2),
+                column: Some(1),
+                message: String::from("hook file /does/not/exist.js does not exist"),
+            }]
+        );
+    }
+}
@@ -26,10 +26,15 @@
 use crate::apps::AppsServiceError;
 use crate::config::{Config, StorageStrategy};
 use crate::deployment::hooks::Hooks;
-use crate::infrastructure::TraefikIngressRoute;
-use crate::models::{AppName, ContainerType, Image, ServiceConfig};
-use crate::registry::ImageInfo;
-use std::collections::{HashMap, HashSet};
+use crate::infrastructure::{TraefikIngressRoute, TraefikRouterRule};
+use crate::models::{
+    AppName, ContainerType, CustomResource, Environment, EnvironmentVariable, HelmRelease, Image,
+    Locale, ReplicationRules, ResourceMetadata, ServiceConfig, TimeZone,
+};
+use crate::registry::{ImageInfo, ImagePlatform};
+use secstr::SecUtf8;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
 
 pub struct Initialized {
     app_name: AppName,
@@ -82,6 +87,7 @@ pub struct WithResolvedImages {
     )>,
     templating_only_service_configs: Vec<ServiceConfig>,
     image_infos: HashMap<Image, ImageInfo>,
+    app_metadata: BTreeMap<String, String>,
 }
 
 pub struct WithAppliedTemplating {
@@ -106,6 +112,11 @@ pub struct DeploymentUnitBuilder<Stage> {
 pub struct DeploymentUnit {
     app_name: AppName,
     services: Vec<DeployableService>,
+    namespace_annotations: BTreeMap<String, String>,
+    app_metadata: BTreeMap<String, String>,
+    target: Option<String>,
+    helm_releases: Vec<HelmRelease>,
+    custom_resources: Vec<CustomResource>,
 }
 
 #[derive(Clone, Debug)]
@@ -121,6 +132,8 @@ pub struct DeployableService {
     strategy: DeploymentStrategy,
     ingress_route: TraefikIngressRoute,
     declared_volumes: Vec<String>,
+    image_digest: Option<String>,
+    image_platforms: Vec<ImagePlatform>,
 }
 
 impl DeployableService {
@@ -136,6 +149,8 @@ impl DeployableService {
             strategy,
             ingress_route,
             declared_volumes,
+            image_digest: None,
+            image_platforms: Vec::new(),
         }
     }
 
@@ -150,6 +165,27 @@ impl DeployableService {
     pub fn declared_volumes(&self) -> &Vec<String> {
         &self.declared_volumes
     }
+
+    /// The digest of the image that has been resolved from the registry while building the
+    /// deployment unit.
+    pub fn image_digest(&self) -> Option<&String> {
+        self.image_digest.as_ref()
+    }
+
+    /// The platforms the image supports according to the registry. The list is empty if the
+    /// registry has not been consulted or does not provide this information.
+    pub fn image_platforms(&self) -> &Vec<ImagePlatform> {
+        &self.image_platforms
+    }
+
+    /// Renames the service and moves its route to the new name, e.g. on behalf of a hook.
+    pub(super) fn rename(&mut self, app_name: &AppName, service_name: &String) {
+        self.raw_service_config.set_service_name(service_name);
+        self.ingress_route = DeploymentUnitBuilder::<WithResolvedImages>::ingress_route(
+            app_name,
+            &self.raw_service_config,
+        );
+    }
 }
 
 impl std::ops::Deref for DeployableService {
@@ -174,6 +210,78 @@ impl DeploymentUnit {
     pub fn app_name(&self) -> &AppName {
         &self.app_name
     }
+
+    /// Annotations that have been requested for the namespace of the app. Infrastructures that
+    /// do not support namespaces ignore them.
+    pub fn namespace_annotations(&self) -> &BTreeMap<String, String> {
+        &self.namespace_annotations
+    }
+
+    pub fn with_namespace_annotations(
+        mut self,
+        namespace_annotations: BTreeMap<String, String>,
+    ) -> Self {
+        self.namespace_annotations = namespace_annotations;
+        self
+    }
+
+    /// Arbitrary metadata of the app, e.g. the originating branch, that infrastructures store
+    /// alongside the app and return with its services.
+    pub fn app_metadata(&self) -> &BTreeMap<String, String> {
+        &self.app_metadata
+    }
+
+    pub fn with_app_metadata(mut self, app_metadata: BTreeMap<String, String>) -> Self {
+        self.app_metadata = app_metadata;
+        self
+    }
+
+    /// The name of the cluster the app should be deployed to. `None` refers to the cluster the
+    /// app is already running on or to the default runtime.
+    pub fn target(&self) -> Option<&String> {
+        self.target.as_ref()
+    }
+
+    pub fn with_target(mut self, target: Option<String>) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// The Helm charts of the companions that infrastructures supporting Helm install alongside
+    /// the services.
+    pub fn helm_releases(&self) -> &Vec<HelmRelease> {
+        &self.helm_releases
+    }
+
+    pub fn with_helm_releases(mut self, helm_releases: Vec<HelmRelease>) -> Self {
+        self.helm_releases = helm_releases;
+        self
+    }
+
+    /// The custom resources of the companions that infrastructures supporting them apply
+    /// alongside the services.
+    pub fn custom_resources(&self) -> &Vec<CustomResource> {
+        &self.custom_resources
+    }
+
+    pub fn with_custom_resources(mut self, custom_resources: Vec<CustomResource>) -> Self {
+        self.custom_resources = custom_resources;
+        self
+    }
+
+    /// Restricts the unit to the given services, e.g. to redeploy a single service, and drops
+    /// the Helm releases and custom resources of the app. An empty list keeps the whole unit.
+    pub fn restrict_to_services(mut self, service_names: &[String]) -> Self {
+        if service_names.is_empty() {
+            return self;
+        }
+
+        self.services
+            .retain(|service| service_names.contains(service.service_name()));
+        self.helm_releases.clear();
+        self.custom_resources.clear();
+        self
+    }
 }
 
 impl DeploymentUnitBuilder<Initialized> {
@@ -188,13 +296,66 @@ impl DeploymentUnitBuilder<Initialized> {
 }
 
 impl DeploymentUnitBuilder<Initialized> {
+    /// Adds the services replicated from the template app, except for the ones excluded by the
+    /// replication rules, and applies the overrides of the rules to them.
+    pub fn extend_with_replicated_configs(
+        mut self,
+        replicated_configs: Vec<ServiceConfig>,
+        replication_rules: &ReplicationRules,
+    ) -> Self {
+        self.stage.configs.extend(
+            replicated_configs
+                .into_iter()
+                .filter_map(|config| replication_rules.apply_to(config)),
+        );
+        self
+    }
+
     pub fn extend_with_config(mut self, config: &Config) -> DeploymentUnitBuilder<WithCompanions> {
+        let mail_catcher = config.mail_catcher_config(&self.stage.app_name);
+        let shared_database = config.shared_database_config(&self.stage.app_name);
+
         for service_config in self.stage.configs.iter_mut() {
             config.add_secrets_to(service_config, &self.stage.app_name);
+            config.add_metadata_to(service_config, &self.stage.app_name);
+            config.add_headers_to(service_config, &self.stage.app_name);
+            config.add_placeholder_to(service_config, &self.stage.app_name);
+            config.add_forward_auth_to(service_config, &self.stage.app_name);
+            config.add_extended_resources_to(service_config);
+            config.add_shared_companion_env_to(service_config, &self.stage.app_name);
+            if let Some(mail_catcher) = mail_catcher {
+                mail_catcher.add_smtp_env_to(service_config);
+            }
+            if let Some(shared_database) = shared_database {
+                shared_database.add_database_env_to(service_config, &self.stage.app_name);
+            }
         }
 
-        let service_companions = config.service_companion_configs(&self.stage.app_name);
-        let app_companions = config.application_companion_configs(&self.stage.app_name);
+        let mut service_companions = config.service_companion_configs(&self.stage.app_name);
+        let mut app_companions = config.application_companion_configs(&self.stage.app_name);
+        if let Some(mail_catcher) = mail_catcher {
+            if !app_companions
+                .iter()
+                .any(|(c, _, _)| c.service_name() == mail_catcher.service_name())
+            {
+                // Keep the captured mails when the app is redeployed
+                app_companions.push((
+                    mail_catcher.companion_config(),
+                    crate::config::DeploymentStrategy::RedeployOnImageUpdate,
+                    StorageStrategy::NoMountVolumes,
+                ));
+            }
+        }
+        for (companion_config, _, _) in service_companions
+            .iter_mut()
+            .chain(app_companions.iter_mut())
+        {
+            config.add_metadata_to(companion_config, &self.stage.app_name);
+            config.add_headers_to(companion_config, &self.stage.app_name);
+            config.add_placeholder_to(companion_config, &self.stage.app_name);
+            config.add_forward_auth_to(companion_config, &self.stage.app_name);
+            config.add_extended_resources_to(companion_config);
+        }
 
         DeploymentUnitBuilder {
             stage: WithCompanions {
@@ -208,6 +369,154 @@ impl DeploymentUnitBuilder<Initialized> {
 }
 
 impl DeploymentUnitBuilder<WithCompanions> {
+    /// Adds the companions of the requested stacks. Companions of a stack replace the
+    /// companions of the config with the same service name.
+    pub fn extend_with_stacks(
+        mut self,
+        config: &Config,
+        stacks: &[String],
+    ) -> Result<Self, AppsServiceError> {
+        for stack in stacks {
+            let companions = config
+                .stack_companion_configs(stack, &self.stage.app_name)
+                .ok_or_else(|| AppsServiceError::UnknownStack {
+                    stack: stack.clone(),
+                })?;
+
+            for (mut companion_config, strategy, storage_strategy) in companions {
+                config.add_metadata_to(&mut companion_config, &self.stage.app_name);
+                config.add_headers_to(&mut companion_config, &self.stage.app_name);
+                config.add_placeholder_to(&mut companion_config, &self.stage.app_name);
+                config.add_forward_auth_to(&mut companion_config, &self.stage.app_name);
+                config.add_extended_resources_to(&mut companion_config);
+
+                let companions = match companion_config.container_type() {
+                    ContainerType::ServiceCompanion => &mut self.stage.service_companions,
+                    _ => &mut self.stage.app_companions,
+                };
+                companions.retain(|(c, _, _)| c.service_name() != companion_config.service_name());
+                companions.push((companion_config, strategy, storage_strategy));
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Adds the requested service doubles as app companions and adds their `appEnv` to the
+    /// services of the app. Variables of the services take precedence.
+    pub fn extend_with_service_doubles(
+        mut self,
+        config: &Config,
+        service_doubles: &[String],
+    ) -> Result<Self, AppsServiceError> {
+        for name in service_doubles {
+            let service_double = config
+                .service_double(name)
+                .ok_or_else(|| AppsServiceError::UnknownServiceDouble { name: name.clone() })?;
+
+            for service_config in self.stage.configs.iter_mut() {
+                service_double.add_app_env_to(service_config);
+            }
+
+            let (mut companion_config, strategy, storage_strategy) =
+                service_double.companion_config();
+            config.add_metadata_to(&mut companion_config, &self.stage.app_name);
+            config.add_headers_to(&mut companion_config, &self.stage.app_name);
+            config.add_placeholder_to(&mut companion_config, &self.stage.app_name);
+            config.add_forward_auth_to(&mut companion_config, &self.stage.app_name);
+            config.add_extended_resources_to(&mut companion_config);
+
+            self.stage
+                .app_companions
+                .retain(|(c, _, _)| c.service_name() != companion_config.service_name());
+            self.stage
+                .app_companions
+                .push((companion_config, strategy, storage_strategy));
+        }
+
+        Ok(self)
+    }
+
+    /// Applies the time zone and locale of the app to all services and companions without their
+    /// own and materializes them as `TZ` and `LANG` variables unless the services define these
+    /// variables explicitly.
+    pub fn extend_with_localization(
+        mut self,
+        timezone: Option<&TimeZone>,
+        locale: Option<&Locale>,
+    ) -> Self {
+        for service_config in self.stage.configs.iter_mut().chain(
+            self.stage
+                .service_companions
+                .iter_mut()
+                .chain(self.stage.app_companions.iter_mut())
+                .map(|(companion_config, _, _)| companion_config),
+        ) {
+            if service_config.timezone().is_none() {
+                service_config.set_timezone(timezone.cloned());
+            }
+            if service_config.locale().is_none() {
+                service_config.set_locale(locale.cloned());
+            }
+
+            if let Some(timezone) = service_config.timezone().cloned() {
+                service_config.add_env(EnvironmentVariable::new(
+                    String::from("TZ"),
+                    SecUtf8::from(timezone.to_string()),
+                ));
+            }
+            if let Some(locale) = service_config.locale().cloned() {
+                service_config.add_env(EnvironmentVariable::new(
+                    String::from("LANG"),
+                    SecUtf8::from(locale.to_string()),
+                ));
+            }
+        }
+        self
+    }
+
+    /// Injects the app env of the deployment request and of the configuration into all services
+    /// and companions of the app. The variables of a service take precedence over the app env of
+    /// the request which takes precedence over the app env of the configuration.
+    pub fn extend_with_app_env(mut self, config: &Config, app_env: Option<&Environment>) -> Self {
+        let app_name = &self.stage.app_name;
+        for service_config in self.stage.configs.iter_mut().chain(
+            self.stage
+                .service_companions
+                .iter_mut()
+                .chain(self.stage.app_companions.iter_mut())
+                .map(|(companion_config, _, _)| companion_config),
+        ) {
+            if let Some(app_env) = app_env {
+                for variable in app_env.iter() {
+                    service_config.add_env(variable.clone().with_templated(true));
+                }
+            }
+            config.add_app_env_to(service_config, app_name);
+        }
+        self
+    }
+
+    /// Adds the labels and annotations of the deployment request to all services and companions
+    /// of the app. The metadata of the services and the metadata of the configuration take
+    /// precedence so that requests cannot override labels that are enforced by the platform.
+    pub fn extend_with_resource_metadata(mut self, metadata: Option<&ResourceMetadata>) -> Self {
+        let Some(metadata) = metadata else {
+            return self;
+        };
+
+        for service_config in self.stage.configs.iter_mut().chain(
+            self.stage
+                .service_companions
+                .iter_mut()
+                .chain(self.stage.app_companions.iter_mut())
+                .map(|(companion_config, _, _)| companion_config),
+        ) {
+            service_config.add_metadata(metadata);
+        }
+        self
+    }
+
     pub fn extend_with_templating_only_service_configs(
         self,
         templating_only_service_configs: Vec<ServiceConfig>,
@@ -288,6 +597,7 @@ impl DeploymentUnitBuilder<WithTemplatedConfigs> {
                 app_companions: self.stage.app_companions,
                 templating_only_service_configs: self.stage.templating_only_service_configs,
                 image_infos,
+                app_metadata: BTreeMap::new(),
             },
         }
     }
@@ -296,7 +606,7 @@ impl DeploymentUnitBuilder<WithTemplatedConfigs> {
     where
         Iter: Iterator<Item = &'a mut ServiceConfig>,
     {
-        for config in configs {
+        for config in configs.filter(|config| !config.has_fixed_port()) {
             if let Some(info) = image_infos.get(config.image()) {
                 if let Some(port) = info.exposed_port() {
                     config.set_port(port);
@@ -307,24 +617,32 @@ impl DeploymentUnitBuilder<WithTemplatedConfigs> {
 }
 
 impl DeploymentUnitBuilder<WithResolvedImages> {
+    /// Makes the metadata of the app, e.g. its originating branch, available to the templates as
+    /// `application.metadata`.
+    pub fn with_app_metadata(mut self, app_metadata: BTreeMap<String, String>) -> Self {
+        self.stage.app_metadata = app_metadata;
+        self
+    }
+
     pub fn apply_templating(
         self,
     ) -> Result<DeploymentUnitBuilder<WithAppliedTemplating>, AppsServiceError> {
         let mut services = HashMap::new();
 
         for config in self.stage.configs.iter() {
-            let templated_config = config.apply_templating(&self.stage.app_name)?;
+            let templated_config = config
+                .apply_templating_with_metadata(&self.stage.app_name, &self.stage.app_metadata)?;
+            let ingress_route = Self::ingress_route(&self.stage.app_name, &templated_config);
 
             services.insert(
                 config.service_name().clone(),
                 DeployableService {
                     raw_service_config: templated_config,
                     strategy: DeploymentStrategy::RedeployAlways,
-                    ingress_route: TraefikIngressRoute::with_defaults(
-                        &self.stage.app_name,
-                        config.service_name(),
-                    ),
+                    ingress_route,
                     declared_volumes: Vec::new(),
+                    image_digest: Self::image_digest(config.image(), &self.stage.image_infos),
+                    image_platforms: Self::image_platforms(config.image(), &self.stage.image_infos),
                 },
             );
         }
@@ -347,7 +665,11 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
                 self.stage.service_companions.iter()
             {
                 let templated_companion = service_companion
-                    .apply_templating_for_service_companion(&self.stage.app_name, &service)?;
+                    .apply_templating_for_service_companion_with_metadata(
+                        &self.stage.app_name,
+                        &service,
+                        &self.stage.app_metadata,
+                    )?;
 
                 service_companions.push(ServiceCompanion {
                     templated_companion,
@@ -410,10 +732,12 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
                 .map(|strategy| ServiceConfig::clone(strategy)),
         );
         for (companion_config, strategy, storage_strategy) in self.stage.app_companions.iter() {
-            let companion_config = companion_config.apply_templating_for_application_companion(
-                &self.stage.app_name,
-                &templating_only_service_configs,
-            )?;
+            let companion_config = companion_config
+                .apply_templating_for_application_companion_with_metadata(
+                    &self.stage.app_name,
+                    &templating_only_service_configs,
+                    &self.stage.app_metadata,
+                )?;
 
             // If a custom application companion was deployed, its config needs to be merged
             // with the companion config
@@ -434,6 +758,21 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
             }
         }
 
+        for service in services.values_mut() {
+            service.apply_removals();
+        }
+
+        for service in services.values() {
+            if let Some(router) = service.router() {
+                TraefikRouterRule::from_str(router.rule()).map_err(|error| {
+                    AppsServiceError::InvalidRouterRule {
+                        service_name: service.service_name().clone(),
+                        error,
+                    }
+                })?;
+            }
+        }
+
         let mut strategies = services.into_values().collect::<Vec<_>>();
 
         strategies.sort_unstable_by(|a, b| {
@@ -457,10 +796,7 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
         storage_strategy: &StorageStrategy,
         image_infos: &HashMap<Image, ImageInfo>,
     ) -> DeployableService {
-        let ingress_route = TraefikIngressRoute::with_defaults(
-            &self.stage.app_name,
-            raw_service_config.service_name(),
-        );
+        let ingress_route = Self::ingress_route(&self.stage.app_name, &raw_service_config);
 
         let volume_paths = match image_infos.get(raw_service_config.image()) {
             None => Vec::new(),
@@ -475,12 +811,17 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
                 .collect(),
         };
 
+        let image_digest = Self::image_digest(raw_service_config.image(), image_infos);
+        let image_platforms = Self::image_platforms(raw_service_config.image(), image_infos);
+
         match strategy {
             crate::config::DeploymentStrategy::RedeployAlways => DeployableService {
                 raw_service_config,
                 ingress_route,
                 strategy: DeploymentStrategy::RedeployAlways,
                 declared_volumes,
+                image_digest,
+                image_platforms,
             },
             crate::config::DeploymentStrategy::RedeployOnImageUpdate => {
                 match image_infos.get(raw_service_config.image()) {
@@ -491,6 +832,8 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
                             image_info.digest().to_string(),
                         ),
                         declared_volumes,
+                        image_digest,
+                        image_platforms,
                     },
 
                     None => DeployableService {
@@ -498,6 +841,8 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
                         ingress_route,
                         strategy: DeploymentStrategy::RedeployAlways,
                         declared_volumes,
+                        image_digest,
+                        image_platforms,
                     },
                 }
             }
@@ -506,10 +851,38 @@ impl DeploymentUnitBuilder<WithResolvedImages> {
                 ingress_route,
                 strategy: DeploymentStrategy::RedeployNever,
                 declared_volumes,
+                image_digest,
+                image_platforms,
             },
         }
     }
 
+    fn ingress_route(app_name: &AppName, service_config: &ServiceConfig) -> TraefikIngressRoute {
+        let mut ingress_route =
+            TraefikIngressRoute::with_defaults(app_name, service_config.service_name());
+        if let Some(middlewares) = service_config.middlewares() {
+            ingress_route.add_middlewares(app_name, service_config.service_name(), middlewares);
+        }
+        ingress_route
+    }
+
+    fn image_digest(image: &Image, image_infos: &HashMap<Image, ImageInfo>) -> Option<String> {
+        match image {
+            Image::Digest { hash } => Some(hash.clone()),
+            image => image_infos.get(image).map(|info| info.digest().clone()),
+        }
+    }
+
+    fn image_platforms(
+        image: &Image,
+        image_infos: &HashMap<Image, ImageInfo>,
+    ) -> Vec<ImagePlatform> {
+        image_infos
+            .get(image)
+            .map(|info| info.platforms().clone())
+            .unwrap_or_default()
+    }
+
     fn container_type_index(container_type: &ContainerType) -> i32 {
         match container_type {
             ContainerType::ApplicationCompanion => 0,
@@ -560,6 +933,11 @@ impl DeploymentUnitBuilder<WithAppliedHooks> {
         DeploymentUnit {
             app_name: self.stage.app_name,
             services: self.stage.services,
+            namespace_annotations: BTreeMap::new(),
+            app_metadata: BTreeMap::new(),
+            target: None,
+            helm_releases: Vec::new(),
+            custom_resources: Vec::new(),
         }
     }
 }
@@ -569,6 +947,11 @@ impl DeploymentUnitBuilder<WithAppliedIngressRoute> {
         DeploymentUnit {
             app_name: self.stage.app_name,
             services: self.stage.services,
+            namespace_annotations: BTreeMap::new(),
+            app_metadata: BTreeMap::new(),
+            target: None,
+            helm_releases: Vec::new(),
+            custom_resources: Vec::new(),
         }
     }
 }
@@ -580,6 +963,7 @@ mod tests {
     use crate::models::{Environment, EnvironmentVariable};
     use crate::{config_from_str, sc};
     use secstr::SecUtf8;
+    use std::path::PathBuf;
     use std::str::FromStr;
 
     #[tokio::test]
@@ -689,6 +1073,100 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_unset_env_and_remove_files_provided_by_companions(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [companions.openid]
+            serviceName = 'openid'
+            type = 'application'
+            image = 'private.example.com/library/openid:latest'
+            env = [ "VAR_1=abcd", "VAR_2=1234" ]
+
+            [companions.openid.files]
+            '/etc/openid/realm.json' = 'realm'
+            '/etc/openid/theme.json' = 'theme'
+        "#
+        );
+
+        let app_name = AppName::from_str("master").unwrap();
+        let service_config = serde_json::from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "openid",
+            "image": "private.example.com/library/openid:latest",
+            "env": { "VAR_3": "xyz" },
+            "unsetEnv": [ "VAR_1", "VAR_3" ],
+            "removeFiles": [ "/etc/openid/theme.json" ]
+        }))
+        .unwrap();
+
+        let unit = DeploymentUnitBuilder::init(app_name, vec![service_config])
+            .extend_with_config(&config)
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await?
+            .build();
+
+        let openid_configs: Vec<_> = unit.services;
+        assert_eq!(openid_configs.len(), 1);
+
+        let openid_env = openid_configs[0].env().unwrap();
+        assert_eq!(
+            openid_env.iter().map(|env| env.key()).collect::<Vec<_>>(),
+            vec!["VAR_2"]
+        );
+
+        let openid_files = openid_configs[0].files().unwrap();
+        assert_eq!(
+            openid_files.keys().collect::<Vec<_>>(),
+            vec![&PathBuf::from("/etc/openid/realm.json")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_deploy_mail_catcher_and_point_services_to_it() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [mailCatcher]
+            appSelector = 'master'
+        "#
+        );
+
+        let unit = DeploymentUnitBuilder::init(AppName::master(), vec![sc!("api")])
+            .extend_with_config(&config)
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await?
+            .build();
+
+        let mail = unit
+            .services
+            .iter()
+            .find(|service| service.service_name() == "mail")
+            .unwrap();
+        assert_eq!(mail.container_type(), &ContainerType::ApplicationCompanion);
+        assert_eq!(mail.port(), 8025);
+
+        let api_env = unit
+            .services
+            .iter()
+            .find(|service| service.service_name() == "api")
+            .and_then(|service| service.env())
+            .unwrap();
+        assert_eq!(
+            api_env.variable("SMTP_HOST").unwrap().value().unsecure(),
+            "mail"
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_merge_with_service_companion_if_services_contain_same_service_name(
     ) -> Result<(), AppsServiceError> {
@@ -827,6 +1305,133 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_inject_app_env_with_precedence_of_service_env() -> Result<(), AppsServiceError>
+    {
+        let config = config_from_str!(
+            r#"
+            [[appEnv]]
+            env = { SENTRY_ENVIRONMENT = 'preview', LOG_LEVEL = 'info', TZ = 'UTC' }
+            "#
+        );
+        let mut service_config = sc!("wordpress", "wordpress:latest");
+        service_config.set_env(Some(Environment::new(vec![EnvironmentVariable::new(
+            String::from("LOG_LEVEL"),
+            SecUtf8::from("debug"),
+        )])));
+        let app_env = Environment::new(vec![EnvironmentVariable::new(
+            String::from("SENTRY_ENVIRONMENT"),
+            SecUtf8::from("{{application.name}}"),
+        )]);
+
+        let unit = DeploymentUnitBuilder::init(AppName::master(), vec![service_config])
+            .extend_with_config(&config)
+            .extend_with_app_env(&config, Some(&app_env))
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await?
+            .build();
+
+        let env = unit.services[0].env().unwrap();
+        let value = |key: &str| env.variable(key).map(|v| v.value().unsecure().to_string());
+        assert_eq!(value("SENTRY_ENVIRONMENT"), Some(String::from("master")));
+        assert_eq!(value("LOG_LEVEL"), Some(String::from("debug")));
+        assert_eq!(value("TZ"), Some(String::from("UTC")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_add_resource_metadata_of_request_with_precedence_of_config(
+    ) -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [[metadata]]
+            labels = { 'cost-center' = '4711' }
+            "#
+        );
+        let mut service_config = sc!("wordpress", "wordpress:latest");
+        service_config.set_metadata(Some(ResourceMetadata::new(
+            BTreeMap::from([(String::from("team"), String::from("blue"))]),
+            BTreeMap::new(),
+        )));
+        let metadata = ResourceMetadata::new(
+            BTreeMap::from([
+                (String::from("cost-center"), String::from("0815")),
+                (String::from("team"), String::from("red")),
+                (String::from("owner"), String::from("alice")),
+            ]),
+            BTreeMap::from([(String::from("example.com/ticket"), String::from("PREV-42"))]),
+        );
+
+        let unit = DeploymentUnitBuilder::init(AppName::master(), vec![service_config])
+            .extend_with_config(&config)
+            .extend_with_resource_metadata(Some(&metadata))
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await?
+            .build();
+
+        assert_eq!(
+            unit.services[0].metadata(),
+            Some(&ResourceMetadata::new(
+                BTreeMap::from([
+                    (String::from("cost-center"), String::from("4711")),
+                    (String::from("owner"), String::from("alice")),
+                    (String::from("team"), String::from("blue")),
+                ]),
+                BTreeMap::from([(String::from("example.com/ticket"), String::from("PREV-42"))]),
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_apply_time_zone_and_locale_of_app_to_services() -> Result<(), AppsServiceError>
+    {
+        let config = Config::default();
+        let mut berlin = sc!("berlin", "nginx:latest");
+        berlin.set_timezone(Some(TimeZone::from_str("Europe/Berlin").unwrap()));
+        let mut lisbon = sc!("lisbon", "nginx:latest");
+        lisbon.set_env(Some(Environment::new(vec![EnvironmentVariable::new(
+            String::from("LANG"),
+            SecUtf8::from("pt_PT.UTF-8"),
+        )])));
+
+        let unit = DeploymentUnitBuilder::init(AppName::master(), vec![berlin, lisbon])
+            .extend_with_config(&config)
+            .extend_with_localization(
+                Some(&TimeZone::from_str("UTC").unwrap()),
+                Some(&Locale::from_str("en_US.UTF-8").unwrap()),
+            )
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await?
+            .build();
+
+        let value = |service_name: &str, key: &str| {
+            unit.services
+                .iter()
+                .find(|service| service.service_name() == service_name)
+                .and_then(|service| service.env())
+                .and_then(|env| env.variable(key))
+                .map(|variable| variable.value().unsecure().to_string())
+        };
+        assert_eq!(value("berlin", "TZ"), Some(String::from("Europe/Berlin")));
+        assert_eq!(value("berlin", "LANG"), Some(String::from("en_US.UTF-8")));
+        assert_eq!(value("lisbon", "TZ"), Some(String::from("UTC")));
+        assert_eq!(value("lisbon", "LANG"), Some(String::from("pt_PT.UTF-8")));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_not_apply_templating_on_service_environment_variables(
     ) -> Result<(), AppsServiceError> {
@@ -916,6 +1521,157 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_add_requested_service_doubles() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [serviceDoubles.smtp]
+            serviceName = 'smtp'
+            type = 'application'
+            image = 'axllent/mailpit:v1.12'
+            appEnv = [ 'SMTP_HOST=smtp', 'SMTP_PORT=1025' ]
+        "#
+        );
+
+        let app_name = AppName::from_str("master").unwrap();
+        let mut wordpress = sc!("wordpress", "wordpress:latest");
+        wordpress.set_env(Some(Environment::new(vec![EnvironmentVariable::new(
+            String::from("AWS_REGION"),
+            SecUtf8::from("eu-central-1"),
+        )])));
+
+        let unit = DeploymentUnitBuilder::init(app_name, vec![wordpress])
+            .extend_with_config(&config)
+            .extend_with_stacks(&config, &[])?
+            .extend_with_service_doubles(&config, &[String::from("s3"), String::from("smtp")])?
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await?
+            .build();
+
+        let mut service_names = unit
+            .services
+            .iter()
+            .map(|config| config.service_name().clone())
+            .collect::<Vec<_>>();
+        service_names.sort();
+        assert_eq!(service_names, vec!["s3", "smtp", "wordpress"]);
+
+        let smtp = unit
+            .services
+            .iter()
+            .find(|config| config.service_name() == "smtp")
+            .unwrap();
+        assert_eq!(smtp.image().to_string(), "docker.io/axllent/mailpit:v1.12");
+
+        let env = unit
+            .services
+            .iter()
+            .find(|config| config.service_name() == "wordpress")
+            .and_then(|config| config.env())
+            .unwrap();
+        let value = |key: &str| {
+            env.variable(key)
+                .map(|variable| variable.value().unsecure().to_string())
+        };
+        assert_eq!(
+            value("AWS_ENDPOINT_URL_S3"),
+            Some(String::from("http://s3:9000"))
+        );
+        assert_eq!(value("AWS_REGION"), Some(String::from("eu-central-1")));
+        assert_eq!(value("SMTP_HOST"), Some(String::from("smtp")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_unknown_service_double() {
+        let config = Config::default();
+
+        let result = DeploymentUnitBuilder::init(AppName::master(), Vec::new())
+            .extend_with_config(&config)
+            .extend_with_service_doubles(&config, &[String::from("mainframe")]);
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::UnknownServiceDouble { name }) if name == "mainframe"
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_add_companions_of_requested_stacks() -> Result<(), AppsServiceError> {
+        let config = config_from_str!(
+            r#"
+            [stacks.kafka.companions.zookeeper]
+            serviceName = 'zookeeper'
+            type = 'application'
+            image = 'confluentinc/cp-zookeeper:7.4.0'
+
+            [stacks.kafka.companions.kafka]
+            serviceName = 'kafka'
+            type = 'application'
+            image = 'confluentinc/cp-kafka:7.4.0'
+            env = [ 'KAFKA_ADVERTISED_LISTENERS=PLAINTEXT://{{application.name}}-kafka:9092' ]
+        "#
+        );
+
+        let app_name = AppName::from_str("master").unwrap();
+        let service_configs = vec![sc!("wordpress", "wordpress:latest")];
+
+        let unit = DeploymentUnitBuilder::init(app_name, service_configs)
+            .extend_with_config(&config)
+            .extend_with_stacks(&config, &[String::from("kafka")])?
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await?
+            .build();
+
+        let mut service_names = unit
+            .services
+            .iter()
+            .map(|config| config.service_name().clone())
+            .collect::<Vec<_>>();
+        service_names.sort();
+        assert_eq!(service_names, vec!["kafka", "wordpress", "zookeeper"]);
+
+        let kafka = unit
+            .services
+            .iter()
+            .find(|config| config.service_name() == "kafka")
+            .unwrap();
+        assert_eq!(
+            kafka
+                .env()
+                .unwrap()
+                .variable("KAFKA_ADVERTISED_LISTENERS")
+                .map(|variable| variable.value().unsecure().to_string()),
+            Some(String::from("PLAINTEXT://master-kafka:9092"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_unknown_stacks() {
+        let config = Config::default();
+
+        let result = DeploymentUnitBuilder::init(
+            AppName::from_str("master").unwrap(),
+            vec![sc!("wordpress", "wordpress:latest")],
+        )
+        .extend_with_config(&config)
+        .extend_with_stacks(&config, &[String::from("kafka")]);
+
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::UnknownStack { stack }) if stack == "kafka"
+        ));
+    }
+
     #[tokio::test]
     async fn should_apply_templating_on_app_companions_with_templating_only_configs(
     ) -> Result<(), AppsServiceError> {
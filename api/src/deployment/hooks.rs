@@ -27,9 +27,9 @@ use crate::apps::AppsServiceError;
 use crate::config::Config;
 use crate::models::{AppName, ContainerType, Environment, EnvironmentVariable, Image};
 use boa_engine::property::Attribute;
-use boa_engine::{Context, JsValue, Source};
+use boa_engine::{Context, JsError, JsValue, Source};
 use secstr::SecUtf8;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::iter::IntoIterator;
 use std::path::{Path, PathBuf};
 
@@ -72,15 +72,24 @@ impl<'a> Hooks<'a> {
                     )
                     .expect("Property registration failed unexpectedly");
 
-                let transformed_configs = context
-                    .eval(Source::from_bytes(
-                        "deploymentHook(appName, serviceConfigs)",
-                    ))
-                    .unwrap();
+                let transformed_configs = match context.eval(Source::from_bytes(
+                    "deploymentHook(appName, serviceConfigs)",
+                )) {
+                    Ok(transformed_configs) => transformed_configs,
+                    Err(err) => {
+                        let reason = Self::rejection_reason(err, &mut context);
+                        info!("Deployment of {} rejected by hook: {}", app_name, reason);
+                        return Err(AppsServiceError::DeploymentRejectedByHook { reason });
+                    }
+                };
 
-                let transformed_configs = transformed_configs.to_json(&mut context).unwrap();
+                let transformed_configs =
+                    transformed_configs.to_json(&mut context).map_err(|err| {
+                        error!("Cannot convert result of deployment hook: {}", err);
+                        AppsServiceError::InvalidDeploymentHook
+                    })?;
 
-                Self::parse_service_config(services, transformed_configs)
+                Self::parse_service_config(app_name, services, transformed_configs)
             }
             None => Ok(services),
         }
@@ -112,13 +121,26 @@ impl<'a> Hooks<'a> {
         }
     }
 
+    /// The hook rejects a deployment by throwing an error or a string that describes the reason.
+    fn rejection_reason(err: JsError, context: &mut Context) -> String {
+        if let Some(reason) = err.as_opaque().and_then(JsValue::as_string) {
+            return reason.to_std_string_escaped();
+        }
+
+        match err.try_native(context) {
+            Ok(native_error) => native_error.message().to_string(),
+            Err(_) => err.to_string(),
+        }
+    }
+
     fn register_configs_as_global_property(
         mut context: &mut Context,
         services: &[DeployableService],
     ) {
         let js_configs = services
             .iter()
-            .map(JsServiceConfig::from)
+            .enumerate()
+            .map(|(id, service)| JsServiceConfig::from(service).with_id(id))
             .collect::<Vec<_>>();
 
         let js_configs = serde_json::to_value(js_configs).expect("Should be serializable");
@@ -130,39 +152,87 @@ impl<'a> Hooks<'a> {
             .expect("Property registration failed unexpectedly");
     }
 
+    /// Applies the configs returned by the hook to the services. The configs are matched to the
+    /// services by their `id`, which allows hooks to rename services, or by name, type, and image
+    /// if the hook created new objects. Services without a config are not deployed.
     fn parse_service_config<Iter>(
+        app_name: &AppName,
         services: Iter,
         transformed_configs: serde_json::value::Value,
     ) -> Result<Vec<DeployableService>, AppsServiceError>
     where
         Iter: IntoIterator<Item = DeployableService>,
     {
-        let mut transformed_configs =
+        let transformed_configs =
             serde_json::from_value::<Vec<JsServiceConfig>>(transformed_configs).map_err(|err| {
                 error!("Cannot parse result of deployment hook: {}", err);
                 AppsServiceError::InvalidDeploymentHook
             })?;
 
-        Ok(services
+        let mut services = services.into_iter().map(Some).collect::<Vec<_>>();
+        let mut transformed_services = services.iter().map(|_| None).collect::<Vec<_>>();
+        for transformed_config in transformed_configs {
+            let id = transformed_config.id.or_else(|| {
+                services.iter().position(|service| {
+                    service.as_ref().map_or(false, |service| {
+                        &transformed_config.name == service.service_name()
+                            && &transformed_config.r#type == service.container_type()
+                            && &transformed_config.image == service.image()
+                    })
+                })
+            });
+
+            let Some((id, service)) = id.and_then(|id| {
+                let service = services.get_mut(id)?.take()?;
+                Some((id, service))
+            }) else {
+                warn!(
+                    "Ignoring service {} returned by deployment hook: hooks cannot add services.",
+                    transformed_config.name
+                );
+                continue;
+            };
+
+            if &transformed_config.image != service.image()
+                || &transformed_config.r#type != service.container_type()
+            {
+                error!(
+                    "Deployment hook changed the image or type of service {}.",
+                    service.service_name()
+                );
+                return Err(AppsServiceError::InvalidDeploymentHook);
+            }
+
+            transformed_services[id] = Some(transformed_config.apply_to(app_name, service));
+        }
+
+        let transformed_services = transformed_services
             .into_iter()
-            .filter_map(move |service| {
-                let index = transformed_configs.iter().position(|transformed_config| {
-                    &transformed_config.name == service.service_name()
-                        && &transformed_config.r#type == service.container_type()
-                        && &transformed_config.image == service.image()
-                })?;
-
-                let transformed_config = transformed_configs.swap_remove(index);
-
-                Some(transformed_config.apply_to(service))
-            })
-            .collect::<Vec<_>>())
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut service_names = HashSet::new();
+        if let Some(service) = transformed_services
+            .iter()
+            .find(|service| !service_names.insert(service.service_name()))
+        {
+            error!(
+                "Deployment hook returned service {} more than once.",
+                service.service_name()
+            );
+            return Err(AppsServiceError::InvalidDeploymentHook);
+        }
+
+        Ok(transformed_services)
     }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct JsServiceConfig {
+    /// The position of the service in the configs that have been passed to the hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<usize>,
     name: String,
     image: Image,
     #[serde(default)]
@@ -173,7 +243,15 @@ struct JsServiceConfig {
 }
 
 impl JsServiceConfig {
-    fn apply_to(mut self, mut service: DeployableService) -> DeployableService {
+    fn with_id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    fn apply_to(mut self, app_name: &AppName, mut service: DeployableService) -> DeployableService {
+        if &self.name != service.service_name() {
+            service.rename(app_name, &self.name);
+        }
         service.set_files(Some(self.files));
 
         let env = match service.env().cloned() {
@@ -213,6 +291,7 @@ impl JsServiceConfig {
 impl From<&DeployableService> for JsServiceConfig {
     fn from(config: &DeployableService) -> Self {
         Self {
+            id: None,
             name: config.service_name().clone(),
             image: config.image().clone(),
             env: config
@@ -234,6 +313,7 @@ mod tests {
     use super::*;
     use crate::apps::*;
     use crate::deployment::deployment_unit::DeploymentUnitBuilder;
+    use crate::infrastructure::TraefikIngressRoute;
     use std::collections::HashMap;
     use std::io::Write;
     use std::str::FromStr;
@@ -520,8 +600,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn do_not_apply_deployment_hook_when_modifying_immutable_values() -> Result<(), AppsError>
-    {
+    async fn apply_deployment_hook_with_renamed_service() -> Result<(), AppsError> {
         let script = r#"
         function deploymentHook( appName, configs ) {
             return configs.map((config, index) => {
@@ -544,9 +623,47 @@ mod tests {
             .await?
             .build();
 
-        let deployed_services = unit.services();
+        let deployed_services = unit
+            .services()
+            .iter()
+            .map(|service| service.service_name().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(deployed_services, vec![String::from("service-a0")]);
+        assert_eq!(
+            unit.services()[0].ingress_route(),
+            &TraefikIngressRoute::with_defaults(&AppName::master(), "service-a0")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fail_with_hook_modifying_immutable_values() -> Result<(), AppsError> {
+        let script = r#"
+        function deploymentHook( appName, configs ) {
+            return configs.map((config, index) => {
+                config.image = 'hello-world';
+                return config;
+            });
+        }
+        "#;
+
+        let service_config = crate::sc!("service-a");
+        let (_temp_js_file, config) = config_with_deployment_hook(script);
+        let app_name = AppName::from_str("master").unwrap();
+
+        let result = DeploymentUnitBuilder::init(app_name, vec![service_config])
+            .extend_with_config(&config)
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()?
+            .apply_hooks(&config)
+            .await;
 
-        assert!(deployed_services.is_empty());
+        assert!(matches!(
+            result,
+            Err(AppsServiceError::InvalidDeploymentHook)
+        ));
 
         Ok(())
     }
@@ -584,4 +701,58 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn reject_deployment_with_hook_throwing_error() {
+        let script = r#"
+        function deploymentHook( appName, configs ) {
+            if (configs.some(config => config.image.startsWith('docker.io/library/mariadb'))) {
+                throw new Error('MariaDB is not allowed');
+            }
+            return configs;
+        }
+        "#;
+        let service_config = crate::sc!("db", "mariadb:10.3");
+        let (_temp_js_file, config) = config_with_deployment_hook(script);
+        let app_name = AppName::from_str("master").unwrap();
+
+        let result = DeploymentUnitBuilder::init(app_name, vec![service_config])
+            .extend_with_config(&config)
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()
+            .unwrap()
+            .apply_hooks(&config)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsError::DeploymentRejectedByHook { reason }) if reason == "MariaDB is not allowed"
+        ));
+    }
+
+    #[tokio::test]
+    async fn reject_deployment_with_hook_throwing_string() {
+        let script = r#"
+        function deploymentHook( appName, configs ) {
+            throw 'Deployments of ' + appName + ' are not allowed';
+        }
+        "#;
+        let (_temp_js_file, config) = config_with_deployment_hook(script);
+        let app_name = AppName::from_str("master").unwrap();
+
+        let result = DeploymentUnitBuilder::init(app_name, vec![crate::sc!("service-a")])
+            .extend_with_config(&config)
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()
+            .unwrap()
+            .apply_hooks(&config)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(AppsError::DeploymentRejectedByHook { reason }) if reason == "Deployments of master are not allowed"
+        ));
+    }
 }
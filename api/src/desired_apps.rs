@@ -0,0 +1,425 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::{Apps, AppsError, DeploymentOptions};
+use crate::config::{ConfigHandle, DesiredAppsConfig};
+use crate::models::{AppName, AppStatusChangeId, ResourceMetadata, ServiceConfig};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// The app metadata key that marks the apps that have been created from the desired apps file.
+/// Only these apps will be deleted if they are removed from the file.
+const MANAGED_BY_METADATA_KEY: &str = "managedBy";
+const MANAGED_BY_METADATA_VALUE: &str = "desired-apps";
+
+/// The content of the desired apps file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DesiredApps {
+    #[serde(default)]
+    apps: BTreeMap<AppName, DesiredApp>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DesiredApp {
+    #[serde(default)]
+    services: Vec<ServiceConfig>,
+    #[serde(default)]
+    replicate_from: Option<AppName>,
+    #[serde(default)]
+    stacks: Vec<String>,
+    #[serde(default)]
+    service_doubles: Vec<String>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    owner: Option<String>,
+    #[serde(default)]
+    resource_metadata: Option<ResourceMetadata>,
+}
+
+impl DesiredApp {
+    fn deployment_options(&self) -> DeploymentOptions {
+        let mut metadata = self.metadata.clone();
+        metadata.insert(
+            String::from(MANAGED_BY_METADATA_KEY),
+            String::from(MANAGED_BY_METADATA_VALUE),
+        );
+
+        DeploymentOptions::default()
+            .with_stacks(self.stacks.clone())
+            .with_service_doubles(self.service_doubles.clone())
+            .with_app_metadata(metadata)
+            .with_owner(self.owner.clone())
+            .with_resource_metadata(self.resource_metadata.clone())
+    }
+}
+
+impl DesiredApps {
+    fn read(file: &Path) -> Result<Self, DesiredAppsError> {
+        let content =
+            std::fs::read_to_string(file).map_err(|err| DesiredAppsError::Unreadable {
+                file: file.to_path_buf(),
+                err: err.to_string(),
+            })?;
+        serde_yaml::from_str(&content).map_err(|err| DesiredAppsError::Unreadable {
+            file: file.to_path_buf(),
+            err: err.to_string(),
+        })
+    }
+}
+
+/// Reconciles the apps of the desired apps file at startup, whenever the file changes, and in
+/// the configured interval. Only the leader among the replicas of PREvant changes the apps.
+///
+/// The returned watcher must be kept alive as long as the file should be watched.
+pub fn spawn_desired_apps_reconciler(
+    apps: Arc<Apps>,
+    config: &ConfigHandle,
+) -> Option<RecommendedWatcher> {
+    let desired_apps_config = config.current().desired_apps_config()?.clone();
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let watcher = match watch(desired_apps_config.file(), sender) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            warn!(
+                "Cannot watch {}, changes will be applied within {} seconds: {}",
+                desired_apps_config.file().display(),
+                desired_apps_config.interval().as_secs(),
+                err
+            );
+            None
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            reconcile_file(&apps, &desired_apps_config).await;
+
+            tokio::select! {
+                _ = receiver.recv() => {},
+                _ = tokio::time::sleep(desired_apps_config.interval()) => {},
+            }
+        }
+    });
+
+    watcher
+}
+
+/// Watches the parent directory of the file because Kubernetes config maps replace the file
+/// instead of modifying it.
+fn watch(
+    file: &Path,
+    sender: mpsc::UnboundedSender<()>,
+) -> Result<RecommendedWatcher, notify::Error> {
+    let directory = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let file = file.to_path_buf();
+    let mut last_content = std::fs::read_to_string(&file).ok();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_err() {
+            return;
+        }
+
+        let content = std::fs::read_to_string(&file).ok();
+        if content != last_content {
+            last_content = content;
+            let _ = sender.send(());
+        }
+    })?;
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
+
+async fn reconcile_file(apps: &Apps, config: &DesiredAppsConfig) {
+    if !apps.is_leader() {
+        return;
+    }
+
+    match DesiredApps::read(config.file()) {
+        Ok(desired_apps) => reconcile(apps, &desired_apps).await,
+        Err(err) => warn!("Ignoring the desired apps: {}", err),
+    }
+}
+
+/// Creates or updates the apps of `desired_apps` that differ from the running apps and deletes
+/// the apps that have been created from the desired apps but are not desired anymore. Desired
+/// apps that have been deleted softly are restored, undesired ones are left to their grace
+/// period. Failing apps are logged and don't affect the other apps.
+pub async fn reconcile(apps: &Apps, desired_apps: &DesiredApps) {
+    let running_apps = match apps.get_apps().await {
+        Ok(running_apps) => running_apps,
+        Err(err) => {
+            warn!("Cannot reconcile the desired apps: {}", err);
+            return;
+        }
+    };
+    let soft_deleted_apps = match apps.soft_deleted_apps().await {
+        Ok(soft_deleted_apps) => soft_deleted_apps,
+        Err(err) => {
+            warn!("Cannot reconcile the desired apps: {}", err);
+            return;
+        }
+    };
+
+    for (app_name, desired_app) in &desired_apps.apps {
+        if soft_deleted_apps.contains(app_name) {
+            info!("Restoring {} because it is still a desired app", app_name);
+            if let Err(err) = apps.restore_app(app_name).await {
+                warn!("Cannot restore the desired app {}: {}", app_name, err);
+                continue;
+            }
+        }
+
+        if let Err(err) = reconcile_app(
+            apps,
+            app_name,
+            desired_app,
+            running_apps.contains_key(app_name),
+        )
+        .await
+        {
+            warn!("Cannot reconcile the desired app {}: {}", app_name, err);
+        }
+    }
+
+    // Deleting a softly deleted app again would tear it down before its grace period expires.
+    for (app_name, services) in running_apps.iter_all() {
+        let is_managed = services.iter().any(|service| {
+            service
+                .app_metadata()
+                .get(MANAGED_BY_METADATA_KEY)
+                .map(String::as_str)
+                == Some(MANAGED_BY_METADATA_VALUE)
+        });
+        if !is_managed
+            || desired_apps.apps.contains_key(app_name)
+            || soft_deleted_apps.contains(app_name)
+        {
+            continue;
+        }
+
+        info!(
+            "Deleting {} because it has been removed from the desired apps",
+            app_name
+        );
+        if let Err(err) = apps.delete_app(app_name, &AppStatusChangeId::new()).await {
+            warn!("Cannot delete the undesired app {}: {}", app_name, err);
+        }
+    }
+}
+
+async fn reconcile_app(
+    apps: &Apps,
+    app_name: &AppName,
+    desired_app: &DesiredApp,
+    is_running: bool,
+) -> Result<(), AppsError> {
+    let options = desired_app.deployment_options();
+
+    if is_running {
+        let diff = apps
+            .dry_run(
+                app_name,
+                desired_app.replicate_from.clone(),
+                &desired_app.services,
+                &options,
+            )
+            .await?;
+        if diff.is_empty() {
+            return Ok(());
+        }
+    }
+
+    info!("Deploying the desired app {}", app_name);
+    apps.create_or_update_with_options(
+        app_name,
+        &AppStatusChangeId::new(),
+        desired_app.replicate_from.clone(),
+        &desired_app.services,
+        &options,
+    )
+    .await?;
+    Ok(())
+}
+
+#[derive(Debug, Fail)]
+enum DesiredAppsError {
+    #[fail(display = "Cannot read {:?}: {}", file, err)]
+    Unreadable { file: PathBuf, err: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::AppsService;
+    use crate::config::Config;
+    use crate::infrastructure::Dummy;
+    use crate::sc;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn should_create_and_delete_desired_apps() -> Result<(), AppsError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+        let feature = AppName::from_str("feature-1").unwrap();
+        apps.create_or_update(&feature, &AppStatusChangeId::new(), None, &vec![sc!("api")])
+            .await?;
+
+        let desired_apps = serde_yaml::from_str::<DesiredApps>(
+            r#"
+            apps:
+              master:
+                services:
+                  - serviceName: db
+                    image: postgres:16
+              demo:
+                replicateFrom: master
+            "#,
+        )
+        .unwrap();
+        reconcile(&apps, &desired_apps).await;
+
+        let running_apps = apps.get_apps().await?;
+        assert!(running_apps.contains_key(&AppName::master()));
+        assert!(running_apps.contains_key(&AppName::from_str("demo").unwrap()));
+
+        let desired_apps = serde_yaml::from_str::<DesiredApps>(
+            r#"
+            apps:
+              master:
+                services:
+                  - serviceName: db
+                    image: postgres:16
+            "#,
+        )
+        .unwrap();
+        reconcile(&apps, &desired_apps).await;
+
+        let running_apps = apps.get_apps().await?;
+        assert!(running_apps.contains_key(&AppName::master()));
+        assert!(!running_apps.contains_key(&AppName::from_str("demo").unwrap()));
+        assert!(running_apps.contains_key(&feature));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_keep_softly_deleted_apps_until_their_grace_period_expires(
+    ) -> Result<(), AppsError> {
+        let config = crate::config_from_str!(
+            r#"
+            [softDelete]
+            gracePeriod = 3600
+            "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+        let demo = AppName::from_str("demo").unwrap();
+
+        let desired_apps = serde_yaml::from_str::<DesiredApps>(
+            r#"
+            apps:
+              demo:
+                services:
+                  - serviceName: db
+                    image: postgres:16
+            "#,
+        )
+        .unwrap();
+        reconcile(&apps, &desired_apps).await;
+
+        let desired_apps = DesiredApps::default();
+        reconcile(&apps, &desired_apps).await;
+        reconcile(&apps, &desired_apps).await;
+
+        assert!(apps.get_apps().await?.contains_key(&demo));
+        assert_eq!(apps.soft_deleted_apps().await?, vec![demo]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_restore_softly_deleted_apps_that_are_still_desired() -> Result<(), AppsError> {
+        let config = crate::config_from_str!(
+            r#"
+            [softDelete]
+            gracePeriod = 3600
+            "#
+        );
+        let apps = AppsService::new(config, Box::new(Dummy::new()))?;
+        let demo = AppName::from_str("demo").unwrap();
+
+        let desired_apps = serde_yaml::from_str::<DesiredApps>(
+            r#"
+            apps:
+              demo:
+                services:
+                  - serviceName: db
+                    image: postgres:16
+            "#,
+        )
+        .unwrap();
+        reconcile(&apps, &desired_apps).await;
+        reconcile(&apps, &DesiredApps::default()).await;
+        assert_eq!(apps.soft_deleted_apps().await?, vec![demo.clone()]);
+
+        reconcile(&apps, &desired_apps).await;
+
+        assert!(apps.get_apps().await?.contains_key(&demo));
+        assert!(apps.soft_deleted_apps().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_redeploy_unchanged_desired_apps() -> Result<(), AppsError> {
+        let apps = AppsService::new(Config::default(), Box::new(Dummy::new()))?;
+
+        let desired_apps = serde_yaml::from_str::<DesiredApps>(
+            r#"
+            apps:
+              master:
+                services:
+                  - serviceName: db
+                    image: postgres:16
+            "#,
+        )
+        .unwrap();
+        reconcile(&apps, &desired_apps).await;
+        reconcile(&apps, &desired_apps).await;
+
+        assert_eq!(apps.deployment_history(&AppName::master()).await?.len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::Apps;
+use crate::config::{Config, ConfigHandle};
+use crate::models::{SelfCheck, SelfCheckStatus};
+use futures::future::join_all;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The registry that is used for images without registry host.
+const DOCKER_HUB_REGISTRY: &str = "registry-1.docker.io";
+
+/// The checklist of the runtime prerequisites of PREvant.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    healthy: bool,
+    checks: Vec<SelfCheck>,
+}
+
+impl DoctorReport {
+    fn new(checks: Vec<SelfCheck>) -> Self {
+        Self {
+            healthy: checks
+                .iter()
+                .all(|check| check.status() != SelfCheckStatus::Failed),
+            checks,
+        }
+    }
+}
+
+/// Verifies that PREvant is able to work with its runtime, e.g. that it is able to connect to
+/// the cluster and to the registries, and returns a checklist that helps to debug the setup.
+#[get("/health/doctor", format = "application/json")]
+pub async fn doctor(
+    config_state: &State<ConfigHandle>,
+    apps: &State<Arc<Apps>>,
+) -> Json<DoctorReport> {
+    let config = config_state.current();
+
+    let mut checks = apps.self_check().await;
+    checks.extend(check_registries(&config).await);
+
+    Json(DoctorReport::new(checks))
+}
+
+async fn check_registries(config: &Config) -> Vec<SelfCheck> {
+    let mut hosts = config.registry_hosts().cloned().collect::<Vec<_>>();
+    if hosts.is_empty() {
+        hosts.push(String::from(DOCKER_HUB_REGISTRY));
+    }
+
+    join_all(
+        hosts
+            .iter()
+            .map(|host| check_registry(config, host.as_str())),
+    )
+    .await
+}
+
+/// A registry is reachable if it responds to the [base
+/// endpoint](https://distribution.github.io/distribution/spec/api/#base) of the registry API,
+/// even if the response requires authentication.
+async fn check_registry(config: &Config, host: &str) -> SelfCheck {
+    let name = format!("registry {host}");
+
+    let client = match config
+        .network_config()
+        .http_client_builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => return SelfCheck::failed(name, err.to_string()),
+    };
+
+    match client.get(format!("https://{host}/v2/")).send().await {
+        Ok(response) if response.status().is_success() || response.status().as_u16() == 401 => {
+            SelfCheck::passed(name, "")
+        }
+        Ok(response) => SelfCheck::failed(
+            name,
+            format!("Unexpected response status {}", response.status()),
+        ),
+        Err(err) => SelfCheck::failed(name, err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_be_unhealthy_with_failed_checks() {
+        let report = DoctorReport::new(vec![
+            SelfCheck::passed("connectivity", ""),
+            SelfCheck::skipped("storage class", ""),
+        ]);
+        assert!(report.healthy);
+
+        let report = DoctorReport::new(vec![
+            SelfCheck::passed("connectivity", ""),
+            SelfCheck::failed("registry docker.io", "timeout"),
+        ]);
+        assert!(!report.healthy);
+    }
+}
@@ -0,0 +1,170 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::Apps;
+use crate::models::{SelfCheck, SelfCheckStatus};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Probes must answer quickly even if the infrastructure hangs.
+const INFRASTRUCTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum HealthStatus {
+    /// PREvant and its dependencies work as expected.
+    Up,
+    /// PREvant works but cannot reach the infrastructure, e.g. the Kubernetes API, or its state
+    /// store.
+    Degraded,
+    /// The in-memory state of PREvant is corrupt and PREvant needs to be restarted.
+    Down,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    status: HealthStatus,
+    checks: Vec<SelfCheck>,
+}
+
+/// Liveness probe that fails only if restarting PREvant helps, i.e. if its in-memory state is
+/// corrupt. An unreachable infrastructure is reported as degraded.
+#[get("/health/live", format = "application/json")]
+pub async fn live(apps: &State<Arc<Apps>>) -> (Status, Json<HealthReport>) {
+    let report = health_report(apps).await;
+    let status = match report.status {
+        HealthStatus::Down => Status::ServiceUnavailable,
+        HealthStatus::Up | HealthStatus::Degraded => Status::Ok,
+    };
+    (status, Json(report))
+}
+
+/// Readiness probe that fails as long as PREvant cannot serve requests, e.g. because the
+/// infrastructure is unreachable.
+#[get("/health/ready", format = "application/json")]
+pub async fn ready(apps: &State<Arc<Apps>>) -> (Status, Json<HealthReport>) {
+    let report = health_report(apps).await;
+    let status = match report.status {
+        HealthStatus::Up => Status::Ok,
+        HealthStatus::Degraded | HealthStatus::Down => Status::ServiceUnavailable,
+    };
+    (status, Json(report))
+}
+
+async fn health_report(apps: &Apps) -> HealthReport {
+    let infrastructure = match timeout(INFRASTRUCTURE_TIMEOUT, apps.get_apps()).await {
+        Ok(Ok(_)) => SelfCheck::passed("infrastructure", ""),
+        Ok(Err(err)) => SelfCheck::failed("infrastructure", err.to_string()),
+        Err(_) => SelfCheck::failed(
+            "infrastructure",
+            format!(
+                "No response within {} seconds",
+                INFRASTRUCTURE_TIMEOUT.as_secs()
+            ),
+        ),
+    };
+    let state_store = match timeout(INFRASTRUCTURE_TIMEOUT, apps.check_state_store()).await {
+        Ok(Ok(())) => SelfCheck::passed("state store", ""),
+        Ok(Err(err)) => SelfCheck::failed("state store", err.to_string()),
+        Err(_) => SelfCheck::failed(
+            "state store",
+            format!(
+                "No response within {} seconds",
+                INFRASTRUCTURE_TIMEOUT.as_secs()
+            ),
+        ),
+    };
+    let in_memory_state = if apps.is_state_consistent() {
+        SelfCheck::passed("in-memory state", "")
+    } else {
+        SelfCheck::failed(
+            "in-memory state",
+            "A panic left the in-memory state in an inconsistent state",
+        )
+    };
+
+    let status = if in_memory_state.status() == SelfCheckStatus::Failed {
+        HealthStatus::Down
+    } else if infrastructure.status() == SelfCheckStatus::Failed
+        || state_store.status() == SelfCheckStatus::Failed
+    {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Up
+    };
+
+    HealthReport {
+        status,
+        checks: vec![infrastructure, state_store, in_memory_state],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::AppsService;
+    use crate::config::{Config, InfrastructureOperation};
+    use crate::infrastructure::Dummy;
+    use rocket::local::asynchronous::Client;
+
+    async fn client(infrastructure: Dummy) -> Client {
+        let apps = Arc::new(AppsService::new(Config::default(), Box::new(infrastructure)).unwrap());
+        let rocket = rocket::build()
+            .manage(apps)
+            .mount("/api", routes![live, ready]);
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    #[tokio::test]
+    async fn should_be_live_and_ready() {
+        let client = client(Dummy::new()).await;
+
+        let response = client.get("/api/health/live").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/api/health/ready").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[tokio::test]
+    async fn should_be_live_but_not_ready_with_unreachable_infrastructure() {
+        let client = client(
+            Dummy::new().with_failing_operations(vec![InfrastructureOperation::GetServices]),
+        )
+        .await;
+
+        let response = client.get("/api/health/live").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_string().await.unwrap();
+        assert!(body.contains("\"status\":\"Degraded\""));
+
+        let response = client.get("/api/health/ready").dispatch().await;
+        assert_eq!(response.status(), Status::ServiceUnavailable);
+    }
+}
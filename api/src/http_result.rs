@@ -25,9 +25,12 @@
  */
 
 use http_api_problem::HttpApiProblem;
-use rocket::http::{hyper::header::CONTENT_TYPE, Header, Status};
+use rocket::http::{hyper::header::CONTENT_TYPE, ContentType, Header, Status};
 use rocket::request::Request;
 use rocket::response::{self, Responder, Response};
+use rocket::serde::json::Json;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::convert::From;
 use std::io::Cursor;
 
@@ -65,3 +68,111 @@ impl<'r> Responder<'r, 'static> for HttpApiError {
             .ok()
     }
 }
+
+/// Serializes the value as YAML if the client prefers `application/yaml` according to the
+/// `Accept` header and as JSON otherwise.
+pub struct JsonOrYaml<T>(pub T);
+
+impl<'r, T: Serialize> Responder<'r, 'static> for JsonOrYaml<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let prefers_yaml = request.accept().map_or(false, |accept| {
+            let preferred = accept.preferred().media_type();
+            preferred.top() == "application" && preferred.sub() == "yaml"
+        });
+
+        if !prefers_yaml {
+            return Json(self.0).respond_to(request);
+        }
+
+        let body = serde_yaml::to_string(&self.0).map_err(|err| {
+            error!("Cannot serialize response as YAML: {}", err);
+            Status::InternalServerError
+        })?;
+        Response::build()
+            .header(ContentType::new("application", "yaml"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+/// Serializes the value as JSON with an `ETag` derived from the JSON so that clients that poll
+/// the resource receive `304 Not Modified` without body as long as the resource does not change.
+///
+/// The `ETag` is weak because the response compression changes the bytes of the representation
+/// without changing the tag.
+pub struct ETaggedJson<T>(pub T);
+
+impl<'r, T: Serialize> Responder<'r, 'static> for ETaggedJson<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::to_vec(&self.0).map_err(|err| {
+            error!("Cannot serialize response as JSON: {}", err);
+            Status::InternalServerError
+        })?;
+        let opaque_tag = format!("\"{}\"", hex::encode(&Sha256::digest(&body)[..16]));
+        let etag = format!("W/{opaque_tag}");
+
+        // If-None-Match uses the weak comparison, i.e. only the opaque tags are compared.
+        let not_modified = request
+            .headers()
+            .get("If-None-Match")
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim())
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == opaque_tag);
+        if not_modified {
+            return Response::build()
+                .status(Status::NotModified)
+                .raw_header("ETag", etag)
+                .raw_header("Cache-Control", "no-cache")
+                .ok();
+        }
+
+        Response::build()
+            .header(ContentType::JSON)
+            .raw_header("ETag", etag)
+            .raw_header("Cache-Control", "no-cache")
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+    use rocket::local::asynchronous::Client;
+
+    #[get("/")]
+    fn apps() -> ETaggedJson<Vec<&'static str>> {
+        ETaggedJson(vec!["master", "feature-1"])
+    }
+
+    #[tokio::test]
+    async fn respond_with_not_modified_for_matching_etag() {
+        let rocket = rocket::build().mount("/", routes![apps]);
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        let response = client.get("/").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+
+        let response = client
+            .get("/")
+            .header(Header::new(
+                "If-None-Match",
+                format!("\"outdated\", {etag}"),
+            ))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotModified);
+        assert_eq!(response.headers().get_one("ETag"), Some(etag.as_str()));
+        assert!(response.into_bytes().await.unwrap_or_default().is_empty());
+
+        let response = client
+            .get("/")
+            .header(Header::new("If-None-Match", "\"outdated\""))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
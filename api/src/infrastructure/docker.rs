@@ -24,16 +24,18 @@
  * =========================LICENSE_END==================================
  */
 
-use crate::config::{Config, ContainerConfig};
+use crate::config::{Config, ContainerConfig, DockerTraefikConfig, RestartPolicy, Runtime};
 use crate::deployment::deployment_unit::{DeployableService, DeploymentStrategy};
 use crate::deployment::DeploymentUnit;
 use crate::infrastructure::{
-    Infrastructure, APP_NAME_LABEL, CONTAINER_TYPE_LABEL, IMAGE_LABEL, REPLICATED_ENV_LABEL,
-    SERVICE_NAME_LABEL, STATUS_ID,
+    Infrastructure, ALIASES_LABEL, APP_METADATA_PREFIX, APP_NAME_LABEL, CONTAINER_TYPE_LABEL,
+    DEPENDS_ON_LABEL, FIXED_PORT_LABEL, IMAGE_DIGEST_LABEL, IMAGE_LABEL, REPLICAS_LABEL,
+    REPLICATED_ENV_LABEL, REPLICATED_FROM_LABEL, SERVICE_NAME_LABEL, STATUS_ID,
 };
 use crate::models::service::{ContainerType, Service, ServiceError, ServiceStatus};
 use crate::models::{
-    AppName, Environment, Image, ServiceBuilder, ServiceBuilderError, ServiceConfig,
+    AppName, Environment, Image, ImagePullPolicy, LogFilter, LogStream, ScratchVolumeMedium,
+    ServiceBuilder, ServiceBuilderError, ServiceConfig, SmokeTestOutcome,
 };
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset};
@@ -42,19 +44,33 @@ use futures::future::join_all;
 use futures::{StreamExt, TryStreamExt};
 use multimap::MultiMap;
 use regex::Regex;
+use secstr::SecUtf8;
+use serde_value::Value;
 use shiplift::container::{ContainerCreateInfo, ContainerDetails, ContainerInfo};
 use shiplift::errors::Error as ShipLiftError;
 use shiplift::tty::TtyChunk;
 use shiplift::volume::VolumeInfo;
 use shiplift::{
     ContainerConnectionOptions, ContainerFilter, ContainerListOptions, ContainerOptions, Docker,
-    LogsOptions, NetworkCreateOptions, PullOptions, RegistryAuth, VolumeCreateOptions,
+    Exec, ExecContainerOptions, LogsOptions, NetworkCreateOptions, PullOptions, RegistryAuth,
+    VolumeCreateOptions,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::{From, TryFrom};
 use std::net::{AddrParseError, IpAddr};
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 static CONTAINER_PORT_LABEL: &str = "traefik.port";
+static STOP_TIMEOUT_LABEL: &str = "com.aixigo.preview.servant.stop-timeout";
+static SCRATCH_VOLUME_LABEL: &str = "com.aixigo.preview.servant.scratch-volume";
+static VOLUME_PATH_LABEL: &str = "com.aixigo.preview.servant.volume-path";
+static DEV_MODE_LABEL: &str = "com.aixigo.preview.servant.dev-mode";
+/// Marks the Traefik container managed by PREvant and stores its image.
+static MANAGED_TRAEFIK_LABEL: &str = "com.aixigo.preview.servant.traefik";
+static MANAGED_TRAEFIK_NAME: &str = "prevant-traefik";
+/// The network that Docker connects containers to if no network is given.
+static DEFAULT_BRIDGE_NETWORK: &str = "bridge";
 
 pub struct DockerInfrastructure {
     config: Config,
@@ -146,7 +162,7 @@ impl DockerInfrastructure {
     async fn create_or_get_network_id(&self, app_name: &String) -> Result<String, ShipLiftError> {
         trace!("Resolve network id for {}", app_name);
 
-        let network_name = format!("{}-net", app_name);
+        let network_name = network_name(app_name);
 
         let docker = Docker::new();
         let network_id = docker
@@ -176,19 +192,214 @@ impl DockerInfrastructure {
         Ok(network_create_info.id)
     }
 
-    async fn connect_traefik(&self, network_id: &String) -> Result<(), ShipLiftError> {
+    fn ipv6_network(&self) -> Option<&String> {
+        match self.config.runtime_config() {
+            Runtime::Docker(runtime) => runtime.ipv6_network(),
+            _ => None,
+        }
+    }
+
+    fn traefik_config(&self) -> Option<&DockerTraefikConfig> {
+        match self.config.runtime_config() {
+            Runtime::Docker(runtime) => runtime.traefik(),
+            _ => None,
+        }
+    }
+
+    fn files_directory(&self) -> Option<&PathBuf> {
+        match self.config.runtime_config() {
+            Runtime::Docker(runtime) => runtime.files_directory(),
+            _ => None,
+        }
+    }
+
+    /// Writes the files of the service into the files directory and returns the binds that
+    /// mount them read-only into the containers. The files are overwritten in place so that
+    /// running containers see updated content through their bind mounts.
+    async fn write_files(
+        &self,
+        app_name: &str,
+        service_name: &str,
+        files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<Vec<String>, std::io::Error> {
+        let directory = match self.files_directory() {
+            Some(directory) => directory,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut host_binds = Vec::with_capacity(files.len());
+        for (path, data) in files {
+            let host_path = host_file_path(directory, app_name, service_name, path)?;
+            if let Some(parent) = host_path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&host_path, data.unsecure()).await?;
+
+            host_binds.push(format!("{}:{}:ro", host_path.display(), path.display()));
+        }
+
+        Ok(host_binds)
+    }
+
+    async fn delete_files(&self, app_name: &str) -> Result<(), std::io::Error> {
+        let directory = match self.files_directory() {
+            Some(directory) => directory.join(app_name),
+            None => return Ok(()),
+        };
+
+        match tokio::fs::remove_dir_all(&directory).await {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Creates the managed Traefik container or replaces it if its image has changed. Traefik
+    /// publishes the only port of the host and routes the requests to the apps through their
+    /// networks.
+    async fn ensure_traefik(&self) -> Result<(), ShipLiftError> {
+        let Some(traefik) = self.traefik_config() else {
+            return Ok(());
+        };
+        let image = traefik.image().to_string();
+
         let docker = Docker::new();
+        let containers = docker.containers();
+        let existing_container = containers
+            .list(
+                &ContainerListOptions::builder()
+                    .all()
+                    .filter(vec![label_filter(MANAGED_TRAEFIK_LABEL, None)])
+                    .build(),
+            )
+            .await?
+            .into_iter()
+            .next();
 
-        let containers = docker
-            .containers()
-            .list(&ContainerListOptions::builder().build())
-            .await?;
-        let traefik_container_id = containers
+        // Concurrent deployments race for Traefik, thus the changes that another deployment has
+        // applied in the meantime are not treated as errors: 304 if the container has already
+        // been started, 404 if it has already been removed, and 409 if it has already been
+        // created.
+        let ignore_status = |result: Result<(), ShipLiftError>, status: u16| match result {
+            Err(ShipLiftError::Fault { code, .. }) if code.as_u16() == status => Ok(()),
+            result => result,
+        };
+
+        if let Some(container_info) = existing_container {
+            if container_info.labels.get(MANAGED_TRAEFIK_LABEL) == Some(&image) {
+                if container_info.state != "running" {
+                    ignore_status(containers.get(&container_info.id).start().await, 304)?;
+                }
+                return Ok(());
+            }
+
+            info!(
+                "Upgrading Traefik from {} to {}",
+                container_info.image, image
+            );
+            let container = containers.get(&container_info.id);
+            if container_info.state == "running" {
+                ignore_status(container.stop(None).await, 404)?;
+            }
+            ignore_status(container.delete().await, 404)?;
+        }
+
+        pull(traefik.image(), &self.config).await?;
+
+        let mut labels = HashMap::new();
+        labels.insert(MANAGED_TRAEFIK_LABEL, image.as_str());
+        // Traefik must not route to itself.
+        labels.insert("traefik.enable", "false");
+
+        let options = ContainerOptions::builder(&image)
+            .name(MANAGED_TRAEFIK_NAME)
+            .labels(&labels)
+            .cmd(vec!["--docker"])
+            .volumes(vec!["/var/run/docker.sock:/var/run/docker.sock"])
+            .expose(80, "tcp", traefik.http_port() as u32)
+            .restart_policy("always", 0)
+            .build();
+        let container_info = match containers.create(&options).await {
+            Ok(container_info) => container_info,
+            Err(ShipLiftError::Fault { code, .. }) if code.as_u16() == 409 => {
+                debug!("Traefik has been created by a concurrent deployment");
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        ignore_status(containers.get(&container_info.id).start().await, 304)?;
+
+        info!(
+            "Started Traefik {} on port {} of the host",
+            image,
+            traefik.http_port()
+        );
+
+        Ok(())
+    }
+
+    /// The managed Traefik container or any running container of a Traefik image.
+    async fn find_traefik_container_id(&self) -> Result<Option<String>, ShipLiftError> {
+        let docker = Docker::new();
+
+        let mut options = ContainerListOptions::builder();
+        if self.traefik_config().is_some() {
+            options.filter(vec![label_filter(MANAGED_TRAEFIK_LABEL, None)]);
+        }
+        let containers = docker.containers().list(&options.build()).await?;
+
+        Ok(containers
             .into_iter()
             .find(|c| c.image.contains("traefik"))
-            .map(|c| c.id);
+            .map(|c| c.id))
+    }
 
-        if let Some(id) = traefik_container_id {
+    /// The container PREvant is running in, if any. Docker uses the short container id as
+    /// hostname.
+    async fn find_own_container_id() -> Option<String> {
+        let hostname = std::env::var("HOSTNAME").ok()?;
+        let details = Docker::new()
+            .containers()
+            .get(&hostname)
+            .inspect()
+            .await
+            .ok()?;
+        Some(details.id)
+    }
+
+    /// Connects PREvant to the network of the app so that it can reach the services, e.g. to
+    /// run HTTP checks, even though they are not attached to the default bridge.
+    async fn connect_prevant(&self, network_id: &String) -> Result<(), ShipLiftError> {
+        if let Some(id) = DockerInfrastructure::find_own_container_id().await {
+            if let Err(e) = Docker::new()
+                .networks()
+                .get(network_id)
+                .connect(&ContainerConnectionOptions::builder(&id).build())
+                .await
+            {
+                debug!("Cannot connect PREvant: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn disconnect_prevant(&self, network_id: &String) -> Result<(), ShipLiftError> {
+        if let Some(id) = DockerInfrastructure::find_own_container_id().await {
+            if let Err(e) = Docker::new()
+                .networks()
+                .get(network_id)
+                .disconnect(&ContainerConnectionOptions::builder(&id).build())
+                .await
+            {
+                debug!("Cannot disconnect PREvant: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn connect_traefik(&self, network_id: &String) -> Result<(), ShipLiftError> {
+        let docker = Docker::new();
+
+        if let Some(id) = self.find_traefik_container_id().await? {
             if let Err(e) = docker
                 .networks()
                 .get(network_id)
@@ -205,16 +416,7 @@ impl DockerInfrastructure {
     async fn disconnect_traefik(&self, network_id: &String) -> Result<(), ShipLiftError> {
         let docker = Docker::new();
 
-        let containers = docker
-            .containers()
-            .list(&ContainerListOptions::builder().build())
-            .await?;
-        let traefik_container_id = containers
-            .into_iter()
-            .find(|c| c.image.contains("traefik"))
-            .map(|c| c.id);
-
-        if let Some(id) = traefik_container_id {
+        if let Some(id) = self.find_traefik_container_id().await? {
             docker
                 .networks()
                 .get(network_id)
@@ -226,7 +428,7 @@ impl DockerInfrastructure {
     }
 
     async fn delete_network(&self, app_name: &String) -> Result<(), ShipLiftError> {
-        let network_name = format!("{}-net", app_name);
+        let network_name = network_name(app_name);
 
         let docker = Docker::new();
         for n in docker
@@ -237,12 +439,47 @@ impl DockerInfrastructure {
             .filter(|n| n.name == network_name)
         {
             self.disconnect_traefik(&n.id).await?;
+            self.disconnect_prevant(&n.id).await?;
             docker.networks().get(&n.id).delete().await?;
         }
 
         Ok(())
     }
 
+    /// Moves a created container from Docker's default bridge into the network of its app before
+    /// it is started. Thus, containers of different apps cannot reach each other and are only
+    /// exposed through Traefik.
+    async fn attach_to_network(
+        container_id: &str,
+        network_id: &str,
+        aliases: Vec<&str>,
+    ) -> Result<(), ShipLiftError> {
+        let docker = Docker::new();
+        let networks = docker.networks();
+
+        networks
+            .get(network_id)
+            .connect(
+                &ContainerConnectionOptions::builder(container_id)
+                    .aliases(aliases)
+                    .build(),
+            )
+            .await?;
+        if let Err(err) = networks
+            .get(DEFAULT_BRIDGE_NETWORK)
+            .disconnect(&ContainerConnectionOptions::builder(container_id).build())
+            .await
+        {
+            debug!(
+                "Cannot disconnect {} from the default bridge: {}",
+                container_id, err
+            );
+        }
+
+        debug!("Connected container {:?} to {:?}", container_id, network_id);
+        Ok(())
+    }
+
     async fn delete_volume_mount(&self, app_name: &String) -> Result<(), ShipLiftError> {
         let docker = Docker::new();
         let docker_volumes = docker.volumes();
@@ -259,9 +496,59 @@ impl DockerInfrastructure {
     ) -> Result<Vec<Service>, Error> {
         let app_name = deployment_unit.app_name();
         let services = deployment_unit.services();
+
+        // shiplift cannot mount the root filesystem read-only. Hardened services are refused
+        // instead of being deployed with a writable root filesystem.
+        if let Some(service) = services
+            .iter()
+            .find(|service| service.read_only_root_filesystem())
+        {
+            bail!(
+                "Cannot deploy {} of app {} with a read-only root filesystem: the Docker backend does not support it.",
+                service.service_name(),
+                app_name
+            );
+        }
+
+        if self.config.egress_allowlist(app_name).is_some() {
+            // Restricting the egress would require host firewall rules that PREvant cannot manage
+            // through the Docker API.
+            warn!(
+                "Ignoring the egress allowlist of app {}: the Docker backend does not restrict outbound traffic.",
+                app_name
+            );
+        }
+
+        if let Some(kustomize) = self.config.kustomize_config() {
+            if !kustomize.overlays(app_name).is_empty() {
+                warn!(
+                    "Ignoring the kustomize overlays of app {}: the Docker backend does not use Kubernetes manifests.",
+                    app_name
+                );
+            }
+        }
+
+        for release in deployment_unit.helm_releases() {
+            warn!(
+                "Ignoring the Helm chart {} of app {}: the Docker backend cannot install Helm charts.",
+                release.chart(),
+                app_name
+            );
+        }
+
+        for resource in deployment_unit.custom_resources() {
+            warn!(
+                "Ignoring the custom resource {} of app {}: the Docker backend cannot apply custom resources.",
+                resource.name(),
+                app_name
+            );
+        }
+
+        self.ensure_traefik().await?;
         let network_id = self.create_or_get_network_id(app_name).await?;
 
         self.connect_traefik(&network_id).await?;
+        self.connect_prevant(&network_id).await?;
         let existing_volumes = DockerInfrastructure::fetch_existing_volumes(app_name).await?;
         let futures = services
             .iter()
@@ -272,6 +559,7 @@ impl DockerInfrastructure {
                     service,
                     container_config,
                     &existing_volumes,
+                    deployment_unit.app_metadata(),
                 )
             })
             .collect::<Vec<_>>();
@@ -315,6 +603,7 @@ impl DockerInfrastructure {
 
         self.delete_network(app_name).await?;
         self.delete_volume_mount(app_name).await?;
+        self.delete_files(app_name).await?;
 
         Ok(services)
     }
@@ -326,21 +615,30 @@ impl DockerInfrastructure {
         service: &DeployableService,
         container_config: &ContainerConfig,
         existing_volumes: &[VolumeInfo],
+        app_metadata: &BTreeMap<String, String>,
     ) -> Result<Service, Error> {
         let docker = Docker::new();
         let containers = docker.containers();
         let images = docker.images();
 
         if let Image::Named { .. } = service.image() {
-            self.pull_image(app_name, service).await?;
+            match container_config.image_pull_policy(service) {
+                ImagePullPolicy::Always => self.pull_image(app_name, service).await?,
+                ImagePullPolicy::IfNotPresent => {
+                    let image = service.image().to_string();
+                    if not_found_to_none(images.get(&image).inspect().await)?.is_none() {
+                        self.pull_image(app_name, service).await?;
+                    }
+                }
+                ImagePullPolicy::Never => {}
+            }
         }
         let mut image_to_delete = None;
-        if let Some(ref container_info) = self
-            .get_app_container(app_name, service.service_name())
-            .await?
-        {
-            let container = containers.get(&container_info.id);
-            let container_details = container.inspect().await?;
+        let existing_containers = self
+            .get_app_containers(Some(app_name), Some(service.service_name()))
+            .await?;
+        if let Some(container_info) = existing_containers.first() {
+            let container_details = containers.get(&container_info.id).inspect().await?;
 
             match service.strategy() {
                 DeploymentStrategy::RedeployOnImageUpdate(image_id)
@@ -359,15 +657,21 @@ impl DockerInfrastructure {
                 DeploymentStrategy::RedeployAlways
                 | DeploymentStrategy::RedeployOnImageUpdate(_) => {}
             };
+        }
 
+        for container_info in &existing_containers {
             info!(
                 "Removing container {:?} of review app {:?}",
                 container_info, app_name
             );
 
+            let container = containers.get(&container_info.id);
+            let container_details = container.inspect().await?;
             if container_details.state.running {
                 container
-                    .stop(Some(core::time::Duration::from_secs(10)))
+                    .stop(Some(
+                        stop_timeout(&container_details).unwrap_or(Duration::from_secs(10)),
+                    ))
                     .await?;
             }
             container.delete().await?;
@@ -382,39 +686,130 @@ impl DockerInfrastructure {
             service.container_type(),
         );
 
-        let host_config_binds =
+        if !service.extended_resources().is_empty() {
+            // shiplift cannot create containers with device requests (`docker run --gpus`)
+            warn!(
+                "Ignoring extended resources {:?} of service {} in app {}: the Docker backend does not support them.",
+                service.extended_resources(),
+                service.service_name(),
+                app_name
+            );
+        }
+
+        if let Some(dns_config) = service.dns_config() {
+            // shiplift cannot set the DNS options of the host config (`docker run --dns`)
+            warn!(
+                "Ignoring DNS config {:?} of service {} in app {}: the Docker backend does not support it.",
+                dns_config,
+                service.service_name(),
+                app_name
+            );
+        }
+
+        let mut host_config_binds =
             DockerInfrastructure::create_host_config_binds(app_name, existing_volumes, service)
                 .await?;
+        host_config_binds.extend(
+            DockerInfrastructure::create_scratch_volume_binds(app_name, existing_volumes, service)
+                .await?,
+        );
+        // The checks of the apps service ensure that only permitted directories are mounted.
+        if !service.dev_mounts().is_empty() {
+            warn!(
+                "Service {} of app {} runs in dev mode with the host directories {:?}.",
+                service.service_name(),
+                app_name,
+                service.dev_mounts()
+            );
+        }
+        for dev_mount in service.dev_mounts() {
+            host_config_binds.push(format!(
+                "{}:{}{}",
+                dev_mount.source().display(),
+                dev_mount.target().display(),
+                if dev_mount.read_only() { ":ro" } else { "" }
+            ));
+        }
+        if let Some(files) = service.files() {
+            host_config_binds.extend(
+                self.write_files(app_name, service.service_name(), files)
+                    .await?,
+            );
+        }
+
+        let mut additional_labels = app_metadata
+            .iter()
+            .map(|(key, value)| (format!("{}{}", APP_METADATA_PREFIX, key), value.clone()))
+            .collect::<BTreeMap<_, _>>();
+        if let Some(image_digest) = service.image_digest() {
+            additional_labels.insert(IMAGE_DIGEST_LABEL.to_string(), image_digest.clone());
+        }
+        if !service.dev_mounts().is_empty() {
+            additional_labels.insert(DEV_MODE_LABEL.to_string(), String::from("true"));
+        }
+        let ipv6_network = self.ipv6_network();
+        // Traefik must route through the IPv6 enabled network if the container is connected to
+        // it as well.
+        additional_labels.insert(
+            String::from("traefik.docker.network"),
+            ipv6_network
+                .cloned()
+                .unwrap_or_else(|| network_name(app_name)),
+        );
 
         let options = DockerInfrastructure::create_container_options(
             app_name,
             service,
             container_config,
             &host_config_binds,
+            &additional_labels,
         );
 
-        let container_info = containers.create(&options).await?;
-        debug!("Created container: {:?}", container_info);
+        // All replicas share the network aliases so that Docker's DNS balances the requests of
+        // other services, and the Traefik backend label so that Traefik balances the requests
+        // of the clients.
+        let network_aliases = std::iter::once(service.service_name())
+            .chain(service.aliases())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        // Like a deployment scaled to zero on Kubernetes, a service without replicas is listed as
+        // paused: its container is created but not started.
+        let replicas = service.replicas().unwrap_or(1);
+        let mut first_container_info = None;
+        for _ in 0..replicas.max(1) {
+            let container_info = containers.create(&options).await?;
+            debug!("Created container: {:?}", container_info);
+
+            self.copy_file_data(&container_info, service).await?;
+
+            DockerInfrastructure::attach_to_network(
+                &container_info.id,
+                network_id,
+                network_aliases.clone(),
+            )
+            .await?;
 
-        self.copy_file_data(&container_info, service).await?;
+            if replicas > 0 {
+                containers.get(&container_info.id).start().await?;
+                debug!("Started container: {:?}", container_info);
+            }
 
-        containers.get(&container_info.id).start().await?;
-        debug!("Started container: {:?}", container_info);
+            if let Some(ipv6_network) = ipv6_network {
+                docker
+                    .networks()
+                    .get(ipv6_network)
+                    .connect(&ContainerConnectionOptions::builder(&container_info.id).build())
+                    .await?;
+                debug!(
+                    "Connected container {:?} to {:?}",
+                    container_info.id, ipv6_network
+                );
+            }
 
-        docker
-            .networks()
-            .get(network_id)
-            .connect(
-                &ContainerConnectionOptions::builder(&container_info.id)
-                    .aliases(vec![service.service_name().as_str()])
-                    .build(),
-            )
-            .await?;
-        debug!(
-            "Connected container {:?} to {:?}",
-            container_info.id, network_id
-        );
+            first_container_info.get_or_insert(container_info);
+        }
 
+        let container_info = first_container_info.expect("At least one container has been created");
         let container_details = containers.get(&container_info.id).inspect().await?;
 
         if let Some(image) = image_to_delete {
@@ -436,6 +831,7 @@ impl DockerInfrastructure {
         service_config: &ServiceConfig,
         container_config: &ContainerConfig,
         host_config_binds: &[String],
+        additional_labels: &BTreeMap<String, String>,
     ) -> ContainerOptions {
         let mut options = ContainerOptions::builder(&service_config.image().to_string());
         if let Some(env) = service_config.env() {
@@ -447,6 +843,30 @@ impl DockerInfrastructure {
             options.env(variables.iter().map(|s| s.as_str()).collect::<Vec<&str>>());
         }
 
+        if let Some(command) = service_config.command() {
+            options.entrypoint(command.iter().map(String::as_str).collect());
+        }
+        if let Some(args) = service_config.args() {
+            options.cmd(args.iter().map(String::as_str).collect());
+        }
+        if let Some(working_dir) = service_config.working_dir() {
+            options.working_dir(working_dir);
+        }
+
+        let extra_hosts = service_config
+            .host_aliases()
+            .iter()
+            .flat_map(|alias| {
+                alias
+                    .hostnames()
+                    .iter()
+                    .map(move |hostname| format!("{}:{}", hostname, alias.ip()))
+            })
+            .collect::<Vec<String>>();
+        if !extra_hosts.is_empty() {
+            options.extra_hosts(extra_hosts.iter().map(String::as_str).collect());
+        }
+
         let mut labels: HashMap<&str, &str> = HashMap::new();
 
         let traefik_frontend = format!(
@@ -456,12 +876,68 @@ impl DockerInfrastructure {
         );
         labels.insert("traefik.frontend.rule", &traefik_frontend);
 
+        let replicas = service_config
+            .replicas()
+            .map(|replicas| replicas.to_string());
+        let traefik_backend = format!("{}-{}", app_name, service_config.service_name());
+        if let Some(replicas) = &replicas {
+            labels.insert(REPLICAS_LABEL, replicas);
+            labels.insert("traefik.backend", &traefik_backend);
+        }
+
+        if let Some(load_balancer) = service_config.load_balancer() {
+            labels.insert("traefik.backend", &traefik_backend);
+
+            if let Some(sticky) = load_balancer.sticky() {
+                labels.insert("traefik.backend.loadbalancer.stickiness", "true");
+                if let Some(cookie_name) = sticky.cookie().name() {
+                    labels.insert(
+                        "traefik.backend.loadbalancer.stickiness.cookieName",
+                        cookie_name,
+                    );
+                }
+            }
+            if let Some(response_forwarding) = load_balancer.response_forwarding() {
+                labels.insert(
+                    "traefik.backend.responseForwarding.flushInterval",
+                    response_forwarding.flush_interval(),
+                );
+            }
+            if load_balancer.servers_transport().is_some() {
+                warn!(
+                    "Servers transports are not supported by the Docker infrastructure and will be ignored for {}.",
+                    service_config.service_name()
+                );
+            }
+        }
+
+        let middleware_labels = service_config
+            .middlewares()
+            .map(|middlewares| {
+                traefik_middleware_labels(service_config.service_name(), middlewares)
+            })
+            .unwrap_or_default();
+        for (k, v) in &middleware_labels {
+            labels.insert(k, v);
+        }
+
         if let Some(config_labels) = service_config.labels() {
             for (k, v) in config_labels {
                 labels.insert(k, v);
             }
         }
 
+        // Docker does not distinguish between labels and annotations
+        if let Some(metadata) = service_config.metadata() {
+            for (k, v) in metadata.annotations().iter().chain(metadata.labels()) {
+                labels.insert(k, v);
+            }
+        }
+
+        for (key, value) in additional_labels {
+            labels.insert(key, value);
+        }
+
         labels.insert(APP_NAME_LABEL, app_name);
         labels.insert(SERVICE_NAME_LABEL, service_config.service_name());
         let container_type_name = service_config.container_type().to_string();
@@ -478,16 +954,55 @@ impl DockerInfrastructure {
             labels.insert(REPLICATED_ENV_LABEL, replicated_env);
         }
 
+        if let Some(replicated_from) = service_config.replicated_from() {
+            labels.insert(REPLICATED_FROM_LABEL, replicated_from);
+        }
+
+        let aliases = service_config.aliases().join(",");
+        if !aliases.is_empty() {
+            labels.insert(ALIASES_LABEL, &aliases);
+        }
+
+        let depends_on = service_config.depends_on().join(",");
+        if !depends_on.is_empty() {
+            labels.insert(DEPENDS_ON_LABEL, &depends_on);
+        }
+
+        let fixed_port = service_config
+            .has_fixed_port()
+            .then(|| service_config.port().to_string());
+        if let Some(fixed_port) = &fixed_port {
+            labels.insert(FIXED_PORT_LABEL, fixed_port);
+            // Traefik cannot choose between multiple exposed ports, e.g. of the mail catcher.
+            labels.insert(CONTAINER_PORT_LABEL, fixed_port);
+        }
+
+        let grace_period = service_config
+            .termination_grace_period()
+            .map(|grace_period| grace_period.as_secs().to_string());
+        if let Some(grace_period) = &grace_period {
+            labels.insert(STOP_TIMEOUT_LABEL, grace_period);
+        }
+
         if !host_config_binds.is_empty() {
             options.volumes(host_config_binds.iter().map(|bind| bind.as_str()).collect());
         }
         options.labels(&labels);
-        options.restart_policy("always", 5);
+        // Docker rejects a maximum retry count for other policies than on-failure.
+        let restart_policy = container_config.restart_policy();
+        let maximum_retry_count = match restart_policy {
+            RestartPolicy::OnFailure => 5,
+            _ => 0,
+        };
+        options.restart_policy(&restart_policy.to_string(), maximum_retry_count);
 
         if let Some(memory_limit) = container_config.memory_limit() {
             options.memory(memory_limit.as_u64());
             options.memory_swap(memory_limit.as_u64() as i64);
         }
+        if let Some(cpu_limit) = container_config.cpu_limit() {
+            options.cpus(cpu_limit);
+        }
 
         options.build()
     }
@@ -498,8 +1013,8 @@ impl DockerInfrastructure {
         service_config: &ServiceConfig,
     ) -> Result<(), ShipLiftError> {
         let files = match service_config.files() {
-            None => return Ok(()),
-            Some(files) => files.clone(),
+            Some(files) if self.files_directory().is_none() => files.clone(),
+            _ => return Ok(()),
         };
 
         debug!(
@@ -539,6 +1054,7 @@ impl DockerInfrastructure {
     async fn create_docker_volume(
         app_name: &str,
         service: &DeployableService,
+        path: &str,
     ) -> Result<String, ShipLiftError> {
         let docker = Docker::new();
         let volumes = docker.volumes();
@@ -546,6 +1062,7 @@ impl DockerInfrastructure {
         let mut labels: HashMap<&str, &str> = HashMap::new();
         labels.insert(APP_NAME_LABEL, app_name);
         labels.insert(SERVICE_NAME_LABEL, service.service_name());
+        labels.insert(VOLUME_PATH_LABEL, path);
 
         let volume_options = VolumeCreateOptions::builder().labels(&labels).build();
         volumes
@@ -565,28 +1082,101 @@ impl DockerInfrastructure {
             return Ok(host_binds);
         }
 
-        let service_volume = existing_volume
+        let service_volumes = existing_volume
+            .iter()
+            .filter(|vol| {
+                vol.labels.as_ref().map_or(false, |label| {
+                    label.get(SERVICE_NAME_LABEL) == Some(service.service_name())
+                        && !label.contains_key(SCRATCH_VOLUME_LABEL)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Apps deployed by previous versions of PREvant share a single volume across all
+        // declared paths of the service. It is kept for them so that no data gets lost.
+        let shared_volume = service_volumes
             .iter()
             .find(|vol| {
-                vol.labels.as_ref().map_or_else(
-                    || false,
-                    |label| label.get(SERVICE_NAME_LABEL) == Some(service.service_name()),
-                )
+                vol.labels
+                    .as_ref()
+                    .map_or(false, |label| !label.contains_key(VOLUME_PATH_LABEL))
             })
             .map(|info| &info.name);
 
-        let volume_name = match service_volume {
-            Some(name) => String::from(name),
-            None => DockerInfrastructure::create_docker_volume(app_name, service).await?,
-        };
-
         for declared_volume in service.declared_volumes() {
+            let path_volume = service_volumes
+                .iter()
+                .find(|vol| {
+                    vol.labels
+                        .as_ref()
+                        .and_then(|label| label.get(VOLUME_PATH_LABEL))
+                        == Some(declared_volume)
+                })
+                .map(|info| &info.name);
+
+            let volume_name = match path_volume.or(shared_volume) {
+                Some(name) => String::from(name),
+                None => {
+                    DockerInfrastructure::create_docker_volume(app_name, service, declared_volume)
+                        .await?
+                }
+            };
+
             host_binds.push(format!("{}:{}", volume_name, declared_volume));
         }
 
         Ok(host_binds)
     }
 
+    /// Docker volumes stand in for the scratch volumes because shiplift cannot mount tmpfs. The
+    /// volumes of the previous container of the service are replaced by empty ones.
+    async fn create_scratch_volume_binds(
+        app_name: &str,
+        existing_volumes: &[VolumeInfo],
+        service: &DeployableService,
+    ) -> Result<Vec<String>, ShipLiftError> {
+        let docker = Docker::new();
+        let volumes = docker.volumes();
+
+        let previous_scratch_volumes = existing_volumes.iter().filter(|volume| {
+            volume.labels.as_ref().map_or(false, |labels| {
+                labels.get(SERVICE_NAME_LABEL) == Some(service.service_name())
+                    && labels.contains_key(SCRATCH_VOLUME_LABEL)
+            })
+        });
+        for volume in previous_scratch_volumes {
+            volumes.get(&volume.name).delete().await?;
+        }
+
+        let mut host_binds = Vec::new();
+        for scratch_volume in &service.ephemeral_volumes() {
+            if scratch_volume.medium() == ScratchVolumeMedium::Memory
+                || scratch_volume.size_limit().is_some()
+            {
+                warn!(
+                    "The scratch volume {} of service {} in app {} is stored on disk without size limit: the Docker backend does not support tmpfs.",
+                    scratch_volume.path().display(),
+                    service.service_name(),
+                    app_name
+                );
+            }
+
+            let path = scratch_volume.path().to_string_lossy().to_string();
+            let mut labels: HashMap<&str, &str> = HashMap::new();
+            labels.insert(APP_NAME_LABEL, app_name);
+            labels.insert(SERVICE_NAME_LABEL, service.service_name());
+            labels.insert(SCRATCH_VOLUME_LABEL, &path);
+
+            let volume_name = volumes
+                .create(&VolumeCreateOptions::builder().labels(&labels).build())
+                .await?
+                .name;
+            host_binds.push(format!("{}:{}", volume_name, path));
+        }
+
+        Ok(host_binds)
+    }
+
     async fn pull_image(
         &self,
         app_name: &String,
@@ -697,16 +1287,36 @@ impl Infrastructure for DockerInfrastructure {
         let container_details = self.get_container_details(None, None).await?;
 
         for (app_name, details_vec) in container_details.iter_all() {
-            for details in details_vec {
-                let service = match Service::try_from(details) {
-                    Ok(service) => service,
+            let mut services = details_vec
+                .iter()
+                .filter_map(|details| match Service::try_from(details) {
+                    Ok(service) => Some(service),
                     Err(e) => {
                         debug!("Container does not provide required data: {:?}", e);
-                        continue;
+                        None
                     }
-                };
+                })
+                .collect::<Vec<_>>();
+
+            // Containers that have not been redeployed still carry the metadata of a previous
+            // deployment. Thus, the metadata of the newer containers takes precedence.
+            services.sort_by_key(|service| *service.started_at());
+            let mut app_metadata = BTreeMap::new();
+            for service in &services {
+                app_metadata.extend(service.app_metadata().clone());
+            }
+
+            // Replicas of a service run in multiple containers but represent a single service.
+            let mut service_names = HashSet::new();
+            services.retain(|service| service_names.insert(service.service_name().clone()));
 
-                apps.insert(app_name.clone(), service);
+            for service in services {
+                apps.insert(
+                    app_name.clone(),
+                    ServiceBuilder::from(service)
+                        .app_metadata(app_metadata.clone())
+                        .build()?,
+                );
             }
         }
 
@@ -783,7 +1393,27 @@ impl Infrastructure for DockerInfrastructure {
         service_name: &str,
         from: &Option<DateTime<FixedOffset>>,
         limit: usize,
+        filter: &LogFilter,
     ) -> Result<Option<Vec<(DateTime<FixedOffset>, String)>>, failure::Error> {
+        Ok(self
+            .get_logs_with_streams(app_name, service_name, from, limit, filter)
+            .await?
+            .map(|logs| {
+                logs.into_iter()
+                    .map(|(timestamp, _, line)| (timestamp, line))
+                    .collect()
+            }))
+    }
+
+    async fn get_logs_with_streams(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        from: &Option<DateTime<FixedOffset>>,
+        limit: usize,
+        filter: &LogFilter,
+    ) -> Result<Option<Vec<(DateTime<FixedOffset>, Option<LogStream>, String)>>, failure::Error>
+    {
         match self.get_app_container(app_name, service_name).await? {
             None => Ok(None),
             Some(container) => {
@@ -817,12 +1447,12 @@ impl Infrastructure for DockerInfrastructure {
                     .await;
 
                 let logs = logs.into_iter()
-                    .enumerate()
-                    // Unfortunately, docker API does not support head (cf. https://github.com/moby/moby/issues/13096)
-                    // Until then we have to skip these log messages which is super slow…
-                    .filter(move |(index, _)| index < &limit)
-                    .filter_map(|(_, chunk)| chunk.ok())
+                    .filter_map(|chunk| chunk.ok())
                     .map(|chunk| {
+                        let stream = match chunk {
+                            TtyChunk::StdErr(_) => LogStream::StdErr,
+                            _ => LogStream::StdOut,
+                        };
                         let line = String::from_utf8_lossy(&chunk.to_vec()).to_string();
 
                         let mut iter = line.splitn(2, ' ');
@@ -834,13 +1464,17 @@ impl Infrastructure for DockerInfrastructure {
                         let log_line : String = iter
                             .collect::<Vec<&str>>()
                             .join(" ");
-                        (datetime, log_line)
+                        (datetime, Some(stream), log_line)
                     })
-                    .filter(move |(timestamp, _)| {
+                    .filter(move |(timestamp, _, _)| {
                         // Due to the fact that docker's REST API supports only unix time (cf. since),
                         // it is necessary to filter the timestamps as well.
                         from.map(|from| timestamp >= &from).unwrap_or(true)
                     })
+                    .filter(|(_, _, line)| filter.matches(line))
+                    // Unfortunately, docker API does not support head (cf. https://github.com/moby/moby/issues/13096)
+                    // Until then we have to skip these log messages which is super slow…
+                    .take(limit)
                     .collect();
 
                 Ok(Some(logs))
@@ -854,54 +1488,270 @@ impl Infrastructure for DockerInfrastructure {
         service_name: &str,
         status: ServiceStatus,
     ) -> Result<Option<Service>, failure::Error> {
-        match self.get_app_container(app_name, service_name).await? {
-            Some(container) => {
-                let docker = Docker::new();
-                let containers = docker.containers();
-                let c = containers.get(&container.id);
-
-                let details = c.inspect().await?;
-
-                macro_rules! run_future_and_map_err {
-                    ( $future:expr, $log_format:expr ) => {
-                        if let Err(err) = $future.await {
-                            match err {
-                                ShipLiftError::Fault { code, message } if code.as_u16() == 304 => {
-                                    trace!(
-                                        "Container {} already in desired state: {}",
-                                        details.id,
-                                        message
-                                    );
-                                }
-                                err => {
-                                    error!($log_format, err);
-                                    return Err(failure::Error::from(err));
-                                }
-                            };
-                        }
-                    };
+        let containers_of_service = self
+            .get_app_containers(Some(app_name), Some(service_name))
+            .await?;
+        if containers_of_service.is_empty() {
+            return Ok(None);
+        }
+
+        let docker = Docker::new();
+        let containers = docker.containers();
+
+        // All replicas of the service have to change their status.
+        let mut service = None;
+        for container in containers_of_service {
+            let c = containers.get(&container.id);
+
+            let details = c.inspect().await?;
+
+            macro_rules! run_future_and_map_err {
+                ( $future:expr, $log_format:expr ) => {
+                    if let Err(err) = $future.await {
+                        match err {
+                            ShipLiftError::Fault { code, message } if code.as_u16() == 304 => {
+                                trace!(
+                                    "Container {} already in desired state: {}",
+                                    details.id,
+                                    message
+                                );
+                            }
+                            err => {
+                                error!($log_format, err);
+                                return Err(failure::Error::from(err));
+                            }
+                        };
+                    }
+                };
+            }
+
+            match status {
+                ServiceStatus::Running => {
+                    if !details.state.running {
+                        run_future_and_map_err!(c.start(), "Could not start container: {}");
+                    }
+                }
+                ServiceStatus::Paused => {
+                    if details.state.running {
+                        run_future_and_map_err!(
+                            c.stop(stop_timeout(&details)),
+                            "Could not pause container: {}"
+                        );
+                    }
+                }
+            }
+
+            if service.is_none() {
+                service = Some(Service::try_from(&details)?);
+            }
+        }
+
+        Ok(service)
+    }
+
+    async fn update_files(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<Option<Service>, failure::Error> {
+        let containers_of_service = self
+            .get_app_containers(Some(app_name), Some(service_name))
+            .await?;
+        if containers_of_service.is_empty() {
+            return Ok(None);
+        }
+
+        let docker = Docker::new();
+        let containers = docker.containers();
+
+        self.write_files(app_name, service_name, files).await?;
+
+        let mut service = None;
+        for container in containers_of_service {
+            let c = containers.get(&container.id);
+
+            debug!(
+                "Update files {:?} in container {} (service = {})",
+                files.keys().collect::<Vec<_>>(),
+                container.id,
+                service_name
+            );
+            // Files that are bind-mounted from the files directory are already up to date, all
+            // others are copied into the container.
+            let details = c.inspect().await?;
+            for (path, data) in files {
+                let is_mounted = details
+                    .mounts
+                    .iter()
+                    .any(|mount| Path::new(&mount.destination) == path);
+                if !is_mounted {
+                    c.copy_file_into(path, data.unsecure().as_bytes()).await?;
                 }
+            }
+
+            if service.is_none() {
+                service = Some(Service::try_from(&details)?);
+            }
+        }
+
+        Ok(service)
+    }
+
+    async fn run_smoke_test(
+        &self,
+        app_name: &AppName,
+        image: &Image,
+        command: &[String],
+        timeout: Duration,
+    ) -> Result<SmokeTestOutcome, failure::Error> {
+        let docker = Docker::new();
+        let containers = docker.containers();
+
+        if let Image::Named { .. } = image {
+            for pull_result in pull(image, &self.config).await? {
+                debug!("{:?}", pull_result);
+            }
+        }
+
+        let network_id = self.create_or_get_network_id(app_name).await?;
+
+        let image_name = image.to_string();
+        let mut options = ContainerOptions::builder(&image_name);
+        if !command.is_empty() {
+            options.cmd(command.iter().map(String::as_str).collect());
+        }
+
+        info!("Running smoke test {} of app {}", image, app_name);
+
+        let container_info = containers.create(&options.build()).await?;
+        let container = containers.get(&container_info.id);
+
+        DockerInfrastructure::attach_to_network(&container_info.id, &network_id, Vec::new())
+            .await?;
+        container.start().await?;
+
+        let passed = match tokio::time::timeout(timeout, container.wait()).await {
+            Ok(exit) => exit.map(|exit| exit.status_code == 0),
+            Err(_) => {
+                warn!("Smoke test {} of app {} timed out", image, app_name);
+                container.stop(None).await.map(|_| false)
+            }
+        };
+
+        let logs = container
+            .logs(&LogsOptions::builder().stdout(true).stderr(true).build())
+            .filter_map(|chunk| async move { chunk.ok() })
+            .map(|chunk| String::from_utf8_lossy(&chunk.to_vec()).to_string())
+            .collect::<Vec<String>>()
+            .await
+            .join("");
+
+        // The containers of smoke tests and seed jobs are removed even if waiting for them failed.
+        container.delete().await?;
+
+        Ok(SmokeTestOutcome::new(passed?, logs))
+    }
+
+    async fn exec_in_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        command: &[String],
+        timeout: Duration,
+    ) -> Result<SmokeTestOutcome, failure::Error> {
+        let container = self
+            .get_app_container(app_name, service_name)
+            .await?
+            .ok_or_else(|| {
+                format_err!(
+                    "Service {} of app {} is not running",
+                    service_name,
+                    app_name
+                )
+            })?;
+
+        info!(
+            "Executing {:?} in {} of app {}",
+            command, service_name, app_name
+        );
+        exec_in_container(&container.id, command, timeout).await
+    }
 
-                match status {
-                    ServiceStatus::Running => {
-                        if !details.state.running {
-                            run_future_and_map_err!(c.start(), "Could not start container: {}");
-                        }
-                    }
-                    ServiceStatus::Paused => {
-                        if details.state.running {
-                            run_future_and_map_err!(c.stop(None), "Could not pause container: {}");
-                        }
-                    }
-                }
+    /// The files are written into the containers right away, thus, the containers are signaled
+    /// without waiting.
+    async fn signal_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        signal: &str,
+        _files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<SmokeTestOutcome, failure::Error> {
+        let containers = self
+            .get_app_containers(Some(app_name), Some(service_name))
+            .await?;
+        if containers.is_empty() {
+            bail!(
+                "Service {} of app {} is not running",
+                service_name,
+                app_name
+            );
+        }
 
-                Ok(Some(Service::try_from(&details)?))
+        let command = vec![
+            String::from("kill"),
+            String::from("-s"),
+            signal.to_string(),
+            String::from("1"),
+        ];
+        let mut logs = String::new();
+        for container in containers {
+            let outcome =
+                exec_in_container(&container.id, &command, Duration::from_secs(10)).await?;
+            logs.push_str(outcome.logs());
+            if !outcome.passed() {
+                return Ok(SmokeTestOutcome::new(false, logs));
             }
-            None => Ok(None),
         }
+        Ok(SmokeTestOutcome::new(true, logs))
     }
 }
 
+/// Executes the `command` in the container and returns whether the command terminated
+/// successfully within the `timeout`, together with its output.
+async fn exec_in_container(
+    container_id: &str,
+    command: &[String],
+    timeout: Duration,
+) -> Result<SmokeTestOutcome, failure::Error> {
+    let docker = Docker::new();
+    let options = ExecContainerOptions::builder()
+        .cmd(command.iter().map(String::as_str).collect())
+        .attach_stdout(true)
+        .attach_stderr(true)
+        .build();
+    let exec = Exec::create(&docker, container_id, &options).await?;
+
+    let output = exec
+        .start()
+        .filter_map(|chunk| async move { chunk.ok() })
+        .map(|chunk| String::from_utf8_lossy(&chunk.to_vec()).to_string())
+        .collect::<Vec<String>>();
+    let Ok(output) = tokio::time::timeout(timeout, output).await else {
+        warn!(
+            "Executing {:?} in container {} timed out",
+            command, container_id
+        );
+        return Ok(SmokeTestOutcome::new(
+            false,
+            format!("{:?} timed out after {:?}", command, timeout),
+        ));
+    };
+
+    let passed = exec.inspect().await?.exit_code == Some(0);
+    Ok(SmokeTestOutcome::new(passed, output.join("")))
+}
+
 /// Helper function to build ContainerFilters
 fn label_filter<S>(label_name: S, label_value: Option<S>) -> ContainerFilter
 where
@@ -914,6 +1764,165 @@ where
     }
 }
 
+fn network_name(app_name: &str) -> String {
+    format!("{}-net", app_name)
+}
+
+/// The path of a service's file within the files directory. The path of the file must not
+/// escape the directory of the service.
+fn host_file_path(
+    directory: &Path,
+    app_name: &str,
+    service_name: &str,
+    path: &Path,
+) -> Result<PathBuf, std::io::Error> {
+    let mut host_path = directory.join(app_name).join(service_name);
+    for component in path.components() {
+        match component {
+            Component::Normal(segment) => host_path.push(segment),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid file path {}", path.display()),
+                ))
+            }
+        }
+    }
+    Ok(host_path)
+}
+
+/// Translates the middlewares of a service into the frontend labels of Traefik 1.7 which only
+/// supports a subset of the middlewares that Traefik 2 provides on Kubernetes.
+fn traefik_middleware_labels(
+    service_name: &str,
+    middlewares: &BTreeMap<String, Value>,
+) -> BTreeMap<String, String> {
+    fn field<'a>(config: &'a Value, key: &str) -> Option<&'a Value> {
+        match config {
+            Value::Map(map) => map.get(&Value::String(key.to_string())),
+            _ => None,
+        }
+    }
+
+    fn scalar(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Bool(b) => Some(b.to_string()),
+            Value::I64(i) => Some(i.to_string()),
+            Value::U64(u) => Some(u.to_string()),
+            _ => None,
+        }
+    }
+
+    fn joined(value: &Value) -> Option<String> {
+        match value {
+            Value::Seq(values) => Some(
+                values
+                    .iter()
+                    .filter_map(scalar)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            value => scalar(value),
+        }
+    }
+
+    fn headers(value: &Value) -> Option<String> {
+        match value {
+            Value::Map(map) => Some(
+                map.iter()
+                    .filter_map(|(k, v)| Some(format!("{}:{}", scalar(k)?, scalar(v)?)))
+                    .collect::<Vec<_>>()
+                    .join("||"),
+            ),
+            _ => None,
+        }
+    }
+
+    let mut labels = BTreeMap::new();
+    let mut insert = |label: &str, value: Option<String>| {
+        if let Some(value) = value {
+            labels.insert(label.to_string(), value);
+        }
+    };
+    for (kind, config) in middlewares {
+        match kind.as_str() {
+            "headers" => {
+                insert(
+                    "traefik.frontend.headers.customRequestHeaders",
+                    field(config, "customRequestHeaders").and_then(headers),
+                );
+                insert(
+                    "traefik.frontend.headers.customResponseHeaders",
+                    field(config, "customResponseHeaders").and_then(headers),
+                );
+            }
+            "basicAuth" => {
+                insert(
+                    "traefik.frontend.auth.basic.users",
+                    field(config, "users").and_then(joined),
+                );
+            }
+            "forwardAuth" => {
+                insert(
+                    "traefik.frontend.auth.forward.address",
+                    field(config, "address").and_then(scalar),
+                );
+                insert(
+                    "traefik.frontend.auth.forward.trustForwardHeader",
+                    field(config, "trustForwardHeader").and_then(scalar),
+                );
+                insert(
+                    "traefik.frontend.auth.forward.authResponseHeaders",
+                    field(config, "authResponseHeaders").and_then(joined),
+                );
+            }
+            "ipWhiteList" | "ipAllowList" => {
+                insert(
+                    "traefik.frontend.whiteList.sourceRange",
+                    field(config, "sourceRange").and_then(joined),
+                );
+            }
+            "redirectRegex" => {
+                insert(
+                    "traefik.frontend.redirect.regex",
+                    field(config, "regex").and_then(scalar),
+                );
+                insert(
+                    "traefik.frontend.redirect.replacement",
+                    field(config, "replacement").and_then(scalar),
+                );
+                insert(
+                    "traefik.frontend.redirect.permanent",
+                    field(config, "permanent").and_then(scalar),
+                );
+            }
+            _ => warn!(
+                "The middleware {} is not supported by the Docker infrastructure and will be ignored for {}.",
+                kind, service_name
+            ),
+        }
+    }
+    labels
+}
+
+/// The address of the container within the network of its app. Containers that have been
+/// deployed before they were moved out of the default bridge use the address of the bridge.
+fn container_ip_address<'a>(
+    container_details: &'a ContainerDetails,
+    app_name: &str,
+) -> Option<&'a str> {
+    let network_settings = &container_details.network_settings;
+    network_settings
+        .networks
+        .get(&network_name(app_name))
+        .map(|network| network.ip_address.as_str())
+        .into_iter()
+        .chain(std::iter::once(network_settings.ip_address.as_str()))
+        .find(|ip_address| !ip_address.is_empty())
+}
+
 /// Helper function to map ShipLift 404 errors to None
 fn not_found_to_none<T>(result: Result<T, ShipLiftError>) -> Result<Option<T>, ShipLiftError> {
     match result {
@@ -952,10 +1961,25 @@ async fn pull(image: &Image, config: &Config) -> Result<Vec<serde_json::Value>,
 async fn stop(details: ContainerDetails) -> Result<ContainerDetails, ShipLiftError> {
     let docker = Docker::new();
     let containers = docker.containers();
-    containers.get(&details.id).stop(None).await?;
+    containers
+        .get(&details.id)
+        .stop(stop_timeout(&details))
+        .await?;
     Ok(details)
 }
 
+/// The termination grace period of the container's service, if configured, after which Docker
+/// kills the container.
+fn stop_timeout(details: &ContainerDetails) -> Option<Duration> {
+    details
+        .config
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.get(STOP_TIMEOUT_LABEL))
+        .and_then(|stop_timeout| stop_timeout.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
 /// Helper function to delete containers with the aid of futures::future::join_all
 async fn delete(details: ContainerDetails) -> Result<ContainerDetails, ShipLiftError> {
     let docker = Docker::new();
@@ -1026,15 +2050,50 @@ impl TryFrom<&ContainerDetails> for Service {
             ServiceStatus::Paused
         };
 
+        let app_metadata = labels
+            .iter()
+            .flat_map(|labels| labels.iter())
+            .filter_map(|(key, value)| {
+                key.strip_prefix(APP_METADATA_PREFIX)
+                    .map(|key| (key.to_string(), value.clone()))
+            })
+            .collect::<BTreeMap<_, _>>();
+
         let mut builder = ServiceBuilder::new()
             .id(container_details.id.clone())
             .app_name(app_name.clone())
             .config(ServiceConfig::try_from(container_details)?)
             .service_status(status)
-            .started_at(started_at);
+            .started_at(started_at)
+            .app_metadata(app_metadata);
+
+        if labels.map_or(false, |labels| labels.contains_key(DEV_MODE_LABEL)) {
+            builder = builder.dev_mode(true);
+        }
+
+        if let Some(image_digest) = labels.and_then(|labels| labels.get(IMAGE_DIGEST_LABEL)) {
+            builder = builder.image_digest(image_digest.clone());
+        }
 
-        if !container_details.network_settings.ip_address.is_empty() {
-            let addr = IpAddr::from_str(&container_details.network_settings.ip_address)?;
+        let state = &container_details.state;
+        builder = builder.restart_count(container_details.restart_count as u32);
+        if container_details.restart_count > 0
+            || state.restarting
+            || state.oom_killed
+            || state.exit_code != 0
+        {
+            builder =
+                builder
+                    .last_exit_code(state.exit_code as i64)
+                    .last_state(if state.oom_killed {
+                        String::from("OOMKilled")
+                    } else {
+                        state.status.clone()
+                    });
+        }
+
+        if let Some(ip_address) = container_ip_address(container_details, app_name) {
+            let addr = IpAddr::from_str(ip_address)?;
             let port = find_port(container_details, labels)?;
             builder = builder.endpoint(addr, port);
         }
@@ -1087,6 +2146,33 @@ impl TryFrom<&ContainerDetails> for ServiceConfig {
             config.set_env(Some(env));
         }
 
+        config.set_replicated_from(
+            labels
+                .and_then(|labels| labels.get(REPLICATED_FROM_LABEL))
+                .cloned(),
+        );
+
+        if let Some(aliases) = labels.and_then(|labels| labels.get(ALIASES_LABEL)) {
+            config.set_aliases(aliases.split(',').map(String::from).collect());
+        }
+
+        if let Some(depends_on) = labels.and_then(|labels| labels.get(DEPENDS_ON_LABEL)) {
+            config.set_depends_on(depends_on.split(',').map(String::from).collect());
+        }
+
+        if let Some(port) = labels
+            .and_then(|labels| labels.get(FIXED_PORT_LABEL))
+            .and_then(|port| port.parse::<u16>().ok())
+        {
+            config.set_fixed_port(port);
+        }
+
+        config.set_replicas(
+            labels
+                .and_then(|labels| labels.get(REPLICAS_LABEL))
+                .and_then(|replicas| replicas.parse::<u32>().ok()),
+        );
+
         Ok(config)
     }
 }
@@ -1143,9 +2229,10 @@ impl From<ServiceBuilderError> for DockerInfrastructureError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{Environment, EnvironmentVariable};
+    use crate::models::{Environment, EnvironmentVariable, ResourceMetadata};
     use crate::sc;
     use secstr::SecUtf8;
+    use std::collections::BTreeMap;
 
     macro_rules! container_details {
         ($id:expr, $app_name:expr, $service_name:expr, $image:expr, $container_type:expr, $($l_key:expr => $l_value:expr),* ) => {{
@@ -1250,6 +2337,49 @@ mod tests {
             &config,
             &ContainerConfig::default(),
             &Vec::new(),
+            &BTreeMap::new(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_eq!(
+            json,
+            serde_json::json!({
+              "name": null,
+              "params": {
+                "HostConfig.RestartPolicy.Name": "always",
+                "Image": "docker.io/library/mariadb:10.3.17",
+                "Labels": {
+                  "com.aixigo.preview.servant.app-name": "master",
+                  "com.aixigo.preview.servant.container-type": "instance",
+                  "com.aixigo.preview.servant.service-name": "db",
+                  "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17",
+                  "traefik.frontend.rule": "PathPrefixStrip: /master/db/; PathPrefix:/master/db/;"
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_container_options_with_metadata() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_metadata(Some(ResourceMetadata::new(
+            BTreeMap::from([
+                (String::from("cost-center"), String::from("4711")),
+                (
+                    String::from("com.aixigo.preview.servant.service-name"),
+                    String::from("other"),
+                ),
+            ]),
+            BTreeMap::from([(String::from("example.com/owner"), String::from("team-a"))]),
+        )));
+
+        let options = DockerInfrastructure::create_container_options(
+            &String::from("master"),
+            &config,
+            &ContainerConfig::default(),
+            &Vec::new(),
+            &BTreeMap::new(),
         );
 
         let json = serde_json::to_value(&options).unwrap();
@@ -1265,6 +2395,8 @@ mod tests {
                   "com.aixigo.preview.servant.container-type": "instance",
                   "com.aixigo.preview.servant.service-name": "db",
                   "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17",
+                  "cost-center": "4711",
+                  "example.com/owner": "team-a",
                   "traefik.frontend.rule": "PathPrefixStrip: /master/db/; PathPrefix:/master/db/;"
                 }
               }
@@ -1285,6 +2417,7 @@ mod tests {
             &config,
             &ContainerConfig::default(),
             &Vec::new(),
+            &BTreeMap::new(),
         );
 
         let json = serde_json::to_value(&options).unwrap();
@@ -1310,6 +2443,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_container_options_with_command_args_and_working_dir() {
+        let mut config = sc!("worker", "example/app:latest");
+        config.set_command(Some(vec![String::from("/bin/sh"), String::from("-c")]));
+        config.set_args(Some(vec![String::from("./worker --queue jobs")]));
+        config.set_working_dir(Some(String::from("/opt/app")));
+
+        let options = DockerInfrastructure::create_container_options(
+            &String::from("master"),
+            &config,
+            &ContainerConfig::default(),
+            &Vec::new(),
+            &BTreeMap::new(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_include!(
+            actual: json,
+            expected: serde_json::json!({
+              "params": {
+                "Entrypoint": ["/bin/sh", "-c"],
+                "Cmd": ["./worker --queue jobs"],
+                "WorkingDir": "/opt/app"
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_container_options_with_replicas() {
+        let mut config = sc!("api", "example/api:latest");
+        config.set_replicas(Some(3));
+
+        let options = DockerInfrastructure::create_container_options(
+            &String::from("master"),
+            &config,
+            &ContainerConfig::default(),
+            &Vec::new(),
+            &BTreeMap::new(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_include!(
+            actual: json,
+            expected: serde_json::json!({
+              "params": {
+                "Labels": {
+                  "com.aixigo.preview.servant.replicas": "3",
+                  "traefik.backend": "master-api"
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_container_options_with_sticky_sessions() {
+        let mut config = sc!("api", "example/api:latest");
+        config.set_load_balancer(Some(
+            serde_json::from_value(serde_json::json!({
+                "sticky": { "cookie": { "name": "session" } }
+            }))
+            .unwrap(),
+        ));
+
+        let options = DockerInfrastructure::create_container_options(
+            &String::from("master"),
+            &config,
+            &ContainerConfig::default(),
+            &Vec::new(),
+            &BTreeMap::new(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_include!(
+            actual: json,
+            expected: serde_json::json!({
+              "params": {
+                "Labels": {
+                  "traefik.backend": "master-api",
+                  "traefik.backend.loadbalancer.stickiness": "true",
+                  "traefik.backend.loadbalancer.stickiness.cookieName": "session"
+                }
+              }
+            })
+        );
+    }
+
     #[test]
     fn should_create_container_options_with_replicated_environment_variable() {
         let mut config = sc!("db", "mariadb:10.3.17");
@@ -1325,6 +2546,7 @@ mod tests {
             &config,
             &ContainerConfig::default(),
             &Vec::new(),
+            &BTreeMap::new(),
         );
 
         let json = serde_json::to_value(&options).unwrap();
@@ -1441,6 +2663,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_service_config_from_container_details_with_fixed_port() {
+        let details = container_details!(
+            "some-random-id".to_string(),
+            Some(String::from("master")),
+            Some(String::from("nginx")),
+            Some(String::from("nginx")),
+            None,
+            String::from(FIXED_PORT_LABEL) => String::from("8080")
+        );
+
+        let service = Service::try_from(&details).unwrap();
+
+        assert_eq!(service.config().port(), 8080);
+        assert!(service.config().has_fixed_port());
+    }
+
+    #[test]
+    fn should_create_service_with_app_metadata_from_container_details() {
+        let details = container_details!(
+            "some-random-id".to_string(),
+            Some(String::from("master")),
+            Some(String::from("nginx")),
+            Some(String::from("nginx")),
+            None,
+            format!("{}branch", APP_METADATA_PREFIX) => String::from("feature/xyz")
+        );
+
+        let service = Service::try_from(&details).unwrap();
+
+        assert_eq!(
+            service.app_metadata(),
+            &BTreeMap::from([(String::from("branch"), String::from("feature/xyz"))])
+        );
+    }
+
     #[test]
     fn should_create_container_options_with_host_config_binds() {
         let config = sc!("db", "mariadb:10.3.17");
@@ -1450,6 +2708,7 @@ mod tests {
             &config,
             &ContainerConfig::default(),
             &[String::from("test-volume:/var/lib/mysql")],
+            &BTreeMap::new(),
         );
 
         let json = serde_json::to_value(&options).unwrap();
@@ -1472,4 +2731,128 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn should_create_container_options_with_resource_limits_and_restart_policy() {
+        let config = sc!("db", "mariadb:10.3.17");
+
+        let options = DockerInfrastructure::create_container_options(
+            &String::from("master"),
+            &config,
+            &toml::from_str::<ContainerConfig>(
+                r#"
+                memory_limit = '1g'
+                cpu_limit = '500m'
+                restart_policy = 'on-failure'
+                "#,
+            )
+            .unwrap(),
+            &Vec::new(),
+            &BTreeMap::new(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_include!(
+            actual: json,
+            expected: serde_json::json!({
+              "params": {
+                "HostConfig.RestartPolicy.Name": "on-failure",
+                "HostConfig.RestartPolicy.MaximumRetryCount": 5,
+                "HostConfig.Memory": 1073741824,
+                "HostConfig.MemorySwap": 1073741824,
+                "HostConfig.NanoCpus": 500000000
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_container_options_without_retry_count_for_restart_policy_always() {
+        let config = sc!("db", "mariadb:10.3.17");
+
+        let options = DockerInfrastructure::create_container_options(
+            &String::from("master"),
+            &config,
+            &ContainerConfig::default(),
+            &Vec::new(),
+            &BTreeMap::new(),
+        );
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_json_diff::assert_json_include!(
+            actual: json,
+            expected: serde_json::json!({
+              "params": {
+                "HostConfig.RestartPolicy.Name": "always",
+                "HostConfig.RestartPolicy.MaximumRetryCount": 0
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_translate_middlewares_into_traefik_frontend_labels() {
+        let middlewares = serde_json::from_value::<BTreeMap<String, Value>>(serde_json::json!({
+            "headers": {
+                "customRequestHeaders": { "X-Script-Name": "test" },
+                "customResponseHeaders": { "X-Custom": "a", "X-Other": "b" }
+            },
+            "basicAuth": { "users": [ "test:$apr1$H6uskkkW$IgXLP6ewTrSuBkTrqE8wj/" ] },
+            "ipWhiteList": { "sourceRange": [ "127.0.0.1/32", "192.168.1.7" ] },
+            "rateLimit": { "average": 100 }
+        }))
+        .unwrap();
+
+        let labels = traefik_middleware_labels("db", &middlewares);
+
+        assert_eq!(
+            labels,
+            BTreeMap::from([
+                (
+                    String::from("traefik.frontend.auth.basic.users"),
+                    String::from("test:$apr1$H6uskkkW$IgXLP6ewTrSuBkTrqE8wj/")
+                ),
+                (
+                    String::from("traefik.frontend.headers.customRequestHeaders"),
+                    String::from("X-Script-Name:test")
+                ),
+                (
+                    String::from("traefik.frontend.headers.customResponseHeaders"),
+                    String::from("X-Custom:a||X-Other:b")
+                ),
+                (
+                    String::from("traefik.frontend.whiteList.sourceRange"),
+                    String::from("127.0.0.1/32,192.168.1.7")
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn should_resolve_host_file_path_below_service_directory() {
+        let path = host_file_path(
+            Path::new("/var/lib/prevant/files"),
+            "master",
+            "nginx",
+            Path::new("/etc/nginx/nginx.conf"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            path,
+            PathBuf::from("/var/lib/prevant/files/master/nginx/etc/nginx/nginx.conf")
+        );
+    }
+
+    #[test]
+    fn should_not_resolve_host_file_path_outside_of_service_directory() {
+        let result = host_file_path(
+            Path::new("/var/lib/prevant/files"),
+            "master",
+            "nginx",
+            Path::new("/etc/../../other/nginx.conf"),
+        );
+
+        assert!(result.is_err());
+    }
 }
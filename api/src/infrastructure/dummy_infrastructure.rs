@@ -24,35 +24,52 @@
  * =========================LICENSE_END==================================
  */
 
-use crate::config::ContainerConfig;
+use crate::config::{BuildsConfig, ContainerConfig, InfrastructureOperation};
 use crate::deployment::deployment_unit::DeployableService;
 use crate::deployment::DeploymentUnit;
 use crate::infrastructure::Infrastructure;
 use crate::models::service::{Service, ServiceStatus};
-use crate::models::{AppName, ServiceBuilder, ServiceConfig};
+use crate::models::{
+    AppName, ClusterCapacity, Image, LogFilter, ServiceBuilder, ServiceConfig, SmokeTestOutcome,
+    SourceBuild,
+};
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset, Utc};
 use multimap::MultiMap;
-use std::collections::HashSet;
+use secstr::SecUtf8;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::iter::FromIterator;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use std::time::Duration;
 
 use super::TraefikIngressRoute;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "dummy-infrastructure"))]
+/// An in-memory infrastructure that behaves deterministically and whose services can be
+/// inspected. Delays and failures of operations can be injected to test the behaviour of PREvant
+/// under slow or broken infrastructures.
 pub struct DummyInfrastructure {
     delay: Option<Duration>,
     services: Mutex<MultiMap<AppName, DeployableService>>,
     base_ingress_route: Option<TraefikIngressRoute>,
+    failing_operations: Mutex<HashSet<InfrastructureOperation>>,
+    app_metadata: Mutex<HashMap<AppName, BTreeMap<String, String>>>,
+    paused_services: Mutex<HashSet<(AppName, String)>>,
+    capacity: Option<ClusterCapacity>,
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "dummy-infrastructure"))]
 impl DummyInfrastructure {
     pub fn new() -> Self {
         Self {
             delay: None,
             services: Mutex::new(MultiMap::new()),
             base_ingress_route: None,
+            failing_operations: Mutex::new(HashSet::new()),
+            app_metadata: Mutex::new(HashMap::new()),
+            paused_services: Mutex::new(HashSet::new()),
+            capacity: None,
         }
     }
 
@@ -61,6 +78,10 @@ impl DummyInfrastructure {
             delay: Some(delay),
             services: Mutex::new(MultiMap::new()),
             base_ingress_route: None,
+            failing_operations: Mutex::new(HashSet::new()),
+            app_metadata: Mutex::new(HashMap::new()),
+            paused_services: Mutex::new(HashSet::new()),
+            capacity: None,
         }
     }
 
@@ -69,9 +90,36 @@ impl DummyInfrastructure {
             delay: None,
             services: Mutex::new(MultiMap::new()),
             base_ingress_route: Some(base_ingress_route),
+            failing_operations: Mutex::new(HashSet::new()),
+            app_metadata: Mutex::new(HashMap::new()),
+            paused_services: Mutex::new(HashSet::new()),
+            capacity: None,
         }
     }
 
+    pub fn with_failing_operations<I>(self, operations: I) -> Self
+    where
+        I: IntoIterator<Item = InfrastructureOperation>,
+    {
+        *self.failing_operations.lock().unwrap() = HashSet::from_iter(operations);
+        self
+    }
+
+    /// Lets the infrastructure report the given free resources instead of an unknown capacity.
+    pub fn with_capacity(mut self, capacity: ClusterCapacity) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Lets the given operation fail until [`recover`](Self::recover) is called.
+    pub fn fail_on(&self, operation: InfrastructureOperation) {
+        self.failing_operations.lock().unwrap().insert(operation);
+    }
+
+    pub fn recover(&self, operation: InfrastructureOperation) {
+        self.failing_operations.lock().unwrap().remove(&operation);
+    }
+
     pub fn services(&self) -> Vec<DeployableService> {
         self.services
             .lock()
@@ -82,38 +130,57 @@ impl DummyInfrastructure {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "dummy-infrastructure"))]
 impl DummyInfrastructure {
     async fn delay_if_configured(&self) {
         if let Some(delay) = &self.delay {
             tokio::time::sleep(*delay).await;
         }
     }
+
+    fn fail_if_injected(&self, operation: InfrastructureOperation) -> Result<(), failure::Error> {
+        if self.failing_operations.lock().unwrap().contains(&operation) {
+            bail!("Injected failure of {:?}", operation);
+        }
+        Ok(())
+    }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "dummy-infrastructure"))]
 #[async_trait]
 impl Infrastructure for DummyInfrastructure {
     async fn get_services(&self) -> Result<MultiMap<AppName, Service>, failure::Error> {
+        self.fail_if_injected(InfrastructureOperation::GetServices)?;
+
         let mut s = MultiMap::new();
 
         let services = self.services.lock().unwrap();
+        let app_metadata = self.app_metadata.lock().unwrap();
+        let paused_services = self.paused_services.lock().unwrap();
         for (app, configs) in services.iter_all() {
             for config in configs {
-                let service = ServiceBuilder::new()
+                let status =
+                    if paused_services.contains(&(app.clone(), config.service_name().clone())) {
+                        ServiceStatus::Paused
+                    } else {
+                        ServiceStatus::Running
+                    };
+                let mut builder = ServiceBuilder::new()
                     .id(format!("{}", config.service_name()))
                     .app_name(app.to_string())
                     .config(ServiceConfig::clone(config))
-                    .service_status(ServiceStatus::Running)
+                    .service_status(status)
                     .started_at(
                         DateTime::parse_from_rfc3339("2019-07-18T07:30:00.000000000Z")
                             .unwrap()
                             .with_timezone(&Utc),
                     )
-                    .build()
-                    .unwrap();
+                    .app_metadata(app_metadata.get(app).cloned().unwrap_or_default());
+                if let Some(image_digest) = config.image_digest() {
+                    builder = builder.image_digest(image_digest.clone());
+                }
 
-                s.insert(app.clone(), service);
+                s.insert(app.clone(), builder.build().unwrap());
             }
         }
 
@@ -127,6 +194,7 @@ impl Infrastructure for DummyInfrastructure {
         _container_config: &ContainerConfig,
     ) -> Result<Vec<Service>, failure::Error> {
         self.delay_if_configured().await;
+        self.fail_if_injected(InfrastructureOperation::DeployServices)?;
 
         let mut services = self.services.lock().unwrap();
         let app_name = deployment_unit.app_name();
@@ -144,6 +212,14 @@ impl Infrastructure for DummyInfrastructure {
             info!("started {} for {}.", config.service_name(), app_name);
             services.insert(app_name.clone(), config.clone());
         }
+
+        self.app_metadata
+            .lock()
+            .unwrap()
+            .entry(app_name.clone())
+            .or_default()
+            .extend(deployment_unit.app_metadata().clone());
+
         Ok(vec![])
     }
 
@@ -153,6 +229,13 @@ impl Infrastructure for DummyInfrastructure {
         app_name: &AppName,
     ) -> Result<Vec<Service>, failure::Error> {
         self.delay_if_configured().await;
+        self.fail_if_injected(InfrastructureOperation::StopServices)?;
+
+        self.app_metadata.lock().unwrap().remove(app_name);
+        self.paused_services
+            .lock()
+            .unwrap()
+            .retain(|(app, _)| app != app_name);
 
         let mut services = self.services.lock().unwrap();
         match services.remove(app_name) {
@@ -181,9 +264,12 @@ impl Infrastructure for DummyInfrastructure {
         app_name: &AppName,
         service_name: &str,
         _from: &Option<DateTime<FixedOffset>>,
-        _limit: usize,
+        limit: usize,
+        filter: &LogFilter,
     ) -> Result<Option<Vec<(DateTime<FixedOffset>, String)>>, failure::Error> {
-        Ok(Some(vec![
+        self.fail_if_injected(InfrastructureOperation::GetLogs)?;
+
+        let logs = vec![
             (
                 DateTime::parse_from_rfc3339("2019-07-18T07:25:00.000000000Z").unwrap(),
                 format!("Log msg 1 of {} of app {}\n", service_name, app_name),
@@ -196,16 +282,61 @@ impl Infrastructure for DummyInfrastructure {
                 DateTime::parse_from_rfc3339("2019-07-18T07:35:00.000000000Z").unwrap(),
                 format!("Log msg 3 of {} of app {}\n", service_name, app_name),
             ),
-        ]))
+        ];
+
+        Ok(Some(
+            logs.into_iter()
+                .filter(|(_, line)| filter.matches(line))
+                .take(limit)
+                .collect(),
+        ))
     }
 
     async fn change_status(
         &self,
-        _app_name: &AppName,
-        _service_name: &str,
-        _status: ServiceStatus,
+        app_name: &AppName,
+        service_name: &str,
+        status: ServiceStatus,
     ) -> Result<Option<Service>, failure::Error> {
-        Ok(None)
+        self.fail_if_injected(InfrastructureOperation::ChangeStatus)?;
+
+        {
+            let mut paused_services = self.paused_services.lock().unwrap();
+            let key = (app_name.clone(), service_name.to_string());
+            match status {
+                ServiceStatus::Paused => paused_services.insert(key),
+                ServiceStatus::Running => paused_services.remove(&key),
+            };
+        }
+
+        Ok(self
+            .get_services()
+            .await?
+            .get_vec(app_name)
+            .and_then(|services| {
+                services
+                    .iter()
+                    .find(|service| service.service_name() == service_name)
+                    .cloned()
+            }))
+    }
+
+    async fn update_files(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        _files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<Option<Service>, failure::Error> {
+        Ok(self
+            .get_services()
+            .await?
+            .get_vec(app_name)
+            .and_then(|services| {
+                services
+                    .iter()
+                    .find(|service| service.service_name() == service_name)
+                    .cloned()
+            }))
     }
 
     async fn base_traefik_ingress_route(
@@ -214,7 +345,123 @@ impl Infrastructure for DummyInfrastructure {
         Ok(self.base_ingress_route.clone())
     }
 
+    async fn cluster_capacity(
+        &self,
+        _deployment_unit: &DeploymentUnit,
+    ) -> Result<Option<ClusterCapacity>, failure::Error> {
+        Ok(self.capacity.clone())
+    }
+
+    /// An injected failure of the smoke test results in a failed smoke test instead of an error
+    /// so that clients can observe failing deployments.
+    async fn run_smoke_test(
+        &self,
+        _app_name: &AppName,
+        image: &Image,
+        _command: &[String],
+        _timeout: Duration,
+    ) -> Result<SmokeTestOutcome, failure::Error> {
+        match self.fail_if_injected(InfrastructureOperation::RunSmokeTest) {
+            Ok(()) => Ok(SmokeTestOutcome::new(
+                true,
+                format!("Smoke test {} passed", image),
+            )),
+            Err(err) => Ok(SmokeTestOutcome::new(false, err.to_string())),
+        }
+    }
+
+    async fn build_image(
+        &self,
+        _app_name: &AppName,
+        _build: &SourceBuild,
+        destination: &Image,
+        _config: &BuildsConfig,
+    ) -> Result<SmokeTestOutcome, failure::Error> {
+        match self.fail_if_injected(InfrastructureOperation::BuildImage) {
+            Ok(()) => Ok(SmokeTestOutcome::new(
+                true,
+                format!("Pushed {}", destination),
+            )),
+            Err(err) => Ok(SmokeTestOutcome::new(false, err.to_string())),
+        }
+    }
+
+    async fn exec_in_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        command: &[String],
+        _timeout: Duration,
+    ) -> Result<SmokeTestOutcome, failure::Error> {
+        let is_running = self
+            .services
+            .lock()
+            .unwrap()
+            .get_vec(app_name)
+            .map(|services| {
+                services
+                    .iter()
+                    .any(|service| service.service_name() == service_name)
+            })
+            .unwrap_or(false);
+        if !is_running {
+            bail!(
+                "Service {} of app {} is not running",
+                service_name,
+                app_name
+            );
+        }
+
+        match self.fail_if_injected(InfrastructureOperation::ExecInService) {
+            Ok(()) => Ok(SmokeTestOutcome::new(
+                true,
+                format!("Executed {} in {}", command.join(" "), service_name),
+            )),
+            Err(err) => Ok(SmokeTestOutcome::new(false, err.to_string())),
+        }
+    }
+
+    async fn signal_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        signal: &str,
+        _files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<SmokeTestOutcome, failure::Error> {
+        let command = vec![
+            String::from("kill"),
+            String::from("-s"),
+            signal.to_string(),
+            String::from("1"),
+        ];
+        self.exec_in_service(app_name, service_name, &command, Duration::from_secs(10))
+            .await
+    }
+
+    #[cfg(test)]
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_fail_injected_operations_until_recovery() {
+        let infrastructure = DummyInfrastructure::new()
+            .with_failing_operations(vec![InfrastructureOperation::GetServices]);
+
+        assert!(infrastructure.get_services().await.is_err());
+
+        infrastructure.recover(InfrastructureOperation::GetServices);
+        assert!(infrastructure.get_services().await.is_ok());
+
+        infrastructure.fail_on(InfrastructureOperation::StopServices);
+        assert!(infrastructure
+            .stop_services("", &AppName::master())
+            .await
+            .is_err());
+    }
+}
@@ -25,14 +25,21 @@
  */
 
 use super::traefik::TraefikIngressRoute;
-use crate::config::ContainerConfig;
+use crate::config::{BuildsConfig, ContainerConfig};
 use crate::deployment::DeploymentUnit;
 use crate::models::service::{Service, ServiceStatus};
-use crate::models::{AppName, ContainerType, ServiceConfig};
+use crate::models::{
+    AppName, ClusterCapacity, ContainerType, Image, LogFilter, LogStream, SelfCheck, ServiceConfig,
+    SmokeTestOutcome, SourceBuild,
+};
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset};
 use failure::Error;
 use multimap::MultiMap;
+use secstr::SecUtf8;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[async_trait]
 pub trait Infrastructure: Send + Sync {
@@ -68,15 +75,37 @@ pub trait Infrastructure: Send + Sync {
         app_name: &AppName,
     ) -> Result<Vec<Service>, Error>;
 
-    /// Returns the log lines with a the corresponding timestamps in it.
+    /// Returns the log lines with a the corresponding timestamps in it. Only lines matching the
+    /// `filter` are returned and count towards the `limit`.
     async fn get_logs(
         &self,
         app_name: &AppName,
         service_name: &str,
         from: &Option<DateTime<FixedOffset>>,
         limit: usize,
+        filter: &LogFilter,
     ) -> Result<Option<Vec<(DateTime<FixedOffset>, String)>>, Error>;
 
+    /// Returns the log lines like [`Infrastructure::get_logs`] does but additionally with the
+    /// stream they have been written to, if the infrastructure is able to distinguish them.
+    async fn get_logs_with_streams(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        from: &Option<DateTime<FixedOffset>>,
+        limit: usize,
+        filter: &LogFilter,
+    ) -> Result<Option<Vec<(DateTime<FixedOffset>, Option<LogStream>, String)>>, Error> {
+        Ok(self
+            .get_logs(app_name, service_name, from, limit, filter)
+            .await?
+            .map(|logs| {
+                logs.into_iter()
+                    .map(|(timestamp, line)| (timestamp, None, line))
+                    .collect()
+            }))
+    }
+
     /// Changes the status of a service, for example, the service might me stopped or started.
     async fn change_status(
         &self,
@@ -85,12 +114,119 @@ pub trait Infrastructure: Send + Sync {
         status: ServiceStatus,
     ) -> Result<Option<Service>, Error>;
 
+    /// Restarts all instances of a service without redeploying the app. By default, the service
+    /// will be stopped and started again through [`Infrastructure::change_status`].
+    async fn restart_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Option<Service>, Error> {
+        self.change_status(app_name, service_name, ServiceStatus::Paused)
+            .await?;
+        self.change_status(app_name, service_name, ServiceStatus::Running)
+            .await
+    }
+
+    /// Replaces the contents of `files` that are mounted into the running service without
+    /// redeploying it. Returns `None` if the service does not exist and fails if a file is not
+    /// mounted into the service.
+    async fn update_files(
+        &self,
+        _app_name: &AppName,
+        _service_name: &str,
+        _files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<Option<Service>, Error> {
+        bail!("Updating files of running services is not supported by this infrastructure")
+    }
+
+    /// Takes over the services of an app that have not been deployed by PREvant, e.g. the
+    /// services of a manually prepared namespace, so that PREvant manages their lifecycle from now
+    /// on. Returns the services of the app afterwards which is empty if there is nothing to adopt.
+    async fn adopt_services(&self, _app_name: &AppName) -> Result<Vec<Service>, Error> {
+        bail!("Adopting apps is not supported by this infrastructure")
+    }
+
     /// Determines the [router rule](https://doc.traefik.io/traefik/routing/routers/) that points
     /// to PREvant it self so services will be reachable on the same route, e.g. host name.
     async fn base_traefik_ingress_route(&self) -> Result<Option<TraefikIngressRoute>, Error> {
         Ok(None)
     }
 
+    /// Returns the resources that are free on the nodes which would run the services of the
+    /// `deployment_unit`. The resources of the app's running pods count as free because the
+    /// deployment replaces them. `None` means that the infrastructure cannot determine its
+    /// capacity.
+    async fn cluster_capacity(
+        &self,
+        _deployment_unit: &DeploymentUnit,
+    ) -> Result<Option<ClusterCapacity>, Error> {
+        Ok(None)
+    }
+
+    /// Runs the `image` of a smoke test within the network of the app and returns whether the
+    /// container terminated successfully within the `timeout`, together with its logs.
+    async fn run_smoke_test(
+        &self,
+        _app_name: &AppName,
+        _image: &Image,
+        _command: &[String],
+        _timeout: Duration,
+    ) -> Result<SmokeTestOutcome, Error> {
+        bail!("Smoke test containers are not supported by this infrastructure")
+    }
+
+    /// Checks out the sources of the `build` and builds the `destination` image from them with
+    /// the builder of the `config`. Returns whether the image has been built and pushed within the
+    /// timeout of the `config`, together with the logs of the build.
+    async fn build_image(
+        &self,
+        _app_name: &AppName,
+        _build: &SourceBuild,
+        _destination: &Image,
+        _config: &BuildsConfig,
+    ) -> Result<SmokeTestOutcome, Error> {
+        bail!("Building images is not supported by this infrastructure")
+    }
+
+    /// Executes the `command` inside a running container of the service and returns whether the
+    /// command terminated successfully within the `timeout`, together with its output.
+    async fn exec_in_service(
+        &self,
+        _app_name: &AppName,
+        _service_name: &str,
+        _command: &[String],
+        _timeout: Duration,
+    ) -> Result<SmokeTestOutcome, Error> {
+        bail!("Executing commands in services is not supported by this infrastructure")
+    }
+
+    /// Sends the `signal` to the main process of every running instance of the service, e.g. to
+    /// reload its configuration, and returns whether all instances received it. Each instance is
+    /// signaled not before it sees the contents of the `files` that have just been updated with
+    /// [`Infrastructure::update_files`].
+    async fn signal_service(
+        &self,
+        _app_name: &AppName,
+        _service_name: &str,
+        _signal: &str,
+        _files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<SmokeTestOutcome, Error> {
+        bail!("Sending signals to services is not supported by this infrastructure")
+    }
+
+    /// Verifies the prerequisites that the infrastructure needs to deploy apps, e.g. the
+    /// connectivity or the permissions of PREvant. By default, only the connectivity is checked by
+    /// listing the services.
+    async fn self_check(&self) -> Vec<SelfCheck> {
+        vec![match self.get_services().await {
+            Ok(services) => SelfCheck::passed(
+                "connectivity",
+                format!("{} apps are running", services.keys().count()),
+            ),
+            Err(err) => SelfCheck::failed("connectivity", err.to_string()),
+        }]
+    }
+
     #[cfg(test)]
     fn as_any(&self) -> &dyn std::any::Any {
         panic!("This should be only use in test environments with following approach: https://stackoverflow.com/a/33687996/5088458")
@@ -0,0 +1,296 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::infrastructure::exec_in_pod;
+use crate::infrastructure::{Condition, ConditionState};
+use crate::models::CustomResourceReadiness;
+use async_trait::async_trait;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, DynamicObject};
+use secstr::SecUtf8;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// All replicas of the deployment are ready.
+pub(super) struct DeploymentAvailable {
+    pub api: Api<Deployment>,
+    pub name: String,
+}
+
+/// The job has completed successfully. A failed job fails the condition.
+pub(super) struct JobCompleted {
+    pub api: Api<Job>,
+    pub name: String,
+}
+
+/// The pod has terminated successfully. A failed pod fails the condition.
+pub(super) struct PodSucceeded {
+    pub api: Api<Pod>,
+    pub name: String,
+}
+
+/// The operator reports the custom resource as ready.
+pub(super) struct CustomResourceReady {
+    pub api: Api<DynamicObject>,
+    pub name: String,
+    pub readiness: CustomResourceReadiness,
+}
+
+/// The container of the pod sees the given contents of the files, i.e. the kubelet has synced the
+/// updated secret into the pod.
+pub(super) struct FilesProjected {
+    pub api: Api<Pod>,
+    pub name: String,
+    pub container: String,
+    pub files: BTreeMap<PathBuf, SecUtf8>,
+}
+
+#[async_trait]
+impl Condition for DeploymentAvailable {
+    fn description(&self) -> String {
+        format!("deployment {} is available", self.name)
+    }
+
+    async fn check(&self) -> Result<ConditionState, failure::Error> {
+        Ok(deployment_state(&self.api.get(&self.name).await?))
+    }
+}
+
+#[async_trait]
+impl Condition for JobCompleted {
+    fn description(&self) -> String {
+        format!("job {} has completed", self.name)
+    }
+
+    async fn check(&self) -> Result<ConditionState, failure::Error> {
+        Ok(job_state(&self.api.get(&self.name).await?))
+    }
+}
+
+#[async_trait]
+impl Condition for PodSucceeded {
+    fn description(&self) -> String {
+        format!("pod {} has succeeded", self.name)
+    }
+
+    async fn check(&self) -> Result<ConditionState, failure::Error> {
+        let pod = self.api.get(&self.name).await?;
+        Ok(
+            match pod.status.and_then(|status| status.phase).as_deref() {
+                Some("Succeeded") => ConditionState::Fulfilled,
+                Some("Failed") => ConditionState::Failed(String::from("the pod has failed")),
+                phase => ConditionState::Pending(format!(
+                    "the pod is in phase {}",
+                    phase.unwrap_or("Unknown")
+                )),
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl Condition for CustomResourceReady {
+    fn description(&self) -> String {
+        format!("custom resource {} is ready", self.name)
+    }
+
+    async fn check(&self) -> Result<ConditionState, failure::Error> {
+        let resource = serde_json::to_value(self.api.get(&self.name).await?)?;
+        Ok(if self.readiness.is_ready(&resource) {
+            ConditionState::Fulfilled
+        } else {
+            ConditionState::Pending(String::from("the custom resource is not ready yet"))
+        })
+    }
+}
+
+#[async_trait]
+impl Condition for FilesProjected {
+    fn description(&self) -> String {
+        format!("the files of pod {} are up to date", self.name)
+    }
+
+    async fn check(&self) -> Result<ConditionState, failure::Error> {
+        for (path, content) in &self.files {
+            let command = vec![String::from("cat"), path.display().to_string()];
+            let outcome = exec_in_pod(
+                &self.api,
+                &self.name,
+                &self.container,
+                &command,
+                Duration::from_secs(10),
+            )
+            .await?;
+            if !outcome.passed() {
+                return Ok(ConditionState::Failed(format!(
+                    "cannot read {}: {}",
+                    path.display(),
+                    outcome.logs()
+                )));
+            }
+            if outcome.logs() != content.unsecure() {
+                return Ok(ConditionState::Pending(format!(
+                    "{} has not been updated yet",
+                    path.display()
+                )));
+            }
+        }
+        Ok(ConditionState::Fulfilled)
+    }
+}
+
+fn deployment_state(deployment: &Deployment) -> ConditionState {
+    let replicas = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let generation = deployment.metadata.generation.unwrap_or_default();
+    let status = deployment.status.clone().unwrap_or_default();
+
+    // The status describes the previous revision until the controller has observed the update.
+    if status.observed_generation.unwrap_or_default() < generation {
+        return ConditionState::Pending(String::from(
+            "the update of the deployment has not been observed yet",
+        ));
+    }
+
+    let updated_replicas = status.updated_replicas.unwrap_or_default();
+    if updated_replicas != replicas {
+        return ConditionState::Pending(format!(
+            "{updated_replicas} of {replicas} replicas are updated"
+        ));
+    }
+
+    let ready_replicas = status.ready_replicas.unwrap_or_default();
+    if ready_replicas >= replicas {
+        ConditionState::Fulfilled
+    } else {
+        ConditionState::Pending(format!("{ready_replicas} of {replicas} replicas are ready"))
+    }
+}
+
+fn job_state(job: &Job) -> ConditionState {
+    let status = job.status.clone().unwrap_or_default();
+    if status.succeeded.unwrap_or_default() > 0 {
+        ConditionState::Fulfilled
+    } else if status.failed.unwrap_or_default() > 0 {
+        ConditionState::Failed(String::from("the job has failed"))
+    } else {
+        ConditionState::Pending(String::from("the job is still running"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::{DeploymentSpec, DeploymentStatus};
+    use k8s_openapi::api::batch::v1::JobStatus;
+
+    #[test]
+    fn should_wait_for_all_replicas_of_deployment() {
+        let deployment = Deployment {
+            spec: Some(DeploymentSpec {
+                replicas: Some(2),
+                ..Default::default()
+            }),
+            status: Some(DeploymentStatus {
+                updated_replicas: Some(2),
+                ready_replicas: Some(1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            deployment_state(&deployment),
+            ConditionState::Pending(String::from("1 of 2 replicas are ready"))
+        );
+    }
+
+    #[test]
+    fn should_wait_for_observed_update_of_deployment() {
+        let mut deployment = Deployment {
+            spec: Some(DeploymentSpec {
+                replicas: Some(1),
+                ..Default::default()
+            }),
+            status: Some(DeploymentStatus {
+                observed_generation: Some(1),
+                updated_replicas: Some(1),
+                ready_replicas: Some(1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        deployment.metadata.generation = Some(2);
+
+        assert_eq!(
+            deployment_state(&deployment),
+            ConditionState::Pending(String::from(
+                "the update of the deployment has not been observed yet"
+            ))
+        );
+
+        // The ready replicas of the previous revision don't count.
+        deployment.status = Some(DeploymentStatus {
+            observed_generation: Some(2),
+            updated_replicas: Some(0),
+            ready_replicas: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(
+            deployment_state(&deployment),
+            ConditionState::Pending(String::from("0 of 1 replicas are updated"))
+        );
+
+        deployment.status = Some(DeploymentStatus {
+            observed_generation: Some(2),
+            updated_replicas: Some(1),
+            ready_replicas: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(deployment_state(&deployment), ConditionState::Fulfilled);
+    }
+
+    #[test]
+    fn should_fail_for_failed_job() {
+        let job = Job {
+            status: Some(JobStatus {
+                failed: Some(1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            job_state(&job),
+            ConditionState::Failed(String::from("the job has failed"))
+        );
+    }
+}
@@ -24,46 +24,83 @@
  * =========================LICENSE_END==================================
  */
 use super::super::{
-    APP_NAME_LABEL, CONTAINER_TYPE_LABEL, IMAGE_LABEL, REPLICATED_ENV_LABEL, SERVICE_NAME_LABEL,
+    ADOPTABLE_LABEL, ALIASES_LABEL, ALIAS_OF_LABEL, APP_METADATA_PREFIX, APP_NAME_LABEL,
+    CONTAINER_TYPE_LABEL, DEPENDS_ON_LABEL, FIXED_PORT_LABEL, IMAGE_DIGEST_LABEL, IMAGE_LABEL,
+    REPLICAS_LABEL, REPLICATED_ENV_LABEL, REPLICATED_FROM_LABEL, SERVICE_NAME_LABEL,
     STORAGE_TYPE_LABEL,
 };
+use super::conditions::{
+    CustomResourceReady, DeploymentAvailable, FilesProjected, JobCompleted, PodSucceeded,
+};
 use super::payloads::{
-    deployment_payload, deployment_replicas_payload, image_pull_secret_payload,
-    ingress_route_payload, middleware_payload, namespace_payload, persistent_volume_claim_payload,
-    secrets_payload, service_payload, IngressRoute,
+    adopted_deployment_payload, adopted_service_payload, alias_service_payloads, app_host,
+    build_job_payload, build_secret_payload, certificate_payload, deployment_payload,
+    deployment_replicas_payload, deployment_restart_payload, egress_network_policy_name,
+    egress_network_policy_payload, horizontal_pod_autoscaler_name,
+    horizontal_pod_autoscaler_payload, host_ingress_route_payload, image_pull_secret_payload,
+    ingress_route_payload, middleware_payload, middlewares_unsupported_by_istio, namespace_name,
+    namespace_payload, persistent_volume_claim_payload, role_binding_payload, role_payload,
+    secret_files_patch, secrets_payload, service_account_payload, service_mesh, service_payload,
+    sibling_host_aliases, smoke_test_pod_payload, virtual_service_payload, Certificate,
+    IngressRoute, Middleware, VirtualService,
 };
-use crate::config::{Config as PREvantConfig, ContainerConfig, Runtime};
+use crate::config::{BuildsConfig, Config as PREvantConfig, ContainerConfig, Runtime, ServiceMesh};
 use crate::deployment::deployment_unit::{DeployableService, DeploymentUnit};
-use crate::infrastructure::traefik::TraefikIngressRoute;
-use crate::infrastructure::Infrastructure;
+use crate::infrastructure::traefik::{TraefikIngressRoute, TraefikMiddleware};
+use crate::infrastructure::{wait_for, Infrastructure, WaitError};
 use crate::models::service::{ContainerType, Service, ServiceError, ServiceStatus};
 use crate::models::{
-    AppName, Environment, Image, ServiceBuilder, ServiceBuilderError, ServiceConfig,
+    parse_quantity, AppName, ClusterCapacity, CustomResource, Environment, HelmRelease, Image,
+    LogFilter, SelfCheck, ServiceBuilder, ServiceBuilderError, ServiceConfig, SmokeTestOutcome,
+    SourceBuild,
 };
 use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset, Utc};
 use failure::Error;
-use futures::future::join_all;
+use futures::future::{join_all, BoxFuture};
+use futures::{stream, FutureExt, StreamExt};
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+};
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use k8s_openapi::api::batch::v1::Job as V1Job;
+use k8s_openapi::api::networking::v1::NetworkPolicy;
+use k8s_openapi::api::rbac::v1::{Role, RoleBinding};
 use k8s_openapi::api::storage::v1::StorageClass;
 use k8s_openapi::api::{
-    apps::v1::Deployment as V1Deployment, core::v1::Namespace as V1Namespace,
-    core::v1::PersistentVolumeClaim, core::v1::Pod as V1Pod, core::v1::Secret as V1Secret,
-    core::v1::Service as V1Service,
+    apps::v1::Deployment as V1Deployment, core::v1::Event as V1Event, core::v1::HostAlias,
+    core::v1::Namespace as V1Namespace, core::v1::Node as V1Node, core::v1::PersistentVolumeClaim,
+    core::v1::Pod as V1Pod, core::v1::Secret as V1Secret, core::v1::Service as V1Service,
+    core::v1::ServiceAccount as V1ServiceAccount,
 };
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::NamespaceResourceScope;
 use kube::{
-    api::{Api, DeleteParams, ListParams, LogParams, Patch, PatchParams, PostParams},
+    api::{
+        Api, AttachParams, DeleteParams, DynamicObject, GroupVersionKind, ListParams, LogParams,
+        Patch, PatchParams, PostParams,
+    },
     client::Client,
-    config::Config,
+    config::{Config, KubeConfigOptions, Kubeconfig},
+    discovery::{verbs, Discovery, Scope},
     error::{Error as KubeError, ErrorResponse},
+    Resource, ResourceExt,
 };
 use log::{debug, warn};
 use multimap::MultiMap;
 use secstr::SecUtf8;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::{BTreeMap, HashMap};
 use std::convert::{From, TryFrom};
-use std::net::IpAddr;
+use std::net::{IpAddr, ToSocketAddrs};
 use std::path::PathBuf;
+use std::process::Stdio;
 use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use uuid::Uuid;
 
 pub struct KubernetesInfrastructure {
     config: PREvantConfig,
@@ -95,6 +132,42 @@ pub enum KubernetesInfrastructureError {
     MissingImageLabel { deployment_name: String },
     #[fail(display = "The default storage class is missing in kubernetes.")]
     MissingDefaultStorageClass,
+    #[fail(display = "Cannot apply the custom resource {}: {}", name, message)]
+    InvalidCustomResource { name: String, message: String },
+    #[fail(display = "{}", error)]
+    ConditionNotFulfilled { error: WaitError },
+    #[fail(
+        display = "Cannot apply the kustomize overlays to {}: {}",
+        resource, message
+    )]
+    KustomizeFailed { resource: String, message: String },
+    #[fail(
+        display = "The service mesh cannot enforce the middleware {} of {}.",
+        middleware, service_name
+    )]
+    UnsupportedMiddleware {
+        service_name: String,
+        middleware: String,
+    },
+    #[fail(
+        display = "The service account of {} cannot be granted any rules because its namespace is shared with other apps.",
+        service_name
+    )]
+    RulesInSharedNamespace { service_name: String },
+    #[fail(
+        display = "Helm failed to {} the release {}: {}",
+        action, release, message
+    )]
+    HelmFailed {
+        action: String,
+        release: String,
+        message: String,
+    },
+    #[fail(
+        display = "The alias {} of {} conflicts with a Kubernetes service that is not an alias.",
+        alias, service_name
+    )]
+    AliasConflict { service_name: String, alias: String },
 }
 
 impl KubernetesInfrastructure {
@@ -102,14 +175,30 @@ impl KubernetesInfrastructure {
         Self { config }
     }
 
-    async fn client(&self) -> Result<Client, KubernetesInfrastructureError> {
-        let configuration = Config::infer().await.map_err(|err| {
-            KubernetesInfrastructureError::UnexpectedError {
-                internal_message: format!(
-                    "Failed to read Kube configuration from cluster env: {err}"
-                ),
-            }
-        })?;
+    fn namespace(&self, app_name: &AppName) -> String {
+        namespace_name(app_name, &self.config)
+    }
+
+    /// Returns `true` if each app has its own namespace that PREvant creates and deletes.
+    /// Otherwise, the resources of the apps are identified by their labels.
+    fn manages_namespaces(&self) -> bool {
+        match self.config.runtime_config() {
+            Runtime::Kubernetes(runtime) => runtime.namespace().is_managed(),
+            _ => true,
+        }
+    }
+
+    /// Returns `true` if all apps share the same namespace, i.e. the name of the namespace does
+    /// not depend on the app.
+    fn shares_namespace(&self) -> bool {
+        match self.config.runtime_config() {
+            Runtime::Kubernetes(runtime) => !runtime.namespace().name().contains("{app}"),
+            _ => false,
+        }
+    }
+
+    pub(super) async fn client(&self) -> Result<Client, KubernetesInfrastructureError> {
+        let configuration = self.kube_configuration().await?;
 
         Client::try_from(configuration).map_err(|err| {
             KubernetesInfrastructureError::UnexpectedError {
@@ -118,6 +207,400 @@ impl KubernetesInfrastructure {
         })
     }
 
+    /// Uses the configured kubeconfig and context to manage a cluster from outside. Otherwise,
+    /// the configuration is inferred from the environment, e.g. the service account of the pod.
+    /// The configuration is read for each client so that rotated tokens are picked up.
+    async fn kube_configuration(&self) -> Result<Config, KubernetesInfrastructureError> {
+        let kubeconfig = match self.config.runtime_config() {
+            Runtime::Kubernetes(k8s_config) => k8s_config.kubeconfig(),
+            _ => None,
+        };
+
+        let Some(kubeconfig) = kubeconfig else {
+            return Config::infer().await.map_err(|err| {
+                KubernetesInfrastructureError::UnexpectedError {
+                    internal_message: format!(
+                        "Failed to read Kube configuration from cluster env: {err}"
+                    ),
+                }
+            });
+        };
+
+        let options = KubeConfigOptions {
+            context: kubeconfig.context().clone(),
+            ..Default::default()
+        };
+        let configuration = match kubeconfig.path() {
+            Some(path) => match Kubeconfig::read_from(path) {
+                Ok(kubeconfig) => Config::from_custom_kubeconfig(kubeconfig, &options).await,
+                Err(err) => Err(err),
+            },
+            None => Config::from_kubeconfig(&options).await,
+        };
+
+        configuration.map_err(|err| KubernetesInfrastructureError::UnexpectedError {
+            internal_message: format!("Failed to read kubeconfig: {err}"),
+        })
+    }
+
+    /// Applies the kustomize overlays matching the app to a generated manifest. The manifest and
+    /// a kustomization that includes the overlays as components are written to a temporary
+    /// directory so that the overlays themselves can be mounted read-only.
+    async fn post_render<K>(
+        &self,
+        app_name: &AppName,
+        payload: K,
+    ) -> Result<K, KubernetesInfrastructureError>
+    where
+        K: Resource + DeserializeOwned + Serialize,
+    {
+        let Some(kustomize) = self.config.kustomize_config() else {
+            return Ok(payload);
+        };
+        let overlays = kustomize.overlays(app_name);
+        if overlays.is_empty() {
+            return Ok(payload);
+        }
+
+        let resource = payload.name_any();
+        let kustomize_failed = |message: String| KubernetesInfrastructureError::KustomizeFailed {
+            resource: resource.clone(),
+            message,
+        };
+
+        let identity = |manifest: &serde_json::Value| {
+            ["/kind", "/metadata/name", "/metadata/namespace"]
+                .map(|pointer| manifest.pointer(pointer).cloned())
+        };
+        let expected_identity = identity(
+            &serde_json::to_value(&payload).map_err(|err| kustomize_failed(err.to_string()))?,
+        );
+
+        let manifest =
+            serde_yaml::to_string(&payload).map_err(|err| kustomize_failed(err.to_string()))?;
+        let kustomization = serde_yaml::to_string(&serde_json::json!({
+            "apiVersion": "kustomize.config.k8s.io/v1beta1",
+            "kind": "Kustomization",
+            "resources": ["manifest.yaml"],
+            "components": overlays,
+        }))
+        .map_err(|err| kustomize_failed(err.to_string()))?;
+
+        let directory = std::env::temp_dir().join(format!("prevant-kustomize-{}", Uuid::new_v4()));
+        let result = async {
+            tokio::fs::create_dir_all(&directory).await?;
+            tokio::fs::write(directory.join("manifest.yaml"), manifest).await?;
+            tokio::fs::write(directory.join("kustomization.yaml"), kustomization).await?;
+            Command::new(kustomize.command())
+                .arg("build")
+                .arg(&directory)
+                .output()
+                .await
+        }
+        .await;
+        if let Err(err) = tokio::fs::remove_dir_all(&directory).await {
+            debug!("Cannot remove {}: {}", directory.display(), err);
+        }
+
+        let output = result.map_err(|err| kustomize_failed(err.to_string()))?;
+        if !output.status.success() {
+            return Err(kustomize_failed(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        // The overlays must neither add, remove, nor rename resources, so that PREvant keeps track
+        // of everything it deploys.
+        let rendered = serde_yaml::from_slice::<serde_json::Value>(&output.stdout)
+            .map_err(|err| kustomize_failed(err.to_string()))?;
+        if identity(&rendered) != expected_identity {
+            return Err(kustomize_failed(String::from(
+                "the overlays must not change the kind, the name, or the namespace",
+            )));
+        }
+        serde_json::from_value::<K>(rendered).map_err(|err| kustomize_failed(err.to_string()))
+    }
+
+    /// Resolves the API of the custom resource's kind through the discovery of the cluster.
+    async fn custom_resource_api(
+        &self,
+        app_name: &AppName,
+        resource: &CustomResource,
+    ) -> Result<Api<DynamicObject>, KubernetesInfrastructureError> {
+        let invalid = |message: &str| KubernetesInfrastructureError::InvalidCustomResource {
+            name: resource.name().to_string(),
+            message: message.to_string(),
+        };
+        let (api_version, kind) = resource
+            .api_version()
+            .zip(resource.kind())
+            .ok_or_else(|| invalid("apiVersion and kind are required"))?;
+        let (group, version) = api_version.rsplit_once('/').unwrap_or(("", api_version));
+
+        let client = self.client().await?;
+        let (api_resource, _) =
+            kube::discovery::pinned_kind(&client, &GroupVersionKind::gvk(group, version, kind))
+                .await
+                .map_err(|err| invalid(&err.to_string()))?;
+        Ok(Api::namespaced_with(
+            client,
+            &self.namespace(app_name),
+            &api_resource,
+        ))
+    }
+
+    /// Applies the custom resources of the companions with server-side apply and waits until
+    /// their operators report them as ready.
+    async fn apply_custom_resources(
+        &self,
+        app_name: &AppName,
+        resources: &[CustomResource],
+    ) -> Result<(), KubernetesInfrastructureError> {
+        for resource in resources {
+            let api = self.custom_resource_api(app_name, resource).await?;
+            let mut object = serde_json::from_value::<DynamicObject>(resource.manifest().clone())
+                .map_err(|err| {
+                KubernetesInfrastructureError::InvalidCustomResource {
+                    name: resource.name().to_string(),
+                    message: err.to_string(),
+                }
+            })?;
+            object.metadata.namespace = Some(self.namespace(app_name));
+            // The resources of different apps would overwrite each other in a shared namespace.
+            if self.shares_namespace() {
+                let name = object.name_any();
+                if !name.starts_with(&format!("{app_name}-")) {
+                    object.metadata.name = Some(format!("{app_name}-{name}"));
+                }
+            }
+            object
+                .labels_mut()
+                .insert(APP_NAME_LABEL.to_string(), app_name.to_string());
+            object
+                .labels_mut()
+                .insert(SERVICE_NAME_LABEL.to_string(), resource.name().to_string());
+
+            debug!(
+                "Applying custom resource {} of {app_name}",
+                object.name_any()
+            );
+            api.patch(
+                &object.name_any(),
+                &PatchParams::apply("prevant").force(),
+                &Patch::Apply(&object),
+            )
+            .await?;
+
+            let Some(readiness) = resource.readiness() else {
+                continue;
+            };
+            let condition = CustomResourceReady {
+                name: object.name_any(),
+                api,
+                readiness: readiness.clone(),
+            };
+            wait_for(&condition, resource.timeout(), Duration::from_secs(2))
+                .await
+                .map_err(|error| KubernetesInfrastructureError::ConditionNotFulfilled { error })?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the custom resources of the companions so that their operators can clean up the
+    /// resources they provisioned. The resources are found through their labels in all API groups
+    /// of custom resource definitions, regardless of the companions that are currently configured.
+    async fn delete_custom_resources(
+        &self,
+        app_name: &AppName,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        let client = self.client().await?;
+        let discovery = Discovery::new(client.clone()).run().await?;
+        let selector = format!("{APP_NAME_LABEL}={app_name},{SERVICE_NAME_LABEL}");
+
+        // Custom resource definitions always belong to a group with a dot, the built-in groups
+        // without a dot or in k8s.io cannot contain custom resources.
+        for group in discovery
+            .groups()
+            .filter(|group| group.name().contains('.') && !group.name().ends_with("k8s.io"))
+        {
+            for (api_resource, capabilities) in group.recommended_resources() {
+                if capabilities.scope != Scope::Namespaced
+                    || !capabilities.supports_operation(verbs::LIST)
+                    || !capabilities.supports_operation(verbs::DELETE)
+                {
+                    continue;
+                }
+
+                let api = Api::<DynamicObject>::namespaced_with(
+                    client.clone(),
+                    &self.namespace(app_name),
+                    &api_resource,
+                );
+                let resources = match api.list(&ListParams::default().labels(&selector)).await {
+                    Ok(resources) => resources,
+                    // PREvant cannot have applied resources that it is not allowed to list.
+                    Err(KubeError::Api(ErrorResponse { code, .. }))
+                        if code == 403 || code == 404 || code == 405 =>
+                    {
+                        continue
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+
+                for resource in resources {
+                    let name = resource.name_any();
+                    debug!(
+                        "Deleting custom resource {} {name} of {app_name}",
+                        api_resource.kind
+                    );
+                    ignore_not_found(
+                        api.delete(&name, &DeleteParams::default())
+                            .await
+                            .map(|_| ()),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the Helm command that targets the namespace of the app, using the same kubeconfig
+    /// and context as the Kubernetes client.
+    fn helm_command(&self, app_name: &AppName) -> Command {
+        let mut command = Command::new(self.config.helm_config().command());
+        command.arg("--namespace").arg(self.namespace(app_name));
+        if let Runtime::Kubernetes(runtime) = self.config.runtime_config() {
+            if let Some(kubeconfig) = runtime.kubeconfig() {
+                if let Some(path) = kubeconfig.path() {
+                    command.arg("--kubeconfig").arg(path);
+                }
+                if let Some(context) = kubeconfig.context() {
+                    command.arg("--kube-context").arg(context);
+                }
+            }
+        }
+        command
+    }
+
+    async fn run_helm(
+        mut command: Command,
+        action: &str,
+        release: &str,
+        stdin: Option<&str>,
+    ) -> Result<String, KubernetesInfrastructureError> {
+        let helm_failed = |message: String| KubernetesInfrastructureError::HelmFailed {
+            action: action.to_string(),
+            release: release.to_string(),
+            message,
+        };
+
+        let mut child = command
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| helm_failed(err.to_string()))?;
+        if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+            child_stdin
+                .write_all(input.as_bytes())
+                .await
+                .map_err(|err| helm_failed(err.to_string()))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|err| helm_failed(err.to_string()))?;
+        if !output.status.success() {
+            return Err(helm_failed(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Installs or upgrades the Helm charts of the companions. The releases are labeled with the
+    /// app name so that they can be found again when the app is stopped.
+    async fn install_helm_releases(
+        &self,
+        app_name: &AppName,
+        releases: &[HelmRelease],
+    ) -> Result<(), KubernetesInfrastructureError> {
+        for release in releases {
+            debug!(
+                "Install Helm chart {} as {}",
+                release.chart(),
+                release.name()
+            );
+
+            let mut command = self.helm_command(app_name);
+            command
+                .args(["upgrade", "--install", "--wait"])
+                .arg(release.name())
+                .arg(release.chart())
+                .arg("--labels")
+                .arg(format!("{APP_NAME_LABEL}={app_name}"));
+            if let Some(repository) = release.repository() {
+                command.arg("--repo").arg(repository);
+            }
+            if let Some(version) = release.version() {
+                command.arg("--version").arg(version);
+            }
+            if release.values().is_some() {
+                command.args(["--values", "-"]);
+            }
+
+            Self::run_helm(
+                command,
+                "install",
+                release.name(),
+                release.values().map(String::as_str),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Uninstalls all Helm releases that are labeled with the app name, regardless of the
+    /// companions that are currently configured.
+    async fn uninstall_helm_releases(
+        &self,
+        app_name: &AppName,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        let mut command = self.helm_command(app_name);
+        command
+            .args(["list", "--short", "--selector"])
+            .arg(format!("{APP_NAME_LABEL}={app_name}"));
+        let releases = match Self::run_helm(command, "list", "", None).await {
+            Ok(releases) => releases,
+            // Without Helm companions, the Helm executable is optional and cannot have installed
+            // any release.
+            Err(err)
+                if self
+                    .config
+                    .helm_releases(app_name)
+                    .map_or(false, |releases| releases.is_empty()) =>
+            {
+                debug!("Cannot list the Helm releases of {app_name}: {err}");
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+
+        for release in releases.lines().map(str::trim).filter(|r| !r.is_empty()) {
+            debug!("Uninstall Helm release {release} of {app_name}");
+
+            let mut command = self.helm_command(app_name);
+            command.args(["uninstall", "--wait", release]);
+            Self::run_helm(command, "uninstall", release, None).await?;
+        }
+        Ok(())
+    }
+
     async fn create_service_from(
         &self,
         deployment: V1Deployment,
@@ -139,13 +622,28 @@ impl KubernetesInfrastructure {
             )),
             ..Default::default()
         };
-        if let Some(pod) = Api::<V1Pod>::namespaced(self.client().await?, &namespace)
+        let pods = Api::<V1Pod>::namespaced(self.client().await?, &namespace)
             .list(&p)
             .await?
-            .items
-            .into_iter()
-            .next()
-        {
+            .items;
+        builder = with_restart_info(builder, &pods);
+
+        if pods.iter().any(is_pending) {
+            // Without the permission to list events, the conditions of the pods are reported.
+            let events = match Api::<V1Event>::namespaced(self.client().await?, &namespace)
+                .list(&ListParams::default().fields("involvedObject.kind=Pod,type=Warning"))
+                .await
+            {
+                Ok(events) => events.items,
+                Err(err) => {
+                    debug!("Cannot list the events of pending pods: {}", err);
+                    Vec::new()
+                }
+            };
+            builder = builder.pending_reasons(pending_reasons(&pods, &events));
+        }
+
+        if let Some(pod) = pods.into_iter().next() {
             if let Some(container) = pod.spec.as_ref().and_then(|spec| spec.containers.first()) {
                 builder = builder.started_at(
                     pod.status
@@ -179,17 +677,20 @@ impl KubernetesInfrastructure {
         &self,
         app_name: &AppName,
     ) -> Result<Vec<Service>, KubernetesInfrastructureError> {
+        let p = ListParams {
+            label_selector: Some(format!("{APP_NAME_LABEL}={app_name}")),
+            ..Default::default()
+        };
+
         let mut services = Vec::new();
-        let futures = Api::<V1Deployment>::namespaced(
-            self.client().await?,
-            &app_name.to_rfc1123_namespace_id(),
-        )
-        .list(&Default::default())
-        .await?
-        .items
-        .into_iter()
-        .map(|deployment| self.create_service_from(deployment))
-        .collect::<Vec<_>>();
+        let futures =
+            Api::<V1Deployment>::namespaced(self.client().await?, &self.namespace(app_name))
+                .list(&p)
+                .await?
+                .items
+                .into_iter()
+                .map(|deployment| self.create_service_from(deployment))
+                .collect::<Vec<_>>();
 
         for create_service_result in join_all(futures).await {
             let service = match create_service_result {
@@ -212,20 +713,19 @@ impl KubernetesInfrastructure {
         service_name: &str,
     ) -> Result<Option<Service>, KubernetesInfrastructureError> {
         let p = ListParams {
-            label_selector: Some(format!("{SERVICE_NAME_LABEL}={service_name}")),
+            label_selector: Some(format!(
+                "{APP_NAME_LABEL}={app_name},{SERVICE_NAME_LABEL}={service_name}"
+            )),
             ..Default::default()
         };
 
-        match Api::<V1Deployment>::namespaced(
-            self.client().await?,
-            &app_name.to_rfc1123_namespace_id(),
-        )
-        .list(&p)
-        .await?
-        .items
-        .into_iter()
-        .next()
-        .map(|deployment| self.create_service_from(deployment))
+        match Api::<V1Deployment>::namespaced(self.client().await?, &self.namespace(app_name))
+            .list(&p)
+            .await?
+            .items
+            .into_iter()
+            .next()
+            .map(|deployment| self.create_service_from(deployment))
         {
             None => Ok(None),
             Some(service) => Ok(Some(service.await?)),
@@ -239,19 +739,36 @@ impl KubernetesInfrastructure {
     ) -> Result<(), KubernetesInfrastructureError> {
         let client = self.client().await?;
 
-        Api::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id())
-            .create(&PostParams::default(), &service_payload(app_name, service))
+        self.create_or_patch(app_name, service_payload(app_name, &self.config, service))
             .await?;
 
-        Api::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id())
+        if let Some(ServiceMesh::Istio(istio)) = service_mesh(&self.config) {
+            return self
+                .create_or_patch(
+                    app_name,
+                    virtual_service_payload(app_name, &self.config, istio, service),
+                )
+                .await;
+        }
+
+        Api::namespaced(client.clone(), &self.namespace(app_name))
             .create(
                 &PostParams::default(),
-                &ingress_route_payload(app_name, service),
+                &ingress_route_payload(app_name, &self.config, service),
             )
             .await?;
 
-        for middleware in middleware_payload(app_name, service) {
-            Api::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id())
+        if let Some(app_host) = app_host(&self.config) {
+            Api::namespaced(client.clone(), &self.namespace(app_name))
+                .create(
+                    &PostParams::default(),
+                    &host_ingress_route_payload(app_name, &self.config, app_host, service),
+                )
+                .await?;
+        }
+
+        for middleware in middleware_payload(app_name, &self.config, service) {
+            Api::namespaced(client.clone(), &self.namespace(app_name))
                 .create(&PostParams::default(), &middleware)
                 .await?;
         }
@@ -262,12 +779,21 @@ impl KubernetesInfrastructure {
     async fn create_namespace_if_necessary(
         &self,
         app_name: &AppName,
+        namespace_annotations: &BTreeMap<String, String>,
     ) -> Result<(), KubernetesInfrastructureError> {
+        if !self.manages_namespaces() {
+            if !namespace_annotations.is_empty() {
+                warn!(
+                    "Ignoring the namespace annotations of {} because its namespace is not managed by PREvant.",
+                    app_name
+                );
+            }
+            return Ok(());
+        }
+
+        let payload = namespace_payload(app_name, &self.config, namespace_annotations);
         match Api::all(self.client().await?)
-            .create(
-                &PostParams::default(),
-                &namespace_payload(app_name, &self.config),
-            )
+            .create(&PostParams::default(), &payload)
             .await
         {
             Ok(result) => {
@@ -280,10 +806,26 @@ impl KubernetesInfrastructure {
                 );
                 Ok(())
             }
-            Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => {
+            Err(KubeError::Api(ErrorResponse { code, .. }))
+                if code == 409 && namespace_annotations.is_empty() =>
+            {
                 debug!("Namespace {} already exists.", app_name);
                 Ok(())
             }
+            Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => {
+                debug!(
+                    "Namespace {} already exists. Updating its annotations.",
+                    app_name
+                );
+                Api::<V1Namespace>::all(self.client().await?)
+                    .patch(
+                        &self.namespace(app_name),
+                        &PatchParams::default(),
+                        &Patch::Merge(payload),
+                    )
+                    .await?;
+                Ok(())
+            }
             Err(e) => {
                 error!("Cannot deploy namespace: {}", e);
                 Err(e.into())
@@ -291,6 +833,157 @@ impl KubernetesInfrastructure {
         }
     }
 
+    /// Lists the apps by the namespaces that PREvant created for them. The app metadata is
+    /// stored in the annotations of the namespaces.
+    async fn app_names_of_namespaces(
+        &self,
+        client: Client,
+    ) -> Result<Vec<(AppName, BTreeMap<String, String>)>, KubernetesInfrastructureError> {
+        Ok(Api::<V1Namespace>::all(client)
+            .list(&ListParams {
+                label_selector: Some(APP_NAME_LABEL.to_string()),
+                ..Default::default()
+            })
+            .await?
+            .iter()
+            .filter(|ns| {
+                ns.status
+                    .as_ref()
+                    .and_then(|status| status.phase.as_ref())
+                    .map(|phase| phase.as_str())
+                    != Some("Terminating")
+            })
+            .filter_map(|ns| {
+                let app_name =
+                    AppName::from_str(ns.metadata.labels.as_ref()?.get(APP_NAME_LABEL)?).ok()?;
+                let app_metadata = ns
+                    .metadata
+                    .annotations
+                    .iter()
+                    .flatten()
+                    .filter_map(|(key, value)| {
+                        key.strip_prefix(APP_METADATA_PREFIX)
+                            .map(|key| (key.to_string(), value.clone()))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                Some((app_name, app_metadata))
+            })
+            .collect::<Vec<_>>())
+    }
+
+    /// Lists the apps by the labels of their deployments if the namespaces are not managed by
+    /// PREvant. Deployments in other namespaces than the app's namespace are ignored.
+    async fn app_names_of_deployments(
+        &self,
+        client: Client,
+    ) -> Result<Vec<(AppName, BTreeMap<String, String>)>, KubernetesInfrastructureError> {
+        let mut app_names = Api::<V1Deployment>::all(client)
+            .list(&ListParams {
+                label_selector: Some(APP_NAME_LABEL.to_string()),
+                ..Default::default()
+            })
+            .await?
+            .iter()
+            .filter_map(|deployment| {
+                let app_name = AppName::from_str(deployment.labels().get(APP_NAME_LABEL)?).ok()?;
+                (deployment.namespace()? == self.namespace(&app_name)).then_some(app_name)
+            })
+            .collect::<Vec<_>>();
+        app_names.sort_unstable_by_key(|app_name| app_name.to_string());
+        app_names.dedup();
+
+        Ok(app_names
+            .into_iter()
+            .map(|app_name| (app_name, BTreeMap::new()))
+            .collect())
+    }
+
+    /// Deletes the resources labeled with the name of an app that shares its namespace or whose
+    /// namespace is not managed by PREvant. The resources are deleted concurrently, at most
+    /// [`MAX_PARALLEL_DELETIONS`] at a time, so that large apps are torn down quickly. Returns the
+    /// result of each deleted resource, including the resources that could not be deleted.
+    async fn delete_resources_of_app(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<ResourceDeletion>, KubernetesInfrastructureError> {
+        let mut deletions = Vec::new();
+        deletions.extend(self.labeled_deletions::<V1Deployment>(app_name).await?);
+        deletions.extend(
+            self.labeled_deletions::<HorizontalPodAutoscaler>(app_name)
+                .await?,
+        );
+        deletions.extend(self.labeled_deletions::<V1Service>(app_name).await?);
+        if let Some(ServiceMesh::Istio(_)) = service_mesh(&self.config) {
+            deletions.extend(self.labeled_deletions::<VirtualService>(app_name).await?);
+        } else {
+            deletions.extend(self.labeled_deletions::<IngressRoute>(app_name).await?);
+            deletions.extend(self.labeled_deletions::<Middleware>(app_name).await?);
+        }
+        if app_host(&self.config).map_or(false, |app_host| app_host.cert_manager().is_some()) {
+            deletions.extend(self.labeled_deletions::<Certificate>(app_name).await?);
+        }
+        deletions.extend(self.labeled_deletions::<NetworkPolicy>(app_name).await?);
+        deletions.extend(self.labeled_deletions::<V1Secret>(app_name).await?);
+        deletions.extend(
+            self.labeled_deletions::<PersistentVolumeClaim>(app_name)
+                .await?,
+        );
+        deletions.extend(self.labeled_deletions::<RoleBinding>(app_name).await?);
+        deletions.extend(self.labeled_deletions::<Role>(app_name).await?);
+        deletions.extend(self.labeled_deletions::<V1ServiceAccount>(app_name).await?);
+        deletions.extend(self.labeled_deletions::<V1Job>(app_name).await?);
+        deletions.extend(self.labeled_deletions::<V1Pod>(app_name).await?);
+
+        Ok(stream::iter(deletions)
+            .buffer_unordered(MAX_PARALLEL_DELETIONS)
+            .collect::<Vec<ResourceDeletion>>()
+            .await)
+    }
+
+    /// Lists the resources of the given kind that belong to the app and returns a future for
+    /// each of them that deletes it.
+    async fn labeled_deletions<K>(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Vec<BoxFuture<'static, ResourceDeletion>>, KubernetesInfrastructureError>
+    where
+        K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+            + Clone
+            + DeserializeOwned
+            + std::fmt::Debug
+            + Send
+            + 'static,
+    {
+        let api = Api::<K>::namespaced(self.client().await?, &self.namespace(app_name));
+        let resources = api
+            .list(&ListParams {
+                label_selector: Some(format!("{APP_NAME_LABEL}={app_name}")),
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(resources
+            .into_iter()
+            .map(|resource| {
+                let api = api.clone();
+                let name = resource.name_any();
+                async move {
+                    let result = ignore_not_found(
+                        api.delete(&name, &DeleteParams::background())
+                            .await
+                            .map(|_| ()),
+                    );
+                    ResourceDeletion {
+                        kind: K::kind(&()).to_string(),
+                        name,
+                        result,
+                    }
+                }
+                .boxed()
+            })
+            .collect())
+    }
+
     async fn create_pull_secrets_if_necessary(
         &self,
         app_name: &AppName,
@@ -311,10 +1004,10 @@ impl KubernetesInfrastructure {
             return Ok(());
         }
 
-        match Api::namespaced(self.client().await?, &app_name.to_rfc1123_namespace_id())
+        match Api::namespaced(self.client().await?, &self.namespace(app_name))
             .create(
                 &PostParams::default(),
-                &image_pull_secret_payload(app_name, registries_and_credentials),
+                &image_pull_secret_payload(app_name, &self.config, registries_and_credentials),
             )
             .await
         {
@@ -344,30 +1037,52 @@ impl KubernetesInfrastructure {
         app_name: &AppName,
         service: &'a DeployableService,
         container_config: &ContainerConfig,
+        sibling_host_aliases: &[HostAlias],
     ) -> Result<&'a DeployableService, KubernetesInfrastructureError> {
         if let Some(files) = service.files() {
             self.deploy_secret(app_name, service, files).await?;
         }
 
+        if service.service_account().is_some() {
+            self.deploy_service_account(app_name, service).await?;
+        }
+
+        self.deploy_horizontal_pod_autoscaler(app_name, service)
+            .await?;
+
+        self.deploy_alias_services(app_name, service).await?;
+
         let client = self.client().await?;
 
         let persistence_volume_map = self
             .create_persistent_volume_claim(app_name, service)
             .await?;
 
-        match Api::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id())
-            .create(
-                &PostParams::default(),
-                &deployment_payload(
-                    app_name,
-                    service,
-                    container_config,
-                    self.config
-                        .registry_credentials(&service.image().registry().unwrap_or_default())
-                        .is_some(),
-                    &persistence_volume_map,
-                ),
-            )
+        let mut payload = deployment_payload(
+            app_name,
+            &self.config,
+            service,
+            container_config,
+            self.config
+                .registry_credentials(&service.image().registry().unwrap_or_default())
+                .is_some(),
+            &persistence_volume_map,
+        );
+        if let Some(pod_spec) = payload
+            .spec
+            .as_mut()
+            .and_then(|spec| spec.template.spec.as_mut())
+            .filter(|_| !sibling_host_aliases.is_empty())
+        {
+            pod_spec
+                .host_aliases
+                .get_or_insert_with(Vec::new)
+                .extend(sibling_host_aliases.iter().cloned());
+        }
+        let payload = self.post_render(app_name, payload).await?;
+
+        match Api::namespaced(client.clone(), &self.namespace(app_name))
+            .create(&PostParams::default(), &payload)
             .await
         {
             Ok(result) => {
@@ -380,41 +1095,366 @@ impl KubernetesInfrastructure {
                 );
                 self.post_service_and_custom_resource_definitions(app_name, service)
                     .await?;
+                self.delete_adopted_deployments(app_name, service).await?;
                 Ok(service)
             }
 
             Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => {
-                Api::<V1Deployment>::namespaced(
-                    client.clone(),
-                    &app_name.to_rfc1123_namespace_id(),
-                )
-                .patch(
-                    &format!(
-                        "{}-{}-deployment",
-                        app_name.to_rfc1123_namespace_id(),
-                        service.service_name()
+                Api::<V1Deployment>::namespaced(client.clone(), &self.namespace(app_name))
+                    .patch(
+                        &format!(
+                            "{}-{}-deployment",
+                            app_name.to_rfc1123_namespace_id(),
+                            service.service_name()
+                        ),
+                        &PatchParams::default(),
+                        &Patch::Merge(&payload),
+                    )
+                    .await?;
+                Ok(service)
+            }
+            Err(e) => {
+                error!("Cannot deploy service: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Returns the names of the running pods of the service.
+    async fn running_pods_of_service(
+        &self,
+        pods: &Api<V1Pod>,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Vec<String>, KubernetesInfrastructureError> {
+        Ok(pods
+            .list(&ListParams::default().labels(&format!(
+                "{}={},{}={}",
+                APP_NAME_LABEL, app_name, SERVICE_NAME_LABEL, service_name
+            )))
+            .await?
+            .into_iter()
+            .filter(|pod| {
+                pod.status
+                    .as_ref()
+                    .and_then(|status| status.phase.as_deref())
+                    == Some("Running")
+            })
+            .map(|pod| pod.name_any())
+            .collect())
+    }
+
+    /// Creates the Kubernetes services of the app before its deployments if the app shares its
+    /// namespace with other apps. Then, the pods can be given host aliases for the bare names of
+    /// their siblings, see [`sibling_host_aliases`]. Returns no host aliases if each app has its
+    /// own namespace because the bare names resolve anyway.
+    async fn deploy_sibling_services(
+        &self,
+        app_name: &AppName,
+        services: &[DeployableService],
+    ) -> Result<Vec<HostAlias>, KubernetesInfrastructureError> {
+        if !self.shares_namespace() {
+            return Ok(Vec::new());
+        }
+
+        for service in services {
+            self.create_or_patch(app_name, service_payload(app_name, &self.config, service))
+                .await?;
+        }
+
+        let k8s_services =
+            Api::<V1Service>::namespaced(self.client().await?, &self.namespace(app_name))
+                .list(&ListParams {
+                    label_selector: Some(format!("{APP_NAME_LABEL}={app_name}")),
+                    ..Default::default()
+                })
+                .await?;
+        Ok(sibling_host_aliases(&k8s_services.items))
+    }
+
+    /// Deletes the adopted deployments of the service once PREvant has deployed the service itself.
+    async fn delete_adopted_deployments(
+        &self,
+        app_name: &AppName,
+        service: &DeployableService,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        let deployments =
+            Api::<V1Deployment>::namespaced(self.client().await?, &self.namespace(app_name));
+        let deployment_name = format!(
+            "{}-{}-deployment",
+            app_name.to_rfc1123_namespace_id(),
+            service.service_name()
+        );
+
+        for deployment in deployments
+            .list(&ListParams {
+                label_selector: Some(format!(
+                    "{APP_NAME_LABEL}={app_name},{SERVICE_NAME_LABEL}={}",
+                    service.service_name()
+                )),
+                ..Default::default()
+            })
+            .await?
+            .items
+            .into_iter()
+            .filter(|deployment| deployment.name_any() != deployment_name)
+        {
+            debug!(
+                "Replacing the adopted deployment {} of {app_name}",
+                deployment.name_any()
+            );
+            ignore_not_found(
+                deployments
+                    .delete(&deployment.name_any(), &DeleteParams::background())
+                    .await
+                    .map(|_| ()),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits until the instances of a service with a startup probe are ready, at most as long as
+    /// the startup probe permits, so that slow services are not reported as deployed while they
+    /// are still booting.
+    async fn wait_until_started(
+        &self,
+        app_name: &AppName,
+        service: &DeployableService,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        let Some(startup_probe) = service.startup_probe() else {
+            return Ok(());
+        };
+
+        let condition = DeploymentAvailable {
+            api: Api::namespaced(self.client().await?, &self.namespace(app_name)),
+            name: format!(
+                "{}-{}-deployment",
+                app_name.to_rfc1123_namespace_id(),
+                service.service_name()
+            ),
+        };
+
+        match wait_for(
+            &condition,
+            startup_probe.max_startup_duration(),
+            Duration::from_secs(2),
+        )
+        .await
+        {
+            Ok(()) => Ok(()),
+            Err(WaitError::Timeout { .. }) => {
+                warn!(
+                    "Service {} of app {} has not started within {:?}",
+                    service.service_name(),
+                    app_name,
+                    startup_probe.max_startup_duration()
+                );
+                Ok(())
+            }
+            Err(error) => Err(KubernetesInfrastructureError::ConditionNotFulfilled { error }),
+        }
+    }
+
+    /// Deploys the service account of the service with a role and a role binding that grant the
+    /// requested permissions within the namespace of the app.
+    async fn deploy_service_account(
+        &self,
+        app_name: &AppName,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        debug!(
+            "Deploying service account for {} in app {}",
+            service_config.service_name(),
+            app_name
+        );
+
+        self.create_or_patch(
+            app_name,
+            service_account_payload(app_name, &self.config, service_config),
+        )
+        .await?;
+        self.create_or_patch(
+            app_name,
+            role_payload(app_name, &self.config, service_config),
+        )
+        .await?;
+        self.create_or_patch(
+            app_name,
+            role_binding_payload(app_name, &self.config, service_config),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deploys the horizontal pod autoscaler of an autoscaled service or deletes the autoscaler
+    /// of a previous deployment if the service is not autoscaled anymore.
+    async fn deploy_horizontal_pod_autoscaler(
+        &self,
+        app_name: &AppName,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        match service_config.autoscale() {
+            Some(autoscale) => {
+                self.create_or_patch(
+                    app_name,
+                    horizontal_pod_autoscaler_payload(
+                        app_name,
+                        &self.config,
+                        service_config,
+                        autoscale,
                     ),
+                )
+                .await
+            }
+            None => {
+                match Api::<HorizontalPodAutoscaler>::namespaced(
+                    self.client().await?,
+                    &self.namespace(app_name),
+                )
+                .delete(
+                    &horizontal_pod_autoscaler_name(app_name, service_config),
+                    &DeleteParams::default(),
+                )
+                .await
+                {
+                    Ok(_) => Ok(()),
+                    Err(KubeError::Api(ErrorResponse { code, .. })) if code == 404 => Ok(()),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+
+    /// Restricts the outbound traffic of the app to its egress allowlist or lifts the restriction
+    /// if the app has no allowlist (anymore).
+    async fn apply_egress_network_policy(
+        &self,
+        app_name: &AppName,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        let Some(allowlist) = self.config.egress_allowlist(app_name) else {
+            return match Api::<NetworkPolicy>::namespaced(
+                self.client().await?,
+                &self.namespace(app_name),
+            )
+            .delete(
+                &egress_network_policy_name(app_name),
+                &DeleteParams::default(),
+            )
+            .await
+            {
+                Ok(_) => Ok(()),
+                // Without egress rules, PREvant does not need the permission to manage network
+                // policies.
+                Err(KubeError::Api(ErrorResponse { code, .. })) if code == 404 || code == 403 => {
+                    Ok(())
+                }
+                Err(e) => Err(e.into()),
+            };
+        };
+
+        let mut allowed_cidrs = allowlist.cidrs().clone();
+        for host in allowlist.hosts() {
+            allowed_cidrs.extend(resolve_host(host).await);
+        }
+
+        self.create_or_patch(
+            app_name,
+            egress_network_policy_payload(app_name, &self.config, &allowed_cidrs),
+        )
+        .await
+    }
+
+    async fn create_or_patch<K>(
+        &self,
+        app_name: &AppName,
+        payload: K,
+    ) -> Result<(), KubernetesInfrastructureError>
+    where
+        K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+            + Clone
+            + DeserializeOwned
+            + Serialize
+            + std::fmt::Debug,
+    {
+        let api = Api::<K>::namespaced(self.client().await?, &self.namespace(app_name));
+        let payload = self.post_render(app_name, payload).await?;
+
+        match api.create(&PostParams::default(), &payload).await {
+            Ok(result) => {
+                debug!("Successfully deployed {}", result.name_any());
+                Ok(())
+            }
+            Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => {
+                api.patch(
+                    &payload.name_any(),
                     &PatchParams::default(),
-                    &Patch::Merge(deployment_payload(
-                        app_name,
-                        service,
-                        container_config,
-                        self.config
-                            .registry_credentials(&service.image().registry().unwrap_or_default())
-                            .is_some(),
-                        &persistence_volume_map,
-                    )),
+                    &Patch::Merge(&payload),
                 )
                 .await?;
-                Ok(service)
+                Ok(())
             }
             Err(e) => {
-                error!("Cannot deploy service: {}", e);
+                error!("Cannot deploy {}: {}", payload.name_any(), e);
                 Err(e.into())
             }
         }
     }
 
+    /// Creates the services of the aliases and deletes the services of the aliases that the
+    /// service does not declare anymore. Other services with the same names are never touched.
+    async fn deploy_alias_services(
+        &self,
+        app_name: &AppName,
+        service_config: &ServiceConfig,
+    ) -> Result<(), KubernetesInfrastructureError> {
+        let client = self.client().await?;
+        let api = Api::<V1Service>::namespaced(client, &self.namespace(app_name));
+
+        let payloads = alias_service_payloads(app_name, &self.config, service_config);
+        for payload in &payloads {
+            let name = payload.name_any();
+            match api.create(&PostParams::default(), payload).await {
+                Ok(_) => {}
+                Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => {
+                    let existing = api.get(&name).await?;
+                    if existing.labels().get(ALIAS_OF_LABEL) != Some(service_config.service_name())
+                        || existing.labels().get(APP_NAME_LABEL) != Some(&app_name.to_string())
+                    {
+                        return Err(KubernetesInfrastructureError::AliasConflict {
+                            service_name: service_config.service_name().clone(),
+                            alias: name,
+                        });
+                    }
+                    api.patch(&name, &PatchParams::default(), &Patch::Merge(payload))
+                        .await?;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let stale_aliases = api
+            .list(&ListParams::default().labels(&format!(
+                "{APP_NAME_LABEL}={app_name},{ALIAS_OF_LABEL}={}",
+                service_config.service_name()
+            )))
+            .await?
+            .into_iter()
+            .map(|service| service.name_any())
+            .filter(|name| !payloads.iter().any(|payload| &payload.name_any() == name));
+        for name in stale_aliases {
+            debug!("Deleting stale alias {name} of {app_name}");
+            ignore_not_found(
+                api.delete(&name, &DeleteParams::default())
+                    .await
+                    .map(|_| ()),
+            )?;
+        }
+
+        Ok(())
+    }
+
     async fn deploy_secret(
         &self,
         app_name: &AppName,
@@ -429,10 +1469,10 @@ impl KubernetesInfrastructure {
 
         let client = self.client().await?;
 
-        match Api::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id())
+        match Api::namespaced(client.clone(), &self.namespace(app_name))
             .create(
                 &PostParams::default(),
-                &secrets_payload(app_name, service_config, volumes),
+                &secrets_payload(app_name, &self.config, service_config, volumes),
             )
             .await
         {
@@ -447,7 +1487,7 @@ impl KubernetesInfrastructure {
                 Ok(())
             }
             Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => {
-                Api::<V1Secret>::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id())
+                Api::<V1Secret>::namespaced(client.clone(), &self.namespace(app_name))
                     .patch(
                         &format!(
                             "{}-{}-secret",
@@ -455,7 +1495,12 @@ impl KubernetesInfrastructure {
                             service_config.service_name()
                         ),
                         &PatchParams::default(),
-                        &Patch::Merge(secrets_payload(app_name, service_config, volumes)),
+                        &Patch::Merge(secrets_payload(
+                            app_name,
+                            &self.config,
+                            service_config,
+                            volumes,
+                        )),
                     )
                     .await?;
                 Ok(())
@@ -477,6 +1522,10 @@ impl KubernetesInfrastructure {
         let Runtime::Kubernetes(k8s_config) = self.config.runtime_config() else {
             return Ok(None);
         };
+        if let Some(ServiceMesh::Istio(_)) = k8s_config.service_mesh() {
+            // Istio's gateway routes the requests, thus there is no ingress route to build upon.
+            return Ok(None);
+        }
 
         let storage_size = k8s_config.storage_config().storage_size();
         let storage_class = match k8s_config.storage_config().storage_class() {
@@ -495,7 +1544,7 @@ impl KubernetesInfrastructure {
 
         let mut persistent_volume_map = HashMap::new();
         let existing_pvc: Api<PersistentVolumeClaim> =
-            Api::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id());
+            Api::namespaced(client.clone(), &self.namespace(app_name));
 
         for declared_volume in service.declared_volumes() {
             let pvc_list_params = ListParams {
@@ -514,7 +1563,7 @@ impl KubernetesInfrastructure {
             let fetched_pvc = existing_pvc.list(&pvc_list_params).await?.items;
 
             if fetched_pvc.is_empty() {
-                match Api::namespaced(client.clone(), &app_name.to_rfc1123_namespace_id())
+                match Api::namespaced(client.clone(), &self.namespace(app_name))
                     .create(
                         &PostParams::default(),
                         &persistent_volume_claim_payload(
@@ -574,36 +1623,185 @@ impl KubernetesInfrastructure {
             Err(err) => Err(err.into()),
         }
     }
+
+    /// Checks which API group provides Traefik's custom resources. PREvant creates its
+    /// ingress routes in the API group of [`IngressRoute`].
+    async fn check_traefik_crds(&self, client: Client) -> SelfCheck {
+        const NAME: &str = "traefik CRDs";
+
+        let crds = Api::<CustomResourceDefinition>::all(client);
+        let mut available_groups = Vec::new();
+        for group in ["traefik.containo.us", "traefik.io"] {
+            match crds.get_opt(&format!("ingressroutes.{group}")).await {
+                Ok(Some(_)) => available_groups.push(group),
+                Ok(None) => {}
+                Err(err) => return SelfCheck::failed(NAME, err.to_string()),
+            }
+        }
+
+        let required_group = IngressRoute::group(&());
+        if available_groups.contains(&required_group.as_ref()) {
+            SelfCheck::passed(
+                NAME,
+                format!("Available in API group {}", available_groups.join(", ")),
+            )
+        } else if available_groups.is_empty() {
+            SelfCheck::failed(NAME, "Traefik's CRDs are not installed")
+        } else {
+            SelfCheck::failed(
+                NAME,
+                format!(
+                    "PREvant requires API group {required_group} but only {} is available",
+                    available_groups.join(", ")
+                ),
+            )
+        }
+    }
+
+    /// Checks whether Istio's custom resources are installed because PREvant routes the requests
+    /// through virtual services if Istio is the service mesh.
+    async fn check_istio_crds(&self, client: Client) -> SelfCheck {
+        const NAME: &str = "istio CRDs";
+
+        let name = format!("virtualservices.{}", VirtualService::group(&()));
+        match Api::<CustomResourceDefinition>::all(client)
+            .get_opt(&name)
+            .await
+        {
+            Ok(Some(_)) => SelfCheck::passed(NAME, format!("{name} is available")),
+            Ok(None) => SelfCheck::failed(NAME, format!("{name} is not installed")),
+            Err(err) => SelfCheck::failed(NAME, err.to_string()),
+        }
+    }
+
+    /// Asks the API server whether PREvant's service account is allowed to manage the resources
+    /// of the apps.
+    async fn check_permissions(&self, client: Client) -> SelfCheck {
+        const NAME: &str = "permissions";
+
+        let mut required_permissions = vec![
+            ("apps", "deployments", "create"),
+            ("apps", "deployments", "patch"),
+            ("apps", "deployments", "delete"),
+            ("", "services", "create"),
+            ("", "services", "delete"),
+            ("", "secrets", "create"),
+            ("", "persistentvolumeclaims", "create"),
+            ("", "pods", "list"),
+        ];
+        if let Some(ServiceMesh::Istio(_)) = service_mesh(&self.config) {
+            required_permissions.push(("networking.istio.io", "virtualservices", "create"));
+            required_permissions.push(("networking.istio.io", "virtualservices", "patch"));
+        } else {
+            required_permissions.push(("traefik.containo.us", "ingressroutes", "create"));
+            required_permissions.push(("traefik.containo.us", "middlewares", "create"));
+        }
+        if app_host(&self.config).map_or(false, |app_host| app_host.cert_manager().is_some()) {
+            required_permissions.push(("cert-manager.io", "certificates", "create"));
+            required_permissions.push(("cert-manager.io", "certificates", "delete"));
+        }
+        if self.config.has_egress_rules() {
+            required_permissions.push(("networking.k8s.io", "networkpolicies", "create"));
+            required_permissions.push(("networking.k8s.io", "networkpolicies", "patch"));
+            required_permissions.push(("networking.k8s.io", "networkpolicies", "delete"));
+        }
+        let namespace = if self.manages_namespaces() {
+            required_permissions.push(("", "namespaces", "create"));
+            required_permissions.push(("", "namespaces", "delete"));
+            None
+        } else {
+            Some(self.namespace(&AppName::master()))
+        };
+
+        let reviews = Api::<SelfSubjectAccessReview>::all(client);
+        let mut denied_permissions = Vec::new();
+        for (group, resource, verb) in required_permissions {
+            let review = SelfSubjectAccessReview {
+                spec: SelfSubjectAccessReviewSpec {
+                    resource_attributes: Some(ResourceAttributes {
+                        group: Some(String::from(group)),
+                        resource: Some(String::from(resource)),
+                        verb: Some(String::from(verb)),
+                        namespace: namespace.clone(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            match reviews.create(&PostParams::default(), &review).await {
+                Ok(review) if review.status.map_or(false, |status| status.allowed) => {}
+                Ok(_) => denied_permissions.push(format!("{verb} {resource}")),
+                Err(err) => return SelfCheck::failed(NAME, err.to_string()),
+            }
+        }
+
+        if denied_permissions.is_empty() {
+            SelfCheck::passed(NAME, "")
+        } else {
+            SelfCheck::failed(
+                NAME,
+                format!("Not allowed to {}", denied_permissions.join(", ")),
+            )
+        }
+    }
+
+    async fn check_storage_class(&self, client: Client) -> SelfCheck {
+        const NAME: &str = "storage class";
+
+        let Runtime::Kubernetes(k8s_config) = self.config.runtime_config() else {
+            return SelfCheck::skipped(NAME, "");
+        };
+
+        match k8s_config.storage_config().storage_class() {
+            Some(storage_class) => match Api::<StorageClass>::all(client)
+                .get_opt(storage_class)
+                .await
+            {
+                Ok(Some(_)) => SelfCheck::passed(NAME, format!("Using {storage_class}")),
+                Ok(None) => SelfCheck::failed(
+                    NAME,
+                    format!("The storage class {storage_class} is missing"),
+                ),
+                Err(err) => SelfCheck::failed(NAME, err.to_string()),
+            },
+            None => match self.fetch_default_storage_class().await {
+                Ok(storage_class) => SelfCheck::passed(
+                    NAME,
+                    format!(
+                        "Using the default storage class {}",
+                        storage_class.metadata.name.unwrap_or_default()
+                    ),
+                ),
+                Err(err) => SelfCheck::failed(NAME, err.to_string()),
+            },
+        }
+    }
 }
 
 #[async_trait]
 impl Infrastructure for KubernetesInfrastructure {
     async fn get_services(&self) -> Result<MultiMap<AppName, Service>, Error> {
         let client = self.client().await?;
-        let app_names = Api::<V1Namespace>::all(client.clone())
-            .list(&ListParams {
-                label_selector: Some(APP_NAME_LABEL.to_string()),
-                ..Default::default()
-            })
-            .await?
-            .iter()
-            .filter(|ns| {
-                ns.status
-                    .as_ref()
-                    .and_then(|status| status.phase.as_ref())
-                    .map(|phase| phase.as_str())
-                    != Some("Terminating")
-            })
-            .filter_map(|ns| {
-                AppName::from_str(ns.metadata.labels.as_ref()?.get(APP_NAME_LABEL)?).ok()
-            })
-            .collect::<Vec<_>>();
+        let app_names = if self.manages_namespaces() {
+            self.app_names_of_namespaces(client).await?
+        } else {
+            self.app_names_of_deployments(client).await?
+        };
 
         let mut apps = MultiMap::new();
 
-        for app_name in app_names {
+        for (app_name, app_metadata) in app_names {
             let services = self.get_services_of_app(&app_name).await?;
-            apps.insert_many(app_name, services);
+            for service in services {
+                apps.insert(
+                    app_name.clone(),
+                    ServiceBuilder::from(service)
+                        .app_metadata(app_metadata.clone())
+                        .build()?,
+                );
+            }
         }
 
         Ok(apps)
@@ -618,13 +1816,77 @@ impl Infrastructure for KubernetesInfrastructure {
         let services = deployment_unit.services();
         let app_name = deployment_unit.app_name();
 
-        self.create_namespace_if_necessary(app_name).await?;
+        // Deploying services without their middlewares, e.g. without the forward authentication,
+        // would expose them silently.
+        if let Some(ServiceMesh::Istio(_)) = service_mesh(&self.config) {
+            if let Some((service, middleware)) = services.iter().find_map(|service| {
+                middlewares_unsupported_by_istio(service)
+                    .into_iter()
+                    .next()
+                    .map(|middleware| (service, middleware))
+            }) {
+                return Err(KubernetesInfrastructureError::UnsupportedMiddleware {
+                    service_name: service.service_name().clone(),
+                    middleware: match middleware {
+                        TraefikMiddleware::Ref(name) => name.clone(),
+                        TraefikMiddleware::Spec { name, .. } => name.clone(),
+                    },
+                }
+                .into());
+            }
+        }
+
+        // A role in a shared namespace would grant access to the resources of the other apps.
+        if self.shares_namespace() {
+            if let Some(service) = services.iter().find(|service| {
+                service
+                    .service_account()
+                    .map_or(false, |service_account| !service_account.rules().is_empty())
+            }) {
+                return Err(KubernetesInfrastructureError::RulesInSharedNamespace {
+                    service_name: service.service_name().clone(),
+                }
+                .into());
+            }
+        }
+
+        let mut namespace_annotations = deployment_unit.namespace_annotations().clone();
+        namespace_annotations.extend(
+            deployment_unit
+                .app_metadata()
+                .iter()
+                .map(|(key, value)| (format!("{APP_METADATA_PREFIX}{key}"), value.clone())),
+        );
+
+        self.create_namespace_if_necessary(app_name, &namespace_annotations)
+            .await?;
         self.create_pull_secrets_if_necessary(app_name, services)
             .await?;
+        self.apply_egress_network_policy(app_name).await?;
+        if let Some((app_host, cert_manager)) =
+            app_host(&self.config).and_then(|app_host| Some((app_host, app_host.cert_manager()?)))
+        {
+            self.create_or_patch(
+                app_name,
+                certificate_payload(app_name, &self.config, app_host, cert_manager),
+            )
+            .await?;
+        }
+
+        // The services usually connect to the companions of the charts and custom resources
+        // right away.
+        self.install_helm_releases(app_name, deployment_unit.helm_releases())
+            .await?;
+        self.apply_custom_resources(app_name, deployment_unit.custom_resources())
+            .await?;
+
+        let sibling_host_aliases = self.deploy_sibling_services(app_name, services).await?;
 
         let futures = services
             .iter()
-            .map(|service| self.deploy_service(app_name, service, container_config))
+            .map(|service| {
+                self.deploy_service(app_name, service, container_config, &sibling_host_aliases)
+            })
             .collect::<Vec<_>>();
 
         for deploy_result in join_all(futures).await {
@@ -632,6 +1894,14 @@ impl Infrastructure for KubernetesInfrastructure {
             deploy_result?;
         }
 
+        let futures = services
+            .iter()
+            .map(|service| self.wait_until_started(app_name, service))
+            .collect::<Vec<_>>();
+        for wait_result in join_all(futures).await {
+            wait_result?;
+        }
+
         Ok(self.get_services_of_app(app_name).await?)
     }
 
@@ -645,12 +1915,20 @@ impl Infrastructure for KubernetesInfrastructure {
             return Ok(services);
         }
 
-        Api::<V1Namespace>::all(self.client().await?)
-            .delete(
-                &app_name.to_rfc1123_namespace_id(),
-                &DeleteParams::default(),
-            )
-            .await?;
+        self.uninstall_helm_releases(app_name).await?;
+        self.delete_custom_resources(app_name).await?;
+
+        if self.manages_namespaces() {
+            ignore_not_found(
+                Api::<V1Namespace>::all(self.client().await?)
+                    .delete(&self.namespace(app_name), &DeleteParams::default())
+                    .await
+                    .map(|_| ()),
+            )?;
+        } else {
+            let deletions = self.delete_resources_of_app(app_name).await?;
+            report_deletions(app_name, &deletions)?;
+        }
 
         Ok(services)
     }
@@ -661,19 +1939,19 @@ impl Infrastructure for KubernetesInfrastructure {
         service_name: &str,
         from: &Option<DateTime<FixedOffset>>,
         limit: usize,
+        filter: &LogFilter,
     ) -> Result<Option<Vec<(DateTime<FixedOffset>, String)>>, Error> {
         let p = ListParams {
-            label_selector: Some(format!("{SERVICE_NAME_LABEL}={service_name}",)),
+            label_selector: Some(format!(
+                "{APP_NAME_LABEL}={app_name},{SERVICE_NAME_LABEL}={service_name}"
+            )),
             ..Default::default()
         };
-        let pod = match Api::<V1Pod>::namespaced(
-            self.client().await?,
-            &app_name.to_rfc1123_namespace_id(),
-        )
-        .list(&p)
-        .await?
-        .into_iter()
-        .next()
+        let pod = match Api::<V1Pod>::namespaced(self.client().await?, &self.namespace(app_name))
+            .list(&p)
+            .await?
+            .into_iter()
+            .next()
         {
             Some(pod) => pod,
             None => {
@@ -700,64 +1978,501 @@ impl Infrastructure for KubernetesInfrastructure {
             ..Default::default()
         };
 
-        let logs =
-            Api::<V1Pod>::namespaced(self.client().await?, &app_name.to_rfc1123_namespace_id())
-                .logs(&pod.metadata.name.unwrap(), &p)
-                .await?;
+        let logs = Api::<V1Pod>::namespaced(self.client().await?, &self.namespace(app_name))
+            .logs(&pod.metadata.name.unwrap(), &p)
+            .await?;
+
+        let logs = logs
+            .split('\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let mut iter = line.splitn(2, ' ');
+                let timestamp = iter.next().expect(
+                    "This should never happen: kubernetes should return timestamps, separated by space",
+                );
+
+                let datetime =
+                    DateTime::parse_from_rfc3339(timestamp).expect("Expecting a valid timestamp");
+
+                let mut log_line: String = iter.collect::<Vec<&str>>().join(" ");
+                log_line.push('\n');
+                (datetime, log_line)
+            })
+            .filter(|(_, line)| filter.matches(line))
+            // Unfortunately,  API does not support head (also like docker, cf. https://github.com/moby/moby/issues/13096)
+            // Until then we have to skip these log messages which is super slow…
+            .take(limit)
+            .collect();
+
+        Ok(Some(logs))
+    }
+
+    async fn change_status(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        status: ServiceStatus,
+    ) -> Result<Option<Service>, Error> {
+        let (service, replicas) = match self.get_service_of_app(app_name, service_name).await? {
+            Some(service) if service.status() == &status => return Ok(None),
+            Some(service) => match status {
+                ServiceStatus::Running => {
+                    // Resuming a service that has been deployed without replicas starts one.
+                    let replicas = service.config().replicas().unwrap_or(1).max(1);
+                    (service, replicas)
+                }
+                ServiceStatus::Paused => (service, 0),
+            },
+            None => return Ok(None),
+        };
+
+        let deployments =
+            Api::<V1Deployment>::namespaced(self.client().await?, &self.namespace(app_name));
+        if is_adopted(app_name, &service) {
+            deployments
+                .patch(
+                    service.id(),
+                    &PatchParams::default(),
+                    &Patch::Merge(serde_json::json!({ "spec": { "replicas": replicas } })),
+                )
+                .await?;
+        } else {
+            deployments
+                .patch(
+                    &format!(
+                        "{}-{}-deployment",
+                        app_name.to_rfc1123_namespace_id(),
+                        service_name
+                    ),
+                    &PatchParams::default(),
+                    &Patch::Merge(deployment_replicas_payload(
+                        app_name,
+                        &self.config,
+                        &service,
+                        replicas,
+                    )),
+                )
+                .await?;
+        }
+
+        Ok(Some(service))
+    }
+
+    async fn adopt_services(&self, app_name: &AppName) -> Result<Vec<Service>, Error> {
+        if !self.manages_namespaces() {
+            bail!("Adopting apps requires namespaces that are managed by PREvant");
+        }
+
+        let client = self.client().await?;
+        let namespace = self.namespace(app_name);
+        let namespaces = Api::<V1Namespace>::all(client.clone());
+        let Some(ns) = namespaces.get_opt(&namespace).await? else {
+            return Ok(Vec::new());
+        };
+        if ns.annotations().get(ADOPTABLE_LABEL).map(String::as_str) != Some("true") {
+            warn!("Refusing to adopt the namespace {namespace} of {app_name} because it is not annotated with {ADOPTABLE_LABEL}=true");
+            return Ok(Vec::new());
+        }
+
+        let deployments = Api::<V1Deployment>::namespaced(client.clone(), &namespace);
+        let k8s_services = Api::<V1Service>::namespaced(client, &namespace);
+        let unmanaged_services = k8s_services
+            .list(&ListParams::default())
+            .await?
+            .items
+            .into_iter()
+            .filter(|service| !service.labels().contains_key(APP_NAME_LABEL))
+            .collect::<Vec<_>>();
+
+        for deployment in deployments
+            .list(&ListParams::default())
+            .await?
+            .items
+            .iter()
+            .filter(|deployment| !deployment.labels().contains_key(APP_NAME_LABEL))
+        {
+            let deployment_name = deployment.name_any();
+            let Some(payload) = adopted_deployment_payload(app_name, deployment) else {
+                warn!("Cannot adopt the deployment {deployment_name} of {app_name} without a valid image");
+                continue;
+            };
+
+            deployments
+                .patch(
+                    &deployment_name,
+                    &PatchParams::default(),
+                    &Patch::Merge(payload),
+                )
+                .await?;
+
+            let pod_labels = deployment
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.template.metadata.as_ref())
+                .and_then(|metadata| metadata.labels.clone())
+                .unwrap_or_default();
+            for service in unmanaged_services.iter().filter(|service| {
+                service
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.selector.as_ref())
+                    .filter(|selector| !selector.is_empty())
+                    .map(|selector| {
+                        selector
+                            .iter()
+                            .all(|(key, value)| pod_labels.get(key) == Some(value))
+                    })
+                    .unwrap_or(false)
+            }) {
+                k8s_services
+                    .patch(
+                        &service.name_any(),
+                        &PatchParams::default(),
+                        &Patch::Merge(adopted_service_payload(app_name, &deployment_name)),
+                    )
+                    .await?;
+            }
+        }
+
+        namespaces
+            .patch(
+                &namespace,
+                &PatchParams::default(),
+                &Patch::Merge(serde_json::json!({
+                    "metadata": { "labels": { (APP_NAME_LABEL): app_name } }
+                })),
+            )
+            .await?;
+
+        Ok(self.get_services_of_app(app_name).await?)
+    }
+
+    async fn restart_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Option<Service>, Error> {
+        let Some(service) = self.get_service_of_app(app_name, service_name).await? else {
+            return Ok(None);
+        };
+
+        let deployments =
+            Api::<V1Deployment>::namespaced(self.client().await?, &self.namespace(app_name));
+        if is_adopted(app_name, &service) {
+            deployments
+                .patch(
+                    service.id(),
+                    &PatchParams::default(),
+                    &Patch::Merge(serde_json::json!({
+                        "spec": { "template": { "metadata": { "annotations": {
+                            "kubectl.kubernetes.io/restartedAt": Utc::now().to_rfc3339()
+                        } } } }
+                    })),
+                )
+                .await?;
+        } else {
+            deployments
+                .patch(
+                    &format!(
+                        "{}-{}-deployment",
+                        app_name.to_rfc1123_namespace_id(),
+                        service_name
+                    ),
+                    &PatchParams::default(),
+                    &Patch::Merge(deployment_restart_payload(
+                        app_name,
+                        &self.config,
+                        &service,
+                        Utc::now(),
+                    )),
+                )
+                .await?;
+        }
+
+        Ok(Some(service))
+    }
+
+    async fn update_files(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<Option<Service>, Error> {
+        let Some(service) = self.get_service_of_app(app_name, service_name).await? else {
+            return Ok(None);
+        };
+        if is_adopted(app_name, &service) {
+            bail!(
+                "The files of service {} have not been deployed by PREvant",
+                service_name
+            );
+        }
+
+        let client = self.client().await?;
+        let namespace = self.namespace(app_name);
+        let deployment = Api::<V1Deployment>::namespaced(client.clone(), &namespace)
+            .get(service.id())
+            .await?;
+        let patch = secret_files_patch(&deployment, files).map_err(|path| {
+            format_err!(
+                "The file {} is not mounted into service {}, add it by patching the service",
+                path.display(),
+                service_name
+            )
+        })?;
+
+        debug!(
+            "Update files {:?} of service {} in namespace {}",
+            files.keys().collect::<Vec<_>>(),
+            service_name,
+            namespace
+        );
+        Api::<V1Secret>::namespaced(client, &namespace)
+            .patch(
+                &format!(
+                    "{}-{}-secret",
+                    app_name.to_rfc1123_namespace_id(),
+                    service_name
+                ),
+                &PatchParams::default(),
+                &Patch::Merge(patch),
+            )
+            .await?;
+
+        Ok(Some(service))
+    }
+
+    async fn run_smoke_test(
+        &self,
+        app_name: &AppName,
+        image: &Image,
+        command: &[String],
+        timeout: Duration,
+    ) -> Result<SmokeTestOutcome, Error> {
+        let use_image_pull_secret = image
+            .registry()
+            .and_then(|registry| self.config.registry_credentials(&registry))
+            .is_some();
+
+        let pods = Api::<V1Pod>::namespaced(self.client().await?, &self.namespace(app_name));
+        let pod = pods
+            .create(
+                &PostParams::default(),
+                &smoke_test_pod_payload(
+                    app_name,
+                    &self.config,
+                    image,
+                    command,
+                    use_image_pull_secret,
+                ),
+            )
+            .await?;
+        let pod_name = pod
+            .metadata
+            .name
+            .ok_or_else(|| format_err!("The smoke test pod of {} has no name", app_name))?;
+
+        let condition = PodSucceeded {
+            api: pods.clone(),
+            name: pod_name.clone(),
+        };
+        let passed = match wait_for(&condition, timeout, Duration::from_secs(2)).await {
+            Ok(()) => Ok(true),
+            Err(WaitError::Timeout { .. }) => {
+                warn!("Smoke test {} of app {} timed out", image, app_name);
+                Ok(false)
+            }
+            Err(WaitError::Failed { .. }) => Ok(false),
+            Err(error) => Err(error),
+        };
+
+        let logs = pods
+            .logs(&pod_name, &LogParams::default())
+            .await
+            .unwrap_or_default();
+
+        // The pods of smoke tests and seed jobs are deleted even if waiting for them failed,
+        // otherwise they would remain in the namespace of the app until the app is deleted.
+        ignore_not_found(
+            pods.delete(&pod_name, &DeleteParams::default())
+                .await
+                .map(|_| ()),
+        )?;
+
+        Ok(SmokeTestOutcome::new(passed?, logs))
+    }
+
+    async fn build_image(
+        &self,
+        app_name: &AppName,
+        build: &SourceBuild,
+        destination: &Image,
+        config: &BuildsConfig,
+    ) -> Result<SmokeTestOutcome, Error> {
+        self.create_namespace_if_necessary(app_name, &BTreeMap::new())
+            .await?;
+
+        let push_credentials = destination.registry().and_then(|registry| {
+            self.config
+                .registry_credentials(&registry)
+                .map(|credentials| (registry, credentials))
+        });
+        let git_credentials = build
+            .url()
+            .host_str()
+            .and_then(|host| self.config.git_config().credentials(host));
+        let use_push_credentials = push_credentials.is_some();
+        let use_git_credentials = git_credentials.is_some();
+        if use_push_credentials || use_git_credentials {
+            self.create_or_patch(
+                app_name,
+                build_secret_payload(app_name, &self.config, push_credentials, git_credentials),
+            )
+            .await?;
+        }
+
+        let jobs = Api::<V1Job>::namespaced(self.client().await?, &self.namespace(app_name));
+        let job = jobs
+            .create(
+                &PostParams::default(),
+                &build_job_payload(
+                    app_name,
+                    &self.config,
+                    build,
+                    destination,
+                    config,
+                    use_push_credentials,
+                    use_git_credentials,
+                ),
+            )
+            .await?;
+        let job_name = job.name_any();
+
+        let condition = JobCompleted {
+            api: jobs.clone(),
+            name: job_name.clone(),
+        };
+        let passed = match wait_for(&condition, config.timeout(), Duration::from_secs(5)).await {
+            Ok(()) => true,
+            Err(WaitError::Timeout { .. }) => {
+                warn!(
+                    "Build of {} for app {} timed out",
+                    build.service_name(),
+                    app_name
+                );
+                false
+            }
+            Err(WaitError::Failed { .. }) => false,
+            Err(error) => return Err(error.into()),
+        };
 
-        let logs = logs
-            .split('\n')
-            .enumerate()
-            // Unfortunately,  API does not support head (also like docker, cf. https://github.com/moby/moby/issues/13096)
-            // Until then we have to skip these log messages which is super slow…
-            .filter(move |(index, _)| index < &limit)
-            .filter(|(_, line)| !line.is_empty())
-            .map(|(_, line)| {
-                let mut iter = line.splitn(2, ' ');
-                let timestamp = iter.next().expect(
-                    "This should never happen: kubernetes should return timestamps, separated by space",
+        let pods = Api::<V1Pod>::namespaced(self.client().await?, &self.namespace(app_name));
+        let mut logs = String::new();
+        if let Some(pod) = pods
+            .list(&ListParams::default().labels(&format!("job-name={job_name}")))
+            .await?
+            .into_iter()
+            .next()
+        {
+            for container in ["checkout", "build"] {
+                let params = LogParams {
+                    container: Some(container.to_string()),
+                    ..Default::default()
+                };
+                logs.push_str(
+                    &pods
+                        .logs(&pod.name_any(), &params)
+                        .await
+                        .unwrap_or_default(),
                 );
+            }
+        }
 
-                let datetime =
-                    DateTime::parse_from_rfc3339(timestamp).expect("Expecting a valid timestamp");
+        jobs.delete(&job_name, &DeleteParams::background()).await?;
 
-                let mut log_line: String = iter.collect::<Vec<&str>>().join(" ");
-                log_line.push('\n');
-                (datetime, log_line)
-            })
-            .collect();
+        Ok(SmokeTestOutcome::new(passed, logs))
+    }
 
-        Ok(Some(logs))
+    async fn exec_in_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        command: &[String],
+        timeout: Duration,
+    ) -> Result<SmokeTestOutcome, Error> {
+        let pods = Api::<V1Pod>::namespaced(self.client().await?, &self.namespace(app_name));
+        let pod_name = self
+            .running_pods_of_service(&pods, app_name, service_name)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                format_err!(
+                    "Service {} of app {} is not running",
+                    service_name,
+                    app_name
+                )
+            })?;
+
+        exec_in_pod(&pods, &pod_name, service_name, command, timeout).await
     }
 
-    async fn change_status(
+    async fn signal_service(
         &self,
         app_name: &AppName,
         service_name: &str,
-        status: ServiceStatus,
-    ) -> Result<Option<Service>, Error> {
-        let (service, replicas) = match self.get_service_of_app(app_name, service_name).await? {
-            Some(service) if service.status() == &status => return Ok(None),
-            Some(service) => match status {
-                ServiceStatus::Running => (service, 1),
-                ServiceStatus::Paused => (service, 0),
-            },
-            None => return Ok(None),
-        };
+        signal: &str,
+        files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<SmokeTestOutcome, Error> {
+        let pods = Api::<V1Pod>::namespaced(self.client().await?, &self.namespace(app_name));
+        let pod_names = self
+            .running_pods_of_service(&pods, app_name, service_name)
+            .await?;
+        if pod_names.is_empty() {
+            bail!(
+                "Service {} of app {} is not running",
+                service_name,
+                app_name
+            );
+        }
 
-        Api::<V1Deployment>::namespaced(self.client().await?, &app_name.to_rfc1123_namespace_id())
-            .patch(
-                &format!(
-                    "{}-{}-deployment",
-                    app_name.to_rfc1123_namespace_id(),
-                    service_name
-                ),
-                &PatchParams::default(),
-                &Patch::Merge(deployment_replicas_payload(app_name, &service, replicas)),
+        let command = vec![
+            String::from("kill"),
+            String::from("-s"),
+            signal.to_string(),
+            String::from("1"),
+        ];
+        let mut logs = String::new();
+        for pod_name in pod_names {
+            // The kubelet syncs the secret volumes periodically, thus, the signal must not be
+            // sent before the pod sees the new contents.
+            let condition = FilesProjected {
+                api: pods.clone(),
+                name: pod_name.clone(),
+                container: service_name.to_string(),
+                files: files.clone(),
+            };
+            if let Err(error) =
+                wait_for(&condition, FILES_PROJECTION_TIMEOUT, Duration::from_secs(2)).await
+            {
+                return Ok(SmokeTestOutcome::new(false, logs + &error.to_string()));
+            }
+
+            let outcome = exec_in_pod(
+                &pods,
+                &pod_name,
+                service_name,
+                &command,
+                Duration::from_secs(10),
             )
             .await?;
+            logs.push_str(outcome.logs());
+            if !outcome.passed() {
+                return Ok(SmokeTestOutcome::new(false, logs));
+            }
+        }
 
-        Ok(Some(service))
+        Ok(SmokeTestOutcome::new(true, logs))
     }
 
     async fn base_traefik_ingress_route(&self) -> Result<Option<TraefikIngressRoute>, Error> {
@@ -827,7 +2542,13 @@ impl Infrastructure for KubernetesInfrastructure {
                     for s in &route.services {
                         if let Some(name) = &service.metadata.name {
                             if &s.name == name {
-                                return Ok(TraefikIngressRoute::try_from(r).ok());
+                                return match TraefikIngressRoute::try_from(r) {
+                                    Ok(route) => Ok(Some(route)),
+                                    Err(err) => {
+                                        warn!("Ignoring the routing of PREvant: {err}");
+                                        Ok(None)
+                                    }
+                                };
                             }
                         }
                     }
@@ -837,6 +2558,397 @@ impl Infrastructure for KubernetesInfrastructure {
 
         Ok(None)
     }
+
+    async fn cluster_capacity(
+        &self,
+        deployment_unit: &DeploymentUnit,
+    ) -> Result<Option<ClusterCapacity>, Error> {
+        let client = self.client().await?;
+        let nodes = Api::<V1Node>::all(client.clone())
+            .list(&ListParams::default())
+            .await?;
+        let pods = Api::<V1Pod>::all(client)
+            .list(&ListParams::default().fields("status.phase!=Succeeded,status.phase!=Failed"))
+            .await?;
+
+        Ok(Some(free_capacity(
+            &nodes.items,
+            &pods.items,
+            deployment_unit.app_name(),
+        )))
+    }
+
+    async fn self_check(&self) -> Vec<SelfCheck> {
+        let client = match self.client().await {
+            Ok(client) => client,
+            Err(err) => return vec![SelfCheck::failed("connectivity", err.to_string())],
+        };
+
+        match client.apiserver_version().await {
+            Ok(version) => {
+                let mut checks = vec![SelfCheck::passed(
+                    "connectivity",
+                    format!("Connected to Kubernetes {}", version.git_version),
+                )];
+                checks.push(match service_mesh(&self.config) {
+                    Some(ServiceMesh::Istio(_)) => self.check_istio_crds(client.clone()).await,
+                    _ => self.check_traefik_crds(client.clone()).await,
+                });
+                checks.push(self.check_permissions(client.clone()).await);
+                checks.push(self.check_storage_class(client).await);
+                checks
+            }
+            Err(err) => vec![SelfCheck::failed("connectivity", err.to_string())],
+        }
+    }
+}
+
+/// The maximum number of resources that are deleted concurrently when an app is torn down.
+const MAX_PARALLEL_DELETIONS: usize = 10;
+
+/// The maximum time to wait until the kubelet has synced updated files into the pods. The kubelet
+/// syncs secret volumes about once a minute.
+const FILES_PROJECTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Executes the `command` in the container of the pod and returns whether the command terminated
+/// successfully within the `timeout`, together with its output.
+pub(super) async fn exec_in_pod(
+    pods: &Api<V1Pod>,
+    pod_name: &str,
+    container: &str,
+    command: &[String],
+    timeout: Duration,
+) -> Result<SmokeTestOutcome, Error> {
+    let mut process = pods
+        .exec(
+            pod_name,
+            command.to_vec(),
+            &AttachParams::default()
+                .container(container)
+                .stdout(true)
+                .stderr(true),
+        )
+        .await?;
+
+    let execution = async {
+        let mut stdout_output = String::new();
+        let mut stderr_output = String::new();
+        let (stdout, stderr) = (process.stdout(), process.stderr());
+        let read_stdout = async {
+            match stdout {
+                Some(mut stdout) => stdout.read_to_string(&mut stdout_output).await.map(|_| ()),
+                None => Ok(()),
+            }
+        };
+        let read_stderr = async {
+            match stderr {
+                Some(mut stderr) => stderr.read_to_string(&mut stderr_output).await.map(|_| ()),
+                None => Ok(()),
+            }
+        };
+        let (stdout_result, stderr_result) = tokio::join!(read_stdout, read_stderr);
+        stdout_result?;
+        stderr_result?;
+
+        let status = match process.take_status() {
+            Some(status) => status.await,
+            None => None,
+        };
+        let passed = status.and_then(|status| status.status).as_deref() == Some("Success");
+        Ok::<_, Error>((passed, stdout_output + &stderr_output))
+    };
+
+    match tokio::time::timeout(timeout, execution).await {
+        Ok(result) => {
+            let (passed, output) = result?;
+            Ok(SmokeTestOutcome::new(passed, output))
+        }
+        Err(_) => {
+            warn!("Executing {:?} in pod {} timed out", command, pod_name);
+            Ok(SmokeTestOutcome::new(
+                false,
+                format!("{:?} timed out after {:?}", command, timeout),
+            ))
+        }
+    }
+}
+
+/// The result of deleting a single resource of an app.
+#[derive(Debug)]
+struct ResourceDeletion {
+    kind: String,
+    name: String,
+    result: Result<(), KubeError>,
+}
+
+impl std::fmt::Display for ResourceDeletion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.result {
+            Ok(()) => write!(f, "Deleted {} {}", self.kind, self.name),
+            Err(err) => write!(f, "Cannot delete {} {}: {}", self.kind, self.name, err),
+        }
+    }
+}
+
+/// Resolves the host to the CIDRs of its IP addresses. Hosts that cannot be resolved are
+/// skipped, i.e. they remain unreachable.
+async fn resolve_host(host: &str) -> Vec<String> {
+    let address = format!("{host}:0");
+    let resolved = tokio::task::spawn_blocking(move || {
+        address
+            .to_socket_addrs()
+            .map(|addresses| addresses.collect::<Vec<_>>())
+    })
+    .await
+    .map_err(|err| err.to_string())
+    .and_then(|addresses| addresses.map_err(|err| err.to_string()));
+
+    match resolved {
+        Ok(addresses) => addresses
+            .into_iter()
+            .map(|address| match address.ip() {
+                ip @ IpAddr::V4(_) => format!("{ip}/32"),
+                ip @ IpAddr::V6(_) => format!("{ip}/128"),
+            })
+            .collect(),
+        Err(err) => {
+            warn!("Cannot resolve {host} for the egress allowlist: {err}");
+            Vec::new()
+        }
+    }
+}
+
+/// Resources that are already gone do not need to be deleted.
+fn ignore_not_found(result: Result<(), KubeError>) -> Result<(), KubeError> {
+    match result {
+        Err(KubeError::Api(ErrorResponse { code, .. })) if code == 404 => Ok(()),
+        result => result,
+    }
+}
+
+/// Logs the result of each deleted resource of the app and fails with all resources that could not
+/// be deleted.
+fn report_deletions(
+    app_name: &AppName,
+    deletions: &[ResourceDeletion],
+) -> Result<(), KubernetesInfrastructureError> {
+    for deletion in deletions {
+        match deletion.result {
+            Ok(()) => debug!("{} of {}", deletion, app_name),
+            Err(_) => warn!("{} of {}", deletion, app_name),
+        }
+    }
+
+    match failed_deletions(deletions) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn failed_deletions(deletions: &[ResourceDeletion]) -> Option<KubernetesInfrastructureError> {
+    let failures = deletions
+        .iter()
+        .filter(|deletion| deletion.result.is_err())
+        .map(|deletion| deletion.to_string())
+        .collect::<Vec<_>>();
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(KubernetesInfrastructureError::UnexpectedError {
+            internal_message: failures.join("; "),
+        })
+    }
+}
+
+/// Subtracts the requests of the pods from the allocatable resources of the nodes that are ready
+/// and accept new pods. The pods of the app are ignored because a deployment replaces them.
+fn free_capacity(nodes: &[V1Node], pods: &[V1Pod], app_name: &AppName) -> ClusterCapacity {
+    let mut free = nodes
+        .iter()
+        .filter(|node| is_schedulable(node))
+        .filter_map(|node| {
+            let allocatable = node
+                .status
+                .as_ref()?
+                .allocatable
+                .as_ref()?
+                .iter()
+                .filter_map(|(resource, quantity)| {
+                    Some((resource.clone(), parse_quantity(&quantity.0)?))
+                })
+                .collect::<BTreeMap<_, _>>();
+            Some((node.metadata.name.clone()?, allocatable))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    for pod in pods {
+        let is_pod_of_app = pod
+            .labels()
+            .get(APP_NAME_LABEL)
+            .map_or(false, |name| name == &app_name.to_string());
+        if is_pod_of_app {
+            continue;
+        }
+
+        let Some(spec) = &pod.spec else { continue };
+        let Some(node) = spec.node_name.as_ref().and_then(|name| free.get_mut(name)) else {
+            continue;
+        };
+
+        for requests in spec
+            .containers
+            .iter()
+            .filter_map(|container| container.resources.as_ref()?.requests.as_ref())
+        {
+            for (resource, quantity) in requests {
+                if let (Some(available), Some(requested)) =
+                    (node.get_mut(resource), parse_quantity(&quantity.0))
+                {
+                    *available = (*available - requested).max(0.0);
+                }
+            }
+        }
+    }
+
+    free.into_iter()
+        .fold(ClusterCapacity::new(), |capacity, (name, resources)| {
+            capacity.with_node(name, resources)
+        })
+}
+
+/// Nodes that are cordoned, not ready, or tainted against new pods cannot run PREvant's pods
+/// because they don't tolerate any taints.
+fn is_schedulable(node: &V1Node) -> bool {
+    let spec = node.spec.as_ref();
+    if spec.and_then(|spec| spec.unschedulable) == Some(true) {
+        return false;
+    }
+
+    let is_tainted = spec
+        .and_then(|spec| spec.taints.as_ref())
+        .map_or(false, |taints| {
+            taints
+                .iter()
+                .any(|taint| taint.effect == "NoSchedule" || taint.effect == "NoExecute")
+        });
+    if is_tainted {
+        return false;
+    }
+
+    node.status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .map_or(false, |conditions| {
+            conditions
+                .iter()
+                .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+        })
+}
+
+fn is_pending(pod: &V1Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.phase.as_deref())
+        == Some("Pending")
+}
+
+/// Collects why the pending pods cannot start: the message of the scheduler, e.g. `0/3 nodes are
+/// available: 3 Insufficient cpu.`, and the warnings that have been recorded for the pods, e.g.
+/// about volumes that cannot be mounted. Repeated messages are reported once.
+fn pending_reasons(pods: &[V1Pod], events: &[V1Event]) -> Vec<String> {
+    let mut reasons = Vec::<String>::new();
+    let mut add_reason = |reason: Option<&String>| {
+        if let Some(reason) = reason.map(|reason| reason.trim()) {
+            if !reason.is_empty() && !reasons.iter().any(|r| r == reason) {
+                reasons.push(reason.to_string());
+            }
+        }
+    };
+
+    for pod in pods.iter().filter(|pod| is_pending(pod)) {
+        let unscheduled = pod
+            .status
+            .as_ref()
+            .and_then(|status| status.conditions.as_ref())
+            .into_iter()
+            .flatten()
+            .filter(|condition| condition.type_ == "PodScheduled" && condition.status == "False");
+        for condition in unscheduled {
+            add_reason(condition.message.as_ref());
+        }
+
+        let mut events_of_pod = events
+            .iter()
+            .filter(|event| event.involved_object.name == pod.metadata.name)
+            .collect::<Vec<_>>();
+        events_of_pod.sort_by_key(|event| event.last_timestamp.as_ref().map(|time| time.0));
+        for event in events_of_pod {
+            add_reason(event.message.as_ref());
+        }
+    }
+
+    reasons
+}
+
+/// Adds the restart count of all pods of a service and the last termination, or the reason why
+/// a container is waiting, e.g. `CrashLoopBackOff`, of the most restarted container.
+fn with_restart_info(mut builder: ServiceBuilder, pods: &[V1Pod]) -> ServiceBuilder {
+    let container_statuses = pods
+        .iter()
+        .filter_map(|pod| pod.status.as_ref())
+        .filter_map(|status| status.container_statuses.as_ref())
+        .filter_map(|statuses| statuses.first())
+        .collect::<Vec<_>>();
+
+    if container_statuses.is_empty() {
+        return builder;
+    }
+
+    let restart_count = container_statuses
+        .iter()
+        .map(|status| status.restart_count.max(0) as u32)
+        .sum::<u32>();
+    builder = builder.restart_count(restart_count);
+
+    let Some(status) = container_statuses
+        .iter()
+        .max_by_key(|status| status.restart_count)
+    else {
+        return builder;
+    };
+
+    let last_terminated = status
+        .last_state
+        .as_ref()
+        .and_then(|state| state.terminated.as_ref());
+    if let Some(terminated) = last_terminated {
+        builder = builder.last_exit_code(terminated.exit_code as i64);
+    }
+
+    let waiting_reason = status
+        .state
+        .as_ref()
+        .and_then(|state| state.waiting.as_ref())
+        .and_then(|waiting| waiting.reason.clone());
+    if let Some(reason) =
+        waiting_reason.or_else(|| last_terminated.and_then(|terminated| terminated.reason.clone()))
+    {
+        builder = builder.last_state(reason);
+    }
+
+    builder
+}
+
+/// Adopted deployments keep their name and selector until PREvant redeploys their service, see
+/// [`Infrastructure::adopt_services`].
+fn is_adopted(app_name: &AppName, service: &Service) -> bool {
+    service.id()
+        != &format!(
+            "{}-{}-deployment",
+            app_name.to_rfc1123_namespace_id(),
+            service.service_name()
+        )
 }
 
 impl TryFrom<V1Deployment> for ServiceBuilder {
@@ -874,6 +2986,15 @@ impl TryFrom<V1Deployment> for ServiceBuilder {
                 .unwrap_or(ServiceStatus::Paused),
         );
 
+        if let Some(image_digest) = deployment
+            .metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(IMAGE_DIGEST_LABEL))
+        {
+            builder = builder.image_digest(image_digest.clone());
+        }
+
         Ok(builder)
     }
 }
@@ -926,6 +3047,29 @@ impl TryFrom<&V1Deployment> for ServiceConfig {
                 config.set_container_type(lb.parse::<ContainerType>()?);
             }
 
+            config.set_replicated_from(annotations.get(REPLICATED_FROM_LABEL).cloned());
+
+            if let Some(aliases) = annotations.get(ALIASES_LABEL) {
+                config.set_aliases(aliases.split(',').map(String::from).collect());
+            }
+
+            if let Some(depends_on) = annotations.get(DEPENDS_ON_LABEL) {
+                config.set_depends_on(depends_on.split(',').map(String::from).collect());
+            }
+
+            if let Some(port) = annotations
+                .get(FIXED_PORT_LABEL)
+                .and_then(|port| port.parse::<u16>().ok())
+            {
+                config.set_fixed_port(port);
+            }
+
+            config.set_replicas(
+                annotations
+                    .get(REPLICAS_LABEL)
+                    .and_then(|replicas| replicas.parse::<u32>().ok()),
+            );
+
             Ok(config)
         } else {
             Err(KubernetesInfrastructureError::UnexpectedError {
@@ -1007,6 +3151,223 @@ mod tests {
         }};
     }
 
+    #[test]
+    fn should_report_failed_deletions_but_ignore_missing_resources() {
+        let api_error = |code: u16| -> Result<(), KubeError> {
+            Err(KubeError::Api(ErrorResponse {
+                status: String::from("Failure"),
+                message: String::from("failed"),
+                reason: String::new(),
+                code,
+            }))
+        };
+        let deletion = |name: &str, result: Result<(), KubeError>| ResourceDeletion {
+            kind: String::from("Deployment"),
+            name: String::from(name),
+            result: ignore_not_found(result),
+        };
+
+        let deletions = vec![
+            deletion("master-nginx", Ok(())),
+            deletion("master-db", api_error(404)),
+        ];
+        assert_eq!(failed_deletions(&deletions), None);
+
+        let deletions = vec![
+            deletion("master-nginx", Ok(())),
+            deletion("master-db", api_error(500)),
+        ];
+        let message = match failed_deletions(&deletions) {
+            Some(KubernetesInfrastructureError::UnexpectedError { internal_message }) => {
+                internal_message
+            }
+            err => panic!("Unexpected result {:?}", err),
+        };
+        assert!(message.starts_with("Cannot delete Deployment master-db"));
+        assert!(!message.contains("master-nginx"));
+    }
+
+    #[test]
+    fn should_report_all_failed_deletions_of_an_app() {
+        let deletion = |kind: &str, name: &str, code: Option<u16>| ResourceDeletion {
+            kind: String::from(kind),
+            name: String::from(name),
+            result: match code {
+                None => Ok(()),
+                Some(code) => Err(KubeError::Api(ErrorResponse {
+                    status: String::from("Failure"),
+                    message: String::from("failed"),
+                    reason: String::new(),
+                    code,
+                })),
+            },
+        };
+
+        let deletions = vec![
+            deletion("Deployment", "master-nginx", None),
+            deletion("Service", "master-nginx", Some(403)),
+            deletion("PersistentVolumeClaim", "master-db", Some(500)),
+        ];
+
+        let message = match report_deletions(&AppName::master(), &deletions) {
+            Err(KubernetesInfrastructureError::UnexpectedError { internal_message }) => {
+                internal_message
+            }
+            result => panic!("Unexpected result {:?}", result),
+        };
+        assert!(message.contains("Cannot delete Service master-nginx"));
+        assert!(message.contains("Cannot delete PersistentVolumeClaim master-db"));
+        assert!(!message.contains("Deployment"));
+        assert!(report_deletions(&AppName::master(), &deletions[..1]).is_ok());
+    }
+
+    #[test]
+    fn should_compute_free_capacity_of_schedulable_nodes() {
+        let node = |name: &str, ready: &str, taint: Option<&str>| {
+            serde_json::from_value::<V1Node>(serde_json::json!({
+              "metadata": { "name": name },
+              "spec": {
+                "taints": taint.map(|effect| vec![serde_json::json!({
+                  "key": "node-role.kubernetes.io/control-plane",
+                  "effect": effect
+                })])
+              },
+              "status": {
+                "allocatable": { "cpu": "4", "memory": "8Gi" },
+                "conditions": [{ "type": "Ready", "status": ready }]
+              }
+            }))
+            .unwrap()
+        };
+        let pod = |app_name: &str, node: &str, memory: &str| {
+            serde_json::from_value::<V1Pod>(serde_json::json!({
+              "metadata": { "labels": { (APP_NAME_LABEL): app_name } },
+              "spec": {
+                "nodeName": node,
+                "containers": [{
+                  "name": "service",
+                  "resources": { "requests": { "memory": memory } }
+                }]
+              }
+            }))
+            .unwrap()
+        };
+
+        let capacity = free_capacity(
+            &[
+                node("worker-1", "True", None),
+                node("worker-2", "False", None),
+                node("control-plane", "True", Some("NoSchedule")),
+            ],
+            &[
+                pod("master", "worker-1", "2Gi"),
+                pod("feature-1", "worker-1", "4Gi"),
+            ],
+            &AppName::from_str("feature-1").unwrap(),
+        );
+
+        assert_eq!(
+            capacity,
+            ClusterCapacity::new().with_node(
+                String::from("worker-1"),
+                BTreeMap::from([
+                    (String::from("cpu"), 4.0),
+                    (String::from("memory"), 6.0 * 1024_f64.powi(3)),
+                ])
+            )
+        );
+    }
+
+    #[test]
+    fn should_collect_reasons_of_pending_pods() {
+        let pod = |name: &str, phase: &str| {
+            serde_json::from_value::<V1Pod>(serde_json::json!({
+              "metadata": { "name": name },
+              "status": {
+                "phase": phase,
+                "conditions": [{
+                  "type": "PodScheduled",
+                  "status": "False",
+                  "message": "0/3 nodes are available: 3 Insufficient cpu."
+                }]
+              }
+            }))
+            .unwrap()
+        };
+        let event = |pod_name: &str, message: &str| {
+            serde_json::from_value::<V1Event>(serde_json::json!({
+              "metadata": { "name": format!("{pod_name}.event") },
+              "involvedObject": { "kind": "Pod", "name": pod_name },
+              "type": "Warning",
+              "reason": "FailedScheduling",
+              "message": message
+            }))
+            .unwrap()
+        };
+
+        let reasons = pending_reasons(
+            &[
+                pod("master-db-1", "Pending"),
+                pod("master-db-2", "Pending"),
+                pod("master-api-1", "Running"),
+            ],
+            &[
+                event(
+                    "master-db-1",
+                    "0/3 nodes are available: 3 pod has unbound immediate PersistentVolumeClaims.",
+                ),
+                event("master-api-1", "Readiness probe failed"),
+            ],
+        );
+
+        assert_eq!(
+            reasons,
+            vec![
+                String::from("0/3 nodes are available: 3 Insufficient cpu."),
+                String::from(
+                    "0/3 nodes are available: 3 pod has unbound immediate PersistentVolumeClaims."
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_add_restart_info_of_crashing_pods() {
+        let pod = |restart_count: i32| {
+            serde_json::from_value::<V1Pod>(serde_json::json!({
+              "status": {
+                "containerStatuses": [{
+                  "name": "nginx",
+                  "image": "nginx",
+                  "imageID": "",
+                  "ready": false,
+                  "restartCount": restart_count,
+                  "state": { "waiting": { "reason": "CrashLoopBackOff" } },
+                  "lastState": { "terminated": { "exitCode": 137, "reason": "OOMKilled" } }
+                }]
+              }
+            }))
+            .unwrap()
+        };
+
+        let service = with_restart_info(
+            ServiceBuilder::new()
+                .id(String::from("nginx"))
+                .app_name(String::from("master"))
+                .config(crate::sc!("nginx")),
+            &[pod(1), pod(3)],
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(service.restart_count(), Some(4));
+        assert_eq!(service.last_exit_code(), Some(137));
+        assert_eq!(
+            service.last_state(),
+            Some(&String::from("CrashLoopBackOff"))
+        );
+    }
+
     #[test]
     fn should_parse_service_from_deployment_spec() {
         let deployment = deployment_object!(
@@ -1053,6 +3414,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_parse_service_from_deployment_spec_with_fixed_port() {
+        let deployment = deployment_object!(
+            "master-db",
+            Some(String::from("master")),
+            Some(String::from("db")),
+            Some(String::from("mariadb")),
+            None,
+            FIXED_PORT_LABEL => String::from("3306")
+        );
+
+        let service = ServiceBuilder::try_from(deployment)
+            .unwrap()
+            .started_at(Utc::now())
+            .build()
+            .unwrap();
+
+        assert_eq!(service.config().port(), 3306);
+        assert!(service.config().has_fixed_port());
+    }
+
     #[test]
     fn should_parse_service_from_deployment_spec_without_container_type() {
         let deployment = deployment_object!(
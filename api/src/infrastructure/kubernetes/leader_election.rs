@@ -0,0 +1,162 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::infrastructure::{KubernetesInfrastructure, KubernetesInfrastructureError};
+use crate::config::LeaderElectionConfig;
+use crate::leadership::Leadership;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::MicroTime;
+use kube::api::{Api, ObjectMeta, PostParams};
+use kube::error::{Error as KubeError, ErrorResponse};
+use log::warn;
+use std::time::Instant;
+
+/// Starts the election of the leader among the replicas of PREvant and returns the leadership
+/// of this replica that follows the holder of the lease.
+pub fn spawn_leader_election(
+    infrastructure: KubernetesInfrastructure,
+    config: LeaderElectionConfig,
+) -> Leadership {
+    let identity = std::env::var("HOSTNAME").unwrap_or_else(|_| uuid::Uuid::new_v4().to_string());
+    let leadership =
+        Leadership::follower().with_patience(config.lease_duration() + config.renew_interval());
+
+    let election = leadership.clone();
+    tokio::spawn(async move {
+        loop {
+            // The lease counts from the start of the attempt because the API server might have
+            // renewed it long before a slow response arrives.
+            let attempt = Instant::now();
+            match try_acquire_lease(&infrastructure, &config, &identity).await {
+                Ok(holder) => {
+                    election.update(&identity, holder, Some(attempt + config.lease_duration()))
+                }
+                Err(err) => {
+                    // Stepping down avoids that two replicas act as leader if this replica is
+                    // unable to renew its lease.
+                    warn!("Cannot acquire the lease {}: {}", config.lease_name(), err);
+                    election.update(&identity, None, None);
+                }
+            }
+            tokio::time::sleep(config.renew_interval()).await;
+        }
+    });
+
+    leadership
+}
+
+/// Acquires the lease if it is free or expired, renews it if this replica holds it, and returns
+/// the identity of the replica that holds the lease afterwards.
+async fn try_acquire_lease(
+    infrastructure: &KubernetesInfrastructure,
+    config: &LeaderElectionConfig,
+    identity: &str,
+) -> Result<Option<String>, KubernetesInfrastructureError> {
+    let leases = Api::<Lease>::namespaced(infrastructure.client().await?, &config.namespace());
+    let now = Utc::now();
+    let lease_duration_seconds = config.lease_duration().as_secs() as i32;
+
+    let Some(mut lease) = leases.get_opt(config.lease_name()).await? else {
+        let lease = Lease {
+            metadata: ObjectMeta {
+                name: Some(config.lease_name().clone()),
+                ..Default::default()
+            },
+            spec: Some(LeaseSpec {
+                holder_identity: Some(identity.to_string()),
+                lease_duration_seconds: Some(lease_duration_seconds),
+                acquire_time: Some(MicroTime(now)),
+                renew_time: Some(MicroTime(now)),
+                lease_transitions: Some(0),
+                ..Default::default()
+            }),
+        };
+
+        return match leases.create(&PostParams::default(), &lease).await {
+            Ok(_) => Ok(Some(identity.to_string())),
+            // Another replica created the lease in the meantime.
+            Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => Ok(None),
+            Err(err) => Err(err.into()),
+        };
+    };
+
+    let mut spec = lease.spec.take().unwrap_or_default();
+    let holds_lease = spec.holder_identity.as_deref() == Some(identity);
+    if !holds_lease && !is_expired(&spec, now) {
+        return Ok(spec.holder_identity);
+    }
+
+    if !holds_lease {
+        spec.holder_identity = Some(identity.to_string());
+        spec.acquire_time = Some(MicroTime(now));
+        spec.lease_transitions = Some(spec.lease_transitions.unwrap_or_default() + 1);
+    }
+    spec.lease_duration_seconds = Some(lease_duration_seconds);
+    spec.renew_time = Some(MicroTime(now));
+    lease.spec = Some(spec);
+
+    // The replacement fails if another replica changed the lease since it has been read because
+    // the lease still contains the resource version.
+    match leases
+        .replace(config.lease_name(), &PostParams::default(), &lease)
+        .await
+    {
+        Ok(_) => Ok(Some(identity.to_string())),
+        Err(KubeError::Api(ErrorResponse { code, .. })) if code == 409 => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn is_expired(spec: &LeaseSpec, now: DateTime<Utc>) -> bool {
+    match (&spec.renew_time, spec.lease_duration_seconds) {
+        (Some(MicroTime(renew_time)), Some(lease_duration_seconds)) => {
+            *renew_time + chrono::Duration::seconds(lease_duration_seconds as i64) < now
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_expire_leases_that_have_not_been_renewed() {
+        let now = Utc::now();
+        let spec = |renewed_seconds_ago: i64| LeaseSpec {
+            holder_identity: Some(String::from("prevant-0")),
+            lease_duration_seconds: Some(15),
+            renew_time: Some(MicroTime(
+                now - chrono::Duration::seconds(renewed_seconds_ago),
+            )),
+            ..Default::default()
+        };
+
+        assert!(!is_expired(&spec(5), now));
+        assert!(is_expired(&spec(20), now));
+        assert!(is_expired(&LeaseSpec::default(), now));
+    }
+}
@@ -24,6 +24,9 @@
  * =========================LICENSE_END==================================
  */
 pub use infrastructure::KubernetesInfrastructure;
+pub use leader_election::spawn_leader_election;
 
+mod conditions;
 mod infrastructure;
+mod leader_election;
 mod payloads;
@@ -24,30 +24,49 @@
  * =========================LICENSE_END==================================
  */
 use super::super::{
-    APP_NAME_LABEL, CONTAINER_TYPE_LABEL, IMAGE_LABEL, REPLICATED_ENV_LABEL, SERVICE_NAME_LABEL,
-    STORAGE_TYPE_LABEL,
+    ALIASES_LABEL, ALIAS_OF_LABEL, APP_NAME_LABEL, CONTAINER_TYPE_LABEL, DEPENDS_ON_LABEL,
+    FIXED_PORT_LABEL, IMAGE_DIGEST_LABEL, IMAGE_LABEL, REPLICAS_LABEL, REPLICATED_ENV_LABEL,
+    REPLICATED_FROM_LABEL, SERVICE_NAME_LABEL, STORAGE_TYPE_LABEL,
+};
+use crate::config::{
+    BuildsConfig, CertManagerConfig, Config, ContainerConfig, IstioConfig, KubernetesAppHostConfig,
+    ServiceMesh,
 };
-use crate::config::{Config, ContainerConfig};
 use crate::deployment::deployment_unit::{DeployableService, DeploymentStrategy};
-use crate::infrastructure::traefik::TraefikMiddleware;
+use crate::infrastructure::traefik::{Matcher, TraefikMiddleware};
 use crate::infrastructure::{TraefikIngressRoute, TraefikRouterRule};
-use crate::models::service::Service;
-use crate::models::{AppName, ServiceConfig};
+use crate::models::service::{ContainerType, Service};
+use crate::models::{
+    AppName, Autoscale, Image, OperatingSystem, ScratchVolumeMedium, ServiceConfig, SourceBuild,
+    StartupProbe,
+};
 use base64::{engine::general_purpose, Engine};
 use bytesize::ByteSize;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use k8s_openapi::api::apps::v1::DeploymentSpec;
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use k8s_openapi::api::batch::v1::{Job as V1Job, JobSpec};
 use k8s_openapi::api::core::v1::{
-    Container, ContainerPort, EnvVar, KeyToPath, LocalObjectReference, PersistentVolumeClaim,
-    PersistentVolumeClaimSpec, PersistentVolumeClaimVolumeSource, PodSpec, PodTemplateSpec,
-    ResourceRequirements, SecretVolumeSource, Volume, VolumeMount,
+    Affinity, Container, ContainerPort, EmptyDirVolumeSource, EnvVar, EnvVarSource, ExecAction,
+    HTTPGetAction, HostAlias, KeyToPath, LocalObjectReference, NodeAffinity, NodeSelector,
+    NodeSelectorRequirement, NodeSelectorTerm, PersistentVolumeClaim, PersistentVolumeClaimSpec,
+    PersistentVolumeClaimVolumeSource, PodDNSConfig, PodOS, PodSpec, PodTemplateSpec, Probe,
+    ResourceRequirements, SecretKeySelector, SecretVolumeSource, SecurityContext, TCPSocketAction,
+    Toleration, Volume, VolumeMount,
 };
+use k8s_openapi::api::networking::v1::{
+    IPBlock, NetworkPolicy, NetworkPolicyEgressRule, NetworkPolicyPeer, NetworkPolicyPort,
+    NetworkPolicySpec,
+};
+use k8s_openapi::api::rbac::v1::{PolicyRule, Role, RoleBinding, RoleRef, Subject};
 use k8s_openapi::api::{
     apps::v1::Deployment as V1Deployment, core::v1::Namespace as V1Namespace,
-    core::v1::Secret as V1Secret, core::v1::Service as V1Service,
+    core::v1::Pod as V1Pod, core::v1::Secret as V1Secret, core::v1::Service as V1Service,
+    core::v1::ServiceAccount as V1ServiceAccount,
 };
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use k8s_openapi::ByteString;
 use kube::core::ObjectMeta;
 use kube::CustomResource;
@@ -56,7 +75,7 @@ use schemars::JsonSchema;
 use secstr::SecUtf8;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::iter::FromIterator;
 use std::path::{Component, PathBuf};
@@ -86,10 +105,17 @@ pub struct TraefikRuleSpec {
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct TraefikRuleService {
     pub kind: Option<String>,
     pub name: String,
     pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sticky: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_forwarding: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub servers_transport: Option<String>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
@@ -101,6 +127,34 @@ pub struct TraefikRuleMiddleware {
 #[serde(rename_all = "camelCase")]
 pub struct TraefikTls {
     cert_resolver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret_name: Option<String>,
+}
+
+#[derive(CustomResource, Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "cert-manager.io",
+    version = "v1",
+    kind = "Certificate",
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateSpec {
+    pub secret_name: String,
+    pub dns_names: Vec<String>,
+    pub issuer_ref: CertificateIssuerRef,
+    pub secret_template: Option<CertificateSecretTemplate>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct CertificateIssuerRef {
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct CertificateSecretTemplate {
+    pub labels: Option<BTreeMap<String, String>>,
 }
 
 #[derive(CustomResource, Clone, Debug, Deserialize, Serialize, JsonSchema)]
@@ -113,6 +167,28 @@ pub struct TraefikTls {
 #[serde(rename_all = "camelCase")]
 pub struct MiddlewareSpec(Value);
 
+#[derive(CustomResource, Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "networking.istio.io",
+    version = "v1beta1",
+    kind = "VirtualService",
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct VirtualServiceSpec {
+    pub hosts: Vec<String>,
+    pub gateways: Vec<String>,
+    pub http: Vec<IstioHttpRoute>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct IstioHttpRoute {
+    pub r#match: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rewrite: Option<Value>,
+    pub route: Vec<Value>,
+}
+
 macro_rules! secret_name_from_path {
     ($path:expr) => {{
         $path
@@ -139,11 +215,19 @@ macro_rules! secret_name_from_name {
 }
 
 impl TryFrom<IngressRoute> for TraefikIngressRoute {
-    type Error = &'static str;
+    type Error = String;
 
     fn try_from(value: IngressRoute) -> Result<Self, Self::Error> {
-        let k8s_route = value.spec.routes.unwrap().into_iter().next().unwrap();
-        let rule = TraefikRouterRule::from_str(&k8s_route.r#match).unwrap();
+        let name = value.metadata.name.unwrap_or_default();
+        let k8s_route = value
+            .spec
+            .routes
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("The ingress route {name} has no routes"))?;
+        let rule = TraefikRouterRule::from_str(&k8s_route.r#match)
+            .map_err(|err| format!("The ingress route {name} has an unsupported rule: {err}"))?;
 
         Ok(TraefikIngressRoute::with_existing_routing_rules(
             value.spec.entrypoints.unwrap_or_default(),
@@ -161,34 +245,70 @@ impl TryFrom<IngressRoute> for TraefikIngressRoute {
 
 /// Creates a JSON payload suitable for [Kubernetes'
 /// Namespaces](https://kubernetes.io/docs/tasks/administer-cluster/namespaces/)
-pub fn namespace_payload(app_name: &AppName, config: &Config) -> V1Namespace {
-    let annotations = match config.runtime_config() {
-        crate::config::Runtime::Docker => None,
-        crate::config::Runtime::Kubernetes(runtime) => {
-            let annotations = runtime.annotations().namespace();
-
-            if annotations.is_empty() {
-                None
-            } else {
-                Some(annotations.clone())
-            }
-        }
+///
+/// The annotations of the namespace consist of the globally configured annotations
+/// (`runtime.annotations.namespace`) and the `namespace_annotations` of the deployment request
+/// where the latter have precedence.
+pub fn namespace_payload(
+    app_name: &AppName,
+    config: &Config,
+    namespace_annotations: &BTreeMap<String, String>,
+) -> V1Namespace {
+    let mut annotations = match config.runtime_config() {
+        crate::config::Runtime::Kubernetes(runtime) => runtime.annotations().namespace().clone(),
+        _ => BTreeMap::new(),
     };
+    let mut labels = BTreeMap::from([(APP_NAME_LABEL.to_string(), app_name.to_string())]);
+    match service_mesh(config) {
+        Some(ServiceMesh::Istio(_)) => {
+            labels.insert(String::from("istio-injection"), String::from("enabled"));
+        }
+        Some(ServiceMesh::Linkerd) => {
+            annotations.insert(String::from("linkerd.io/inject"), String::from("enabled"));
+        }
+        None => {}
+    }
+    annotations.extend(namespace_annotations.clone());
 
     V1Namespace {
         metadata: ObjectMeta {
-            name: Some(app_name.to_rfc1123_namespace_id()),
-            annotations,
-            labels: Some(BTreeMap::from([(
-                APP_NAME_LABEL.to_string(),
-                app_name.to_string(),
-            )])),
+            name: Some(namespace_name(app_name, config)),
+            annotations: if annotations.is_empty() {
+                None
+            } else {
+                Some(annotations)
+            },
+            labels: Some(labels),
             ..Default::default()
         },
         ..Default::default()
     }
 }
 
+/// The host configuration of the apps of the Kubernetes runtime, if any.
+pub fn app_host(config: &Config) -> Option<&KubernetesAppHostConfig> {
+    match config.runtime_config() {
+        crate::config::Runtime::Kubernetes(runtime) => runtime.app_host(),
+        _ => None,
+    }
+}
+
+/// The DNS domain of the cluster, `cluster.local` by default.
+fn cluster_domain(config: &Config) -> &str {
+    match config.runtime_config() {
+        crate::config::Runtime::Kubernetes(runtime) => runtime.cluster_domain(),
+        _ => "cluster.local",
+    }
+}
+
+/// The service mesh of the Kubernetes runtime, if any.
+pub fn service_mesh(config: &Config) -> Option<&ServiceMesh> {
+    match config.runtime_config() {
+        crate::config::Runtime::Kubernetes(runtime) => runtime.service_mesh(),
+        _ => None,
+    }
+}
+
 impl AppName {
     /// See https://kubernetes.io/docs/concepts/overview/working-with-objects/names/#dns-label-names
     pub fn to_rfc1123_namespace_id(&self) -> String {
@@ -196,10 +316,36 @@ impl AppName {
     }
 }
 
+/// Resolves the namespace of the app by replacing `{app}` in `runtime.namespace.name`.
+pub fn namespace_name(app_name: &AppName, config: &Config) -> String {
+    match config.runtime_config() {
+        crate::config::Runtime::Kubernetes(runtime) => runtime
+            .namespace()
+            .name()
+            .replace("{app}", &app_name.to_rfc1123_namespace_id()),
+        _ => app_name.to_rfc1123_namespace_id(),
+    }
+}
+
+/// The name of the Kubernetes service through which the other services reach the given
+/// service. If multiple apps share a namespace, the name is prefixed with the app name to
+/// avoid conflicts and the bare name is provided by [`sibling_host_aliases`].
+pub fn kubernetes_service_name(app_name: &AppName, config: &Config, service_name: &str) -> String {
+    match config.runtime_config() {
+        crate::config::Runtime::Kubernetes(runtime)
+            if !runtime.namespace().name().contains("{app}") =>
+        {
+            format!("{}-{}", app_name.to_rfc1123_namespace_id(), service_name)
+        }
+        _ => service_name.to_string(),
+    }
+}
+
 /// Creates a JSON payload suitable for [Kubernetes'
 /// Deployments](https://kubernetes.io/docs/concepts/workloads/controllers/deployment/)
 pub fn deployment_payload(
     app_name: &AppName,
+    config: &Config,
     service: &DeployableService,
     container_config: &ContainerConfig,
     use_image_pull_secret: bool,
@@ -215,7 +361,7 @@ pub fn deployment_payload(
             .collect()
     });
 
-    let annotations = if let Some(replicated_env) = service
+    let mut annotations = if let Some(replicated_env) = service
         .env()
         .and_then(super::super::replicated_environment_variable_to_json)
     {
@@ -226,6 +372,24 @@ pub fn deployment_payload(
     } else {
         BTreeMap::from([(IMAGE_LABEL.to_string(), service.image().to_string())])
     };
+    if let Some(image_digest) = service.image_digest() {
+        annotations.insert(IMAGE_DIGEST_LABEL.to_string(), image_digest.clone());
+    }
+    if let Some(replicas) = service.replicas() {
+        annotations.insert(REPLICAS_LABEL.to_string(), replicas.to_string());
+    }
+    if service.has_fixed_port() {
+        annotations.insert(FIXED_PORT_LABEL.to_string(), service.port().to_string());
+    }
+    if let Some(replicated_from) = service.replicated_from() {
+        annotations.insert(REPLICATED_FROM_LABEL.to_string(), replicated_from.clone());
+    }
+    if !service.aliases().is_empty() {
+        annotations.insert(ALIASES_LABEL.to_string(), service.aliases().join(","));
+    }
+    if !service.depends_on().is_empty() {
+        annotations.insert(DEPENDS_ON_LABEL.to_string(), service.depends_on().join(","));
+    }
 
     let volume_mounts = service.files().map(|files| {
         let parent_paths = files
@@ -303,15 +467,62 @@ pub fn deployment_payload(
         None => volumes,
     };
 
-    let resources = container_config
-        .memory_limit()
-        .map(|mem_limit| ResourceRequirements {
-            limits: Some(BTreeMap::from([(
-                String::from("memory"),
-                Quantity(format!("{}", mem_limit.as_u64())),
-            )])),
+    // Scratch volumes live as long as the pod, thus they don't need a persistent volume claim.
+    // The writable paths of a read-only root filesystem are backed by scratch volumes as well.
+    let ephemeral_volumes = service.ephemeral_volumes();
+    let (volume_mounts, volumes) = if ephemeral_volumes.is_empty() {
+        (volume_mounts, volumes)
+    } else {
+        let mut mounts = volume_mounts.unwrap_or_default();
+        let mut vols = volumes.unwrap_or_default();
+        for (index, scratch_volume) in ephemeral_volumes.iter().enumerate() {
+            let name = format!("scratch-{index}");
+            mounts.push(VolumeMount {
+                name: name.clone(),
+                mount_path: scratch_volume.path().to_string_lossy().to_string(),
+                ..Default::default()
+            });
+            vols.push(Volume {
+                name,
+                empty_dir: Some(EmptyDirVolumeSource {
+                    medium: match scratch_volume.medium() {
+                        ScratchVolumeMedium::Disk => None,
+                        ScratchVolumeMedium::Memory => Some(String::from("Memory")),
+                    },
+                    size_limit: scratch_volume.size_limit().cloned().map(Quantity),
+                }),
+                ..Default::default()
+            });
+        }
+        (Some(mounts), Some(vols))
+    };
+
+    // Extended resources cannot be overcommitted, thus Kubernetes only requires them as limits.
+    let mut limits = service
+        .extended_resources()
+        .iter()
+        .map(|(resource, quantity)| (resource.clone(), Quantity(quantity.clone())))
+        .collect::<BTreeMap<_, _>>();
+    if let Some(mem_limit) = container_config.memory_limit() {
+        limits.insert(
+            String::from("memory"),
+            Quantity(format!("{}", mem_limit.as_u64())),
+        );
+    }
+    if let Some(cpu_limit) = container_config.cpu_limit() {
+        limits.insert(
+            String::from("cpu"),
+            Quantity(format!("{}m", (cpu_limit * 1000.0).round() as u64)),
+        );
+    }
+    let resources = if limits.is_empty() {
+        None
+    } else {
+        Some(ResourceRequirements {
+            limits: Some(limits),
             ..Default::default()
-        });
+        })
+    };
 
     let labels = BTreeMap::from([
         (APP_NAME_LABEL.to_string(), app_name.to_string()),
@@ -325,6 +536,8 @@ pub fn deployment_payload(
         ),
     ]);
 
+    let resource_labels = labels_with_metadata(service, labels.clone());
+
     V1Deployment {
         metadata: ObjectMeta {
             name: Some(format!(
@@ -332,21 +545,29 @@ pub fn deployment_payload(
                 app_name.to_rfc1123_namespace_id(),
                 service.service_name()
             )),
-            namespace: Some(app_name.to_rfc1123_namespace_id()),
-            labels: Some(labels.clone()),
-            annotations: Some(annotations),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(resource_labels.clone()),
+            annotations: Some(annotations_with_metadata(service, annotations)),
             ..Default::default()
         },
         spec: Some(DeploymentSpec {
-            replicas: Some(1),
+            // The horizontal pod autoscaler controls the replicas of autoscaled services and
+            // services without replicas are deployed paused.
+            replicas: match service.autoscale() {
+                Some(_) => None,
+                None => Some(service.replicas().unwrap_or(1) as i32),
+            },
             selector: LabelSelector {
-                match_labels: Some(labels.clone()),
+                match_labels: Some(labels),
                 ..Default::default()
             },
             template: PodTemplateSpec {
                 metadata: Some(ObjectMeta {
-                    labels: Some(labels),
-                    annotations: Some(deployment_annotations(service)),
+                    labels: Some(resource_labels),
+                    annotations: Some(annotations_with_metadata(
+                        service,
+                        deployment_annotations(service),
+                    )),
                     ..Default::default()
                 }),
                 spec: Some(PodSpec {
@@ -354,16 +575,50 @@ pub fn deployment_payload(
                     containers: vec![Container {
                         name: service.service_name().to_string(),
                         image: Some(service.image().to_string()),
-                        image_pull_policy: Some(String::from("Always")),
+                        image_pull_policy: Some(
+                            container_config.image_pull_policy(service).to_string(),
+                        ),
+                        command: service.command().cloned(),
+                        args: service.args().cloned(),
+                        working_dir: service.working_dir().cloned(),
                         env,
                         volume_mounts,
-                        ports: Some(vec![ContainerPort {
-                            container_port: service.port() as i32,
-                            ..Default::default()
-                        }]),
+                        ports: Some(
+                            std::iter::once(service.port())
+                                .chain(service.additional_ports().iter().copied())
+                                .map(|port| ContainerPort {
+                                    container_port: port as i32,
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        ),
                         resources,
+                        startup_probe: service
+                            .startup_probe()
+                            .map(|probe| startup_probe_payload(service, probe)),
+                        security_context: service.read_only_root_filesystem().then(|| {
+                            SecurityContext {
+                                read_only_root_filesystem: Some(true),
+                                ..Default::default()
+                            }
+                        }),
                         ..Default::default()
                     }],
+                    termination_grace_period_seconds: service
+                        .termination_grace_period()
+                        .map(|grace_period| grace_period.as_secs() as i64),
+                    host_aliases: host_aliases_payload(service),
+                    dns_config: service.dns_config().map(|dns_config| PodDNSConfig {
+                        nameservers: Some(
+                            dns_config
+                                .nameservers()
+                                .iter()
+                                .map(|nameserver| nameserver.to_string())
+                                .collect(),
+                        ),
+                        searches: Some(dns_config.searches().to_vec()),
+                        ..Default::default()
+                    }),
                     image_pull_secrets: if use_image_pull_secret {
                         Some(vec![LocalObjectReference {
                             name: Some(format!(
@@ -374,6 +629,28 @@ pub fn deployment_payload(
                     } else {
                         None
                     },
+                    service_account_name: service
+                        .service_account()
+                        .map(|_| service_account_name(app_name, service)),
+                    affinity: platform_affinity(service),
+                    node_selector: service.os().map(|os| {
+                        BTreeMap::from([(String::from("kubernetes.io/os"), os.to_string())])
+                    }),
+                    tolerations: match service.os() {
+                        // Mixed clusters taint their Windows nodes to keep Linux pods away.
+                        Some(OperatingSystem::Windows) => Some(vec![Toleration {
+                            key: Some(String::from("node.kubernetes.io/os")),
+                            operator: Some(String::from("Equal")),
+                            value: Some(String::from("windows")),
+                            effect: Some(String::from("NoSchedule")),
+                            ..Default::default()
+                        }]),
+                        _ => None,
+                    },
+                    // Lets the API server reject fields that are not supported by the OS
+                    os: service.os().map(|os| PodOS {
+                        name: os.to_string(),
+                    }),
                     ..Default::default()
                 }),
             },
@@ -383,6 +660,182 @@ pub fn deployment_payload(
     }
 }
 
+fn host_aliases_payload(service: &DeployableService) -> Option<Vec<HostAlias>> {
+    if service.host_aliases().is_empty() {
+        return None;
+    }
+
+    Some(
+        service
+            .host_aliases()
+            .iter()
+            .map(|alias| HostAlias {
+                ip: Some(alias.ip().to_string()),
+                hostnames: Some(alias.hostnames().to_vec()),
+            })
+            .collect(),
+    )
+}
+
+/// Maps the bare names of the sibling services to the cluster IPs of their Kubernetes services.
+/// If multiple apps share a namespace, the Kubernetes services are prefixed with the app name
+/// (see [`kubernetes_service_name`]). These host aliases let the services of an app still reach
+/// each other by their bare names, e.g. `http://s3:9000`. Headless services are left out.
+pub fn sibling_host_aliases(services: &[V1Service]) -> Vec<HostAlias> {
+    let mut hostnames_by_ip = BTreeMap::<String, Vec<String>>::new();
+    for service in services {
+        let Some(service_name) = service
+            .metadata
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get(SERVICE_NAME_LABEL))
+        else {
+            continue;
+        };
+        let Some(cluster_ip) = service
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.cluster_ip.as_ref())
+            .filter(|ip| !ip.is_empty() && *ip != "None")
+        else {
+            continue;
+        };
+
+        hostnames_by_ip
+            .entry(cluster_ip.clone())
+            .or_default()
+            .push(service_name.clone());
+    }
+
+    hostnames_by_ip
+        .into_iter()
+        .map(|(ip, hostnames)| HostAlias {
+            ip: Some(ip),
+            hostnames: Some(hostnames),
+        })
+        .collect()
+}
+
+/// Requires nodes whose operating system and architecture match the forced platform of the
+/// service or, if there is none, one of the platforms its image supports.
+fn platform_affinity(service: &DeployableService) -> Option<Affinity> {
+    let platforms = match service.platform() {
+        Some(platform) => {
+            let mut parts = platform.split('/');
+            match (parts.next(), parts.next()) {
+                (Some(os), Some(architecture)) if !os.is_empty() && !architecture.is_empty() => {
+                    vec![(os.to_string(), architecture.to_string())]
+                }
+                _ => {
+                    warn!(
+                        "Ignoring platform {platform} of {} because it is not in the format os/architecture",
+                        service.service_name()
+                    );
+                    Vec::new()
+                }
+            }
+        }
+        None => service
+            .image_platforms()
+            .iter()
+            .filter(|platform| match service.os() {
+                Some(os) => platform.os() == os.to_string(),
+                None => true,
+            })
+            .map(|platform| {
+                (
+                    platform.os().to_string(),
+                    platform.architecture().to_string(),
+                )
+            })
+            .collect(),
+    };
+
+    if platforms.is_empty() {
+        return None;
+    }
+
+    let architectures_by_os = platforms.into_iter().fold(
+        BTreeMap::<String, Vec<String>>::new(),
+        |mut map, (os, arch)| {
+            let architectures = map.entry(os).or_default();
+            if !architectures.contains(&arch) {
+                architectures.push(arch);
+            }
+            map
+        },
+    );
+
+    Some(Affinity {
+        node_affinity: Some(NodeAffinity {
+            required_during_scheduling_ignored_during_execution: Some(NodeSelector {
+                node_selector_terms: architectures_by_os
+                    .into_iter()
+                    .map(|(os, architectures)| NodeSelectorTerm {
+                        match_expressions: Some(vec![
+                            NodeSelectorRequirement {
+                                key: String::from("kubernetes.io/os"),
+                                operator: String::from("In"),
+                                values: Some(vec![os]),
+                            },
+                            NodeSelectorRequirement {
+                                key: String::from("kubernetes.io/arch"),
+                                operator: String::from("In"),
+                                values: Some(architectures),
+                            },
+                        ]),
+                        ..Default::default()
+                    })
+                    .collect(),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// Probes the service with an HTTP GET request on its `path`, its `command`, or a connection to its
+/// port.
+fn startup_probe_payload(service: &DeployableService, probe: &StartupProbe) -> Probe {
+    let port = IntOrString::Int(service.port() as i32);
+    let (http_get, exec, tcp_socket) = match (probe.path(), probe.command()) {
+        (Some(path), _) => (
+            Some(HTTPGetAction {
+                path: Some(path.clone()),
+                port,
+                ..Default::default()
+            }),
+            None,
+            None,
+        ),
+        (None, Some(command)) => (
+            None,
+            Some(ExecAction {
+                command: Some(command.clone()),
+            }),
+            None,
+        ),
+        (None, None) => (
+            None,
+            None,
+            Some(TCPSocketAction {
+                port,
+                ..Default::default()
+            }),
+        ),
+    };
+
+    Probe {
+        http_get,
+        exec,
+        tcp_socket,
+        initial_delay_seconds: Some(probe.initial_delay_seconds() as i32),
+        period_seconds: Some(probe.period_seconds() as i32),
+        failure_threshold: Some(probe.failure_threshold() as i32),
+        ..Default::default()
+    }
+}
+
 /// Creates the value of an [annotations object](https://kubernetes.io/docs/concepts/overview/working-with-objects/annotations/)
 /// so that the underlying pod will be deployed according to its [deployment strategy](`DeploymentStrategy`).
 ///
@@ -401,8 +854,73 @@ fn deployment_annotations(service: &DeployableService) -> BTreeMap<String, Strin
     }
 }
 
+/// Merges the labels, that PREvant requires to identify its resources, with the labels of the
+/// service's [metadata](`crate::models::ResourceMetadata`). PREvant's labels have precedence.
+fn labels_with_metadata(
+    service_config: &ServiceConfig,
+    labels: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut labels_with_metadata = service_config
+        .metadata()
+        .map(|metadata| metadata.labels().clone())
+        .unwrap_or_default();
+    labels_with_metadata.extend(labels);
+    labels_with_metadata
+}
+
+/// Merges the annotations, that PREvant requires to manage its resources, with the annotations
+/// of the service's [metadata](`crate::models::ResourceMetadata`). PREvant's annotations have
+/// precedence.
+fn annotations_with_metadata(
+    service_config: &ServiceConfig,
+    annotations: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut annotations_with_metadata = service_config
+        .metadata()
+        .map(|metadata| metadata.annotations().clone())
+        .unwrap_or_default();
+    annotations_with_metadata.extend(annotations);
+    annotations_with_metadata
+}
+
+/// Creates a payload that restarts the pods of a deployment in the same way as
+/// `kubectl rollout restart` does.
+pub fn deployment_restart_payload(
+    app_name: &AppName,
+    config: &Config,
+    service: &Service,
+    restarted_at: DateTime<Utc>,
+) -> V1Deployment {
+    serde_json::from_value(serde_json::json!({
+      "apiVersion": "apps/v1",
+      "kind": "Deployment",
+      "metadata": {
+        "name": format!("{}-{}-deployment", app_name.to_rfc1123_namespace_id(), service.service_name()),
+        "namespace": namespace_name(app_name, config),
+      },
+      "spec": {
+        "selector": {
+          "matchLabels": {
+            APP_NAME_LABEL: app_name,
+            SERVICE_NAME_LABEL: service.service_name(),
+            CONTAINER_TYPE_LABEL: service.container_type().to_string()
+          }
+        },
+        "template": {
+          "metadata": {
+            "annotations": {
+              "kubectl.kubernetes.io/restartedAt": restarted_at.to_rfc3339()
+            }
+          }
+        }
+      }
+    }))
+    .expect("Cannot convert value to apps/v1/Deployment")
+}
+
 pub fn deployment_replicas_payload(
     app_name: &AppName,
+    config: &Config,
     service: &Service,
     replicas: u32,
 ) -> V1Deployment {
@@ -411,7 +929,7 @@ pub fn deployment_replicas_payload(
       "kind": "Deployment",
       "metadata": {
         "name": format!("{}-{}-deployment", app_name.to_rfc1123_namespace_id(), service.service_name()),
-        "namespace": app_name.to_rfc1123_namespace_id(),
+        "namespace": namespace_name(app_name, config),
         "labels": {
           APP_NAME_LABEL: app_name,
           SERVICE_NAME_LABEL: service.service_name(),
@@ -432,9 +950,106 @@ pub fn deployment_replicas_payload(
     .expect("Cannot convert value to apps/v1/Deployment")
 }
 
+/// Creates a merge patch that labels a deployment that has not been deployed by PREvant, e.g. a
+/// deployment of a manually prepared namespace, so that PREvant manages it as a service of the
+/// app. The service is named after the deployment and its image, environment, port, and replicas
+/// are taken from the first container and the deployment. The environment and the port are stored
+/// as annotations, like the ones of deployed services, so that they survive when the service
+/// configuration is read back from the deployment. Environment variables that reference secrets
+/// or config maps are left out. Returns `None` if the deployment has no valid image.
+///
+/// Labeling the pod template rolls out the pods of the deployment once again.
+pub fn adopted_deployment_payload(app_name: &AppName, deployment: &V1Deployment) -> Option<Value> {
+    let name = deployment.metadata.name.as_ref()?;
+    let spec = deployment.spec.as_ref()?;
+    let container = spec.template.spec.as_ref()?.containers.first()?;
+    let image = container
+        .image
+        .as_ref()
+        .and_then(|image| Image::from_str(image).ok())?;
+
+    let labels = serde_json::json!({
+        APP_NAME_LABEL: app_name,
+        SERVICE_NAME_LABEL: name,
+        CONTAINER_TYPE_LABEL: ContainerType::Instance.to_string()
+    });
+    let mut annotations = Map::new();
+    annotations.insert(IMAGE_LABEL.to_string(), Value::String(image.to_string()));
+    if let Some(replicas) = spec.replicas {
+        annotations.insert(
+            REPLICAS_LABEL.to_string(),
+            Value::String(replicas.to_string()),
+        );
+    }
+
+    let env = container
+        .env
+        .iter()
+        .flatten()
+        .filter_map(|env| {
+            env.value.as_ref().map(|value| {
+                (
+                    env.name.clone(),
+                    serde_json::json!({
+                        "value": value,
+                        "templated": false,
+                        "replicate": true
+                    }),
+                )
+            })
+        })
+        .collect::<Map<String, Value>>();
+    if !env.is_empty() {
+        annotations.insert(
+            REPLICATED_ENV_LABEL.to_string(),
+            Value::String(Value::Object(env).to_string()),
+        );
+    }
+    if let Some(port) = container
+        .ports
+        .iter()
+        .flatten()
+        .next()
+        .map(|port| port.container_port)
+    {
+        annotations.insert(
+            FIXED_PORT_LABEL.to_string(),
+            Value::String(port.to_string()),
+        );
+    }
+
+    Some(serde_json::json!({
+      "metadata": {
+        "labels": labels,
+        "annotations": annotations
+      },
+      "spec": {
+        "template": {
+          "metadata": {
+            "labels": labels
+          }
+        }
+      }
+    }))
+}
+
+/// Creates a merge patch that labels a Kubernetes service that selects the pods of an adopted
+/// deployment, see [`adopted_deployment_payload`].
+pub fn adopted_service_payload(app_name: &AppName, service_name: &str) -> Value {
+    serde_json::json!({
+      "metadata": {
+        "labels": {
+          APP_NAME_LABEL: app_name,
+          SERVICE_NAME_LABEL: service_name
+        }
+      }
+    })
+}
+
 /// Creates a JSON payload suitable for [Kubernetes' Secrets](https://kubernetes.io/docs/concepts/configuration/secret/)
 pub fn secrets_payload(
     app_name: &AppName,
+    config: &Config,
     service_config: &ServiceConfig,
     files: &BTreeMap<PathBuf, SecUtf8>,
 ) -> V1Secret {
@@ -453,10 +1068,13 @@ pub fn secrets_payload(
       "kind": "Secret",
       "metadata": {
         "name": format!("{}-{}-secret", app_name.to_rfc1123_namespace_id(), service_config.service_name()),
-        "namespace": app_name.to_rfc1123_namespace_id(),
-         APP_NAME_LABEL: app_name,
-         SERVICE_NAME_LABEL: service_config.service_name(),
-         CONTAINER_TYPE_LABEL: service_config.container_type().to_string()
+        "namespace": namespace_name(app_name, config),
+        "labels": labels_with_metadata(service_config, BTreeMap::from([
+          (APP_NAME_LABEL.to_string(), app_name.to_string()),
+          (SERVICE_NAME_LABEL.to_string(), service_config.service_name().to_string()),
+          (CONTAINER_TYPE_LABEL.to_string(), service_config.container_type().to_string()),
+        ])),
+         "annotations": annotations_with_metadata(service_config, BTreeMap::new())
       },
       "type": "Opaque",
       "data": secrets
@@ -464,36 +1082,108 @@ pub fn secrets_payload(
     .expect("Cannot convert value to core/v1/Secret")
 }
 
-pub fn image_pull_secret_payload(
-    app_name: &AppName,
-    registries_and_credentials: BTreeMap<String, (&str, &SecUtf8)>,
-) -> V1Secret {
-    let data = ByteString(
-        serde_json::json!({
-            "auths":
-            serde_json::Map::from_iter(registries_and_credentials.into_iter().map(
-                |(registry, (username, password))| {
-                    (
-                        registry,
-                        serde_json::json!({
-                            "username": username.to_string(),
-                            "password": password.unsecure().to_string(),
-                        }),
-                    )
-                },
-            ))
-        })
-        .to_string()
-        .into_bytes(),
-    );
+/// Creates a merge patch that replaces the contents of `files` in the secret of a service, see
+/// [`secrets_payload`]. Fails with the path of the first file that is not mounted by the
+/// `deployment` because its pods would not see new keys of the secret.
+pub fn secret_files_patch(
+    deployment: &V1Deployment,
+    files: &BTreeMap<PathBuf, SecUtf8>,
+) -> Result<Value, PathBuf> {
+    let volumes = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.spec.as_ref())
+        .and_then(|spec| spec.volumes.as_ref())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
 
-    V1Secret {
+    let mut data = Map::new();
+    for (path, file_content) in files {
+        let key = secret_name_from_name!(path);
+        let is_mounted = path.parent().map_or(false, |parent| {
+            let volume_name = secret_name_from_path!(parent);
+            volumes
+                .iter()
+                .filter(|volume| volume.name == volume_name)
+                .filter_map(|volume| volume.secret.as_ref())
+                .filter_map(|secret| secret.items.as_ref())
+                .flatten()
+                .any(|item| item.key == key)
+        });
+        if !is_mounted {
+            return Err(path.clone());
+        }
+
+        data.insert(
+            key,
+            Value::String(general_purpose::STANDARD.encode(file_content.unsecure())),
+        );
+    }
+
+    Ok(serde_json::json!({ "data": data }))
+}
+
+/// A pod that runs the smoke test image once in the namespace of the app.
+pub fn smoke_test_pod_payload(
+    app_name: &AppName,
+    config: &Config,
+    image: &Image,
+    command: &[String],
+    use_image_pull_secret: bool,
+) -> V1Pod {
+    V1Pod {
+        metadata: ObjectMeta {
+            generate_name: Some(format!(
+                "{}-smoke-test-",
+                app_name.to_rfc1123_namespace_id()
+            )),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(BTreeMap::from([(
+                APP_NAME_LABEL.to_string(),
+                app_name.to_string(),
+            )])),
+            ..Default::default()
+        },
+        spec: Some(PodSpec {
+            containers: vec![Container {
+                name: String::from("smoke-test"),
+                image: Some(image.to_string()),
+                args: if command.is_empty() {
+                    None
+                } else {
+                    Some(command.to_vec())
+                },
+                ..Default::default()
+            }],
+            restart_policy: Some(String::from("Never")),
+            image_pull_secrets: if use_image_pull_secret {
+                Some(vec![LocalObjectReference {
+                    name: Some(format!(
+                        "{}-image-pull-secret",
+                        app_name.to_rfc1123_namespace_id()
+                    )),
+                }])
+            } else {
+                None
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn image_pull_secret_payload(
+    app_name: &AppName,
+    config: &Config,
+    registries_and_credentials: BTreeMap<String, (&str, &SecUtf8)>,
+) -> V1Secret {
+    V1Secret {
         metadata: ObjectMeta {
             name: Some(format!(
                 "{}-image-pull-secret",
                 app_name.to_rfc1123_namespace_id()
             )),
-            namespace: Some(app_name.to_rfc1123_namespace_id()),
+            namespace: Some(namespace_name(app_name, config)),
             labels: Some(BTreeMap::from([(
                 APP_NAME_LABEL.to_string(),
                 app_name.to_string(),
@@ -501,32 +1191,442 @@ pub fn image_pull_secret_payload(
             ..Default::default()
         },
         immutable: Some(true),
-        data: Some(BTreeMap::from([(String::from(".dockerconfigjson"), data)])),
+        data: Some(BTreeMap::from([(
+            String::from(".dockerconfigjson"),
+            docker_config_json(registries_and_credentials),
+        )])),
         type_: Some(String::from("kubernetes.io/dockerconfigjson")),
         ..Default::default()
     }
 }
 
+/// The Docker `config.json` that contains the credentials of the registries.
+fn docker_config_json(
+    registries_and_credentials: BTreeMap<String, (&str, &SecUtf8)>,
+) -> ByteString {
+    ByteString(
+        serde_json::json!({
+            "auths":
+            serde_json::Map::from_iter(registries_and_credentials.into_iter().map(
+                |(registry, (username, password))| {
+                    (
+                        registry,
+                        serde_json::json!({
+                            "username": username.to_string(),
+                            "password": password.unsecure().to_string(),
+                        }),
+                    )
+                },
+            ))
+        })
+        .to_string()
+        .into_bytes(),
+    )
+}
+
+fn build_secret_name(app_name: &AppName) -> String {
+    format!("{}-build-secret", app_name.to_rfc1123_namespace_id())
+}
+
+/// The credentials of the builds of the app: the Docker `config.json` to push the images to the
+/// registry and the credentials of the Git host to check out the sources.
+pub fn build_secret_payload(
+    app_name: &AppName,
+    config: &Config,
+    push_credentials: Option<(String, (&str, &SecUtf8))>,
+    git_credentials: Option<(&str, &SecUtf8)>,
+) -> V1Secret {
+    let mut data = BTreeMap::new();
+    if let Some((registry, credentials)) = push_credentials {
+        data.insert(
+            String::from("config.json"),
+            docker_config_json(BTreeMap::from([(registry, credentials)])),
+        );
+    }
+    if let Some((username, password)) = git_credentials {
+        data.insert(
+            String::from("git-username"),
+            ByteString(username.as_bytes().to_vec()),
+        );
+        data.insert(
+            String::from("git-password"),
+            ByteString(password.unsecure().as_bytes().to_vec()),
+        );
+    }
+
+    V1Secret {
+        metadata: ObjectMeta {
+            name: Some(build_secret_name(app_name)),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(BTreeMap::from([(
+                APP_NAME_LABEL.to_string(),
+                app_name.to_string(),
+            )])),
+            ..Default::default()
+        },
+        data: Some(data),
+        ..Default::default()
+    }
+}
+
+/// A job that checks out the sources of the `build` into a shared volume with an init container
+/// and then builds and pushes the `destination` image with the configured builder.
+pub fn build_job_payload(
+    app_name: &AppName,
+    config: &Config,
+    build: &SourceBuild,
+    destination: &Image,
+    builds_config: &BuildsConfig,
+    use_push_credentials: bool,
+    use_git_credentials: bool,
+) -> V1Job {
+    const WORKSPACE: &str = "/workspace";
+
+    let secret_env = |name: &str, key: &str| EnvVar {
+        name: name.to_string(),
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                name: Some(build_secret_name(app_name)),
+                key: key.to_string(),
+                optional: Some(false),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let mut git_env = vec![EnvVar {
+        name: String::from("REPOSITORY"),
+        value: Some(build.url().to_string()),
+        ..Default::default()
+    }];
+    if let Some(reference) = build.reference() {
+        git_env.push(EnvVar {
+            name: String::from("REVISION"),
+            value: Some(reference.to_string()),
+            ..Default::default()
+        });
+    }
+    if use_git_credentials {
+        git_env.push(secret_env("GIT_USERNAME", "git-username"));
+        git_env.push(secret_env("GIT_PASSWORD", "git-password"));
+    }
+
+    let mut volumes = vec![Volume {
+        name: String::from("workspace"),
+        empty_dir: Some(Default::default()),
+        ..Default::default()
+    }];
+    let mut volume_mounts = vec![VolumeMount {
+        name: String::from("workspace"),
+        mount_path: String::from(WORKSPACE),
+        ..Default::default()
+    }];
+    let mut builder_env = Vec::new();
+    if use_push_credentials {
+        volumes.push(Volume {
+            name: String::from("docker-config"),
+            secret: Some(SecretVolumeSource {
+                secret_name: Some(build_secret_name(app_name)),
+                items: Some(vec![KeyToPath {
+                    key: String::from("config.json"),
+                    path: String::from("config.json"),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        volume_mounts.push(VolumeMount {
+            name: String::from("docker-config"),
+            mount_path: String::from("/docker-config"),
+            read_only: Some(true),
+            ..Default::default()
+        });
+        builder_env.push(EnvVar {
+            name: String::from("DOCKER_CONFIG"),
+            value: Some(String::from("/docker-config")),
+            ..Default::default()
+        });
+    }
+
+    let (command, args) = builds_config.builder_command(build, WORKSPACE, destination);
+
+    V1Job {
+        metadata: ObjectMeta {
+            generate_name: Some(format!(
+                "{}-build-{}-",
+                app_name.to_rfc1123_namespace_id(),
+                build.service_name().to_lowercase()
+            )),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(BTreeMap::from([(
+                APP_NAME_LABEL.to_string(),
+                app_name.to_string(),
+            )])),
+            ..Default::default()
+        },
+        spec: Some(JobSpec {
+            backoff_limit: Some(0),
+            active_deadline_seconds: Some(builds_config.timeout().as_secs() as i64),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(BTreeMap::from([(
+                        APP_NAME_LABEL.to_string(),
+                        app_name.to_string(),
+                    )])),
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    init_containers: Some(vec![Container {
+                        name: String::from("checkout"),
+                        image: Some(builds_config.git_image().to_string()),
+                        command: Some(vec![String::from("/bin/sh"), String::from("-c")]),
+                        args: Some(vec![format!(
+                            r#"git -c credential.helper='!f() {{ echo "username=$GIT_USERNAME"; echo "password=$GIT_PASSWORD"; }}; f' clone --quiet "$REPOSITORY" {WORKSPACE} && cd {WORKSPACE} && {{ [ -z "$REVISION" ] || git checkout --quiet "$REVISION"; }} && chmod -R a+rwX {WORKSPACE}"#
+                        )]),
+                        env: Some(git_env),
+                        volume_mounts: Some(vec![VolumeMount {
+                            name: String::from("workspace"),
+                            mount_path: String::from(WORKSPACE),
+                            ..Default::default()
+                        }]),
+                        ..Default::default()
+                    }]),
+                    containers: vec![Container {
+                        name: String::from("build"),
+                        image: Some(builds_config.builder_image().to_string()),
+                        command,
+                        args: Some(args),
+                        env: Some(builder_env),
+                        volume_mounts: Some(volume_mounts),
+                        ..Default::default()
+                    }],
+                    volumes: Some(volumes),
+                    restart_policy: Some(String::from("Never")),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Creates a [horizontal pod autoscaler](https://kubernetes.io/docs/tasks/run-application/horizontal-pod-autoscale/)
+/// that scales the deployment of the service based on its CPU utilization.
+pub fn horizontal_pod_autoscaler_payload(
+    app_name: &AppName,
+    config: &Config,
+    service_config: &ServiceConfig,
+    autoscale: &Autoscale,
+) -> HorizontalPodAutoscaler {
+    serde_json::from_value(serde_json::json!({
+      "apiVersion": "autoscaling/v2",
+      "kind": "HorizontalPodAutoscaler",
+      "metadata": {
+        "name": horizontal_pod_autoscaler_name(app_name, service_config),
+        "namespace": namespace_name(app_name, config),
+        "labels": labels_with_metadata(service_config, BTreeMap::from([
+          (APP_NAME_LABEL.to_string(), app_name.to_string()),
+          (SERVICE_NAME_LABEL.to_string(), service_config.service_name().to_string()),
+        ])),
+        "annotations": annotations_with_metadata(service_config, BTreeMap::new())
+      },
+      "spec": {
+        "scaleTargetRef": {
+          "apiVersion": "apps/v1",
+          "kind": "Deployment",
+          "name": format!("{}-{}-deployment", app_name.to_rfc1123_namespace_id(), service_config.service_name())
+        },
+        "minReplicas": autoscale.min().max(1),
+        "maxReplicas": autoscale.max().max(autoscale.min()),
+        "metrics": [{
+          "type": "Resource",
+          "resource": {
+            "name": "cpu",
+            "target": {
+              "type": "Utilization",
+              "averageUtilization": autoscale.cpu()
+            }
+          }
+        }]
+      }
+    }))
+    .expect("Cannot convert value to autoscaling/v2/HorizontalPodAutoscaler")
+}
+
+pub fn horizontal_pod_autoscaler_name(
+    app_name: &AppName,
+    service_config: &ServiceConfig,
+) -> String {
+    format!(
+        "{}-{}-autoscaler",
+        app_name.to_rfc1123_namespace_id(),
+        service_config.service_name()
+    )
+}
+
+fn service_account_name(app_name: &AppName, service_config: &ServiceConfig) -> String {
+    format!(
+        "{}-{}-service-account",
+        app_name.to_rfc1123_namespace_id(),
+        service_config.service_name()
+    )
+}
+
+/// Creates a [service account](https://kubernetes.io/docs/concepts/security/service-accounts/)
+/// for services that requested one.
+pub fn service_account_payload(
+    app_name: &AppName,
+    config: &Config,
+    service_config: &ServiceConfig,
+) -> V1ServiceAccount {
+    V1ServiceAccount {
+        metadata: ObjectMeta {
+            name: Some(service_account_name(app_name, service_config)),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(labels_with_metadata(
+                service_config,
+                BTreeMap::from([
+                    (APP_NAME_LABEL.to_string(), app_name.to_string()),
+                    (
+                        SERVICE_NAME_LABEL.to_string(),
+                        service_config.service_name().to_string(),
+                    ),
+                ]),
+            )),
+            annotations: Some(annotations_with_metadata(service_config, BTreeMap::new())),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Creates a role with the rules of the service's service account. The role is limited to the
+/// namespace of the app.
+pub fn role_payload(app_name: &AppName, config: &Config, service_config: &ServiceConfig) -> Role {
+    let rules = service_config
+        .service_account()
+        .map(|service_account| {
+            service_account
+                .rules()
+                .iter()
+                .map(|rule| PolicyRule {
+                    api_groups: Some(rule.api_groups().clone()),
+                    resources: Some(rule.resources().clone()),
+                    verbs: rule.verbs().clone(),
+                    ..Default::default()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Role {
+        metadata: ObjectMeta {
+            name: Some(service_account_name(app_name, service_config)),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(labels_with_metadata(
+                service_config,
+                BTreeMap::from([
+                    (APP_NAME_LABEL.to_string(), app_name.to_string()),
+                    (
+                        SERVICE_NAME_LABEL.to_string(),
+                        service_config.service_name().to_string(),
+                    ),
+                ]),
+            )),
+            annotations: Some(annotations_with_metadata(service_config, BTreeMap::new())),
+            ..Default::default()
+        },
+        rules: Some(rules),
+    }
+}
+
+/// Binds the role of [`role_payload`] to the service account of [`service_account_payload`].
+pub fn role_binding_payload(
+    app_name: &AppName,
+    config: &Config,
+    service_config: &ServiceConfig,
+) -> RoleBinding {
+    let name = service_account_name(app_name, service_config);
+
+    RoleBinding {
+        metadata: ObjectMeta {
+            name: Some(name.clone()),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(labels_with_metadata(
+                service_config,
+                BTreeMap::from([
+                    (APP_NAME_LABEL.to_string(), app_name.to_string()),
+                    (
+                        SERVICE_NAME_LABEL.to_string(),
+                        service_config.service_name().to_string(),
+                    ),
+                ]),
+            )),
+            annotations: Some(annotations_with_metadata(service_config, BTreeMap::new())),
+            ..Default::default()
+        },
+        role_ref: RoleRef {
+            api_group: String::from("rbac.authorization.k8s.io"),
+            kind: String::from("Role"),
+            name: name.clone(),
+        },
+        subjects: Some(vec![Subject {
+            kind: String::from("ServiceAccount"),
+            name,
+            namespace: Some(namespace_name(app_name, config)),
+            ..Default::default()
+        }]),
+    }
+}
+
 /// Creates a JSON payload suitable for [Kubernetes' Services](https://kubernetes.io/docs/concepts/services-networking/service/)
-pub fn service_payload(app_name: &AppName, service_config: &ServiceConfig) -> V1Service {
+pub fn service_payload(
+    app_name: &AppName,
+    config: &Config,
+    service_config: &ServiceConfig,
+) -> V1Service {
+    let (ip_family_policy, ip_families) = match config.runtime_config() {
+        crate::config::Runtime::Kubernetes(runtime) if !runtime.ip_families().is_empty() => (
+            runtime.ip_family_policy(),
+            Some(runtime.ip_families().to_vec()),
+        ),
+        crate::config::Runtime::Kubernetes(runtime) => (runtime.ip_family_policy(), None),
+        _ => (None, None),
+    };
+
+    let mut ports = vec![serde_json::json!({
+        "name": service_config.service_name(),
+        "targetPort": service_config.port(),
+        "port": service_config.port()
+    })];
+    ports.extend(service_config.additional_ports().iter().map(|port| {
+        serde_json::json!({
+            "name": format!("tcp-{port}"),
+            "targetPort": port,
+            "port": port
+        })
+    }));
+
     serde_json::from_value(serde_json::json!({
       "apiVersion": "v1",
       "kind": "Service",
-      "namespace": app_name.to_rfc1123_namespace_id(),
       "metadata": {
-        "name": service_config.service_name(),
-        APP_NAME_LABEL: app_name,
-        SERVICE_NAME_LABEL: service_config.service_name(),
-        CONTAINER_TYPE_LABEL: service_config.container_type().to_string()
+        "name": kubernetes_service_name(app_name, config, service_config.service_name()),
+        "namespace": namespace_name(app_name, config),
+        "labels": labels_with_metadata(service_config, BTreeMap::from([
+          (APP_NAME_LABEL.to_string(), app_name.to_string()),
+          (SERVICE_NAME_LABEL.to_string(), service_config.service_name().to_string()),
+          (CONTAINER_TYPE_LABEL.to_string(), service_config.container_type().to_string()),
+        ])),
+        "annotations": annotations_with_metadata(service_config, BTreeMap::new())
       },
       "spec": {
-        "ports": [
-          {
-            "name": service_config.service_name(),
-            "targetPort": service_config.port(),
-            "port": service_config.port()
-          }
-        ],
+        "ipFamilyPolicy": ip_family_policy,
+        "ipFamilies": ip_families,
+        "ports": ports,
         "selector": {
           APP_NAME_LABEL: app_name,
           SERVICE_NAME_LABEL: service_config.service_name(),
@@ -537,11 +1637,62 @@ pub fn service_payload(app_name: &AppName, service_config: &ServiceConfig) -> V1
     .expect("Cannot convert value to core/v1/Service")
 }
 
+/// Creates a service of the type `ExternalName` for each alias of the service so that the other
+/// services of the app can reach the service by its aliases.
+pub fn alias_service_payloads(
+    app_name: &AppName,
+    config: &Config,
+    service_config: &ServiceConfig,
+) -> Vec<V1Service> {
+    let external_name = format!(
+        "{}.{}.svc.{}",
+        kubernetes_service_name(app_name, config, service_config.service_name()),
+        namespace_name(app_name, config),
+        cluster_domain(config)
+    );
+    let labels = BTreeMap::from([
+        (APP_NAME_LABEL.to_string(), app_name.to_string()),
+        (
+            SERVICE_NAME_LABEL.to_string(),
+            service_config.service_name().to_string(),
+        ),
+        (
+            ALIAS_OF_LABEL.to_string(),
+            service_config.service_name().to_string(),
+        ),
+    ]);
+
+    service_config
+        .aliases()
+        .iter()
+        .map(|alias| {
+            serde_json::from_value(serde_json::json!({
+              "apiVersion": "v1",
+              "kind": "Service",
+              "metadata": {
+                "name": kubernetes_service_name(app_name, config, alias),
+                "namespace": namespace_name(app_name, config),
+                "labels": labels_with_metadata(service_config, labels.clone())
+              },
+              "spec": {
+                "type": "ExternalName",
+                "externalName": external_name
+              }
+            }))
+            .expect("Cannot convert value to core/v1/Service")
+        })
+        .collect()
+}
+
 /// Creates a payload that ensures that Traefik find the correct route in Kubernetes
 ///
 /// See [Traefik Routers](https://docs.traefik.io/v2.0/user-guides/crd-acme/#traefik-routers)
 /// for more information.
-pub fn ingress_route_payload(app_name: &AppName, service: &DeployableService) -> IngressRoute {
+pub fn ingress_route_payload(
+    app_name: &AppName,
+    config: &Config,
+    service: &DeployableService,
+) -> IngressRoute {
     let rules = service
         .ingress_route()
         .routes()
@@ -570,11 +1721,7 @@ pub fn ingress_route_payload(app_name: &AppName, service: &DeployableService) ->
                 kind: String::from("Rule"),
                 r#match: route.rule().to_string(),
                 middlewares: Some(middlewares),
-                services: vec![TraefikRuleService {
-                    kind: Some(String::from("Service")),
-                    name: service.service_name().to_string(),
-                    port: Some(service.port()),
-                }],
+                services: vec![traefik_rule_service(app_name, config, service)],
             }
         })
         .collect::<Vec<_>>();
@@ -586,22 +1733,29 @@ pub fn ingress_route_payload(app_name: &AppName, service: &DeployableService) ->
                 app_name.to_rfc1123_namespace_id(),
                 service.service_name()
             )),
-            namespace: Some(app_name.to_rfc1123_namespace_id()),
-            annotations: Some(BTreeMap::from([
-                (APP_NAME_LABEL.to_string(), app_name.to_string()),
-                (
-                    SERVICE_NAME_LABEL.to_string(),
-                    service.service_name().to_string(),
-                ),
-                (
-                    CONTAINER_TYPE_LABEL.to_string(),
-                    service.container_type().to_string(),
-                ),
-                (
-                    String::from("traefik.ingress.kubernetes.io/router.entrypoints"),
-                    String::from("web"),
-                ),
-            ])),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(labels_with_metadata(
+                service,
+                BTreeMap::from([(APP_NAME_LABEL.to_string(), app_name.to_string())]),
+            )),
+            annotations: Some(annotations_with_metadata(
+                service,
+                BTreeMap::from([
+                    (APP_NAME_LABEL.to_string(), app_name.to_string()),
+                    (
+                        SERVICE_NAME_LABEL.to_string(),
+                        service.service_name().to_string(),
+                    ),
+                    (
+                        CONTAINER_TYPE_LABEL.to_string(),
+                        service.container_type().to_string(),
+                    ),
+                    (
+                        String::from("traefik.ingress.kubernetes.io/router.entrypoints"),
+                        String::from("web"),
+                    ),
+                ]),
+            )),
             ..Default::default()
         },
         spec: IngressRouteSpec {
@@ -611,21 +1765,344 @@ pub fn ingress_route_payload(app_name: &AppName, service: &DeployableService) ->
     }
 }
 
-/// Creates a payload that ensures that Traefik strips out the path prefix.
-///
-/// See [Traefik Routers](https://docs.traefik.io/v2.0/user-guides/crd-acme/#traefik-routers)
-/// for more information.
-pub fn middleware_payload(app_name: &AppName, service: &DeployableService) -> Vec<Middleware> {
-    service
+/// Creates an Istio [virtual service](https://istio.io/latest/docs/reference/config/networking/virtual-service/)
+/// that routes the requests of the gateways to the service. The Traefik rules of the service are
+/// translated into Istio's matchers and the `stripPrefix` middleware into a rewrite of the URI.
+/// Other middlewares cannot be translated and are ignored.
+pub fn virtual_service_payload(
+    app_name: &AppName,
+    config: &Config,
+    istio: &IstioConfig,
+    service: &DeployableService,
+) -> VirtualService {
+    let namespace = namespace_name(app_name, config);
+    let destination = serde_json::json!({
+        "destination": {
+            "host": format!(
+                "{}.{namespace}.svc.{}",
+                kubernetes_service_name(app_name, config, service.service_name()),
+                cluster_domain(config)
+            ),
+            "port": { "number": service.port() }
+        }
+    });
+
+    let http = service
         .ingress_route()
         .routes()
         .iter()
-        .flat_map(|r| {
-            r.middlewares()
+        .map(|route| {
+            let mut matches = vec![Map::new()];
+            for matcher in route.rule().matches() {
+                let alternatives = match matcher {
+                    Matcher::Headers { key, value } => {
+                        vec![("headers", serde_json::json!({ key: { "exact": value } }))]
+                    }
+                    Matcher::Host { domains } => domains
+                        .iter()
+                        .map(|domain| ("authority", serde_json::json!({ "exact": domain })))
+                        .collect(),
+                    Matcher::PathPrefix { paths } => paths
+                        .iter()
+                        .map(|path| ("uri", serde_json::json!({ "prefix": path })))
+                        .collect(),
+                    Matcher::Expression(expression) => {
+                        warn!(
+                            "Cannot translate {expression} of {} into an Istio matcher",
+                            service.service_name()
+                        );
+                        continue;
+                    }
+                };
+
+                // Istio combines the fields of a matcher with AND and the matchers with OR.
+                matches = matches
+                    .into_iter()
+                    .flat_map(|m| {
+                        alternatives.iter().map(move |(field, value)| {
+                            let mut m = m.clone();
+                            m.insert(field.to_string(), value.clone());
+                            m
+                        })
+                    })
+                    .collect();
+            }
+
+            // The other middlewares have been rejected by `middlewares_unsupported_by_istio`
+            // before the deployment.
+            let rewrite = route
+                .middlewares()
                 .iter()
-                .filter_map(|middleware| match middleware {
-                    TraefikMiddleware::Ref(_) => None,
-                    TraefikMiddleware::Spec { name, spec } => Some((
+                .any(is_strip_prefix)
+                .then(|| serde_json::json!({ "uri": "/" }));
+
+            IstioHttpRoute {
+                r#match: matches.into_iter().map(Value::Object).collect(),
+                rewrite,
+                route: vec![destination.clone()],
+            }
+        })
+        .collect::<Vec<_>>();
+
+    VirtualService {
+        metadata: ObjectMeta {
+            name: Some(format!(
+                "{}-{}-virtual-service",
+                app_name.to_rfc1123_namespace_id(),
+                service.service_name()
+            )),
+            namespace: Some(namespace),
+            labels: Some(labels_with_metadata(
+                service,
+                BTreeMap::from([(APP_NAME_LABEL.to_string(), app_name.to_string())]),
+            )),
+            ..Default::default()
+        },
+        spec: VirtualServiceSpec {
+            hosts: istio.hosts().clone(),
+            gateways: istio.gateways().clone(),
+            http,
+        },
+    }
+}
+
+fn is_strip_prefix(middleware: &TraefikMiddleware) -> bool {
+    match middleware {
+        TraefikMiddleware::Spec { spec, .. } => {
+            serde_json::json!(spec).get("stripPrefix").is_some()
+        }
+        TraefikMiddleware::Ref(_) => false,
+    }
+}
+
+/// Returns the middlewares of the service that the virtual service of Istio cannot enforce.
+/// Only `stripPrefix` can be translated into a rewrite, all other middlewares, e.g. the forward
+/// authentication or IP allowlists, would be lost.
+pub fn middlewares_unsupported_by_istio(service: &DeployableService) -> Vec<&TraefikMiddleware> {
+    service
+        .ingress_route()
+        .routes()
+        .iter()
+        .flat_map(|route| route.middlewares())
+        .filter(|middleware| !is_strip_prefix(middleware))
+        .collect()
+}
+
+/// Creates an ingress route that routes the requests of the app's host to the service. If
+/// cert-manager issues the certificate of the app, the route is served by TLS with the
+/// certificate's secret. The route carries the annotations of the app host, e.g. for ExternalDNS.
+pub fn host_ingress_route_payload(
+    app_name: &AppName,
+    config: &Config,
+    app_host: &KubernetesAppHostConfig,
+    service: &DeployableService,
+) -> IngressRoute {
+    let host = app_host.host(&app_name.to_rfc1123_namespace_id());
+    let mut route = ingress_route_payload(app_name, config, service);
+
+    route.metadata.name = Some(format!(
+        "{}-{}-host-ingress-route",
+        app_name.to_rfc1123_namespace_id(),
+        service.service_name()
+    ));
+    if let Some(rules) = route.spec.routes.as_mut() {
+        for (rule, service_route) in rules.iter_mut().zip(service.ingress_route().routes()) {
+            rule.r#match = service_route.rule().with_host(&host).to_string();
+        }
+    }
+    route
+        .metadata
+        .annotations
+        .get_or_insert_with(BTreeMap::new)
+        .extend(app_host.annotations(&app_name.to_rfc1123_namespace_id()));
+    route.spec.tls = app_host.cert_manager().map(|_| TraefikTls {
+        cert_resolver: None,
+        secret_name: Some(certificate_secret_name(app_name)),
+    });
+
+    route
+}
+
+/// Creates a cert-manager [certificate](https://cert-manager.io/docs/usage/certificate/) for the
+/// host of the app. The secret of the certificate carries the app label so that it is deleted
+/// with the app.
+pub fn certificate_payload(
+    app_name: &AppName,
+    config: &Config,
+    app_host: &KubernetesAppHostConfig,
+    cert_manager: &CertManagerConfig,
+) -> Certificate {
+    let labels = BTreeMap::from([(APP_NAME_LABEL.to_string(), app_name.to_string())]);
+
+    Certificate {
+        metadata: ObjectMeta {
+            name: Some(format!(
+                "{}-certificate",
+                app_name.to_rfc1123_namespace_id()
+            )),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(labels.clone()),
+            ..Default::default()
+        },
+        spec: CertificateSpec {
+            secret_name: certificate_secret_name(app_name),
+            dns_names: vec![app_host.host(&app_name.to_rfc1123_namespace_id())],
+            issuer_ref: CertificateIssuerRef {
+                name: cert_manager.issuer().clone(),
+                kind: cert_manager.issuer_kind().clone(),
+            },
+            secret_template: Some(CertificateSecretTemplate {
+                labels: Some(labels),
+            }),
+        },
+    }
+}
+
+/// The name of the network policy that restricts the outbound traffic of the app. The name is
+/// unique per app because the apps may share a namespace.
+pub fn egress_network_policy_name(app_name: &AppName) -> String {
+    format!("{}-egress", app_name.to_rfc1123_namespace_id())
+}
+
+/// Creates a [network policy](https://kubernetes.io/docs/concepts/services-networking/network-policies/)
+/// that restricts the outbound traffic of all pods of the app to the pods of the app, to DNS
+/// lookups, and to the allowed CIDRs.
+pub fn egress_network_policy_payload(
+    app_name: &AppName,
+    config: &Config,
+    allowed_cidrs: &BTreeSet<String>,
+) -> NetworkPolicy {
+    let pods_of_app = LabelSelector {
+        match_labels: Some(BTreeMap::from([(
+            APP_NAME_LABEL.to_string(),
+            app_name.to_string(),
+        )])),
+        ..Default::default()
+    };
+
+    let mut egress = vec![
+        NetworkPolicyEgressRule {
+            to: Some(vec![NetworkPolicyPeer {
+                pod_selector: Some(pods_of_app.clone()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        },
+        NetworkPolicyEgressRule {
+            to: Some(vec![NetworkPolicyPeer {
+                namespace_selector: Some(LabelSelector::default()),
+                ..Default::default()
+            }]),
+            ports: Some(
+                ["UDP", "TCP"]
+                    .into_iter()
+                    .map(|protocol| NetworkPolicyPort {
+                        port: Some(IntOrString::Int(53)),
+                        protocol: Some(String::from(protocol)),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+        },
+    ];
+    if !allowed_cidrs.is_empty() {
+        egress.push(NetworkPolicyEgressRule {
+            to: Some(
+                allowed_cidrs
+                    .iter()
+                    .map(|cidr| NetworkPolicyPeer {
+                        ip_block: Some(IPBlock {
+                            cidr: cidr.clone(),
+                            except: None,
+                        }),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        });
+    }
+
+    NetworkPolicy {
+        metadata: ObjectMeta {
+            name: Some(egress_network_policy_name(app_name)),
+            namespace: Some(namespace_name(app_name, config)),
+            labels: Some(BTreeMap::from([(
+                APP_NAME_LABEL.to_string(),
+                app_name.to_string(),
+            )])),
+            ..Default::default()
+        },
+        spec: Some(NetworkPolicySpec {
+            pod_selector: pods_of_app,
+            policy_types: Some(vec![String::from("Egress")]),
+            egress: Some(egress),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn certificate_secret_name(app_name: &AppName) -> String {
+    format!("{}-tls", app_name.to_rfc1123_namespace_id())
+}
+
+fn traefik_rule_service(
+    app_name: &AppName,
+    config: &Config,
+    service: &DeployableService,
+) -> TraefikRuleService {
+    let load_balancer = service.load_balancer();
+
+    TraefikRuleService {
+        kind: Some(String::from("Service")),
+        name: kubernetes_service_name(app_name, config, service.service_name()),
+        port: Some(service.port()),
+        sticky: load_balancer
+            .and_then(|load_balancer| load_balancer.sticky())
+            .map(|sticky| {
+                let cookie = sticky.cookie();
+                let mut cookie_payload = Map::new();
+                if let Some(name) = cookie.name() {
+                    cookie_payload.insert(String::from("name"), serde_json::json!(name));
+                }
+                cookie_payload.insert(String::from("secure"), serde_json::json!(cookie.secure()));
+                cookie_payload.insert(String::from("httpOnly"), serde_json::json!(cookie.http_only()));
+                if let Some(same_site) = cookie.same_site() {
+                    cookie_payload.insert(String::from("sameSite"), serde_json::json!(same_site));
+                }
+                serde_json::json!({ "cookie": cookie_payload })
+            }),
+        response_forwarding: load_balancer
+            .and_then(|load_balancer| load_balancer.response_forwarding())
+            .map(|response_forwarding| {
+                serde_json::json!({ "flushInterval": response_forwarding.flush_interval() })
+            }),
+        servers_transport: load_balancer
+            .and_then(|load_balancer| load_balancer.servers_transport())
+            .cloned(),
+    }
+}
+
+/// Creates a payload that ensures that Traefik strips out the path prefix.
+///
+/// See [Traefik Routers](https://docs.traefik.io/v2.0/user-guides/crd-acme/#traefik-routers)
+/// for more information.
+pub fn middleware_payload(
+    app_name: &AppName,
+    config: &Config,
+    service: &DeployableService,
+) -> Vec<Middleware> {
+    service
+        .ingress_route()
+        .routes()
+        .iter()
+        .flat_map(|r| {
+            r.middlewares()
+                .iter()
+                .filter_map(|middleware| match middleware {
+                    TraefikMiddleware::Ref(_) => None,
+                    TraefikMiddleware::Spec { name, spec } => Some((
                         AppName::from_str(name)
                             .map(|app_name| app_name.to_rfc1123_namespace_id())
                             .unwrap_or_else(|_| name.clone()),
@@ -636,7 +2113,11 @@ pub fn middleware_payload(app_name: &AppName, service: &DeployableService) -> Ve
         .map(|(name, spec)| Middleware {
             metadata: ObjectMeta {
                 name: Some(name),
-                namespace: Some(app_name.to_rfc1123_namespace_id()),
+                namespace: Some(namespace_name(app_name, config)),
+                labels: Some(BTreeMap::from([(
+                    APP_NAME_LABEL.to_string(),
+                    app_name.to_string(),
+                )])),
                 ..Default::default()
             },
             spec: MiddlewareSpec(serde_json::json!(spec)),
@@ -702,21 +2183,28 @@ pub fn persistent_volume_claim_payload(
                 app_name.to_rfc1123_namespace_id(),
                 service.service_name()
             )),
-            labels: Some(BTreeMap::from([
-                (APP_NAME_LABEL.to_owned(), app_name.to_string()),
-                (
-                    SERVICE_NAME_LABEL.to_owned(),
-                    service.service_name().to_owned(),
-                ),
-                (
-                    STORAGE_TYPE_LABEL.to_owned(),
-                    declared_volume
-                        .split('/')
-                        .last()
-                        .unwrap_or("default")
-                        .to_owned(),
-                ),
-            ])),
+            labels: Some(labels_with_metadata(
+                service,
+                BTreeMap::from([
+                    (APP_NAME_LABEL.to_owned(), app_name.to_string()),
+                    (
+                        SERVICE_NAME_LABEL.to_owned(),
+                        service.service_name().to_owned(),
+                    ),
+                    (
+                        STORAGE_TYPE_LABEL.to_owned(),
+                        declared_volume
+                            .split('/')
+                            .last()
+                            .unwrap_or("default")
+                            .to_owned(),
+                    ),
+                ]),
+            )),
+            annotations: service
+                .metadata()
+                .filter(|metadata| !metadata.annotations().is_empty())
+                .map(|metadata| metadata.annotations().clone()),
             ..Default::default()
         },
         spec: Some(PersistentVolumeClaimSpec {
@@ -739,85 +2227,1342 @@ pub fn persistent_volume_claim_payload(
 mod tests {
     use super::*;
     use crate::infrastructure::{TraefikIngressRoute, TraefikRouterRule};
-    use crate::models::{AppName, Environment, EnvironmentVariable};
+    use crate::models::{
+        AppName, Environment, EnvironmentVariable, ImagePullPolicy, ResourceMetadata, TimeZone,
+    };
     use crate::sc;
     use std::str::FromStr;
 
     #[test]
-    fn should_create_deployment_payload() {
-        let config = sc!("db", "mariadb:10.3.17");
+    fn should_create_deployment_payload() {
+        let config = sc!("db", "mariadb:10.3.17");
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "db",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "apiVersion": "apps/v1",
+              "kind": "Deployment",
+              "metadata": {
+                "annotations": {
+                  "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17"
+                },
+                "labels": {
+                  "com.aixigo.preview.servant.app-name": "master",
+                  "com.aixigo.preview.servant.container-type": "instance",
+                  "com.aixigo.preview.servant.service-name": "db"
+                },
+                "name": "master-db-deployment",
+                "namespace": "master"
+              },
+              "spec": {
+                "replicas": 1,
+                "selector": {
+                  "matchLabels": {
+                    "com.aixigo.preview.servant.app-name": "master",
+                    "com.aixigo.preview.servant.container-type": "instance",
+                    "com.aixigo.preview.servant.service-name": "db"
+                  }
+                },
+                "template": {
+                  "metadata": {
+                    "annotations": {
+                    },
+                    "labels": {
+                      "com.aixigo.preview.servant.app-name": "master",
+                      "com.aixigo.preview.servant.container-type": "instance",
+                      "com.aixigo.preview.servant.service-name": "db"
+                    }
+                  },
+                  "spec": {
+                    "containers": [
+                      {
+                        "image": "docker.io/library/mariadb:10.3.17",
+                        "imagePullPolicy": "Always",
+                        "name": "db",
+                        "ports": [
+                          {
+                            "containerPort": 80
+                          }
+                        ]
+                      }
+                    ]
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_metadata() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_metadata(Some(ResourceMetadata::new(
+            BTreeMap::from([
+                (String::from("cost-center"), String::from("4711")),
+                (
+                    String::from("com.aixigo.preview.servant.app-name"),
+                    String::from("other"),
+                ),
+            ]),
+            BTreeMap::from([(String::from("example.com/owner"), String::from("team-a"))]),
+        )));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployNever,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "db",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "metadata": {
+                "annotations": {
+                  "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17",
+                  "example.com/owner": "team-a"
+                },
+                "labels": {
+                  "com.aixigo.preview.servant.app-name": "master",
+                  "com.aixigo.preview.servant.container-type": "instance",
+                  "com.aixigo.preview.servant.service-name": "db",
+                  "cost-center": "4711"
+                }
+              },
+              "spec": {
+                "template": {
+                  "metadata": {
+                    "annotations": {
+                      "example.com/owner": "team-a"
+                    },
+                    "labels": {
+                      "com.aixigo.preview.servant.app-name": "master",
+                      "cost-center": "4711"
+                    }
+                  }
+                }
+              }
+            })
+        );
+        assert_eq!(
+            payload.spec.unwrap().selector.match_labels.unwrap().len(),
+            3,
+            "The selector must not contain user defined labels"
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_command_args_and_working_dir() {
+        let mut config = sc!("worker", "example/app:latest");
+        config.set_command(Some(vec![String::from("/bin/sh"), String::from("-c")]));
+        config.set_args(Some(vec![String::from("./worker --queue jobs")]));
+        config.set_working_dir(Some(String::from("/opt/app")));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "worker",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "containers": [{
+                      "command": ["/bin/sh", "-c"],
+                      "args": ["./worker --queue jobs"],
+                      "workingDir": "/opt/app"
+                    }]
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_startup_probe_and_termination_grace_period() {
+        let mut config = sc!("wildfly", "jboss/wildfly:latest");
+        config.set_startup_probe(Some(
+            serde_json::from_value(serde_json::json!({
+                "path": "/health",
+                "failureThreshold": 60
+            }))
+            .unwrap(),
+        ));
+        config.set_termination_grace_period(Some(std::time::Duration::from_secs(120)));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "wildfly",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "containers": [{
+                      "startupProbe": {
+                        "httpGet": {
+                          "path": "/health",
+                          "port": 80
+                        },
+                        "initialDelaySeconds": 0,
+                        "periodSeconds": 10,
+                        "failureThreshold": 60
+                      }
+                    }],
+                    "terminationGracePeriodSeconds": 120
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_scratch_volumes() {
+        let mut config = sc!("wildfly", "jboss/wildfly:latest");
+        config.set_scratch_volumes(
+            serde_json::from_value(serde_json::json!([
+                { "path": "/var/cache/wildfly", "sizeLimit": "1Gi" },
+                { "path": "/tmp", "sizeLimit": "64Mi", "medium": "Memory" }
+            ]))
+            .unwrap(),
+        );
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "wildfly",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "containers": [{
+                      "volumeMounts": [
+                        { "name": "scratch-0", "mountPath": "/var/cache/wildfly" },
+                        { "name": "scratch-1", "mountPath": "/tmp" }
+                      ]
+                    }],
+                    "volumes": [
+                      { "name": "scratch-0", "emptyDir": { "sizeLimit": "1Gi" } },
+                      { "name": "scratch-1", "emptyDir": { "medium": "Memory", "sizeLimit": "64Mi" } }
+                    ]
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_read_only_root_filesystem() {
+        let mut config = sc!("nginx", "nginx:latest");
+        config.set_read_only_root_filesystem(true);
+        config.set_writable_paths(vec![
+            PathBuf::from("/var/cache/nginx"),
+            PathBuf::from("/var/run"),
+        ]);
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "nginx",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "containers": [{
+                      "securityContext": { "readOnlyRootFilesystem": true },
+                      "volumeMounts": [
+                        { "name": "scratch-0", "mountPath": "/var/cache/nginx" },
+                        { "name": "scratch-1", "mountPath": "/var/run" }
+                      ]
+                    }],
+                    "volumes": [
+                      { "name": "scratch-0", "emptyDir": {} },
+                      { "name": "scratch-1", "emptyDir": {} }
+                    ]
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_egress_network_policy_payload() {
+        let payload = egress_network_policy_payload(
+            &AppName::master(),
+            &Default::default(),
+            &BTreeSet::from([String::from("10.10.0.0/16")]),
+        );
+
+        assert_eq!(
+            serde_json::to_value(payload).unwrap(),
+            serde_json::json!({
+              "apiVersion": "networking.k8s.io/v1",
+              "kind": "NetworkPolicy",
+              "metadata": {
+                "name": "master-egress",
+                "namespace": "master",
+                "labels": { "com.aixigo.preview.servant.app-name": "master" }
+              },
+              "spec": {
+                "podSelector": {
+                  "matchLabels": { "com.aixigo.preview.servant.app-name": "master" }
+                },
+                "policyTypes": ["Egress"],
+                "egress": [
+                  {
+                    "to": [{
+                      "podSelector": {
+                        "matchLabels": { "com.aixigo.preview.servant.app-name": "master" }
+                      }
+                    }]
+                  },
+                  {
+                    "to": [{ "namespaceSelector": {} }],
+                    "ports": [
+                      { "port": 53, "protocol": "UDP" },
+                      { "port": 53, "protocol": "TCP" }
+                    ]
+                  },
+                  { "to": [{ "ipBlock": { "cidr": "10.10.0.0/16" } }] }
+                ]
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_fixed_port() {
+        let mut config = sc!("wildfly", "jboss/wildfly:latest");
+        config.set_fixed_port(9990);
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "wildfly",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "metadata": {
+                "annotations": { FIXED_PORT_LABEL: "9990" }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_time_zone_without_host_path() {
+        let mut config = sc!("wildfly", "jboss/wildfly:latest");
+        config.set_timezone(Some(TimeZone::from_str("Europe/Berlin").unwrap()));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "wildfly",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        // The variable TZ provides the time zone, host paths are forbidden by the baseline pod
+        // security standard.
+        let pod_spec = payload.spec.unwrap().template.spec.unwrap();
+        assert_eq!(pod_spec.volumes, None);
+        assert_eq!(pod_spec.containers[0].volume_mounts, None);
+    }
+
+    #[test]
+    fn should_create_secret_files_patch_for_mounted_files() {
+        let config = sc!(
+            "api",
+            labels = (),
+            env = (),
+            files = ("/etc/api/api.conf" => "a=1")
+        );
+        let deployment = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "api",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        let patch = secret_files_patch(
+            &deployment,
+            &BTreeMap::from([(PathBuf::from("/etc/api/api.conf"), SecUtf8::from("a=2"))]),
+        );
+        assert_eq!(
+            patch,
+            Ok(serde_json::json!({ "data": { "api-conf": "YT0y" } }))
+        );
+
+        let patch = secret_files_patch(
+            &deployment,
+            &BTreeMap::from([(PathBuf::from("/etc/other/api.conf"), SecUtf8::from("a=2"))]),
+        );
+        assert_eq!(patch, Err(PathBuf::from("/etc/other/api.conf")));
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_host_aliases_and_dns_config() {
+        let mut config = sc!("wildfly", "jboss/wildfly:latest");
+        config.set_host_aliases(
+            serde_json::from_value(serde_json::json!([{
+                "ip": "10.0.0.15",
+                "hostnames": ["ldap.corp.example.com", "ldap"]
+            }]))
+            .unwrap(),
+        );
+        config.set_dns_config(Some(
+            serde_json::from_value(serde_json::json!({
+                "nameservers": ["10.0.0.2"],
+                "searches": ["corp.example.com"]
+            }))
+            .unwrap(),
+        ));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "wildfly",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "hostAliases": [{
+                      "ip": "10.0.0.15",
+                      "hostnames": ["ldap.corp.example.com", "ldap"]
+                    }],
+                    "dnsConfig": {
+                      "nameservers": ["10.0.0.2"],
+                      "searches": ["corp.example.com"]
+                    }
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_node_affinity_of_forced_platform() {
+        let mut config = sc!("wildfly", "jboss/wildfly:latest");
+        config.set_platform(Some(String::from("linux/arm64")));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "wildfly",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "affinity": {
+                      "nodeAffinity": {
+                        "requiredDuringSchedulingIgnoredDuringExecution": {
+                          "nodeSelectorTerms": [{
+                            "matchExpressions": [
+                              { "key": "kubernetes.io/os", "operator": "In", "values": ["linux"] },
+                              { "key": "kubernetes.io/arch", "operator": "In", "values": ["arm64"] }
+                            ]
+                          }]
+                        }
+                      }
+                    }
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_for_windows_nodes() {
+        let mut config = sc!("legacy", "mcr.microsoft.com/dotnet/framework/aspnet:4.8");
+        config.set_os(Some(OperatingSystem::Windows));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "legacy",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "nodeSelector": { "kubernetes.io/os": "windows" },
+                    "tolerations": [{
+                      "key": "node.kubernetes.io/os",
+                      "operator": "Equal",
+                      "value": "windows",
+                      "effect": "NoSchedule"
+                    }],
+                    "os": { "name": "windows" }
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_host_ingress_route_and_certificate_payload() {
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+            [runtime.appHost]
+            template = '{app}.previews.example.com'
+            externalDns = true
+            [runtime.appHost.certManager]
+            issuer = 'letsencrypt'
+            "#,
+        )
+        .unwrap();
+        let crate::config::Runtime::Kubernetes(runtime) = config.runtime_config() else {
+            unreachable!()
+        };
+        let app_host = runtime.app_host().unwrap();
+
+        let route = host_ingress_route_payload(
+            &AppName::master(),
+            &config,
+            app_host,
+            &DeployableService::new(
+                sc!("wildfly", "jboss/wildfly:latest"),
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_defaults(&AppName::master(), "wildfly"),
+                Vec::new(),
+            ),
+        );
+        let certificate = certificate_payload(
+            &AppName::master(),
+            &config,
+            app_host,
+            app_host.cert_manager().unwrap(),
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: route,
+            expected: serde_json::json!({
+              "metadata": {
+                "name": "master-wildfly-host-ingress-route",
+                "annotations": {
+                  "external-dns.alpha.kubernetes.io/hostname": "master.previews.example.com"
+                }
+              },
+              "spec": {
+                "routes": [{
+                  "match": "Host(`master.previews.example.com`) && PathPrefix(`/master/wildfly/`)"
+                }],
+                "tls": { "secretName": "master-tls" }
+              }
+            })
+        );
+        assert_json_diff::assert_json_include!(
+            actual: certificate,
+            expected: serde_json::json!({
+              "apiVersion": "cert-manager.io/v1",
+              "kind": "Certificate",
+              "metadata": { "name": "master-certificate", "namespace": "master" },
+              "spec": {
+                "secretName": "master-tls",
+                "dnsNames": ["master.previews.example.com"],
+                "issuerRef": { "name": "letsencrypt", "kind": "ClusterIssuer" },
+                "secretTemplate": { "labels": { APP_NAME_LABEL: "master" } }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_convert_existing_ingress_route() {
+        let route = IngressRoute::new(
+            "prevant",
+            IngressRouteSpec {
+                entrypoints: Some(vec![String::from("websecure")]),
+                routes: Some(vec![TraefikRuleSpec {
+                    kind: String::from("Rule"),
+                    r#match: String::from(
+                        "Host(`prevant.example.com`) && (PathPrefix(`/`) || Method(`GET`))",
+                    ),
+                    services: Vec::new(),
+                    middlewares: None,
+                }]),
+                tls: None,
+            },
+        );
+
+        let route = TraefikIngressRoute::try_from(route).unwrap();
+
+        assert_eq!(
+            route.routes()[0].rule(),
+            &TraefikRouterRule::from_str(
+                "Host(`prevant.example.com`) && (PathPrefix(`/`) || Method(`GET`))"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn should_not_convert_existing_ingress_route_with_invalid_rule() {
+        let route = IngressRoute::new(
+            "prevant",
+            IngressRouteSpec {
+                entrypoints: None,
+                routes: Some(vec![TraefikRuleSpec {
+                    kind: String::from("Rule"),
+                    r#match: String::from("Host(`prevant.example.com`"),
+                    services: Vec::new(),
+                    middlewares: None,
+                }]),
+                tls: None,
+            },
+        );
+
+        assert!(TraefikIngressRoute::try_from(route).is_err());
+        assert!(TraefikIngressRoute::try_from(IngressRoute::new(
+            "prevant",
+            IngressRouteSpec::default()
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn should_create_virtual_service_payload() {
+        let istio = serde_json::from_value::<IstioConfig>(serde_json::json!({
+            "gateways": ["istio-system/previews"]
+        }))
+        .unwrap();
+
+        let payload = virtual_service_payload(
+            &AppName::master(),
+            &Default::default(),
+            &istio,
+            &DeployableService::new(
+                sc!("wildfly", "jboss/wildfly:latest"),
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_defaults(&AppName::master(), "wildfly"),
+                Vec::new(),
+            ),
+        );
+
+        assert_json_diff::assert_json_eq!(
+            payload,
+            serde_json::json!({
+              "apiVersion": "networking.istio.io/v1beta1",
+              "kind": "VirtualService",
+              "metadata": {
+                "name": "master-wildfly-virtual-service",
+                "namespace": "master",
+                "labels": {
+                  APP_NAME_LABEL: "master"
+                }
+              },
+              "spec": {
+                "hosts": ["*"],
+                "gateways": ["istio-system/previews"],
+                "http": [{
+                  "match": [{ "uri": { "prefix": "/master/wildfly/" } }],
+                  "rewrite": { "uri": "/" },
+                  "route": [{
+                    "destination": {
+                      "host": "wildfly.master.svc.cluster.local",
+                      "port": { "number": 80 }
+                    }
+                  }]
+                }]
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_find_middlewares_unsupported_by_istio() {
+        let mut config = sc!("wildfly", "jboss/wildfly:latest");
+        let mut middlewares = BTreeMap::new();
+        middlewares.insert(
+            String::from("forwardAuth"),
+            serde_value::to_value(serde_json::json!({
+                "address": "http://prevant.tools.svc/auth/forward"
+            }))
+            .unwrap(),
+        );
+        config.set_middlewares(middlewares);
+        let mut ingress_route = TraefikIngressRoute::with_defaults(&AppName::master(), "wildfly");
+        ingress_route.add_middlewares(&AppName::master(), "wildfly", config.middlewares().unwrap());
+
+        let service = DeployableService::new(
+            config,
+            DeploymentStrategy::RedeployAlways,
+            ingress_route,
+            Vec::new(),
+        );
+        let unsupported = middlewares_unsupported_by_istio(&service);
+
+        assert_eq!(unsupported.len(), 1);
+        assert!(matches!(
+            unsupported[0],
+            TraefikMiddleware::Spec { spec, .. } if serde_json::json!(spec).get("forwardAuth").is_some()
+        ));
+        assert!(middlewares_unsupported_by_istio(&DeployableService::new(
+            sc!("wildfly", "jboss/wildfly:latest"),
+            DeploymentStrategy::RedeployAlways,
+            TraefikIngressRoute::with_defaults(&AppName::master(), "wildfly"),
+            Vec::new(),
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_replicas() {
+        let mut config = sc!("api", "example/api:latest");
+        config.set_replicas(Some(3));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "api",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "metadata": {
+                "annotations": {
+                  "com.aixigo.preview.servant.replicas": "3"
+                }
+              },
+              "spec": {
+                "replicas": 3
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_paused_deployment_payload_without_replicas() {
+        let mut config = sc!("api", "example/api:latest");
+        config.set_replicas(Some(0));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "api",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_eq!(payload.spec.unwrap().replicas, Some(0));
+    }
+
+    #[test]
+    fn should_create_alias_service_payloads() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_aliases(vec![String::from("mysql"), String::from("database")]);
+
+        let payloads = alias_service_payloads(
+            &AppName::from_str("master").unwrap(),
+            &Default::default(),
+            &config,
+        );
+
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0].metadata.name, Some(String::from("mysql")));
+        let spec = payloads[0].spec.as_ref().unwrap();
+        assert_eq!(spec.type_, Some(String::from("ExternalName")));
+        assert_eq!(
+            spec.external_name,
+            Some(String::from("db.master.svc.cluster.local"))
+        );
+    }
+
+    #[test]
+    fn should_create_alias_service_payloads_in_configured_cluster_domain() {
+        let mut service_config = sc!("db", "mariadb:10.3.17");
+        service_config.set_aliases(vec![String::from("mysql")]);
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+            clusterDomain = 'k8s.example.com'
+            "#,
+        )
+        .unwrap();
+
+        let payloads = alias_service_payloads(&AppName::master(), &config, &service_config);
+
+        assert_eq!(
+            payloads[0].spec.as_ref().unwrap().external_name,
+            Some(String::from("db.master.svc.k8s.example.com"))
+        );
+        assert_eq!(
+            payloads[0]
+                .metadata
+                .labels
+                .as_ref()
+                .unwrap()
+                .get(ALIAS_OF_LABEL),
+            Some(&String::from("db"))
+        );
+    }
+
+    #[test]
+    fn should_create_horizontal_pod_autoscaler_payload() {
+        let mut config = sc!("api", "example/api:latest");
+        let autoscale = toml::de::from_str::<Autoscale>("min = 2\nmax = 5\ncpu = 70").unwrap();
+        config.set_autoscale(Some(autoscale.clone()));
+
+        let deployment = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config.clone(),
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "api",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+        assert_eq!(
+            deployment.spec.unwrap().replicas,
+            None,
+            "The replicas are controlled by the autoscaler"
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: horizontal_pod_autoscaler_payload(&AppName::master(), &Default::default(), &config, &autoscale),
+            expected: serde_json::json!({
+              "metadata": {
+                "name": "master-api-autoscaler",
+                "namespace": "master"
+              },
+              "spec": {
+                "scaleTargetRef": {
+                  "apiVersion": "apps/v1",
+                  "kind": "Deployment",
+                  "name": "master-api-deployment"
+                },
+                "minReplicas": 2,
+                "maxReplicas": 5,
+                "metrics": [{
+                  "type": "Resource",
+                  "resource": {
+                    "name": "cpu",
+                    "target": {
+                      "type": "Utilization",
+                      "averageUtilization": 70
+                    }
+                  }
+                }]
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_extended_resources() {
+        let mut config = sc!("model", "example/model:latest");
+        config.set_extended_resources(BTreeMap::from([(
+            String::from("nvidia.com/gpu"),
+            String::from("1"),
+        )]));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "model",
+                ])),
+                Vec::new(),
+            ),
+            &toml::from_str::<ContainerConfig>("memory_limit = '1g'").unwrap(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "containers": [{
+                      "resources": {
+                        "limits": {
+                          "memory": "1073741824",
+                          "nvidia.com/gpu": "1"
+                        }
+                      }
+                    }]
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_cpu_limit() {
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                sc!("db", "mariadb:10.3.17"),
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "db",
+                ])),
+                Vec::new(),
+            ),
+            &toml::from_str::<ContainerConfig>("cpu_limit = 1.5").unwrap(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "containers": [{
+                      "resources": {
+                        "limits": {
+                          "cpu": "1500m"
+                        }
+                      }
+                    }]
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_image_pull_policy_of_service() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_image_pull_policy(Some(ImagePullPolicy::Never));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config,
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master", "db",
+                ])),
+                Vec::new(),
+            ),
+            &toml::from_str::<ContainerConfig>("image_pull_policy = 'IfNotPresent'").unwrap(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "containers": [{
+                      "imagePullPolicy": "Never"
+                    }]
+                  }
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_payload_with_service_account() {
+        let mut config = sc!("jenkins-agent", "jenkins/inbound-agent:latest");
+        config.set_service_account(Some(
+            serde_json::from_value(serde_json::json!({
+                "rules": [{ "resources": ["pods"], "verbs": ["get", "create"] }]
+            }))
+            .unwrap(),
+        ));
+
+        let payload = deployment_payload(
+            &AppName::master(),
+            &Default::default(),
+            &DeployableService::new(
+                config.clone(),
+                DeploymentStrategy::RedeployAlways,
+                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
+                    "master",
+                    "jenkins-agent",
+                ])),
+                Vec::new(),
+            ),
+            &ContainerConfig::default(),
+            false,
+            &None,
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "template": {
+                  "spec": {
+                    "serviceAccountName": "master-jenkins-agent-service-account"
+                  }
+                }
+              }
+            })
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: role_payload(&AppName::master(), &Default::default(), &config),
+            expected: serde_json::json!({
+              "metadata": {
+                "name": "master-jenkins-agent-service-account",
+                "namespace": "master"
+              },
+              "rules": [{
+                "apiGroups": [""],
+                "resources": ["pods"],
+                "verbs": ["get", "create"]
+              }]
+            })
+        );
+
+        assert_json_diff::assert_json_include!(
+            actual: role_binding_payload(&AppName::master(), &Default::default(), &config),
+            expected: serde_json::json!({
+              "roleRef": {
+                "apiGroup": "rbac.authorization.k8s.io",
+                "kind": "Role",
+                "name": "master-jenkins-agent-service-account"
+              },
+              "subjects": [{
+                "kind": "ServiceAccount",
+                "name": "master-jenkins-agent-service-account",
+                "namespace": "master"
+              }]
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_service_payload_with_metadata() {
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_metadata(Some(ResourceMetadata::new(
+            BTreeMap::from([(String::from("cost-center"), String::from("4711"))]),
+            BTreeMap::from([(String::from("example.com/owner"), String::from("team-a"))]),
+        )));
+
+        let payload = service_payload(&AppName::master(), &Default::default(), &config);
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "metadata": {
+                "name": "db",
+                "annotations": {
+                  "example.com/owner": "team-a"
+                },
+                "labels": {
+                  "cost-center": "4711"
+                }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_service_payload_with_additional_ports() {
+        let mut service_config = sc!("mail", "mailhog/mailhog:v1.0.1");
+        service_config.set_fixed_port(8025);
+        service_config.set_additional_ports(vec![1025]);
+
+        let payload = service_payload(&AppName::master(), &Config::default(), &service_config);
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "ports": [
+                  { "name": "mail", "port": 8025, "targetPort": 8025 },
+                  { "name": "tcp-1025", "port": 1025, "targetPort": 1025 }
+                ]
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_dual_stack_service_payload() {
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+            ipFamilyPolicy = 'PreferDualStack'
+            ipFamilies = [ 'IPv6', 'IPv4' ]
+            "#,
+        )
+        .unwrap();
+
+        let payload = service_payload(&AppName::master(), &config, &sc!("db", "mariadb:10.3.17"));
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "spec": {
+                "ipFamilyPolicy": "PreferDualStack",
+                "ipFamilies": [ "IPv6", "IPv4" ]
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn should_create_service_payload_in_named_namespace() {
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+            [runtime.namespace]
+            name = 'preview-{app}'
+            mode = 'Existing'
+            "#,
+        )
+        .unwrap();
 
-        let payload = deployment_payload(
-            &AppName::master(),
-            &DeployableService::new(
-                config,
-                DeploymentStrategy::RedeployAlways,
-                TraefikIngressRoute::with_rule(TraefikRouterRule::path_prefix_rule(&[
-                    "master", "db",
-                ])),
-                Vec::new(),
-            ),
-            &ContainerConfig::default(),
-            false,
-            &None,
+        let payload = service_payload(
+            &AppName::from_str("MY-APP").unwrap(),
+            &config,
+            &sc!("db", "mariadb:10.3.17"),
         );
 
         assert_json_diff::assert_json_include!(
             actual: payload,
             expected: serde_json::json!({
-              "apiVersion": "apps/v1",
-              "kind": "Deployment",
               "metadata": {
-                "annotations": {
-                  "com.aixigo.preview.servant.image": "docker.io/library/mariadb:10.3.17"
-                },
+                "name": "db",
+                "namespace": "preview-my-app",
                 "labels": {
-                  "com.aixigo.preview.servant.app-name": "master",
-                  "com.aixigo.preview.servant.container-type": "instance",
+                  "com.aixigo.preview.servant.app-name": "MY-APP",
                   "com.aixigo.preview.servant.service-name": "db"
-                },
-                "name": "master-db-deployment",
-                "namespace": "master"
-              },
-              "spec": {
-                "replicas": 1,
-                "selector": {
-                  "matchLabels": {
-                    "com.aixigo.preview.servant.app-name": "master",
-                    "com.aixigo.preview.servant.container-type": "instance",
-                    "com.aixigo.preview.servant.service-name": "db"
-                  }
-                },
-                "template": {
-                  "metadata": {
-                    "annotations": {
-                    },
-                    "labels": {
-                      "com.aixigo.preview.servant.app-name": "master",
-                      "com.aixigo.preview.servant.container-type": "instance",
-                      "com.aixigo.preview.servant.service-name": "db"
-                    }
-                  },
-                  "spec": {
-                    "containers": [
-                      {
-                        "image": "docker.io/library/mariadb:10.3.17",
-                        "imagePullPolicy": "Always",
-                        "name": "db",
-                        "ports": [
-                          {
-                            "containerPort": 80
-                          }
-                        ]
-                      }
-                    ]
-                  }
                 }
               }
             })
         );
     }
 
+    #[test]
+    fn should_prefix_services_in_shared_namespace() {
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+            [runtime.namespace]
+            name = 'previews'
+            mode = 'Existing'
+            "#,
+        )
+        .unwrap();
+        let app_name = AppName::master();
+        let service = DeployableService::new(
+            sc!("db", "mariadb:10.3.17"),
+            DeploymentStrategy::RedeployAlways,
+            TraefikIngressRoute::with_defaults(&app_name, "db"),
+            Vec::new(),
+        );
+
+        let payload = service_payload(&app_name, &config, &service);
+        assert_eq!(payload.metadata.name, Some(String::from("master-db")));
+        assert_eq!(payload.metadata.namespace, Some(String::from("previews")));
+
+        let payload = ingress_route_payload(&app_name, &config, &service);
+        assert_eq!(payload.metadata.namespace, Some(String::from("previews")));
+        assert_eq!(
+            payload.spec.routes.unwrap()[0].services[0].name,
+            String::from("master-db")
+        );
+    }
+
     #[test]
     fn should_create_deployment_with_environment_variable() {
         let mut config = sc!("db", "mariadb:10.3.17");
@@ -828,6 +3573,7 @@ mod tests {
 
         let payload = deployment_payload(
             &AppName::master(),
+            &Default::default(),
             &DeployableService::new(
                 config,
                 DeploymentStrategy::RedeployAlways,
@@ -910,6 +3656,7 @@ mod tests {
 
         let payload = deployment_payload(
             &AppName::master(),
+            &Default::default(),
             &DeployableService::new(
                 config,
                 DeploymentStrategy::RedeployAlways,
@@ -993,6 +3740,7 @@ mod tests {
 
         let payload = deployment_payload(
             &AppName::from_str("MY-APP").unwrap(),
+            &Default::default(),
             &DeployableService::new(
                 config,
                 DeploymentStrategy::RedeployAlways,
@@ -1074,7 +3822,7 @@ mod tests {
             TraefikIngressRoute::with_defaults(&app_name, "db"),
             Vec::new(),
         );
-        let payload = ingress_route_payload(&app_name, &config);
+        let payload = ingress_route_payload(&app_name, &Default::default(), &config);
 
         assert_json_diff::assert_json_include!(
             actual: payload,
@@ -1108,6 +3856,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_ingress_route_with_load_balancer_options() {
+        let app_name = AppName::master();
+        let mut config = sc!("db", "mariadb:10.3.17");
+        config.set_load_balancer(Some(
+            serde_json::from_value(serde_json::json!({
+                "sticky": { "cookie": { "name": "session", "sameSite": "lax" } },
+                "serversTransport": "insecure-transport"
+            }))
+            .unwrap(),
+        ));
+        let config = DeployableService::new(
+            config,
+            DeploymentStrategy::RedeployAlways,
+            TraefikIngressRoute::with_defaults(&app_name, "db"),
+            Vec::new(),
+        );
+        let payload = ingress_route_payload(&app_name, &Default::default(), &config);
+
+        assert_json_diff::assert_json_eq!(
+            serde_json::to_value(payload).unwrap()["spec"]["routes"][0]["services"],
+            serde_json::json!([{
+              "kind": "Service",
+              "name": "db",
+              "port": 80,
+              "sticky": {
+                "cookie": {
+                  "name": "session",
+                  "secure": false,
+                  "httpOnly": false,
+                  "sameSite": "lax"
+                }
+              },
+              "serversTransport": "insecure-transport"
+            }])
+        );
+    }
+
     #[test]
     fn should_create_ingress_route_with_app_name_that_is_not_compliant_to_rfc1123() {
         let app_name = AppName::from_str("MY-APP").unwrap();
@@ -1120,7 +3906,7 @@ mod tests {
             TraefikIngressRoute::with_defaults(&app_name, "db"),
             Vec::new(),
         );
-        let payload = ingress_route_payload(&app_name, &config);
+        let payload = ingress_route_payload(&app_name, &Default::default(), &config);
 
         assert_json_diff::assert_json_include!(
             actual: payload,
@@ -1154,6 +3940,188 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_map_sibling_services_to_their_cluster_ips() {
+        let services = serde_json::from_value::<Vec<V1Service>>(serde_json::json!([
+          {
+            "metadata": {
+              "name": "master-s3",
+              "labels": { APP_NAME_LABEL: "master", SERVICE_NAME_LABEL: "s3" }
+            },
+            "spec": { "clusterIP": "10.0.0.12" }
+          },
+          {
+            "metadata": {
+              "name": "master-db",
+              "labels": { APP_NAME_LABEL: "master", SERVICE_NAME_LABEL: "db" }
+            },
+            "spec": { "clusterIP": "None" }
+          },
+          {
+            "metadata": { "name": "master-unlabeled" },
+            "spec": { "clusterIP": "10.0.0.13" }
+          }
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            sibling_host_aliases(&services),
+            vec![HostAlias {
+                ip: Some(String::from("10.0.0.12")),
+                hostnames: Some(vec![String::from("s3")]),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_create_adopted_deployment_payload() {
+        let deployment = serde_json::from_value::<V1Deployment>(serde_json::json!({
+          "metadata": { "name": "db", "namespace": "master" },
+          "spec": {
+            "replicas": 2,
+            "selector": { "matchLabels": { "app": "db" } },
+            "template": {
+              "metadata": { "labels": { "app": "db" } },
+              "spec": {
+                "containers": [{ "name": "db", "image": "mariadb:10.3.17" }]
+              }
+            }
+          }
+        }))
+        .unwrap();
+
+        let payload = adopted_deployment_payload(&AppName::master(), &deployment);
+
+        assert_eq!(
+            payload,
+            Some(serde_json::json!({
+              "metadata": {
+                "labels": {
+                  APP_NAME_LABEL: "master",
+                  SERVICE_NAME_LABEL: "db",
+                  CONTAINER_TYPE_LABEL: "instance"
+                },
+                "annotations": {
+                  IMAGE_LABEL: "docker.io/library/mariadb:10.3.17",
+                  REPLICAS_LABEL: "2"
+                }
+              },
+              "spec": {
+                "template": {
+                  "metadata": {
+                    "labels": {
+                      APP_NAME_LABEL: "master",
+                      SERVICE_NAME_LABEL: "db",
+                      CONTAINER_TYPE_LABEL: "instance"
+                    }
+                  }
+                }
+              }
+            }))
+        );
+    }
+
+    #[test]
+    fn should_create_adopted_deployment_payload_with_env_and_port() {
+        let deployment = serde_json::from_value::<V1Deployment>(serde_json::json!({
+          "metadata": { "name": "db", "namespace": "master" },
+          "spec": {
+            "selector": { "matchLabels": { "app": "db" } },
+            "template": {
+              "metadata": { "labels": { "app": "db" } },
+              "spec": {
+                "containers": [{
+                  "name": "db",
+                  "image": "mariadb:10.3.17",
+                  "env": [
+                    { "name": "MYSQL_DATABASE", "value": "example" },
+                    {
+                      "name": "MYSQL_ROOT_PASSWORD",
+                      "valueFrom": { "secretKeyRef": { "name": "db", "key": "password" } }
+                    }
+                  ],
+                  "ports": [{ "containerPort": 3306 }]
+                }]
+              }
+            }
+          }
+        }))
+        .unwrap();
+
+        let payload = adopted_deployment_payload(&AppName::master(), &deployment).unwrap();
+
+        assert_eq!(
+            payload["metadata"]["annotations"],
+            serde_json::json!({
+              IMAGE_LABEL: "docker.io/library/mariadb:10.3.17",
+              REPLICATED_ENV_LABEL: serde_json::json!({
+                "MYSQL_DATABASE": {
+                  "value": "example",
+                  "templated": false,
+                  "replicate": true
+                }
+              }).to_string(),
+              FIXED_PORT_LABEL: "3306"
+            })
+        );
+    }
+
+    #[test]
+    fn should_not_adopt_deployment_without_image() {
+        let deployment = serde_json::from_value::<V1Deployment>(serde_json::json!({
+          "metadata": { "name": "db", "namespace": "master" },
+          "spec": {
+            "selector": { "matchLabels": { "app": "db" } },
+            "template": {
+              "spec": { "containers": [{ "name": "db" }] }
+            }
+          }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            adopted_deployment_payload(&AppName::master(), &deployment),
+            None
+        );
+    }
+
+    #[test]
+    fn should_create_deployment_restart_payload() {
+        let app_name = AppName::master();
+        let service = crate::models::ServiceBuilder::new()
+            .id(String::from("some-id"))
+            .app_name(app_name.to_string())
+            .config(sc!("db", "mariadb:10.3.17"))
+            .started_at(Utc::now())
+            .build()
+            .unwrap();
+        let restarted_at = DateTime::parse_from_rfc3339("2023-09-01T12:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let payload =
+            deployment_restart_payload(&app_name, &Default::default(), &service, restarted_at);
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!({
+              "metadata": {
+                "name": "master-db-deployment",
+                "namespace": "master",
+              },
+              "spec": {
+                "template": {
+                  "metadata": {
+                    "annotations": {
+                      "kubectl.kubernetes.io/restartedAt": "2023-09-01T12:00:00+00:00"
+                    }
+                  }
+                }
+              }
+            }),
+        );
+    }
+
     #[test]
     fn should_create_middleware_with_default_prefix() {
         let app_name = AppName::master();
@@ -1165,7 +4133,7 @@ mod tests {
             Vec::new(),
         );
 
-        let payload = middleware_payload(&app_name, &service);
+        let payload = middleware_payload(&app_name, &Default::default(), &service);
 
         assert_json_diff::assert_json_include!(
             actual: payload,
@@ -1187,6 +4155,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_create_middlewares_of_service() {
+        let app_name = AppName::master();
+        let config = sc!("db", "mariadb:10.3.17");
+        let mut ingress_route = TraefikIngressRoute::with_defaults(&app_name, "db");
+        ingress_route.add_middlewares(
+            &app_name,
+            "db",
+            &BTreeMap::from([(
+                String::from("rateLimit"),
+                serde_value::to_value(serde_json::json!({ "average": 100 })).unwrap(),
+            )]),
+        );
+        let service = DeployableService::new(
+            config,
+            DeploymentStrategy::RedeployAlways,
+            ingress_route,
+            Vec::new(),
+        );
+
+        let payload = middleware_payload(&app_name, &Default::default(), &service);
+
+        assert_json_diff::assert_json_include!(
+            actual: payload,
+            expected: serde_json::json!([{
+              "metadata": {
+                "name": "master-db-middleware",
+              },
+            }, {
+              "apiVersion": "traefik.containo.us/v1alpha1",
+              "kind": "Middleware",
+              "metadata": {
+                "name": "master-db-ratelimit-middleware",
+                "namespace": "master",
+              },
+              "spec": {
+                "rateLimit": {
+                  "average": 100
+                }
+              },
+            }]),
+        );
+        assert_json_diff::assert_json_include!(
+            actual: ingress_route_payload(&app_name, &Default::default(), &service),
+            expected: serde_json::json!({
+              "spec": {
+                "routes": [{
+                  "middlewares": [
+                    { "name": "master-db-middleware" },
+                    { "name": "master-db-ratelimit-middleware" }
+                  ]
+                }]
+              }
+            }),
+        );
+    }
+
     #[test]
     fn should_create_middleware_with_default_prefix_with_name_rfc1123_app_name() {
         let app_name = AppName::from_str("MY-APP").unwrap();
@@ -1198,7 +4223,7 @@ mod tests {
             Vec::new(),
         );
 
-        let payload = middleware_payload(&app_name, &service);
+        let payload = middleware_payload(&app_name, &Default::default(), &service);
 
         assert_json_diff::assert_json_include!(
             actual: payload,
@@ -1251,6 +4276,7 @@ mod tests {
         };
         let payload = deployment_payload(
             &AppName::master(),
+            &Default::default(),
             &DeployableService::new(
                 config,
                 DeploymentStrategy::RedeployAlways,
@@ -1350,6 +4376,7 @@ mod tests {
 
         let payload = deployment_payload(
             &AppName::master(),
+            &Default::default(),
             &DeployableService::new(
                 config,
                 DeploymentStrategy::RedeployAlways,
@@ -1437,8 +4464,11 @@ mod tests {
 
     #[test]
     fn create_namespace_with_screaming_snake_case() {
-        let namespace =
-            namespace_payload(&AppName::from_str("MY-APP").unwrap(), &Default::default());
+        let namespace = namespace_payload(
+            &AppName::from_str("MY-APP").unwrap(),
+            &Default::default(),
+            &BTreeMap::new(),
+        );
 
         assert_eq!(
             namespace,
@@ -1468,7 +4498,11 @@ mod tests {
         )
         .unwrap();
 
-        let namespace = namespace_payload(&AppName::from_str("myapp").unwrap(), &config);
+        let namespace = namespace_payload(
+            &AppName::from_str("myapp").unwrap(),
+            &config,
+            &BTreeMap::new(),
+        );
 
         assert_eq!(
             namespace,
@@ -1489,4 +4523,42 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn create_namespace_payload_with_annotations_of_request() {
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+            [runtime.annotations.namespace]
+            'field.cattle.io/projectId' = 'rancher-project-id'
+            'example.com/team' = 'platform'
+            "#,
+        )
+        .unwrap();
+
+        let namespace = namespace_payload(
+            &AppName::from_str("myapp").unwrap(),
+            &config,
+            &BTreeMap::from([
+                (
+                    String::from("field.cattle.io/projectId"),
+                    String::from("other-rancher-project-id"),
+                ),
+                (String::from("istio-injection"), String::from("enabled")),
+            ]),
+        );
+
+        assert_eq!(
+            namespace.metadata.annotations,
+            Some(BTreeMap::from([
+                (String::from("example.com/team"), String::from("platform")),
+                (
+                    String::from("field.cattle.io/projectId"),
+                    String::from("other-rancher-project-id"),
+                ),
+                (String::from("istio-injection"), String::from("enabled")),
+            ]))
+        );
+    }
 }
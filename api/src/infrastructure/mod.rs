@@ -26,19 +26,24 @@
 
 use crate::models::Environment;
 pub use docker::DockerInfrastructure as Docker;
-#[cfg(test)]
+#[cfg(any(test, feature = "dummy-infrastructure"))]
 pub use dummy_infrastructure::DummyInfrastructure as Dummy;
 pub use infrastructure::Infrastructure;
+pub use kubernetes::spawn_leader_election;
 pub use kubernetes::KubernetesInfrastructure as Kubernetes;
+pub use multi_cluster::MultiClusterInfrastructure as MultiCluster;
 use serde_json::{map::Map, Value};
-pub use traefik::{TraefikIngressRoute, TraefikRouterRule};
+pub use traefik::{TraefikIngressRoute, TraefikRouterRule, TraefikRouterRuleError};
+pub use wait::{wait_for, Condition, ConditionState, HttpCondition, WaitError};
 
 mod docker;
-#[cfg(test)]
+#[cfg(any(test, feature = "dummy-infrastructure"))]
 mod dummy_infrastructure;
 mod infrastructure;
 mod kubernetes;
+mod multi_cluster;
 mod traefik;
+mod wait;
 
 static APP_NAME_LABEL: &str = "com.aixigo.preview.servant.app-name";
 static SERVICE_NAME_LABEL: &str = "com.aixigo.preview.servant.service-name";
@@ -47,6 +52,21 @@ static REPLICATED_ENV_LABEL: &str = "com.aixigo.preview.servant.replicated-env";
 static IMAGE_LABEL: &str = "com.aixigo.preview.servant.image";
 static STATUS_ID: &str = "com.aixigo.preview.servant.status-id";
 static STORAGE_TYPE_LABEL: &str = "com.aixigo.preview.servant.storage-type";
+static IMAGE_DIGEST_LABEL: &str = "com.aixigo.preview.servant.image-digest";
+static REPLICAS_LABEL: &str = "com.aixigo.preview.servant.replicas";
+static REPLICATED_FROM_LABEL: &str = "com.aixigo.preview.servant.replicated-from";
+static ALIASES_LABEL: &str = "com.aixigo.preview.servant.aliases";
+/// Marks the Kubernetes services that PREvant created for an alias with the name of the aliased
+/// service.
+static ALIAS_OF_LABEL: &str = "com.aixigo.preview.servant.alias-of";
+static DEPENDS_ON_LABEL: &str = "com.aixigo.preview.servant.depends-on";
+/// The port of a service that must not be replaced by the port that its image exposes.
+static FIXED_PORT_LABEL: &str = "com.aixigo.preview.servant.fixed-port";
+/// Annotation that a manually prepared namespace must carry with the value `"true"` so that
+/// PREvant may adopt it.
+static ADOPTABLE_LABEL: &str = "com.aixigo.preview.servant.adoptable";
+/// Prefix of the labels or annotations that store the metadata of an app
+static APP_METADATA_PREFIX: &str = "com.aixigo.preview.servant.metadata/";
 
 /// This function converts the environment variables and adds all variables, that
 /// must be replicated, into a JSON object. This function should be used by implementations
@@ -0,0 +1,483 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use super::traefik::TraefikIngressRoute;
+use crate::config::{BuildsConfig, ContainerConfig};
+use crate::deployment::DeploymentUnit;
+use crate::infrastructure::Infrastructure;
+use crate::models::service::{Service, ServiceStatus};
+use crate::models::{
+    AppName, ClusterCapacity, Image, LogFilter, LogStream, SelfCheck, ServiceBuilder,
+    SmokeTestOutcome, SourceBuild,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use failure::Error;
+use multimap::MultiMap;
+use secstr::SecUtf8;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Combines the infrastructure of the default runtime with the infrastructures of further named
+/// clusters. Apps will be deployed to the cluster that has been requested as target or to the
+/// cluster the app is already running on.
+pub struct MultiClusterInfrastructure {
+    default: Box<dyn Infrastructure>,
+    clusters: BTreeMap<String, Box<dyn Infrastructure>>,
+}
+
+impl MultiClusterInfrastructure {
+    pub fn new(
+        default: Box<dyn Infrastructure>,
+        clusters: BTreeMap<String, Box<dyn Infrastructure>>,
+    ) -> Self {
+        Self { default, clusters }
+    }
+
+    fn infrastructures(&self) -> impl Iterator<Item = (Option<&String>, &dyn Infrastructure)> {
+        std::iter::once((None, self.default.as_ref())).chain(
+            self.clusters
+                .iter()
+                .map(|(name, infrastructure)| (Some(name), infrastructure.as_ref())),
+        )
+    }
+
+    /// Returns the infrastructure that runs the app.
+    async fn infrastructure_of_app(
+        &self,
+        app_name: &AppName,
+    ) -> Result<Option<(Option<&String>, &dyn Infrastructure)>, Error> {
+        for (cluster, infrastructure) in self.infrastructures() {
+            if infrastructure.get_services().await?.contains_key(app_name) {
+                return Ok(Some((cluster, infrastructure)));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn infrastructure_of_app_or_default(
+        &self,
+        app_name: &AppName,
+    ) -> Result<(Option<&String>, &dyn Infrastructure), Error> {
+        Ok(self
+            .infrastructure_of_app(app_name)
+            .await?
+            .unwrap_or((None, self.default.as_ref())))
+    }
+
+    /// Returns the infrastructure that the `deployment_unit` will be deployed to: the target
+    /// cluster of the deployment, otherwise the cluster that already runs the app.
+    async fn infrastructure_of_deployment(
+        &self,
+        deployment_unit: &DeploymentUnit,
+    ) -> Result<(Option<&String>, &dyn Infrastructure), Error> {
+        let app_name = deployment_unit.app_name();
+        let running_on = self.infrastructure_of_app(app_name).await?;
+
+        Ok(match deployment_unit.target() {
+            Some(target) => {
+                let infrastructure = self
+                    .clusters
+                    .get_key_value(target)
+                    .ok_or_else(|| format_err!("Unknown cluster {}", target))?;
+
+                if let Some((running_cluster, _)) = running_on {
+                    if running_cluster != Some(target) {
+                        bail!(
+                            "App {} is already running on {}",
+                            app_name,
+                            running_cluster.map_or("the default cluster", |c| c.as_str())
+                        );
+                    }
+                }
+
+                (Some(infrastructure.0), infrastructure.1.as_ref())
+            }
+            None => running_on.unwrap_or((None, self.default.as_ref())),
+        })
+    }
+
+    fn tag_with_cluster(cluster: Option<&String>, service: Service) -> Service {
+        match cluster {
+            Some(cluster) => ServiceBuilder::from(service)
+                .cluster(cluster.clone())
+                .build()
+                .expect("Service has been built before"),
+            None => service,
+        }
+    }
+}
+
+#[async_trait]
+impl Infrastructure for MultiClusterInfrastructure {
+    async fn get_services(&self) -> Result<MultiMap<AppName, Service>, Error> {
+        let mut services = MultiMap::new();
+
+        for (cluster, infrastructure) in self.infrastructures() {
+            for (app_name, services_of_app) in infrastructure.get_services().await? {
+                for service in services_of_app {
+                    services.insert(app_name.clone(), Self::tag_with_cluster(cluster, service));
+                }
+            }
+        }
+
+        Ok(services)
+    }
+
+    async fn deploy_services(
+        &self,
+        status_id: &str,
+        deployment_unit: &DeploymentUnit,
+        container_config: &ContainerConfig,
+    ) -> Result<Vec<Service>, Error> {
+        let (cluster, infrastructure) = self.infrastructure_of_deployment(deployment_unit).await?;
+
+        Ok(infrastructure
+            .deploy_services(status_id, deployment_unit, container_config)
+            .await?
+            .into_iter()
+            .map(|service| Self::tag_with_cluster(cluster, service))
+            .collect())
+    }
+
+    async fn get_status_change(&self, status_id: &str) -> Result<Option<Vec<Service>>, Error> {
+        for (cluster, infrastructure) in self.infrastructures() {
+            if let Some(services) = infrastructure.get_status_change(status_id).await? {
+                return Ok(Some(
+                    services
+                        .into_iter()
+                        .map(|service| Self::tag_with_cluster(cluster, service))
+                        .collect(),
+                ));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn stop_services(
+        &self,
+        status_id: &str,
+        app_name: &AppName,
+    ) -> Result<Vec<Service>, Error> {
+        let (cluster, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+
+        Ok(infrastructure
+            .stop_services(status_id, app_name)
+            .await?
+            .into_iter()
+            .map(|service| Self::tag_with_cluster(cluster, service))
+            .collect())
+    }
+
+    async fn get_logs(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        from: &Option<DateTime<FixedOffset>>,
+        limit: usize,
+        filter: &LogFilter,
+    ) -> Result<Option<Vec<(DateTime<FixedOffset>, String)>>, Error> {
+        let (_, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+        infrastructure
+            .get_logs(app_name, service_name, from, limit, filter)
+            .await
+    }
+
+    async fn get_logs_with_streams(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        from: &Option<DateTime<FixedOffset>>,
+        limit: usize,
+        filter: &LogFilter,
+    ) -> Result<Option<Vec<(DateTime<FixedOffset>, Option<LogStream>, String)>>, Error> {
+        let (_, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+        infrastructure
+            .get_logs_with_streams(app_name, service_name, from, limit, filter)
+            .await
+    }
+
+    async fn change_status(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        status: ServiceStatus,
+    ) -> Result<Option<Service>, Error> {
+        let (cluster, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+
+        Ok(infrastructure
+            .change_status(app_name, service_name, status)
+            .await?
+            .map(|service| Self::tag_with_cluster(cluster, service)))
+    }
+
+    async fn restart_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+    ) -> Result<Option<Service>, Error> {
+        let (cluster, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+
+        Ok(infrastructure
+            .restart_service(app_name, service_name)
+            .await?
+            .map(|service| Self::tag_with_cluster(cluster, service)))
+    }
+
+    async fn update_files(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<Option<Service>, Error> {
+        let (cluster, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+
+        Ok(infrastructure
+            .update_files(app_name, service_name, files)
+            .await?
+            .map(|service| Self::tag_with_cluster(cluster, service)))
+    }
+
+    async fn adopt_services(&self, app_name: &AppName) -> Result<Vec<Service>, Error> {
+        let (cluster, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+
+        Ok(infrastructure
+            .adopt_services(app_name)
+            .await?
+            .into_iter()
+            .map(|service| Self::tag_with_cluster(cluster, service))
+            .collect())
+    }
+
+    async fn run_smoke_test(
+        &self,
+        app_name: &AppName,
+        image: &Image,
+        command: &[String],
+        timeout: Duration,
+    ) -> Result<SmokeTestOutcome, Error> {
+        let (_, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+        infrastructure
+            .run_smoke_test(app_name, image, command, timeout)
+            .await
+    }
+
+    async fn build_image(
+        &self,
+        app_name: &AppName,
+        build: &SourceBuild,
+        destination: &Image,
+        config: &BuildsConfig,
+    ) -> Result<SmokeTestOutcome, Error> {
+        let (_, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+        infrastructure
+            .build_image(app_name, build, destination, config)
+            .await
+    }
+
+    async fn exec_in_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        command: &[String],
+        timeout: Duration,
+    ) -> Result<SmokeTestOutcome, Error> {
+        let (_, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+        infrastructure
+            .exec_in_service(app_name, service_name, command, timeout)
+            .await
+    }
+
+    async fn signal_service(
+        &self,
+        app_name: &AppName,
+        service_name: &str,
+        signal: &str,
+        files: &BTreeMap<PathBuf, SecUtf8>,
+    ) -> Result<SmokeTestOutcome, Error> {
+        let (_, infrastructure) = self.infrastructure_of_app_or_default(app_name).await?;
+        infrastructure
+            .signal_service(app_name, service_name, signal, files)
+            .await
+    }
+
+    /// The route of the default runtime is used for all clusters because PREvant itself runs
+    /// there.
+    async fn base_traefik_ingress_route(&self) -> Result<Option<TraefikIngressRoute>, Error> {
+        self.default.base_traefik_ingress_route().await
+    }
+
+    async fn cluster_capacity(
+        &self,
+        deployment_unit: &DeploymentUnit,
+    ) -> Result<Option<ClusterCapacity>, Error> {
+        let (_, infrastructure) = self.infrastructure_of_deployment(deployment_unit).await?;
+        infrastructure.cluster_capacity(deployment_unit).await
+    }
+
+    async fn self_check(&self) -> Vec<SelfCheck> {
+        let mut checks = Vec::new();
+        for (cluster, infrastructure) in self.infrastructures() {
+            let prefix = cluster.map_or("default cluster", |cluster| cluster.as_str());
+            checks.extend(
+                infrastructure
+                    .self_check()
+                    .await
+                    .into_iter()
+                    .map(|check| check.with_prefix(prefix)),
+            );
+        }
+        checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployment::deployment_unit::DeploymentUnitBuilder;
+    use crate::infrastructure::Dummy;
+    use crate::sc;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    fn infrastructure() -> MultiClusterInfrastructure {
+        MultiClusterInfrastructure::new(
+            Box::new(Dummy::new()),
+            BTreeMap::from([(
+                String::from("gpu"),
+                Box::new(Dummy::new()) as Box<dyn Infrastructure>,
+            )]),
+        )
+    }
+
+    async fn deployment_unit(app_name: &str, target: Option<&str>) -> DeploymentUnit {
+        let config = crate::config::Config::default();
+        DeploymentUnitBuilder::init(AppName::from_str(app_name).unwrap(), vec![sc!("service-a")])
+            .extend_with_config(&config)
+            .extend_with_templating_only_service_configs(Vec::new())
+            .extend_with_image_infos(HashMap::new())
+            .apply_templating()
+            .unwrap()
+            .apply_hooks(&config)
+            .await
+            .unwrap()
+            .build()
+            .with_target(target.map(String::from))
+    }
+
+    #[tokio::test]
+    async fn should_tag_services_with_cluster() -> Result<(), Error> {
+        let infrastructure = infrastructure();
+
+        infrastructure
+            .deploy_services(
+                "",
+                &deployment_unit("master", None).await,
+                &ContainerConfig::default(),
+            )
+            .await?;
+        infrastructure
+            .deploy_services(
+                "",
+                &deployment_unit("PR-1", Some("gpu")).await,
+                &ContainerConfig::default(),
+            )
+            .await?;
+
+        let services = infrastructure.get_services().await?;
+
+        assert_eq!(
+            services
+                .get(&AppName::from_str("master").unwrap())
+                .unwrap()
+                .cluster(),
+            None
+        );
+        assert_eq!(
+            services
+                .get(&AppName::from_str("PR-1").unwrap())
+                .unwrap()
+                .cluster(),
+            Some(&String::from("gpu"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_redeploy_to_cluster_of_running_app() -> Result<(), Error> {
+        let infrastructure = infrastructure();
+
+        infrastructure
+            .deploy_services(
+                "",
+                &deployment_unit("PR-1", Some("gpu")).await,
+                &ContainerConfig::default(),
+            )
+            .await?;
+        infrastructure
+            .deploy_services(
+                "",
+                &deployment_unit("PR-1", None).await,
+                &ContainerConfig::default(),
+            )
+            .await?;
+
+        let services = infrastructure.get_services().await?;
+        let services = services
+            .get_vec(&AppName::from_str("PR-1").unwrap())
+            .unwrap();
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].cluster(), Some(&String::from("gpu")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_move_running_app_to_other_cluster() -> Result<(), Error> {
+        let infrastructure = infrastructure();
+
+        infrastructure
+            .deploy_services(
+                "",
+                &deployment_unit("PR-1", None).await,
+                &ContainerConfig::default(),
+            )
+            .await?;
+        let result = infrastructure
+            .deploy_services(
+                "",
+                &deployment_unit("PR-1", Some("gpu")).await,
+                &ContainerConfig::default(),
+            )
+            .await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
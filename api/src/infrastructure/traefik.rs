@@ -55,6 +55,31 @@ impl TraefikIngressRoute {
         }
     }
 
+    /// Adds a middleware for each entry of `middlewares` to the routes. The key of each entry
+    /// is the [middleware's type](https://doc.traefik.io/traefik/middlewares/http/overview/),
+    /// e.g. `rateLimit`, and the value is its configuration.
+    pub fn add_middlewares(
+        &mut self,
+        app_name: &AppName,
+        service_name: &str,
+        middlewares: &BTreeMap<String, Value>,
+    ) {
+        for route in self.routes.iter_mut() {
+            for (kind, config) in middlewares {
+                let mut spec = BTreeMap::new();
+                spec.insert(Value::String(kind.clone()), config.clone());
+
+                route.middlewares.push(TraefikMiddleware::Spec {
+                    name: format!(
+                        "{app_name}-{service_name}-{}-middleware",
+                        kind.to_lowercase()
+                    ),
+                    spec: Value::Map(spec),
+                });
+            }
+        }
+    }
+
     #[cfg(test)]
     pub fn with_rule(rule: TraefikRouterRule) -> Self {
         Self::with_existing_routing_rules(Vec::new(), rule, Vec::new(), None)
@@ -168,6 +193,24 @@ impl TraefikRouterRule {
         }
     }
 
+    pub fn matches(&self) -> &Vec<Matcher> {
+        &self.matches
+    }
+
+    /// Returns a copy of the rule that matches the given host instead of the hosts of this rule.
+    pub fn with_host(&self, host: &str) -> Self {
+        let mut matches = vec![Matcher::Host {
+            domains: vec![host.to_string()],
+        }];
+        matches.extend(
+            self.matches
+                .iter()
+                .filter(|m| !matches!(m, Matcher::Host { .. }))
+                .cloned(),
+        );
+        Self { matches }
+    }
+
     pub fn path_prefix_rule<S>(segments: S) -> Self
     where
         S: IntoIterator,
@@ -186,6 +229,14 @@ impl TraefikRouterRule {
                 Matcher::Headers { key, value } => {
                     self.matches.push(Matcher::Headers { key, value });
                 }
+                Matcher::Expression(expression) => {
+                    if !self
+                        .matches
+                        .contains(&Matcher::Expression(expression.clone()))
+                    {
+                        self.matches.push(Matcher::Expression(expression));
+                    }
+                }
                 Matcher::Host {
                     domains: other_domains,
                 } => {
@@ -253,67 +304,290 @@ pub enum TraefikMiddleware {
 
 #[derive(pest_derive::Parser)]
 #[grammar_inline = r#"
-ident = { (ASCII_ALPHANUMERIC | PUNCTUATION)+ }
+WHITESPACE = _{ " " | "\t" | "\r" | "\n" }
+
+Root = { SOI ~ Or ~ EOI }
 
-Root = _{ (Headers | Host | PathPrefix) ~ ( " "* ~ "&&" ~ " "* ~ (Headers | Host | PathPrefix))* }
+Or = { And ~ ("||" ~ And)* }
+And = { Not ~ ("&&" ~ Not)* }
+Not = { Negation* ~ (Call | Group) }
+Negation = { "!" }
+Group = { "(" ~ Or ~ ")" }
 
-Headers = { "Headers" ~ "(`" ~ ident ~ "`, `" ~ ident ~ "`)"  }
-Host = { "Host" ~ "(`" ~ ident ~ ("`, `" ~ ident)* ~ "`)" }
-PathPrefix = { "PathPrefix" ~ "(`" ~ ident ~ ("`, `" ~ ident)* ~ "`)" }
+Call = { Name ~ "(" ~ Argument ~ ("," ~ Argument)* ~ ")" }
+Name = @{ ASCII_ALPHA ~ ASCII_ALPHANUMERIC* }
+Argument = ${ "`" ~ BacktickValue ~ "`" | "\"" ~ QuotedValue ~ "\"" }
+BacktickValue = @{ (!"`" ~ ANY)* }
+QuotedValue = @{ (!"\"" ~ ANY)* }
 "#]
 struct RuleParser;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Matcher {
-    Headers { key: String, value: String },
-    Host { domains: Vec<String> },
-    PathPrefix { paths: Vec<String> },
+    Headers {
+        key: String,
+        value: String,
+    },
+    Host {
+        domains: Vec<String>,
+    },
+    PathPrefix {
+        paths: Vec<String>,
+    },
+    /// Any other part of a rule, e.g. ``Method(`GET`)`` or ``(Path(`/a`) || Path(`/b`))``, that
+    /// is kept in its normalized form.
+    Expression(String),
 }
 
-impl FromStr for TraefikRouterRule {
-    type Err = String;
+#[derive(Clone, Debug, Eq, Fail, PartialEq)]
+pub enum TraefikRouterRuleError {
+    #[fail(
+        display = "Invalid router rule at line {}, column {}: {}",
+        line, column, message
+    )]
+    InvalidSyntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[fail(display = "Invalid router rule: {}", issues)]
+    InvalidMatchers { issues: String },
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut rule = TraefikRouterRule {
-            matches: Vec::new(),
-        };
+/// The matchers of [Traefik's rules](https://doc.traefik.io/traefik/v2.10/routing/routers/#rule)
+/// and the number of arguments they require. `None` means one or more arguments.
+const KNOWN_MATCHERS: [(&str, Option<usize>); 10] = [
+    ("ClientIP", None),
+    ("Headers", Some(2)),
+    ("HeadersRegexp", Some(2)),
+    ("Host", None),
+    ("HostHeader", None),
+    ("HostRegexp", None),
+    ("Method", None),
+    ("Path", None),
+    ("PathPrefix", None),
+    ("Query", None),
+];
+
+/// The syntax tree of a rule. The operators follow Traefik's precedence: `!` binds stronger than
+/// `&&` which binds stronger than `||`.
+#[derive(Clone, Debug)]
+enum Expression {
+    Call {
+        name: String,
+        arguments: Vec<String>,
+    },
+    Not(Box<Expression>),
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+}
 
-        let pairs = RuleParser::parse(Rule::Root, s).map_err(|e| e.to_string())?;
+impl Expression {
+    fn from_pair(pair: pest::iterators::Pair<'_, Rule>) -> Self {
+        match pair.as_rule() {
+            Rule::Or | Rule::And => {
+                let is_or = pair.as_rule() == Rule::Or;
+                // Operands of the same operator, e.g. of nested groups, are merged so that the
+                // operator is rendered only once.
+                let mut operands = pair
+                    .into_inner()
+                    .map(Self::from_pair)
+                    .flat_map(|operand| match operand {
+                        Expression::Or(operands) if is_or => operands,
+                        Expression::And(operands) if !is_or => operands,
+                        operand => vec![operand],
+                    })
+                    .collect::<Vec<_>>();
+                if operands.len() == 1 {
+                    operands.remove(0)
+                } else if is_or {
+                    Expression::Or(operands)
+                } else {
+                    Expression::And(operands)
+                }
+            }
+            Rule::Not => {
+                let mut negations = 0;
+                let mut expression = None;
+                for inner in pair.into_inner() {
+                    match inner.as_rule() {
+                        Rule::Negation => negations += 1,
+                        _ => expression = Some(Self::from_pair(inner)),
+                    }
+                }
 
-        for pair in pairs {
-            match pair.as_rule() {
-                Rule::Headers => {
-                    let key_and_value = pair
-                        .into_inner()
-                        .map(|pair| pair.as_str())
-                        .collect::<Vec<_>>();
+                let expression = expression.expect("The grammar requires an operand");
+                (0..negations).fold(expression, |expression, _| {
+                    Expression::Not(Box::new(expression))
+                })
+            }
+            Rule::Group | Rule::Root => Self::from_pair(
+                pair.into_inner()
+                    .find(|pair| pair.as_rule() == Rule::Or)
+                    .expect("The grammar requires an expression"),
+            ),
+            Rule::Call => {
+                let mut inner = pair.into_inner();
+                let name = inner
+                    .next()
+                    .expect("The grammar requires a name")
+                    .as_str()
+                    .to_string();
+                let arguments = inner
+                    .map(|argument| {
+                        argument
+                            .into_inner()
+                            .next()
+                            .map(|value| value.as_str().to_string())
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                Expression::Call { name, arguments }
+            }
+            rule => unreachable!("Unexpected rule {:?}", rule),
+        }
+    }
 
-                    rule.matches.push(Matcher::Headers {
-                        key: key_and_value[0].to_string(),
-                        value: key_and_value[1].to_string(),
-                    });
+    /// Collects all invalid matchers instead of stopping at the first one so that a rule can be
+    /// fixed at once.
+    fn collect_issues(&self, issues: &mut Vec<String>) {
+        match self {
+            Expression::Call { name, arguments } => {
+                match KNOWN_MATCHERS
+                    .iter()
+                    .find(|(known, _)| *known == name.as_str())
+                {
+                    None => issues.push(format!("unknown matcher {name}")),
+                    Some((_, Some(count))) if arguments.len() != *count => issues.push(format!(
+                        "{name} requires {count} arguments but got {}",
+                        arguments.len()
+                    )),
+                    Some(_) if arguments.iter().any(String::is_empty) => {
+                        issues.push(format!("{name} requires non-empty arguments"))
+                    }
+                    Some(_) => {}
+                }
+            }
+            Expression::Not(expression) => expression.collect_issues(issues),
+            Expression::And(expressions) | Expression::Or(expressions) => {
+                for expression in expressions {
+                    expression.collect_issues(issues);
                 }
-                Rule::Host => {
-                    let domains = pair
-                        .into_inner()
-                        .map(|pair| pair.as_str().to_string())
-                        .collect::<Vec<_>>();
+            }
+        }
+    }
+
+    /// Splits the expression into the operands of its top-level `&&` operators, including the
+    /// ones of nested groups.
+    fn into_conjunction(self) -> Vec<Expression> {
+        match self {
+            Expression::And(expressions) => expressions
+                .into_iter()
+                .flat_map(Expression::into_conjunction)
+                .collect(),
+            expression => vec![expression],
+        }
+    }
 
-                    rule.matches.push(Matcher::Host { domains });
+    fn into_matcher(self) -> Matcher {
+        match self {
+            Expression::Call {
+                name,
+                mut arguments,
+            } => match name.as_str() {
+                "Headers" => {
+                    let value = arguments.pop().unwrap_or_default();
+                    let key = arguments.pop().unwrap_or_default();
+                    Matcher::Headers { key, value }
                 }
-                Rule::PathPrefix => {
-                    let paths = pair
-                        .into_inner()
-                        .map(|pair| pair.as_str().to_string())
-                        .collect::<Vec<_>>();
+                "Host" => Matcher::Host { domains: arguments },
+                "PathPrefix" => Matcher::PathPrefix { paths: arguments },
+                _ => Matcher::Expression(Expression::Call { name, arguments }.to_string()),
+            },
+            expression => Matcher::Expression(expression.to_string()),
+        }
+    }
+}
 
-                    rule.matches.push(Matcher::PathPrefix { paths });
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Call { name, arguments } => {
+                write!(f, "{name}(")?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{argument}`")?;
                 }
-                Rule::ident | Rule::Root => {}
+                write!(f, ")")
+            }
+            // A disjunction provides its parentheses itself.
+            Expression::Not(expression) => match expression.as_ref() {
+                Expression::And(_) => write!(f, "!({expression})"),
+                expression => write!(f, "!{expression}"),
+            },
+            Expression::And(expressions) => {
+                for (i, expression) in expressions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " && ")?;
+                    }
+                    write!(f, "{expression}")?;
+                }
+                Ok(())
+            }
+            Expression::Or(expressions) => {
+                // Wrapped in parentheses so that the expression keeps its meaning when it is
+                // combined with other matchers.
+                write!(f, "(")?;
+                for (i, expression) in expressions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " || ")?;
+                    }
+                    write!(f, "{expression}")?;
+                }
+                write!(f, ")")
             }
         }
+    }
+}
+
+impl FromStr for TraefikRouterRule {
+    type Err = TraefikRouterRuleError;
 
-        Ok(rule)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let root = RuleParser::parse(Rule::Root, s)
+            .map_err(|err| {
+                let (line, column) = match err.line_col {
+                    pest::error::LineColLocation::Pos(pos) => pos,
+                    pest::error::LineColLocation::Span(start, _) => start,
+                };
+                TraefikRouterRuleError::InvalidSyntax {
+                    line,
+                    column,
+                    message: err.variant.message().to_string(),
+                }
+            })?
+            .next()
+            .expect("The grammar requires a root");
+
+        let expression = Expression::from_pair(root);
+
+        let mut issues = Vec::new();
+        expression.collect_issues(&mut issues);
+        if !issues.is_empty() {
+            return Err(TraefikRouterRuleError::InvalidMatchers {
+                issues: issues.join(", "),
+            });
+        }
+
+        Ok(TraefikRouterRule {
+            matches: expression
+                .into_conjunction()
+                .into_iter()
+                .map(Expression::into_matcher)
+                .collect(),
+        })
     }
 }
 
@@ -353,6 +627,9 @@ impl Display for TraefikRouterRule {
 
                     write!(f, ")")?;
                 }
+                Matcher::Expression(expression) => {
+                    write!(f, "{expression}")?;
+                }
             }
         }
         Ok(())
@@ -473,6 +750,105 @@ mod test {
         )
     }
 
+    #[test]
+    fn parse_rule_with_other_matchers_and_operators() {
+        let rule =
+            "Host(\"example.com\")&&(PathPrefix(`/api`) || Path(`/health`)) && !Method(`DELETE`)"
+                .parse::<TraefikRouterRule>()
+                .unwrap();
+
+        assert_eq!(
+            rule,
+            TraefikRouterRule {
+                matches: vec![
+                    Matcher::Host {
+                        domains: vec![String::from("example.com")]
+                    },
+                    Matcher::Expression(String::from("(PathPrefix(`/api`) || Path(`/health`))")),
+                    Matcher::Expression(String::from("!Method(`DELETE`)")),
+                ]
+            }
+        );
+        assert_eq!(
+            rule.to_string(),
+            "Host(`example.com`) && (PathPrefix(`/api`) || Path(`/health`)) && !Method(`DELETE`)"
+        );
+    }
+
+    #[test]
+    fn parse_nested_conjunctions() {
+        let rule = "(Host(`example.com`) && (PathPrefix(`/test`)))"
+            .parse::<TraefikRouterRule>()
+            .unwrap();
+
+        assert_eq!(
+            rule,
+            "Host(`example.com`) && PathPrefix(`/test`)"
+                .parse::<TraefikRouterRule>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn render_disjunctions_with_single_parentheses() {
+        let rule =
+            "Host(`example.com`) && !(Method(`GET`) && (Path(`/a`) || (Path(`/b`) || Path(`/c`))))"
+                .parse::<TraefikRouterRule>()
+                .unwrap();
+
+        assert_eq!(
+            rule.to_string(),
+            "Host(`example.com`) && !(Method(`GET`) && (Path(`/a`) || Path(`/b`) || Path(`/c`)))"
+        );
+    }
+
+    #[test]
+    fn report_position_of_syntax_errors() {
+        let result = "Host(`example.com`) && PathPrefix(`/test`".parse::<TraefikRouterRule>();
+
+        assert!(std::matches!(
+            result,
+            Err(TraefikRouterRuleError::InvalidSyntax {
+                line: 1,
+                column: 42,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn report_all_invalid_matchers() {
+        let result =
+            "Hots(`example.com`) || Headers(`Host`) || PathPrefix(``)".parse::<TraefikRouterRule>();
+
+        assert_eq!(
+            result,
+            Err(TraefikRouterRuleError::InvalidMatchers {
+                issues: String::from(
+                    "unknown matcher Hots, Headers requires 2 arguments but got 1, PathPrefix requires non-empty arguments"
+                )
+            })
+        );
+    }
+
+    #[test]
+    fn merge_rules_with_expressions() {
+        let mut rule = "Host(`example.com`) && Method(`GET`)"
+            .parse::<TraefikRouterRule>()
+            .unwrap();
+
+        rule.merge_with(
+            "Method(`GET`) && PathPrefix(`/test`)"
+                .parse::<TraefikRouterRule>()
+                .unwrap(),
+        );
+
+        assert_eq!(
+            rule.to_string(),
+            "Host(`example.com`) && Method(`GET`) && PathPrefix(`/test`)"
+        );
+    }
+
     #[test]
     fn display_path_prefixes() {
         let rule = "PathPrefix(`/articles`, `/products`)"
@@ -482,6 +858,19 @@ mod test {
         assert_eq!(&rule.to_string(), "PathPrefix(`/articles`, `/products`)");
     }
 
+    #[test]
+    fn replace_hosts() {
+        let rule = TraefikRouterRule::from_str(
+            "Host(`prevant.example.com`) && PathPrefix(`/master/wildfly/`)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rule.with_host("master.previews.example.com").to_string(),
+            "Host(`master.previews.example.com`) && PathPrefix(`/master/wildfly/`)"
+        );
+    }
+
     #[test]
     fn display_host() {
         let rule = TraefikRouterRule::host_rule(vec![
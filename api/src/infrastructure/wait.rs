@@ -0,0 +1,226 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use async_trait::async_trait;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// The state of a resource that is checked repeatedly while waiting for a condition.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConditionState {
+    Fulfilled,
+    /// The condition is not fulfilled yet but might be later on.
+    Pending(String),
+    /// The condition will never be fulfilled, e.g. because a job has failed.
+    Failed(String),
+}
+
+/// A condition that a resource of an app has to fulfil before the deployment continues, e.g. the
+/// availability of a deployment, the completion of a job, or the status of a custom resource.
+#[async_trait]
+pub trait Condition: Send + Sync {
+    /// Describes the condition, e.g. `deployment master-frontend-deployment is available`.
+    fn description(&self) -> String;
+
+    async fn check(&self) -> Result<ConditionState, failure::Error>;
+}
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum WaitError {
+    #[fail(
+        display = "Timed out after {:?} waiting until {}: {}",
+        timeout, description, state
+    )]
+    Timeout {
+        description: String,
+        timeout: Duration,
+        state: String,
+    },
+    #[fail(display = "Stopped waiting until {}: {}", description, reason)]
+    Failed { description: String, reason: String },
+    #[fail(display = "Cannot check whether {}: {}", description, error)]
+    CheckFailed { description: String, error: String },
+}
+
+/// Checks the condition every `interval` until it is fulfilled, failed, or until the timeout has
+/// been reached. The condition is checked at least once, even with a zero timeout.
+pub async fn wait_for(
+    condition: &dyn Condition,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<(), WaitError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let state = condition
+            .check()
+            .await
+            .map_err(|err| WaitError::CheckFailed {
+                description: condition.description(),
+                error: err.to_string(),
+            })?;
+
+        match state {
+            ConditionState::Fulfilled => return Ok(()),
+            ConditionState::Failed(reason) => {
+                return Err(WaitError::Failed {
+                    description: condition.description(),
+                    reason,
+                })
+            }
+            ConditionState::Pending(state) if Instant::now() >= deadline => {
+                return Err(WaitError::Timeout {
+                    description: condition.description(),
+                    timeout,
+                    state,
+                })
+            }
+            ConditionState::Pending(_) => tokio::time::sleep(interval).await,
+        }
+    }
+}
+
+/// Requests the URL until it responds with the expected status code.
+pub struct HttpCondition {
+    url: Url,
+    expected_status: u16,
+}
+
+impl HttpCondition {
+    pub fn new(url: Url, expected_status: u16) -> Self {
+        Self {
+            url,
+            expected_status,
+        }
+    }
+}
+
+#[async_trait]
+impl Condition for HttpCondition {
+    fn description(&self) -> String {
+        format!("GET {} responds with {}", self.url, self.expected_status)
+    }
+
+    async fn check(&self) -> Result<ConditionState, failure::Error> {
+        let response = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(Duration::from_secs(10))
+            .build()?
+            .get(self.url.as_str())
+            .send()
+            .await;
+
+        Ok(match response {
+            Ok(response) if response.status().as_u16() == self.expected_status => {
+                ConditionState::Fulfilled
+            }
+            Ok(response) => ConditionState::Pending(format!(
+                "GET {} responded with {}",
+                self.url,
+                response.status()
+            )),
+            Err(err) => ConditionState::Pending(format!("GET {} failed: {}", self.url, err)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Countdown {
+        remaining: Mutex<u32>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Condition for Countdown {
+        fn description(&self) -> String {
+            String::from("countdown is over")
+        }
+
+        async fn check(&self) -> Result<ConditionState, failure::Error> {
+            let mut remaining = self.remaining.lock().unwrap();
+            if *remaining == 0 {
+                return Ok(ConditionState::Fulfilled);
+            }
+            *remaining -= 1;
+            Ok(if self.fail {
+                ConditionState::Failed(String::from("countdown broke"))
+            } else {
+                ConditionState::Pending(format!("{} left", *remaining + 1))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_wait_until_condition_is_fulfilled() {
+        let countdown = Countdown {
+            remaining: Mutex::new(3),
+            fail: false,
+        };
+
+        let result = wait_for(&countdown, Duration::from_secs(5), Duration::from_millis(1)).await;
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn should_time_out_with_last_state() {
+        let countdown = Countdown {
+            remaining: Mutex::new(1000),
+            fail: false,
+        };
+
+        let result = wait_for(&countdown, Duration::ZERO, Duration::from_millis(1)).await;
+
+        assert_eq!(
+            result,
+            Err(WaitError::Timeout {
+                description: String::from("countdown is over"),
+                timeout: Duration::ZERO,
+                state: String::from("1000 left"),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_stop_waiting_for_failed_condition() {
+        let countdown = Countdown {
+            remaining: Mutex::new(3),
+            fail: true,
+        };
+
+        let result = wait_for(&countdown, Duration::from_secs(5), Duration::from_millis(1)).await;
+
+        assert_eq!(
+            result,
+            Err(WaitError::Failed {
+                description: String::from("countdown is over"),
+                reason: String::from("countdown broke"),
+            })
+        );
+    }
+}
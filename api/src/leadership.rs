@@ -0,0 +1,178 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Tells whether this replica of PREvant is the leader that is allowed to change apps. Without
+/// leader election, the only replica is always the leader.
+#[derive(Clone, Debug)]
+pub struct Leadership {
+    state: Arc<RwLock<LeadershipState>>,
+    patience: Duration,
+}
+
+#[derive(Debug)]
+struct LeadershipState {
+    is_leader: bool,
+    leader: Option<String>,
+    /// The point in time until which the lease of this replica is valid. After it passed without
+    /// a renewal, this replica is not the leader anymore, even if the renewal is still pending.
+    valid_until: Option<Instant>,
+}
+
+impl Leadership {
+    /// The leadership of a replica that has to wait for the election of a leader.
+    pub fn follower() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(LeadershipState {
+                is_leader: false,
+                leader: None,
+                valid_until: None,
+            })),
+            patience: Duration::ZERO,
+        }
+    }
+
+    /// Lets changes wait up to the given duration for this replica to become the leader, e.g.
+    /// while the lease is handed over from a leader that stopped, before they are rejected.
+    pub fn with_patience(mut self, patience: Duration) -> Self {
+        self.patience = patience;
+        self
+    }
+
+    pub fn is_leader(&self) -> bool {
+        let state = self.state.read().unwrap();
+        state.is_leader
+            && state
+                .valid_until
+                .map_or(true, |valid_until| Instant::now() < valid_until)
+    }
+
+    /// Waits up to the patience of this replica until it is the leader and returns whether it is
+    /// the leader.
+    pub async fn wait_for_leadership(&self) -> bool {
+        let deadline = Instant::now() + self.patience;
+        loop {
+            if self.is_leader() {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(250).min(self.patience)).await;
+        }
+    }
+
+    /// The identity of the current leader, if known.
+    pub fn leader(&self) -> Option<String> {
+        self.state.read().unwrap().leader.clone()
+    }
+
+    /// Updates the leadership with the current holder of the lease and the point in time until
+    /// which the lease is valid.
+    pub fn update(&self, identity: &str, leader: Option<String>, valid_until: Option<Instant>) {
+        let is_leader = leader.as_deref() == Some(identity);
+
+        let mut state = self.state.write().unwrap();
+        let was_leader = state.is_leader
+            && state
+                .valid_until
+                .map_or(true, |valid_until| Instant::now() < valid_until);
+        if was_leader != is_leader {
+            if is_leader {
+                log::info!("{} became the leader", identity);
+            } else {
+                log::info!("{} is not the leader anymore", identity);
+            }
+        }
+        state.is_leader = is_leader;
+        state.leader = leader;
+        state.valid_until = valid_until;
+    }
+}
+
+impl Default for Leadership {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(LeadershipState {
+                is_leader: true,
+                leader: None,
+                valid_until: None,
+            })),
+            patience: Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_follow_the_holder_of_the_lease() {
+        let leadership = Leadership::follower();
+        assert!(!leadership.is_leader());
+
+        leadership.update("prevant-0", Some(String::from("prevant-0")), None);
+        assert!(leadership.is_leader());
+
+        leadership.update("prevant-0", Some(String::from("prevant-1")), None);
+        assert!(!leadership.is_leader());
+        assert_eq!(leadership.leader(), Some(String::from("prevant-1")));
+    }
+
+    #[test]
+    fn should_stop_leading_after_the_lease_expired() {
+        let leadership = Leadership::follower();
+
+        leadership.update(
+            "prevant-0",
+            Some(String::from("prevant-0")),
+            Some(Instant::now() + Duration::from_secs(15)),
+        );
+        assert!(leadership.is_leader());
+
+        leadership.update(
+            "prevant-0",
+            Some(String::from("prevant-0")),
+            Some(Instant::now() - Duration::from_secs(1)),
+        );
+        assert!(!leadership.is_leader());
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_leadership() {
+        let leadership = Leadership::follower().with_patience(Duration::from_secs(5));
+
+        let election = leadership.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            election.update("prevant-0", Some(String::from("prevant-0")), None);
+        });
+
+        assert!(leadership.wait_for_leadership().await);
+    }
+}
@@ -34,9 +34,15 @@ extern crate rocket;
 extern crate serde_derive;
 
 use crate::apps::host_meta_crawling;
+use crate::apps::spawn_service_state_watcher;
+use crate::apps::spawn_soft_delete_reaper;
 use crate::apps::Apps;
-use crate::config::{Config, Runtime};
-use crate::infrastructure::{Docker, Infrastructure, Kubernetes};
+use crate::config::{watch_config_file, Config, ConfigHandle, Runtime};
+use crate::desired_apps::spawn_desired_apps_reconciler;
+use crate::infrastructure::{
+    spawn_leader_election, Docker, Infrastructure, Kubernetes, MultiCluster,
+};
+use crate::leadership::Leadership;
 use crate::models::request_info::RequestInfo;
 use clap::Parser;
 use rocket::fs::{FileServer, Options};
@@ -47,13 +53,29 @@ use std::process;
 use std::sync::Arc;
 
 mod apps;
+mod audit;
+mod auth;
+mod backup;
+mod compression;
 mod config;
 mod deployment;
+mod desired_apps;
+mod doctor;
+mod health;
 mod http_result;
 mod infrastructure;
+mod leadership;
+mod log_archive;
 mod models;
+mod notifications;
 mod registry;
+mod reports;
+mod sbom;
+mod search;
+mod static_assets;
 mod tickets;
+mod version;
+mod vulnerabilities;
 mod webhooks;
 
 #[get("/")]
@@ -77,8 +99,29 @@ fn openapi(request_info: RequestInfo) -> Option<String> {
 }
 
 fn create_infrastructure(config: &Config) -> Box<dyn Infrastructure> {
+    let default = create_runtime_infrastructure(config);
+    if config.runtime_clusters().is_empty() {
+        return default;
+    }
+
+    let clusters = config
+        .runtime_clusters()
+        .iter()
+        .map(|(name, runtime)| {
+            log::info!("Adding cluster {}", name);
+            (
+                name.clone(),
+                create_runtime_infrastructure(&config.with_runtime(runtime.clone())),
+            )
+        })
+        .collect();
+
+    Box::new(MultiCluster::new(default, clusters))
+}
+
+fn create_runtime_infrastructure(config: &Config) -> Box<dyn Infrastructure> {
     match config.runtime_config() {
-        Runtime::Docker => {
+        Runtime::Docker(_) => {
             log::info!("Using Docker backend");
             Box::new(Docker::new(config.clone()))
         }
@@ -86,22 +129,83 @@ fn create_infrastructure(config: &Config) -> Box<dyn Infrastructure> {
             log::info!("Using Kubernetes backend");
             Box::new(Kubernetes::new(config.clone()))
         }
+        #[cfg(feature = "dummy-infrastructure")]
+        Runtime::Dummy(dummy_config) => {
+            log::warn!("Using in-memory dummy backend, nothing will be deployed");
+            Box::new(
+                crate::infrastructure::Dummy::with_delay(dummy_config.delay())
+                    .with_failing_operations(dummy_config.failing_operations().iter().copied()),
+            )
+        }
+    }
+}
+
+fn create_leadership(config: &Config) -> Leadership {
+    let Some(leader_election) = config.leader_election_config() else {
+        return Leadership::default();
+    };
+
+    match config.runtime_config() {
+        Runtime::Kubernetes(_) => {
+            log::info!(
+                "Electing the leader through the lease {}",
+                leader_election.lease_name()
+            );
+            spawn_leader_election(Kubernetes::new(config.clone()), leader_election.clone())
+        }
+        _ => {
+            warn!("Leader election requires the Kubernetes backend, this replica is the leader");
+            Leadership::default()
+        }
     }
 }
 
-#[rocket::main]
-async fn main() -> Result<(), StartUpError> {
+fn main() -> Result<(), StartUpError> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
     let cli = crate::config::CliArgs::parse();
 
+    if cli.check_config() {
+        let validation = crate::config::validate(&cli);
+        for issue in validation.issues() {
+            error!("{}: {}", cli.config_file().display(), issue);
+        }
+
+        if validation.is_valid() {
+            log::info!("Configuration {} is valid", cli.config_file().display());
+            return Ok(());
+        }
+        process::exit(0x0001);
+    }
+
     let config = Config::from_figment(&cli).map_err(|err| StartUpError::InvalidConfiguration {
         err: err.to_string(),
     })?;
 
+    // Changing the environment is only sound before the runtime has started its threads.
+    config.network_config().export_proxy_environment();
+
+    rocket::execute(serve(cli, config))
+}
+
+async fn serve(cli: crate::config::CliArgs, config: Config) -> Result<(), StartUpError> {
     let infrastructure = create_infrastructure(&config);
-    let apps = match Apps::new(config.clone(), infrastructure) {
-        Ok(apps_service) => apps_service,
+    let leadership = create_leadership(&config);
+    let config = ConfigHandle::new(config);
+    let _config_watcher = match watch_config_file(cli.clone(), config.clone()) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            warn!(
+                "Cannot watch {}, changes require a restart: {}",
+                cli.config_file().display(),
+                err
+            );
+            None
+        }
+    };
+
+    let apps = match Apps::with_config_handle(config.clone(), infrastructure) {
+        Ok(apps_service) => apps_service.with_leadership(leadership),
         Err(e) => {
             error!("Cannot create apps service: {}", e);
             process::exit(0x0200);
@@ -111,19 +215,38 @@ async fn main() -> Result<(), StartUpError> {
     let (host_meta_cache, host_meta_crawler) = host_meta_crawling();
     let apps = Arc::new(apps);
     host_meta_crawler.spawn(apps.clone());
+    spawn_soft_delete_reaper(apps.clone());
+    spawn_service_state_watcher(apps.clone());
+    let _desired_apps_watcher = spawn_desired_apps_reconciler(apps.clone(), &config);
 
     let _rocket = rocket::build()
         .manage(config)
+        .manage(cli)
         .manage(apps)
         .manage(host_meta_cache)
+        .attach(audit::AuditLog)
+        .attach(compression::ResponseCompression)
         .mount(
             "/",
             FileServer::new(Path::new("frontend"), Options::Index | Options::Missing),
         )
         .mount("/openapi.yaml", routes![openapi])
+        .mount("/", routes![auth::forward])
         .mount("/api/apps", crate::apps::apps_routes())
+        .mount("/api/v2/apps", crate::apps::apps_v2_routes())
         .mount("/api", routes![tickets::tickets])
         .mount("/api", routes![webhooks::webhooks])
+        .mount("/api", routes![crate::config::validate_config])
+        .mount("/api", routes![search::search])
+        .mount(
+            "/api",
+            routes![reports::images, reports::apps_of_image, reports::costs],
+        )
+        .mount("/api", routes![doctor::doctor, health::live, health::ready])
+        .mount("/api", routes![audit::audit_log])
+        .mount("/api", routes![backup::backup, backup::restore])
+        .mount("/api", routes![version::version])
+        .mount("/api", routes![crate::apps::events])
         .launch()
         .await?;
 
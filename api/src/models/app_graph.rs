@@ -0,0 +1,257 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::service::ContainerType;
+use crate::models::{AppName, ServiceConfig};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// The services of an app with their companions, volumes, and routes as nodes and the relations
+/// between them as edges so that clients can render a diagram of the app.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AppGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GraphNode {
+    id: String,
+    kind: NodeKind,
+    label: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeKind {
+    Service,
+    Companion,
+    Volume,
+    Route,
+}
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct GraphEdge {
+    from: String,
+    to: String,
+    kind: EdgeKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EdgeKind {
+    /// The service declares the other service in `dependsOn`.
+    DependsOn,
+    /// An environment variable of the service contains the host name of the other service.
+    References,
+    Mounts,
+    Routes,
+}
+
+impl AppGraph {
+    pub fn new(app_name: &AppName, configs: &[ServiceConfig]) -> Self {
+        let mut configs = configs.iter().collect::<Vec<_>>();
+        configs.sort_by(|c1, c2| c1.service_name().cmp(c2.service_name()));
+
+        // Other services reach a service by its name and by its aliases.
+        let hosts = configs
+            .iter()
+            .copied()
+            .flat_map(|config| {
+                std::iter::once(config.service_name())
+                    .chain(config.aliases())
+                    .map(move |host| (host.as_str(), config.service_name()))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut nodes = Vec::new();
+        let mut edges = BTreeSet::new();
+        for config in &configs {
+            let service_name = config.service_name();
+            let id = service_id(service_name);
+            nodes.push(GraphNode {
+                id: id.clone(),
+                kind: match config.container_type() {
+                    ContainerType::Instance | ContainerType::Replica => NodeKind::Service,
+                    ContainerType::ApplicationCompanion | ContainerType::ServiceCompanion => {
+                        NodeKind::Companion
+                    }
+                },
+                label: service_name.clone(),
+            });
+
+            let route = match config.router() {
+                Some(router) => router.rule().clone(),
+                None => format!("/{}/{}/", app_name, service_name),
+            };
+            let route_id = format!("route/{}", service_name);
+            nodes.push(GraphNode {
+                id: route_id.clone(),
+                kind: NodeKind::Route,
+                label: route,
+            });
+            edges.insert(GraphEdge {
+                from: route_id,
+                to: id.clone(),
+                kind: EdgeKind::Routes,
+            });
+
+            for volume in volumes(config) {
+                let volume_id = format!("volume/{}{}", service_name, volume.display());
+                nodes.push(GraphNode {
+                    id: volume_id.clone(),
+                    kind: NodeKind::Volume,
+                    label: volume.display().to_string(),
+                });
+                edges.insert(GraphEdge {
+                    from: id.clone(),
+                    to: volume_id,
+                    kind: EdgeKind::Mounts,
+                });
+            }
+
+            for dependency in config.depends_on() {
+                if let Some(target) = hosts.get(dependency.as_str()) {
+                    edges.insert(GraphEdge {
+                        from: id.clone(),
+                        to: service_id(target),
+                        kind: EdgeKind::DependsOn,
+                    });
+                }
+            }
+
+            for variable in config.env().into_iter().flat_map(|env| env.iter()) {
+                let referenced_hosts = variable
+                    .value()
+                    .unsecure()
+                    .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+                    .filter_map(|token| hosts.get(token))
+                    .filter(|target| **target != service_name)
+                    .collect::<Vec<_>>();
+                for target in referenced_hosts {
+                    edges.insert(GraphEdge {
+                        from: id.clone(),
+                        to: service_id(target),
+                        kind: EdgeKind::References,
+                    });
+                }
+            }
+        }
+
+        Self {
+            nodes,
+            edges: edges.into_iter().collect(),
+        }
+    }
+}
+
+fn service_id(service_name: &str) -> String {
+    format!("service/{}", service_name)
+}
+
+/// The directories of the mounted files and the scratch volumes of the service.
+fn volumes(config: &ServiceConfig) -> BTreeSet<PathBuf> {
+    let file_directories = config
+        .files()
+        .into_iter()
+        .flat_map(|files| files.keys())
+        .filter_map(|path| path.parent())
+        .map(PathBuf::from);
+    let scratch_volumes = config
+        .ephemeral_volumes()
+        .into_iter()
+        .map(|volume| volume.path().clone());
+
+    file_directories.chain(scratch_volumes).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Environment, EnvironmentVariable};
+    use crate::sc;
+    use secstr::SecUtf8;
+
+    fn node(id: &str, kind: NodeKind, label: &str) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            kind,
+            label: label.to_string(),
+        }
+    }
+
+    fn edge(from: &str, to: &str, kind: EdgeKind) -> GraphEdge {
+        GraphEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn should_build_graph_of_services() {
+        let mut blog = sc!("blog");
+        blog.set_depends_on(vec![String::from("db"), String::from("unknown")]);
+        blog.set_env(Some(Environment::new(vec![EnvironmentVariable::new(
+            String::from("WORDPRESS_DB_HOST"),
+            SecUtf8::from("database:3306"),
+        )])));
+        blog.add_file(
+            PathBuf::from("/var/www/html/.htaccess"),
+            SecUtf8::from("Options -Indexes"),
+        );
+
+        let mut db = sc!("db");
+        db.set_container_type(ContainerType::ApplicationCompanion);
+        db.set_aliases(vec![String::from("database")]);
+
+        let graph = AppGraph::new(&AppName::master(), &[db, blog]);
+
+        assert_eq!(
+            graph.nodes,
+            vec![
+                node("service/blog", NodeKind::Service, "blog"),
+                node("route/blog", NodeKind::Route, "/master/blog/"),
+                node(
+                    "volume/blog/var/www/html",
+                    NodeKind::Volume,
+                    "/var/www/html"
+                ),
+                node("service/db", NodeKind::Companion, "db"),
+                node("route/db", NodeKind::Route, "/master/db/"),
+            ]
+        );
+        assert_eq!(
+            graph.edges,
+            vec![
+                edge("route/blog", "service/blog", EdgeKind::Routes),
+                edge("route/db", "service/db", EdgeKind::Routes),
+                edge("service/blog", "service/db", EdgeKind::DependsOn),
+                edge("service/blog", "service/db", EdgeKind::References),
+                edge("service/blog", "volume/blog/var/www/html", EdgeKind::Mounts),
+            ]
+        );
+    }
+}
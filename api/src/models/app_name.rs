@@ -33,13 +33,18 @@ use std::collections::HashSet;
 use std::ops::Deref;
 use std::str::{FromStr, Utf8Error};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct AppName(String);
 
 impl AppName {
     pub fn master() -> Self {
         Self(String::from("master"))
     }
+
+    /// The app that runs the companions which are shared by all apps.
+    pub fn shared_companions() -> Self {
+        Self(String::from("shared-companions"))
+    }
 }
 
 impl serde::Serialize for AppName {
@@ -51,6 +56,16 @@ impl serde::Serialize for AppName {
     }
 }
 
+impl<'de> serde::Deserialize<'de> for AppName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        AppName::from_str(&name).map_err(serde::de::Error::custom)
+    }
+}
+
 impl Deref for AppName {
     type Target = String;
 
@@ -107,7 +122,7 @@ impl<'r> FromFormField<'r> for AppName {
     }
 }
 
-#[derive(Debug, Fail)]
+#[derive(Clone, Debug, Fail)]
 pub enum AppNameError {
     #[fail(
         display = "Invalid characters in app name: “{}” are invalid.",
@@ -116,6 +131,31 @@ pub enum AppNameError {
     InvalidChars { invalid_chars: String },
     #[fail(display = "Invalid url encoded parameter: {}", err)]
     InvalidUrlDecodedParam { err: String },
+    #[fail(display = "The app name {} is reserved.", app_name)]
+    Reserved { app_name: String },
+    #[fail(
+        display = "The app name {} is too long: it has {} characters but at most {} are allowed.",
+        app_name, length, max_length
+    )]
+    TooLong {
+        app_name: String,
+        length: usize,
+        max_length: usize,
+    },
+    #[fail(
+        display = "The app name {} does not match the pattern {}.",
+        app_name, pattern
+    )]
+    InvalidPattern { app_name: String, pattern: String },
+    #[fail(
+        display = "The app name {} is too long for the service {}: at most {} characters are allowed.",
+        app_name, service_name, max_length
+    )]
+    TooLongForService {
+        app_name: String,
+        service_name: String,
+        max_length: usize,
+    },
 }
 
 impl From<Utf8Error> for AppNameError {
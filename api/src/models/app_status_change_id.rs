@@ -43,6 +43,15 @@ impl std::fmt::Display for AppStatusChangeId {
     }
 }
 
+impl serde::Serialize for AppStatusChangeId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 impl std::str::FromStr for AppStatusChangeId {
     type Err = AppStatusChangeIdError;
 
@@ -0,0 +1,116 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::AppName;
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+/// A mutating request to PREvant's API as it has been recorded in the audit log.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    recorded_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    method: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    app_name: Option<AppName>,
+    status: u16,
+    /// The hex encoded SHA-256 hash of the JSON payload of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload_hash: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        user: Option<String>,
+        method: String,
+        path: String,
+        status: u16,
+        payload_hash: Option<String>,
+    ) -> Self {
+        Self {
+            recorded_at: Utc::now(),
+            user,
+            app_name: app_name_of(&path),
+            method,
+            path,
+            status,
+            payload_hash,
+        }
+    }
+
+    pub fn recorded_at(&self) -> &DateTime<Utc> {
+        &self.recorded_at
+    }
+
+    pub fn user(&self) -> Option<&String> {
+        self.user.as_ref()
+    }
+
+    pub fn app_name(&self) -> Option<&AppName> {
+        self.app_name.as_ref()
+    }
+}
+
+/// Determines the app that has been changed by requests to `/api/apps/<app>/…`.
+fn app_name_of(path: &str) -> Option<AppName> {
+    let path = path.split('?').next().unwrap_or_default();
+    path.strip_prefix("/api/apps/")
+        .and_then(|path| path.split('/').next())
+        .and_then(|app_name| AppName::from_str(app_name).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_determine_app_of_request() {
+        let entry = AuditEntry::new(
+            Some(String::from("alice")),
+            String::from("PUT"),
+            String::from("/api/apps/master/states/db?wait=10s"),
+            202,
+            None,
+        );
+
+        assert_eq!(entry.app_name(), Some(&AppName::master()));
+    }
+
+    #[test]
+    fn should_not_determine_app_of_other_requests() {
+        let entry = AuditEntry::new(
+            None,
+            String::from("POST"),
+            String::from("/api/webhooks"),
+            204,
+            None,
+        );
+
+        assert_eq!(entry.app_name(), None);
+    }
+}
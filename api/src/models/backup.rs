@@ -0,0 +1,83 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use super::{AppName, AuditEntry, DeploymentHistoryEntry, LifecycleHook, StoredShareToken};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// A portable snapshot of the state that PREvant keeps in its state store, e.g. to migrate
+/// PREvant to another cluster or to recover from the loss of the state store.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    version: u32,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    apps: BTreeMap<AppName, AppBackup>,
+    #[serde(default)]
+    audit_log: Vec<AuditEntry>,
+}
+
+/// The state of a single app.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppBackup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft_deleted_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub deployment_history: Vec<DeploymentHistoryEntry>,
+    #[serde(default)]
+    pub share_tokens: Vec<StoredShareToken>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_destroy_hooks: Vec<LifecycleHook>,
+}
+
+impl Backup {
+    /// The version of the format of the backups written by this version of PREvant.
+    pub const VERSION: u32 = 1;
+
+    pub fn new(apps: BTreeMap<AppName, AppBackup>, audit_log: Vec<AuditEntry>) -> Self {
+        Self {
+            version: Self::VERSION,
+            created_at: Utc::now(),
+            apps,
+            audit_log,
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn apps(&self) -> &BTreeMap<AppName, AppBackup> {
+        &self.apps
+    }
+
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        &self.audit_log
+    }
+}
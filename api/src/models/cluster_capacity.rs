@@ -0,0 +1,285 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The resources, e.g. `memory` in bytes or `nvidia.com/gpu` in units, that are still free on
+/// the schedulable nodes of a cluster.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterCapacity {
+    nodes: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+/// The resources that each of the `replicas` of a service requests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceRequests {
+    service_name: String,
+    replicas: u32,
+    resources: BTreeMap<String, f64>,
+}
+
+/// A resource that the cluster cannot provide for a service or for the app as a whole.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapacityShortage {
+    resource: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_name: Option<String>,
+    requested: f64,
+    available: f64,
+}
+
+impl ClusterCapacity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node with the resources that have not been requested by other pods yet.
+    pub fn with_node(mut self, name: String, free: BTreeMap<String, f64>) -> Self {
+        self.nodes.insert(name, free);
+        self
+    }
+
+    fn available_on_largest_node(&self, resource: &str) -> f64 {
+        self.nodes
+            .values()
+            .map(|free| free.get(resource).copied().unwrap_or_default())
+            .fold(0.0, f64::max)
+    }
+
+    fn available_in_total(&self, resource: &str) -> f64 {
+        self.nodes
+            .values()
+            .map(|free| free.get(resource).copied().unwrap_or_default())
+            .sum()
+    }
+
+    /// Returns the resources that cannot be provided for the `requests`, either because a single
+    /// replica does not fit on any node or because all replicas together exceed the free
+    /// resources of the cluster. Pods with these requests would stay pending forever.
+    pub fn shortages(&self, requests: &[ResourceRequests]) -> Vec<CapacityShortage> {
+        let mut shortages = Vec::new();
+        let mut totals = BTreeMap::<&String, f64>::new();
+
+        for request in requests.iter().filter(|request| request.replicas > 0) {
+            for (resource, quantity) in &request.resources {
+                *totals.entry(resource).or_default() += quantity * f64::from(request.replicas);
+
+                let available = self.available_on_largest_node(resource);
+                if *quantity > available {
+                    shortages.push(CapacityShortage {
+                        resource: resource.clone(),
+                        service_name: Some(request.service_name.clone()),
+                        requested: *quantity,
+                        available,
+                    });
+                }
+            }
+        }
+
+        for (resource, requested) in totals {
+            let available = self.available_in_total(resource);
+            let reported = shortages
+                .iter()
+                .any(|shortage| &shortage.resource == resource);
+            if requested > available && !reported {
+                shortages.push(CapacityShortage {
+                    resource: resource.clone(),
+                    service_name: None,
+                    requested,
+                    available,
+                });
+            }
+        }
+
+        shortages
+    }
+}
+
+impl ResourceRequests {
+    pub fn new(service_name: String, replicas: u32, resources: BTreeMap<String, f64>) -> Self {
+        Self {
+            service_name,
+            replicas,
+            resources,
+        }
+    }
+}
+
+impl CapacityShortage {
+    pub fn resource(&self) -> &String {
+        &self.resource
+    }
+
+    pub fn service_name(&self) -> Option<&String> {
+        self.service_name.as_ref()
+    }
+}
+
+impl fmt::Display for CapacityShortage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.service_name {
+            Some(service_name) => write!(
+                f,
+                "{} requests {} {} per replica but at most {} are free on a single node",
+                service_name, self.requested, self.resource, self.available
+            ),
+            None => write!(
+                f,
+                "the services request {} {} in total but only {} are free",
+                self.requested, self.resource, self.available
+            ),
+        }
+    }
+}
+
+/// Parses a Kubernetes [quantity](https://kubernetes.io/docs/reference/kubernetes-api/common-definitions/quantity/),
+/// e.g. `16Gi`, `500m`, or `1e3`, into its value in base units.
+pub fn parse_quantity(quantity: &str) -> Option<f64> {
+    let quantity = quantity.trim();
+    let mut suffix_start = quantity
+        .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+        .unwrap_or(quantity.len());
+    // A trailing `E` is the suffix exa instead of an exponent without digits.
+    if quantity[..suffix_start].ends_with('E') {
+        suffix_start -= 1;
+    }
+    let (number, suffix) = quantity.split_at(suffix_start);
+
+    let factor = match suffix {
+        "" => 1.0,
+        "n" => 1e-9,
+        "u" => 1e-6,
+        "m" => 1e-3,
+        "k" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        "Ki" => 1024_f64,
+        "Mi" => 1024_f64.powi(2),
+        "Gi" => 1024_f64.powi(3),
+        "Ti" => 1024_f64.powi(4),
+        "Pi" => 1024_f64.powi(5),
+        "Ei" => 1024_f64.powi(6),
+        _ => return None,
+    };
+
+    number.parse::<f64>().ok().map(|number| number * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_quantities() {
+        assert_eq!(parse_quantity("2"), Some(2.0));
+        assert_eq!(parse_quantity("500m"), Some(0.5));
+        assert_eq!(parse_quantity("16Gi"), Some(17179869184.0));
+        assert_eq!(parse_quantity("1e3"), Some(1000.0));
+        assert_eq!(parse_quantity("1.5k"), Some(1500.0));
+        assert_eq!(parse_quantity("2E"), Some(2e18));
+        assert_eq!(parse_quantity("1Xi"), None);
+    }
+
+    #[test]
+    fn should_report_replica_that_fits_on_no_node() {
+        let capacity = ClusterCapacity::new()
+            .with_node(
+                String::from("node-1"),
+                BTreeMap::from([(String::from("memory"), 4.0)]),
+            )
+            .with_node(
+                String::from("node-2"),
+                BTreeMap::from([(String::from("memory"), 4.0)]),
+            );
+
+        let shortages = capacity.shortages(&[ResourceRequests::new(
+            String::from("db"),
+            1,
+            BTreeMap::from([(String::from("memory"), 6.0)]),
+        )]);
+
+        assert_eq!(
+            shortages,
+            vec![CapacityShortage {
+                resource: String::from("memory"),
+                service_name: Some(String::from("db")),
+                requested: 6.0,
+                available: 4.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn should_report_replicas_exceeding_the_cluster() {
+        let capacity = ClusterCapacity::new().with_node(
+            String::from("node-1"),
+            BTreeMap::from([(String::from("nvidia.com/gpu"), 2.0)]),
+        );
+
+        let shortages = capacity.shortages(&[
+            ResourceRequests::new(
+                String::from("trainer"),
+                2,
+                BTreeMap::from([(String::from("nvidia.com/gpu"), 1.0)]),
+            ),
+            ResourceRequests::new(
+                String::from("inference"),
+                1,
+                BTreeMap::from([(String::from("nvidia.com/gpu"), 1.0)]),
+            ),
+        ]);
+
+        assert_eq!(
+            shortages,
+            vec![CapacityShortage {
+                resource: String::from("nvidia.com/gpu"),
+                service_name: None,
+                requested: 3.0,
+                available: 2.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn should_report_no_shortages_if_requests_fit() {
+        let capacity = ClusterCapacity::new().with_node(
+            String::from("node-1"),
+            BTreeMap::from([(String::from("memory"), 4.0)]),
+        );
+
+        let shortages = capacity.shortages(&[ResourceRequests::new(
+            String::from("api"),
+            2,
+            BTreeMap::from([(String::from("memory"), 2.0)]),
+        )]);
+
+        assert!(shortages.is_empty());
+    }
+}
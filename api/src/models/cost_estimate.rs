@@ -0,0 +1,120 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use std::iter::Sum;
+use std::ops::Add;
+
+/// Hours per month that are used to extrapolate the monthly costs (365 days × 24 hours / 12).
+const HOURS_PER_MONTH: f64 = 730.0;
+
+/// The estimated costs of running a service or an app, based on the configured prices for the
+/// resources that the containers request.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostEstimate {
+    currency: String,
+    hourly: f64,
+    monthly: f64,
+}
+
+impl CostEstimate {
+    pub fn from_hourly(currency: String, hourly: f64) -> Self {
+        Self {
+            currency,
+            hourly: round(hourly, 4),
+            monthly: round(hourly * HOURS_PER_MONTH, 2),
+        }
+    }
+
+    pub fn currency(&self) -> &String {
+        &self.currency
+    }
+
+    pub fn hourly(&self) -> f64 {
+        self.hourly
+    }
+
+    pub fn monthly(&self) -> f64 {
+        self.monthly
+    }
+}
+
+impl Add for CostEstimate {
+    type Output = CostEstimate;
+
+    fn add(self, other: Self) -> Self::Output {
+        let currency = if self.currency.is_empty() {
+            other.currency
+        } else {
+            self.currency
+        };
+        Self {
+            currency,
+            hourly: round(self.hourly + other.hourly, 4),
+            monthly: round(self.monthly + other.monthly, 2),
+        }
+    }
+}
+
+impl<'a> Sum<&'a CostEstimate> for CostEstimate {
+    fn sum<I: Iterator<Item = &'a CostEstimate>>(iter: I) -> Self {
+        iter.fold(CostEstimate::default(), |sum, estimate| {
+            sum + estimate.clone()
+        })
+    }
+}
+
+fn round(value: f64, decimals: i32) -> f64 {
+    let factor = 10_f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_extrapolate_monthly_costs() {
+        let estimate = CostEstimate::from_hourly(String::from("EUR"), 0.0123456);
+
+        assert_eq!(estimate.hourly(), 0.0123);
+        assert_eq!(estimate.monthly(), 9.01);
+    }
+
+    #[test]
+    fn should_sum_estimates() {
+        let estimates = vec![
+            CostEstimate::from_hourly(String::from("EUR"), 0.01),
+            CostEstimate::from_hourly(String::from("EUR"), 0.02),
+        ];
+
+        let sum = estimates.iter().sum::<CostEstimate>();
+
+        assert_eq!(sum.currency(), "EUR");
+        assert_eq!(sum.hourly(), 0.03);
+        assert_eq!(sum.monthly(), 21.9);
+    }
+}
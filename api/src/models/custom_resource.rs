@@ -0,0 +1,212 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use serde_json::Value;
+use std::time::Duration;
+
+/// An arbitrary custom resource, e.g. a `Kafka` of Strimzi or a `postgresql` of the Zalando
+/// operator, that will be applied into the namespace of an app. Group, version, and kind are taken
+/// from the manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomResource {
+    name: String,
+    manifest: Value,
+    readiness: Option<CustomResourceReadiness>,
+    timeout: Duration,
+}
+
+/// Describes when the operator has finished to provision a custom resource.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CustomResourceReadiness {
+    /// The resource reports the condition with the status `True` in `status.conditions`.
+    Condition(String),
+    /// The value at the JSON pointer, e.g. `/status/PostgresClusterStatus`, equals the value.
+    Value { pointer: String, value: String },
+}
+
+impl CustomResource {
+    pub fn new(
+        name: String,
+        manifest: Value,
+        readiness: Option<CustomResourceReadiness>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            name,
+            manifest,
+            readiness,
+            timeout,
+        }
+    }
+
+    /// The name of the companion that declared the custom resource.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The rendered manifest of the custom resource.
+    pub fn manifest(&self) -> &Value {
+        &self.manifest
+    }
+
+    pub fn api_version(&self) -> Option<&str> {
+        self.manifest.get("apiVersion")?.as_str()
+    }
+
+    pub fn kind(&self) -> Option<&str> {
+        self.manifest.get("kind")?.as_str()
+    }
+
+    pub fn readiness(&self) -> Option<&CustomResourceReadiness> {
+        self.readiness.as_ref()
+    }
+
+    /// How long the deployment waits for the readiness of the custom resource.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl CustomResourceReadiness {
+    /// Checks the readiness against the current state of the custom resource.
+    pub fn is_ready(&self, resource: &Value) -> bool {
+        match self {
+            CustomResourceReadiness::Condition(condition_type) => resource
+                .pointer("/status/conditions")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .any(|condition| {
+                    condition.get("type").and_then(Value::as_str) == Some(condition_type)
+                        && condition.get("status").and_then(Value::as_str) == Some("True")
+                }),
+            CustomResourceReadiness::Value { pointer, value } => match resource.pointer(pointer) {
+                Some(Value::String(actual)) => actual == value,
+                Some(actual) => &actual.to_string() == value,
+                None => false,
+            },
+        }
+    }
+}
+
+/// Converts a JSONPath in the syntax of [jsonpath_lib](https://docs.rs/jsonpath_lib) that selects
+/// a single value by keys and indices, e.g. `$.status.conditions[0].type` or
+/// `$['status']['phase']`, into a JSON pointer. Wildcards, recursive descent, and filters are
+/// rejected because they might select multiple values.
+pub fn json_path_to_pointer(path: &str) -> Result<String, String> {
+    let unsupported = || {
+        format!(
+            "the JSONPath {} must select a single value by keys and indices, e.g. $.status.phase",
+            path
+        )
+    };
+
+    let mut rest = path.strip_prefix('$').ok_or_else(unsupported)?;
+    let mut pointer = String::new();
+    while !rest.is_empty() {
+        let token = if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot
+                .find(|c| c == '.' || c == '[')
+                .unwrap_or(after_dot.len());
+            rest = &after_dot[end..];
+            &after_dot[..end]
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket.find(']').ok_or_else(unsupported)?;
+            let inner = after_bracket[..end].trim();
+            rest = &after_bracket[end + 1..];
+            match inner
+                .strip_prefix('\'')
+                .and_then(|inner| inner.strip_suffix('\''))
+                .or_else(|| {
+                    inner
+                        .strip_prefix('"')
+                        .and_then(|inner| inner.strip_suffix('"'))
+                }) {
+                Some(key) => key,
+                None if inner.parse::<usize>().is_ok() => inner,
+                None => return Err(unsupported()),
+            }
+        } else {
+            return Err(unsupported());
+        };
+
+        if token.is_empty() || token == "*" {
+            return Err(unsupported());
+        }
+        pointer.push('/');
+        pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    }
+
+    Ok(pointer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn should_be_ready_with_true_condition() {
+        let readiness = CustomResourceReadiness::Condition(String::from("Ready"));
+
+        assert!(readiness.is_ready(&json!({
+            "status": { "conditions": [
+                { "type": "NotReady", "status": "False" },
+                { "type": "Ready", "status": "True" }
+            ]}
+        })));
+        assert!(!readiness.is_ready(&json!({
+            "status": { "conditions": [ { "type": "Ready", "status": "False" } ] }
+        })));
+        assert!(!readiness.is_ready(&json!({ "spec": {} })));
+    }
+
+    #[test]
+    fn should_be_ready_with_expected_value() {
+        let readiness = CustomResourceReadiness::Value {
+            pointer: String::from("/status/PostgresClusterStatus"),
+            value: String::from("Running"),
+        };
+
+        assert!(readiness.is_ready(&json!({ "status": { "PostgresClusterStatus": "Running" } })));
+        assert!(!readiness.is_ready(&json!({ "status": { "PostgresClusterStatus": "Creating" } })));
+        assert!(!readiness.is_ready(&json!({})));
+    }
+
+    #[test]
+    fn should_convert_json_paths_to_pointers() {
+        assert_eq!(
+            json_path_to_pointer("$.status.PostgresClusterStatus"),
+            Ok(String::from("/status/PostgresClusterStatus"))
+        );
+        assert_eq!(
+            json_path_to_pointer("$.status.conditions[0]['last/type']"),
+            Ok(String::from("/status/conditions/0/last~1type"))
+        );
+        assert!(json_path_to_pointer("$..phase").is_err());
+        assert!(json_path_to_pointer("$.status.conditions[?(@.type == 'Ready')]").is_err());
+        assert!(json_path_to_pointer("status.phase").is_err());
+    }
+}
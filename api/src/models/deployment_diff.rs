@@ -0,0 +1,331 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::{LifecycleHookLog, SeedLog, ServiceConfig};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// The changes a deployment applies to the services of an app. Services that are running but
+/// not part of the deployment are kept as they are, thus there are no removed services.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentDiff {
+    added_services: Vec<String>,
+    changed_services: Vec<ServiceDiff>,
+}
+
+/// The changes of a running service. The values of environment variables and the contents of
+/// files are not part of the diff because they might contain secrets.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceDiff {
+    service_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<ImageChange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    added_env: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    removed_env: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    changed_env: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    updated_files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ImageChange {
+    from: String,
+    to: String,
+}
+
+/// A diff that has been applied to an app at the given time.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentHistoryEntry {
+    deployed_at: DateTime<Utc>,
+    outcome: DeploymentOutcome,
+    diff: DeploymentDiff,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hooks: Vec<LifecycleHookLog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    seeds: Vec<SeedLog>,
+}
+
+/// Whether a deployment has been completed or has been canceled while it was running. Canceled
+/// deployments might have applied parts of their diff.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum DeploymentOutcome {
+    Deployed,
+    Canceled,
+}
+
+impl DeploymentDiff {
+    pub fn new<'a, C, I>(current: C, incoming: I) -> Self
+    where
+        C: IntoIterator<Item = &'a ServiceConfig>,
+        I: IntoIterator<Item = &'a ServiceConfig>,
+    {
+        let current = current
+            .into_iter()
+            .map(|config| (config.service_name(), config))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut diff = DeploymentDiff::default();
+        for config in incoming {
+            match current.get(config.service_name()) {
+                None => diff.added_services.push(config.service_name().clone()),
+                Some(running) => {
+                    let service_diff = ServiceDiff::new(running, config);
+                    if !service_diff.is_empty() {
+                        diff.changed_services.push(service_diff);
+                    }
+                }
+            }
+        }
+
+        diff
+    }
+
+    /// The diff of files that have been replaced in the running service without redeploying it.
+    pub fn with_updated_files<'a, P>(service_name: &str, paths: P) -> Self
+    where
+        P: IntoIterator<Item = &'a PathBuf>,
+    {
+        DeploymentDiff {
+            added_services: Vec::new(),
+            changed_services: vec![ServiceDiff {
+                service_name: service_name.to_string(),
+                image: None,
+                added_env: Vec::new(),
+                removed_env: Vec::new(),
+                changed_env: Vec::new(),
+                updated_files: paths.into_iter().cloned().collect(),
+            }],
+        }
+    }
+
+    /// Returns `true` if the deployment neither adds nor changes any service.
+    pub fn is_empty(&self) -> bool {
+        self.added_services.is_empty() && self.changed_services.is_empty()
+    }
+
+    pub fn added_services(&self) -> &Vec<String> {
+        &self.added_services
+    }
+
+    #[cfg(test)]
+    pub fn changed_services(&self) -> &Vec<ServiceDiff> {
+        &self.changed_services
+    }
+}
+
+impl ServiceDiff {
+    fn new(running: &ServiceConfig, incoming: &ServiceConfig) -> Self {
+        let image = if running.image() != incoming.image() {
+            Some(ImageChange {
+                from: running.image().to_string(),
+                to: incoming.image().to_string(),
+            })
+        } else {
+            None
+        };
+
+        let env_of = |config: &ServiceConfig| {
+            config
+                .env()
+                .map(|env| {
+                    env.iter()
+                        .map(|variable| {
+                            (
+                                variable.key().clone(),
+                                variable.value().unsecure().to_string(),
+                            )
+                        })
+                        .collect::<BTreeMap<_, _>>()
+                })
+                .unwrap_or_default()
+        };
+        let running_env = env_of(running);
+        let incoming_env = env_of(incoming);
+        let keys = running_env
+            .keys()
+            .chain(incoming_env.keys())
+            .collect::<BTreeSet<_>>();
+
+        let mut diff = ServiceDiff {
+            service_name: incoming.service_name().clone(),
+            image,
+            added_env: Vec::new(),
+            removed_env: Vec::new(),
+            changed_env: Vec::new(),
+            updated_files: Vec::new(),
+        };
+        for key in keys {
+            match (running_env.get(key), incoming_env.get(key)) {
+                (None, Some(_)) => diff.added_env.push(key.clone()),
+                (Some(_), None) => diff.removed_env.push(key.clone()),
+                (Some(running), Some(incoming)) if running != incoming => {
+                    diff.changed_env.push(key.clone())
+                }
+                _ => {}
+            }
+        }
+
+        diff
+    }
+
+    fn is_empty(&self) -> bool {
+        self.image.is_none()
+            && self.added_env.is_empty()
+            && self.removed_env.is_empty()
+            && self.changed_env.is_empty()
+            && self.updated_files.is_empty()
+    }
+}
+
+impl DeploymentHistoryEntry {
+    pub fn new(diff: DeploymentDiff, hooks: Vec<LifecycleHookLog>) -> Self {
+        Self {
+            deployed_at: Utc::now(),
+            outcome: DeploymentOutcome::Deployed,
+            diff,
+            hooks,
+            seeds: Vec::new(),
+        }
+    }
+
+    /// Records the logs of the seed jobs that ran with the deployment.
+    pub fn with_seeds(mut self, seeds: Vec<SeedLog>) -> Self {
+        self.seeds = seeds;
+        self
+    }
+
+    pub fn canceled(diff: DeploymentDiff) -> Self {
+        Self {
+            deployed_at: Utc::now(),
+            outcome: DeploymentOutcome::Canceled,
+            diff,
+            hooks: Vec::new(),
+            seeds: Vec::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn outcome(&self) -> DeploymentOutcome {
+        self.outcome
+    }
+
+    #[cfg(test)]
+    pub fn diff(&self) -> &DeploymentDiff {
+        &self.diff
+    }
+
+    /// The logs of the post-deploy hooks of the deployment.
+    #[cfg(test)]
+    pub fn hooks(&self) -> &[LifecycleHookLog] {
+        &self.hooks
+    }
+
+    /// The logs of the seed jobs of the deployment.
+    #[cfg(test)]
+    pub fn seeds(&self) -> &[SeedLog] {
+        &self.seeds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sc;
+
+    #[test]
+    fn should_diff_added_and_changed_services() {
+        let running = vec![
+            sc!("db", "postgres:13"),
+            sc!(
+                "api",
+                labels = (),
+                env = ("LOG_LEVEL" => "info", "DB_HOST" => "db", "LEGACY" => "true"),
+                files = ()
+            ),
+            sc!("frontend", "nginx:1.25"),
+        ];
+        let incoming = vec![
+            sc!("db", "postgres:15"),
+            sc!(
+                "api",
+                labels = (),
+                env = ("LOG_LEVEL" => "debug", "DB_HOST" => "db", "FEATURE_X" => "on"),
+                files = ()
+            ),
+            sc!("frontend", "nginx:1.25"),
+            sc!("cache", "redis:7"),
+        ];
+
+        let diff = DeploymentDiff::new(&running, &incoming);
+
+        assert_eq!(diff.added_services(), &vec![String::from("cache")]);
+        assert_eq!(
+            serde_json::to_value(diff.changed_services()).unwrap(),
+            serde_json::json!([{
+                "serviceName": "db",
+                "image": {
+                    "from": "docker.io/library/postgres:13",
+                    "to": "docker.io/library/postgres:15"
+                }
+            }, {
+                "serviceName": "api",
+                "addedEnv": ["FEATURE_X"],
+                "removedEnv": ["LEGACY"],
+                "changedEnv": ["LOG_LEVEL"]
+            }])
+        );
+    }
+
+    #[test]
+    fn should_diff_updated_files() {
+        let paths = vec![PathBuf::from("/etc/nginx/nginx.conf")];
+
+        let diff = DeploymentDiff::with_updated_files("frontend", &paths);
+
+        assert!(!diff.is_empty());
+        assert_eq!(
+            serde_json::to_value(diff.changed_services()).unwrap(),
+            serde_json::json!([{
+                "serviceName": "frontend",
+                "updatedFiles": ["/etc/nginx/nginx.conf"]
+            }])
+        );
+    }
+
+    #[test]
+    fn should_be_empty_for_identical_configs() {
+        let configs = vec![sc!("db", "postgres:15")];
+
+        assert!(DeploymentDiff::new(&configs, &configs).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// A Helm chart that will be installed as release into the namespace of an app, e.g. Kafka or
+/// Keycloak from their upstream charts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HelmRelease {
+    name: String,
+    chart: String,
+    repository: Option<String>,
+    version: Option<String>,
+    values: Option<String>,
+}
+
+impl HelmRelease {
+    pub fn new(
+        name: String,
+        chart: String,
+        repository: Option<String>,
+        version: Option<String>,
+        values: Option<String>,
+    ) -> Self {
+        Self {
+            name,
+            chart,
+            repository,
+            version,
+            values,
+        }
+    }
+
+    /// The name of the release that also becomes the prefix of the chart's resources.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The chart reference, e.g. `kafka` together with a repository or `oci://…` without one.
+    pub fn chart(&self) -> &str {
+        &self.chart
+    }
+
+    pub fn repository(&self) -> Option<&String> {
+        self.repository.as_ref()
+    }
+
+    pub fn version(&self) -> Option<&String> {
+        self.version.as_ref()
+    }
+
+    /// The rendered values in YAML.
+    pub fn values(&self) -> Option<&String> {
+        self.values.as_ref()
+    }
+}
@@ -0,0 +1,250 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::Image;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// Commands that run at certain points of the lifecycle of an app, e.g. to flush caches after a
+/// deployment or to deregister licenses before the app is deleted.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecycleHooks {
+    #[serde(alias = "post_deploy", default)]
+    post_deploy: Vec<LifecycleHook>,
+    #[serde(alias = "pre_destroy", default)]
+    pre_destroy: Vec<LifecycleHook>,
+}
+
+impl LifecycleHooks {
+    /// The hooks that run after all services of the app have been deployed.
+    pub fn post_deploy(&self) -> &[LifecycleHook] {
+        &self.post_deploy
+    }
+
+    /// The hooks that run before the services of the app are stopped.
+    pub fn pre_destroy(&self) -> &[LifecycleHook] {
+        &self.pre_destroy
+    }
+}
+
+/// A command that runs either inside the container of a service of the app or in a one-shot
+/// container of the given image within the network of the app.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "RawLifecycleHook", into = "RawLifecycleHook")]
+pub struct LifecycleHook {
+    target: LifecycleHookTarget,
+    command: Vec<String>,
+    timeout: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LifecycleHookTarget {
+    Service(String),
+    Job(Image),
+}
+
+impl LifecycleHook {
+    pub fn target(&self) -> &LifecycleHookTarget {
+        &self.target
+    }
+
+    pub fn command(&self) -> &[String] {
+        &self.command
+    }
+
+    /// The maximal time the hook may take until it is considered as failed.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl std::fmt::Display for LifecycleHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.target {
+            LifecycleHookTarget::Service(service_name) => {
+                write!(f, "{} in {}", self.command.join(" "), service_name)
+            }
+            LifecycleHookTarget::Job(image) => {
+                write!(f, "{} in a job of {}", self.command.join(" "), image)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawLifecycleHook {
+    #[serde(alias = "service_name", skip_serializing_if = "Option::is_none")]
+    service_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<Image>,
+    #[serde(default)]
+    command: Vec<String>,
+    #[serde(default = "RawLifecycleHook::default_timeout")]
+    timeout: u64,
+}
+
+impl RawLifecycleHook {
+    fn default_timeout() -> u64 {
+        300
+    }
+}
+
+impl TryFrom<RawLifecycleHook> for LifecycleHook {
+    type Error = String;
+
+    fn try_from(raw: RawLifecycleHook) -> Result<Self, Self::Error> {
+        let target = match (raw.service_name, raw.image) {
+            (Some(service_name), None) => LifecycleHookTarget::Service(service_name),
+            (None, Some(image)) => LifecycleHookTarget::Job(image),
+            _ => {
+                return Err(String::from(
+                    "a lifecycle hook requires either a serviceName or an image",
+                ))
+            }
+        };
+
+        if raw.command.is_empty() && matches!(target, LifecycleHookTarget::Service(_)) {
+            return Err(String::from(
+                "a lifecycle hook that runs in a service requires a command",
+            ));
+        }
+
+        Ok(Self {
+            target,
+            command: raw.command,
+            timeout: Duration::from_secs(raw.timeout),
+        })
+    }
+}
+
+impl From<LifecycleHook> for RawLifecycleHook {
+    fn from(hook: LifecycleHook) -> Self {
+        let (service_name, image) = match hook.target {
+            LifecycleHookTarget::Service(service_name) => (Some(service_name), None),
+            LifecycleHookTarget::Job(image) => (None, Some(image)),
+        };
+
+        Self {
+            service_name,
+            image,
+            command: hook.command,
+            timeout: hook.timeout.as_secs(),
+        }
+    }
+}
+
+/// The result of a lifecycle hook that is recorded in the deployment history.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecycleHookLog {
+    hook: String,
+    passed: bool,
+    logs: String,
+}
+
+impl LifecycleHookLog {
+    pub fn new(hook: &LifecycleHook, passed: bool, logs: String) -> Self {
+        Self {
+            hook: hook.to_string(),
+            passed,
+            logs,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+
+    pub fn logs(&self) -> &str {
+        &self.logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_parse_lifecycle_hooks() {
+        let hooks = serde_json::from_value::<LifecycleHooks>(serde_json::json!({
+            "postDeploy": [
+                { "serviceName": "backend", "command": ["./flush-caches.sh"] }
+            ],
+            "preDestroy": [
+                { "image": "curlimages/curl", "command": ["curl", "-X", "DELETE", "http://backend/license"], "timeout": 30 }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            hooks.post_deploy()[0].target(),
+            &LifecycleHookTarget::Service(String::from("backend"))
+        );
+        assert_eq!(
+            hooks.pre_destroy()[0].target(),
+            &LifecycleHookTarget::Job(Image::from_str("curlimages/curl").unwrap())
+        );
+        assert_eq!(hooks.pre_destroy()[0].timeout(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn should_serialize_lifecycle_hook_in_its_parsable_form() {
+        let hook = serde_json::from_value::<LifecycleHook>(serde_json::json!({
+            "image": "curlimages/curl",
+            "command": ["curl", "-X", "DELETE", "http://backend/license"],
+            "timeout": 30
+        }))
+        .unwrap();
+
+        let serialized = serde_json::to_value(&hook).unwrap();
+
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "image": "docker.io/curlimages/curl:latest",
+                "command": ["curl", "-X", "DELETE", "http://backend/license"],
+                "timeout": 30
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<LifecycleHook>(serialized).unwrap(),
+            hook
+        );
+    }
+
+    #[test]
+    fn should_not_parse_lifecycle_hook_with_service_and_image() {
+        let result = serde_json::from_value::<LifecycleHook>(serde_json::json!({
+            "serviceName": "backend",
+            "image": "curlimages/curl",
+            "command": ["true"]
+        }));
+
+        assert!(result.is_err());
+    }
+}
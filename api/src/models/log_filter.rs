@@ -0,0 +1,137 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use regex::Regex;
+use std::str::FromStr;
+
+/// The severity of a log line, ordered from the lowest to the highest one.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Detects the level of a log line by the first level keyword, e.g. `WARN` or `error`, that
+    /// the line contains.
+    fn of_line(line: &str) -> Option<LogLevel> {
+        lazy_static! {
+            static ref LEVEL_REGEX: Regex =
+                Regex::new(r"(?i)\b(trace|debug|info|warn|warning|error|fatal)\b").unwrap();
+        }
+
+        LEVEL_REGEX
+            .captures(line)
+            .and_then(|captures| LogLevel::from_str(&captures[1]).ok())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "trace" => Ok(LogLevel::Trace),
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" | "fatal" => Ok(LogLevel::Error),
+            level => Err(format!("Unknown log level {level}")),
+        }
+    }
+}
+
+/// Restricts the log lines of a service to the ones that contain a text, match a regular
+/// expression, and/or have at least the given level. The filter is applied while the logs are
+/// read from the infrastructure so that only matching lines count towards the limit.
+#[derive(Clone, Debug, Default)]
+pub struct LogFilter {
+    grep: Option<String>,
+    regex: Option<Regex>,
+    level: Option<LogLevel>,
+}
+
+impl LogFilter {
+    pub fn new(grep: Option<String>, regex: Option<Regex>, level: Option<LogLevel>) -> Self {
+        Self { grep, regex, level }
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        if let Some(grep) = &self.grep {
+            if !line.contains(grep.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(line) {
+                return false;
+            }
+        }
+
+        match self.level {
+            Some(level) => LogLevel::of_line(line)
+                .map(|line_level| line_level >= level)
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_all_lines_without_filter() {
+        assert!(LogFilter::default().matches("some line"));
+    }
+
+    #[test]
+    fn should_match_lines_with_text_and_regex() {
+        let filter = LogFilter::new(
+            Some(String::from("timeout")),
+            Some(Regex::new(r"after \d+ms").unwrap()),
+            None,
+        );
+
+        assert!(filter.matches("connection timeout after 500ms"));
+        assert!(!filter.matches("connection timeout"));
+        assert!(!filter.matches("request finished after 500ms"));
+    }
+
+    #[test]
+    fn should_match_lines_with_minimum_level() {
+        let filter = LogFilter::new(None, None, Some(LogLevel::Warn));
+
+        assert!(filter.matches("2023-09-01 ERROR connection refused"));
+        assert!(filter.matches(r#"{"level":"warning","msg":"slow query"}"#));
+        assert!(!filter.matches("2023-09-01 INFO started"));
+        assert!(!filter.matches("line without level"));
+    }
+}
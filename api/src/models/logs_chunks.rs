@@ -25,6 +25,23 @@
  */
 use chrono::{DateTime, FixedOffset, Utc};
 use std::convert::From;
+use std::fmt::Display;
+
+/// The stream of a container a log line has been written to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogStream {
+    StdOut,
+    StdErr,
+}
+
+impl Display for LogStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LogStream::StdOut => write!(f, "stdout"),
+            LogStream::StdErr => write!(f, "stderr"),
+        }
+    }
+}
 
 pub struct LogChunk {
     since: DateTime<FixedOffset>,
@@ -47,6 +64,45 @@ impl LogChunk {
     }
 }
 
+impl LogChunk {
+    /// Interleaves the log lines of multiple services by their timestamps and prefixes each line
+    /// with the service name and, if known, the stream, e.g. `[db] [stderr] `. At most `limit`
+    /// lines will be kept.
+    pub fn from_services(
+        logs: Vec<(
+            String,
+            Vec<(DateTime<FixedOffset>, Option<LogStream>, String)>,
+        )>,
+        limit: usize,
+    ) -> Self {
+        let mut lines = logs
+            .into_iter()
+            .flat_map(|(service_name, logs)| {
+                logs.into_iter().map(move |(timestamp, stream, line)| {
+                    let mut tagged_line = format!("[{service_name}] ");
+                    if let Some(stream) = stream {
+                        tagged_line.push_str(&format!("[{stream}] "));
+                    }
+                    tagged_line.push_str(line.trim_end_matches('\n'));
+                    tagged_line.push('\n');
+                    (timestamp, service_name.clone(), tagged_line)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // The sort is stable, thus, lines of a service with the same timestamp keep their order.
+        lines.sort_by(|(t1, s1, _), (t2, s2, _)| t1.cmp(t2).then_with(|| s1.cmp(s2)));
+        lines.truncate(limit);
+
+        LogChunk::from(
+            lines
+                .into_iter()
+                .map(|(timestamp, _, line)| (timestamp, line))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
 impl From<Vec<(DateTime<FixedOffset>, String)>> for LogChunk {
     fn from(logs: Vec<(DateTime<FixedOffset>, String)>) -> Self {
         let since = DateTime::<Utc>::MAX_UTC.fixed_offset();
@@ -0,0 +1,140 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// A mail that has been captured by the mail catcher of an app.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mail {
+    id: String,
+    from: String,
+    to: Vec<String>,
+    subject: Option<String>,
+    body: String,
+    received_at: DateTime<Utc>,
+}
+
+impl Mail {
+    /// Parses the response of MailHog's API `GET /api/v2/messages`.
+    pub fn from_mailhog_messages(json: &str) -> Result<Vec<Mail>, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct Messages {
+            items: Vec<Message>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Message {
+            #[serde(rename = "ID")]
+            id: String,
+            content: Content,
+            created: DateTime<Utc>,
+            raw: Raw,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Content {
+            #[serde(default)]
+            headers: BTreeMap<String, Vec<String>>,
+            body: String,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Raw {
+            from: String,
+            #[serde(default)]
+            to: Vec<String>,
+        }
+
+        let messages = serde_json::from_str::<Messages>(json)?;
+        Ok(messages
+            .items
+            .into_iter()
+            .map(|message| Mail {
+                id: message.id,
+                from: message.raw.from,
+                to: message.raw.to,
+                subject: message
+                    .content
+                    .headers
+                    .get("Subject")
+                    .and_then(|subject| subject.first())
+                    .cloned(),
+                body: message.content.body,
+                received_at: message.created,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_mailhog_messages() {
+        let mails = Mail::from_mailhog_messages(
+            r#"{
+                "total": 1,
+                "count": 1,
+                "start": 0,
+                "items": [{
+                    "ID": "abc@mailhog.example",
+                    "From": { "Mailbox": "noreply", "Domain": "example.com" },
+                    "To": [{ "Mailbox": "qa", "Domain": "example.com" }],
+                    "Content": {
+                        "Headers": { "Subject": ["Welcome"] },
+                        "Body": "Please confirm your signup.",
+                        "Size": 27
+                    },
+                    "Created": "2023-10-17T08:15:00Z",
+                    "Raw": {
+                        "From": "noreply@example.com",
+                        "To": ["qa@example.com"],
+                        "Data": "Subject: Welcome"
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&mails).unwrap(),
+            serde_json::json!([{
+                "id": "abc@mailhog.example",
+                "from": "noreply@example.com",
+                "to": ["qa@example.com"],
+                "subject": "Welcome",
+                "body": "Please confirm your signup.",
+                "receivedAt": "2023-10-17T08:15:00Z"
+            }])
+        );
+    }
+}
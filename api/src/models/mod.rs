@@ -24,23 +24,65 @@
  * =========================LICENSE_END==================================
  */
 
+pub use app_graph::AppGraph;
 pub use app_name::{AppName, AppNameError};
 pub use app_status_change_id::{AppStatusChangeId, AppStatusChangeIdError};
+pub use audit_entry::AuditEntry;
+pub use backup::{AppBackup, Backup};
+pub use cluster_capacity::{parse_quantity, CapacityShortage, ClusterCapacity, ResourceRequests};
+pub use cost_estimate::CostEstimate;
+pub use custom_resource::{json_path_to_pointer, CustomResource, CustomResourceReadiness};
+pub use deployment_diff::{DeploymentDiff, DeploymentHistoryEntry, DeploymentOutcome};
+pub use helm_release::HelmRelease;
 pub use image::Image;
-pub use logs_chunks::LogChunk;
+pub use lifecycle_hooks::{LifecycleHook, LifecycleHookLog, LifecycleHookTarget, LifecycleHooks};
+pub use log_filter::{LogFilter, LogLevel};
+pub use logs_chunks::{LogChunk, LogStream};
+pub use mail::Mail;
+pub use replication_rules::ReplicationRules;
 pub use request_info::RequestInfo;
+pub use sbom::{Sbom, SbomFormat};
+pub use self_check::{SelfCheck, SelfCheckStatus};
 pub use service::{ContainerType, ServiceBuilder, ServiceBuilderError};
-pub use service_config::{Environment, EnvironmentVariable, Router, ServiceConfig};
+pub(crate) use service_config::deserialize_extended_resources;
+pub use service_config::{
+    is_dns_label, AccessRule, Autoscale, DevMount, DnsConfig, Environment, EnvironmentVariable,
+    FileReload, FilesUpdate, HostAlias, ImagePullPolicy, LoadBalancer, Locale, OperatingSystem,
+    ResourceMetadata, Router, ScratchVolume, ScratchVolumeMedium, Seed, SeedEngine, SeedLog,
+    ServiceAccount, ServiceConfig, ServicePatch, StartupProbe, TemplateEngine, TimeZone,
+};
+pub use share_token::{ShareToken, StoredShareToken};
+pub use smoke_test::{HttpCheck, SmokeTest, SmokeTestOutcome};
+pub use source_build::SourceBuild;
+pub use vulnerabilities::{Severity, VulnerabilitySummary};
 pub use web_host_meta::WebHostMeta;
 
+mod app_graph;
 mod app_name;
 mod app_status_change_id;
+mod audit_entry;
+mod backup;
+mod cluster_capacity;
+mod cost_estimate;
+mod custom_resource;
+mod deployment_diff;
+mod helm_release;
 mod image;
+mod lifecycle_hooks;
+mod log_filter;
 mod logs_chunks;
+mod mail;
+mod replication_rules;
 pub mod request_info;
+mod sbom;
+mod self_check;
 #[cfg_attr(test, macro_use)]
 pub mod service;
 mod service_config;
+mod share_token;
+mod smoke_test;
+mod source_build;
 pub mod ticket_info;
+mod vulnerabilities;
 pub mod web_hook_info;
 pub mod web_host_meta;
@@ -0,0 +1,96 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::{ServiceConfig, ServicePatch};
+use std::collections::BTreeMap;
+
+/// Rules that control which services are replicated from the template app of a deployment and
+/// how the replicated services differ from their template.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationRules {
+    /// Names of services of the template app that won't be replicated.
+    #[serde(default)]
+    exclude: Vec<String>,
+    /// Patches, keyed by service name, that will be applied to the replicated services.
+    #[serde(default)]
+    overrides: BTreeMap<String, ServicePatch>,
+}
+
+impl ReplicationRules {
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty() && self.overrides.is_empty()
+    }
+
+    /// Returns the replicated config with the override of its service applied, or `None` if the
+    /// service is excluded from the replication.
+    pub fn apply_to(&self, mut config: ServiceConfig) -> Option<ServiceConfig> {
+        if self.exclude.contains(config.service_name()) {
+            return None;
+        }
+
+        if let Some(patch) = self.overrides.get(config.service_name()) {
+            patch.clone().apply_to(&mut config);
+        }
+
+        Some(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sc;
+
+    #[test]
+    fn should_exclude_and_override_replicated_services() {
+        let rules = serde_json::from_value::<ReplicationRules>(serde_json::json!({
+            "exclude": ["db"],
+            "overrides": {
+                "api": {
+                    "image": "api:template",
+                    "env": { "LOG_LEVEL": "debug" }
+                }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(rules.apply_to(sc!("db", "postgres")), None);
+        assert_eq!(
+            rules.apply_to(sc!("cache", "redis")),
+            Some(sc!("cache", "redis"))
+        );
+
+        let api = rules.apply_to(sc!("api", "api:latest")).unwrap();
+        assert_eq!(api.image().to_string(), "docker.io/library/api:template");
+        assert_eq!(
+            api.env()
+                .and_then(|env| env.get(0))
+                .map(|var| var.key().clone()),
+            Some(String::from("LOG_LEVEL"))
+        );
+    }
+}
@@ -0,0 +1,97 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use chrono::{DateTime, Utc};
+use std::fmt::{Display, Formatter};
+
+/// A software bill of materials of the image of a deployed service.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sbom {
+    image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image_digest: Option<String>,
+    format: SbomFormat,
+    generated_at: DateTime<Utc>,
+    document: serde_json::Value,
+}
+
+/// The formats of the SBOM documents as they are named by [Syft](https://github.com/anchore/syft).
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SbomFormat {
+    #[default]
+    #[serde(rename = "cyclonedx-json")]
+    CycloneDxJson,
+    #[serde(rename = "spdx-json")]
+    SpdxJson,
+    #[serde(rename = "syft-json")]
+    SyftJson,
+}
+
+impl Sbom {
+    pub fn new(
+        image: String,
+        image_digest: Option<String>,
+        format: SbomFormat,
+        document: serde_json::Value,
+    ) -> Self {
+        Self {
+            image,
+            image_digest,
+            format,
+            generated_at: Utc::now(),
+            document,
+        }
+    }
+
+    pub fn format(&self) -> SbomFormat {
+        self.format
+    }
+
+    pub fn document(&self) -> &serde_json::Value {
+        &self.document
+    }
+}
+
+impl SbomFormat {
+    /// The media type of the documents, e.g. `application/vnd.cyclonedx+json`.
+    pub fn media_type(&self) -> (&'static str, &'static str) {
+        match self {
+            SbomFormat::CycloneDxJson => ("application", "vnd.cyclonedx+json"),
+            SbomFormat::SpdxJson => ("application", "spdx+json"),
+            SbomFormat::SyftJson => ("application", "json"),
+        }
+    }
+}
+
+impl Display for SbomFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SbomFormat::CycloneDxJson => write!(f, "cyclonedx-json"),
+            SbomFormat::SpdxJson => write!(f, "spdx-json"),
+            SbomFormat::SyftJson => write!(f, "syft-json"),
+        }
+    }
+}
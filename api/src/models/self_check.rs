@@ -0,0 +1,84 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+/// The result of checking a single prerequisite that PREvant needs at runtime, for example,
+/// whether it is able to connect to the cluster.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfCheck {
+    name: String,
+    status: SelfCheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum SelfCheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl SelfCheck {
+    pub fn passed(name: impl Into<String>, details: impl Into<String>) -> Self {
+        Self::new(name, SelfCheckStatus::Passed, details)
+    }
+
+    pub fn failed(name: impl Into<String>, details: impl Into<String>) -> Self {
+        Self::new(name, SelfCheckStatus::Failed, details)
+    }
+
+    pub fn skipped(name: impl Into<String>, details: impl Into<String>) -> Self {
+        Self::new(name, SelfCheckStatus::Skipped, details)
+    }
+
+    fn new(name: impl Into<String>, status: SelfCheckStatus, details: impl Into<String>) -> Self {
+        let details = details.into();
+        Self {
+            name: name.into(),
+            status,
+            details: if details.is_empty() {
+                None
+            } else {
+                Some(details)
+            },
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn status(&self) -> SelfCheckStatus {
+        self.status
+    }
+
+    /// Prefixes the name of the check, e.g. with the cluster that has been checked.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.name = format!("{prefix}: {}", self.name);
+        self
+    }
+}
@@ -24,10 +24,13 @@
  * =========================LICENSE_END==================================
  */
 
-use crate::models::{web_host_meta::WebHostMeta, ServiceConfig};
+use crate::models::{
+    web_host_meta::WebHostMeta, CostEstimate, ServiceConfig, VulnerabilitySummary,
+};
 use chrono::{DateTime, Utc};
 use serde::ser::{Serialize, Serializer};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::net::IpAddr;
 use std::str::FromStr;
@@ -43,6 +46,20 @@ pub struct Service {
     web_host_meta: Option<WebHostMeta>,
     state: State,
     config: ServiceConfig,
+    /// The name of the cluster the service runs on if PREvant manages multiple clusters
+    cluster: Option<String>,
+    /// Metadata that has been attached to the app of the service, e.g. the originating branch
+    app_metadata: BTreeMap<String, String>,
+    /// The digest of the image the service has been deployed with, if known
+    image_digest: Option<String>,
+    /// The vulnerabilities of the image if it has been scanned
+    vulnerabilities: Option<VulnerabilitySummary>,
+    /// The estimated costs of the service if prices for the resources have been configured
+    cost_estimate: Option<CostEstimate>,
+    /// The user that owns the app of the service
+    owner: Option<String>,
+    /// Whether the container mounts directories of the host in dev mode
+    dev_mode: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -67,6 +84,20 @@ pub struct State {
     status: ServiceStatus,
     #[serde(skip)]
     started_at: DateTime<Utc>,
+    /// How often the containers of the service have been restarted, e.g. due to crashes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart_count: Option<u32>,
+    /// The exit code of the last terminated container of the service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_exit_code: Option<i64>,
+    /// The reason why the last container terminated or why it is waiting, e.g. `OOMKilled` or
+    /// `CrashLoopBackOff`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_state: Option<String>,
+    /// Why the pods of the service are still pending, e.g. the events of the scheduler about
+    /// insufficient resources or unbound persistent volume claims
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pending_reasons: Vec<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -115,6 +146,50 @@ impl Service {
     pub fn status(&self) -> &ServiceStatus {
         &self.state.status
     }
+
+    pub fn restart_count(&self) -> Option<u32> {
+        self.state.restart_count
+    }
+
+    pub fn last_exit_code(&self) -> Option<i64> {
+        self.state.last_exit_code
+    }
+
+    pub fn last_state(&self) -> Option<&String> {
+        self.state.last_state.as_ref()
+    }
+
+    pub fn pending_reasons(&self) -> &[String] {
+        &self.state.pending_reasons
+    }
+
+    pub fn dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    pub fn cluster(&self) -> Option<&String> {
+        self.cluster.as_ref()
+    }
+
+    pub fn app_metadata(&self) -> &BTreeMap<String, String> {
+        &self.app_metadata
+    }
+
+    pub fn image_digest(&self) -> Option<&String> {
+        self.image_digest.as_ref()
+    }
+
+    pub fn vulnerabilities(&self) -> Option<&VulnerabilitySummary> {
+        self.vulnerabilities.as_ref()
+    }
+
+    pub fn cost_estimate(&self) -> Option<&CostEstimate> {
+        self.cost_estimate.as_ref()
+    }
+
+    pub fn owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
 }
 
 impl Serialize for Service {
@@ -131,10 +206,24 @@ impl Serialize for Service {
             #[serde(rename = "type")]
             service_type: String,
             #[serde(skip_serializing_if = "Option::is_none")]
+            replicated_from: Option<&'a String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
             version: Option<Version>,
             #[serde(skip_serializing_if = "Option::is_none")]
             open_api_url: Option<Url>,
             state: &'a State,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cluster: Option<&'a String>,
+            #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+            app_metadata: &'a BTreeMap<String, String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            vulnerabilities: Option<&'a VulnerabilitySummary>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cost_estimate: Option<&'a CostEstimate>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            owner: Option<&'a String>,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            dev_mode: bool,
         }
 
         #[derive(Serialize)]
@@ -165,9 +254,16 @@ impl Serialize for Service {
                 _ => None,
             },
             service_type: self.container_type().to_string(),
+            replicated_from: self.config.replicated_from(),
             version,
             open_api_url,
             state: &self.state,
+            cluster: self.cluster.as_ref(),
+            app_metadata: &self.app_metadata,
+            vulnerabilities: self.vulnerabilities.as_ref(),
+            cost_estimate: self.cost_estimate.as_ref(),
+            owner: self.owner.as_ref(),
+            dev_mode: self.dev_mode,
         };
 
         s.serialize(serializer)
@@ -181,9 +277,20 @@ pub struct ServiceBuilder {
     config: Option<ServiceConfig>,
     status: Option<ServiceStatus>,
     started_at: Option<DateTime<Utc>>,
+    restart_count: Option<u32>,
+    last_exit_code: Option<i64>,
+    last_state: Option<String>,
+    pending_reasons: Vec<String>,
     base_url: Option<Url>,
     web_host_meta: Option<WebHostMeta>,
     endpoint: Option<ServiceEndpoint>,
+    cluster: Option<String>,
+    app_metadata: BTreeMap<String, String>,
+    image_digest: Option<String>,
+    vulnerabilities: Option<VulnerabilitySummary>,
+    cost_estimate: Option<CostEstimate>,
+    owner: Option<String>,
+    dev_mode: bool,
 }
 
 impl ServiceBuilder {
@@ -193,10 +300,21 @@ impl ServiceBuilder {
             app_name: None,
             status: None,
             started_at: None,
+            restart_count: None,
+            last_exit_code: None,
+            last_state: None,
+            pending_reasons: Vec::new(),
             base_url: None,
             web_host_meta: None,
             endpoint: None,
             config: None,
+            cluster: None,
+            app_metadata: BTreeMap::new(),
+            image_digest: None,
+            vulnerabilities: None,
+            cost_estimate: None,
+            owner: None,
+            dev_mode: false,
         }
     }
 
@@ -215,9 +333,20 @@ impl ServiceBuilder {
             base_url: self.base_url,
             endpoint: self.endpoint,
             web_host_meta: self.web_host_meta,
+            cluster: self.cluster,
+            app_metadata: self.app_metadata,
+            image_digest: self.image_digest,
+            vulnerabilities: self.vulnerabilities,
+            cost_estimate: self.cost_estimate,
+            owner: self.owner,
+            dev_mode: self.dev_mode,
             state: State {
                 started_at,
                 status: self.status.unwrap_or(ServiceStatus::Running),
+                restart_count: self.restart_count,
+                last_exit_code: self.last_exit_code,
+                last_state: self.last_state,
+                pending_reasons: self.pending_reasons,
             },
         })
     }
@@ -250,6 +379,26 @@ impl ServiceBuilder {
         self
     }
 
+    pub fn restart_count(mut self, restart_count: u32) -> Self {
+        self.restart_count = Some(restart_count);
+        self
+    }
+
+    pub fn last_exit_code(mut self, last_exit_code: i64) -> Self {
+        self.last_exit_code = Some(last_exit_code);
+        self
+    }
+
+    pub fn last_state(mut self, last_state: String) -> Self {
+        self.last_state = Some(last_state);
+        self
+    }
+
+    pub fn pending_reasons(mut self, pending_reasons: Vec<String>) -> Self {
+        self.pending_reasons = pending_reasons;
+        self
+    }
+
     pub fn base_url(mut self, base_url: Url) -> Self {
         self.base_url = Some(base_url);
         self
@@ -265,6 +414,41 @@ impl ServiceBuilder {
         self
     }
 
+    pub fn cluster(mut self, cluster: String) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    pub fn app_metadata(mut self, app_metadata: BTreeMap<String, String>) -> Self {
+        self.app_metadata = app_metadata;
+        self
+    }
+
+    pub fn image_digest(mut self, image_digest: String) -> Self {
+        self.image_digest = Some(image_digest);
+        self
+    }
+
+    pub fn vulnerabilities(mut self, vulnerabilities: VulnerabilitySummary) -> Self {
+        self.vulnerabilities = Some(vulnerabilities);
+        self
+    }
+
+    pub fn cost_estimate(mut self, cost_estimate: CostEstimate) -> Self {
+        self.cost_estimate = Some(cost_estimate);
+        self
+    }
+
+    pub fn owner(mut self, owner: String) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn dev_mode(mut self, dev_mode: bool) -> Self {
+        self.dev_mode = dev_mode;
+        self
+    }
+
     pub fn endpoint(mut self, addr: IpAddr, port: u16) -> Self {
         self.endpoint = Some(ServiceEndpoint {
             internal_addr: addr,
@@ -292,9 +476,20 @@ impl From<Service> for ServiceBuilder {
             config: Some(service.config),
             status: Some(service.state.status),
             started_at: Some(service.state.started_at),
+            restart_count: service.state.restart_count,
+            last_exit_code: service.state.last_exit_code,
+            last_state: service.state.last_state,
+            pending_reasons: service.state.pending_reasons,
             base_url: service.base_url,
             web_host_meta: service.web_host_meta,
             endpoint: service.endpoint,
+            cluster: service.cluster,
+            app_metadata: service.app_metadata,
+            image_digest: service.image_digest,
+            vulnerabilities: service.vulnerabilities,
+            cost_estimate: service.cost_estimate,
+            owner: service.owner,
+            dev_mode: service.dev_mode,
         }
     }
 }
@@ -0,0 +1,74 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use serde::Deserialize;
+
+/// Scales the instances of a service between `min` and `max` depending on the average CPU
+/// utilization (in percent of the requested CPU).
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Autoscale {
+    #[serde(default = "Autoscale::default_min")]
+    min: u32,
+    max: u32,
+    #[serde(default = "Autoscale::default_cpu")]
+    cpu: u32,
+}
+
+impl Autoscale {
+    fn default_min() -> u32 {
+        1
+    }
+
+    fn default_cpu() -> u32 {
+        80
+    }
+
+    pub fn min(&self) -> u32 {
+        self.min
+    }
+
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    /// The target of the average CPU utilization in percent.
+    pub fn cpu(&self) -> u32 {
+        self.cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_autoscale_with_defaults() {
+        let autoscale = toml::de::from_str::<Autoscale>("max = 5").unwrap();
+
+        assert_eq!(autoscale.min(), 1);
+        assert_eq!(autoscale.max(), 5);
+        assert_eq!(autoscale.cpu(), 80);
+    }
+}
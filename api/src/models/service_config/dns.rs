@@ -0,0 +1,118 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use serde::Deserialize;
+use std::net::IpAddr;
+
+/// Resolves the `hostnames` to a fixed `ip` inside the container of a service, e.g. internal
+/// hostnames that the DNS of the cluster does not know.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct HostAlias {
+    ip: IpAddr,
+    hostnames: Vec<String>,
+}
+
+impl HostAlias {
+    pub fn ip(&self) -> &IpAddr {
+        &self.ip
+    }
+
+    pub fn hostnames(&self) -> &[String] {
+        &self.hostnames
+    }
+}
+
+/// Additional name servers and search domains of the container of a service.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsConfig {
+    #[serde(default)]
+    nameservers: Vec<IpAddr>,
+    #[serde(default)]
+    searches: Vec<String>,
+}
+
+impl DnsConfig {
+    pub fn nameservers(&self) -> &[IpAddr] {
+        &self.nameservers
+    }
+
+    pub fn searches(&self) -> &[String] {
+        &self.searches
+    }
+}
+
+/// Returns `true` if the name is a DNS label as defined by RFC 1123, i.e. at most 63 lowercase
+/// alphanumeric characters or '-' that start and end with an alphanumeric character. Such names
+/// are safe to use as hostnames, as Kubernetes object names, and as path segments.
+pub fn is_dns_label(name: &str) -> bool {
+    let is_alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+
+    !name.is_empty()
+        && name.len() <= 63
+        && name.starts_with(is_alphanumeric)
+        && name.ends_with(is_alphanumeric)
+        && name.chars().all(|c| is_alphanumeric(c) || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_parse_host_alias() {
+        let alias = serde_json::from_value::<HostAlias>(serde_json::json!({
+            "ip": "10.0.0.15",
+            "hostnames": ["ldap.corp.example.com"]
+        }))
+        .unwrap();
+
+        assert_eq!(alias.ip(), &IpAddr::from_str("10.0.0.15").unwrap());
+        assert_eq!(alias.hostnames(), &[String::from("ldap.corp.example.com")]);
+    }
+
+    #[test]
+    fn should_not_parse_host_alias_with_invalid_ip() {
+        let alias = serde_json::from_value::<HostAlias>(serde_json::json!({
+            "ip": "ldap",
+            "hostnames": ["ldap.corp.example.com"]
+        }));
+
+        assert!(alias.is_err());
+    }
+
+    #[test]
+    fn should_accept_dns_labels() {
+        assert!(is_dns_label("web"));
+        assert!(is_dns_label("web-1"));
+        assert!(!is_dns_label(""));
+        assert!(!is_dns_label("-web"));
+        assert!(!is_dns_label("Web"));
+        assert!(!is_dns_label("../../x"));
+        assert!(!is_dns_label("/etc/passwd"));
+        assert!(!is_dns_label(&"a".repeat(64)));
+    }
+}
@@ -66,6 +66,18 @@ impl Environment {
     pub(super) fn push(&mut self, variable: EnvironmentVariable) {
         self.values.push(variable);
     }
+
+    pub(super) fn remove(&mut self, keys: &[String]) {
+        self.values.retain(|env| !keys.contains(&env.key));
+    }
+
+    /// Adds the variable or replaces the variable with the same key.
+    pub(super) fn insert(&mut self, variable: EnvironmentVariable) {
+        match self.values.iter_mut().find(|env| env.key == variable.key) {
+            Some(env) => *env = variable,
+            None => self.values.push(variable),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Environment {
@@ -0,0 +1,146 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use serde::Deserialize;
+
+/// Options of the load balancer that routes the requests to the instances of a service. See
+/// [Traefik's services](https://doc.traefik.io/traefik/routing/services/) for more details.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadBalancer {
+    #[serde(default)]
+    sticky: Option<Sticky>,
+    #[serde(default)]
+    response_forwarding: Option<ResponseForwarding>,
+    #[serde(default)]
+    servers_transport: Option<String>,
+}
+
+impl LoadBalancer {
+    pub fn sticky(&self) -> Option<&Sticky> {
+        self.sticky.as_ref()
+    }
+
+    pub fn response_forwarding(&self) -> Option<&ResponseForwarding> {
+        self.response_forwarding.as_ref()
+    }
+
+    /// The name of an existing `ServersTransport` that configures the connection to the
+    /// instances of the service.
+    pub fn servers_transport(&self) -> Option<&String> {
+        self.servers_transport.as_ref()
+    }
+}
+
+/// Session affinity through a cookie that Traefik sets on the first response.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Sticky {
+    cookie: StickyCookie,
+}
+
+impl Sticky {
+    pub fn cookie(&self) -> &StickyCookie {
+        &self.cookie
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StickyCookie {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default)]
+    http_only: bool,
+    #[serde(default)]
+    same_site: Option<String>,
+}
+
+impl StickyCookie {
+    pub fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+
+    pub fn same_site(&self) -> Option<&String> {
+        self.same_site.as_ref()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseForwarding {
+    flush_interval: String,
+}
+
+impl ResponseForwarding {
+    /// The interval in which the response is flushed to the client, e.g. `100ms` or `-1` for
+    /// immediate flushes.
+    pub fn flush_interval(&self) -> &String {
+        &self.flush_interval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_load_balancer() {
+        let load_balancer = serde_json::from_value::<LoadBalancer>(serde_json::json!({
+            "sticky": {
+                "cookie": {
+                    "name": "session",
+                    "httpOnly": true
+                }
+            },
+            "responseForwarding": {
+                "flushInterval": "100ms"
+            }
+        }))
+        .unwrap();
+
+        let cookie = load_balancer.sticky().unwrap().cookie();
+        assert_eq!(cookie.name(), Some(&String::from("session")));
+        assert!(cookie.http_only());
+        assert!(!cookie.secure());
+        assert_eq!(
+            load_balancer
+                .response_forwarding()
+                .unwrap()
+                .flush_interval(),
+            "100ms"
+        );
+        assert_eq!(load_balancer.servers_transport(), None);
+    }
+}
@@ -0,0 +1,132 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer};
+
+/// The name of a time zone of the IANA time zone database, e.g. `Europe/Berlin`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimeZone(String);
+
+/// A POSIX locale, e.g. `de_DE.UTF-8`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Locale(String);
+
+impl std::str::FromStr for TimeZone {
+    type Err = String;
+
+    fn from_str(time_zone: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            // Excludes `..` to keep the zone info path inside of the time zone database
+            static ref RE: Regex = Regex::new(r"^[A-Za-z][A-Za-z0-9_+\-]*(/[A-Za-z0-9_+\-]+)*$").unwrap();
+        }
+
+        if RE.is_match(time_zone) {
+            Ok(TimeZone(time_zone.to_string()))
+        } else {
+            Err(format!(
+                "{time_zone} is not a valid time zone, e.g. Europe/Berlin"
+            ))
+        }
+    }
+}
+
+impl std::str::FromStr for Locale {
+    type Err = String;
+
+    fn from_str(locale: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"^[A-Za-z]+(_[A-Za-z]+)?(\.[A-Za-z0-9\-]+)?(@[A-Za-z0-9]+)?$").unwrap();
+        }
+
+        if RE.is_match(locale) {
+            Ok(Locale(locale.to_string()))
+        } else {
+            Err(format!("{locale} is not a valid locale, e.g. de_DE.UTF-8"))
+        }
+    }
+}
+
+impl std::fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeZone {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Locale {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_parse_time_zone() {
+        let time_zone = TimeZone::from_str("America/Argentina/Buenos_Aires").unwrap();
+
+        assert_eq!(time_zone.to_string(), "America/Argentina/Buenos_Aires");
+    }
+
+    #[test]
+    fn should_not_parse_time_zone_outside_of_zoneinfo() {
+        assert!(TimeZone::from_str("../../etc/passwd").is_err());
+        assert!(TimeZone::from_str("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn should_parse_locales() {
+        assert!(Locale::from_str("de_DE.UTF-8").is_ok());
+        assert!(Locale::from_str("C").is_ok());
+        assert!(Locale::from_str("ca_ES@valencia").is_ok());
+        assert!(Locale::from_str("de DE").is_err());
+    }
+}
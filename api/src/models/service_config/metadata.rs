@@ -0,0 +1,105 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Arbitrary labels and annotations that will be attached to all resources that are created for a
+/// service, e.g. to stamp them with cost centers or owners.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct ResourceMetadata {
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    annotations: BTreeMap<String, String>,
+}
+
+impl ResourceMetadata {
+    pub fn new(labels: BTreeMap<String, String>, annotations: BTreeMap<String, String>) -> Self {
+        ResourceMetadata {
+            labels,
+            annotations,
+        }
+    }
+
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    pub fn annotations(&self) -> &BTreeMap<String, String> {
+        &self.annotations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.annotations.is_empty()
+    }
+
+    /// Copies labels and annotations from other into self.
+    /// If a key is defined in self and other, self has precedence.
+    pub fn merge_with(&mut self, other: &Self) {
+        for (key, value) in &other.labels {
+            self.labels
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for (key, value) in &other.annotations {
+            self.annotations
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_merge_metadata_with_precedence_of_self() {
+        let mut metadata = ResourceMetadata::new(
+            BTreeMap::from([(String::from("owner"), String::from("team-a"))]),
+            BTreeMap::new(),
+        );
+
+        metadata.merge_with(&ResourceMetadata::new(
+            BTreeMap::from([
+                (String::from("owner"), String::from("team-b")),
+                (String::from("cost-center"), String::from("4711")),
+            ]),
+            BTreeMap::from([(String::from("description"), String::from("demo"))]),
+        ));
+
+        assert_eq!(
+            metadata,
+            ResourceMetadata::new(
+                BTreeMap::from([
+                    (String::from("cost-center"), String::from("4711")),
+                    (String::from("owner"), String::from("team-a")),
+                ]),
+                BTreeMap::from([(String::from("description"), String::from("demo"))]),
+            )
+        );
+    }
+}
@@ -25,34 +25,137 @@
  */
 use crate::models::service::ContainerType;
 use crate::models::Image;
+pub use autoscale::Autoscale;
+pub use dev_mount::DevMount;
+pub use dns::{is_dns_label, DnsConfig, HostAlias};
 pub use environment::{Environment, EnvironmentVariable};
+pub use image_pull_policy::ImagePullPolicy;
+pub use load_balancer::LoadBalancer;
+pub use localization::{Locale, TimeZone};
+pub use metadata::ResourceMetadata;
+pub use operating_system::OperatingSystem;
+pub use patch::{FileReload, FilesUpdate, ServicePatch};
+pub use scratch_volume::{ScratchVolume, ScratchVolumeMedium};
 use secstr::SecUtf8;
+pub use seed::{Seed, SeedEngine, SeedLog};
 use serde::Deserialize;
 use serde_value::Value;
+pub use service_account::{AccessRule, ServiceAccount};
+pub use startup_probe::StartupProbe;
 use std::collections::BTreeMap;
 use std::hash::Hash;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+pub use templating::TemplateEngine;
 
+mod autoscale;
+mod dev_mount;
+mod dns;
 mod environment;
+mod image_pull_policy;
+mod load_balancer;
+mod localization;
+mod metadata;
+mod operating_system;
+mod patch;
+mod scratch_volume;
+mod seed;
+mod service_account;
+mod startup_probe;
 mod templating;
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceConfig {
     service_name: String,
+    #[serde(default = "ServiceConfig::image_of_template")]
     image: Image,
+    #[serde(default)]
+    extends: Option<String>,
     env: Option<Environment>,
     #[serde(alias = "volumes", alias = "files", default)]
     files: Option<BTreeMap<PathBuf, SecUtf8>>,
+    #[serde(alias = "unset_env", default)]
+    unset_env: Vec<String>,
+    #[serde(alias = "remove_files", default)]
+    remove_files: Vec<PathBuf>,
+    #[serde(alias = "scratch_volumes", default)]
+    scratch_volumes: Vec<ScratchVolume>,
+    #[serde(alias = "read_only_root_filesystem", default)]
+    read_only_root_filesystem: bool,
+    #[serde(alias = "writable_paths", default)]
+    writable_paths: Vec<PathBuf>,
+    #[serde(default)]
+    metadata: Option<ResourceMetadata>,
+    #[serde(default)]
+    service_account: Option<ServiceAccount>,
+    #[serde(default)]
+    command: Option<Vec<String>>,
+    #[serde(default)]
+    args: Option<Vec<String>>,
+    #[serde(default)]
+    working_dir: Option<String>,
+    #[serde(default)]
+    replicas: Option<u32>,
+    #[serde(default)]
+    autoscale: Option<Autoscale>,
+    #[serde(default, deserialize_with = "deserialize_extended_resources")]
+    extended_resources: BTreeMap<String, String>,
+    #[serde(default)]
+    image_pull_policy: Option<ImagePullPolicy>,
+    #[serde(default)]
+    load_balancer: Option<LoadBalancer>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(alias = "depends_on", default)]
+    depends_on: Vec<String>,
+    #[serde(alias = "host_aliases", default)]
+    host_aliases: Vec<HostAlias>,
+    #[serde(alias = "dns_config", default)]
+    dns_config: Option<DnsConfig>,
+    #[serde(alias = "time_zone", default)]
+    timezone: Option<TimeZone>,
+    #[serde(default)]
+    locale: Option<Locale>,
+    #[serde(alias = "dev_mounts", default)]
+    dev_mounts: Vec<DevMount>,
+    #[serde(alias = "startup_probe", default)]
+    startup_probe: Option<StartupProbe>,
+    #[serde(default)]
+    seed: Option<Seed>,
+    /// The seconds the service may take to shut down gracefully before it is killed.
+    #[serde(alias = "termination_grace_period", default)]
+    termination_grace_period: Option<u64>,
+    /// Forces the platform, e.g. `linux/arm64`, the service is scheduled on instead of the
+    /// platforms the image supports.
+    #[serde(default)]
+    platform: Option<String>,
+    #[serde(default)]
+    os: Option<OperatingSystem>,
+    /// The template engine can only be configured by companions of the server configuration
+    /// because Tera gives templates access to more than the template parameters.
+    #[serde(skip)]
+    template_engine: TemplateEngine,
     #[serde(skip)]
     labels: Option<BTreeMap<String, String>>,
     #[serde(skip, default = "ContainerType::default")]
     container_type: ContainerType,
     #[serde(skip)]
+    replicated_from: Option<String>,
+    #[serde(skip)]
     port: u16,
+    /// The port has been defined by the request and must not be replaced by the port that the
+    /// image exposes.
     #[serde(skip)]
-    router: Option<Router>,
+    fixed_port: bool,
+    /// Ports that the other services of the app can connect to but that are not routed, e.g. the
+    /// SMTP port of the mail catcher.
     #[serde(skip)]
+    additional_ports: Vec<u16>,
+    #[serde(skip)]
+    router: Option<Router>,
+    #[serde(default)]
     middlewares: Option<BTreeMap<String, Value>>,
 }
 
@@ -61,11 +164,43 @@ impl ServiceConfig {
         ServiceConfig {
             service_name,
             image,
+            extends: None,
             env: None,
             files: None,
+            unset_env: Vec::new(),
+            remove_files: Vec::new(),
+            scratch_volumes: Vec::new(),
+            read_only_root_filesystem: false,
+            writable_paths: Vec::new(),
+            metadata: None,
+            service_account: None,
+            command: None,
+            args: None,
+            working_dir: None,
+            replicas: None,
+            autoscale: None,
+            extended_resources: BTreeMap::new(),
+            image_pull_policy: None,
+            load_balancer: None,
+            aliases: Vec::new(),
+            depends_on: Vec::new(),
+            host_aliases: Vec::new(),
+            dns_config: None,
+            timezone: None,
+            locale: None,
+            dev_mounts: Vec::new(),
+            startup_probe: None,
+            seed: None,
+            termination_grace_period: None,
+            platform: None,
+            os: None,
+            template_engine: TemplateEngine::default(),
             labels: None,
             container_type: ContainerType::Instance,
+            replicated_from: None,
             port: 80,
+            fixed_port: false,
+            additional_ports: Vec::new(),
             router: None,
             middlewares: None,
         }
@@ -79,11 +214,43 @@ impl ServiceConfig {
         &self.container_type
     }
 
+    /// The name of the app this service has been replicated from, if it is a replica.
+    pub fn replicated_from(&self) -> Option<&String> {
+        self.replicated_from.as_ref()
+    }
+
+    pub fn set_replicated_from(&mut self, replicated_from: Option<String>) {
+        self.replicated_from = replicated_from;
+    }
+
     /// Returns a fully qualifying docker image
     pub fn image(&self) -> &Image {
         &self.image
     }
 
+    pub fn set_image(&mut self, image: Image) {
+        self.image = image;
+    }
+
+    /// Services that extend a template may omit their image, which will be taken from the
+    /// template. Until the template has been applied, such services use this placeholder.
+    pub fn image_of_template() -> Image {
+        Image::from_str("prevant/image-of-template").unwrap()
+    }
+
+    pub fn has_image_of_template(&self) -> bool {
+        self.image == Self::image_of_template()
+    }
+
+    /// The name of the configured service template this service is based on.
+    pub fn extends(&self) -> Option<&String> {
+        self.extends.as_ref()
+    }
+
+    pub fn set_extends(&mut self, extends: Option<String>) {
+        self.extends = extends;
+    }
+
     pub fn set_service_name(&mut self, service_name: &String) {
         self.service_name = service_name.clone()
     }
@@ -116,6 +283,16 @@ impl ServiceConfig {
         }
     }
 
+    /// Adds the environment variable unless the service already defines a variable with the same
+    /// key.
+    pub fn add_env(&mut self, variable: EnvironmentVariable) {
+        match &mut self.env {
+            Some(env) if env.variable(variable.key()).is_some() => {}
+            Some(env) => env.push(variable),
+            None => self.env = Some(Environment::new(vec![variable])),
+        }
+    }
+
     pub fn add_file(&mut self, path: PathBuf, data: SecUtf8) {
         if let Some(ref mut files) = self.files {
             files.insert(path, data);
@@ -137,6 +314,261 @@ impl ServiceConfig {
         }
     }
 
+    /// Labels and annotations that should be attached to all resources of this service.
+    pub fn metadata(&self) -> Option<&ResourceMetadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn set_metadata(&mut self, metadata: Option<ResourceMetadata>) {
+        self.metadata = metadata;
+    }
+
+    /// Adds the labels and annotations of `metadata` unless they are already defined for this
+    /// service.
+    pub fn add_metadata(&mut self, metadata: &ResourceMetadata) {
+        match &mut self.metadata {
+            Some(own_metadata) => own_metadata.merge_with(metadata),
+            None => self.metadata = Some(metadata.clone()),
+        }
+    }
+
+    /// The service account, and its in-namespace permissions, that the service will run with.
+    pub fn service_account(&self) -> Option<&ServiceAccount> {
+        self.service_account.as_ref()
+    }
+
+    pub fn set_service_account(&mut self, service_account: Option<ServiceAccount>) {
+        self.service_account = service_account;
+    }
+
+    /// Overrides the entrypoint of the image.
+    pub fn command(&self) -> Option<&Vec<String>> {
+        self.command.as_ref()
+    }
+
+    pub fn set_command(&mut self, command: Option<Vec<String>>) {
+        self.command = command;
+    }
+
+    /// Overrides the arguments (`CMD`) of the image.
+    pub fn args(&self) -> Option<&Vec<String>> {
+        self.args.as_ref()
+    }
+
+    pub fn set_args(&mut self, args: Option<Vec<String>>) {
+        self.args = args;
+    }
+
+    /// Overrides the working directory of the image.
+    pub fn working_dir(&self) -> Option<&String> {
+        self.working_dir.as_ref()
+    }
+
+    pub fn set_working_dir(&mut self, working_dir: Option<String>) {
+        self.working_dir = working_dir;
+    }
+
+    /// The number of instances of the service that should run behind the same route.
+    pub fn replicas(&self) -> Option<u32> {
+        self.replicas
+    }
+
+    pub fn set_replicas(&mut self, replicas: Option<u32>) {
+        self.replicas = replicas;
+    }
+
+    /// Scales the service automatically instead of running a fixed number of replicas.
+    pub fn autoscale(&self) -> Option<&Autoscale> {
+        self.autoscale.as_ref()
+    }
+
+    pub fn set_autoscale(&mut self, autoscale: Option<Autoscale>) {
+        self.autoscale = autoscale;
+    }
+
+    /// Extended resources, e.g. `nvidia.com/gpu`, and their quantities that the service's
+    /// container requires.
+    pub fn extended_resources(&self) -> &BTreeMap<String, String> {
+        &self.extended_resources
+    }
+
+    pub fn set_extended_resources(&mut self, extended_resources: BTreeMap<String, String>) {
+        self.extended_resources = extended_resources;
+    }
+
+    /// Adds the extended resources unless they are already defined for this service.
+    pub fn add_extended_resources(&mut self, extended_resources: &BTreeMap<String, String>) {
+        for (resource, quantity) in extended_resources {
+            self.extended_resources
+                .entry(resource.clone())
+                .or_insert_with(|| quantity.clone());
+        }
+    }
+
+    /// The pull policy of the service's image which overrides the pull policy of the container
+    /// configuration.
+    pub fn image_pull_policy(&self) -> Option<ImagePullPolicy> {
+        self.image_pull_policy
+    }
+
+    pub fn set_image_pull_policy(&mut self, image_pull_policy: Option<ImagePullPolicy>) {
+        self.image_pull_policy = image_pull_policy;
+    }
+
+    /// Additional hostnames under which the other services of the app reach this service.
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    pub fn set_aliases(&mut self, aliases: Vec<String>) {
+        self.aliases = aliases;
+    }
+
+    /// The services of the app that this service needs. PREvant does not order the deployment by
+    /// them but shows them in the graph of the app.
+    pub fn depends_on(&self) -> &[String] {
+        &self.depends_on
+    }
+
+    pub fn set_depends_on(&mut self, depends_on: Vec<String>) {
+        self.depends_on = depends_on;
+    }
+
+    /// Fixed IP addresses of hostnames that the service resolves, e.g. of internal hosts.
+    pub fn host_aliases(&self) -> &[HostAlias] {
+        &self.host_aliases
+    }
+
+    pub fn set_host_aliases(&mut self, host_aliases: Vec<HostAlias>) {
+        self.host_aliases = host_aliases;
+    }
+
+    pub fn dns_config(&self) -> Option<&DnsConfig> {
+        self.dns_config.as_ref()
+    }
+
+    pub fn set_dns_config(&mut self, dns_config: Option<DnsConfig>) {
+        self.dns_config = dns_config;
+    }
+
+    /// The time zone of the service that becomes its `TZ` variable.
+    pub fn timezone(&self) -> Option<&TimeZone> {
+        self.timezone.as_ref()
+    }
+
+    pub fn set_timezone(&mut self, timezone: Option<TimeZone>) {
+        self.timezone = timezone;
+    }
+
+    /// The locale of the service that becomes its `LANG` variable.
+    pub fn locale(&self) -> Option<&Locale> {
+        self.locale.as_ref()
+    }
+
+    pub fn set_locale(&mut self, locale: Option<Locale>) {
+        self.locale = locale;
+    }
+
+    /// Directories of the Docker host that are mounted into the container in dev mode.
+    pub fn dev_mounts(&self) -> &[DevMount] {
+        &self.dev_mounts
+    }
+
+    pub fn set_dev_mounts(&mut self, dev_mounts: Vec<DevMount>) {
+        self.dev_mounts = dev_mounts;
+    }
+
+    /// Volumes for caches and temporary data that don't outlive the container of the service.
+    pub fn scratch_volumes(&self) -> &[ScratchVolume] {
+        &self.scratch_volumes
+    }
+
+    pub fn set_scratch_volumes(&mut self, scratch_volumes: Vec<ScratchVolume>) {
+        self.scratch_volumes = scratch_volumes;
+    }
+
+    /// Whether the root filesystem of the container is mounted read-only. Then, the service can
+    /// only write into the [writable paths](Self::writable_paths), the scratch volumes, and the
+    /// declared volumes of the image.
+    pub fn read_only_root_filesystem(&self) -> bool {
+        self.read_only_root_filesystem
+    }
+
+    pub fn set_read_only_root_filesystem(&mut self, read_only_root_filesystem: bool) {
+        self.read_only_root_filesystem = read_only_root_filesystem;
+    }
+
+    pub fn writable_paths(&self) -> &[PathBuf] {
+        &self.writable_paths
+    }
+
+    pub fn set_writable_paths(&mut self, writable_paths: Vec<PathBuf>) {
+        self.writable_paths = writable_paths;
+    }
+
+    /// The scratch volumes together with an empty scratch volume for each writable path that is
+    /// not a scratch volume already.
+    pub fn ephemeral_volumes(&self) -> Vec<ScratchVolume> {
+        let mut volumes = self.scratch_volumes.clone();
+        for path in &self.writable_paths {
+            if !volumes.iter().any(|volume| volume.path() == path) {
+                volumes.push(ScratchVolume::new(path.clone()));
+            }
+        }
+        volumes
+    }
+
+    pub fn startup_probe(&self) -> Option<&StartupProbe> {
+        self.startup_probe.as_ref()
+    }
+
+    pub fn set_startup_probe(&mut self, startup_probe: Option<StartupProbe>) {
+        self.startup_probe = startup_probe;
+    }
+
+    /// The data that is loaded into the database of the service when the service is deployed
+    /// for the first time.
+    pub fn seed(&self) -> Option<&Seed> {
+        self.seed.as_ref()
+    }
+
+    pub fn set_seed(&mut self, seed: Option<Seed>) {
+        self.seed = seed;
+    }
+
+    pub fn termination_grace_period(&self) -> Option<Duration> {
+        self.termination_grace_period.map(Duration::from_secs)
+    }
+
+    pub fn set_termination_grace_period(&mut self, termination_grace_period: Option<Duration>) {
+        self.termination_grace_period =
+            termination_grace_period.map(|grace_period| grace_period.as_secs());
+    }
+
+    pub fn platform(&self) -> Option<&String> {
+        self.platform.as_ref()
+    }
+
+    pub fn set_platform(&mut self, platform: Option<String>) {
+        self.platform = platform;
+    }
+
+    pub fn os(&self) -> Option<OperatingSystem> {
+        self.os
+    }
+
+    pub fn set_os(&mut self, os: Option<OperatingSystem>) {
+        self.os = os;
+    }
+
+    pub fn template_engine(&self) -> TemplateEngine {
+        self.template_engine
+    }
+
+    pub fn set_template_engine(&mut self, template_engine: TemplateEngine) {
+        self.template_engine = template_engine;
+    }
+
     pub fn set_port(&mut self, port: u16) {
         self.port = port;
     }
@@ -145,6 +577,33 @@ impl ServiceConfig {
         self.port
     }
 
+    /// Sets the port that receives the traffic of the service's route regardless of the ports
+    /// that the image exposes.
+    pub fn set_fixed_port(&mut self, port: u16) {
+        self.port = port;
+        self.fixed_port = true;
+    }
+
+    pub fn has_fixed_port(&self) -> bool {
+        self.fixed_port
+    }
+
+    pub fn set_additional_ports(&mut self, ports: Vec<u16>) {
+        self.additional_ports = ports;
+    }
+
+    pub fn additional_ports(&self) -> &Vec<u16> {
+        &self.additional_ports
+    }
+
+    pub fn load_balancer(&self) -> Option<&LoadBalancer> {
+        self.load_balancer.as_ref()
+    }
+
+    pub fn set_load_balancer(&mut self, load_balancer: Option<LoadBalancer>) {
+        self.load_balancer = load_balancer;
+    }
+
     pub fn set_router(&mut self, router: Router) {
         self.router = Some(router);
     }
@@ -160,6 +619,22 @@ impl ServiceConfig {
         self.middlewares = Some(middlewares);
     }
 
+    /// Adds the middleware if the service config does not declare a middleware of the same type.
+    pub fn add_middleware(&mut self, kind: String, middleware: Value) {
+        self.middlewares
+            .get_or_insert_with(BTreeMap::new)
+            .entry(kind)
+            .or_insert(middleware);
+    }
+
+    /// Sets the middleware and replaces a middleware of the same type that the service config
+    /// declares.
+    pub fn set_middleware(&mut self, kind: String, middleware: Value) {
+        self.middlewares
+            .get_or_insert_with(BTreeMap::new)
+            .insert(kind, middleware);
+    }
+
     pub fn middlewares<'a, 'b: 'a>(&'b self) -> Option<&BTreeMap<String, Value>> {
         match &self.middlewares {
             None => None,
@@ -167,7 +642,7 @@ impl ServiceConfig {
         }
     }
 
-    /// Copy labels, envs and files from other into self.
+    /// Copy labels, metadata, envs and files from other into self.
     /// If something is defined in self and other, self has precedence.
     pub fn merge_with(&mut self, other: &Self) {
         if let Some(env) = &other.env {
@@ -192,9 +667,65 @@ impl ServiceConfig {
         let mut labels = other.labels.as_ref().cloned().unwrap_or_default();
         labels.extend(self.labels.as_ref().cloned().unwrap_or_default());
         self.labels = Some(labels);
+
+        if let Some(metadata) = &other.metadata {
+            self.add_metadata(metadata);
+        }
+
+        for alias in &other.aliases {
+            if !self.aliases.contains(alias) {
+                self.aliases.push(alias.clone());
+            }
+        }
+
+        for dependency in &other.depends_on {
+            if !self.depends_on.contains(dependency) {
+                self.depends_on.push(dependency.clone());
+            }
+        }
+    }
+
+    /// Removes the environment variables of `unsetEnv` and the files of `removeFiles` that have
+    /// been requested in the payload. This must be the last modification of the configuration so
+    /// that removals win over values that have been added by companions, secrets, or merges.
+    pub fn apply_removals(&mut self) {
+        if let Some(env) = &mut self.env {
+            env.remove(&self.unset_env);
+        }
+
+        let remove_files = &self.remove_files;
+        if let Some(files) = &mut self.files {
+            files.retain(|path, _| !remove_files.contains(path));
+        }
     }
 }
 
+/// Accepts quantities of extended resources as strings or as numbers, e.g. `{ "nvidia.com/gpu": 1 }`.
+pub(crate) fn deserialize_extended_resources<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Quantity {
+        Text(String),
+        Number(u64),
+    }
+
+    Ok(BTreeMap::<String, Quantity>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(resource, quantity)| {
+            let quantity = match quantity {
+                Quantity::Text(text) => text,
+                Quantity::Number(number) => number.to_string(),
+            };
+            (resource, quantity)
+        })
+        .collect())
+}
+
 /// Helper that configures the service routing for Traefik (see
 /// [here](https://docs.traefik.io/routing/routers/)).
 #[derive(Clone, Debug, Hash, Deserialize, Eq, PartialEq)]
@@ -332,6 +863,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_parse_service_config_json_with_metadata() {
+        let config = from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "mariadb",
+            "image": "mariadb:10.3",
+            "metadata": {
+                "labels": {
+                    "cost-center": "4711"
+                },
+                "annotations": {
+                    "example.com/owner": "team-a"
+                }
+            }
+        }))
+        .unwrap();
+
+        let metadata = config.metadata().unwrap();
+        assert_eq!(
+            metadata.labels().get("cost-center"),
+            Some(&String::from("4711"))
+        );
+        assert_eq!(
+            metadata.annotations().get("example.com/owner"),
+            Some(&String::from("team-a"))
+        );
+    }
+
     #[test]
     fn should_merge_service_configs_labels() {
         let mut config = sc!(
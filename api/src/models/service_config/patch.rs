@@ -0,0 +1,199 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use super::{Environment, ServiceConfig};
+use crate::models::Image;
+use secstr::SecUtf8;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Partial changes of a running service: the image is replaced while environment variables and
+/// files are merged into the ones of the running service, or removed from them.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServicePatch {
+    image: Option<Image>,
+    env: Option<Environment>,
+    #[serde(alias = "volumes")]
+    files: Option<BTreeMap<PathBuf, SecUtf8>>,
+    #[serde(alias = "unset_env", default)]
+    unset_env: Vec<String>,
+    #[serde(alias = "remove_files", default)]
+    remove_files: Vec<PathBuf>,
+}
+
+impl ServicePatch {
+    pub fn apply_to(self, config: &mut ServiceConfig) {
+        if let Some(image) = self.image {
+            config.image = image;
+        }
+
+        if let Some(patched_env) = self.env {
+            let mut env = config
+                .env
+                .take()
+                .unwrap_or_else(|| Environment::new(Vec::new()));
+            for variable in patched_env.into_iter() {
+                env.insert(variable);
+            }
+            config.env = Some(env);
+        }
+
+        if let Some(files) = self.files {
+            for (path, data) in files {
+                config.add_file(path, data);
+            }
+        }
+
+        config.unset_env = self.unset_env;
+        config.remove_files = self.remove_files;
+    }
+}
+
+/// New contents of files that are already mounted into a running service. The files are replaced
+/// in place and the service is notified according to `reload` so that it picks up the changes.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FilesUpdate {
+    files: BTreeMap<PathBuf, SecUtf8>,
+    #[serde(default)]
+    reload: Option<FileReload>,
+}
+
+impl FilesUpdate {
+    pub fn files(&self) -> &BTreeMap<PathBuf, SecUtf8> {
+        &self.files
+    }
+
+    pub fn reload(&self) -> Option<&FileReload> {
+        self.reload.as_ref()
+    }
+}
+
+/// Defines how a service is notified about updated files.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FileReload {
+    /// Restarts all instances of the service.
+    Restart,
+    /// Sends the signal, e.g. `HUP`, to the main process of each instance.
+    #[serde(deserialize_with = "deserialize_signal")]
+    Signal(String),
+}
+
+fn deserialize_signal<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let signal = String::deserialize(deserializer)?;
+    let signal = signal.trim_start_matches("SIG").to_string();
+    if signal.is_empty() || !signal.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(serde::de::Error::custom(format!(
+            "{} is not a valid signal name",
+            signal
+        )));
+    }
+    Ok(signal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sc;
+
+    #[test]
+    fn should_merge_patch_into_config() {
+        let mut config = sc!(
+            "api",
+            labels = (),
+            env = ("LOG_LEVEL" => "info", "DB_HOST" => "db"),
+            files = ("/etc/api.conf" => "a=1")
+        );
+
+        let patch = serde_json::from_value::<ServicePatch>(serde_json::json!({
+            "image": "example/api:2.0",
+            "env": { "LOG_LEVEL": "debug" },
+            "files": { "/etc/feature.conf": "x=1" }
+        }))
+        .unwrap();
+        patch.apply_to(&mut config);
+
+        assert_eq!(config.image().to_string(), "docker.io/example/api:2.0");
+
+        let env = config.env().unwrap();
+        assert_eq!(
+            env.variable("LOG_LEVEL").unwrap().value(),
+            &SecUtf8::from("debug")
+        );
+        assert_eq!(
+            env.variable("DB_HOST").unwrap().value(),
+            &SecUtf8::from("db")
+        );
+
+        let files = config.files().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            files.get(&PathBuf::from("/etc/api.conf")),
+            Some(&SecUtf8::from("a=1"))
+        );
+    }
+
+    #[test]
+    fn should_parse_files_update_with_signal() {
+        let update = serde_json::from_value::<FilesUpdate>(serde_json::json!({
+            "files": { "/etc/nginx/nginx.conf": "worker_processes 2;" },
+            "reload": { "signal": "SIGHUP" }
+        }))
+        .unwrap();
+
+        assert_eq!(update.files().len(), 1);
+        assert_eq!(
+            update.reload(),
+            Some(&FileReload::Signal(String::from("HUP")))
+        );
+    }
+
+    #[test]
+    fn should_parse_files_update_with_restart() {
+        let update = serde_json::from_value::<FilesUpdate>(serde_json::json!({
+            "files": { "/etc/api.conf": "a=2" },
+            "reload": "restart"
+        }))
+        .unwrap();
+
+        assert_eq!(update.reload(), Some(&FileReload::Restart));
+    }
+
+    #[test]
+    fn should_not_parse_files_update_with_invalid_signal() {
+        let update = serde_json::from_value::<FilesUpdate>(serde_json::json!({
+            "files": { "/etc/api.conf": "a=2" },
+            "reload": { "signal": "-9 1; reboot" }
+        }));
+
+        assert!(update.is_err());
+    }
+}
@@ -0,0 +1,116 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::parse_quantity;
+use serde::{de, Deserialize, Deserializer};
+use std::path::PathBuf;
+
+/// A volume for caches and temporary data that lives as long as the container of the service and
+/// does not require a persistent volume.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScratchVolume {
+    path: PathBuf,
+    /// The maximal size as Kubernetes quantity, e.g. `512Mi`
+    #[serde(default, deserialize_with = "ScratchVolume::deserialize_size_limit")]
+    size_limit: Option<String>,
+    #[serde(default)]
+    medium: ScratchVolumeMedium,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum ScratchVolumeMedium {
+    /// The volume is backed by the disk of the node.
+    #[default]
+    Disk,
+    /// The volume is backed by memory (tmpfs) and its content counts against the memory of the
+    /// container.
+    Memory,
+}
+
+impl ScratchVolume {
+    /// A scratch volume on disk without size limit.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            size_limit: None,
+            medium: ScratchVolumeMedium::Disk,
+        }
+    }
+
+    fn deserialize_size_limit<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let size_limit = String::deserialize(deserializer)?;
+        match parse_quantity(&size_limit) {
+            Some(_) => Ok(Some(size_limit)),
+            None => Err(de::Error::custom(format!(
+                "{size_limit} is not a valid quantity, e.g. 512Mi"
+            ))),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn size_limit(&self) -> Option<&String> {
+        self.size_limit.as_ref()
+    }
+
+    pub fn medium(&self) -> ScratchVolumeMedium {
+        self.medium
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_scratch_volume() {
+        let volume = serde_json::from_value::<ScratchVolume>(serde_json::json!({
+            "path": "/tmp/cache",
+            "sizeLimit": "64Mi",
+            "medium": "Memory"
+        }))
+        .unwrap();
+
+        assert_eq!(volume.path(), &PathBuf::from("/tmp/cache"));
+        assert_eq!(volume.size_limit(), Some(&String::from("64Mi")));
+        assert_eq!(volume.medium(), ScratchVolumeMedium::Memory);
+    }
+
+    #[test]
+    fn should_not_parse_scratch_volume_with_invalid_size_limit() {
+        let volume = serde_json::from_value::<ScratchVolume>(serde_json::json!({
+            "path": "/tmp/cache",
+            "sizeLimit": "a lot"
+        }));
+
+        assert!(volume.is_err());
+    }
+}
@@ -0,0 +1,177 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::Image;
+use serde::Deserialize;
+use std::time::Duration;
+use url::Url;
+
+/// Data that is loaded into the database of a service after the service has been deployed for the
+/// first time, e.g. the fixtures that the tests of a feature branch require. The `scripts` are
+/// executed before the `dumps` are restored.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Seed {
+    /// SQL statements that are executed one script after another.
+    #[serde(default)]
+    scripts: Vec<String>,
+    /// URLs of dumps that are downloaded and restored. Dumps ending with `.gz` are decompressed.
+    #[serde(default)]
+    dumps: Vec<Url>,
+    /// The database engine which will be derived from the image of the service if it is absent.
+    #[serde(default)]
+    engine: Option<SeedEngine>,
+    /// The image of the seed job that provides the client of the database. Defaults to the image
+    /// of the service.
+    #[serde(default)]
+    image: Option<Image>,
+    /// The database that will be seeded. Defaults to the database that the service creates.
+    #[serde(default)]
+    database: Option<String>,
+    #[serde(default = "Seed::default_timeout")]
+    timeout: u64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SeedEngine {
+    Postgres,
+    #[serde(alias = "mariadb")]
+    Mysql,
+}
+
+impl Seed {
+    fn default_timeout() -> u64 {
+        600
+    }
+
+    pub fn scripts(&self) -> &[String] {
+        &self.scripts
+    }
+
+    pub fn dumps(&self) -> &[Url] {
+        &self.dumps
+    }
+
+    /// The engine of the database that is either declared or derived from the `service_image`.
+    pub fn engine(&self, service_image: &Image) -> Option<SeedEngine> {
+        self.engine.or_else(|| SeedEngine::of_image(service_image))
+    }
+
+    pub fn image(&self) -> Option<&Image> {
+        self.image.as_ref()
+    }
+
+    pub fn database(&self) -> Option<&String> {
+        self.database.as_ref()
+    }
+
+    /// The maximal time the seed job may take, including the time the database needs to accept
+    /// connections.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+impl SeedEngine {
+    fn of_image(image: &Image) -> Option<Self> {
+        match image {
+            Image::Named {
+                image_repository, ..
+            } => match image_repository.as_str() {
+                "postgres" | "postgis" | "timescaledb" => Some(SeedEngine::Postgres),
+                "mysql" | "mariadb" | "percona" => Some(SeedEngine::Mysql),
+                _ => None,
+            },
+            Image::Digest { .. } => None,
+        }
+    }
+
+    /// The port the database listens on by default.
+    pub fn port(self) -> u16 {
+        match self {
+            SeedEngine::Postgres => 5432,
+            SeedEngine::Mysql => 3306,
+        }
+    }
+}
+
+/// The result of seeding the database of a service that is recorded in the deployment history.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedLog {
+    service_name: String,
+    passed: bool,
+    logs: String,
+}
+
+impl SeedLog {
+    pub fn new(service_name: String, passed: bool, logs: String) -> Self {
+        Self {
+            service_name,
+            passed,
+            logs,
+        }
+    }
+
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+
+    pub fn logs(&self) -> &str {
+        &self.logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_parse_seed_and_derive_engine() {
+        let seed = serde_json::from_value::<Seed>(serde_json::json!({
+            "scripts": ["INSERT INTO users (name) VALUES ('alice');"],
+            "dumps": ["https://fixtures.example.com/shop.sql.gz"]
+        }))
+        .unwrap();
+
+        assert_eq!(seed.dumps().len(), 1);
+        assert_eq!(seed.timeout(), Duration::from_secs(600));
+        assert_eq!(
+            seed.engine(&Image::from_str("postgres:15").unwrap()),
+            Some(SeedEngine::Postgres)
+        );
+        assert_eq!(
+            seed.engine(&Image::from_str("bitnami/mariadb:11").unwrap()),
+            Some(SeedEngine::Mysql)
+        );
+        assert_eq!(seed.engine(&Image::from_str("redis:7").unwrap()), None);
+    }
+}
@@ -0,0 +1,94 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use serde::Deserialize;
+
+/// Requests a service account for a service that grants in-namespace permissions to the Kubernetes
+/// API, e.g. for Jenkins agents or operators.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct ServiceAccount {
+    #[serde(default)]
+    rules: Vec<AccessRule>,
+}
+
+/// A rule of the role that will be bound to the service account (see
+/// [PolicyRule](https://kubernetes.io/docs/reference/access-authn-authz/rbac/#role-example)).
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRule {
+    #[serde(default = "AccessRule::default_api_groups")]
+    api_groups: Vec<String>,
+    resources: Vec<String>,
+    verbs: Vec<String>,
+}
+
+impl ServiceAccount {
+    pub fn rules(&self) -> &Vec<AccessRule> {
+        &self.rules
+    }
+}
+
+impl AccessRule {
+    fn default_api_groups() -> Vec<String> {
+        vec![String::new()]
+    }
+
+    pub fn api_groups(&self) -> &Vec<String> {
+        &self.api_groups
+    }
+
+    pub fn resources(&self) -> &Vec<String> {
+        &self.resources
+    }
+
+    pub fn verbs(&self) -> &Vec<String> {
+        &self.verbs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_rules_with_core_api_group_as_default() {
+        let service_account = serde_json::from_value::<ServiceAccount>(serde_json::json!({
+            "rules": [{
+                "resources": ["pods", "pods/log"],
+                "verbs": ["get", "list", "create", "delete"]
+            }]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            service_account.rules()[0].api_groups(),
+            &vec![String::new()]
+        );
+        assert_eq!(
+            service_account.rules()[0].resources(),
+            &vec![String::from("pods"), String::from("pods/log")]
+        );
+    }
+}
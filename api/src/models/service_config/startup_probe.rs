@@ -0,0 +1,104 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Probes whether a slow service, e.g. a JVM service, has started. If the service does not define
+/// a `path` or a `command`, the probe checks whether the port of the service accepts connections.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupProbe {
+    /// The path that is requested by HTTP GET on the port of the service.
+    #[serde(default)]
+    path: Option<String>,
+    /// The command that is executed inside the container.
+    #[serde(default)]
+    command: Option<Vec<String>>,
+    #[serde(default)]
+    initial_delay_seconds: u32,
+    #[serde(default = "StartupProbe::default_period_seconds")]
+    period_seconds: u32,
+    #[serde(default = "StartupProbe::default_failure_threshold")]
+    failure_threshold: u32,
+}
+
+impl StartupProbe {
+    fn default_period_seconds() -> u32 {
+        10
+    }
+
+    fn default_failure_threshold() -> u32 {
+        30
+    }
+
+    pub fn path(&self) -> Option<&String> {
+        self.path.as_ref()
+    }
+
+    pub fn command(&self) -> Option<&Vec<String>> {
+        self.command.as_ref()
+    }
+
+    pub fn initial_delay_seconds(&self) -> u32 {
+        self.initial_delay_seconds
+    }
+
+    pub fn period_seconds(&self) -> u32 {
+        self.period_seconds
+    }
+
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    /// The longest time the service may take to start before it is considered as failed.
+    pub fn max_startup_duration(&self) -> Duration {
+        Duration::from_secs(
+            u64::from(self.initial_delay_seconds)
+                + u64::from(self.period_seconds) * u64::from(self.failure_threshold),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_startup_probe_with_defaults() {
+        let probe = serde_json::from_value::<StartupProbe>(serde_json::json!({
+            "path": "/health",
+            "initialDelaySeconds": 20
+        }))
+        .unwrap();
+
+        assert_eq!(probe.path(), Some(&String::from("/health")));
+        assert_eq!(probe.command(), None);
+        assert_eq!(probe.period_seconds(), 10);
+        assert_eq!(probe.failure_threshold(), 30);
+        assert_eq!(probe.max_startup_duration(), Duration::from_secs(320));
+    }
+}
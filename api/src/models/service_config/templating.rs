@@ -34,18 +34,36 @@ use serde_value::Value;
 use std::collections::BTreeMap;
 use std::str::FromStr;
 
+/// The template engine that renders the templated fields of a service, e.g. its environment
+/// variables. Handlebars is the default for backwards compatibility and [Tera](https://keats.github.io/tera/)
+/// provides conditions, loops, and filters with a Jinja2-like syntax.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateEngine {
+    #[default]
+    Handlebars,
+    Tera,
+}
+
 impl ServiceConfig {
     pub fn apply_templating(&self, app_name: &String) -> Result<Self, RenderError> {
+        self.apply_templating_with_metadata(app_name, &BTreeMap::new())
+    }
+
+    /// Applies the templating with the metadata of the app, e.g. the originating branch, that is
+    /// available as `application.metadata`.
+    pub fn apply_templating_with_metadata(
+        &self,
+        app_name: &String,
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<Self, RenderError> {
         let parameters = TemplateParameters {
             application: ApplicationTemplateParameter {
                 name: app_name.clone(),
+                metadata: metadata.clone(),
             },
             services: None,
-            service: Some(ServiceTemplateParameter {
-                name: self.service_name().clone(),
-                container_type: self.container_type().clone(),
-                port: self.port(),
-            }),
+            service: Some(ServiceTemplateParameter::from(self)),
         };
 
         self.apply_template(&parameters)
@@ -55,17 +73,27 @@ impl ServiceConfig {
         &self,
         app_name: &String,
         service_config: &Self,
+    ) -> Result<Self, RenderError> {
+        self.apply_templating_for_service_companion_with_metadata(
+            app_name,
+            service_config,
+            &BTreeMap::new(),
+        )
+    }
+
+    pub fn apply_templating_for_service_companion_with_metadata(
+        &self,
+        app_name: &String,
+        service_config: &Self,
+        metadata: &BTreeMap<String, String>,
     ) -> Result<Self, RenderError> {
         let parameters = TemplateParameters {
             application: ApplicationTemplateParameter {
                 name: app_name.clone(),
+                metadata: metadata.clone(),
             },
             services: None,
-            service: Some(ServiceTemplateParameter {
-                name: service_config.service_name().clone(),
-                container_type: service_config.container_type().clone(),
-                port: service_config.port(),
-            }),
+            service: Some(ServiceTemplateParameter::from(service_config)),
         };
 
         self.apply_template(&parameters)
@@ -75,19 +103,29 @@ impl ServiceConfig {
         &self,
         app_name: &String,
         service_configs: &Vec<Self>,
+    ) -> Result<Self, RenderError> {
+        self.apply_templating_for_application_companion_with_metadata(
+            app_name,
+            service_configs,
+            &BTreeMap::new(),
+        )
+    }
+
+    pub fn apply_templating_for_application_companion_with_metadata(
+        &self,
+        app_name: &String,
+        service_configs: &Vec<Self>,
+        metadata: &BTreeMap<String, String>,
     ) -> Result<Self, RenderError> {
         let parameters = TemplateParameters {
             application: ApplicationTemplateParameter {
                 name: app_name.clone(),
+                metadata: metadata.clone(),
             },
             services: Some(
                 service_configs
                     .iter()
-                    .map(|c| ServiceTemplateParameter {
-                        name: c.service_name().clone(),
-                        container_type: c.container_type().clone(),
-                        port: c.port(),
-                    })
+                    .map(ServiceTemplateParameter::from)
                     .collect(),
             ),
             service: None,
@@ -97,15 +135,13 @@ impl ServiceConfig {
     }
 
     fn apply_template(&self, parameters: &TemplateParameters) -> Result<Self, RenderError> {
-        let mut reg = Handlebars::new();
-        reg.register_helper("isCompanion", Box::new(is_companion));
-        reg.register_helper("isNotCompanion", Box::new(is_not_companion));
+        let reg = Renderer::new(self.template_engine());
 
         let mut templated_config = self.clone();
-        templated_config.set_service_name(&reg.render_template(self.service_name(), &parameters)?);
+        templated_config.set_service_name(&reg.render(self.service_name(), parameters)?);
 
         if let Some(env) = self.env() {
-            templated_config.set_env(Some(env.apply_templating(parameters, &mut reg)?));
+            templated_config.set_env(Some(env.apply_templating(parameters, &reg)?));
         }
 
         if let Some(files) = self.files() {
@@ -119,7 +155,7 @@ impl ServiceConfig {
         }
 
         if let Some(router) = self.router() {
-            let rule = reg.render_template(router.rule(), &parameters)?;
+            let rule = reg.render(router.rule(), parameters)?;
             templated_config.set_router(router.with_rule(rule));
         }
 
@@ -135,18 +171,82 @@ impl ServiceConfig {
     }
 }
 
+/// Renders templates with the template engine of a service.
+enum Renderer {
+    Handlebars(Handlebars<'static>),
+    Tera,
+}
+
+impl Renderer {
+    fn new(engine: TemplateEngine) -> Self {
+        match engine {
+            TemplateEngine::Handlebars => {
+                let mut reg = Handlebars::new();
+                reg.register_helper("isCompanion", Box::new(is_companion));
+                reg.register_helper("isNotCompanion", Box::new(is_not_companion));
+                Renderer::Handlebars(reg)
+            }
+            TemplateEngine::Tera => Renderer::Tera,
+        }
+    }
+
+    /// Creates a Tera instance without the `get_env` function so that templates cannot read the
+    /// environment of PREvant, e.g. the credentials of its database or registries.
+    fn tera() -> tera::Tera {
+        let mut tera = tera::Tera::default();
+        tera.register_function(
+            "get_env",
+            |_: &std::collections::HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+                Err(tera::Error::msg(
+                    "The function get_env is not available in templates",
+                ))
+            },
+        );
+        tera
+    }
+
+    fn render(
+        &self,
+        template: &str,
+        parameters: &TemplateParameters,
+    ) -> Result<String, RenderError> {
+        match self {
+            Renderer::Handlebars(reg) => reg.render_template(template, parameters),
+            Renderer::Tera => {
+                let context = tera::Context::from_serialize(parameters)
+                    .map_err(|err| RenderError::new(tera_error_message(&err)))?;
+                Self::tera()
+                    .render_str(template, &context)
+                    .map_err(|err| RenderError::new(tera_error_message(&err)))
+            }
+        }
+    }
+}
+
+/// Tera nests the cause of an error, e.g. the syntax error, in its sources.
+fn tera_error_message(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
 impl Environment {
     fn apply_templating(
         &self,
         parameters: &TemplateParameters,
-        reg: &mut Handlebars,
+        reg: &Renderer,
     ) -> Result<Self, RenderError> {
         let mut templated_env = Vec::new();
 
         for e in self.iter() {
             let v = if e.templated() {
                 EnvironmentVariable::with_original(
-                    SecUtf8::from(reg.render_template(e.value().unsecure(), &parameters)?),
+                    SecUtf8::from(reg.render(e.value().unsecure(), parameters)?),
                     e.clone(),
                 )
             } else {
@@ -216,7 +316,7 @@ fn is_companion<'reg, 'rc>(
 }
 
 fn apply_templates<K>(
-    reg: &Handlebars,
+    reg: &Renderer,
     parameters: &TemplateParameters,
     original_values: &BTreeMap<K, String>,
 ) -> Result<BTreeMap<K, String>, RenderError>
@@ -226,14 +326,14 @@ where
     let mut templated_values = BTreeMap::new();
 
     for (k, v) in original_values {
-        templated_values.insert(k.clone(), reg.render_template(v, &parameters)?);
+        templated_values.insert(k.clone(), reg.render(v, parameters)?);
     }
 
     Ok(templated_values)
 }
 
 fn apply_templates_with_secrets<K>(
-    reg: &Handlebars,
+    reg: &Renderer,
     parameters: &TemplateParameters,
     original_values: &BTreeMap<K, SecUtf8>,
 ) -> Result<BTreeMap<K, SecUtf8>, RenderError>
@@ -245,7 +345,7 @@ where
     for (k, v) in original_values {
         templated_values.insert(
             k.clone(),
-            SecUtf8::from(reg.render_template(v.unsecure(), &parameters)?),
+            SecUtf8::from(reg.render(v.unsecure(), parameters)?),
         );
     }
 
@@ -253,7 +353,7 @@ where
 }
 
 fn apply_templating_to_middlewares(
-    reg: &Handlebars,
+    reg: &Renderer,
     parameters: &TemplateParameters,
     original_values: &BTreeMap<String, Value>,
 ) -> Result<BTreeMap<String, Value>, RenderError> {
@@ -270,12 +370,12 @@ fn apply_templating_to_middlewares(
 }
 
 fn apply_templating_to_middleware_value(
-    reg: &Handlebars,
+    reg: &Renderer,
     parameters: &TemplateParameters,
     value: &Value,
 ) -> Result<Value, RenderError> {
     match value {
-        Value::String(v) => Ok(Value::String(reg.render_template(v, &parameters)?)),
+        Value::String(v) => Ok(Value::String(reg.render(v, parameters)?)),
         Value::Seq(values) => {
             let mut templated_values = Vec::with_capacity(values.len());
             for v in values.iter() {
@@ -307,6 +407,7 @@ struct TemplateParameters {
 #[derive(Serialize)]
 struct ApplicationTemplateParameter {
     name: String,
+    metadata: BTreeMap<String, String>,
 }
 
 #[derive(Serialize)]
@@ -315,6 +416,18 @@ struct ServiceTemplateParameter {
     port: u16,
     #[serde(rename = "type")]
     container_type: ContainerType,
+    aliases: Vec<String>,
+}
+
+impl From<&ServiceConfig> for ServiceTemplateParameter {
+    fn from(config: &ServiceConfig) -> Self {
+        ServiceTemplateParameter {
+            name: config.service_name().clone(),
+            port: config.port(),
+            container_type: config.container_type().clone(),
+            aliases: config.aliases().to_vec(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -340,6 +453,26 @@ mod tests {
         assert_eq!(templated_config.service_name(), "postgres-master");
     }
 
+    #[test]
+    fn should_apply_templating_with_aliases() {
+        let mut config = sc!("db", "mariadb");
+        config.set_aliases(vec![String::from("mysql")]);
+        config.set_env(Some(Environment::new(vec![
+            EnvironmentVariable::with_templating(
+                "JDBC_URL".to_string(),
+                SecUtf8::from("jdbc:mysql://{{service.aliases.[0]}}:3306/{{application.name}}"),
+            ),
+        ])));
+
+        let templated_config = config.apply_templating(&String::from("master")).unwrap();
+
+        let env = templated_config.env().unwrap().get(0).unwrap();
+        assert_eq!(
+            env.value(),
+            &SecUtf8::from("jdbc:mysql://mysql:3306/master")
+        );
+    }
+
     #[test]
     fn should_apply_app_companion_templating_with_envs() {
         let mut config = ServiceConfig::new(
@@ -780,4 +913,122 @@ location /service-d {
         );
         assert_eq!(env.original().value().unsecure(), "admin-{{service.name}}");
     }
+
+    #[test]
+    fn should_apply_tera_templating_for_application_companion() {
+        let mut config = sc!("openid", "keycloak");
+        config.set_template_engine(TemplateEngine::Tera);
+        config.set_env(Some(Environment::new(vec![
+            EnvironmentVariable::with_templating(
+                String::from("REDIRECT_URIS"),
+                SecUtf8::from(
+                    "{% for service in services %}/{{ application.name }}/{{ service.name }}:{{ service.port }}{% if not loop.last %},{% endif %}{% endfor %}",
+                ),
+            ),
+        ])));
+
+        let templated_config = config
+            .apply_templating_for_application_companion(
+                &String::from("master"),
+                &vec![sc!("service-a", "service-a"), sc!("service-b", "service-b")],
+            )
+            .unwrap();
+
+        let env = templated_config.env().unwrap().get(0).unwrap();
+        assert_eq!(
+            env.value().unsecure(),
+            "/master/service-a:80,/master/service-b:80"
+        );
+    }
+
+    #[test]
+    fn should_apply_tera_templating_with_app_metadata() {
+        let mut config = sc!("api-gateway", "api-gateway");
+        config.set_template_engine(TemplateEngine::Tera);
+        config.set_env(Some(Environment::new(vec![
+            EnvironmentVariable::with_templating(
+                String::from("BRANCH"),
+                SecUtf8::from("{{ application.metadata.branch | upper }}"),
+            ),
+            EnvironmentVariable::with_templating(
+                String::from("TICKET"),
+                SecUtf8::from(r#"{{ application.metadata.ticket | default(value="none") }}"#),
+            ),
+        ])));
+        let headers = serde_value::to_value(serde_json::json!({
+            "customRequestHeaders": {
+                "X-Branch": "{{ application.metadata.branch }}"
+            }
+        }))
+        .unwrap();
+        let mut middlewares = BTreeMap::new();
+        middlewares.insert("headers".to_string(), headers);
+        config.set_middlewares(middlewares);
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert(String::from("branch"), String::from("feature/login"));
+
+        let templated_config = config
+            .apply_templating_with_metadata(&String::from("master"), &metadata)
+            .unwrap();
+
+        let env = templated_config.env().unwrap();
+        assert_eq!(env.get(0).unwrap().value().unsecure(), "FEATURE/LOGIN");
+        assert_eq!(env.get(1).unwrap().value().unsecure(), "none");
+        assert_eq!(
+            templated_config.middlewares().unwrap().get("headers"),
+            Some(
+                &serde_value::to_value(serde_json::json!({
+                    "customRequestHeaders": {
+                        "X-Branch": "feature/login"
+                    }
+                }))
+                .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn should_not_expose_environment_to_tera_templates() {
+        let mut config = sc!("db", "mariadb");
+        config.set_template_engine(TemplateEngine::Tera);
+        config.set_env(Some(Environment::new(vec![
+            EnvironmentVariable::with_templating(
+                String::from("DB_PASSWORD"),
+                SecUtf8::from(r#"{{ get_env(name="PATH") }}"#),
+            ),
+        ])));
+
+        let templated_config = config.apply_templating(&String::from("master"));
+
+        assert!(templated_config.is_err());
+    }
+
+    #[test]
+    fn should_ignore_template_engine_of_payload() {
+        let config = serde_json::from_value::<ServiceConfig>(serde_json::json!({
+            "serviceName": "db",
+            "image": "mariadb",
+            "templateEngine": "tera"
+        }))
+        .unwrap();
+
+        assert_eq!(config.template_engine(), TemplateEngine::Handlebars);
+    }
+
+    #[test]
+    fn should_not_apply_tera_templating_with_invalid_syntax() {
+        let mut config = sc!("db", "mariadb");
+        config.set_template_engine(TemplateEngine::Tera);
+        config.set_env(Some(Environment::new(vec![
+            EnvironmentVariable::with_templating(
+                String::from("DB_USER"),
+                SecUtf8::from("{% if service.name %}admin"),
+            ),
+        ])));
+
+        let templated_config = config.apply_templating(&String::from("master"));
+
+        assert!(templated_config.is_err());
+    }
 }
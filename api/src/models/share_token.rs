@@ -0,0 +1,157 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// A token that grants access to the services of an app without authentication, e.g. to share a
+/// preview with an external customer. Only the hash of the secret token is kept so that the token
+/// itself is known only to the user who created it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareToken {
+    id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    #[serde(skip_serializing)]
+    token_hash: String,
+}
+
+/// The form in which the state stores and backups keep a share token. In contrast to the API
+/// responses, it contains the hash of the secret token.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredShareToken {
+    id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    token_hash: String,
+}
+
+impl From<&ShareToken> for StoredShareToken {
+    fn from(share_token: &ShareToken) -> Self {
+        Self {
+            id: share_token.id,
+            description: share_token.description.clone(),
+            created_at: share_token.created_at,
+            expires_at: share_token.expires_at,
+            token_hash: share_token.token_hash.clone(),
+        }
+    }
+}
+
+impl From<StoredShareToken> for ShareToken {
+    fn from(share_token: StoredShareToken) -> Self {
+        Self {
+            id: share_token.id,
+            description: share_token.description,
+            created_at: share_token.created_at,
+            expires_at: share_token.expires_at,
+            token_hash: share_token.token_hash,
+        }
+    }
+}
+
+impl ShareToken {
+    /// Creates a share token and returns it together with the secret token.
+    pub fn new(description: Option<String>, expires_at: DateTime<Utc>) -> (Self, String) {
+        let token = Uuid::new_v4().simple().to_string();
+        (
+            Self {
+                id: Uuid::new_v4(),
+                description,
+                created_at: Utc::now(),
+                expires_at,
+                token_hash: hash(&token),
+            },
+            token,
+        )
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn expires_at(&self) -> &DateTime<Utc> {
+        &self.expires_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Returns `true` if the secret token belongs to this share token and did not expire.
+    pub fn grants_access(&self, token: &str) -> bool {
+        !self.is_expired() && self.token_hash == hash(token)
+    }
+}
+
+fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_grant_access_with_secret_token() {
+        let (share_token, token) = ShareToken::new(None, Utc::now() + chrono::Duration::hours(1));
+
+        assert!(share_token.grants_access(&token));
+        assert!(!share_token.grants_access("guessed-token"));
+    }
+
+    #[test]
+    fn should_not_grant_access_after_expiry() {
+        let (share_token, token) = ShareToken::new(None, Utc::now() - chrono::Duration::seconds(1));
+
+        assert!(!share_token.grants_access(&token));
+    }
+
+    #[test]
+    fn should_not_serialize_token_hash() {
+        let (share_token, _) = ShareToken::new(None, Utc::now() + chrono::Duration::hours(1));
+
+        let json = serde_json::to_value(&share_token).unwrap();
+
+        assert_eq!(json.get("tokenHash"), None);
+    }
+
+    #[test]
+    fn should_keep_token_hash_in_stored_form() {
+        let (share_token, token) = ShareToken::new(None, Utc::now() + chrono::Duration::hours(1));
+
+        let json = serde_json::to_string(&StoredShareToken::from(&share_token)).unwrap();
+        let stored = serde_json::from_str::<StoredShareToken>(&json).unwrap();
+
+        assert!(ShareToken::from(stored).grants_access(&token));
+    }
+}
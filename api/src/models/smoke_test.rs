@@ -0,0 +1,150 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::models::Image;
+use std::time::Duration;
+
+/// A smoke test that will be executed after all services of an app have been deployed. The
+/// deployment only succeeds if the HTTP checks and the container of the smoke test pass.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeTest {
+    image: Option<Image>,
+    #[serde(default)]
+    command: Vec<String>,
+    #[serde(default)]
+    http: Vec<HttpCheck>,
+    #[serde(default = "SmokeTest::default_timeout")]
+    timeout: u64,
+}
+
+impl SmokeTest {
+    fn default_timeout() -> u64 {
+        300
+    }
+
+    /// The image of the container that runs within the network of the app.
+    pub fn image(&self) -> Option<&Image> {
+        self.image.as_ref()
+    }
+
+    /// Overrides the command of the smoke test image.
+    pub fn command(&self) -> &[String] {
+        &self.command
+    }
+
+    pub fn http_checks(&self) -> &[HttpCheck] {
+        &self.http
+    }
+
+    /// The maximal time the smoke test may take until it is considered as failed.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+/// Requests a path of a service until the service responds with the expected status code.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpCheck {
+    service_name: String,
+    #[serde(default = "HttpCheck::default_path")]
+    path: String,
+    #[serde(default = "HttpCheck::default_expected_status")]
+    expected_status: u16,
+}
+
+impl HttpCheck {
+    fn default_path() -> String {
+        String::from("/")
+    }
+
+    fn default_expected_status() -> u16 {
+        200
+    }
+
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn expected_status(&self) -> u16 {
+        self.expected_status
+    }
+}
+
+/// The result of a smoke test run including the logs that have been captured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmokeTestOutcome {
+    passed: bool,
+    logs: String,
+}
+
+impl SmokeTestOutcome {
+    pub fn new(passed: bool, logs: String) -> Self {
+        Self { passed, logs }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+
+    pub fn logs(&self) -> &str {
+        &self.logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_parse_smoke_test_with_defaults() {
+        let smoke_test = serde_json::from_str::<SmokeTest>(
+            r#"{
+                "image": "curlimages/curl",
+                "command": ["curl", "-f", "http://frontend/health"],
+                "http": [{ "serviceName": "frontend" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            smoke_test.image(),
+            Some(&Image::from_str("curlimages/curl").unwrap())
+        );
+        assert_eq!(
+            smoke_test.command(),
+            &["curl", "-f", "http://frontend/health"]
+        );
+        assert_eq!(smoke_test.http_checks()[0].path(), "/");
+        assert_eq!(smoke_test.http_checks()[0].expected_status(), 200);
+        assert_eq!(smoke_test.timeout(), Duration::from_secs(300));
+    }
+}
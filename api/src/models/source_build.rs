@@ -0,0 +1,70 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use url::Url;
+
+/// Sources of a service in a Git repository from which PREvant builds the image of the service
+/// instead of deploying a prebuilt image.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceBuild {
+    service_name: String,
+    url: Url,
+    /// The branch, tag, or commit. Without it, the default branch of the repository is used.
+    #[serde(rename = "ref", default)]
+    reference: Option<String>,
+    #[serde(default)]
+    context: Option<String>,
+    #[serde(default)]
+    dockerfile: Option<String>,
+}
+
+impl SourceBuild {
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    /// The URL of the Git repository, e.g. `https://github.com/aixigo/PREvant.git`.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn reference(&self) -> Option<&str> {
+        self.reference.as_deref()
+    }
+
+    /// The directory of the repository that contains the sources of the image.
+    pub fn context(&self) -> &str {
+        self.context
+            .as_deref()
+            .map(|context| context.trim_matches('/'))
+            .unwrap_or_default()
+    }
+
+    /// The path of the Dockerfile relative to the context which is only used by Kaniko.
+    pub fn dockerfile(&self) -> &str {
+        self.dockerfile.as_deref().unwrap_or("Dockerfile")
+    }
+}
@@ -0,0 +1,107 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use std::iter::FromIterator;
+
+/// The severity of a vulnerability as reported by [Trivy](https://trivy.dev).
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Severity {
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// The number of vulnerabilities of an image per severity.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilitySummary {
+    critical: usize,
+    high: usize,
+    medium: usize,
+    low: usize,
+    unknown: usize,
+}
+
+impl VulnerabilitySummary {
+    pub fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Critical => self.critical += 1,
+            Severity::High => self.high += 1,
+            Severity::Medium => self.medium += 1,
+            Severity::Low => self.low += 1,
+            Severity::Unknown => self.unknown += 1,
+        }
+    }
+
+    /// Counts the vulnerabilities with the given severity or a higher one.
+    pub fn count_at_least(&self, severity: Severity) -> usize {
+        [
+            (Severity::Unknown, self.unknown),
+            (Severity::Low, self.low),
+            (Severity::Medium, self.medium),
+            (Severity::High, self.high),
+            (Severity::Critical, self.critical),
+        ]
+        .iter()
+        .filter(|(s, _)| s >= &severity)
+        .map(|(_, count)| count)
+        .sum()
+    }
+}
+
+impl FromIterator<Severity> for VulnerabilitySummary {
+    fn from_iter<I: IntoIterator<Item = Severity>>(iter: I) -> Self {
+        let mut summary = VulnerabilitySummary::default();
+        for severity in iter {
+            summary.add(severity);
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_vulnerabilities_at_least_high() {
+        let summary = vec![
+            Severity::Critical,
+            Severity::High,
+            Severity::High,
+            Severity::Low,
+        ]
+        .into_iter()
+        .collect::<VulnerabilitySummary>();
+
+        assert_eq!(summary.count_at_least(Severity::High), 3);
+        assert_eq!(summary.count_at_least(Severity::Critical), 1);
+        assert_eq!(summary.count_at_least(Severity::Unknown), 4);
+    }
+}
@@ -102,7 +102,7 @@ impl<'r> FromData<'r> for WebHookInfo {
     type Error = String;
 
     async fn from_data(
-        _request: &'r Request<'_>,
+        request: &'r Request<'_>,
         data: Data<'r>,
     ) -> data::Outcome<'r, Self, Self::Error> {
         let body = match data.open(2.mebibytes()).into_string().await {
@@ -115,6 +115,7 @@ impl<'r> FromData<'r> for WebHookInfo {
             }
             Err(e) => return data::Outcome::Error((Status::InternalServerError, e.to_string())),
         };
+        crate::audit::record_payload(request, body.as_bytes());
 
         let data = match from_str::<WebHookInfo>(&body) {
             Ok(v) => v,
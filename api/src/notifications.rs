@@ -0,0 +1,360 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::{
+    NetworkConfig, NotificationChannelConfig, NotificationEvent, NotificationTarget,
+};
+use crate::models::service::Service;
+use crate::models::AppName;
+use handlebars::Handlebars;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Describes a change of an app that will be announced through the notification channels.
+#[derive(Clone, Debug)]
+pub struct Notification {
+    event: NotificationEvent,
+    app_name: AppName,
+    metadata: BTreeMap<String, String>,
+    service_names: Vec<String>,
+    owner: Option<String>,
+    error: Option<String>,
+}
+
+impl Notification {
+    pub fn new(event: NotificationEvent, app_name: AppName) -> Self {
+        Self {
+            event,
+            app_name,
+            metadata: BTreeMap::new(),
+            service_names: Vec::new(),
+            owner: None,
+            error: None,
+        }
+    }
+
+    /// Describes the change of the app with the given services. The metadata of the app is taken
+    /// from the services.
+    pub fn for_services(event: NotificationEvent, app_name: AppName, services: &[Service]) -> Self {
+        let metadata = services
+            .first()
+            .map(|service| service.app_metadata().clone())
+            .unwrap_or_default();
+        let service_names = services
+            .iter()
+            .map(|service| service.service_name().clone())
+            .collect::<Vec<_>>();
+
+        Self::new(event, app_name)
+            .with_metadata(metadata)
+            .with_service_names(service_names)
+    }
+
+    /// The metadata of the app, e.g. the branch or the committer, that will be included in the
+    /// messages.
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_service_names(mut self, service_names: Vec<String>) -> Self {
+        self.service_names = service_names;
+        self
+    }
+
+    /// The user that owns the app, e.g. to mention the user in the message.
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    pub fn with_error(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Renders the message for the channel with the channel's template.
+    fn message(&self, channel: &NotificationChannelConfig) -> Result<String, NotificationError> {
+        let services = self
+            .service_names
+            .iter()
+            .map(|service_name| {
+                serde_json::json!({
+                    "name": service_name,
+                    "url": channel
+                        .base_url()
+                        .and_then(|base_url| service_url(base_url, &self.app_name, service_name)),
+                })
+            })
+            .collect::<Vec<_>>();
+        let parameters = serde_json::json!({
+            "event": self.event,
+            "application": {
+                "name": self.app_name,
+                "url": channel.base_url(),
+                "metadata": self.metadata,
+                "owner": self.owner,
+            },
+            "services": services,
+            "error": self.error,
+        });
+
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(handlebars::no_escape);
+        handlebars
+            .render_template(channel.template(self.event), &parameters)
+            .map_err(|err| NotificationError::InvalidTemplate {
+                event: self.event,
+                err: err.to_string(),
+            })
+    }
+}
+
+/// Posts the notification to all channels that subscribed to the event of the app. Failing
+/// channels are logged and don't affect the other channels.
+pub async fn send_notifications(
+    network: &NetworkConfig,
+    channels: &[NotificationChannelConfig],
+    notification: &Notification,
+) {
+    let client = match network.http_client_builder().build() {
+        Ok(client) => client,
+        Err(err) => {
+            warn!(
+                "Cannot notify about the {} app {}: {}",
+                notification.event, notification.app_name, err
+            );
+            return;
+        }
+    };
+
+    for channel in channels
+        .iter()
+        .filter(|channel| channel.subscribes_to(notification.event, &notification.app_name))
+    {
+        if let Err(err) = send_notification(&client, channel, notification).await {
+            warn!(
+                "Cannot notify about the {} app {}: {}",
+                notification.event, notification.app_name, err
+            );
+        }
+    }
+}
+
+async fn send_notification(
+    client: &reqwest::Client,
+    channel: &NotificationChannelConfig,
+    notification: &Notification,
+) -> Result<(), NotificationError> {
+    let message = notification.message(channel)?;
+
+    let (request, url) = match channel.target() {
+        NotificationTarget::Slack { webhook_url } | NotificationTarget::Teams { webhook_url } => {
+            let url = parse_url(webhook_url.unsecure())?;
+            (
+                client
+                    .post(url.clone())
+                    .json(&serde_json::json!({ "text": message })),
+                url,
+            )
+        }
+        NotificationTarget::Matrix {
+            homeserver,
+            room_id,
+            access_token,
+        } => {
+            let url = matrix_send_url(homeserver, room_id)?;
+            (
+                client
+                    .put(url.clone())
+                    .bearer_auth(access_token.unsecure())
+                    .json(&serde_json::json!({ "msgtype": "m.text", "body": message })),
+                url,
+            )
+        }
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|err| NotificationError::RequestFailed {
+            host: host_of(&url),
+            err: err.to_string(),
+        })?;
+    if !response.status().is_success() {
+        let status = response.status();
+        return Err(NotificationError::RequestFailed {
+            host: host_of(&url),
+            err: format!("{}: {}", status, response.text().await.unwrap_or_default()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Each message requires a unique transaction id, see
+/// [Matrix' client-server API](https://spec.matrix.org/v1.8/client-server-api/#put_matrixclientv3roomsroomidsendeventtypetxnid).
+fn matrix_send_url(homeserver: &Url, room_id: &str) -> Result<Url, NotificationError> {
+    let mut url = homeserver.clone();
+    url.path_segments_mut()
+        .map_err(|_| NotificationError::InvalidUrl {
+            url: homeserver.to_string(),
+        })?
+        .pop_if_empty()
+        .extend(&[
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            room_id,
+            "send",
+            "m.room.message",
+            &uuid::Uuid::new_v4().to_string(),
+        ]);
+    Ok(url)
+}
+
+/// Appends the app and the service to the path of the base URL, so that a base path, e.g. of a
+/// PREvant behind a reverse proxy, is kept.
+fn service_url(base_url: &Url, app_name: &AppName, service_name: &str) -> Option<Url> {
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .push(app_name.as_str())
+        .push(service_name)
+        .push("");
+    Some(url)
+}
+
+fn parse_url(url: &str) -> Result<Url, NotificationError> {
+    Url::parse(url).map_err(|_| NotificationError::InvalidUrl {
+        url: url.split('/').take(3).collect::<Vec<_>>().join("/"),
+    })
+}
+
+/// Webhook URLs contain credentials and must not end up in the logs.
+fn host_of(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_string()
+}
+
+#[derive(Debug, Clone, Fail)]
+pub enum NotificationError {
+    #[fail(display = "Invalid URL of notification channel: {}", url)]
+    InvalidUrl { url: String },
+    #[fail(display = "Cannot render the message of the {} event: {}", event, err)]
+    InvalidTemplate {
+        event: NotificationEvent,
+        err: String,
+    },
+    #[fail(display = "Cannot post the message to {}: {}", host, err)]
+    RequestFailed { host: String, err: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn channel(toml: &str) -> NotificationChannelConfig {
+        toml::de::from_str::<NotificationChannelConfig>(toml).unwrap()
+    }
+
+    #[test]
+    fn should_render_default_message_with_preview_urls() {
+        let channel = channel(
+            r#"
+            type = 'Slack'
+            webhookUrl = 'https://hooks.slack.com/services/T/B/X'
+            baseUrl = 'https://prevant.example.com'
+            "#,
+        );
+        let notification = Notification::new(
+            NotificationEvent::Created,
+            AppName::from_str("feature-1").unwrap(),
+        )
+        .with_service_names(vec![String::from("api")])
+        .with_metadata(BTreeMap::from([(
+            String::from("committer"),
+            String::from("jane"),
+        )]));
+
+        assert_eq!(
+            notification.message(&channel).unwrap(),
+            "The app feature-1 has been deployed. api: https://prevant.example.com/feature-1/api/ committer: jane"
+        );
+    }
+
+    #[test]
+    fn should_keep_base_path_of_preview_urls() {
+        let url = service_url(
+            &Url::parse("https://example.com/prevant").unwrap(),
+            &AppName::from_str("feature-1").unwrap(),
+            "api",
+        );
+
+        assert_eq!(
+            url.map(String::from),
+            Some(String::from("https://example.com/prevant/feature-1/api/"))
+        );
+    }
+
+    #[test]
+    fn should_render_template_override() {
+        let channel = channel(
+            r#"
+            type = 'Teams'
+            webhookUrl = 'https://example.webhook.office.com/webhookb2/x'
+
+            [templates]
+            failed = '{{application.name}} ({{event}}): {{error}}'
+            "#,
+        );
+        let notification = Notification::new(
+            NotificationEvent::Failed,
+            AppName::from_str("feature-1").unwrap(),
+        )
+        .with_error(String::from("image not found"));
+
+        assert_eq!(
+            notification.message(&channel).unwrap(),
+            "feature-1 (failed): image not found"
+        );
+    }
+
+    #[test]
+    fn should_build_matrix_url() {
+        let url = matrix_send_url(
+            &Url::parse("https://matrix.example.com/").unwrap(),
+            "!review:example.com",
+        )
+        .unwrap();
+
+        assert!(url.as_str().starts_with(
+            "https://matrix.example.com/_matrix/client/v3/rooms/!review:example.com/send/m.room.message/"
+        ));
+    }
+}
@@ -28,14 +28,15 @@ use crate::config::Config;
 use crate::models::Image;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
-use oci_distribution::client::ClientConfig;
+use oci_distribution::client::{Certificate, CertificateEncoding, ClientConfig};
 use oci_distribution::errors::OciDistributionError;
 use oci_distribution::secrets::RegistryAuth;
 use oci_distribution::{Client, Reference};
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::convert::From;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
 pub struct Registry<'a> {
     config: &'a Config,
@@ -98,8 +99,24 @@ impl<'a> Registry<'a> {
     ) -> Result<(&'i Image, ImageInfo), (&'i Image, OciDistributionError)> {
         debug!("Resolve image manifest for {:?}", image);
 
+        // The platforms of multi-arch images are only visible in the image index which is consumed
+        // by the platform resolver. Thus, the resolver records them while choosing the manifest.
+        let index_platforms = Arc::new(Mutex::new(BTreeSet::new()));
+        let recorded_platforms = index_platforms.clone();
+
         let mut client = Client::new(ClientConfig {
-            platform_resolver: Some(Box::new(|entries| {
+            platform_resolver: Some(Box::new(move |entries| {
+                if let Ok(mut platforms) = recorded_platforms.lock() {
+                    platforms.extend(
+                        entries
+                            .iter()
+                            .filter_map(|entry| entry.platform.as_ref())
+                            .filter_map(|platform| {
+                                ImagePlatform::new(&platform.os, &platform.architecture)
+                            }),
+                    );
+                }
+
                 oci_distribution::client::current_platform_resolver(entries).or(
                     // There are cases where current_platform_resolver fails, e.g. in tests on
                     // MacOS. However it is not safe to assume the current platform that PREvant
@@ -115,6 +132,15 @@ impl<'a> Registry<'a> {
                     entries.first().map(|e| e.digest.clone()),
                 )
             })),
+            extra_root_certificates: config
+                .network_config()
+                .extra_ca_certificates()
+                .into_iter()
+                .map(|data| Certificate {
+                    encoding: CertificateEncoding::Pem,
+                    data,
+                })
+                .collect(),
             ..Default::default()
         });
 
@@ -126,14 +152,31 @@ impl<'a> Registry<'a> {
             .await
             .map_err(|err| (image, dbg!(err)))?;
 
+        let index_platforms = index_platforms
+            .lock()
+            .map(|platforms| platforms.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
         let blob = match serde_json::from_str::<ImageBlob>(&config) {
-            Ok(blob) => ImageInfo {
-                blob: Some(blob),
-                digest,
-            },
+            Ok(blob) => {
+                let platforms = if index_platforms.is_empty() {
+                    blob.platform().into_iter().collect()
+                } else {
+                    index_platforms
+                };
+                ImageInfo {
+                    blob: Some(blob),
+                    digest,
+                    platforms,
+                }
+            }
             Err(err) => {
                 warn!("Cannot parse manifest blob for {image}: {err}");
-                ImageInfo { blob: None, digest }
+                ImageInfo {
+                    blob: None,
+                    digest,
+                    platforms: index_platforms,
+                }
             }
         };
 
@@ -154,6 +197,7 @@ impl<'a> Registry<'a> {
 pub struct ImageInfo {
     blob: Option<ImageBlob>,
     digest: String,
+    platforms: Vec<ImagePlatform>,
 }
 
 impl ImageInfo {
@@ -171,14 +215,65 @@ impl ImageInfo {
             None => Vec::new(),
         }
     }
+
+    /// The platforms the image has been built for. For multi-arch images these are the platforms
+    /// of the image index and for single-arch images the platform of the image config. The list
+    /// is empty if the registry does not provide this information.
+    pub fn platforms(&self) -> &Vec<ImagePlatform> {
+        &self.platforms
+    }
+}
+
+/// The operating system and the CPU architecture an image can run on, e.g. `linux/arm64`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ImagePlatform {
+    os: String,
+    architecture: String,
+}
+
+impl ImagePlatform {
+    fn new(os: &str, architecture: &str) -> Option<Self> {
+        // Attestation manifests, e.g. created by buildx, are listed with the unknown platform.
+        if os.is_empty() || architecture.is_empty() || os == "unknown" || architecture == "unknown"
+        {
+            return None;
+        }
+
+        Some(Self {
+            os: os.to_string(),
+            architecture: architecture.to_string(),
+        })
+    }
+
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    pub fn architecture(&self) -> &str {
+        &self.architecture
+    }
+}
+
+impl std::fmt::Display for ImagePlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct ImageBlob {
     config: ImageConfig,
+    #[serde(default)]
+    os: Option<String>,
+    #[serde(default)]
+    architecture: Option<String>,
 }
 
 impl ImageBlob {
+    fn platform(&self) -> Option<ImagePlatform> {
+        ImagePlatform::new(self.os.as_deref()?, self.architecture.as_deref()?)
+    }
+
     pub fn exposed_port(&self) -> Option<u16> {
         self.config.exposed_port()
     }
@@ -332,4 +427,28 @@ mod tests {
 
         assert!(blob.declared_volumes().is_empty());
     }
+
+    #[test]
+    fn should_return_platform_of_image_config() {
+        let blob = serde_json::from_str::<ImageBlob>(
+            r#"{
+                "architecture": "arm64",
+                "os": "linux",
+                "config": {
+                    "Hostname": "837a64dcc771"
+                } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            blob.platform().map(|platform| platform.to_string()),
+            Some(String::from("linux/arm64"))
+        );
+    }
+
+    #[test]
+    fn should_ignore_unknown_platforms() {
+        assert_eq!(ImagePlatform::new("unknown", "unknown"), None);
+        assert_eq!(ImagePlatform::new("linux", ""), None);
+    }
 }
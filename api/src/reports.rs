@@ -0,0 +1,303 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::Apps;
+use crate::http_result::HttpResult;
+use crate::models::service::Service;
+use crate::models::{AppName, CostEstimate};
+use http_api_problem::{HttpApiProblem, StatusCode};
+use multimap::MultiMap;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// A running image and the services of the apps that run it.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageReport {
+    image: String,
+    digest: Option<String>,
+    usages: Vec<ImageUsage>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageUsage {
+    app_name: String,
+    service_name: String,
+    image: String,
+}
+
+/// The estimated costs of all apps and their sum.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostReport {
+    #[serde(flatten)]
+    total: CostEstimate,
+    apps: Vec<AppCosts>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppCosts {
+    app_name: String,
+    #[serde(flatten)]
+    costs: CostEstimate,
+    services: Vec<ServiceCosts>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceCosts {
+    service_name: String,
+    #[serde(flatten)]
+    costs: CostEstimate,
+}
+
+/// Aggregates all running images across the apps, grouped by image reference and digest.
+#[get("/reports/images", format = "application/json")]
+pub async fn images(apps: &State<Arc<Apps>>) -> HttpResult<Json<Vec<ImageReport>>> {
+    let apps = apps.get_apps().await?;
+    Ok(Json(image_reports(&apps)))
+}
+
+/// Lists the services of all apps that run the image with the given digest, e.g.
+/// `sha256:9895c9b90b58c9490471b877f6bb6a90e6bdc154da7fbb526a0322ea242fc913`.
+#[get("/reports/images/<digest>/apps", format = "application/json")]
+pub async fn apps_of_image(
+    digest: String,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<ImageUsage>>> {
+    let apps = apps.get_apps().await?;
+    Ok(Json(usages_of_digest(&apps, &digest)))
+}
+
+/// Estimates the costs of all apps based on the configured prices of the resources.
+#[get("/reports/costs", format = "application/json")]
+pub async fn costs(apps: &State<Arc<Apps>>) -> HttpResult<Json<CostReport>> {
+    let costs = apps.costs_config().ok_or_else(|| {
+        HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail("The estimation of costs has not been configured.")
+    })?;
+
+    let apps = apps.get_apps().await?;
+    Ok(Json(cost_report(&apps, costs.currency())))
+}
+
+fn image_reports(apps: &MultiMap<AppName, Service>) -> Vec<ImageReport> {
+    let mut reports = BTreeMap::<(String, Option<String>), Vec<ImageUsage>>::new();
+
+    for (app_name, services) in apps.iter_all() {
+        for service in services {
+            let image = service.config().image().to_string();
+            reports
+                .entry((image, service.image_digest().cloned()))
+                .or_default()
+                .push(ImageUsage::new(app_name, service));
+        }
+    }
+
+    reports
+        .into_iter()
+        .map(|((image, digest), mut usages)| {
+            usages.sort_by(|u1, u2| {
+                (&u1.app_name, &u1.service_name).cmp(&(&u2.app_name, &u2.service_name))
+            });
+            ImageReport {
+                image,
+                digest,
+                usages,
+            }
+        })
+        .collect()
+}
+
+fn usages_of_digest(apps: &MultiMap<AppName, Service>, digest: &str) -> Vec<ImageUsage> {
+    let digest = normalize_digest(digest);
+
+    let mut usages = apps
+        .iter_all()
+        .flat_map(|(app_name, services)| {
+            services
+                .iter()
+                .filter(|service| {
+                    service
+                        .image_digest()
+                        .map(|d| normalize_digest(d) == digest)
+                        .unwrap_or(false)
+                })
+                .map(move |service| ImageUsage::new(app_name, service))
+        })
+        .collect::<Vec<_>>();
+
+    usages
+        .sort_by(|u1, u2| (&u1.app_name, &u1.service_name).cmp(&(&u2.app_name, &u2.service_name)));
+    usages
+}
+
+fn cost_report(apps: &MultiMap<AppName, Service>, currency: &str) -> CostReport {
+    let mut app_costs = apps
+        .iter_all()
+        .map(|(app_name, services)| {
+            let mut services = services
+                .iter()
+                .filter_map(|service| {
+                    Some(ServiceCosts {
+                        service_name: service.service_name().clone(),
+                        costs: service.cost_estimate()?.clone(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            services.sort_by(|s1, s2| s1.service_name.cmp(&s2.service_name));
+
+            AppCosts {
+                app_name: app_name.to_string(),
+                costs: CostEstimate::from_hourly(currency.to_string(), 0.0)
+                    + services
+                        .iter()
+                        .map(|service| &service.costs)
+                        .sum::<CostEstimate>(),
+                services,
+            }
+        })
+        .collect::<Vec<_>>();
+    app_costs.sort_by(|a1, a2| a1.app_name.cmp(&a2.app_name));
+
+    CostReport {
+        total: CostEstimate::from_hourly(currency.to_string(), 0.0)
+            + app_costs.iter().map(|app| &app.costs).sum::<CostEstimate>(),
+        apps: app_costs,
+    }
+}
+
+/// Digests can be provided with or without the algorithm prefix.
+fn normalize_digest(digest: &str) -> String {
+    let digest = digest.trim().to_lowercase();
+    match digest.strip_prefix("sha256:") {
+        Some(hash) => hash.to_string(),
+        None => digest,
+    }
+}
+
+impl ImageUsage {
+    fn new(app_name: &AppName, service: &Service) -> Self {
+        Self {
+            app_name: app_name.to_string(),
+            service_name: service.service_name().clone(),
+            image: service.config().image().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ServiceBuilder;
+    use crate::sc;
+    use std::str::FromStr;
+
+    fn apps() -> MultiMap<AppName, Service> {
+        let mut apps = MultiMap::new();
+        for (app_name, service_name, image, digest) in [
+            ("master", "api", "api:1.0", "sha256:aaa"),
+            ("feature-1", "api", "api:1.0", "sha256:aaa"),
+            ("feature-2", "api", "api:1.1", "sha256:bbb"),
+        ] {
+            apps.insert(
+                AppName::from_str(app_name).unwrap(),
+                ServiceBuilder::new()
+                    .id(format!("{}-{}", app_name, service_name))
+                    .app_name(app_name.to_string())
+                    .config(sc!(service_name, image))
+                    .image_digest(digest.to_string())
+                    .build()
+                    .unwrap(),
+            );
+        }
+        apps
+    }
+
+    #[test]
+    fn aggregate_images_across_apps() {
+        let reports = image_reports(&apps());
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].image, "docker.io/library/api:1.0");
+        assert_eq!(reports[0].digest, Some(String::from("sha256:aaa")));
+        assert_eq!(
+            reports[0]
+                .usages
+                .iter()
+                .map(|usage| usage.app_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["feature-1", "master"]
+        );
+    }
+
+    #[test]
+    fn sum_costs_of_apps() {
+        let mut apps = MultiMap::new();
+        for (app_name, service_name, hourly) in [
+            ("master", "api", 0.01),
+            ("master", "db", 0.02),
+            ("feature-1", "api", 0.01),
+        ] {
+            apps.insert(
+                AppName::from_str(app_name).unwrap(),
+                ServiceBuilder::new()
+                    .id(format!("{}-{}", app_name, service_name))
+                    .app_name(app_name.to_string())
+                    .config(sc!(service_name, "api:1.0"))
+                    .cost_estimate(CostEstimate::from_hourly(String::from("EUR"), hourly))
+                    .build()
+                    .unwrap(),
+            );
+        }
+
+        let report = cost_report(&apps, "EUR");
+
+        assert_eq!(report.total.hourly(), 0.04);
+        assert_eq!(report.total.monthly(), 29.2);
+        assert_eq!(report.apps[0].app_name, "feature-1");
+        assert_eq!(report.apps[1].app_name, "master");
+        assert_eq!(report.apps[1].costs.hourly(), 0.03);
+        assert_eq!(report.apps[1].services.len(), 2);
+    }
+
+    #[test]
+    fn find_apps_by_digest_without_algorithm() {
+        let usages = usages_of_digest(&apps(), "bbb");
+
+        assert_eq!(
+            usages,
+            vec![ImageUsage {
+                app_name: String::from("feature-2"),
+                service_name: String::from("api"),
+                image: String::from("docker.io/library/api:1.1"),
+            }]
+        );
+    }
+}
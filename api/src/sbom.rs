@@ -0,0 +1,127 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::Config;
+use crate::models::{Image, Sbom};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::process::Command;
+
+/// Generates SBOMs of images with [Syft](https://github.com/anchore/syft) and caches them by the
+/// digests of the images so that images that are shared by several apps are only analyzed once.
+pub struct SbomGenerator {
+    sboms: Mutex<HashMap<String, Sbom>>,
+}
+
+impl SbomGenerator {
+    pub fn new() -> Self {
+        Self {
+            sboms: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Generates the SBOM of the image with the configured Syft executable. Images are pulled
+    /// directly from their registries so that Syft does not require a container runtime.
+    pub async fn generate(
+        &self,
+        config: &Config,
+        image: &Image,
+        digest: Option<&String>,
+    ) -> Result<Option<Sbom>, SbomError> {
+        let sbom_config = match config.sbom_config() {
+            Some(sbom_config) => sbom_config,
+            None => return Ok(None),
+        };
+
+        if let Some(sbom) =
+            digest.and_then(|digest| self.sboms.lock().unwrap().get(digest).cloned())
+        {
+            if sbom.format() == sbom_config.format() {
+                return Ok(Some(sbom));
+            }
+        }
+
+        debug!("Generate the SBOM of {}", image);
+
+        let mut command = Command::new(sbom_config.command());
+        command
+            .arg(format!("registry:{}", image))
+            .args(["--quiet", "--output"])
+            .arg(sbom_config.format().to_string());
+        if let Some((registry, (username, password))) = image.registry().and_then(|registry| {
+            config
+                .registry_credentials(&registry)
+                .map(|credentials| (registry.clone(), credentials))
+        }) {
+            command
+                .env("SYFT_REGISTRY_AUTH_AUTHORITY", registry)
+                .env("SYFT_REGISTRY_AUTH_USERNAME", username)
+                .env("SYFT_REGISTRY_AUTH_PASSWORD", password.unsecure());
+        }
+
+        let output = command
+            .output()
+            .await
+            .map_err(|err| SbomError::CannotRunSyft {
+                err: err.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(SbomError::GenerationFailed {
+                image: image.to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        let document =
+            serde_json::from_slice(&output.stdout).map_err(|err| SbomError::GenerationFailed {
+                image: image.to_string(),
+                message: format!("Cannot parse SBOM: {}", err),
+            })?;
+        let sbom = Sbom::new(
+            image.to_string(),
+            digest.cloned(),
+            sbom_config.format(),
+            document,
+        );
+
+        if let Some(digest) = digest {
+            self.sboms
+                .lock()
+                .unwrap()
+                .insert(digest.clone(), sbom.clone());
+        }
+
+        Ok(Some(sbom))
+    }
+}
+
+#[derive(Debug, Clone, Fail)]
+pub enum SbomError {
+    #[fail(display = "Cannot run syft: {}", err)]
+    CannotRunSyft { err: String },
+    #[fail(display = "Cannot generate the SBOM of image {}: {}", image, message)]
+    GenerationFailed { image: String, message: String },
+}
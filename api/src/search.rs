@@ -0,0 +1,233 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::apps::Apps;
+use crate::http_result::HttpResult;
+use crate::models::service::Service;
+use crate::models::AppName;
+use http_api_problem::{HttpApiProblem, StatusCode};
+use multimap::MultiMap;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::sync::Arc;
+
+/// A match of a search query. The type of the hit denotes which part of the app matched.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum SearchHit {
+    #[serde(rename_all = "camelCase")]
+    App { app_name: String },
+    #[serde(rename_all = "camelCase")]
+    Service {
+        app_name: String,
+        service_name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Image {
+        app_name: String,
+        service_name: String,
+        image: String,
+    },
+    /// A label or annotation of a service or, without service name, the metadata of the app
+    #[serde(rename_all = "camelCase")]
+    Label {
+        app_name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        service_name: Option<String>,
+        key: String,
+        value: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    EnvKey {
+        app_name: String,
+        service_name: String,
+        key: String,
+    },
+}
+
+/// Searches case-insensitively through app names, service names, image references, labels
+/// and the keys of environment variables. The values of environment variables are never
+/// searched because they might contain secrets.
+#[get("/search?<q>", format = "application/json")]
+pub async fn search(
+    q: Option<String>,
+    apps: &State<Arc<Apps>>,
+) -> HttpResult<Json<Vec<SearchHit>>> {
+    let q = match q {
+        Some(q) if !q.trim().is_empty() => q,
+        _ => {
+            return Err(HttpApiProblem::with_title_and_type(StatusCode::BAD_REQUEST)
+                .detail("The query parameter q must not be empty")
+                .into())
+        }
+    };
+
+    let apps = apps.get_apps().await?;
+    Ok(Json(find_hits(&apps, q.trim())))
+}
+
+fn find_hits(apps: &MultiMap<AppName, Service>, q: &str) -> Vec<SearchHit> {
+    let q = q.to_lowercase();
+    let matches = |s: &str| s.to_lowercase().contains(&q);
+
+    let mut app_names = apps.keys().collect::<Vec<_>>();
+    app_names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+    let mut hits = Vec::new();
+    for app_name in app_names {
+        if matches(app_name.as_str()) {
+            hits.push(SearchHit::App {
+                app_name: app_name.to_string(),
+            });
+        }
+
+        let services = apps.get_vec(app_name).map(Vec::as_slice).unwrap_or(&[]);
+
+        if let Some(service) = services.first() {
+            for (key, value) in service.app_metadata() {
+                if matches(key) || matches(value) {
+                    hits.push(SearchHit::Label {
+                        app_name: app_name.to_string(),
+                        service_name: None,
+                        key: key.clone(),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+
+        for service in services {
+            let service_name = service.service_name();
+            if matches(service_name) {
+                hits.push(SearchHit::Service {
+                    app_name: app_name.to_string(),
+                    service_name: service_name.clone(),
+                });
+            }
+
+            let image = service.config().image().to_string();
+            if matches(&image) {
+                hits.push(SearchHit::Image {
+                    app_name: app_name.to_string(),
+                    service_name: service_name.clone(),
+                    image,
+                });
+            }
+
+            let labels = service
+                .config()
+                .metadata()
+                .into_iter()
+                .flat_map(|metadata| metadata.labels().iter().chain(metadata.annotations()));
+            for (key, value) in labels {
+                if matches(key) || matches(value) {
+                    hits.push(SearchHit::Label {
+                        app_name: app_name.to_string(),
+                        service_name: Some(service_name.clone()),
+                        key: key.clone(),
+                        value: value.clone(),
+                    });
+                }
+            }
+
+            for env in service
+                .config()
+                .env()
+                .into_iter()
+                .flat_map(|env| env.iter())
+            {
+                if matches(env.key()) {
+                    hits.push(SearchHit::EnvKey {
+                        app_name: app_name.to_string(),
+                        service_name: service_name.clone(),
+                        key: env.key().to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Environment, EnvironmentVariable, ServiceBuilder};
+    use crate::sc;
+    use secstr::SecUtf8;
+    use std::str::FromStr;
+
+    fn apps() -> MultiMap<AppName, Service> {
+        let mut config = sc!("db", "postgres:15.3");
+        config.set_env(Some(Environment::new(vec![EnvironmentVariable::new(
+            String::from("POSTGRES_PASSWORD"),
+            SecUtf8::from("postgres-secret"),
+        )])));
+
+        let mut apps = MultiMap::new();
+        apps.insert(
+            AppName::from_str("postgres-upgrade").unwrap(),
+            ServiceBuilder::new()
+                .id(String::from("db"))
+                .app_name(String::from("postgres-upgrade"))
+                .config(config)
+                .build()
+                .unwrap(),
+        );
+        apps
+    }
+
+    #[test]
+    fn find_typed_hits() {
+        let hits = find_hits(&apps(), "POSTGRES");
+
+        assert_eq!(
+            hits,
+            vec![
+                SearchHit::App {
+                    app_name: String::from("postgres-upgrade")
+                },
+                SearchHit::Image {
+                    app_name: String::from("postgres-upgrade"),
+                    service_name: String::from("db"),
+                    image: String::from("docker.io/library/postgres:15.3")
+                },
+                SearchHit::EnvKey {
+                    app_name: String::from("postgres-upgrade"),
+                    service_name: String::from("db"),
+                    key: String::from("POSTGRES_PASSWORD")
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn ignore_values_of_environment_variables() {
+        let hits = find_hits(&apps(), "secret");
+
+        assert!(hits.is_empty());
+    }
+}
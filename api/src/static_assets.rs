@@ -0,0 +1,257 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::{NetworkConfig, S3Config, StaticAssetsStorage};
+use crate::log_archive::{s3_authorization, s3_object_url};
+use crate::models::AppName;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Stores the uploaded bundle of the service, replacing a previously uploaded bundle.
+pub async fn store_bundle(
+    network: &NetworkConfig,
+    storage: &StaticAssetsStorage,
+    app_name: &AppName,
+    service_name: &str,
+    bundle: Vec<u8>,
+) -> Result<(), StaticAssetsError> {
+    match storage {
+        StaticAssetsStorage::Directory { path } => {
+            let file = bundle_path(path, app_name, service_name);
+            if let Some(parent) = file.parent() {
+                tokio::fs::create_dir_all(parent).await.map_err(|err| {
+                    StaticAssetsError::StorageFailed {
+                        err: err.to_string(),
+                    }
+                })?;
+            }
+            tokio::fs::write(&file, bundle)
+                .await
+                .map_err(|err| StaticAssetsError::StorageFailed {
+                    err: err.to_string(),
+                })
+        }
+        StaticAssetsStorage::S3(s3) => {
+            let payload_hash = hex::encode(Sha256::digest(&bundle));
+            let request =
+                s3_request(network, s3, "PUT", app_name, service_name, &payload_hash)?.body(bundle);
+            send(request).await.map(|_| ())
+        }
+    }
+}
+
+/// Loads the bundle of the service or `None` if no bundle has been uploaded.
+pub async fn load_bundle(
+    network: &NetworkConfig,
+    storage: &StaticAssetsStorage,
+    app_name: &AppName,
+    service_name: &str,
+) -> Result<Option<Vec<u8>>, StaticAssetsError> {
+    match storage {
+        StaticAssetsStorage::Directory { path } => {
+            match tokio::fs::read(bundle_path(path, app_name, service_name)).await {
+                Ok(bundle) => Ok(Some(bundle)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(StaticAssetsError::StorageFailed {
+                    err: err.to_string(),
+                }),
+            }
+        }
+        StaticAssetsStorage::S3(s3) => {
+            let request = s3_request(
+                network,
+                s3,
+                "GET",
+                app_name,
+                service_name,
+                EMPTY_PAYLOAD_HASH,
+            )?;
+            let Some(response) = send(request).await? else {
+                return Ok(None);
+            };
+            let bundle =
+                response
+                    .bytes()
+                    .await
+                    .map_err(|err| StaticAssetsError::StorageFailed {
+                        err: err.to_string(),
+                    })?;
+            Ok(Some(bundle.to_vec()))
+        }
+    }
+}
+
+/// Removes the bundles of the services, e.g. when the app is deleted.
+pub async fn remove_bundles(
+    network: &NetworkConfig,
+    storage: &StaticAssetsStorage,
+    app_name: &AppName,
+    service_names: &[String],
+) -> Result<(), StaticAssetsError> {
+    match storage {
+        StaticAssetsStorage::Directory { path } => {
+            match tokio::fs::remove_dir_all(path.join(app_name.as_str())).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    Err(StaticAssetsError::StorageFailed {
+                        err: err.to_string(),
+                    })
+                }
+                _ => Ok(()),
+            }
+        }
+        StaticAssetsStorage::S3(s3) => {
+            for service_name in service_names {
+                let request = s3_request(
+                    network,
+                    s3,
+                    "DELETE",
+                    app_name,
+                    service_name,
+                    EMPTY_PAYLOAD_HASH,
+                )?;
+                send(request).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The hex encoded SHA-256 digest of the bundle.
+pub fn digest(bundle: &[u8]) -> String {
+    hex::encode(Sha256::digest(bundle))
+}
+
+fn bundle_path(path: &std::path::Path, app_name: &AppName, service_name: &str) -> PathBuf {
+    path.join(app_name.as_str())
+        .join(format!("{service_name}.bundle"))
+}
+
+/// The SHA-256 digest of an empty body.
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+fn s3_request(
+    network: &NetworkConfig,
+    config: &S3Config,
+    method: &str,
+    app_name: &AppName,
+    service_name: &str,
+    payload_hash: &str,
+) -> Result<reqwest::RequestBuilder, StaticAssetsError> {
+    let key = format!(
+        "{}static-assets/{}/{}.bundle",
+        config.prefix(),
+        app_name,
+        service_name
+    );
+    let url = s3_object_url(config, &key).map_err(|err| StaticAssetsError::StorageFailed {
+        err: err.to_string(),
+    })?;
+
+    let now = Utc::now();
+    let authorization = s3_authorization(config, method, &url, payload_hash, &now);
+    let method =
+        reqwest::Method::from_bytes(method.as_bytes()).expect("The HTTP method should be valid");
+
+    let client =
+        network
+            .http_client_builder()
+            .build()
+            .map_err(|err| StaticAssetsError::StorageFailed {
+                err: err.to_string(),
+            })?;
+
+    Ok(client
+        .request(method, url)
+        .header("x-amz-content-sha256", payload_hash)
+        .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+        .header("Authorization", authorization))
+}
+
+/// Sends the request to S3 and returns `None` if the object does not exist.
+async fn send(
+    request: reqwest::RequestBuilder,
+) -> Result<Option<reqwest::Response>, StaticAssetsError> {
+    let response = request
+        .send()
+        .await
+        .map_err(|err| StaticAssetsError::StorageFailed {
+            err: err.to_string(),
+        })?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(StaticAssetsError::StorageFailed {
+            err: format!("{}: {}", status, response.text().await.unwrap_or_default()),
+        });
+    }
+
+    Ok(Some(response))
+}
+
+#[derive(Debug, Clone, Fail)]
+pub enum StaticAssetsError {
+    #[fail(display = "Cannot access the stored static assets: {}", err)]
+    StorageFailed { err: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn should_store_and_remove_bundles_in_directory() -> Result<(), StaticAssetsError> {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StaticAssetsStorage::Directory {
+            path: dir.path().to_path_buf(),
+        };
+        let app_name = AppName::from_str("feature-1").unwrap();
+        let network = NetworkConfig::default();
+
+        assert_eq!(
+            load_bundle(&network, &storage, &app_name, "web").await?,
+            None
+        );
+
+        store_bundle(&network, &storage, &app_name, "web", b"bundle".to_vec()).await?;
+        assert_eq!(
+            load_bundle(&network, &storage, &app_name, "web").await?,
+            Some(b"bundle".to_vec())
+        );
+
+        remove_bundles(&network, &storage, &app_name, &[String::from("web")]).await?;
+        assert_eq!(
+            load_bundle(&network, &storage, &app_name, "web").await?,
+            None
+        );
+
+        Ok(())
+    }
+}
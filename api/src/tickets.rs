@@ -25,11 +25,11 @@
  */
 
 use crate::apps::Apps;
-use crate::config::Config;
+use crate::config::{ConfigHandle, JiraAuth};
 use crate::http_result::{HttpApiError, HttpResult};
 use crate::models::ticket_info::TicketInfo;
 use http_api_problem::{HttpApiProblem, StatusCode};
-use jira_query::{JiraInstance, JiraQueryError};
+use jira_query::Issue;
 use rocket::serde::json::Json;
 use rocket::State;
 use std::collections::HashMap;
@@ -40,12 +40,13 @@ use std::sync::Arc;
 /// corresponding `TicketInfo`.
 #[get("/apps/tickets", format = "application/json")]
 pub async fn tickets(
-    config_state: &State<Config>,
+    config_state: &State<ConfigHandle>,
     apps_service: &State<Arc<Apps>>,
 ) -> HttpResult<Json<HashMap<String, TicketInfo>>> {
     let mut tickets: HashMap<String, TicketInfo> = HashMap::new();
 
-    match config_state.jira_config() {
+    let config = config_state.current();
+    match config.jira_config() {
         None => {
             return Err(ListTicketsError::MissingIssueTrackingConfiguration.into());
         }
@@ -55,18 +56,6 @@ pub async fn tickets(
                 return Ok(Json(tickets));
             }
 
-            let jira = JiraInstance::at(jira_config.host().clone())
-                .unwrap()
-                .authenticate(match jira_config.auth() {
-                    crate::config::JiraAuth::Basic { user, password } => jira_query::Auth::Basic {
-                        user: user.clone(),
-                        password: password.unsecure().to_string(),
-                    },
-                    crate::config::JiraAuth::ApiKey { api_key } => {
-                        jira_query::Auth::ApiKey(api_key.unsecure().to_string())
-                    }
-                });
-
             let issue_keys = services
                 .keys()
                 .map(|s| format!("{:?}", s))
@@ -75,9 +64,39 @@ pub async fn tickets(
 
             debug!("Search for issues: {}", issue_keys);
 
-            let query = format!("issuekey in ({})&validateQuery=False", issue_keys);
-            let issues = jira.search(&query).await.map_err(ListTicketsError::from)?;
-            for issue in issues {
+            // The request is sent by PREvant's own client because the client of jira_query
+            // neither uses the proxy nor the extra certificate authorities of the network config.
+            let client = config
+                .network_config()
+                .http_client_builder()
+                .build()
+                .map_err(ListTicketsError::from)?;
+            let request = client
+                .get(format!(
+                    "{}/rest/api/2/search",
+                    jira_config.host().trim_end_matches('/')
+                ))
+                .query(&[
+                    ("jql", format!("issuekey in ({})", issue_keys)),
+                    ("validateQuery", String::from("false")),
+                    ("maxResults", services.len().to_string()),
+                ]);
+            let request = match jira_config.auth() {
+                JiraAuth::Basic { user, password } => {
+                    request.basic_auth(user, Some(password.unsecure()))
+                }
+                JiraAuth::ApiKey { api_key } => request.bearer_auth(api_key.unsecure()),
+            };
+
+            let search_results = request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(ListTicketsError::from)?
+                .json::<SearchResults>()
+                .await
+                .map_err(ListTicketsError::from)?;
+            for issue in search_results.issues {
                 tickets.insert(issue.key.clone(), TicketInfo::from(issue));
             }
         }
@@ -86,8 +105,14 @@ pub async fn tickets(
     Ok(Json(tickets))
 }
 
-impl From<JiraQueryError> for ListTicketsError {
-    fn from(err: JiraQueryError) -> Self {
+/// The response of Jira's [issue search](https://developer.atlassian.com/cloud/jira/platform/rest/v2/api-group-issue-search/#api-rest-api-2-search-get).
+#[derive(Deserialize)]
+struct SearchResults {
+    issues: Vec<Issue>,
+}
+
+impl From<reqwest::Error> for ListTicketsError {
+    fn from(err: reqwest::Error) -> Self {
         ListTicketsError::UnexpectedError {
             internal_message: err.to_string(),
         }
@@ -0,0 +1,137 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2020 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+use crate::config::{Config, ConfigHandle, Runtime};
+use rocket::serde::json::Json;
+use rocket::State;
+
+mod build_info {
+    include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+}
+
+/// The versions of the REST API that this build of PREvant serves.
+const API_VERSIONS: &[&str] = &["v1", "v2"];
+
+/// Describes this build of PREvant and the capabilities that are enabled at runtime so that
+/// clients, e.g. CLIs or the frontend, can adapt their behavior.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    version: &'static str,
+    git_sha: Option<&'static str>,
+    features: &'static [&'static str],
+    api_versions: &'static [&'static str],
+    capabilities: Capabilities,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    backend: &'static str,
+    /// Whether the routes of the apps are protected by the forward authentication.
+    authentication: bool,
+    /// Whether PREvant keeps the apps in sync with the file of desired apps.
+    operator_mode: bool,
+}
+
+impl VersionInfo {
+    fn new(config: &Config) -> Self {
+        let backend = match config.runtime_config() {
+            Runtime::Docker(_) => "Docker",
+            Runtime::Kubernetes(_) => "Kubernetes",
+            #[cfg(feature = "dummy-infrastructure")]
+            Runtime::Dummy(_) => "Dummy",
+        };
+
+        Self {
+            version: build_info::VERSION,
+            git_sha: build_info::GIT_SHA,
+            features: build_info::FEATURES,
+            api_versions: API_VERSIONS,
+            capabilities: Capabilities {
+                backend,
+                authentication: config.forward_auth_config().is_some(),
+                operator_mode: config.desired_apps_config().is_some(),
+            },
+        }
+    }
+}
+
+#[get("/version", format = "application/json")]
+pub fn version(config_state: &State<ConfigHandle>) -> Json<VersionInfo> {
+    Json(VersionInfo::new(&config_state.current()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::{Accept, Status};
+    use rocket::local::asynchronous::Client;
+
+    #[test]
+    fn should_describe_capabilities() {
+        let config = toml::de::from_str::<Config>(
+            r#"
+            [runtime]
+            type = 'Kubernetes'
+
+            [forwardAuth]
+            address = 'http://prevant.tools.svc/auth/forward'
+            "#,
+        )
+        .unwrap();
+
+        let info = VersionInfo::new(&config);
+
+        assert_eq!(
+            info.capabilities,
+            Capabilities {
+                backend: "Kubernetes",
+                authentication: true,
+                operator_mode: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_version() {
+        let rocket = rocket::build()
+            .manage(ConfigHandle::new(Config::default()))
+            .mount("/api", routes![version]);
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        let response = client
+            .get("/api/version")
+            .header(Accept::JSON)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let body = response.into_json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(body["apiVersions"], serde_json::json!(["v1", "v2"]));
+        assert_eq!(body["capabilities"]["backend"], "Docker");
+    }
+}
@@ -0,0 +1,204 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2019 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::config::Config;
+use crate::models::{Image, Severity, VulnerabilitySummary};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::process::Command;
+
+/// Scans images with [Trivy](https://trivy.dev) and caches the results so that the summaries
+/// can be attached to the services without scanning the images again.
+pub struct VulnerabilityScanner {
+    summaries: Mutex<HashMap<String, VulnerabilitySummary>>,
+}
+
+impl VulnerabilityScanner {
+    pub fn new() -> Self {
+        Self {
+            summaries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the summary of a previous scan of the image. The digest is preferred over the
+    /// image name because tags like `latest` might point to different images over time.
+    pub fn cached_summary(
+        &self,
+        image: &Image,
+        digest: Option<&String>,
+    ) -> Option<VulnerabilitySummary> {
+        let summaries = self.summaries.lock().unwrap();
+        summaries.get(&Self::cache_key(image, digest)).cloned()
+    }
+
+    /// Scans the image with the configured Trivy executable unless there is a cached summary for
+    /// the digest of the image.
+    pub async fn scan(
+        &self,
+        config: &Config,
+        image: &Image,
+        digest: Option<&String>,
+    ) -> Result<VulnerabilitySummary, VulnerabilityScanError> {
+        let trivy_config = match config.trivy_config() {
+            Some(trivy_config) => trivy_config,
+            None => return Ok(VulnerabilitySummary::default()),
+        };
+
+        if digest.is_some() {
+            if let Some(summary) = self.cached_summary(image, digest) {
+                return Ok(summary);
+            }
+        }
+
+        debug!("Scan {} for vulnerabilities", image);
+
+        let mut command = Command::new(trivy_config.command());
+        command.args(["image", "--quiet", "--format", "json"]);
+        if let Some(server) = trivy_config.server() {
+            command.arg("--server").arg(server.as_str());
+        }
+        if let Some((username, password)) = image
+            .registry()
+            .and_then(|registry| config.registry_credentials(&registry))
+        {
+            command
+                .env("TRIVY_USERNAME", username)
+                .env("TRIVY_PASSWORD", password.unsecure());
+        }
+        command.arg(image.to_string());
+
+        let output =
+            command
+                .output()
+                .await
+                .map_err(|err| VulnerabilityScanError::CannotRunTrivy {
+                    err: err.to_string(),
+                })?;
+        if !output.status.success() {
+            return Err(VulnerabilityScanError::ScanFailed {
+                image: image.to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        let summary = parse_trivy_report(&output.stdout).map_err(|err| {
+            VulnerabilityScanError::ScanFailed {
+                image: image.to_string(),
+                message: format!("Cannot parse report: {}", err),
+            }
+        })?;
+
+        let mut summaries = self.summaries.lock().unwrap();
+        summaries.insert(Self::cache_key(image, digest), summary.clone());
+
+        Ok(summary)
+    }
+
+    fn cache_key(image: &Image, digest: Option<&String>) -> String {
+        match digest {
+            Some(digest) => digest.clone(),
+            None => image.to_string(),
+        }
+    }
+}
+
+fn parse_trivy_report(report: &[u8]) -> Result<VulnerabilitySummary, serde_json::Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Report {
+        #[serde(default)]
+        results: Vec<ReportResult>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct ReportResult {
+        #[serde(default)]
+        vulnerabilities: Option<Vec<Vulnerability>>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Vulnerability {
+        severity: Severity,
+    }
+
+    let report = serde_json::from_slice::<Report>(report)?;
+    Ok(report
+        .results
+        .into_iter()
+        .flat_map(|result| result.vulnerabilities.unwrap_or_default())
+        .map(|vulnerability| vulnerability.severity)
+        .collect())
+}
+
+#[derive(Debug, Clone, Fail)]
+pub enum VulnerabilityScanError {
+    #[fail(display = "Cannot run trivy: {}", err)]
+    CannotRunTrivy { err: String },
+    #[fail(display = "Cannot scan image {}: {}", image, message)]
+    ScanFailed { image: String, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_count_vulnerabilities_of_report() {
+        let summary = parse_trivy_report(
+            br#"{
+                "SchemaVersion": 2,
+                "ArtifactName": "nginx:1.25",
+                "Results": [
+                    {
+                        "Target": "nginx:1.25 (debian 12.1)",
+                        "Vulnerabilities": [
+                            { "VulnerabilityID": "CVE-2023-0001", "Severity": "CRITICAL" },
+                            { "VulnerabilityID": "CVE-2023-0002", "Severity": "HIGH" },
+                            { "VulnerabilityID": "CVE-2023-0003", "Severity": "LOW" }
+                        ]
+                    },
+                    {
+                        "Target": "usr/local/bin/app",
+                        "Vulnerabilities": null
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(summary.count_at_least(Severity::Critical), 1);
+        assert_eq!(summary.count_at_least(Severity::Low), 3);
+    }
+
+    #[test]
+    fn should_parse_report_without_results() {
+        let summary = parse_trivy_report(br#"{ "SchemaVersion": 2 }"#).unwrap();
+
+        assert_eq!(summary, VulnerabilitySummary::default());
+    }
+}
@@ -0,0 +1,77 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+//! Generates the request and response models from the schemas of PREvant's OpenAPI
+//! specification so that the client cannot drift from the API.
+
+use schemars::schema::Schema;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use typify::{TypeSpace, TypeSpaceSettings};
+
+const OPENAPI_SPEC: &str = "../api/res/openapi.yml";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", OPENAPI_SPEC);
+
+    let spec =
+        std::fs::read_to_string(OPENAPI_SPEC).expect("Cannot read the OpenAPI specification");
+    let spec = serde_yaml::from_str::<serde_yaml::Value>(&spec)
+        .expect("The OpenAPI specification is not valid YAML");
+    let mut schemas = spec["components"]["schemas"].clone();
+    remove_defaults(&mut schemas, true);
+    let schemas = serde_yaml::from_value::<BTreeMap<String, Schema>>(schemas)
+        .expect("The schemas of the OpenAPI specification are not valid JSON schemas");
+
+    let mut type_space = TypeSpace::new(&TypeSpaceSettings::default());
+    type_space
+        .add_ref_types(schemas)
+        .expect("Cannot generate the types of the OpenAPI specification");
+
+    let file = syn::parse2::<syn::File>(type_space.to_stream())
+        .expect("The generated types are not valid Rust code");
+
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("models.rs"), prettyplease::unparse(&file))
+        .expect("Cannot write the generated types");
+}
+
+/// Removes the default values from the schemas because the generated types would always send
+/// them, which overrides the values of service templates. PREvant applies the defaults itself.
+fn remove_defaults(value: &mut serde_yaml::Value, is_named_map: bool) {
+    if let serde_yaml::Value::Mapping(mapping) = value {
+        if !is_named_map {
+            mapping.remove("default");
+        }
+        for (key, value) in mapping.iter_mut() {
+            remove_defaults(value, key.as_str() == Some("properties"));
+        }
+    } else if let serde_yaml::Value::Sequence(values) = value {
+        for value in values {
+            remove_defaults(value, false);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+use crate::models::{Apps, Problem, Service, ServiceConfig, ServiceStatus};
+use chrono::{DateTime, FixedOffset};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use url::Url;
+
+/// Typed async client for PREvant's REST API.
+///
+/// All requests are executed synchronously from the perspective of the API, i.e. the client
+/// waits until PREvant completed the deployment or deletion of an app.
+#[derive(Clone, Debug)]
+pub struct Client {
+    base_url: Url,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// Creates a new client for the PREvant instance available at `base_url`, e.g.
+    /// `http://localhost:8000/`.
+    pub fn new(base_url: Url) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_http_client(base_url: Url, http: reqwest::Client) -> Self {
+        Client { base_url, http }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Returns all apps and their services.
+    pub async fn apps(&self) -> Result<Apps, ClientError> {
+        let url = self.url(&["api", "apps", ""])?;
+        let response = self.send(self.http.get(url)).await?;
+        Self::json(response).await
+    }
+
+    /// Deploys the given services as the app `app_name`.
+    pub async fn deploy_app(
+        &self,
+        app_name: &str,
+        services: &[ServiceConfig],
+    ) -> Result<Vec<Service>, ClientError> {
+        let url = self.url(&["api", "apps", app_name])?;
+        let response = self.send(self.http.post(url).json(services)).await?;
+        Self::json(response).await
+    }
+
+    /// Deploys the given services as the app `app_name` and replicates all services of the app
+    /// `replicate_from` that are not part of `services`.
+    pub async fn replicate_app(
+        &self,
+        app_name: &str,
+        replicate_from: &str,
+        services: &[ServiceConfig],
+    ) -> Result<Vec<Service>, ClientError> {
+        let mut url = self.url(&["api", "apps", app_name])?;
+        url.query_pairs_mut()
+            .append_pair("replicateFrom", replicate_from);
+
+        let response = self.send(self.http.post(url).json(services)).await?;
+        Self::json(response).await
+    }
+
+    /// Deletes the app `app_name` and returns the services that have been stopped.
+    pub async fn delete_app(&self, app_name: &str) -> Result<Vec<Service>, ClientError> {
+        let url = self.url(&["api", "apps", app_name])?;
+        let response = self.send(self.http.delete(url)).await?;
+        Self::json(response).await
+    }
+
+    /// Returns the log lines of the service `service_name` of the app `app_name`.
+    pub async fn logs(
+        &self,
+        app_name: &str,
+        service_name: &str,
+        since: Option<DateTime<FixedOffset>>,
+        limit: Option<usize>,
+    ) -> Result<String, ClientError> {
+        let mut url = self.url(&["api", "apps", app_name, "logs", service_name])?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(since) = since {
+                query.append_pair("since", &since.to_rfc3339());
+            }
+            if let Some(limit) = limit {
+                query.append_pair("limit", &limit.to_string());
+            }
+        }
+
+        let response = self.send(self.http.get(url)).await?;
+        response
+            .text()
+            .await
+            .map_err(|err| ClientError::RequestFailed {
+                err: err.to_string(),
+            })
+    }
+
+    /// Starts or stops the service `service_name` of the app `app_name`.
+    pub async fn change_status(
+        &self,
+        app_name: &str,
+        service_name: &str,
+        status: ServiceStatus,
+    ) -> Result<(), ClientError> {
+        let url = self.url(&["api", "apps", app_name, "states", service_name])?;
+        self.send(self.http.put(url).json(&json!({ "status": status })))
+            .await?;
+        Ok(())
+    }
+
+    /// Restarts all instances of the service `service_name` of the app `app_name`.
+    pub async fn restart_service(
+        &self,
+        app_name: &str,
+        service_name: &str,
+    ) -> Result<(), ClientError> {
+        let url = self.url(&["api", "apps", app_name, "services", service_name, "restart"])?;
+        self.send(self.http.post(url)).await?;
+        Ok(())
+    }
+
+    fn url(&self, segments: &[&str]) -> Result<Url, ClientError> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| ClientError::InvalidUrl {
+                url: self.base_url.to_string(),
+            })?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ClientError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|err| ClientError::RequestFailed {
+                err: err.to_string(),
+            })?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let problem = response.json::<Problem>().await.ok();
+        Err(ClientError::from_response(status, problem))
+    }
+
+    async fn json<T>(response: Response) -> Result<T, ClientError>
+    where
+        T: DeserializeOwned,
+    {
+        response
+            .json::<T>()
+            .await
+            .map_err(|err| ClientError::InvalidResponse {
+                err: err.to_string(),
+            })
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum ClientError {
+    #[fail(display = "Cannot use {} as base URL.", url)]
+    InvalidUrl { url: String },
+    #[fail(display = "Cannot send request to PREvant: {}", err)]
+    RequestFailed { err: String },
+    #[fail(display = "Cannot parse response of PREvant: {}", err)]
+    InvalidResponse { err: String },
+    #[fail(display = "The app or service does not exist: {:?}", problem)]
+    NotFound { problem: Option<Problem> },
+    #[fail(
+        display = "The app is currently in deployment or deletion: {:?}",
+        problem
+    )]
+    Conflict { problem: Option<Problem> },
+    #[fail(display = "Unexpected status {}: {:?}", status, problem)]
+    UnexpectedStatus {
+        status: u16,
+        problem: Option<Problem>,
+    },
+}
+
+impl ClientError {
+    fn from_response(status: StatusCode, problem: Option<Problem>) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => ClientError::NotFound { problem },
+            StatusCode::CONFLICT => ClientError::Conflict { problem },
+            status => ClientError::UnexpectedStatus {
+                status: status.as_u16(),
+                problem,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_build_url_with_encoded_segments() {
+        let client = Client::new(Url::parse("http://localhost:8000/").unwrap());
+
+        let url = client.url(&["api", "apps", "feature xyz"]).unwrap();
+
+        assert_eq!(url.as_str(), "http://localhost:8000/api/apps/feature%20xyz");
+    }
+
+    #[test]
+    fn should_build_url_with_base_path() {
+        let client = Client::new(Url::parse("http://example.com/prevant").unwrap());
+
+        let url = client.url(&["api", "apps", ""]).unwrap();
+
+        assert_eq!(url.as_str(), "http://example.com/prevant/api/apps/");
+    }
+}
@@ -0,0 +1,57 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+//! A typed async client for [PREvant's](https://github.com/aixigo/PREvant) REST API.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), prevant_client::ClientError> {
+//! use prevant_client::{Client, ServiceConfig};
+//!
+//! let client = Client::new("http://localhost:8000".parse().unwrap());
+//! let services = client
+//!     .deploy_app(
+//!         "master",
+//!         &[ServiceConfig::new(
+//!             String::from("nginx"),
+//!             String::from("nginx:alpine"),
+//!         )],
+//!     )
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[macro_use]
+extern crate failure;
+
+pub use client::{Client, ClientError};
+pub use models::{
+    Apps, Problem, Service, ServiceConfig, ServiceStatus, ServiceType, State as ServiceState,
+    Version as ServiceVersion,
+};
+
+mod client;
+pub mod models;
@@ -0,0 +1,153 @@
+/*-
+ * ========================LICENSE_START=================================
+ * PREvant REST API
+ * %%
+ * Copyright (C) 2018 - 2023 aixigo AG
+ * %%
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in
+ * all copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+ * THE SOFTWARE.
+ * =========================LICENSE_END==================================
+ */
+
+//! Request and response models of PREvant's REST API.
+//!
+//! The types are generated from the schemas of PREvant's OpenAPI specification
+//! (`api/res/openapi.yml`) by the build script, so that they always match the JSON documents
+//! that are accepted and returned by the API.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+include!(concat!(env!("OUT_DIR"), "/models.rs"));
+
+/// All apps that are known by PREvant, grouped by their app name.
+pub type Apps = HashMap<String, Vec<Service>>;
+
+/// The payload that describes a service which should be deployed as part of an app.
+pub type ServiceConfig = ServiceConfiguration;
+
+/// Problem details ([RFC 7807](https://tools.ietf.org/html/rfc7807)) returned by the API in
+/// case of errors.
+pub type Problem = ProblemDetails;
+
+/// Whether a service is running or paused.
+pub type ServiceStatus = StateStatus;
+
+impl ServiceConfiguration {
+    pub fn new(service_name: String, image: String) -> Self {
+        serde_json::from_value(json!({ "serviceName": service_name, "image": image }))
+            .expect("A service name and an image are a valid service configuration")
+    }
+
+    pub fn with_env(self, key: String, value: String) -> Self {
+        self.with_entry("env", &key, Value::String(value))
+    }
+
+    pub fn with_replicated_env(self, key: String, value: String) -> Self {
+        self.with_entry(
+            "env",
+            &key,
+            json!({ "value": value, "templated": false, "replicate": true }),
+        )
+    }
+
+    pub fn with_file(self, path: &Path, content: String) -> Self {
+        self.with_entry("files", &path.to_string_lossy(), Value::String(content))
+    }
+
+    /// Adds the entry to the object `field` through the JSON document of the configuration
+    /// which keeps the builder methods independent of the types generated for the object.
+    fn with_entry(self, field: &str, key: &str, value: Value) -> Self {
+        let mut config = serde_json::to_value(&self).expect("Configurations are serializable");
+        config[field][key] = value;
+        serde_json::from_value(config).expect("The entry conforms to the OpenAPI specification")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_serialize_service_config() {
+        let config = ServiceConfig::new(String::from("db"), String::from("mariadb:10.3.17"))
+            .with_env(String::from("MYSQL_USER"), String::from("admin"))
+            .with_replicated_env(
+                String::from("MYSQL_RANDOM_ROOT_PASSWORD"),
+                String::from("yes"),
+            );
+
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({
+                "serviceName": "db",
+                "image": "mariadb:10.3.17",
+                "env": {
+                    "MYSQL_RANDOM_ROOT_PASSWORD": {
+                        "value": "yes",
+                        "templated": false,
+                        "replicate": true
+                    },
+                    "MYSQL_USER": "admin"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn should_deserialize_service() {
+        let service = json!({
+            "name": "http",
+            "type": "instance",
+            "url": "http://example.com/master/http/",
+            "version": {
+                "softwareVersion": "1.2.3"
+            },
+            "state": {
+                "status": "running"
+            },
+            "appMetadata": {
+                "branch": "feature/xyz"
+            }
+        });
+
+        let deserialized = serde_json::from_value::<Service>(service.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&deserialized).unwrap(), service);
+    }
+
+    #[test]
+    fn should_keep_fields_that_have_been_added_to_the_api() {
+        let config = json!({
+            "serviceName": "http",
+            "image": "nginx:alpine",
+            "command": [ "nginx" ],
+            "args": [ "-g", "daemon off;" ],
+            "workingDir": "/usr/share/nginx",
+            "replicas": 2,
+            "metadata": {
+                "labels": { "cost-center": "4711" }
+            }
+        });
+
+        let deserialized = serde_json::from_value::<ServiceConfig>(config.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&deserialized).unwrap(), config);
+    }
+}
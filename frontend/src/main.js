@@ -63,6 +63,7 @@ library.add(faTrash);
 library.add(faWindowClose);
 
 store.dispatch('fetchData');
+store.dispatch('subscribeToEvents');
 
 const router = createRouter({
    history: createWebHashHistory(),
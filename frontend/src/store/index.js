@@ -227,6 +227,12 @@ export default new Vuex.Store( {
          });
       },
 
+      subscribeToEvents( context ) {
+         const events = new EventSource( '/api/events' );
+         [ 'appCreated', 'appUpdated', 'appSoftDeleted', 'appDeleted', 'serviceStateChanged', 'serviceRemoved', 'lagged' ]
+            .forEach( eventName => events.addEventListener( eventName, () => context.dispatch( 'fetchData' ) ) );
+      },
+
       changeServiceState( context, { appName, serviceName } ) {
          const service = context.state.apps[ appName ].find( service => service.name === serviceName );
          let newStatus;